@@ -1,15 +1,22 @@
-use itertools::izip;
+use itertools::{izip, Itertools};
 use p3_air::BaseAir;
-use p3_field::{Field, FieldAlgebra};
-use p3_matrix::{dense::RowMajorMatrixView, stack::VerticalPair, Matrix};
+use p3_field::{Field, FieldAlgebra, PackedField, PackedValue};
+use p3_matrix::{dense::RowMajorMatrixView, Matrix};
 use p3_maybe_rayon::prelude::*;
 
 use crate::{
-    air_builders::debug::DebugConstraintBuilder,
+    air_builders::{
+        debug::DebugConstraintBuilder,
+        symbolic::{
+            symbolic_expression::SymbolicEvaluator,
+            symbolic_variable::{Entry, SymbolicVariable},
+            SymbolicExpressionDag, SymbolicExpressionNode,
+        },
+    },
     config::{StarkGenericConfig, Val},
     interaction::{
-        debug::{generate_logical_interactions, LogicalInteractions},
-        InteractionType, RapPhaseSeqKind, SymbolicInteraction,
+        bus::BusRegistry, debug::check_interaction_balance, public_lookup::PublicLookupTable,
+        RapPhaseSeqKind, SymbolicInteraction,
     },
     rap::{PartitionedBaseAir, Rap},
 };
@@ -22,6 +29,7 @@ pub fn check_constraints<R, SC>(
     preprocessed: &Option<RowMajorMatrixView<Val<SC>>>,
     partitioned_main: &[RowMajorMatrixView<Val<SC>>],
     public_values: &[Val<SC>],
+    periodic_columns: &[Vec<Val<SC>>],
 ) where
     R: for<'a> Rap<DebugConstraintBuilder<'a, SC>>
         + BaseAir<Val<SC>>
@@ -29,53 +37,52 @@ pub fn check_constraints<R, SC>(
         + ?Sized,
     SC: StarkGenericConfig,
 {
-    let height = partitioned_main[0].height();
+    let height = partitioned_main
+        .first()
+        .or(preprocessed.as_ref())
+        .expect("AIR has no trace")
+        .height();
     assert!(partitioned_main.iter().all(|mat| mat.height() == height));
 
+    // Each window holds the rows at rotations `0..=max_rotation` from the current row.
+    let window_height = rap.max_rotation().max(1) + 1;
+    let window = |mat: &RowMajorMatrixView<Val<SC>>, i: usize| row_window(mat, i, window_height);
+
     // Check that constraints are satisfied.
     (0..height).into_par_iter().for_each(|i| {
-        let i_next = (i + 1) % height;
-
-        let (preprocessed_local, preprocessed_next) = preprocessed
+        let preprocessed_window = preprocessed
             .as_ref()
-            .map(|preprocessed| {
-                (
-                    preprocessed.row_slice(i).to_vec(),
-                    preprocessed.row_slice(i_next).to_vec(),
-                )
-            })
-            .unwrap_or((vec![], vec![]));
-
-        let partitioned_main_row_pair = partitioned_main
+            .map(|preprocessed| window(preprocessed, i))
+            .unwrap_or_default();
+        let preprocessed_width = preprocessed.as_ref().map_or(0, |p| p.width());
+
+        let partitioned_main_windows = partitioned_main
             .iter()
-            .map(|part| (part.row_slice(i), part.row_slice(i_next)))
+            .map(|part| window(part, i))
+            .collect::<Vec<_>>();
+        let partitioned_main = izip!(&partitioned_main_windows, partitioned_main)
+            .map(|(values, part)| RowMajorMatrixView::new(values, part.width()))
             .collect::<Vec<_>>();
-        let partitioned_main = partitioned_main_row_pair
+        let periodic_values = periodic_columns
             .iter()
-            .map(|(local, next)| {
-                VerticalPair::new(
-                    RowMajorMatrixView::new_row(local),
-                    RowMajorMatrixView::new_row(next),
-                )
-            })
+            .map(|values| values[i % values.len()])
             .collect::<Vec<_>>();
 
         let mut builder = DebugConstraintBuilder {
             air_name: rap_name,
             row_index: i,
-            preprocessed: VerticalPair::new(
-                RowMajorMatrixView::new_row(preprocessed_local.as_slice()),
-                RowMajorMatrixView::new_row(preprocessed_next.as_slice()),
-            ),
+            preprocessed: RowMajorMatrixView::new(&preprocessed_window, preprocessed_width),
             partitioned_main,
             after_challenge: vec![], // unreachable
             challenges: &[],         // unreachable
             public_values,
+            periodic_values: &periodic_values,
             exposed_values_after_challenge: &[], // unreachable
             is_first_row: Val::<SC>::ZERO,
             is_last_row: Val::<SC>::ZERO,
             is_transition: Val::<SC>::ONE,
             rap_phase_seq_kind: RapPhaseSeqKind::FriLogUp, // unused
+            num_cached_mains: rap.cached_main_widths().len(),
             has_common_main: rap.common_main_width() > 0,
         };
         if i == 0 {
@@ -90,58 +97,353 @@ pub fn check_constraints<R, SC>(
     });
 }
 
+/// Check that the constraints in the symbolic constraints DAG of the verifying key vanish on the
+/// subgroup. This catches discrepancies between `Air::eval` and the constraints captured at keygen.
+///
+/// Constraints that depend on values of the challenge phases are skipped.
+pub fn check_symbolic_constraints<F: Field>(
+    air_name: &str,
+    constraints: &SymbolicExpressionDag<F>,
+    preprocessed: &Option<RowMajorMatrixView<F>>,
+    partitioned_main: &[RowMajorMatrixView<F>],
+    public_values: &[F],
+    periodic_columns: &[Vec<F>],
+) {
+    let checker = SymbolicConstraintChecker::new(
+        constraints,
+        preprocessed.as_ref(),
+        partitioned_main,
+        public_values,
+        periodic_columns,
+    );
+    if let Some(&(row, constraint_idx)) = checker.packed_failures().first() {
+        panic!(
+            "symbolic constraint {} had nonzero value on air {}, row {}",
+            constraint_idx, air_name, row
+        );
+    }
+}
+
+/// Evaluates a symbolic constraints DAG on the rows of a trace.
+struct SymbolicConstraintChecker<'a, F> {
+    constraints: &'a SymbolicExpressionDag<F>,
+    preprocessed: Option<&'a RowMajorMatrixView<'a, F>>,
+    partitioned_main: &'a [RowMajorMatrixView<'a, F>],
+    public_values: &'a [F],
+    periodic_columns: &'a [Vec<F>],
+    height: usize,
+    window_height: usize,
+    /// Whether each node depends on a value only known after a challenge phase.
+    depends_on_challenge: Vec<bool>,
+}
+
+impl<'a, F: Field> SymbolicConstraintChecker<'a, F> {
+    fn new(
+        constraints: &'a SymbolicExpressionDag<F>,
+        preprocessed: Option<&'a RowMajorMatrixView<'a, F>>,
+        partitioned_main: &'a [RowMajorMatrixView<'a, F>],
+        public_values: &'a [F],
+        periodic_columns: &'a [Vec<F>],
+    ) -> Self {
+        let height = partitioned_main
+            .first()
+            .or(preprocessed)
+            .expect("AIR has no trace")
+            .height();
+        assert!(partitioned_main.iter().all(|mat| mat.height() == height));
+
+        let mut depends_on_challenge: Vec<bool> = Vec::with_capacity(constraints.nodes.len());
+        for node in &constraints.nodes {
+            let depends = match *node {
+                SymbolicExpressionNode::Variable(var) => matches!(
+                    var.entry,
+                    Entry::Permutation { .. } | Entry::Challenge { .. } | Entry::Exposed { .. }
+                ),
+                SymbolicExpressionNode::Add {
+                    left_idx,
+                    right_idx,
+                    ..
+                }
+                | SymbolicExpressionNode::Sub {
+                    left_idx,
+                    right_idx,
+                    ..
+                }
+                | SymbolicExpressionNode::Mul {
+                    left_idx,
+                    right_idx,
+                    ..
+                } => depends_on_challenge[left_idx] || depends_on_challenge[right_idx],
+                SymbolicExpressionNode::Neg { idx, .. } => depends_on_challenge[idx],
+                SymbolicExpressionNode::IsFirstRow
+                | SymbolicExpressionNode::IsLastRow
+                | SymbolicExpressionNode::IsTransition
+                | SymbolicExpressionNode::Constant(_) => false,
+            };
+            depends_on_challenge.push(depends);
+        }
+
+        Self {
+            constraints,
+            preprocessed,
+            partitioned_main,
+            public_values,
+            periodic_columns,
+            height,
+            window_height: constraints.max_rotation().max(1) + 1,
+            depends_on_challenge,
+        }
+    }
+
+    /// Returns `(row, constraint index)` of every constraint with a nonzero value, in order,
+    /// evaluating one row at a time.
+    fn scalar_failures(&self) -> Vec<(usize, usize)> {
+        (0..self.height)
+            .into_par_iter()
+            .flat_map_iter(|row| self.row_failures(row))
+            .collect()
+    }
+
+    /// Returns the same failures as [scalar_failures](Self::scalar_failures), evaluating
+    /// `F::Packing::WIDTH` rows at a time. Rows whose window wraps around the end of the trace are
+    /// evaluated one at a time, and so is each row of a packed lane with a failure, to pinpoint
+    /// the failing constraints.
+    fn packed_failures(&self) -> Vec<(usize, usize)> {
+        let width = F::Packing::WIDTH;
+        // Rows `0..num_packed_rows` have windows that do not wrap around.
+        let num_packed_rows = (self.height + 1).saturating_sub(self.window_height);
+        let num_packed_rows = num_packed_rows - num_packed_rows % width;
+        let packed = (0..num_packed_rows)
+            .into_par_iter()
+            .step_by(width)
+            .flat_map_iter(|row| {
+                let evaluated = self.eval_rows::<F::Packing>(row);
+                let failing_lanes = (0..width)
+                    .filter(|&lane| {
+                        self.checked_values(&evaluated)
+                            .any(|(_, value)| !value.as_slice()[lane].is_zero())
+                    })
+                    .collect_vec();
+                failing_lanes
+                    .into_iter()
+                    .flat_map(|lane| self.row_failures(row + lane))
+                    .collect_vec()
+            });
+        let tail = (num_packed_rows..self.height)
+            .into_par_iter()
+            .flat_map_iter(|row| self.row_failures(row));
+        packed.chain(tail).collect()
+    }
+
+    fn row_failures(&self, row: usize) -> Vec<(usize, usize)> {
+        let evaluated = self.eval_rows::<F>(row);
+        self.checked_values(&evaluated)
+            .filter(|(_, value)| !value.is_zero())
+            .map(|(constraint_idx, _)| (row, constraint_idx))
+            .collect()
+    }
+
+    /// Values of the constraints that do not depend on a challenge phase, with their index.
+    fn checked_values<'b, P>(
+        &'b self,
+        evaluated: &'b [P],
+    ) -> impl Iterator<Item = (usize, &'b P)> + 'b {
+        self.constraints
+            .constraint_idx
+            .iter()
+            .enumerate()
+            .filter(move |&(_, &node_idx)| !self.depends_on_challenge[node_idx])
+            .map(move |(constraint_idx, &node_idx)| (constraint_idx, &evaluated[node_idx]))
+    }
+
+    /// Evaluates all nodes on rows `row..row + P::WIDTH`, with lane `j` holding row `row + j`.
+    fn eval_rows<P: PackedField<Scalar = F>>(&self, row: usize) -> Vec<P> {
+        let window = |mat: &RowMajorMatrixView<F>| {
+            (0..self.window_height)
+                .map(|rotation| {
+                    (0..mat.width())
+                        .map(|col| {
+                            P::from_fn(|lane| {
+                                let r = (row + lane + rotation) % self.height;
+                                mat.values[r * mat.width() + col]
+                            })
+                        })
+                        .collect_vec()
+                })
+                .collect_vec()
+        };
+        let values = PackedRowValues {
+            preprocessed: self.preprocessed.map(window).unwrap_or_default(),
+            partitioned_main: self.partitioned_main.iter().map(window).collect(),
+            public_values: self.public_values,
+            periodic_values: self
+                .periodic_columns
+                .iter()
+                .map(|values| P::from_fn(|lane| values[(row + lane) % values.len()]))
+                .collect(),
+            is_first_row: P::from_fn(|lane| F::from_bool(row + lane == 0)),
+            is_last_row: P::from_fn(|lane| F::from_bool(row + lane == self.height - 1)),
+            is_transition: P::from_fn(|lane| F::from_bool(row + lane != self.height - 1)),
+        };
+        values.eval_nodes(&self.constraints.nodes)
+    }
+}
+
+/// Values of `P::WIDTH` consecutive rows, by rotation and then column. Values of the challenge
+/// phases evaluate to zero, since the constraints depending on them are not checked.
+struct PackedRowValues<'a, F, P> {
+    preprocessed: Vec<Vec<P>>,
+    partitioned_main: Vec<Vec<Vec<P>>>,
+    public_values: &'a [F],
+    periodic_values: Vec<P>,
+    is_first_row: P,
+    is_last_row: P,
+    is_transition: P,
+}
+
+impl<F: Field, P: PackedField<Scalar = F>> SymbolicEvaluator<F, P> for PackedRowValues<'_, F, P> {
+    fn eval_const(&self, c: F) -> P {
+        c.into()
+    }
+    fn eval_is_first_row(&self) -> P {
+        self.is_first_row
+    }
+    fn eval_is_last_row(&self) -> P {
+        self.is_last_row
+    }
+    fn eval_is_transition(&self) -> P {
+        self.is_transition
+    }
+    fn eval_var(&self, symbolic_var: SymbolicVariable<F>) -> P {
+        let index = symbolic_var.index;
+        match symbolic_var.entry {
+            Entry::Preprocessed { offset } => self.preprocessed[offset][index],
+            Entry::Main { part_index, offset } => self.partitioned_main[part_index][offset][index],
+            Entry::Public => self.public_values[index].into(),
+            Entry::Periodic => self.periodic_values[index],
+            Entry::Permutation { .. } | Entry::Challenge { .. } | Entry::Exposed { .. } => P::ZERO,
+        }
+    }
+}
+
+/// Returns the rows at rotations `0..window_height` from row `i`, wrapping around the matrix.
+fn row_window<F: Clone + Send + Sync>(
+    mat: &RowMajorMatrixView<F>,
+    i: usize,
+    window_height: usize,
+) -> Vec<F> {
+    let height = mat.height();
+    (0..window_height)
+        .flat_map(|rotation| mat.row_slice((i + rotation) % height).to_vec())
+        .collect_vec()
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn check_logup<F: Field>(
     air_names: &[String],
+    bus_registry: &BusRegistry,
+    public_lookup_tables: &[PublicLookupTable<F>],
     interactions: &[Vec<SymbolicInteraction<F>>],
     preprocessed: &[Option<RowMajorMatrixView<F>>],
     partitioned_main: &[Vec<RowMajorMatrixView<F>>],
     public_values: &[Vec<F>],
+    periodic_columns: &[&[Vec<F>]],
 ) {
-    let mut logical_interactions = LogicalInteractions::<F>::default();
-    for (air_idx, (interactions, preprocessed, partitioned_main, public_values)) in
-        izip!(interactions, preprocessed, partitioned_main, public_values).enumerate()
-    {
-        generate_logical_interactions(
-            air_idx,
-            interactions,
-            preprocessed,
-            partitioned_main,
-            public_values,
-            &mut logical_interactions,
-        );
+    if let Err(report) = check_interaction_balance(
+        air_names,
+        bus_registry,
+        public_lookup_tables,
+        interactions,
+        preprocessed,
+        partitioned_main,
+        public_values,
+        periodic_columns,
+    ) {
+        print!("{report}");
+        panic!("LogUp multiset equality check failed.");
     }
+}
 
-    let mut logup_failed = false;
-    // For each bus, check each `fields` key by summing up multiplicities.
-    for (bus_idx, bus_interactions) in logical_interactions.at_bus.into_iter() {
-        for (fields, connections) in bus_interactions.into_iter() {
-            let mut sum = F::ZERO;
-            for (_, itype, count) in &connections {
-                match *itype {
-                    InteractionType::Send => {
-                        sum += *count;
-                    }
-                    InteractionType::Receive => {
-                        sum -= *count;
-                    }
-                }
-            }
-            if !sum.is_zero() {
-                logup_failed = true;
-                println!(
-                    "Bus {} failed to balance the multiplicities for fields={:?}. The bus connections for this were:",
-                    bus_idx, fields
-                );
-                for (air_idx, itype, count) in connections {
-                    println!(
-                        "   Air idx: {}, Air name: {}, interaction type: {:?}, count: {:?}",
-                        air_idx, air_names[air_idx], itype, count
-                    );
-                }
-            }
+#[cfg(test)]
+mod tests {
+    use p3_baby_bear::BabyBear;
+    use p3_field::FieldAlgebra;
+    use p3_matrix::dense::RowMajorMatrix;
+
+    use super::SymbolicConstraintChecker;
+    use crate::air_builders::symbolic::{
+        symbolic_expression::SymbolicExpression,
+        symbolic_variable::{Entry, SymbolicVariable},
+        SymbolicExpressionDag,
+    };
+
+    type F = BabyBear;
+
+    /// Fibonacci constraints on columns `a, b` with public values `a_0` and `b_{n-1}`.
+    fn fib_constraints() -> SymbolicExpressionDag<F> {
+        let var = |offset, index| -> SymbolicExpression<F> {
+            SymbolicVariable::new(
+                Entry::Main {
+                    part_index: 0,
+                    offset,
+                },
+                index,
+            )
+            .into()
+        };
+        let public =
+            |index| -> SymbolicExpression<F> { SymbolicVariable::new(Entry::Public, index).into() };
+        let (a, b, next_a, next_b) = (var(0, 0), var(0, 1), var(1, 0), var(1, 1));
+        SymbolicExpressionDag::from_expressions(&[
+            SymbolicExpression::IsFirstRow * (a.clone() - public(0)),
+            SymbolicExpression::IsTransition * (next_a - b.clone()),
+            SymbolicExpression::IsTransition * (next_b - (a + b.clone())),
+            SymbolicExpression::IsLastRow * (b - public(1)),
+        ])
+    }
+
+    fn fib_trace(height: usize) -> RowMajorMatrix<F> {
+        let mut values = vec![F::ZERO, F::ONE];
+        for i in 1..height {
+            values.push(values[2 * i - 1]);
+            values.push(values[2 * i - 2] + values[2 * i - 1]);
         }
+        RowMajorMatrix::new(values, 2)
     }
-    if logup_failed {
-        panic!("LogUp multiset equality check failed.");
+
+    #[test]
+    fn test_packed_checker_matches_scalar() {
+        let constraints = fib_constraints();
+        for (height, corrupted) in [(4, vec![1]), (64, vec![0, 17, 24, 62, 63])] {
+            let mut trace = fib_trace(height);
+            let public_values = [F::ZERO, trace.values[2 * height - 1]];
+            let partitioned_main = [trace.as_view()];
+            let checker = SymbolicConstraintChecker::new(
+                &constraints,
+                None,
+                &partitioned_main,
+                &public_values,
+                &[],
+            );
+            assert!(checker.scalar_failures().is_empty());
+            assert!(checker.packed_failures().is_empty());
+
+            for &row in &corrupted {
+                trace.values[2 * row + 1] += F::ONE;
+            }
+            let partitioned_main = [trace.as_view()];
+            let checker = SymbolicConstraintChecker::new(
+                &constraints,
+                None,
+                &partitioned_main,
+                &public_values,
+                &[],
+            );
+            let failures = checker.scalar_failures();
+            assert!(corrupted
+                .iter()
+                .all(|row| failures.iter().any(|(failed_row, _)| failed_row == row)));
+            assert_eq!(checker.packed_failures(), failures);
+        }
     }
 }
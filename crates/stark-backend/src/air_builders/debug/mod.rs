@@ -5,17 +5,18 @@ use p3_air::{
     AirBuilder, AirBuilderWithPublicValues, ExtensionBuilder, PairBuilder, PermutationAirBuilder,
 };
 use p3_field::FieldAlgebra;
-use p3_matrix::{dense::RowMajorMatrixView, stack::VerticalPair};
+use p3_matrix::dense::RowMajorMatrixView;
 
-use super::{symbolic::SymbolicConstraints, PartitionedAirBuilder, ViewPair};
+use super::{symbolic::SymbolicConstraints, PartitionedAirBuilder, PeriodicAirBuilder, ViewWindow};
 use crate::{
     config::{StarkGenericConfig, Val},
     interaction::{
+        bus::BusRegistry, fri_log_up::FriLogUpParams, public_lookup::PublicLookupTable,
         rap::InteractionPhaseAirBuilder, Interaction, InteractionBuilder, InteractionType,
-        RapPhaseSeqKind, SymbolicInteraction,
+        RapPhaseSeqKind, RapPhases, SymbolicInteraction,
     },
     keygen::types::StarkProvingKey,
-    rap::{AnyRap, PermutationAirBuilderWithExposedValues},
+    rap::{AnyRap, MultiPhaseAirBuilder, PermutationAirBuilderWithExposedValues},
 };
 
 mod check_constraints;
@@ -34,6 +35,8 @@ thread_local! {
 pub fn debug_constraints_and_interactions<SC: StarkGenericConfig>(
     airs: &[Arc<dyn AnyRap<SC>>],
     pk: &[StarkProvingKey<SC>],
+    bus_registry: &BusRegistry,
+    public_lookup_tables: &[PublicLookupTable<Val<SC>>],
     main_views_per_air: &[Vec<RowMajorMatrixView<'_, Val<SC>>>],
     public_values_per_air: &[Vec<Val<SC>>],
 ) {
@@ -52,6 +55,15 @@ pub fn debug_constraints_and_interactions<SC: StarkGenericConfig>(
                         &preprocessed_trace,
                         main,
                         public_values,
+                        &pk.vk.periodic_columns,
+                    );
+                    check_symbolic_constraints(
+                        &rap.name(),
+                        &pk.vk.symbolic_constraints.constraints,
+                        &preprocessed_trace,
+                        main,
+                        public_values,
+                        &pk.vk.periodic_columns,
                     );
                     preprocessed_trace
                 })
@@ -64,12 +76,35 @@ pub fn debug_constraints_and_interactions<SC: StarkGenericConfig>(
                     (pk.air_name.clone(), sym_constraints.interactions)
                 })
                 .unzip();
+            #[cfg(feature = "interaction-log")]
+            for (air_name, interactions, preprocessed, main, public_values, pk) in izip!(
+                &air_names,
+                &interactions,
+                &preprocessed,
+                main_views_per_air,
+                public_values_per_air,
+                pk
+            ) {
+                crate::interaction::log::record_interactions(
+                    air_name,
+                    interactions,
+                    preprocessed,
+                    main,
+                    public_values,
+                    &pk.vk.periodic_columns,
+                );
+            }
             check_logup(
                 &air_names,
+                bus_registry,
+                public_lookup_tables,
                 &interactions,
                 &preprocessed,
                 main_views_per_air,
                 public_values_per_air,
+                &pk.iter()
+                    .map(|pk| pk.vk.periodic_columns.as_slice())
+                    .collect_vec(),
             );
         }
     });
@@ -80,16 +115,18 @@ pub fn debug_constraints_and_interactions<SC: StarkGenericConfig>(
 pub struct DebugConstraintBuilder<'a, SC: StarkGenericConfig> {
     pub air_name: &'a str,
     pub row_index: usize,
-    pub preprocessed: ViewPair<'a, Val<SC>>,
-    pub partitioned_main: Vec<ViewPair<'a, Val<SC>>>,
-    pub after_challenge: Vec<ViewPair<'a, SC::Challenge>>,
+    pub preprocessed: ViewWindow<'a, Val<SC>>,
+    pub partitioned_main: Vec<ViewWindow<'a, Val<SC>>>,
+    pub after_challenge: Vec<ViewWindow<'a, SC::Challenge>>,
     pub challenges: &'a [Vec<SC::Challenge>],
     pub is_first_row: Val<SC>,
     pub is_last_row: Val<SC>,
     pub is_transition: Val<SC>,
     pub public_values: &'a [Val<SC>],
+    pub periodic_values: &'a [Val<SC>],
     pub exposed_values_after_challenge: &'a [Vec<SC::Challenge>],
     pub rap_phase_seq_kind: RapPhaseSeqKind,
+    pub num_cached_mains: usize,
     pub has_common_main: bool,
 }
 
@@ -100,7 +137,7 @@ where
     type F = Val<SC>;
     type Expr = Val<SC>;
     type Var = Val<SC>;
-    type M = VerticalPair<RowMajorMatrixView<'a, Val<SC>>, RowMajorMatrixView<'a, Val<SC>>>;
+    type M = ViewWindow<'a, Val<SC>>;
 
     /// It is difficult to horizontally concatenate matrices when the main trace is partitioned, so we disable this method in that case.
     fn main(&self) -> Self::M {
@@ -196,21 +233,16 @@ impl<'a, SC> PermutationAirBuilder for DebugConstraintBuilder<'a, SC>
 where
     SC: StarkGenericConfig,
 {
-    type MP = ViewPair<'a, SC::Challenge>;
+    type MP = ViewWindow<'a, SC::Challenge>;
 
     type RandomVar = SC::Challenge;
 
     fn permutation(&self) -> Self::MP {
-        *self
-            .after_challenge
-            .first()
-            .expect("Challenge phase not supported")
+        self.phase_trace(0)
     }
 
     fn permutation_randomness(&self) -> &[Self::EF] {
-        self.challenges
-            .first()
-            .expect("Challenge phase not supported")
+        self.phase_challenges(0)
     }
 }
 
@@ -225,13 +257,46 @@ where
     }
 }
 
+impl<SC> PeriodicAirBuilder for DebugConstraintBuilder<'_, SC>
+where
+    SC: StarkGenericConfig,
+{
+    type PeriodicVar = Val<SC>;
+
+    fn periodic_values(&self) -> &[Self::PeriodicVar] {
+        self.periodic_values
+    }
+}
+
 impl<SC> PermutationAirBuilderWithExposedValues for DebugConstraintBuilder<'_, SC>
 where
     SC: StarkGenericConfig,
 {
     fn permutation_exposed_values(&self) -> &[Self::EF] {
+        self.phase_exposed_values(0)
+    }
+}
+
+impl<SC> MultiPhaseAirBuilder for DebugConstraintBuilder<'_, SC>
+where
+    SC: StarkGenericConfig,
+{
+    fn phase_trace(&self, phase: usize) -> Self::MP {
+        *self
+            .after_challenge
+            .get(phase)
+            .expect("Challenge phase not supported")
+    }
+
+    fn phase_challenges(&self, phase: usize) -> &[Self::EF] {
+        self.challenges
+            .get(phase)
+            .expect("Challenge phase not supported")
+    }
+
+    fn phase_exposed_values(&self, phase: usize) -> &[Self::EF] {
         self.exposed_values_after_challenge
-            .first()
+            .get(phase)
             .expect("Challenge phase not supported")
     }
 }
@@ -241,15 +306,14 @@ where
     SC: StarkGenericConfig,
 {
     fn cached_mains(&self) -> &[Self::M] {
-        let mut num_cached_mains = self.partitioned_main.len();
-        if self.has_common_main {
-            num_cached_mains -= 1;
-        }
-        &self.partitioned_main[..num_cached_mains]
+        &self.partitioned_main[..self.num_cached_mains]
     }
     fn common_main(&self) -> &Self::M {
         assert!(self.has_common_main, "AIR doesn't have a common main trace");
-        self.partitioned_main.last().unwrap()
+        &self.partitioned_main[self.num_cached_mains]
+    }
+    fn custom_mains(&self) -> &[Self::M] {
+        &self.partitioned_main[self.num_cached_mains + self.has_common_main as usize..]
     }
 }
 
@@ -285,8 +349,12 @@ impl<SC: StarkGenericConfig> InteractionPhaseAirBuilder for DebugConstraintBuild
         0
     }
 
-    fn rap_phase_seq_kind(&self) -> RapPhaseSeqKind {
-        self.rap_phase_seq_kind
+    fn log_up_params(&self) -> FriLogUpParams {
+        FriLogUpParams::default()
+    }
+
+    fn rap_phases(&self) -> RapPhases {
+        self.rap_phase_seq_kind.into()
     }
 
     fn symbolic_interactions(&self) -> Vec<SymbolicInteraction<Val<SC>>> {
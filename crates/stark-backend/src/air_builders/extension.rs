@@ -0,0 +1,570 @@
+//! Extension field arithmetic on base field expressions of an [AirBuilder].
+//!
+//! An element of the binomial extension `F[X]/(X^D - W)` is represented by its `D` coordinates
+//! in the basis `1, X, ..., X^{D-1}`, each a base field expression. Arithmetic and assertions
+//! are expanded into base field expressions and constraints, so the constraints recorded by any
+//! builder are exactly those of an AIR splitting the extension arithmetic by hand.
+
+use std::{
+    array,
+    borrow::Borrow,
+    marker::PhantomData,
+    ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign},
+};
+
+use p3_air::{AirBuilder, PermutationAirBuilder};
+use p3_field::{extension::BinomiallyExtendable, FieldAlgebra};
+use p3_matrix::Matrix;
+
+/// Element of the binomial extension of degree `D` of `F`, as `D` expressions of type `E` over
+/// `F`.
+///
+/// No inverse is provided: constraints involving inverses should be written as identities, for
+/// example `a * a_inv == 1`.
+#[derive(Clone, Debug)]
+pub struct BinomialExtExpr<F, E, const D: usize> {
+    /// Coordinates in the basis `1, X, ..., X^{D-1}`.
+    pub coeffs: [E; D],
+    _marker: PhantomData<F>,
+}
+
+impl<F, E, const D: usize> BinomialExtExpr<F, E, D>
+where
+    F: BinomiallyExtendable<D>,
+    E: FieldAlgebra + From<F>,
+{
+    pub fn new(coeffs: [E; D]) -> Self {
+        Self {
+            coeffs,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Reads an extension element from `D` consecutive base columns, such as a slice of a trace
+    /// row.
+    pub fn from_vars<V: Into<E> + Copy>(vars: &[V]) -> Self {
+        assert_eq!(vars.len(), D, "expected {D} base columns");
+        Self::new(array::from_fn(|i| vars[i].into()))
+    }
+
+    /// Embeds a base field expression.
+    pub fn from_base(x: impl Into<E>) -> Self {
+        let mut coeffs: [E; D] = array::from_fn(|_| E::ZERO);
+        coeffs[0] = x.into();
+        Self::new(coeffs)
+    }
+
+    /// Embeds a constant of the extension field, given by its coordinates.
+    pub fn from_constant(coeffs: [F; D]) -> Self {
+        Self::new(coeffs.map(E::from))
+    }
+
+    pub fn zero() -> Self {
+        Self::new(array::from_fn(|_| E::ZERO))
+    }
+
+    pub fn one() -> Self {
+        Self::from_base(E::ONE)
+    }
+
+    /// Multiplies every coordinate by the base field expression `rhs`.
+    pub fn mul_base(self, rhs: impl Into<E>) -> Self {
+        let rhs = rhs.into();
+        Self::new(self.coeffs.map(|c| c * rhs.clone()))
+    }
+
+    pub fn square(&self) -> Self {
+        self.clone() * self.clone()
+    }
+}
+
+impl<F, E, const D: usize> Add for BinomialExtExpr<F, E, D>
+where
+    F: BinomiallyExtendable<D>,
+    E: FieldAlgebra + From<F>,
+{
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        let mut rhs = rhs.coeffs.into_iter();
+        Self::new(self.coeffs.map(|c| c + rhs.next().unwrap()))
+    }
+}
+
+impl<F, E, const D: usize> Sub for BinomialExtExpr<F, E, D>
+where
+    F: BinomiallyExtendable<D>,
+    E: FieldAlgebra + From<F>,
+{
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        let mut rhs = rhs.coeffs.into_iter();
+        Self::new(self.coeffs.map(|c| c - rhs.next().unwrap()))
+    }
+}
+
+impl<F, E, const D: usize> Neg for BinomialExtExpr<F, E, D>
+where
+    F: BinomiallyExtendable<D>,
+    E: FieldAlgebra + From<F>,
+{
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::new(self.coeffs.map(|c| -c))
+    }
+}
+
+impl<F, E, const D: usize> Mul for BinomialExtExpr<F, E, D>
+where
+    F: BinomiallyExtendable<D>,
+    E: FieldAlgebra + From<F>,
+{
+    type Output = Self;
+
+    /// Schoolbook multiplication, reducing with `X^D = W`.
+    fn mul(self, rhs: Self) -> Self {
+        let (a, b) = (&self.coeffs, &rhs.coeffs);
+        Self::new(array::from_fn(|k| {
+            (0..D)
+                .map(|i| {
+                    let term = a[i].clone() * b[(k + D - i) % D].clone();
+                    if i > k {
+                        term * E::from(F::W)
+                    } else {
+                        term
+                    }
+                })
+                .sum()
+        }))
+    }
+}
+
+impl<F, E, const D: usize> AddAssign for BinomialExtExpr<F, E, D>
+where
+    F: BinomiallyExtendable<D>,
+    E: FieldAlgebra + From<F>,
+{
+    fn add_assign(&mut self, rhs: Self) {
+        *self = self.clone() + rhs;
+    }
+}
+
+impl<F, E, const D: usize> SubAssign for BinomialExtExpr<F, E, D>
+where
+    F: BinomiallyExtendable<D>,
+    E: FieldAlgebra + From<F>,
+{
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = self.clone() - rhs;
+    }
+}
+
+impl<F, E, const D: usize> MulAssign for BinomialExtExpr<F, E, D>
+where
+    F: BinomiallyExtendable<D>,
+    E: FieldAlgebra + From<F>,
+{
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = self.clone() * rhs;
+    }
+}
+
+/// Extension field expressions of a builder, over its base field expressions.
+pub type ExtExpr<AB, const D: usize> =
+    BinomialExtExpr<<AB as AirBuilder>::F, <AB as AirBuilder>::Expr, D>;
+
+/// Assertions on [BinomialExtExpr]s. Each assertion adds one base field constraint per
+/// coordinate.
+pub trait ExtAirBuilder: AirBuilder {
+    fn assert_ext_zero<const D: usize>(&mut self, x: ExtExpr<Self, D>)
+    where
+        Self::F: BinomiallyExtendable<D>,
+    {
+        for c in x.coeffs {
+            self.assert_zero(c);
+        }
+    }
+
+    fn assert_ext_eq<const D: usize>(&mut self, x: ExtExpr<Self, D>, y: ExtExpr<Self, D>)
+    where
+        Self::F: BinomiallyExtendable<D>,
+    {
+        self.assert_ext_zero(x - y);
+    }
+
+    fn assert_ext_one<const D: usize>(&mut self, x: ExtExpr<Self, D>)
+    where
+        Self::F: BinomiallyExtendable<D>,
+    {
+        self.assert_ext_eq(x, ExtExpr::<Self, D>::one());
+    }
+
+    /// Asserts on every transition row that the extension element in the `D` base columns
+    /// `next` equals `transition(cur, challenge)`, where `cur` is the element in the `D` base
+    /// columns `local`.
+    ///
+    /// This is the shape of accumulator columns such as log-up running sums. `challenge` is
+    /// typically read from public values or main columns by [BinomialExtExpr::from_vars].
+    fn assert_ext_transition<const D: usize, V: Into<Self::Expr> + Copy>(
+        &mut self,
+        local: &[V],
+        next: &[V],
+        challenge: ExtExpr<Self, D>,
+        transition: impl FnOnce(ExtExpr<Self, D>, ExtExpr<Self, D>) -> ExtExpr<Self, D>,
+    ) where
+        Self::F: BinomiallyExtendable<D>,
+    {
+        let cur = ExtExpr::<Self, D>::from_vars(local);
+        let next = ExtExpr::<Self, D>::from_vars(next);
+        self.when_transition()
+            .assert_ext_eq(next, transition(cur, challenge));
+    }
+}
+
+impl<AB: AirBuilder> ExtAirBuilder for AB {}
+
+/// Returns the row at rotation `offset` of the after challenge trace of the first challenge
+/// phase, as extension field expressions.
+///
+/// After challenge traces are committed over the extension field, so each column is a single
+/// [ExprEF](p3_air::ExtensionBuilder::ExprEF) rather than `D` base field expressions.
+pub fn permutation_row_ext<AB: PermutationAirBuilder>(
+    builder: &AB,
+    offset: usize,
+) -> Vec<AB::ExprEF> {
+    let perm = builder.permutation();
+    let row = perm.row_slice(offset);
+    let row: &[AB::VarEF] = (*row).borrow();
+    row.iter().map(|&v| v.into()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+    use openvm_stark_sdk::utils::create_seeded_rng;
+    use p3_air::{Air, AirBuilderWithPublicValues, BaseAir};
+    use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
+    use p3_challenger::DuplexChallenger;
+    use p3_field::{extension::BinomialExtensionField, FieldExtensionAlgebra};
+    use p3_matrix::dense::{RowMajorMatrix, RowMajorMatrixView};
+    use rand::Rng;
+
+    use super::*;
+    use crate::{
+        air_builders::symbolic::{
+            get_symbolic_builder,
+            interpreter::{eval_symbolic_constraints, SymbolicRowValues},
+            symbolic_expression::SymbolicExpression,
+            symbolic_variable::{Entry, SymbolicVariable},
+            SymbolicConstraintsDag, SymbolicExpressionDag, SymbolicRapBuilder,
+        },
+        interaction::{
+            fri_log_up::{FriLogUpParams, FriLogUpPhase},
+            InteractionType, RapPhaseSeqKind, SymbolicInteraction,
+        },
+        keygen::types::TraceWidth,
+        prover::types::PairView,
+        rap::{BaseAirWithPublicValues, PartitionedBaseAir, Rap},
+    };
+
+    type F = BabyBear;
+    type EF = BinomialExtensionField<F, 4>;
+    type Challenger = DuplexChallenger<F, Poseidon2BabyBear<16>, 16, 8>;
+
+    /// Grand product argument checking that column `b` is a permutation of column `a`:
+    /// | a | b | z_0 | z_1 | z_2 | z_3 |
+    ///
+    /// `z` is an extension element split into 4 base columns, and the challenge `beta` is given
+    /// by 4 public values. Constrains `z` to start at 1 and `z' * (beta - b) == z * (beta - a)`.
+    struct GrandProductAir {
+        hand_split: bool,
+    }
+
+    impl<T> BaseAirWithPublicValues<T> for GrandProductAir {
+        fn num_public_values(&self) -> usize {
+            4
+        }
+    }
+    impl<T> PartitionedBaseAir<T> for GrandProductAir {}
+    impl<T> BaseAir<T> for GrandProductAir {
+        fn width(&self) -> usize {
+            6
+        }
+    }
+
+    impl<AB: AirBuilderWithPublicValues> Air<AB> for GrandProductAir
+    where
+        AB::F: BinomiallyExtendable<4>,
+    {
+        fn eval(&self, builder: &mut AB) {
+            let main = builder.main();
+            let (local, next) = (main.row_slice(0), main.row_slice(1));
+            let (local, next): (&[AB::Var], &[AB::Var]) = ((*local).borrow(), (*next).borrow());
+            let beta: [AB::Expr; 4] = array::from_fn(|i| builder.public_values()[i].into());
+            let (a, b) = (local[0], local[1]);
+            if self.hand_split {
+                eval_hand_split::<AB>(builder, beta, a, b, &local[2..], &next[2..]);
+            } else {
+                let beta = ExtExpr::<AB, 4>::new(beta);
+                let z = ExtExpr::<AB, 4>::from_vars(&local[2..]);
+                let z_next = ExtExpr::<AB, 4>::from_vars(&next[2..]);
+                builder.when_first_row().assert_ext_one(z.clone());
+                builder.when_transition().assert_ext_eq(
+                    z_next * (beta.clone() - ExtExpr::<AB, 4>::from_base(b)),
+                    z * (beta - ExtExpr::<AB, 4>::from_base(a)),
+                );
+            }
+        }
+    }
+
+    fn eval_hand_split<AB: AirBuilder>(
+        builder: &mut AB,
+        beta: [AB::Expr; 4],
+        a: AB::Var,
+        b: AB::Var,
+        z: &[AB::Var],
+        z_next: &[AB::Var],
+    ) where
+        AB::F: BinomiallyExtendable<4>,
+    {
+        let w = AB::Expr::from(AB::F::W);
+        let mul = |x: [AB::Expr; 4], y: [AB::Expr; 4]| -> [AB::Expr; 4] {
+            let [x0, x1, x2, x3] = x;
+            let [y0, y1, y2, y3] = y;
+            [
+                x0.clone() * y0.clone()
+                    + (x1.clone() * y3.clone() + x2.clone() * y2.clone() + x3.clone() * y1.clone())
+                        * w.clone(),
+                x0.clone() * y1.clone()
+                    + x1.clone() * y0.clone()
+                    + (x2.clone() * y3.clone() + x3.clone() * y2.clone()) * w.clone(),
+                x0.clone() * y2.clone()
+                    + x1.clone() * y1.clone()
+                    + x2.clone() * y0.clone()
+                    + x3.clone() * y3.clone() * w.clone(),
+                x0 * y3 + x1 * y2 + x2 * y1 + x3 * y0,
+            ]
+        };
+        let [beta0, beta1, beta2, beta3] = beta;
+        let lhs = mul(
+            array::from_fn(|i| z_next[i].into()),
+            [
+                beta0.clone() - b,
+                beta1.clone(),
+                beta2.clone(),
+                beta3.clone(),
+            ],
+        );
+        let rhs = mul(
+            array::from_fn(|i| z[i].into()),
+            [beta0 - a, beta1, beta2, beta3],
+        );
+
+        builder.when_first_row().assert_one(z[0]);
+        for &z_i in &z[1..] {
+            builder.when_first_row().assert_zero(z_i);
+        }
+        for (l, r) in lhs.into_iter().zip(rhs) {
+            builder.when_transition().assert_eq(l, r);
+        }
+    }
+
+    /// Log-up running sum of a single interaction sending the fields `[a, b]` with multiplicity
+    /// `count`:
+    /// | a | b | count | perm_0 | ... | perm_3 | phi_0 | ... | phi_3 |
+    ///
+    /// The challenges `alpha` and `beta` are given by 8 public values. As in the after challenge
+    /// trace of [FriLogUpPhase], `perm = count / (alpha + a + beta * b)` and `phi` is the running
+    /// sum of `perm`.
+    struct LogUpAccumulatorAir;
+
+    impl<T> BaseAirWithPublicValues<T> for LogUpAccumulatorAir {
+        fn num_public_values(&self) -> usize {
+            8
+        }
+    }
+    impl<T> PartitionedBaseAir<T> for LogUpAccumulatorAir {}
+    impl<T> BaseAir<T> for LogUpAccumulatorAir {
+        fn width(&self) -> usize {
+            11
+        }
+    }
+
+    impl<AB: AirBuilderWithPublicValues> Air<AB> for LogUpAccumulatorAir
+    where
+        AB::F: BinomiallyExtendable<4>,
+    {
+        fn eval(&self, builder: &mut AB) {
+            let main = builder.main();
+            let (local, next) = (main.row_slice(0), main.row_slice(1));
+            let (local, next): (&[AB::Var], &[AB::Var]) = ((*local).borrow(), (*next).borrow());
+            let alpha = ExtExpr::<AB, 4>::from_vars(&builder.public_values()[..4]);
+            let beta = ExtExpr::<AB, 4>::from_vars(&builder.public_values()[4..]);
+            let (a, b, count) = (local[0], local[1], local[2]);
+            let perm = ExtExpr::<AB, 4>::from_vars(&local[3..7]);
+            let perm_next = ExtExpr::<AB, 4>::from_vars(&next[3..7]);
+
+            let denom = alpha + ExtExpr::<AB, 4>::from_base(a) + beta.mul_base(b);
+            builder.assert_ext_eq(perm.clone() * denom, ExtExpr::<AB, 4>::from_base(count));
+            builder
+                .when_first_row()
+                .assert_ext_eq(ExtExpr::<AB, 4>::from_vars(&local[7..]), perm);
+            builder
+                .assert_ext_transition(&local[7..], &next[7..], perm_next, |phi, perm| phi + perm);
+        }
+    }
+
+    fn symbolic_constraints<A>(air: &A) -> SymbolicExpressionDag<F>
+    where
+        A: Rap<SymbolicRapBuilder<F>> + BaseAirWithPublicValues<F> + PartitionedBaseAir<F>,
+    {
+        let width = TraceWidth {
+            preprocessed: None,
+            cached_mains: vec![],
+            common_main: air.width(),
+            after_challenge: vec![],
+            custom: vec![],
+        };
+        let builder = get_symbolic_builder(
+            air,
+            &width,
+            &[],
+            &[],
+            RapPhaseSeqKind::FriLogUp,
+            3,
+            FriLogUpParams::default(),
+            true,
+        );
+        SymbolicConstraintsDag::from(builder.constraints()).constraints
+    }
+
+    #[test]
+    fn test_ext_mul_matches_extension_field() {
+        let mut rng = create_seeded_rng();
+        for _ in 0..10 {
+            let x: [F; 4] = rng.gen();
+            let y: [F; 4] = rng.gen();
+            let product = BinomialExtExpr::<F, F, 4>::new(x) * BinomialExtExpr::new(y);
+            assert_eq!(
+                EF::from_base_slice(&product.coeffs),
+                EF::from_base_slice(&x) * EF::from_base_slice(&y)
+            );
+        }
+    }
+
+    #[test]
+    fn test_ext_air_matches_hand_split() {
+        let dag = symbolic_constraints(&GrandProductAir { hand_split: false });
+        let hand_split_dag = symbolic_constraints(&GrandProductAir { hand_split: true });
+        assert_eq!(dag.constraint_idx.len(), 8);
+        assert_eq!(hand_split_dag.constraint_idx.len(), 8);
+
+        let mut rng = create_seeded_rng();
+        for _ in 0..10 {
+            let main = (0..12).map(|_| rng.gen()).collect_vec();
+            let public_values: [F; 4] = rng.gen();
+            let [is_first_row, is_last_row, is_transition] = rng.gen();
+            let values = SymbolicRowValues::<F, F> {
+                preprocessed: RowMajorMatrixView::new(&[], 0),
+                partitioned_main: vec![RowMajorMatrixView::new(&main, 6)],
+                after_challenge: vec![],
+                challenges: &[],
+                public_values: &public_values,
+                periodic_values: &[],
+                exposed_values_after_challenge: &[],
+                is_first_row,
+                is_last_row,
+                is_transition,
+            };
+            assert_eq!(
+                eval_symbolic_constraints(&dag, &values),
+                eval_symbolic_constraints(&hand_split_dag, &values)
+            );
+        }
+    }
+
+    #[test]
+    fn test_ext_transition_matches_fri_log_up() {
+        const HEIGHT: usize = 8;
+        const WIDTH: usize = 11;
+        let dag = symbolic_constraints(&LogUpAccumulatorAir);
+        assert_eq!(dag.constraint_idx.len(), 12);
+
+        let mut rng = create_seeded_rng();
+        let main = RowMajorMatrix::<F>::new((0..3 * HEIGHT).map(|_| rng.gen()).collect(), 3);
+        let var = |index| -> SymbolicExpression<F> {
+            SymbolicVariable::new(
+                Entry::Main {
+                    part_index: 0,
+                    offset: 0,
+                },
+                index,
+            )
+            .into()
+        };
+        let interaction = SymbolicInteraction {
+            fields: vec![var(0), var(1)],
+            count: var(2),
+            bus_index: 0,
+            interaction_type: InteractionType::Send,
+        };
+        let challenges: [EF; 2] = rng.gen();
+        let trace_view = PairView {
+            log_trace_height: HEIGHT.ilog2() as u8,
+            preprocessed: None,
+            partitioned_main: vec![&main],
+            public_values: vec![],
+            periodic_columns: vec![],
+        };
+        let perm_trace = FriLogUpPhase::<F, EF, Challenger>::generate_after_challenge_trace(
+            &[interaction],
+            &trace_view,
+            &challenges,
+            &[vec![0]],
+        )
+        .unwrap();
+
+        // The accumulator of the AIR is the after challenge trace split into base columns.
+        let mut values = main
+            .rows()
+            .zip(perm_trace.rows())
+            .flat_map(|(main_row, perm_row)| {
+                main_row.chain(perm_row.flat_map(|v| v.as_base_slice().to_vec()))
+            })
+            .collect_vec();
+        let public_values = challenges
+            .iter()
+            .flat_map(|c| c.as_base_slice().to_vec())
+            .collect_vec();
+        let is_satisfied = |values: &[F]| {
+            (0..HEIGHT).all(|row| {
+                let window = [row, (row + 1) % HEIGHT]
+                    .into_iter()
+                    .flat_map(|r| values[r * WIDTH..(r + 1) * WIDTH].to_vec())
+                    .collect_vec();
+                let row_values = SymbolicRowValues::<F, F> {
+                    preprocessed: RowMajorMatrixView::new(&[], 0),
+                    partitioned_main: vec![RowMajorMatrixView::new(&window, WIDTH)],
+                    after_challenge: vec![],
+                    challenges: &[],
+                    public_values: &public_values,
+                    periodic_values: &[],
+                    exposed_values_after_challenge: &[],
+                    is_first_row: F::from_bool(row == 0),
+                    is_last_row: F::from_bool(row == HEIGHT - 1),
+                    is_transition: F::from_bool(row != HEIGHT - 1),
+                };
+                eval_symbolic_constraints(&dag, &row_values)
+                    .into_iter()
+                    .all(|c| c == F::ZERO)
+            })
+        };
+        assert!(is_satisfied(&values));
+
+        // Break the running sum in the middle of the trace.
+        values[4 * WIDTH + 7] += F::ONE;
+        assert!(!is_satisfied(&values));
+    }
+}
@@ -1,13 +1,17 @@
 use p3_air::AirBuilder;
-use p3_matrix::{dense::RowMajorMatrixView, stack::VerticalPair};
+use p3_matrix::dense::RowMajorMatrixView;
 
 pub mod debug;
+/// Extension field arithmetic on base field expressions.
+pub mod extension;
 pub mod sub;
 /// AIR builder that collects the constraints expressed via the [Air](p3_air::Air) trait into
 /// a directed acyclic graph of symbolic expressions for serialization purposes.
 pub mod symbolic;
 
-pub type ViewPair<'a, T> = VerticalPair<RowMajorMatrixView<'a, T>, RowMajorMatrixView<'a, T>>;
+/// Window of consecutive trace rows, where row `i` of the window is the trace row at rotation `i`
+/// from the current row. A window contains at least the local and next rows.
+pub type ViewWindow<'a, T> = RowMajorMatrixView<'a, T>;
 
 /// AIR builder that supports main trace matrix which is partitioned
 /// into sub-matrices which belong to different commitments.
@@ -16,4 +20,17 @@ pub trait PartitionedAirBuilder: AirBuilder {
     fn cached_mains(&self) -> &[Self::M];
     /// Common main trace matrix. Panic if there is no common main trace.
     fn common_main(&self) -> &Self::M;
+    /// Matrices of the custom commitment rounds of the AIR, ordered by custom round index.
+    fn custom_mains(&self) -> &[Self::M] {
+        &[]
+    }
+}
+
+/// AIR builder that supports periodic columns, see
+/// [PartitionedBaseAir::periodic_columns](crate::rap::PartitionedBaseAir::periodic_columns).
+pub trait PeriodicAirBuilder: AirBuilder {
+    type PeriodicVar: Into<Self::Expr> + Copy;
+
+    /// Values of the periodic columns at the current row.
+    fn periodic_values(&self) -> &[Self::PeriodicVar];
 }
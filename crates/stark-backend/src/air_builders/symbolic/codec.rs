@@ -0,0 +1,526 @@
+//! Compact binary encoding of [SymbolicExpressionDag].
+//!
+//! The encoding is independent of the in-memory layout of [SymbolicExpressionNode], so that
+//! refactors of the node enum do not silently change serialized verifying keys. Layout:
+//!
+//! - format version byte
+//! - varint number of nodes, followed by each node in topological order as a one byte tag and
+//!   its operands: varint variable coordinates, varint child indices and degree multiple, or the
+//!   canonical little-endian bytes of a field constant. Variables of the challenge phases after
+//!   the first have their own tags, with the phase as an extra varint coordinate, so that the
+//!   encoding of single phase DAGs is unchanged
+//! - varint number of constraints, followed by the varint node index of each constraint
+//!
+//! Varints are unsigned LEB128.
+
+use p3_field::PrimeField64;
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+use thiserror::Error;
+
+use super::{
+    dag::{SymbolicConstraintsDag, SymbolicExpressionDag, SymbolicExpressionNode},
+    symbolic_variable::{Entry, SymbolicVariable},
+};
+use crate::interaction::Interaction;
+
+/// Current version of the binary DAG encoding.
+pub const DAG_FORMAT_VERSION: u8 = 1;
+
+const TAG_PREPROCESSED: u8 = 0;
+const TAG_MAIN: u8 = 1;
+const TAG_PERMUTATION: u8 = 2;
+const TAG_PUBLIC: u8 = 3;
+const TAG_CHALLENGE: u8 = 4;
+const TAG_EXPOSED: u8 = 5;
+const TAG_IS_FIRST_ROW: u8 = 6;
+const TAG_IS_LAST_ROW: u8 = 7;
+const TAG_IS_TRANSITION: u8 = 8;
+const TAG_CONSTANT: u8 = 9;
+const TAG_ADD: u8 = 10;
+const TAG_SUB: u8 = 11;
+const TAG_NEG: u8 = 12;
+const TAG_MUL: u8 = 13;
+const TAG_PERIODIC: u8 = 14;
+const TAG_PHASE_PERMUTATION: u8 = 15;
+const TAG_PHASE_CHALLENGE: u8 = 16;
+const TAG_PHASE_EXPOSED: u8 = 17;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum DagDecodingError {
+    #[error("unsupported DAG format version {0}")]
+    UnsupportedVersion(u8),
+    #[error("unexpected end of input")]
+    UnexpectedEnd,
+    #[error("unknown node tag {0}")]
+    UnknownTag(u8),
+    /// A node index refers to a node that is not strictly before the referencing node, or a
+    /// constraint or interaction refers to a node that does not exist.
+    #[error("invalid node index {0}")]
+    InvalidNodeIndex(usize),
+    #[error("non-canonical field constant")]
+    NonCanonicalConstant,
+    #[error("varint overflow")]
+    VarintOverflow,
+    #[error("{0} trailing bytes")]
+    TrailingBytes(usize),
+}
+
+impl<F: PrimeField64> SymbolicExpressionDag<F> {
+    /// Encodes the DAG in the compact binary format of version [DAG_FORMAT_VERSION].
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = vec![DAG_FORMAT_VERSION];
+        write_varint(&mut out, self.nodes.len());
+        for node in &self.nodes {
+            match node {
+                SymbolicExpressionNode::Variable(var) => {
+                    match var.entry {
+                        Entry::Preprocessed { offset } => {
+                            out.push(TAG_PREPROCESSED);
+                            write_varint(&mut out, offset);
+                        }
+                        Entry::Main { part_index, offset } => {
+                            out.push(TAG_MAIN);
+                            write_varint(&mut out, part_index);
+                            write_varint(&mut out, offset);
+                        }
+                        Entry::Permutation { phase: 0, offset } => {
+                            out.push(TAG_PERMUTATION);
+                            write_varint(&mut out, offset);
+                        }
+                        Entry::Permutation { phase, offset } => {
+                            out.push(TAG_PHASE_PERMUTATION);
+                            write_varints(&mut out, &[phase, offset]);
+                        }
+                        Entry::Public => out.push(TAG_PUBLIC),
+                        Entry::Challenge { phase: 0 } => out.push(TAG_CHALLENGE),
+                        Entry::Challenge { phase } => {
+                            out.push(TAG_PHASE_CHALLENGE);
+                            write_varint(&mut out, phase);
+                        }
+                        Entry::Exposed { phase: 0 } => out.push(TAG_EXPOSED),
+                        Entry::Exposed { phase } => {
+                            out.push(TAG_PHASE_EXPOSED);
+                            write_varint(&mut out, phase);
+                        }
+                        Entry::Periodic => out.push(TAG_PERIODIC),
+                    }
+                    write_varint(&mut out, var.index);
+                }
+                SymbolicExpressionNode::IsFirstRow => out.push(TAG_IS_FIRST_ROW),
+                SymbolicExpressionNode::IsLastRow => out.push(TAG_IS_LAST_ROW),
+                SymbolicExpressionNode::IsTransition => out.push(TAG_IS_TRANSITION),
+                SymbolicExpressionNode::Constant(c) => {
+                    out.push(TAG_CONSTANT);
+                    let bytes = c.as_canonical_u64().to_le_bytes();
+                    out.extend_from_slice(&bytes[..constant_len::<F>()]);
+                }
+                SymbolicExpressionNode::Add {
+                    left_idx,
+                    right_idx,
+                    degree_multiple,
+                } => {
+                    out.push(TAG_ADD);
+                    write_varints(&mut out, &[*left_idx, *right_idx, *degree_multiple]);
+                }
+                SymbolicExpressionNode::Sub {
+                    left_idx,
+                    right_idx,
+                    degree_multiple,
+                } => {
+                    out.push(TAG_SUB);
+                    write_varints(&mut out, &[*left_idx, *right_idx, *degree_multiple]);
+                }
+                SymbolicExpressionNode::Neg {
+                    idx,
+                    degree_multiple,
+                } => {
+                    out.push(TAG_NEG);
+                    write_varints(&mut out, &[*idx, *degree_multiple]);
+                }
+                SymbolicExpressionNode::Mul {
+                    left_idx,
+                    right_idx,
+                    degree_multiple,
+                } => {
+                    out.push(TAG_MUL);
+                    write_varints(&mut out, &[*left_idx, *right_idx, *degree_multiple]);
+                }
+            }
+        }
+        write_varint(&mut out, self.constraint_idx.len());
+        write_varints(&mut out, &self.constraint_idx);
+        out
+    }
+
+    /// Decodes a DAG encoded by [to_bytes](Self::to_bytes). Malformed input, including an
+    /// unknown format version, is rejected with an error.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DagDecodingError> {
+        let mut reader = Reader { bytes };
+        let version = reader.read_u8()?;
+        if version != DAG_FORMAT_VERSION {
+            return Err(DagDecodingError::UnsupportedVersion(version));
+        }
+        let num_nodes = reader.read_varint()?;
+        // Every node takes at least one byte, which bounds the allocation by the input size.
+        let mut nodes = Vec::with_capacity(num_nodes.min(reader.bytes.len()));
+        for node_idx in 0..num_nodes {
+            let child = |reader: &mut Reader| -> Result<usize, DagDecodingError> {
+                let idx = reader.read_varint()?;
+                if idx >= node_idx {
+                    return Err(DagDecodingError::InvalidNodeIndex(idx));
+                }
+                Ok(idx)
+            };
+            let variable = |reader: &mut Reader,
+                            entry: Entry|
+             -> Result<SymbolicExpressionNode<F>, DagDecodingError> {
+                let index = reader.read_varint()?;
+                Ok(SymbolicExpressionNode::Variable(SymbolicVariable::new(
+                    entry, index,
+                )))
+            };
+            let node = match reader.read_u8()? {
+                TAG_PREPROCESSED => {
+                    let offset = reader.read_varint()?;
+                    variable(&mut reader, Entry::Preprocessed { offset })?
+                }
+                TAG_MAIN => {
+                    let part_index = reader.read_varint()?;
+                    let offset = reader.read_varint()?;
+                    variable(&mut reader, Entry::Main { part_index, offset })?
+                }
+                TAG_PERMUTATION => {
+                    let offset = reader.read_varint()?;
+                    variable(&mut reader, Entry::Permutation { phase: 0, offset })?
+                }
+                TAG_PHASE_PERMUTATION => {
+                    let phase = reader.read_varint()?;
+                    let offset = reader.read_varint()?;
+                    variable(&mut reader, Entry::Permutation { phase, offset })?
+                }
+                TAG_PUBLIC => variable(&mut reader, Entry::Public)?,
+                TAG_CHALLENGE => variable(&mut reader, Entry::Challenge { phase: 0 })?,
+                TAG_PHASE_CHALLENGE => {
+                    let phase = reader.read_varint()?;
+                    variable(&mut reader, Entry::Challenge { phase })?
+                }
+                TAG_EXPOSED => variable(&mut reader, Entry::Exposed { phase: 0 })?,
+                TAG_PHASE_EXPOSED => {
+                    let phase = reader.read_varint()?;
+                    variable(&mut reader, Entry::Exposed { phase })?
+                }
+                TAG_PERIODIC => variable(&mut reader, Entry::Periodic)?,
+                TAG_IS_FIRST_ROW => SymbolicExpressionNode::IsFirstRow,
+                TAG_IS_LAST_ROW => SymbolicExpressionNode::IsLastRow,
+                TAG_IS_TRANSITION => SymbolicExpressionNode::IsTransition,
+                TAG_CONSTANT => {
+                    let mut bytes = [0u8; 8];
+                    bytes[..constant_len::<F>()]
+                        .copy_from_slice(reader.read_bytes(constant_len::<F>())?);
+                    let value = u64::from_le_bytes(bytes);
+                    if value >= F::ORDER_U64 {
+                        return Err(DagDecodingError::NonCanonicalConstant);
+                    }
+                    SymbolicExpressionNode::Constant(F::from_canonical_u64(value))
+                }
+                TAG_ADD => SymbolicExpressionNode::Add {
+                    left_idx: child(&mut reader)?,
+                    right_idx: child(&mut reader)?,
+                    degree_multiple: reader.read_varint()?,
+                },
+                TAG_SUB => SymbolicExpressionNode::Sub {
+                    left_idx: child(&mut reader)?,
+                    right_idx: child(&mut reader)?,
+                    degree_multiple: reader.read_varint()?,
+                },
+                TAG_NEG => SymbolicExpressionNode::Neg {
+                    idx: child(&mut reader)?,
+                    degree_multiple: reader.read_varint()?,
+                },
+                TAG_MUL => SymbolicExpressionNode::Mul {
+                    left_idx: child(&mut reader)?,
+                    right_idx: child(&mut reader)?,
+                    degree_multiple: reader.read_varint()?,
+                },
+                tag => return Err(DagDecodingError::UnknownTag(tag)),
+            };
+            nodes.push(node);
+        }
+        let num_constraints = reader.read_varint()?;
+        let mut constraint_idx = Vec::with_capacity(num_constraints.min(reader.bytes.len()));
+        for _ in 0..num_constraints {
+            let idx = reader.read_varint()?;
+            if idx >= nodes.len() {
+                return Err(DagDecodingError::InvalidNodeIndex(idx));
+            }
+            constraint_idx.push(idx);
+        }
+        if !reader.bytes.is_empty() {
+            return Err(DagDecodingError::TrailingBytes(reader.bytes.len()));
+        }
+        Ok(Self {
+            nodes,
+            constraint_idx,
+        })
+    }
+}
+
+/// Number of bytes of an encoded constant of `F`.
+fn constant_len<F: PrimeField64>() -> usize {
+    let bits = u64::BITS - F::ORDER_U64.leading_zeros();
+    bits.div_ceil(8) as usize
+}
+
+pub(crate) fn write_varint(out: &mut Vec<u8>, value: usize) {
+    let mut value = value as u64;
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn write_varints(out: &mut Vec<u8>, values: &[usize]) {
+    for &value in values {
+        write_varint(out, value);
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], DagDecodingError> {
+        if self.bytes.len() < len {
+            return Err(DagDecodingError::UnexpectedEnd);
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, DagDecodingError> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_varint(&mut self) -> Result<usize, DagDecodingError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.read_u8()?;
+            let bits = (byte & 0x7f) as u64;
+            if (bits << shift) >> shift != bits {
+                return Err(DagDecodingError::VarintOverflow);
+            }
+            value |= bits << shift;
+            if byte & 0x80 == 0 {
+                return usize::try_from(value).map_err(|_| DagDecodingError::VarintOverflow);
+            }
+        }
+        Err(DagDecodingError::VarintOverflow)
+    }
+}
+
+/// Verifying key encoding of a [SymbolicConstraintsDag]: the expression DAG in the compact
+/// binary format, with the interactions serialized as is.
+#[derive(Serialize, Deserialize)]
+struct CompactConstraintsDag<I> {
+    constraints: Vec<u8>,
+    interactions: I,
+}
+
+pub(crate) fn serialize_constraints_dag<F: PrimeField64, S: Serializer>(
+    dag: &SymbolicConstraintsDag<F>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    CompactConstraintsDag {
+        constraints: dag.constraints.to_bytes(),
+        interactions: &dag.interactions,
+    }
+    .serialize(serializer)
+}
+
+pub(crate) fn deserialize_constraints_dag<'de, F: PrimeField64, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<SymbolicConstraintsDag<F>, D::Error> {
+    let compact = CompactConstraintsDag::<Vec<Interaction<usize>>>::deserialize(deserializer)?;
+    let constraints =
+        SymbolicExpressionDag::from_bytes(&compact.constraints).map_err(D::Error::custom)?;
+    let num_nodes = constraints.nodes.len();
+    if let Some(&idx) = compact
+        .interactions
+        .iter()
+        .flat_map(|interaction| interaction.fields.iter().chain([&interaction.count]))
+        .find(|&&idx| idx >= num_nodes)
+    {
+        return Err(D::Error::custom(DagDecodingError::InvalidNodeIndex(idx)));
+    }
+    Ok(SymbolicConstraintsDag {
+        constraints,
+        interactions: compact.interactions,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use p3_baby_bear::BabyBear;
+    use p3_field::FieldAlgebra;
+    use p3_goldilocks::Goldilocks;
+
+    use super::*;
+
+    fn sample_dag<F: PrimeField64>() -> SymbolicExpressionDag<F> {
+        SymbolicExpressionDag {
+            nodes: vec![
+                SymbolicExpressionNode::IsFirstRow,
+                SymbolicExpressionNode::Variable(SymbolicVariable::new(
+                    Entry::Main {
+                        part_index: 1,
+                        offset: 2,
+                    },
+                    300,
+                )),
+                SymbolicExpressionNode::Constant(F::NEG_ONE),
+                SymbolicExpressionNode::Mul {
+                    left_idx: 0,
+                    right_idx: 1,
+                    degree_multiple: 2,
+                },
+                SymbolicExpressionNode::Sub {
+                    left_idx: 3,
+                    right_idx: 2,
+                    degree_multiple: 2,
+                },
+                SymbolicExpressionNode::Neg {
+                    idx: 4,
+                    degree_multiple: 2,
+                },
+                SymbolicExpressionNode::Variable(SymbolicVariable::new(Entry::Public, 0)),
+                SymbolicExpressionNode::Variable(SymbolicVariable::new(
+                    Entry::Permutation {
+                        phase: 0,
+                        offset: 1,
+                    },
+                    5,
+                )),
+                SymbolicExpressionNode::Add {
+                    left_idx: 6,
+                    right_idx: 7,
+                    degree_multiple: 1,
+                },
+                SymbolicExpressionNode::IsTransition,
+            ],
+            constraint_idx: vec![5, 8],
+        }
+    }
+
+    #[test]
+    fn test_dag_bytes_round_trip() {
+        let dag = sample_dag::<BabyBear>();
+        let bytes = dag.to_bytes();
+        assert_eq!(SymbolicExpressionDag::from_bytes(&bytes), Ok(dag));
+
+        let dag = sample_dag::<Goldilocks>();
+        let bytes = dag.to_bytes();
+        assert_eq!(SymbolicExpressionDag::from_bytes(&bytes), Ok(dag));
+    }
+
+    #[test]
+    fn test_dag_bytes_later_phases() {
+        let dag = SymbolicExpressionDag::<BabyBear> {
+            nodes: [
+                Entry::Permutation {
+                    phase: 1,
+                    offset: 1,
+                },
+                Entry::Challenge { phase: 2 },
+                Entry::Exposed { phase: 1 },
+            ]
+            .into_iter()
+            .map(|entry| SymbolicExpressionNode::Variable(SymbolicVariable::new(entry, 3)))
+            .collect(),
+            constraint_idx: vec![0, 1, 2],
+        };
+        let bytes = dag.to_bytes();
+        assert_eq!(bytes[2..6], [TAG_PHASE_PERMUTATION, 1, 1, 3]);
+        assert_eq!(SymbolicExpressionDag::from_bytes(&bytes), Ok(dag));
+    }
+
+    #[test]
+    fn test_dag_bytes_fixture() {
+        // Changing the encoding of an existing format version breaks serialized verifying keys.
+        // Bump `DAG_FORMAT_VERSION` instead of updating this fixture.
+        let fixture = include_bytes!("../../../tests/fixtures/symbolic_expression_dag_v1.bin");
+        let dag = sample_dag::<BabyBear>();
+        assert_eq!(dag.to_bytes(), fixture);
+        assert_eq!(SymbolicExpressionDag::from_bytes(fixture), Ok(dag));
+    }
+
+    #[test]
+    fn test_dag_bytes_malformed() {
+        let bytes = sample_dag::<BabyBear>().to_bytes();
+        let decode = |bytes: &[u8]| SymbolicExpressionDag::<BabyBear>::from_bytes(bytes);
+
+        let mut unknown_version = bytes.clone();
+        unknown_version[0] = DAG_FORMAT_VERSION + 1;
+        assert_eq!(
+            decode(&unknown_version),
+            Err(DagDecodingError::UnsupportedVersion(DAG_FORMAT_VERSION + 1))
+        );
+        assert_eq!(
+            decode(&bytes[..bytes.len() - 1]),
+            Err(DagDecodingError::UnexpectedEnd)
+        );
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(decode(&trailing), Err(DagDecodingError::TrailingBytes(1)));
+
+        // A node referring to itself.
+        let self_loop = [DAG_FORMAT_VERSION, 1, TAG_NEG, 0, 1, 0];
+        assert_eq!(
+            decode(&self_loop),
+            Err(DagDecodingError::InvalidNodeIndex(0))
+        );
+        let unknown_tag = [DAG_FORMAT_VERSION, 1, 0xff, 0];
+        assert_eq!(
+            decode(&unknown_tag),
+            Err(DagDecodingError::UnknownTag(0xff))
+        );
+        // The BabyBear modulus 2^31 - 2^27 + 1 is not a canonical constant.
+        let non_canonical = [DAG_FORMAT_VERSION, 1, TAG_CONSTANT, 0x01, 0, 0, 0x78, 0];
+        assert_eq!(
+            decode(&non_canonical),
+            Err(DagDecodingError::NonCanonicalConstant)
+        );
+        assert_eq!(
+            decode(&[
+                DAG_FORMAT_VERSION,
+                0xff,
+                0xff,
+                0xff,
+                0xff,
+                0xff,
+                0xff,
+                0xff,
+                0xff,
+                0xff,
+                0x7f
+            ]),
+            Err(DagDecodingError::VarintOverflow)
+        );
+    }
+
+    #[test]
+    fn test_dag_bytes_constant_len() {
+        assert_eq!(constant_len::<BabyBear>(), 4);
+        assert_eq!(constant_len::<Goldilocks>(), 8);
+        assert_eq!(
+            SymbolicExpressionDag::<BabyBear> {
+                nodes: vec![SymbolicExpressionNode::Constant(BabyBear::TWO)],
+                constraint_idx: vec![0],
+            }
+            .to_bytes(),
+            vec![DAG_FORMAT_VERSION, 1, TAG_CONSTANT, 2, 0, 0, 0, 1, 0]
+        );
+    }
+}
@@ -1,5 +1,6 @@
 use std::sync::Arc;
 
+use itertools::Itertools;
 use p3_field::Field;
 use rustc_hash::FxHashMap;
 use serde::{Deserialize, Serialize};
@@ -7,7 +8,8 @@ use serde::{Deserialize, Serialize};
 use super::SymbolicConstraints;
 use crate::{
     air_builders::symbolic::{
-        symbolic_expression::SymbolicExpression, symbolic_variable::SymbolicVariable,
+        symbolic_expression::SymbolicExpression,
+        symbolic_variable::{Entry, SymbolicVariable},
     },
     interaction::{Interaction, SymbolicInteraction},
 };
@@ -56,6 +58,11 @@ pub struct SymbolicExpressionDag<F> {
 }
 
 impl<F> SymbolicExpressionDag<F> {
+    /// Number of expressions asserted to equal zero.
+    pub fn num_constraints(&self) -> usize {
+        self.constraint_idx.len()
+    }
+
     pub fn max_rotation(&self) -> usize {
         let mut rotation = 0;
         for node in &self.nodes {
@@ -65,6 +72,25 @@ impl<F> SymbolicExpressionDag<F> {
         }
         rotation
     }
+
+    /// Sorted row offsets greater than 1 referenced by preprocessed or main trace variables.
+    /// The local and next rows are always opened, so they are not included.
+    pub fn extra_rotations(&self) -> Vec<usize> {
+        self.nodes
+            .iter()
+            .filter_map(|node| match node {
+                SymbolicExpressionNode::Variable(var) => match var.entry {
+                    Entry::Preprocessed { offset } | Entry::Main { offset, .. } if offset > 1 => {
+                        Some(offset)
+                    }
+                    _ => None,
+                },
+                _ => None,
+            })
+            .sorted()
+            .dedup()
+            .collect()
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -87,7 +113,18 @@ pub struct SymbolicConstraintsDag<F> {
     pub interactions: Vec<Interaction<usize>>,
 }
 
-pub(crate) fn build_symbolic_constraints_dag<F: Field>(
+/// Builds the DAG of the given constraints and interactions.
+///
+/// Subexpressions reached through the same `Arc` are mapped to a single node, as are equal
+/// leaves. Structurally equal subexpressions held in distinct `Arc`s are only merged if their
+/// children were already merged, so the DAG is not guaranteed to be minimal.
+///
+/// The conversion back to expressions, [`SymbolicConstraintsDag::to_symbolic_constraints`],
+/// returns expressions that are structurally equal to the inputs of this function. Converting
+/// a DAG to expressions and back preserves the node degrees and the values of all constraints
+/// and interactions, but may merge nodes with the same operation and children. After one such
+/// round trip the DAG is a fixpoint: further round trips return an identical DAG.
+pub fn build_symbolic_constraints_dag<F: Field>(
     constraints: &[SymbolicExpression<F>],
     interactions: &[SymbolicInteraction<F>],
 ) -> SymbolicConstraintsDag<F> {
@@ -204,9 +241,17 @@ fn topological_sort_symbolic_expr<'a, F: Field>(
 }
 
 impl<F: Field> SymbolicExpressionDag<F> {
+    /// Builds the DAG of the given constraints. See [`build_symbolic_constraints_dag`].
+    pub fn from_expressions(constraints: &[SymbolicExpression<F>]) -> Self {
+        build_symbolic_constraints_dag(constraints, &[]).constraints
+    }
+
     /// Convert each node to a [`SymbolicExpression<F>`] reference and return
     /// the full list.
-    fn to_symbolic_expressions(&self) -> Vec<Arc<SymbolicExpression<F>>> {
+    ///
+    /// This is the let-binding form of the DAG: the expression at index `i` references the
+    /// expressions of its children by `Arc`, at indices smaller than `i`, so sharing is preserved.
+    pub fn to_symbolic_expressions(&self) -> Vec<Arc<SymbolicExpression<F>>> {
         let mut exprs: Vec<Arc<SymbolicExpression<_>>> = Vec::with_capacity(self.nodes.len());
         for node in &self.nodes {
             let expr = match *node {
@@ -254,19 +299,30 @@ impl<F: Field> SymbolicExpressionDag<F> {
         }
         exprs
     }
+
+    /// Returns the constraints as expressions, with shared subexpressions held in the same `Arc`.
+    pub fn constraint_expressions(&self) -> Vec<SymbolicExpression<F>> {
+        let exprs = self.to_symbolic_expressions();
+        self.constraint_idx
+            .iter()
+            .map(|&idx| exprs[idx].as_ref().clone())
+            .collect()
+    }
 }
 
-// TEMPORARY conversions until we switch main interfaces to use SymbolicConstraintsDag
-impl<'a, F: Field> From<&'a SymbolicConstraintsDag<F>> for SymbolicConstraints<F> {
-    fn from(dag: &'a SymbolicConstraintsDag<F>) -> Self {
-        let exprs = dag.constraints.to_symbolic_expressions();
-        let constraints = dag
+impl<F: Field> SymbolicConstraintsDag<F> {
+    /// Converts the DAG back to constraint and interaction expressions, with shared
+    /// subexpressions held in the same `Arc`. See [`build_symbolic_constraints_dag`] for what is
+    /// preserved by the round trip.
+    pub fn to_symbolic_constraints(&self) -> SymbolicConstraints<F> {
+        let exprs = self.constraints.to_symbolic_expressions();
+        let constraints = self
             .constraints
             .constraint_idx
             .iter()
             .map(|&idx| exprs[idx].as_ref().clone())
             .collect::<Vec<_>>();
-        let interactions = dag
+        let interactions = self
             .interactions
             .iter()
             .map(|interaction| {
@@ -291,6 +347,12 @@ impl<'a, F: Field> From<&'a SymbolicConstraintsDag<F>> for SymbolicConstraints<F
     }
 }
 
+impl<'a, F: Field> From<&'a SymbolicConstraintsDag<F>> for SymbolicConstraints<F> {
+    fn from(dag: &'a SymbolicConstraintsDag<F>) -> Self {
+        dag.to_symbolic_constraints()
+    }
+}
+
 impl<F: Field> From<SymbolicConstraintsDag<F>> for SymbolicConstraints<F> {
     fn from(dag: SymbolicConstraintsDag<F>) -> Self {
         (&dag).into()
@@ -307,11 +369,14 @@ impl<F: Field> From<SymbolicConstraints<F>> for SymbolicConstraintsDag<F> {
 mod tests {
     use p3_baby_bear::BabyBear;
     use p3_field::FieldAlgebra;
+    use p3_matrix::dense::RowMajorMatrixView;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
 
     use crate::{
         air_builders::symbolic::{
             dag::{build_symbolic_constraints_dag, SymbolicExpressionDag, SymbolicExpressionNode},
-            symbolic_expression::SymbolicExpression,
+            interpreter::{eval_symbolic_constraints, SymbolicRowValues},
+            symbolic_expression::{SymbolicEvaluator, SymbolicExpression},
             symbolic_variable::{Entry, SymbolicVariable},
             SymbolicConstraints,
         },
@@ -417,4 +482,82 @@ mod tests {
         let new_sc: SymbolicConstraints<_> = serde_json::from_str(&ser_str).unwrap();
         assert_eq!(sc, new_sc);
     }
+
+    /// Random expressions over a few variables, where later expressions share earlier ones as
+    /// subexpressions.
+    fn random_constraints(rng: &mut StdRng) -> Vec<SymbolicExpression<F>> {
+        let mut pool: Vec<SymbolicExpression<F>> = vec![
+            SymbolicExpression::IsFirstRow,
+            SymbolicExpression::IsLastRow,
+            SymbolicExpression::IsTransition,
+            SymbolicExpression::Constant(rng.gen()),
+            SymbolicVariable::new(Entry::Preprocessed { offset: 0 }, 0).into(),
+            SymbolicVariable::new(Entry::Public, rng.gen_range(0..2)).into(),
+        ];
+        for _ in 0..4 {
+            let entry = Entry::Main {
+                part_index: 0,
+                offset: rng.gen_range(0..2),
+            };
+            pool.push(SymbolicVariable::new(entry, rng.gen_range(0..3)).into());
+        }
+        // Few enough operations that evaluating the expressions as trees stays cheap.
+        for _ in 0..16 {
+            let x = pool[rng.gen_range(0..pool.len())].clone();
+            let y = pool[rng.gen_range(0..pool.len())].clone();
+            let expr = match rng.gen_range(0..4) {
+                0 => x + y,
+                1 => x - y,
+                2 => -x,
+                _ => x * y,
+            };
+            pool.push(expr);
+        }
+        pool.split_off(pool.len() - 4)
+    }
+
+    #[test]
+    fn test_symbolic_constraints_dag_round_trip() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..32 {
+            let constraints = random_constraints(&mut rng);
+            let dag = SymbolicExpressionDag::from_expressions(&constraints);
+            let constraints2 = dag.constraint_expressions();
+            assert_eq!(constraints, constraints2);
+            let dag2 = SymbolicExpressionDag::from_expressions(&constraints2);
+            let constraints3 = dag2.constraint_expressions();
+            assert_eq!(constraints, constraints3);
+            let dag3 = SymbolicExpressionDag::from_expressions(&constraints3);
+            assert_eq!(dag2, dag3);
+            assert!(dag2.nodes.len() <= dag.nodes.len());
+
+            let preprocessed: Vec<F> = (0..2).map(|_| rng.gen()).collect();
+            let main: Vec<F> = (0..6).map(|_| rng.gen()).collect();
+            let public_values: Vec<F> = (0..2).map(|_| rng.gen()).collect();
+            let values = SymbolicRowValues::<F, F> {
+                preprocessed: RowMajorMatrixView::new(&preprocessed, 1),
+                partitioned_main: vec![RowMajorMatrixView::new(&main, 3)],
+                after_challenge: vec![],
+                challenges: &[],
+                public_values: &public_values,
+                periodic_values: &[],
+                exposed_values_after_challenge: &[],
+                is_first_row: rng.gen(),
+                is_last_row: rng.gen(),
+                is_transition: rng.gen(),
+            };
+            let expected = constraints
+                .iter()
+                .map(|expr| values.eval_expr(expr))
+                .collect::<Vec<_>>();
+            for dag in [&dag, &dag2, &dag3] {
+                assert_eq!(eval_symbolic_constraints(dag, &values), expected);
+            }
+            for constraints in [&constraints2, &constraints3] {
+                for (expr, &value) in constraints.iter().zip(&expected) {
+                    assert_eq!(values.eval_expr(expr), value);
+                }
+            }
+        }
+    }
 }
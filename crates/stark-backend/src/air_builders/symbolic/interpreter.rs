@@ -0,0 +1,78 @@
+//! Reference interpreter evaluating a [SymbolicExpressionDag] on concrete values.
+//!
+//! The interpreter is deliberately simple: every value is lifted to the extension field and
+//! nodes are evaluated serially. It serves as ground truth for the optimized constraint
+//! evaluators and allows evaluating constraints without implementing an `AirBuilder`.
+
+use p3_field::{ExtensionField, Field};
+use p3_matrix::Matrix;
+
+use super::{
+    symbolic_expression::SymbolicEvaluator,
+    symbolic_variable::{Entry, SymbolicVariable},
+    SymbolicExpressionDag,
+};
+use crate::air_builders::ViewWindow;
+
+/// Concrete values of a single row of a RAP, used to evaluate its symbolic constraints.
+pub struct SymbolicRowValues<'a, F, EF> {
+    /// Window of the preprocessed trace. Use an empty matrix if there is no preprocessed trace.
+    pub preprocessed: ViewWindow<'a, F>,
+    /// Window of each main trace partition.
+    pub partitioned_main: Vec<ViewWindow<'a, F>>,
+    /// Window of the trace of each challenge phase.
+    pub after_challenge: Vec<ViewWindow<'a, EF>>,
+    /// For each challenge phase, the challenges drawn.
+    pub challenges: &'a [Vec<EF>],
+    pub public_values: &'a [F],
+    /// Values of the periodic columns at the row.
+    pub periodic_values: &'a [F],
+    /// For each challenge phase, the values exposed to the verifier.
+    pub exposed_values_after_challenge: &'a [Vec<EF>],
+    pub is_first_row: F,
+    pub is_last_row: F,
+    pub is_transition: F,
+}
+
+impl<F: Field, EF: ExtensionField<F>> SymbolicEvaluator<F, EF> for SymbolicRowValues<'_, F, EF> {
+    fn eval_const(&self, c: F) -> EF {
+        c.into()
+    }
+    fn eval_is_first_row(&self) -> EF {
+        self.is_first_row.into()
+    }
+    fn eval_is_last_row(&self) -> EF {
+        self.is_last_row.into()
+    }
+    fn eval_is_transition(&self) -> EF {
+        self.is_transition.into()
+    }
+    fn eval_var(&self, symbolic_var: SymbolicVariable<F>) -> EF {
+        let index = symbolic_var.index;
+        match symbolic_var.entry {
+            Entry::Preprocessed { offset } => self.preprocessed.get(offset, index).into(),
+            Entry::Main { part_index, offset } => {
+                self.partitioned_main[part_index].get(offset, index).into()
+            }
+            Entry::Public => self.public_values[index].into(),
+            Entry::Periodic => self.periodic_values[index].into(),
+            Entry::Permutation { phase, offset } => self.after_challenge[phase].get(offset, index),
+            Entry::Challenge { phase } => self.challenges[phase][index],
+            Entry::Exposed { phase } => self.exposed_values_after_challenge[phase][index],
+        }
+    }
+}
+
+/// Evaluates every constraint of `dag` on the concrete `values` of a single row.
+///
+/// Returns the value of each constraint, in the order of the constraints in the DAG.
+pub fn eval_symbolic_constraints<F: Field, EF: ExtensionField<F>>(
+    dag: &SymbolicExpressionDag<F>,
+    values: &SymbolicRowValues<'_, F, EF>,
+) -> Vec<EF> {
+    let evaluated_nodes = values.eval_nodes(&dag.nodes);
+    dag.constraint_idx
+        .iter()
+        .map(|&idx| evaluated_nodes[idx])
+        .collect()
+}
@@ -1,5 +1,7 @@
 // Copied from uni-stark/src/symbolic_builder.rs to allow A: ?Sized
 
+use std::mem;
+
 use itertools::Itertools;
 use p3_air::{
     AirBuilder, AirBuilderWithPublicValues, ExtensionBuilder, PairBuilder, PermutationAirBuilder,
@@ -14,20 +16,31 @@ use self::{
     symbolic_expression::SymbolicExpression,
     symbolic_variable::{Entry, SymbolicVariable},
 };
-use super::PartitionedAirBuilder;
+use super::{PartitionedAirBuilder, PeriodicAirBuilder};
 use crate::{
     interaction::{
-        fri_log_up::find_interaction_chunks, rap::InteractionPhaseAirBuilder, Interaction,
-        InteractionBuilder, InteractionType, RapPhaseSeqKind, SymbolicInteraction,
+        fri_log_up::{find_interaction_partitions, FriLogUpParams},
+        gkr_log_up::GKR_LU_WIDTH,
+        prune::{prune_interactions, PrunedInteractions},
+        rap::InteractionPhaseAirBuilder,
+        Interaction, InteractionBuilder, InteractionType, RapPhaseSeqKind, RapPhases,
+        SymbolicInteraction,
     },
     keygen::types::{StarkVerifyingParams, TraceWidth},
-    rap::{BaseAirWithPublicValues, PermutationAirBuilderWithExposedValues, Rap},
+    rap::{
+        BaseAirWithPublicValues, MultiPhaseAirBuilder, PartitionedBaseAir,
+        PermutationAirBuilderWithExposedValues, Rap,
+    },
 };
 
+mod codec;
 mod dag;
+pub mod interpreter;
 pub mod symbolic_expression;
 pub mod symbolic_variable;
 
+pub(crate) use codec::{deserialize_constraints_dag, serialize_constraints_dag, write_varint};
+pub use codec::{DagDecodingError, DAG_FORMAT_VERSION};
 pub use dag::*;
 
 /// Symbolic constraints for a single AIR with interactions.
@@ -66,6 +79,16 @@ impl<F: Field> SymbolicConstraints<F> {
         log2_ceil_usize(constraint_degree - 1)
     }
 
+    /// Same as [get_log_quotient_degree](Self::get_log_quotient_degree) when the traces are
+    /// randomized for zero knowledge, see
+    /// [set_zero_knowledge](crate::keygen::MultiStarkKeygenBuilder::set_zero_knowledge).
+    pub fn get_zk_log_quotient_degree(&self) -> usize {
+        let constraint_degree = self.max_constraint_degree().max(2);
+        // A randomized trace has degree below twice the trace height, so the quotient's degree is
+        // below (2 * max_constraint_degree - 1) * (trace height).
+        log2_ceil_usize(2 * constraint_degree - 1)
+    }
+
     /// Returns the maximum field degree and count degree across all interactions
     pub fn max_interaction_degrees(&self) -> (usize, usize) {
         let max_field_degree = self
@@ -93,26 +116,35 @@ impl<F: Field> SymbolicConstraints<F> {
     }
 }
 
+/// If `prune_interactions`, the interactions are pruned before the RAP phase constraints are
+/// added, see [SymbolicRapBuilder::pruned_interactions].
+#[allow(clippy::too_many_arguments)]
 #[instrument(name = "evaluate constraints symbolically", skip_all, level = "debug")]
 pub fn get_symbolic_builder<F, R>(
     rap: &R,
     width: &TraceWidth,
     num_challenges_to_sample: &[usize],
     num_exposed_values_after_challenge: &[usize],
-    rap_phase_seq_kind: RapPhaseSeqKind,
+    rap_phases: impl Into<RapPhases>,
     max_constraint_degree: usize,
+    log_up_params: FriLogUpParams,
+    prune_interactions: bool,
 ) -> SymbolicRapBuilder<F>
 where
     F: Field,
-    R: Rap<SymbolicRapBuilder<F>> + BaseAirWithPublicValues<F> + ?Sized,
+    R: Rap<SymbolicRapBuilder<F>> + BaseAirWithPublicValues<F> + PartitionedBaseAir<F> + ?Sized,
 {
     let mut builder = SymbolicRapBuilder::new(
         width,
         rap.num_public_values(),
         num_challenges_to_sample,
         num_exposed_values_after_challenge,
-        rap_phase_seq_kind,
+        rap_phases.into(),
         max_constraint_degree,
+        log_up_params,
+        prune_interactions,
+        rap.max_rotation(),
+        rap.periodic_columns().len(),
     );
     Rap::eval(rap, &mut builder);
     builder
@@ -125,12 +157,17 @@ pub struct SymbolicRapBuilder<F> {
     partitioned_main: Vec<RowMajorMatrix<SymbolicVariable<F>>>,
     after_challenge: Vec<RowMajorMatrix<SymbolicVariable<F>>>,
     public_values: Vec<SymbolicVariable<F>>,
+    periodic_values: Vec<SymbolicVariable<F>>,
     challenges: Vec<Vec<SymbolicVariable<F>>>,
     exposed_values_after_challenge: Vec<Vec<SymbolicVariable<F>>>,
     constraints: Vec<SymbolicExpression<F>>,
     interactions: Vec<SymbolicInteraction<F>>,
     max_constraint_degree: usize,
-    rap_phase_seq_kind: RapPhaseSeqKind,
+    /// The parameters of the FRI log-up constraints of the RAP phase.
+    log_up_params: FriLogUpParams,
+    prune_interactions: bool,
+    pruned_interactions: PrunedInteractions,
+    rap_phases: RapPhases,
     trace_width: TraceWidth,
 
     /// Caching for FRI logup to avoid recomputation during keygen
@@ -140,17 +177,26 @@ pub struct SymbolicRapBuilder<F> {
 impl<F: Field> SymbolicRapBuilder<F> {
     /// - `num_challenges_to_sample`: for each challenge phase, how many challenges to sample
     /// - `num_exposed_values_after_challenge`: in each challenge phase, how many values to expose to verifier
+    /// - `log_up_params`: see [RapPhaseSeq::log_up_params](crate::interaction::RapPhaseSeq::log_up_params)
+    /// - `prune_interactions`: whether to prune the interactions once they are all pushed
+    /// - `max_rotation`: the preprocessed and main trace matrices contain the rows at offsets `0..=max_rotation`
+    /// - `num_periodic_columns`: number of periodic columns of the AIR
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
         width: &TraceWidth,
         num_public_values: usize,
         num_challenges_to_sample: &[usize],
         num_exposed_values_after_challenge: &[usize],
-        rap_phase_seq_kind: RapPhaseSeqKind,
+        rap_phases: RapPhases,
         max_constraint_degree: usize,
+        log_up_params: FriLogUpParams,
+        prune_interactions: bool,
+        max_rotation: usize,
+        num_periodic_columns: usize,
     ) -> Self {
+        let max_rotation = max_rotation.max(1);
         let preprocessed_width = width.preprocessed.unwrap_or(0);
-        let prep_values = [0, 1]
-            .into_iter()
+        let prep_values = (0..=max_rotation)
             .flat_map(|offset| {
                 (0..width.preprocessed.unwrap_or(0))
                     .map(move |index| SymbolicVariable::new(Entry::Preprocessed { offset }, index))
@@ -162,10 +208,21 @@ impl<F: Field> SymbolicRapBuilder<F> {
             .cached_mains
             .iter()
             .enumerate()
-            .map(|(part_index, &width)| gen_main_trace(part_index, width))
+            .map(|(part_index, &width)| gen_main_trace(part_index, width, max_rotation))
             .collect();
         if width.common_main != 0 {
-            partitioned_main.push(gen_main_trace(width.cached_mains.len(), width.common_main));
+            partitioned_main.push(gen_main_trace(
+                width.cached_mains.len(),
+                width.common_main,
+                max_rotation,
+            ));
+        }
+        for &(_, custom_width) in &width.custom {
+            partitioned_main.push(gen_main_trace(
+                partitioned_main.len(),
+                custom_width,
+                max_rotation,
+            ));
         }
         let after_challenge = Self::new_after_challenge(&width.after_challenge);
 
@@ -173,6 +230,10 @@ impl<F: Field> SymbolicRapBuilder<F> {
             .map(move |index| SymbolicVariable::new(Entry::Public, index))
             .collect();
 
+        let periodic_values = (0..num_periodic_columns)
+            .map(|index| SymbolicVariable::new(Entry::Periodic, index))
+            .collect();
+
         let challenges = Self::new_challenges(num_challenges_to_sample);
 
         let exposed_values_after_challenge =
@@ -183,17 +244,27 @@ impl<F: Field> SymbolicRapBuilder<F> {
             partitioned_main,
             after_challenge,
             public_values,
+            periodic_values,
             challenges,
             exposed_values_after_challenge,
             constraints: vec![],
             interactions: vec![],
             max_constraint_degree,
-            rap_phase_seq_kind,
+            log_up_params,
+            prune_interactions,
+            pruned_interactions: PrunedInteractions::default(),
+            rap_phases,
             trace_width: width.clone(),
             interaction_partitions: None,
         }
     }
 
+    /// The interactions removed by the pruning, which drops the interactions with a constant
+    /// zero count and merges duplicate interactions.
+    pub fn pruned_interactions(&self) -> PrunedInteractions {
+        self.pruned_interactions
+    }
+
     pub fn constraints(self) -> SymbolicConstraints<F> {
         SymbolicConstraints {
             constraints: self.constraints,
@@ -210,6 +281,12 @@ impl<F: Field> SymbolicRapBuilder<F> {
             num_public_values: self.public_values.len(),
             num_exposed_values_after_challenge,
             num_challenges_to_sample,
+            // Determined from the constraints DAG during keygen
+            extra_rotations: vec![],
+            configuration_public_values: vec![],
+            // Set by keygen
+            max_log_height: None,
+            may_be_absent: false,
         }
     }
 
@@ -235,12 +312,13 @@ impl<F: Field> SymbolicRapBuilder<F> {
     ) -> Vec<RowMajorMatrix<SymbolicVariable<F>>> {
         width_after_phase
             .iter()
-            .map(|&width| {
+            .enumerate()
+            .map(|(phase, &width)| {
                 let mat_values = [0, 1]
                     .into_iter()
                     .flat_map(|offset| {
                         (0..width).map(move |index| {
-                            SymbolicVariable::new(Entry::Permutation { offset }, index)
+                            SymbolicVariable::new(Entry::Permutation { phase, offset }, index)
                         })
                     })
                     .collect_vec();
@@ -252,9 +330,10 @@ impl<F: Field> SymbolicRapBuilder<F> {
     fn new_challenges(num_challenges_to_sample: &[usize]) -> Vec<Vec<SymbolicVariable<F>>> {
         num_challenges_to_sample
             .iter()
-            .map(|&num_challenges| {
+            .enumerate()
+            .map(|(phase, &num_challenges)| {
                 (0..num_challenges)
-                    .map(|index| SymbolicVariable::new(Entry::Challenge, index))
+                    .map(|index| SymbolicVariable::new(Entry::Challenge { phase }, index))
                     .collect_vec()
             })
             .collect_vec()
@@ -265,9 +344,10 @@ impl<F: Field> SymbolicRapBuilder<F> {
     ) -> Vec<Vec<SymbolicVariable<F>>> {
         num_exposed_values_after_challenge
             .iter()
-            .map(|&num| {
+            .enumerate()
+            .map(|(phase, &num)| {
                 (0..num)
-                    .map(|index| SymbolicVariable::new(Entry::Exposed, index))
+                    .map(|index| SymbolicVariable::new(Entry::Exposed { phase }, index))
                     .collect_vec()
             })
             .collect_vec()
@@ -337,29 +417,51 @@ impl<F: Field> AirBuilderWithPublicValues for SymbolicRapBuilder<F> {
     }
 }
 
+impl<F: Field> PeriodicAirBuilder for SymbolicRapBuilder<F> {
+    type PeriodicVar = SymbolicVariable<F>;
+
+    fn periodic_values(&self) -> &[Self::PeriodicVar] {
+        &self.periodic_values
+    }
+}
+
 impl<F: Field> PermutationAirBuilder for SymbolicRapBuilder<F> {
     type MP = RowMajorMatrix<Self::VarEF>;
     type RandomVar = SymbolicVariable<F>;
 
     fn permutation(&self) -> Self::MP {
+        self.phase_trace(0)
+    }
+
+    fn permutation_randomness(&self) -> &[Self::RandomVar] {
+        self.phase_challenges(0)
+    }
+}
+
+impl<F: Field> PermutationAirBuilderWithExposedValues for SymbolicRapBuilder<F> {
+    fn permutation_exposed_values(&self) -> &[Self::VarEF] {
+        self.phase_exposed_values(0)
+    }
+}
+
+impl<F: Field> MultiPhaseAirBuilder for SymbolicRapBuilder<F> {
+    fn phase_trace(&self, phase: usize) -> Self::MP {
         self.after_challenge
-            .first()
+            .get(phase)
             .expect("Challenge phase not supported")
             .clone()
     }
 
-    fn permutation_randomness(&self) -> &[Self::RandomVar] {
+    fn phase_challenges(&self, phase: usize) -> &[Self::RandomVar] {
         self.challenges
-            .first()
+            .get(phase)
             .map(|c| c.as_slice())
             .expect("Challenge phase not supported")
     }
-}
 
-impl<F: Field> PermutationAirBuilderWithExposedValues for SymbolicRapBuilder<F> {
-    fn permutation_exposed_values(&self) -> &[Self::VarEF] {
+    fn phase_exposed_values(&self, phase: usize) -> &[Self::VarEF] {
         self.exposed_values_after_challenge
-            .first()
+            .get(phase)
             .map(|c| c.as_slice())
             .expect("Challenge phase not supported")
     }
@@ -392,8 +494,29 @@ impl<F: Field> InteractionBuilder for SymbolicRapBuilder<F> {
     }
 }
 
+impl<F: Field> SymbolicRapBuilder<F> {
+    /// Width of the FRI log-up after challenge trace, over all repetitions. Records the
+    /// interaction partitions.
+    fn fri_log_up_width(&mut self) -> usize {
+        let interaction_partitions = find_interaction_partitions(
+            &self.interactions,
+            self.max_constraint_degree,
+            self.log_up_params,
+        )
+        .interaction_partitions();
+        let num_chunks = interaction_partitions.len();
+        self.interaction_partitions.replace(interaction_partitions);
+        (num_chunks + 1) * self.log_up_params.num_repetitions
+    }
+}
+
 impl<F: Field> InteractionPhaseAirBuilder for SymbolicRapBuilder<F> {
     fn finalize_interactions(&mut self) {
+        if self.prune_interactions {
+            let (interactions, pruned) = prune_interactions(mem::take(&mut self.interactions));
+            self.interactions = interactions;
+            self.pruned_interactions = pruned;
+        }
         let num_interactions = self.num_interactions();
         if num_interactions != 0 {
             assert!(
@@ -403,22 +526,32 @@ impl<F: Field> InteractionPhaseAirBuilder for SymbolicRapBuilder<F> {
             assert!(self.challenges.is_empty());
             assert!(self.exposed_values_after_challenge.is_empty());
 
-            if self.rap_phase_seq_kind == RapPhaseSeqKind::FriLogUp {
-                let interaction_partitions =
-                    find_interaction_chunks(&self.interactions, self.max_constraint_degree)
-                        .interaction_partitions();
-                let num_chunks = interaction_partitions.len();
-                self.interaction_partitions.replace(interaction_partitions);
-                let perm_width = num_chunks + 1;
-                self.after_challenge = Self::new_after_challenge(&[perm_width]);
+            let widths = match self.rap_phases {
+                RapPhases::Kind(RapPhaseSeqKind::FriLogUp) => vec![self.fri_log_up_width()],
+                RapPhases::Kind(RapPhaseSeqKind::GkrLogUp) => vec![GKR_LU_WIDTH],
+                #[cfg(test)]
+                RapPhases::TestTwoPhase => vec![
+                    self.fri_log_up_width(),
+                    crate::interaction::two_phase::SECOND_PHASE_WIDTH,
+                ],
+            };
+            self.after_challenge = Self::new_after_challenge(&widths);
+
+            let mut phases_shapes = self.rap_phases.shape();
+            if self.rap_phases.kind() != RapPhaseSeqKind::GkrLogUp {
+                // Each repetition of the FRI log-up argument samples its own challenges and
+                // exposes its own cumulative sum.
+                let num_repetitions = self.log_up_params.num_repetitions;
+                phases_shapes[0].num_challenges *= num_repetitions;
+                phases_shapes[0].num_exposed_values *= num_repetitions;
             }
-
-            let phases_shapes = self.rap_phase_seq_kind.shape();
-            let phase_shape = phases_shapes.first().unwrap();
-
-            self.challenges = Self::new_challenges(&[phase_shape.num_challenges]);
+            let (num_challenges, num_exposed_values): (Vec<_>, Vec<_>) = phases_shapes
+                .iter()
+                .map(|shape| (shape.num_challenges, shape.num_exposed_values))
+                .unzip();
+            self.challenges = Self::new_challenges(&num_challenges);
             self.exposed_values_after_challenge =
-                Self::new_exposed_values_after_challenge(&[phase_shape.num_exposed_values]);
+                Self::new_exposed_values_after_challenge(&num_exposed_values);
         }
     }
 
@@ -426,8 +559,12 @@ impl<F: Field> InteractionPhaseAirBuilder for SymbolicRapBuilder<F> {
         self.max_constraint_degree
     }
 
-    fn rap_phase_seq_kind(&self) -> RapPhaseSeqKind {
-        self.rap_phase_seq_kind
+    fn log_up_params(&self) -> FriLogUpParams {
+        self.log_up_params
+    }
+
+    fn rap_phases(&self) -> RapPhases {
+        self.rap_phases
     }
 
     fn symbolic_interactions(&self) -> Vec<SymbolicInteraction<F>> {
@@ -446,6 +583,9 @@ impl<F: Field> PartitionedAirBuilder for SymbolicRapBuilder<F> {
         );
         &self.partitioned_main[self.trace_width.cached_mains.len()]
     }
+    fn custom_mains(&self) -> &[Self::M] {
+        &self.partitioned_main[self.partitioned_main.len() - self.trace_width.custom.len()..]
+    }
 }
 
 #[allow(dead_code)]
@@ -457,10 +597,11 @@ impl LocalOnlyChecker {
         match var.entry {
             Entry::Preprocessed { offset } => offset == 0,
             Entry::Main { offset, .. } => offset == 0,
-            Entry::Permutation { offset } => offset == 0,
+            Entry::Permutation { offset, .. } => offset == 0,
             Entry::Public => true,
-            Entry::Challenge => true,
-            Entry::Exposed => true,
+            Entry::Challenge { .. } => true,
+            Entry::Exposed { .. } => true,
+            Entry::Periodic => true,
         }
     }
 
@@ -482,9 +623,9 @@ impl LocalOnlyChecker {
 fn gen_main_trace<F: Field>(
     part_index: usize,
     width: usize,
+    max_rotation: usize,
 ) -> RowMajorMatrix<SymbolicVariable<F>> {
-    let mat_values = [0, 1]
-        .into_iter()
+    let mat_values = (0..=max_rotation)
         .flat_map(|offset| {
             (0..width)
                 .map(move |index| SymbolicVariable::new(Entry::Main { part_index, offset }, index))
@@ -21,12 +21,25 @@ pub enum Entry {
         part_index: usize,
         offset: usize,
     },
+    /// After challenge trace of challenge phase `phase`.
     Permutation {
+        phase: usize,
         offset: usize,
     },
     Public,
-    Challenge,
-    Exposed,
+    /// Challenge sampled in challenge phase `phase`.
+    Challenge {
+        phase: usize,
+    },
+    /// Value exposed to the verifier in challenge phase `phase`.
+    Exposed {
+        phase: usize,
+    },
+    /// Periodic column of the AIR, see [PartitionedBaseAir::periodic_columns]. Only the value at
+    /// the current row is available.
+    ///
+    /// [PartitionedBaseAir::periodic_columns]: crate::rap::PartitionedBaseAir::periodic_columns
+    Periodic,
 }
 
 impl Entry {
@@ -34,10 +47,11 @@ impl Entry {
         match self {
             Entry::Preprocessed { offset } => Some(*offset),
             Entry::Main { offset, .. } => Some(*offset),
-            Entry::Permutation { offset } => Some(*offset),
+            Entry::Permutation { offset, .. } => Some(*offset),
             Entry::Public => None,
-            Entry::Challenge => None,
-            Entry::Exposed => None,
+            Entry::Challenge { .. } => None,
+            Entry::Exposed { .. } => None,
+            Entry::Periodic => None,
         }
     }
 
@@ -54,10 +68,21 @@ impl Entry {
                 part_index,
                 offset: old_offset + offset,
             },
-            Entry::Permutation { offset: old_offset } => Entry::Permutation {
+            Entry::Permutation {
+                phase,
+                offset: old_offset,
+            } => Entry::Permutation {
+                phase,
                 offset: old_offset + offset,
             },
-            Entry::Public | Entry::Challenge | Entry::Exposed => self,
+            Entry::Public | Entry::Challenge { .. } | Entry::Exposed { .. } => self,
+            Entry::Periodic => {
+                assert_eq!(
+                    offset, 0,
+                    "periodic columns can only be referenced at the current row"
+                );
+                self
+            }
         }
     }
 
@@ -85,8 +110,11 @@ impl<F: Field> SymbolicVariable<F> {
 
     pub const fn degree_multiple(&self) -> usize {
         match self.entry {
-            Entry::Preprocessed { .. } | Entry::Main { .. } | Entry::Permutation { .. } => 1,
-            Entry::Public | Entry::Challenge | Entry::Exposed => 0,
+            Entry::Preprocessed { .. }
+            | Entry::Main { .. }
+            | Entry::Permutation { .. }
+            | Entry::Periodic => 1,
+            Entry::Public | Entry::Challenge { .. } | Entry::Exposed { .. } => 0,
         }
     }
 
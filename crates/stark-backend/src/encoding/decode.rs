@@ -0,0 +1,188 @@
+//! Decoding of the data types of digests, for the commitments of the
+//! [JSON encoding](crate::keygen::json) of verifying keys.
+
+use serde::{
+    de::{DeserializeOwned, DeserializeSeed, Deserializer, SeqAccess, Visitor},
+    forward_to_deserialize_any, Serialize,
+};
+
+use super::{to_bytes, unsupported, EncodingError};
+
+/// Decodes the whole of `bytes`. Also rejects bytes that are not the encoding of the decoded
+/// value, e.g. a word above the order of a field that its deserializer reduces.
+pub(crate) fn from_bytes<T: Serialize + DeserializeOwned>(
+    bytes: &[u8],
+) -> Result<T, EncodingError> {
+    let mut decoder = Decoder { bytes };
+    let value = T::deserialize(&mut decoder)?;
+    if !decoder.bytes.is_empty() {
+        return Err(EncodingError(format!(
+            "{} trailing bytes",
+            decoder.bytes.len()
+        )));
+    }
+    if to_bytes(&value)? != bytes {
+        return Err(EncodingError("non-canonical encoding".to_string()));
+    }
+    Ok(value)
+}
+
+struct Decoder<'de> {
+    bytes: &'de [u8],
+}
+
+impl<'de> Decoder<'de> {
+    fn take(&mut self, len: usize) -> Result<&'de [u8], EncodingError> {
+        if self.bytes.len() < len {
+            return Err(EncodingError("unexpected end of the input".to_string()));
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn take_array<const N: usize>(&mut self) -> Result<[u8; N], EncodingError> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn read_len(&mut self) -> Result<usize, EncodingError> {
+        let len = u64::from_le_bytes(self.take_array()?);
+        usize::try_from(len).map_err(|_| EncodingError(format!("length {len} does not fit")))
+    }
+}
+
+impl<'de> Deserializer<'de> for &mut Decoder<'de> {
+    type Error = EncodingError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, EncodingError> {
+        Err(unsupported("a self-describing type"))
+    }
+
+    fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, EncodingError> {
+        visitor.visit_i8(i8::from_le_bytes(self.take_array()?))
+    }
+
+    fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, EncodingError> {
+        visitor.visit_i16(i16::from_le_bytes(self.take_array()?))
+    }
+
+    fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, EncodingError> {
+        visitor.visit_i32(i32::from_le_bytes(self.take_array()?))
+    }
+
+    fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, EncodingError> {
+        visitor.visit_i64(i64::from_le_bytes(self.take_array()?))
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, EncodingError> {
+        visitor.visit_u8(u8::from_le_bytes(self.take_array()?))
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, EncodingError> {
+        visitor.visit_u16(u16::from_le_bytes(self.take_array()?))
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, EncodingError> {
+        visitor.visit_u32(u32::from_le_bytes(self.take_array()?))
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, EncodingError> {
+        visitor.visit_u64(u64::from_le_bytes(self.take_array()?))
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, EncodingError> {
+        let len = self.read_len()?;
+        visitor.visit_borrowed_bytes(self.take(len)?)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, EncodingError> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, EncodingError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, EncodingError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, EncodingError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, EncodingError> {
+        let remaining = self.read_len()?;
+        visitor.visit_seq(Elements {
+            de: self,
+            remaining,
+        })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, EncodingError> {
+        visitor.visit_seq(Elements {
+            de: self,
+            remaining: len,
+        })
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, EncodingError> {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, EncodingError> {
+        self.deserialize_tuple(fields.len(), visitor)
+    }
+
+    forward_to_deserialize_any! {
+        bool i128 u128 f32 f64 char str string option map enum identifier ignored_any
+    }
+}
+
+/// The elements of a sequence, tuple or struct.
+struct Elements<'a, 'de> {
+    de: &'a mut Decoder<'de>,
+    remaining: usize,
+}
+
+impl<'de> SeqAccess<'de> for Elements<'_, 'de> {
+    type Error = EncodingError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, EncodingError> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        // A sequence reads its length from the input, which may be larger than the input.
+        Some(self.remaining.min(self.de.bytes.len()))
+    }
+}
@@ -0,0 +1,319 @@
+//! The binary encoding of serde values used by the backend, in the layout of bincode with its
+//! default options: integers and floats in fixed-width little-endian, sequences, maps, strings
+//! and byte strings preceded by their length as a little-endian `u64`, a byte for booleans and
+//! options, a little-endian `u32` for the index of an enum variant, and the fields of structs and
+//! tuples in order. A digest is thus the words of the digest in order, each in little-endian.
+//!
+//! It encodes the commitments of the JSON encoding of verifying keys, and measures the size of
+//! proofs, see [Proof::size_breakdown](crate::proof::Proof::size_breakdown). The decoder, only
+//! built with the `vk-json` feature, only decodes the data types of digests: integers, byte
+//! strings, sequences, tuples and structs.
+
+use std::fmt;
+
+use serde::{
+    de,
+    ser::{self, Serialize, Serializer},
+};
+
+#[cfg(feature = "vk-json")]
+mod decode;
+#[cfg(feature = "vk-json")]
+pub(crate) use decode::from_bytes;
+
+/// The value has a serde data type without an encoding, or its encoding is malformed.
+#[derive(Debug)]
+pub(crate) struct EncodingError(String);
+
+impl fmt::Display for EncodingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl ser::StdError for EncodingError {}
+
+impl ser::Error for EncodingError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+impl de::Error for EncodingError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+fn unsupported(data_type: &str) -> EncodingError {
+    EncodingError(format!("{data_type} is not supported"))
+}
+
+pub(crate) fn to_bytes<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, EncodingError> {
+    let mut encoder = Encoder { out: Vec::new() };
+    value.serialize(&mut encoder)?;
+    Ok(encoder.out)
+}
+
+/// The length of the encoding of `value`, without encoding it.
+pub(crate) fn serialized_size<T: Serialize + ?Sized>(value: &T) -> Result<usize, EncodingError> {
+    let mut encoder = Encoder { out: 0 };
+    value.serialize(&mut encoder)?;
+    Ok(encoder.out)
+}
+
+/// Where an [Encoder] writes: a buffer, or a count of the bytes written.
+trait Output {
+    fn write(&mut self, bytes: &[u8]);
+}
+
+impl Output for Vec<u8> {
+    fn write(&mut self, bytes: &[u8]) {
+        self.extend_from_slice(bytes);
+    }
+}
+
+impl Output for usize {
+    fn write(&mut self, bytes: &[u8]) {
+        *self += bytes.len();
+    }
+}
+
+struct Encoder<O> {
+    out: O,
+}
+
+impl<O: Output> Encoder<O> {
+    fn write_len(&mut self, len: Option<usize>) -> Result<(), EncodingError> {
+        let len = len.ok_or_else(|| unsupported("a sequence of unknown length"))?;
+        self.out.write(&(len as u64).to_le_bytes());
+        Ok(())
+    }
+
+    fn write_variant(&mut self, index: u32) {
+        self.out.write(&index.to_le_bytes());
+    }
+}
+
+macro_rules! serialize_le {
+    ($($method:ident: $ty:ty),*) => {
+        $(
+            fn $method(self, v: $ty) -> Result<(), EncodingError> {
+                self.out.write(&v.to_le_bytes());
+                Ok(())
+            }
+        )*
+    };
+}
+
+impl<O: Output> Serializer for &mut Encoder<O> {
+    type Ok = ();
+    type Error = EncodingError;
+
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    serialize_le!(
+        serialize_i8: i8, serialize_i16: i16, serialize_i32: i32, serialize_i64: i64,
+        serialize_i128: i128, serialize_u8: u8, serialize_u16: u16, serialize_u32: u32,
+        serialize_u64: u64, serialize_u128: u128, serialize_f32: f32, serialize_f64: f64
+    );
+
+    fn serialize_bool(self, v: bool) -> Result<(), EncodingError> {
+        self.out.write(&[v as u8]);
+        Ok(())
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), EncodingError> {
+        self.out.write(v.encode_utf8(&mut [0; 4]).as_bytes());
+        Ok(())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), EncodingError> {
+        self.serialize_bytes(v.as_bytes())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), EncodingError> {
+        self.write_len(Some(v.len()))?;
+        self.out.write(v);
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<(), EncodingError> {
+        self.out.write(&[0]);
+        Ok(())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), EncodingError> {
+        self.out.write(&[1]);
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), EncodingError> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<(), EncodingError> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        index: u32,
+        _: &'static str,
+    ) -> Result<(), EncodingError> {
+        self.write_variant(index);
+        Ok(())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<(), EncodingError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        index: u32,
+        _: &'static str,
+        value: &T,
+    ) -> Result<(), EncodingError> {
+        self.write_variant(index);
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self, EncodingError> {
+        self.write_len(len)?;
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _: usize) -> Result<Self, EncodingError> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(self, _: &'static str, _: usize) -> Result<Self, EncodingError> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        index: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self, EncodingError> {
+        self.write_variant(index);
+        Ok(self)
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self, EncodingError> {
+        self.write_len(len)?;
+        Ok(self)
+    }
+
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<Self, EncodingError> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        index: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self, EncodingError> {
+        self.write_variant(index);
+        Ok(self)
+    }
+}
+
+/// Implements the traits of the serializers of compound values, which encode their elements in
+/// order.
+macro_rules! impl_serialize_compound {
+    ($($trait:ident::$method:ident),*) => {
+        $(
+            impl<O: Output> ser::$trait for &mut Encoder<O> {
+                type Ok = ();
+                type Error = EncodingError;
+
+                fn $method<T: Serialize + ?Sized>(
+                    &mut self,
+                    value: &T,
+                ) -> Result<(), EncodingError> {
+                    value.serialize(&mut **self)
+                }
+
+                fn end(self) -> Result<(), EncodingError> {
+                    Ok(())
+                }
+            }
+        )*
+    };
+}
+
+impl_serialize_compound!(
+    SerializeSeq::serialize_element,
+    SerializeTuple::serialize_element,
+    SerializeTupleStruct::serialize_field,
+    SerializeTupleVariant::serialize_field
+);
+
+impl<O: Output> ser::SerializeMap for &mut Encoder<O> {
+    type Ok = ();
+    type Error = EncodingError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), EncodingError> {
+        key.serialize(&mut **self)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), EncodingError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), EncodingError> {
+        Ok(())
+    }
+}
+
+impl<O: Output> ser::SerializeStruct for &mut Encoder<O> {
+    type Ok = ();
+    type Error = EncodingError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<(), EncodingError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), EncodingError> {
+        Ok(())
+    }
+}
+
+impl<O: Output> ser::SerializeStructVariant for &mut Encoder<O> {
+    type Ok = ();
+    type Error = EncodingError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<(), EncodingError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), EncodingError> {
+        Ok(())
+    }
+}
@@ -1,26 +1,44 @@
-use std::{iter::zip, sync::Arc};
+use std::{
+    iter::zip,
+    sync::{Arc, Mutex},
+};
 
 use itertools::{izip, Itertools};
-use p3_matrix::Matrix;
+use p3_field::PrimeField64;
+use p3_matrix::{dense::RowMajorMatrix, Matrix};
 use p3_util::log2_strict_usize;
 
 use crate::{
     air_builders::debug::debug_constraints_and_interactions,
-    config::StarkGenericConfig,
+    config::{Com, PcsProof, RapPhaseSeqPartialProof, StarkGenericConfig, Val},
+    encoding,
+    interaction::{bus::BusRegistry, public_lookup::PublicLookupTable},
     keygen::{
-        types::{MultiStarkProvingKey, MultiStarkVerifyingKey, StarkProvingKey},
-        MultiStarkKeygenBuilder,
+        types::{
+            MultiStarkProvingKey, MultiStarkVerifyingKey, PrunedVerifyingKey, StarkProvingKey,
+            VkFeature,
+        },
+        MultiStarkKeygenBuilder, StaleProvingKeyError, TraceHeightTooLargeError,
     },
-    proof::Proof,
+    proof::{OpeningProof, Proof},
     prover::{
-        cpu::{CpuBackend, CpuDevice, PcsData},
-        hal::{DeviceDataTransporter, TraceCommitter},
+        coordinator::Coordinator,
+        cpu::{
+            opener::OpeningWitness, CpuBackend, CpuDevice, OpeningWitnessDevice, PcsData,
+            ZeroKnowledge, ZkRandomness,
+        },
+        hal::{DeviceDataTransporter, ProverBackend, ProverDevice, TraceCommitter},
+        metrics::ProofMetrics,
         types::{
-            AirProofInput, AirProvingContext, ProofInput, ProvingContext, SingleCommitPreimage,
+            AirProofInput, AirProvingContext, CommittedTraceData, ProofInput, ProvingContext,
+            SingleCommitPreimage,
         },
-        MultiTraceStarkProver, Prover,
+        MultiTraceStarkProver, Prover, ProvingError, ProvingOptions,
+    },
+    verifier::{
+        observe_transcript_prefix, MultiTraceStarkVerifier, PcsShape, PublicValuesView,
+        VerificationError,
     },
-    verifier::{MultiTraceStarkVerifier, VerificationError},
     AirRef,
 };
 
@@ -37,8 +55,21 @@ pub trait StarkEngine<SC: StarkGenericConfig> {
     fn config(&self) -> &SC;
 
     /// During keygen, the circuit may be optimized but it will **try** to keep the
-    /// constraint degree at most this value.
+    /// constraint degree at most this value. Keygen fails if an AIR cannot be kept below this
+    /// value. Defaults to the maximum constraint degree of the [PCS shape](Self::pcs_shape), if
+    /// any.
     fn max_constraint_degree(&self) -> Option<usize> {
+        self.pcs_shape()
+            .map(|pcs_shape| pcs_shape.max_constraint_degree())
+    }
+
+    /// The source of randomness and the number of FRI queries of the proofs of zero-knowledge
+    /// keys, see
+    /// [set_zero_knowledge](crate::keygen::MultiStarkKeygenBuilder::set_zero_knowledge). An
+    /// engine without it cannot prove for a zero-knowledge key: its config should also salt the
+    /// leaves of its Merkle trees, so that the commitments reveal nothing about the rows that are
+    /// not opened.
+    fn zero_knowledge(&self) -> Option<ZeroKnowledge<'_>> {
         None
     }
 
@@ -51,6 +82,7 @@ pub trait StarkEngine<SC: StarkGenericConfig> {
         let mut builder = MultiStarkKeygenBuilder::new(self.config());
         if let Some(max_constraint_degree) = self.max_constraint_degree() {
             builder.set_max_constraint_degree(max_constraint_degree);
+            builder.set_max_supported_constraint_degree(max_constraint_degree);
         }
         builder
     }
@@ -66,8 +98,21 @@ pub trait StarkEngine<SC: StarkGenericConfig> {
         )
     }
 
+    /// The shape of the opening proofs of the PCS of the config, checked by the
+    /// [verifier](Self::verifier) with the shape of each proof. The PCS is opaque to the backend,
+    /// so only the engine knows it.
+    fn pcs_shape(&self) -> Option<PcsShape<SC>> {
+        None
+    }
+
+    /// The verifier of the config, which also checks each proof against the limits implied by
+    /// its verifying key, see [with_key_limits](MultiTraceStarkVerifier::with_key_limits).
     fn verifier(&self) -> MultiTraceStarkVerifier<SC> {
-        MultiTraceStarkVerifier::new(self.config())
+        let verifier = MultiTraceStarkVerifier::new(self.config()).with_key_limits();
+        match self.pcs_shape() {
+            Some(pcs_shape) => verifier.with_pcs_shape(pcs_shape),
+            None => verifier,
+        }
     }
 
     /// Add AIRs and get AIR IDs
@@ -85,28 +130,239 @@ pub trait StarkEngine<SC: StarkGenericConfig> {
         &self,
         mpk: &MultiStarkProvingKey<SC>,
         proof_input: ProofInput<SC>,
-    ) -> Result<(), VerificationError> {
+    ) -> Result<(), VerificationError>
+    where
+        Val<SC>: PrimeField64,
+    {
         let proof = self.prove(mpk, proof_input);
         self.verify(&mpk.get_vk(), &proof)
     }
 
-    fn prove(&self, mpk: &MultiStarkProvingKey<SC>, proof_input: ProofInput<SC>) -> Proof<SC> {
-        let mut prover = self.prover();
-        let backend = prover.backend;
+    /// Proves `proof_input` with `mpk`.
+    ///
+    /// # Panics
+    /// If an AIR with a preprocessed trace committed per height does not have the preprocessed
+    /// trace of its height committed in `mpk`, see
+    /// [prove_with_preprocessed_heights](Self::prove_with_preprocessed_heights).
+    ///
+    /// If `proof_input` has an AIR id not in `mpk` or two inputs for the same AIR, or the traces
+    /// or public values of an AIR do not fit the AIR, e.g. a trace is taller than the maximum
+    /// trace height of its AIR, see [check_proof_input](MultiStarkProvingKey::check_proof_input).
+    /// The error is returned by [prove_with_options](Self::prove_with_options) instead.
+    ///
+    /// If an AIR marked absent in `proof_input` cannot be absent, or `mpk` has an AIR that may be
+    /// absent and an AIR has no input without being marked absent, see
+    /// [check_absent_airs](MultiStarkProvingKey::check_absent_airs).
+    ///
+    /// Unless `mpk` has an AIR that may be absent, the inputs may be for any subset of the AIRs of
+    /// `mpk`, in any order.
+    fn prove(&self, mpk: &MultiStarkProvingKey<SC>, proof_input: ProofInput<SC>) -> Proof<SC>
+    where
+        Val<SC>: PrimeField64,
+    {
+        self.prove_with_options(mpk, proof_input, ProvingOptions::default())
+            .unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Same as [prove](Self::prove), but the transcript starts by observing `prefix`, e.g. the
+    /// commitments of the previous proof of a chain of proofs, so that the proof is bound to it.
+    /// See [ProofInput::transcript_prefix].
+    ///
+    /// The prefix is not recorded in the proof: it is supplied out-of-band to
+    /// [verify_with_transcript_prefix](Self::verify_with_transcript_prefix). An empty prefix gives
+    /// the same proof as [prove](Self::prove).
+    fn prove_with_transcript_prefix(
+        &self,
+        mpk: &MultiStarkProvingKey<SC>,
+        proof_input: ProofInput<SC>,
+        prefix: &[Com<SC>],
+    ) -> Proof<SC>
+    where
+        Val<SC>: PrimeField64,
+    {
+        self.prove(
+            mpk,
+            proof_input.with_transcript_prefix(prefix.iter().cloned()),
+        )
+    }
+
+    /// Same as [prove](Self::prove), but also returns the time spent in each part of the proof.
+    /// The proof is the same.
+    fn prove_with_metrics(
+        &self,
+        mpk: &MultiStarkProvingKey<SC>,
+        proof_input: ProofInput<SC>,
+    ) -> (Proof<SC>, ProofMetrics)
+    where
+        Val<SC>: PrimeField64,
+    {
+        let metrics = Arc::new(Mutex::new(ProofMetrics::default()));
+        let options = ProvingOptions {
+            metrics: Some(metrics.clone()),
+            ..Default::default()
+        };
+        let proof = self
+            .prove_with_options(mpk, proof_input, options)
+            .unwrap_or_else(|err| panic!("{err}"));
+        let metrics = metrics.lock().unwrap().clone();
+        (proof, metrics)
+    }
+
+    /// Same as [prove](Self::prove), but also returns the points and values of the PCS opening of
+    /// the proof, in the order the verifier checks them, e.g. to build the witness of a recursive
+    /// verifier. The proof is the same.
+    fn prove_with_opening_witness(
+        &self,
+        mpk: &MultiStarkProvingKey<SC>,
+        proof_input: ProofInput<SC>,
+    ) -> (Proof<SC>, OpeningWitness<SC::Challenge>)
+    where
+        Val<SC>: PrimeField64,
+    {
+        let witness = Mutex::new(None);
+        let device = OpeningWitnessDevice {
+            device: CpuDevice::new(self.config()).with_zero_knowledge(zk_randomness(self, mpk)),
+            witness: &witness,
+        };
+        let proof = self
+            .prove_with_device(
+                CpuBackend::default(),
+                device,
+                mpk,
+                proof_input,
+                ProvingOptions::default(),
+            )
+            .unwrap_or_else(|err| panic!("{err}"));
+        let witness = witness
+            .into_inner()
+            .unwrap()
+            .expect("the opening witness is recorded by the device");
+        (proof, witness)
+    }
+
+    /// Same as [prove](Self::prove), but reports the progress of the proof and can be cancelled
+    /// with `options`. Returns [ProvingError::Cancelled] once the cancellation flag is noticed,
+    /// otherwise the proof is the same.
+    ///
+    /// Returns [ProvingError::InvalidInput] if `proof_input` does not fit `mpk`, before any
+    /// commitment.
+    fn prove_with_options(
+        &self,
+        mpk: &MultiStarkProvingKey<SC>,
+        proof_input: ProofInput<SC>,
+        options: ProvingOptions,
+    ) -> Result<Proof<SC>, ProvingError>
+    where
+        Val<SC>: PrimeField64,
+    {
+        let device = CpuDevice::new(self.config()).with_zero_knowledge(zk_randomness(self, mpk));
+        self.prove_with_device(CpuBackend::default(), device, mpk, proof_input, options)
+    }
+
+    /// Same as [prove_with_options](Self::prove_with_options), but the proof runs on `pool`,
+    /// e.g. built by [ProverConfig::build_pool](crate::prover::pool::ProverConfig::build_pool),
+    /// instead of the global rayon pool: the trace commitments, the quotient and the opening
+    /// proof only use the threads of `pool`. The proof is the same.
+    #[cfg(feature = "parallel")]
+    fn prove_on_pool(
+        &self,
+        pool: &rayon::ThreadPool,
+        mpk: &MultiStarkProvingKey<SC>,
+        proof_input: ProofInput<SC>,
+        options: ProvingOptions,
+    ) -> Result<Proof<SC>, ProvingError>
+    where
+        Self: Sync,
+        Val<SC>: PrimeField64,
+        MultiStarkProvingKey<SC>: Sync,
+        ProofInput<SC>: Send,
+        Proof<SC>: Send,
+    {
+        pool.install(|| self.prove_with_options(mpk, proof_input, options))
+    }
+
+    /// Same as [prove_with_options](Self::prove_with_options), but the trace commitments, the
+    /// after challenge traces, the quotient polynomials and the opening proofs are computed by
+    /// `device` instead of a [CpuDevice]. The proof is the same as long as `device` computes the
+    /// same values.
+    ///
+    /// For a zero-knowledge key, `device` must randomize the traces and the quotients as
+    /// [CpuDevice::with_zero_knowledge] does. Returns [ProvingError::ZeroKnowledgeUnsupported] if
+    /// the engine has no [zero_knowledge](Self::zero_knowledge) parameters, and
+    /// [ProvingError::InvalidInput] if a trace is too low to be hidden, see
+    /// [check_zero_knowledge_heights](MultiStarkProvingKey::check_zero_knowledge_heights).
+    ///
+    /// The device works on the buffers of `backend`, e.g. the host buffers of the [CpuBackend]
+    /// or buffers on a GPU: `backend` transports the traces, the pre-committed data and the
+    /// proving key to them.
+    fn prove_with_device<PB, PD>(
+        &self,
+        backend: PB,
+        device: PD,
+        mpk: &MultiStarkProvingKey<SC>,
+        proof_input: ProofInput<SC>,
+        options: ProvingOptions,
+    ) -> Result<Proof<SC>, ProvingError>
+    where
+        Val<SC>: PrimeField64,
+        PB: ProverBackend<
+                Val = Val<SC>,
+                Challenge = SC::Challenge,
+                Commitment = Com<SC>,
+                Challenger = SC::Challenger,
+            > + DeviceDataTransporter<SC, PB>,
+        PB::OpeningProof: Into<OpeningProof<PcsProof<SC>, SC::Challenge>>,
+        PB::RapPartialProof: Into<Option<RapPhaseSeqPartialProof<SC>>>,
+        PD: ProverDevice<PB>,
+    {
+        // A proof may be for any subset of the AIRs of the key, listed in increasing order.
+        let mut proof_input = proof_input;
+        proof_input.per_air.sort_by_key(|(air_id, _)| *air_id);
+        let log_blowup = self.pcs_shape().map_or(0, |pcs_shape| pcs_shape.log_blowup);
+        mpk.check_proof_input(&proof_input, log_blowup)?;
+        if mpk.features.contains(VkFeature::ZeroKnowledge) {
+            let zero_knowledge = self
+                .zero_knowledge()
+                .ok_or(ProvingError::ZeroKnowledgeUnsupported)?;
+            mpk.check_zero_knowledge_heights(&proof_input, zero_knowledge.num_queries)?;
+        }
+        proof_input.absent_air_ids.sort_unstable();
+        proof_input.absent_air_ids.dedup();
+        if let Err(err) = mpk.check_absent_airs(&proof_input) {
+            panic!("{err}");
+        }
+        let mpk = mpk
+            .at_heights(&mpk.preprocessed_air_heights(&proof_input))
+            .unwrap_or_else(|err| panic!("{err}"));
+        let mpk = &*mpk;
+        let mut challenger = self.new_challenger();
+        observe_transcript_prefix::<SC>(&mut challenger, &proof_input.transcript_prefix);
+        let mut prover = Coordinator::<SC, PB, PD>::new(backend, device, challenger);
+        prover.set_options(options);
+        #[cfg(feature = "mem-metrics")]
+        prover.begin_memory_phase(crate::prover::ProvingPhase::MainTraceCommit);
+        let backend = &prover.backend;
         let air_ids = proof_input.per_air.iter().map(|(id, _)| *id).collect();
         // Commit cached traces if they are not provided
         let cached_mains_per_air = proof_input
             .per_air
             .iter()
-            .map(|(_, input)| {
+            .map(|(air_id, input)| {
+                let air_name = mpk.per_air[*air_id].air_name.as_str();
                 if input.cached_mains_pdata.len() != input.raw.cached_mains.len() {
+                    #[cfg(feature = "bench-metrics")]
+                    metrics::counter!("cached_main_commits", "air_name" => air_name.to_string())
+                        .increment(input.raw.cached_mains.len() as u64);
                     input
                         .raw
                         .cached_mains
                         .iter()
                         .map(|trace| {
-                            let trace = backend.transport_matrix_to_device(trace);
-                            let (com, data) = prover.device.commit(&[trace.clone()]);
+                            let traces = [backend.transport_matrix_to_device(trace)];
+                            let (com, data) =
+                                tracing::info_span!("commit cached main trace", air_name)
+                                    .in_scope(|| prover.device.commit(&traces));
+                            let [trace] = traces;
                             (
                                 com,
                                 SingleCommitPreimage {
@@ -118,6 +374,10 @@ pub trait StarkEngine<SC: StarkGenericConfig> {
                         })
                         .collect_vec()
                 } else {
+                    // Pre-committed: only the commitment is observed by the prover.
+                    #[cfg(feature = "bench-metrics")]
+                    metrics::counter!("cached_main_reuses", "air_name" => air_name.to_string())
+                        .increment(input.raw.cached_mains.len() as u64);
                     zip(&input.cached_mains_pdata, &input.raw.cached_mains)
                         .map(|((com, data), trace)| {
                             let data_view = PcsData {
@@ -125,8 +385,8 @@ pub trait StarkEngine<SC: StarkGenericConfig> {
                                 log_trace_heights: vec![log2_strict_usize(trace.height()) as u8],
                             };
                             let preimage = SingleCommitPreimage {
-                                trace: trace.clone(),
-                                data: data_view,
+                                trace: backend.transport_matrix_to_device(trace),
+                                data: backend.transport_pcs_data_to_device(&data_view),
                                 matrix_idx: 0,
                             };
                             (com.clone(), preimage)
@@ -150,9 +410,19 @@ pub trait StarkEngine<SC: StarkGenericConfig> {
                         )
                     })
                     .collect_vec();
+                let custom_mains = input
+                    .raw
+                    .custom_mains
+                    .iter()
+                    .map(|trace| backend.transport_matrix_to_device(trace))
+                    .collect();
                 let air_ctx = AirProvingContext {
                     cached_mains,
-                    common_main: input.raw.common_main.map(Arc::new),
+                    common_main: input
+                        .raw
+                        .common_main
+                        .map(|trace| backend.transport_matrix_to_device(&Arc::new(trace))),
+                    custom_mains,
                     public_values: input.raw.public_values,
                 };
                 (air_id, air_ctx)
@@ -160,27 +430,246 @@ pub trait StarkEngine<SC: StarkGenericConfig> {
             .collect();
         let ctx = ProvingContext {
             per_air: ctx_per_air,
+            absent_air_ids: proof_input.absent_air_ids,
         };
         let mpk_view = backend.transport_pk_to_device(mpk, air_ids);
-        let proof = Prover::prove(&mut prover, &mpk_view, ctx);
-        proof.into()
+        let proof = Prover::prove(&mut prover, &mpk_view, ctx)?;
+        Ok(proof.into())
+    }
+
+    /// Same as [prove](Self::prove), for golden files and comparisons of proofs across runs or
+    /// machines. The proof only depends on the config, `mpk` and `proof_input`: the challenges
+    /// are sampled from the transcript, and the prover does not depend on the number of threads,
+    /// on hash seeds or on the iteration order of hash maps.
+    ///
+    /// The config must not randomize its commitments either, e.g. with the salted Merkle trees of
+    /// the zero-knowledge configs of the sdk. See `docs/deterministic-proving.md` for the sources
+    /// of nondeterminism that were audited.
+    ///
+    /// With the `determinism-check` feature, debug builds prove twice and panic if the proofs
+    /// differ, i.e. if the transcript, and so the challenges, changed between the runs.
+    ///
+    /// # Panics
+    /// If `mpk` is zero-knowledge, since its proofs are randomized.
+    fn prove_deterministic(
+        &self,
+        mpk: &MultiStarkProvingKey<SC>,
+        proof_input: ProofInput<SC>,
+    ) -> Proof<SC>
+    where
+        Val<SC>: PrimeField64,
+    {
+        assert!(
+            !mpk.features.contains(VkFeature::ZeroKnowledge),
+            "the proofs of a zero-knowledge key are not deterministic"
+        );
+        if !cfg!(all(debug_assertions, feature = "determinism-check")) {
+            return self.prove(mpk, proof_input);
+        }
+        let proof = self.prove(mpk, proof_input.clone());
+        let rerun = self.prove(mpk, proof_input);
+        assert!(
+            encoding::to_bytes(&proof).unwrap() == encoding::to_bytes(&rerun).unwrap(),
+            "proving the same input twice gave different proofs"
+        );
+        proof
+    }
+
+    /// Commits to `trace` once, to be used as a cached main trace by any number of proofs, see
+    /// [AirProofInput::with_cached_mains]. The prover then reuses the committed data instead of
+    /// committing the trace again.
+    ///
+    /// The trace is not randomized, so it cannot be used with a zero-knowledge key.
+    fn commit_trace(&self, trace: RowMajorMatrix<Val<SC>>) -> CommittedTraceData<SC> {
+        let prover = self.prover();
+        let trace = Arc::new(trace);
+        let (commitment, data) = prover.device.commit(&[trace.clone()]);
+        CommittedTraceData {
+            trace,
+            commitment,
+            pcs_data: data.data,
+        }
+    }
+
+    /// Commits to `matrices`, of any heights, under a single commitment. The domain of each
+    /// matrix is the natural domain of its height, as for the traces committed by the prover.
+    ///
+    /// Returns the commitment and the preimage of each matrix, in the order of `matrices`: the
+    /// `i`-th preimage shares the [PcsData] of the commitment and has `matrix_idx` `i`, the
+    /// index of its matrix among the committed ones.
+    ///
+    /// # Panics
+    /// If the height of a matrix is not a power of two.
+    #[allow(clippy::type_complexity)]
+    fn commit_matrices(
+        &self,
+        matrices: Vec<RowMajorMatrix<Val<SC>>>,
+    ) -> (
+        Com<SC>,
+        Vec<SingleCommitPreimage<Arc<RowMajorMatrix<Val<SC>>>, PcsData<SC>>>,
+    ) {
+        for (i, matrix) in matrices.iter().enumerate() {
+            assert!(
+                matrix.height().is_power_of_two(),
+                "matrix {i} has height {}, which is not a power of two",
+                matrix.height()
+            );
+        }
+        let matrices = matrices.into_iter().map(Arc::new).collect_vec();
+        let (commitment, data) = self.prover().device.commit(&matrices);
+        let preimages = matrices
+            .into_iter()
+            .enumerate()
+            .map(|(matrix_idx, trace)| SingleCommitPreimage {
+                trace,
+                data: data.clone(),
+                matrix_idx: matrix_idx as u32,
+            })
+            .collect();
+        (commitment, preimages)
+    }
+
+    /// Same as [prove](Self::prove), but returns an error if a trace is taller than the maximum
+    /// trace height of its AIR.
+    fn try_prove(
+        &self,
+        mpk: &MultiStarkProvingKey<SC>,
+        proof_input: ProofInput<SC>,
+    ) -> Result<Proof<SC>, TraceHeightTooLargeError>
+    where
+        Val<SC>: PrimeField64,
+    {
+        mpk.check_trace_heights(&proof_input)?;
+        Ok(self.prove(mpk, proof_input))
+    }
+
+    /// Same as [prove](Self::prove), but first commits the preprocessed traces of the heights
+    /// of `proof_input` that are not committed in `mpk` yet, for the AIRs with a preprocessed
+    /// trace committed per height. `airs[air_id]` is the AIR `mpk` was generated from.
+    ///
+    /// The verifying key of the proof must be taken from `mpk` afterwards.
+    fn prove_with_preprocessed_heights(
+        &self,
+        airs: &[AirRef<SC>],
+        mpk: &mut MultiStarkProvingKey<SC>,
+        proof_input: ProofInput<SC>,
+    ) -> Proof<SC>
+    where
+        Val<SC>: PrimeField64,
+    {
+        let air_heights = mpk.preprocessed_air_heights(&proof_input);
+        mpk.prepare_preprocessed_heights(self.config().pcs(), airs, &air_heights);
+        self.prove(mpk, proof_input)
+    }
+
+    /// Same as [prove](Self::prove), but first checks that the preprocessed data in `mpk` is
+    /// consistent with `airs`, where `airs[air_id]` is the AIR `mpk` was generated from.
+    ///
+    /// Setting `force` skips the consistency check and trusts the proving key.
+    fn prove_checked(
+        &self,
+        airs: &[AirRef<SC>],
+        mpk: &MultiStarkProvingKey<SC>,
+        proof_input: ProofInput<SC>,
+        force: bool,
+    ) -> Result<Proof<SC>, StaleProvingKeyError>
+    where
+        Val<SC>: PrimeField64,
+    {
+        if !force {
+            mpk.check_preprocessed_consistency(airs)?;
+        }
+        Ok(self.prove(mpk, proof_input))
     }
 
     fn verify(
         &self,
         vk: &MultiStarkVerifyingKey<SC>,
         proof: &Proof<SC>,
-    ) -> Result<(), VerificationError> {
+    ) -> Result<(), VerificationError>
+    where
+        Val<SC>: PrimeField64,
+    {
         let mut challenger = self.new_challenger();
         let verifier = self.verifier();
         verifier.verify(&mut challenger, vk, proof)
     }
 
+    /// Verifies each of `proofs` with `vk`, in parallel with the `parallel` feature, and returns
+    /// the result of each proof in the order of `proofs`, see
+    /// [MultiTraceStarkVerifier::verify_batch]. A rejected proof does not stop the verification of
+    /// the others.
+    fn verify_batch(
+        &self,
+        vk: &MultiStarkVerifyingKey<SC>,
+        proofs: &[Proof<SC>],
+    ) -> Vec<Result<(), VerificationError>>
+    where
+        Val<SC>: PrimeField64,
+        Self: Sync,
+        SC: Sync,
+        MultiStarkVerifyingKey<SC>: Sync,
+        Proof<SC>: Sync,
+    {
+        self.verifier()
+            .verify_batch(|| self.new_challenger(), vk, proofs)
+    }
+
+    /// Same as [verify](Self::verify), then runs the application `check` on the public values
+    /// of the proof, whose error is returned as [VerificationError::PublicValuesRejected]. The
+    /// check only runs once the proof verifies, so that its error never hides an invalid proof.
+    fn verify_with_public_values_check(
+        &self,
+        vk: &MultiStarkVerifyingKey<SC>,
+        proof: &Proof<SC>,
+        check: impl Fn(&PublicValuesView<Val<SC>>) -> Result<(), String>,
+    ) -> Result<(), VerificationError>
+    where
+        Val<SC>: PrimeField64,
+    {
+        self.verify(vk, proof)?;
+        check(&proof.public_values_view(vk)).map_err(VerificationError::PublicValuesRejected)
+    }
+
+    /// Same as [verify](Self::verify), for a proof whose transcript starts with `prefix`, see
+    /// [prove_with_transcript_prefix](Self::prove_with_transcript_prefix).
+    fn verify_with_transcript_prefix(
+        &self,
+        vk: &MultiStarkVerifyingKey<SC>,
+        proof: &Proof<SC>,
+        prefix: &[Com<SC>],
+    ) -> Result<(), VerificationError>
+    where
+        Val<SC>: PrimeField64,
+    {
+        let mut challenger = self.new_challenger();
+        observe_transcript_prefix::<SC>(&mut challenger, prefix);
+        let verifier = self.verifier();
+        verifier.verify(&mut challenger, vk, proof)
+    }
+
+    /// Same as [verify](Self::verify) with a key pruned to a subset of AIRs, see
+    /// [MultiStarkVerifyingKey::prune].
+    fn verify_pruned(
+        &self,
+        pvk: &PrunedVerifyingKey<SC>,
+        proof: &Proof<SC>,
+    ) -> Result<(), VerificationError>
+    where
+        Val<SC>: PrimeField64,
+    {
+        let mut challenger = self.new_challenger();
+        let verifier = self.verifier();
+        verifier.verify_pruned(&mut challenger, pvk, proof)
+    }
+
     // mpk can be removed if we use BaseAir trait to regenerate preprocessed traces
     fn debug(
         &self,
         airs: &[AirRef<SC>],
         pk: &[StarkProvingKey<SC>],
+        bus_registry: &BusRegistry,
+        public_lookup_tables: &[PublicLookupTable<Val<SC>>],
         proof_inputs: &[AirProofInput<SC>],
     ) {
         let (trace_views, pvs): (Vec<_>, Vec<_>) = proof_inputs
@@ -195,10 +684,18 @@ pub trait StarkEngine<SC: StarkGenericConfig> {
                 if let Some(trace) = input.raw.common_main.as_ref() {
                     views.push(trace.as_view());
                 }
+                views.extend(input.raw.custom_mains.iter().map(|trace| trace.as_view()));
                 (views, input.raw.public_values.clone())
             })
             .unzip();
-        debug_constraints_and_interactions(airs, pk, &trace_views, &pvs);
+        debug_constraints_and_interactions(
+            airs,
+            pk,
+            bus_registry,
+            public_lookup_tables,
+            &trace_views,
+            &pvs,
+        );
     }
 
     /// Runs a single end-to-end test for a given set of chips and traces partitions.
@@ -207,17 +704,43 @@ pub trait StarkEngine<SC: StarkGenericConfig> {
         &self,
         airs: Vec<AirRef<SC>>,
         air_proof_inputs: Vec<AirProofInput<SC>>,
-    ) -> Result<VerificationData<SC>, VerificationError> {
+    ) -> Result<VerificationData<SC>, VerificationError>
+    where
+        Val<SC>: PrimeField64,
+    {
         let mut keygen_builder = self.keygen_builder();
         let air_ids = self.set_up_keygen_builder(&mut keygen_builder, &airs);
-        let pk = keygen_builder.generate_pk();
-        self.debug(&airs, &pk.per_air, &air_proof_inputs);
+        let mut pk = keygen_builder.generate_pk();
+        let air_heights = izip!(&air_ids, &air_proof_inputs)
+            .filter(|(&air_id, _)| pk.per_air[air_id].vk.preprocessed_per_height.is_some())
+            .map(|(&air_id, input)| (air_id, input.raw.height()))
+            .collect_vec();
+        pk.prepare_preprocessed_heights(self.config().pcs(), &airs, &air_heights);
         let vk = pk.get_vk();
-        let proof_input = ProofInput {
-            per_air: izip!(air_ids, air_proof_inputs).collect(),
-        };
+        let pk = pk.at_heights(&air_heights).unwrap();
+        self.debug(
+            &airs,
+            &pk.per_air,
+            &pk.bus_registry,
+            &pk.public_lookup_tables,
+            &air_proof_inputs,
+        );
+        let proof_input = ProofInput::new(izip!(air_ids, air_proof_inputs).collect());
         let proof = self.prove(&pk, proof_input);
         self.verify(&vk, &proof)?;
         Ok(VerificationData { vk, proof })
     }
 }
+
+/// The source of the random values of a [CpuDevice] of `engine` proving for `mpk`, if `mpk` is
+/// zero-knowledge.
+fn zk_randomness<'a, SC: StarkGenericConfig, E: StarkEngine<SC> + ?Sized>(
+    engine: &'a E,
+    mpk: &MultiStarkProvingKey<SC>,
+) -> Option<&'a dyn ZkRandomness> {
+    let zero_knowledge = mpk.features.contains(VkFeature::ZeroKnowledge);
+    zero_knowledge
+        .then(|| engine.zero_knowledge())
+        .flatten()
+        .map(|zk| zk.randomness)
+}
@@ -13,7 +13,7 @@ use thiserror::Error;
 use crate::{
     gkr::types::{GkrArtifact, GkrBatchProof, GkrMask, Layer},
     poly::{
-        multi::{hypercube_eq, Mle, MultivariatePolyOracle},
+        multi::{hypercube_eq, hypercube_eq_evals, Mle, MultivariatePolyOracle},
         uni::{random_linear_combination, UnivariatePolynomial},
     },
     sumcheck,
@@ -48,20 +48,7 @@ impl<F: Field> HypercubeEqEvals<F> {
 
     /// Returns evaluations of the function `x -> eq(x, y) * v` for each `x` in `{0, 1}^n`.
     fn gen(y: &[F], v: F) -> Vec<F> {
-        let mut evals = Vec::with_capacity(1 << y.len());
-        evals.push(v);
-
-        for &y_i in y.iter().rev() {
-            for j in 0..evals.len() {
-                // `lhs[j] = eq(0, y_i) * c[i]`
-                // `rhs[j] = eq(1, y_i) * c[i]`
-                let tmp = evals[j] * y_i;
-                evals.push(tmp);
-                evals[j] -= tmp;
-            }
-        }
-
-        evals
+        hypercube_eq_evals(y, v)
     }
 }
 
@@ -0,0 +1,115 @@
+//! Named buses, registered at keygen.
+
+use serde::{Deserialize, Serialize};
+
+/// Index of a bus registered in a [BusRegistry]. AIRs push their interactions on
+/// [BusIndex::index].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct BusIndex(usize);
+
+impl BusIndex {
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+impl From<BusIndex> for usize {
+    fn from(bus: BusIndex) -> usize {
+        bus.0
+    }
+}
+
+/// A registered bus.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BusInfo {
+    pub name: String,
+    /// Number of fields of every message on the bus.
+    pub arity: usize,
+}
+
+/// The named buses of a system of AIRs. Named buses are indexed in registration order, after
+/// the buses registered by raw index.
+///
+/// AIRs that predate the registry interact on buses by raw index. Such a bus `i` may be registered
+/// with [register_raw](Self::register_raw), and is named `bus_{i}`. Registering a bus on an index
+/// that is already registered panics, so named and raw buses never share an index.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BusRegistry {
+    /// The bus of each index, if it is registered.
+    buses: Vec<Option<BusInfo>>,
+}
+
+impl BusRegistry {
+    pub(crate) fn from_buses(buses: Vec<Option<BusInfo>>) -> Self {
+        Self { buses }
+    }
+
+    /// Registers a bus whose messages have `arity` fields, and returns its index, the first index
+    /// after all registered buses.
+    ///
+    /// ## Panics
+    /// If a bus named `name` is already registered.
+    pub fn register(&mut self, name: impl Into<String>, arity: usize) -> BusIndex {
+        let name = name.into();
+        assert!(
+            self.buses().all(|(_, bus)| bus.name != name),
+            "bus {name} is already registered"
+        );
+        self.buses.push(Some(BusInfo { name, arity }));
+        BusIndex(self.buses.len() - 1)
+    }
+
+    /// Registers the bus with raw index `bus_index`, whose messages have `arity` fields, under the
+    /// name `bus_{bus_index}`.
+    ///
+    /// ## Panics
+    /// If a bus is already registered at `bus_index`.
+    pub fn register_raw(&mut self, bus_index: usize, arity: usize) -> BusIndex {
+        if let Some(bus) = self.get(bus_index) {
+            panic!(
+                "bus index {bus_index} is already registered as {}",
+                bus.name
+            );
+        }
+        if self.buses.len() <= bus_index {
+            self.buses.resize(bus_index + 1, None);
+        }
+        self.buses[bus_index] = Some(BusInfo {
+            name: format!("bus_{bus_index}"),
+            arity,
+        });
+        BusIndex(bus_index)
+    }
+
+    /// Returns the registered bus with index `bus_index`, if any.
+    pub fn get(&self, bus_index: usize) -> Option<&BusInfo> {
+        self.buses.get(bus_index).and_then(Option::as_ref)
+    }
+
+    /// Returns the name of the bus with index `bus_index`, which is `bus_{bus_index}` if it is
+    /// not registered.
+    pub fn name(&self, bus_index: usize) -> String {
+        match self.get(bus_index) {
+            Some(bus) => bus.name.clone(),
+            None => format!("bus_{bus_index}"),
+        }
+    }
+
+    /// The registered buses, with their indices, in increasing order of index.
+    pub fn buses(&self) -> impl Iterator<Item = (usize, &BusInfo)> {
+        self.buses
+            .iter()
+            .enumerate()
+            .filter_map(|(bus_index, bus)| Some((bus_index, bus.as_ref()?)))
+    }
+
+    /// Whether no bus is registered.
+    pub fn is_empty(&self) -> bool {
+        self.buses.iter().all(Option::is_none)
+    }
+
+    /// The bus of each index up to the last registered one, `None` where no bus is registered.
+    pub(crate) fn slots(&self) -> &[Option<BusInfo>] {
+        &self.buses
+    }
+}
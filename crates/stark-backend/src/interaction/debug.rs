@@ -1,19 +1,25 @@
-use std::collections::{BTreeMap, HashMap};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+};
 
-use itertools::Itertools;
+use itertools::{izip, Itertools};
 use p3_field::Field;
 use p3_matrix::{dense::RowMajorMatrixView, Matrix};
 
-use super::{trace::Evaluator, InteractionType, SymbolicInteraction};
+use super::{
+    bus::BusRegistry, public_lookup::PublicLookupTable, trace::Evaluator, InteractionType,
+    SymbolicInteraction,
+};
 use crate::air_builders::symbolic::symbolic_expression::SymbolicEvaluator;
 
 /// The actual interactions that are sent/received during a single run
 /// of trace generation. For debugging purposes only.
 #[derive(Default, Clone, Debug)]
 pub struct LogicalInteractions<F: Field> {
-    /// Bus index => (fields => (air_idx, interaction_type, count))
+    /// Bus index => (fields => (air_idx, row, interaction_type, count))
     #[allow(clippy::type_complexity)]
-    pub at_bus: BTreeMap<usize, HashMap<Vec<F>, Vec<(usize, InteractionType, F)>>>,
+    pub at_bus: BTreeMap<usize, HashMap<Vec<F>, Vec<(usize, usize, InteractionType, F)>>>,
 }
 
 pub fn generate_logical_interactions<F: Field>(
@@ -22,39 +28,256 @@ pub fn generate_logical_interactions<F: Field>(
     preprocessed: &Option<RowMajorMatrixView<F>>,
     partitioned_main: &[RowMajorMatrixView<F>],
     public_values: &[F],
+    periodic_columns: &[Vec<F>],
     logical_interactions: &mut LogicalInteractions<F>,
+) {
+    for_each_row_interaction(
+        all_interactions,
+        preprocessed,
+        partitioned_main,
+        public_values,
+        periodic_columns,
+        |row, interaction, fields, count| {
+            logical_interactions
+                .at_bus
+                .entry(interaction.bus_index)
+                .or_default()
+                .entry(fields)
+                .or_default()
+                .push((air_idx, row, interaction.interaction_type, count));
+        },
+    );
+}
+
+/// Calls `f(row, interaction, fields, count)` for each interaction of each row with a nonzero
+/// count, in the order of the rows and then of the interactions.
+pub(crate) fn for_each_row_interaction<F: Field>(
+    all_interactions: &[SymbolicInteraction<F>],
+    preprocessed: &Option<RowMajorMatrixView<F>>,
+    partitioned_main: &[RowMajorMatrixView<F>],
+    public_values: &[F],
+    periodic_columns: &[Vec<F>],
+    mut f: impl FnMut(usize, &SymbolicInteraction<F>, Vec<F>, F),
 ) {
     if all_interactions.is_empty() {
         return;
     }
 
-    let height = partitioned_main[0].height();
+    // The main trace may be a single multiplicity column, or absent if the AIR is preprocessed.
+    let height = partitioned_main
+        .first()
+        .or(preprocessed.as_ref())
+        .expect("AIR has no trace")
+        .height();
 
     for n in 0..height {
         let evaluator = Evaluator {
             preprocessed,
             partitioned_main,
             public_values,
+            periodic_columns,
             height,
             local_index: n,
         };
         for interaction in all_interactions {
+            let count = evaluator.eval_expr(&interaction.count);
+            if count.is_zero() {
+                continue;
+            }
             let fields = interaction
                 .fields
                 .iter()
                 .map(|expr| evaluator.eval_expr(expr))
                 .collect_vec();
-            let count = evaluator.eval_expr(&interaction.count);
-            if count.is_zero() {
-                continue;
+            f(n, interaction, fields, count);
+        }
+    }
+}
+
+/// Maximum number of unbalanced messages reported on each side of a bus.
+pub const MAX_REPORTED_INTERACTIONS: usize = 5;
+
+/// A message sent or received by an AIR on a single row.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RowInteraction<F> {
+    pub air_idx: usize,
+    pub row: usize,
+    pub fields: Vec<F>,
+    pub count: F,
+}
+
+/// A bus on which the sent and received multiplicities of some messages differ.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BusImbalance<F> {
+    pub bus_index: usize,
+    /// For each AIR interacting on the bus, ordered by AIR index, the multiplicity of all
+    /// messages it sends minus the multiplicity of all messages it receives.
+    pub net_per_air: Vec<(usize, F)>,
+    /// The first sends of unbalanced messages, ordered by AIR index and row.
+    pub unbalanced_sends: Vec<RowInteraction<F>>,
+    /// The first receives of unbalanced messages, ordered by AIR index and row.
+    pub unbalanced_receives: Vec<RowInteraction<F>>,
+}
+
+/// The unbalanced buses of a set of traces.
+#[derive(Clone, Debug)]
+pub struct InteractionReport<F> {
+    /// Name of each AIR, indexed by AIR index.
+    pub air_names: Vec<String>,
+    /// The named buses, used to name the unbalanced buses.
+    pub bus_registry: BusRegistry,
+    pub imbalances: Vec<BusImbalance<F>>,
+}
+
+/// Returns the buses on which the multiplicities of some messages do not balance. At most
+/// [MAX_REPORTED_INTERACTIONS] messages are kept on each side of a bus.
+pub fn find_bus_imbalances<F: Field>(
+    logical_interactions: &LogicalInteractions<F>,
+) -> Vec<BusImbalance<F>> {
+    logical_interactions
+        .at_bus
+        .iter()
+        .filter_map(|(&bus_index, bus_interactions)| {
+            let mut net_per_air = BTreeMap::<usize, F>::new();
+            let mut unbalanced_sends = vec![];
+            let mut unbalanced_receives = vec![];
+            for (fields, connections) in bus_interactions {
+                let mut sum = F::ZERO;
+                for &(air_idx, _, interaction_type, count) in connections {
+                    let count = match interaction_type {
+                        InteractionType::Send => count,
+                        InteractionType::Receive => -count,
+                    };
+                    sum += count;
+                    *net_per_air.entry(air_idx).or_insert(F::ZERO) += count;
+                }
+                if sum.is_zero() {
+                    continue;
+                }
+                for &(air_idx, row, interaction_type, count) in connections {
+                    let interaction = RowInteraction {
+                        air_idx,
+                        row,
+                        fields: fields.clone(),
+                        count,
+                    };
+                    match interaction_type {
+                        InteractionType::Send => unbalanced_sends.push(interaction),
+                        InteractionType::Receive => unbalanced_receives.push(interaction),
+                    }
+                }
+            }
+            if unbalanced_sends.is_empty() && unbalanced_receives.is_empty() {
+                return None;
+            }
+            for interactions in [&mut unbalanced_sends, &mut unbalanced_receives] {
+                interactions.sort_by_key(|interaction| (interaction.air_idx, interaction.row));
+                interactions.truncate(MAX_REPORTED_INTERACTIONS);
+            }
+            Some(BusImbalance {
+                bus_index,
+                net_per_air: net_per_air.into_iter().collect(),
+                unbalanced_sends,
+                unbalanced_receives,
+            })
+        })
+        .collect()
+}
+
+/// Evaluates the interactions of each AIR on its traces, and returns the report of the unbalanced
+/// buses if there are any. The messages on the bus of a public lookup table that are rows of the
+/// table are received by the table, so they never unbalance the bus.
+#[allow(clippy::too_many_arguments)]
+pub fn check_interaction_balance<F: Field>(
+    air_names: &[String],
+    bus_registry: &BusRegistry,
+    public_lookup_tables: &[PublicLookupTable<F>],
+    interactions: &[Vec<SymbolicInteraction<F>>],
+    preprocessed: &[Option<RowMajorMatrixView<F>>],
+    partitioned_main: &[Vec<RowMajorMatrixView<F>>],
+    public_values: &[Vec<F>],
+    periodic_columns: &[&[Vec<F>]],
+) -> Result<(), InteractionReport<F>> {
+    let mut logical_interactions = LogicalInteractions::<F>::default();
+    for (
+        air_idx,
+        (interactions, preprocessed, partitioned_main, public_values, periodic_columns),
+    ) in izip!(
+        interactions,
+        preprocessed,
+        partitioned_main,
+        public_values,
+        periodic_columns
+    )
+    .enumerate()
+    {
+        generate_logical_interactions(
+            air_idx,
+            interactions,
+            preprocessed,
+            partitioned_main,
+            public_values,
+            periodic_columns,
+            &mut logical_interactions,
+        );
+    }
+
+    for table in public_lookup_tables {
+        if let Some(messages) = logical_interactions.at_bus.get_mut(&table.bus_index) {
+            messages.retain(|fields, _| !table.rows.contains(fields));
+        }
+    }
+
+    let imbalances = find_bus_imbalances(&logical_interactions);
+    if imbalances.is_empty() {
+        Ok(())
+    } else {
+        Err(InteractionReport {
+            air_names: air_names.to_vec(),
+            bus_registry: bus_registry.clone(),
+            imbalances,
+        })
+    }
+}
+
+impl<F: Field> fmt::Display for InteractionReport<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for imbalance in &self.imbalances {
+            writeln!(
+                f,
+                "Bus {} ({}) failed to balance the multiplicities.",
+                imbalance.bus_index,
+                self.bus_registry.name(imbalance.bus_index)
+            )?;
+            writeln!(f, "  Net multiplicity sent per AIR:")?;
+            for &(air_idx, net) in &imbalance.net_per_air {
+                writeln!(
+                    f,
+                    "    Air idx: {}, Air name: {}, net count: {:?}",
+                    air_idx, self.air_names[air_idx], net
+                )?;
+            }
+            for (side, interactions) in [
+                ("sends", &imbalance.unbalanced_sends),
+                ("receives", &imbalance.unbalanced_receives),
+            ] {
+                if interactions.is_empty() {
+                    continue;
+                }
+                writeln!(f, "  First unbalanced {side}:")?;
+                for interaction in interactions {
+                    writeln!(
+                        f,
+                        "    Air idx: {}, Air name: {}, row: {}, fields: {:?}, count: {:?}",
+                        interaction.air_idx,
+                        self.air_names[interaction.air_idx],
+                        interaction.row,
+                        interaction.fields,
+                        interaction.count
+                    )?;
+                }
             }
-            logical_interactions
-                .at_bus
-                .entry(interaction.bus_index)
-                .or_default()
-                .entry(fields)
-                .or_default()
-                .push((air_idx, interaction.interaction_type, count));
         }
+        Ok(())
     }
 }
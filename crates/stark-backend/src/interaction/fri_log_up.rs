@@ -13,10 +13,11 @@ use super::{PairTraceView, SymbolicInteraction};
 use crate::{
     air_builders::symbolic::{symbolic_expression::SymbolicEvaluator, SymbolicConstraints},
     interaction::{
+        public_lookup::PublicLookupTable,
         trace::Evaluator,
         utils::{generate_betas, generate_rlc_elements},
-        InteractionBuilder, InteractionType, RapPhaseProverData, RapPhaseSeq, RapPhaseSeqKind,
-        RapPhaseVerifierData,
+        Interaction, InteractionBuilder, InteractionType, RapPhaseProverData, RapPhaseSeq,
+        RapPhaseSeqKind, RapPhaseVerifierData,
     },
     parizip,
     rap::PermutationAirBuilderWithExposedValues,
@@ -25,21 +26,89 @@ use crate::{
 
 #[derive(Default)]
 pub struct FriLogUpPhase<F, Challenge, Challenger> {
+    params: FriLogUpParams,
     _marker: PhantomData<(F, Challenge, Challenger)>,
 }
 
 impl<F, Challenge, Challenger> FriLogUpPhase<F, Challenge, Challenger> {
-    pub fn new() -> Self {
+    /// ## Panics
+    /// If the batch size or the number of repetitions of `params` is zero, or if `params` has a
+    /// batch size and commits inverses.
+    pub fn new(params: FriLogUpParams) -> Self {
+        assert_ne!(params.batch_size, Some(0), "batch size must be positive");
+        assert!(
+            !(params.commit_inverses && params.batch_size.is_some()),
+            "interactions are not batched when committing inverses"
+        );
+        assert_ne!(
+            params.num_repetitions, 0,
+            "number of repetitions must be positive"
+        );
         Self {
+            params,
             _marker: PhantomData,
         }
     }
+
+    pub fn params(&self) -> FriLogUpParams {
+        self.params
+    }
+}
+
+/// Parameters of the FRI log-up phase.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FriLogUpParams {
+    /// The number of interactions batched into each after challenge column, the last column of
+    /// an AIR possibly having fewer. Batching more interactions reduces the after challenge width
+    /// but increases the degree of the log-up constraints.
+    ///
+    /// If `None`, the interactions are batched as much as the max constraint degree allows.
+    pub batch_size: Option<usize>,
+    /// The number of independent repetitions of the log-up argument. Each repetition samples its
+    /// own `alpha, beta` challenges, has its own after challenge columns and exposes its own
+    /// cumulative sum, which must sum to zero across AIRs. The soundness error of the argument
+    /// is raised to the power `num_repetitions`, at the cost of a wider after challenge trace.
+    #[serde(default = "default_num_repetitions")]
+    pub num_repetitions: usize,
+    /// Whether the after challenge trace commits the inverse of each distinct log-up denominator
+    /// of an AIR, instead of the batched fractions of its interactions. The interactions with the
+    /// same bus and fields share their inverse column. The log-up constraints then have degree
+    /// `max(field_degree, count_degree) + 1` whatever the number of interactions, at the cost of
+    /// more after challenge columns than batching when the max constraint degree is high.
+    ///
+    /// The batch size must be `None`.
+    #[serde(default)]
+    pub commit_inverses: bool,
+}
+
+impl Default for FriLogUpParams {
+    fn default() -> Self {
+        Self {
+            batch_size: None,
+            num_repetitions: 1,
+            commit_inverses: false,
+        }
+    }
+}
+
+fn default_num_repetitions() -> usize {
+    1
 }
 
 #[derive(Error, Debug)]
 pub enum FriLogUpError {
     #[error("non-zero cumulative sum")]
     NonZeroCumulativeSum,
+    #[error("the multiplicities do not match the public lookup tables")]
+    InvalidPublicLookupMultiplicities,
+}
+
+/// The part of the proof of the FRI log-up phase outside of the after challenge traces.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct FriLogUpPartialProof<F> {
+    /// The multiplicity of each row of each public lookup table of the verifying key, see
+    /// [PublicLookupTable].
+    pub public_lookup_multiplicities: Vec<Vec<F>>,
 }
 
 #[derive(Clone, Default, Serialize, Deserialize)]
@@ -63,7 +132,7 @@ where
     Challenge: ExtensionField<F>,
     Challenger: FieldChallenger<F>,
 {
-    type PartialProof = ();
+    type PartialProof = FriLogUpPartialProof<F>;
     type PartialProvingKey = FriLogUpProvingKey;
     type Error = FriLogUpError;
     const ID: RapPhaseSeqKind = RapPhaseSeqKind::FriLogUp;
@@ -76,18 +145,28 @@ where
         symbolic_constraints_per_air
             .iter()
             .map(|constraints| {
-                find_interaction_chunks(&constraints.interactions, max_constraint_degree)
+                find_interaction_partitions(
+                    &constraints.interactions,
+                    max_constraint_degree,
+                    self.params,
+                )
             })
             .collect()
     }
 
+    fn log_up_params(&self) -> FriLogUpParams {
+        self.params
+    }
+
     fn partially_prove(
         &self,
         challenger: &mut Challenger,
         constraints_per_air: &[&SymbolicConstraints<F>],
         params_per_air: &[&FriLogUpProvingKey],
+        public_lookup_tables: &[PublicLookupTable<F>],
         trace_view_per_air: &[PairTraceView<F>],
-    ) -> Option<(Self::PartialProof, RapPhaseProverData<Challenge>)> {
+        commit_phase: &mut impl FnMut(&mut Challenger, &[Option<RowMajorMatrix<Challenge>>]),
+    ) -> Option<(Self::PartialProof, Vec<RapPhaseProverData<Challenge>>)> {
         let has_any_interactions = constraints_per_air
             .iter()
             .any(|constraints| !constraints.interactions.is_empty());
@@ -96,43 +175,61 @@ where
             return None;
         }
 
-        let challenges: [Challenge; STARK_LU_NUM_CHALLENGES] =
-            array::from_fn(|_| challenger.sample_ext_element::<Challenge>());
+        // The multiplicities of the public lookup tables are fixed before the challenges.
+        let interactions_per_air = constraints_per_air
+            .iter()
+            .map(|constraints| constraints.interactions.as_slice())
+            .collect_vec();
+        let public_lookup_multiplicities = public_lookup_tables
+            .iter()
+            .map(|table| table.multiplicities(&interactions_per_air, trace_view_per_air))
+            .collect_vec();
+        observe_public_lookups(
+            challenger,
+            public_lookup_tables,
+            &public_lookup_multiplicities,
+        );
 
-        let after_challenge_trace_per_air = metrics_span("generate_perm_trace_time_ms", || {
-            Self::generate_after_challenge_traces_per_air(
-                &challenges,
-                constraints_per_air,
-                params_per_air,
-                trace_view_per_air,
-            )
-        });
-        let cumulative_sum_per_air = Self::extract_cumulative_sums(&after_challenge_trace_per_air);
+        let challenges = (0..self.params.num_repetitions)
+            .map(|_| array::from_fn(|_| challenger.sample_ext_element::<Challenge>()))
+            .collect_vec();
+
+        let (after_challenge_trace_per_air, cumulative_sums_per_air) =
+            metrics_span("generate_perm_trace_time_ms", || {
+                Self::generate_after_challenge_traces_per_air(
+                    &challenges,
+                    self.params.commit_inverses,
+                    constraints_per_air,
+                    params_per_air,
+                    trace_view_per_air,
+                )
+            });
 
         // Challenger needs to observe what is exposed (cumulative_sums)
-        for cumulative_sum in cumulative_sum_per_air.iter().flatten() {
+        for cumulative_sum in cumulative_sums_per_air.iter().flatten().flatten() {
             challenger.observe_slice(cumulative_sum.as_base_slice());
         }
-
-        let exposed_values_per_air = cumulative_sum_per_air
-            .iter()
-            .map(|csum| csum.map(|csum| vec![csum]))
-            .collect_vec();
+        commit_phase(challenger, &after_challenge_trace_per_air);
 
         Some((
-            (),
-            RapPhaseProverData {
-                challenges: challenges.to_vec(),
-                after_challenge_trace_per_air,
-                exposed_values_per_air,
+            FriLogUpPartialProof {
+                public_lookup_multiplicities,
             },
+            vec![RapPhaseProverData {
+                challenges: challenges.concat(),
+                after_challenge_trace_per_air,
+                exposed_values_per_air: cumulative_sums_per_air,
+            }],
         ))
     }
 
     fn partially_verify<Commitment: Clone>(
         &self,
         challenger: &mut Challenger,
-        _partial_proof: Option<&Self::PartialProof>,
+        partial_proof: Option<&Self::PartialProof>,
+        public_lookup_tables: &[PublicLookupTable<F>],
+        _num_interactions_per_air: &[usize],
+        _log_trace_height_per_air: &[usize],
         exposed_values_per_phase_per_air: &[Vec<Vec<Challenge>>],
         commitment_per_phase: &[Commitment],
         _permutation_opened_values: &[Vec<Vec<Vec<Challenge>>>],
@@ -147,13 +244,28 @@ where
             return (
                 RapPhaseVerifierData {
                     challenges_per_phase: vec![],
+                    eq_points_per_air: vec![],
                 },
                 Ok(()),
             );
         }
 
-        let challenges: [Challenge; STARK_LU_NUM_CHALLENGES] =
-            array::from_fn(|_| challenger.sample_ext_element::<Challenge>());
+        let public_lookup_multiplicities = partial_proof
+            .map(|proof| proof.public_lookup_multiplicities.as_slice())
+            .unwrap_or_default();
+        let valid_multiplicities = public_lookup_multiplicities.len() == public_lookup_tables.len()
+            && zip(public_lookup_multiplicities, public_lookup_tables)
+                .all(|(multiplicities, table)| multiplicities.len() == table.rows.len());
+        observe_public_lookups(
+            challenger,
+            public_lookup_tables,
+            public_lookup_multiplicities,
+        );
+
+        let num_repetitions = self.params.num_repetitions;
+        let challenges = (0..num_repetitions * STARK_LU_NUM_CHALLENGES)
+            .map(|_| challenger.sample_ext_element::<Challenge>())
+            .collect_vec();
 
         for exposed_values_per_phase in exposed_values_per_phase_per_air.iter() {
             if let Some(exposed_values) = exposed_values_per_phase.first() {
@@ -175,30 +287,63 @@ where
                 exposed_values_per_phase.first().map(|exposed_values| {
                     assert_eq!(
                         exposed_values.len(),
-                        1,
-                        "Only exposed value should be cumulative sum"
+                        num_repetitions,
+                        "Only exposed values should be cumulative sums"
                     );
-                    exposed_values[0]
+                    exposed_values
                 })
             })
             .collect_vec();
 
-        // Check cumulative sum
-        let sum: Challenge = cumulative_sums
-            .into_iter()
-            .map(|c| c.unwrap_or(Challenge::ZERO))
-            .sum();
+        // Check the cumulative sum of each repetition. The public lookup tables receive the
+        // messages not received by any AIR.
+        let all_sums_zero = (0..num_repetitions).all(|repetition| {
+            let sum: Challenge = cumulative_sums
+                .iter()
+                .map(|c| c.map_or(Challenge::ZERO, |c| c[repetition]))
+                .sum();
+            let [alpha, beta] =
+                [0, 1].map(|i| challenges[repetition * STARK_LU_NUM_CHALLENGES + i]);
+            let public_lookup_sum: Challenge =
+                zip(public_lookup_tables, public_lookup_multiplicities)
+                    .map(|(table, multiplicities)| table.log_up_sum(multiplicities, alpha, beta))
+                    .sum();
+            sum == public_lookup_sum
+        });
 
-        let result = if sum == Challenge::ZERO {
+        let result = if !valid_multiplicities {
+            Err(Self::Error::InvalidPublicLookupMultiplicities)
+        } else if all_sums_zero {
             Ok(())
         } else {
             Err(Self::Error::NonZeroCumulativeSum)
         };
         let verifier_data = RapPhaseVerifierData {
-            challenges_per_phase: vec![challenges.to_vec()],
+            challenges_per_phase: vec![challenges],
+            eq_points_per_air: vec![],
         };
         (verifier_data, result)
     }
+
+    fn is_sum_mismatch(&self, error: &FriLogUpError) -> bool {
+        matches!(error, FriLogUpError::NonZeroCumulativeSum)
+    }
+}
+
+/// Observes the rows of the public lookup tables and their multiplicities.
+fn observe_public_lookups<F: Field, Challenger: FieldChallenger<F>>(
+    challenger: &mut Challenger,
+    public_lookup_tables: &[PublicLookupTable<F>],
+    public_lookup_multiplicities: &[Vec<F>],
+) {
+    for table in public_lookup_tables {
+        for row in &table.rows {
+            challenger.observe_slice(row);
+        }
+    }
+    for multiplicities in public_lookup_multiplicities {
+        challenger.observe_slice(multiplicities);
+    }
 }
 
 pub const STARK_LU_NUM_CHALLENGES: usize = 2;
@@ -210,39 +355,56 @@ where
     Challenge: ExtensionField<F>,
     Challenger: FieldChallenger<F>,
 {
-    /// Returns a list of optional tuples of (permutation trace,cumulative sum) for each AIR.
+    /// Returns the permutation trace and the cumulative sums of each AIR with interactions.
+    ///
+    /// The permutation trace of an AIR is the horizontal concatenation of the permutation traces
+    /// of all repetitions, the `i`-th one using `challenges[i]`. The `i`-th cumulative sum is the
+    /// one of the `i`-th repetition.
+    #[allow(clippy::type_complexity)]
     fn generate_after_challenge_traces_per_air(
-        challenges: &[Challenge; STARK_LU_NUM_CHALLENGES],
+        challenges: &[[Challenge; STARK_LU_NUM_CHALLENGES]],
+        commit_inverses: bool,
         constraints_per_air: &[&SymbolicConstraints<F>],
         params_per_air: &[&FriLogUpProvingKey],
         trace_view_per_air: &[PairTraceView<F>],
-    ) -> Vec<Option<RowMajorMatrix<Challenge>>> {
+    ) -> (
+        Vec<Option<RowMajorMatrix<Challenge>>>,
+        Vec<Option<Vec<Challenge>>>,
+    ) {
         parizip!(constraints_per_air, trace_view_per_air, params_per_air)
             .map(|(constraints, trace_view, params)| {
-                Self::generate_after_challenge_trace(
-                    &constraints.interactions,
-                    trace_view,
-                    challenges,
-                    &params.interaction_partitions,
-                )
-            })
-            .collect::<Vec<_>>()
-    }
-
-    fn extract_cumulative_sums(
-        perm_traces: &[Option<RowMajorMatrix<Challenge>>],
-    ) -> Vec<Option<Challenge>> {
-        perm_traces
-            .iter()
-            .map(|perm_trace| {
-                perm_trace.as_ref().map(|perm_trace| {
-                    *perm_trace
-                        .row_slice(perm_trace.height() - 1)
-                        .last()
-                        .unwrap()
-                })
+                let (perm_traces, cumulative_sums): (Vec<_>, Vec<_>) = challenges
+                    .iter()
+                    .map(|challenges| {
+                        if commit_inverses {
+                            return Self::generate_inverse_trace(
+                                &constraints.interactions,
+                                trace_view,
+                                challenges,
+                                &params.interaction_partitions,
+                            );
+                        }
+                        Self::generate_after_challenge_trace(
+                            &constraints.interactions,
+                            trace_view,
+                            challenges,
+                            &params.interaction_partitions,
+                        )
+                        .map(|perm_trace| {
+                            let cumulative_sum = *perm_trace
+                                .row_slice(perm_trace.height() - 1)
+                                .last()
+                                .unwrap();
+                            (perm_trace, cumulative_sum)
+                        })
+                    })
+                    .collect::<Option<Vec<_>>>()?
+                    .into_iter()
+                    .unzip();
+                Some((concat_horizontally(perm_traces), cumulative_sums))
             })
-            .collect()
+            .map(Option::unzip)
+            .unzip()
     }
 
     // Copied from valida/machine/src/chip.rs, modified to allow partitioned main trace
@@ -291,7 +453,7 @@ where
         // Row: | perm_1 | perm_2 | perm_3 | ... | perm_s | phi |, where s
         // is the number of bundles
         let num_interactions = all_interactions.len();
-        let height = trace_view.partitioned_main[0].height();
+        let height = 1usize << trace_view.log_trace_height;
 
         // Note: we could precompute this and include in the proving key, but this should be
         // a fast scan and only done once per AIR and not per row, so it is more ergonomic to compute
@@ -325,6 +487,7 @@ where
             preprocessed: &preprocessed,
             partitioned_main: &partitioned_main,
             public_values: &trace_view.public_values,
+            periodic_columns: &trace_view.periodic_columns,
             height,
             local_index,
         };
@@ -402,6 +565,121 @@ where
 
         Some(RowMajorMatrix::new(perm_values, perm_width))
     }
+
+    /// Generates the after challenge trace of a single repetition with committed inverses, see
+    /// [FriLogUpParams::commit_inverses], and returns it with its cumulative sum.
+    ///
+    /// Row: | inv_1 | inv_2 | ... | inv_s | phi |, where `inv_k` is the inverse of the denominator
+    /// shared by the interactions of `interaction_partitions[k]`, and `phi` is the sum of the
+    /// fractions of all the previous rows.
+    fn generate_inverse_trace(
+        all_interactions: &[SymbolicInteraction<F>],
+        trace_view: &PairTraceView<F>,
+        permutation_randomness: &[Challenge; STARK_LU_NUM_CHALLENGES],
+        interaction_partitions: &[Vec<usize>],
+    ) -> Option<(RowMajorMatrix<Challenge>, Challenge)> {
+        if all_interactions.is_empty() {
+            return None;
+        }
+        let &[alpha, beta] = permutation_randomness;
+
+        let alphas = generate_rlc_elements(alpha, all_interactions);
+        let betas = generate_betas(beta, all_interactions);
+
+        let height = 1usize << trace_view.log_trace_height;
+        let num_denoms = interaction_partitions.len();
+        let width = num_denoms + 1;
+
+        let preprocessed = trace_view.preprocessed.as_ref().map(|m| m.as_view());
+        let partitioned_main = trace_view
+            .partitioned_main
+            .iter()
+            .map(|m| m.as_view())
+            .collect_vec();
+        let evaluator = |local_index: usize| Evaluator {
+            preprocessed: &preprocessed,
+            partitioned_main: &partitioned_main,
+            public_values: &trace_view.public_values,
+            periodic_columns: &trace_view.periodic_columns,
+            height,
+            local_index,
+        };
+
+        let mut denoms = Challenge::zero_vec(height * num_denoms);
+        denoms
+            .par_chunks_exact_mut(num_denoms)
+            .enumerate()
+            .for_each(|(row, denom_row)| {
+                let evaluator = evaluator(row);
+                for (denom, part) in denom_row.iter_mut().zip(interaction_partitions) {
+                    let interaction = &all_interactions[part[0]];
+                    let mut fields = interaction.fields.iter();
+                    *denom = alphas[interaction.bus_index]
+                        + evaluator.eval_expr(fields.next().expect("fields should not be empty"));
+                    for (expr, &beta) in fields.zip(betas.iter().skip(1)) {
+                        *denom += beta * evaluator.eval_expr(expr);
+                    }
+                }
+            });
+        // The logup denominators should never be zero, so trace generation should panic if
+        // trying to divide by zero.
+        let inverses = p3_field::batch_multiplicative_inverse(&denoms);
+        drop(denoms);
+
+        let fractions_per_row: Vec<Challenge> = inverses
+            .par_chunks_exact(num_denoms)
+            .enumerate()
+            .map(|(row, inverses)| {
+                let evaluator = evaluator(row);
+                zip(interaction_partitions, inverses)
+                    .map(|(part, &inverse)| {
+                        let count: F = part
+                            .iter()
+                            .map(|&interaction_idx| {
+                                let interaction = &all_interactions[interaction_idx];
+                                let count = evaluator.eval_expr(&interaction.count);
+                                if interaction.interaction_type == InteractionType::Receive {
+                                    -count
+                                } else {
+                                    count
+                                }
+                            })
+                            .sum();
+                        inverse * count
+                    })
+                    .sum()
+            })
+            .collect();
+
+        let mut values = Challenge::zero_vec(height * width);
+        let mut phi = Challenge::ZERO;
+        for ((row, inverses), fractions) in values
+            .chunks_exact_mut(width)
+            .zip(inverses.chunks_exact(num_denoms))
+            .zip(fractions_per_row)
+        {
+            row[..num_denoms].copy_from_slice(inverses);
+            row[num_denoms] = phi;
+            phi += fractions;
+        }
+        Some((RowMajorMatrix::new(values, width), phi))
+    }
+}
+
+/// Concatenates matrices of the same height horizontally.
+fn concat_horizontally<EF: Field>(mut matrices: Vec<RowMajorMatrix<EF>>) -> RowMajorMatrix<EF> {
+    if matrices.len() == 1 {
+        return matrices.pop().unwrap();
+    }
+    let width = matrices.iter().map(|m| m.width()).sum();
+    let height = matrices[0].height();
+    let mut values = Vec::with_capacity(width * height);
+    for row in 0..height {
+        for matrix in &matrices {
+            values.extend_from_slice(&matrix.values[row * matrix.width..(row + 1) * matrix.width]);
+        }
+    }
+    RowMajorMatrix::new(values, width)
 }
 
 // Initial version taken from valida/machine/src/chip.rs under MIT license.
@@ -410,41 +688,155 @@ where
 /// and one column for the partial sum of log derivative. These columns are trace columns
 /// "after challenge" phase 0, and they are valued in the extension field.
 /// For more details, see the comment in the trace.rs file
+///
+/// With several repetitions, the permutation row is the concatenation of the permutation rows of
+/// the repetitions, and repetition `i` uses the challenges `2i, 2i + 1` and the exposed value `i`.
+///
+/// With committed inverses, the constraints of each repetition are instead given by
+/// [eval_fri_log_up_inverse_repetition].
 pub fn eval_fri_log_up_phase<AB>(
     builder: &mut AB,
     symbolic_interactions: &[SymbolicInteraction<AB::F>],
     max_constraint_degree: usize,
+    params: FriLogUpParams,
 ) where
     AB: InteractionBuilder + PermutationAirBuilderWithExposedValues,
 {
-    let exposed_values = builder.permutation_exposed_values();
+    let exposed_values = builder.permutation_exposed_values().to_vec();
     // There are interactions, add constraints for the virtual columns
     assert_eq!(
         exposed_values.len(),
-        1,
-        "Should have one exposed value for cumulative_sum"
+        params.num_repetitions,
+        "Should have one exposed value for cumulative_sum per repetition"
+    );
+    let rand_elems = builder.permutation_randomness().to_vec();
+    assert_eq!(
+        rand_elems.len(),
+        params.num_repetitions * STARK_LU_NUM_CHALLENGES
     );
-    let cumulative_sum = exposed_values[0];
-
-    let rand_elems = builder.permutation_randomness();
 
     let perm = builder.permutation();
     let (perm_local, perm_next) = (perm.row_slice(0), perm.row_slice(1));
     let perm_local: &[AB::VarEF] = (*perm_local).borrow();
     let perm_next: &[AB::VarEF] = (*perm_next).borrow();
+    let (perm_local, perm_next) = (perm_local.to_vec(), perm_next.to_vec());
 
     let all_interactions = builder.all_interactions().to_vec();
     let FriLogUpProvingKey {
         interaction_partitions,
-    } = find_interaction_chunks(symbolic_interactions, max_constraint_degree);
+    } = find_interaction_partitions(symbolic_interactions, max_constraint_degree, params);
     let num_chunks = interaction_partitions.len();
-    debug_assert_eq!(num_chunks + 1, perm_local.len());
+    debug_assert_eq!((num_chunks + 1) * params.num_repetitions, perm_local.len());
+
+    for (((perm_local, perm_next), rand_elems), &cumulative_sum) in perm_local
+        .chunks_exact(num_chunks + 1)
+        .zip(perm_next.chunks_exact(num_chunks + 1))
+        .zip(rand_elems.chunks_exact(STARK_LU_NUM_CHALLENGES))
+        .zip(&exposed_values)
+    {
+        if params.commit_inverses {
+            eval_fri_log_up_inverse_repetition(
+                builder,
+                &all_interactions,
+                &interaction_partitions,
+                perm_local,
+                perm_next,
+                rand_elems,
+                cumulative_sum,
+            );
+        } else {
+            eval_fri_log_up_repetition(
+                builder,
+                &all_interactions,
+                &interaction_partitions,
+                perm_local,
+                perm_next,
+                rand_elems,
+                cumulative_sum,
+            );
+        }
+    }
+}
+
+/// Constraints of a single repetition of the log-up argument with committed inverses, see
+/// [FriLogUpParams::commit_inverses].
+///
+/// The permutation row is `| inv_1 | inv_2 | ... | inv_s | phi |`, where `inv_k` is constrained to
+/// be the inverse of the denominator shared by the interactions of the `k`-th partition. The
+/// fractions of a row are then `sum_k inv_k * count_k`, where `count_k` is the signed sum of the
+/// counts of the `k`-th partition, and `phi` is the sum of the fractions of all the previous rows.
+/// The running sum constraint
+///
+///     phi_next - phi_local = sum_k inv_k * count_k - is_last_row * cumulative_sum
+///
+/// holds on every row, the next row of the last row being the first one, so that summing it over
+/// all rows shows that `cumulative_sum` is the sum of all fractions. Its degree is
+/// `max_count_degree + 1`, and the one of the inverse constraints is `max_field_degree + 1`.
+fn eval_fri_log_up_inverse_repetition<AB>(
+    builder: &mut AB,
+    all_interactions: &[Interaction<AB::Expr>],
+    interaction_partitions: &[Vec<usize>],
+    perm_local: &[AB::VarEF],
+    perm_next: &[AB::VarEF],
+    rand_elems: &[AB::RandomVar],
+    cumulative_sum: AB::VarEF,
+) where
+    AB: InteractionBuilder + PermutationAirBuilderWithExposedValues,
+{
+    let alphas = generate_rlc_elements(rand_elems[0].into(), all_interactions);
+    let betas = generate_betas(rand_elems[1].into(), all_interactions);
+
+    let mut fractions = AB::ExprEF::ZERO;
+    for (part, &inverse) in zip(interaction_partitions, perm_local) {
+        let interaction = &all_interactions[part[0]];
+        assert!(!interaction.fields.is_empty(), "fields should not be empty");
+        let mut denom = alphas[interaction.bus_index].clone();
+        for (field, beta) in interaction.fields.iter().zip(betas.iter()) {
+            denom += beta.clone() * field.clone();
+        }
+        let inverse: AB::ExprEF = inverse.into();
+        builder.assert_eq_ext(denom * inverse.clone(), AB::ExprEF::ONE);
 
+        let mut count = AB::Expr::ZERO;
+        for &interaction_idx in part {
+            let interaction = &all_interactions[interaction_idx];
+            if interaction.interaction_type == InteractionType::Receive {
+                count -= interaction.count.clone();
+            } else {
+                count += interaction.count.clone();
+            }
+        }
+        let count: AB::ExprEF = count.into();
+        fractions += count * inverse;
+    }
+
+    let phi_local: AB::ExprEF = (*perm_local.last().unwrap()).into();
+    let phi_next: AB::ExprEF = (*perm_next.last().unwrap()).into();
+    let is_last_row: AB::ExprEF = builder.is_last_row().into();
+    let cumulative_sum: AB::ExprEF = cumulative_sum.into();
+    builder.assert_eq_ext(
+        phi_next - phi_local,
+        fractions - is_last_row * cumulative_sum,
+    );
+}
+
+/// Constraints of a single repetition of the log-up argument, see [eval_fri_log_up_phase].
+fn eval_fri_log_up_repetition<AB>(
+    builder: &mut AB,
+    all_interactions: &[Interaction<AB::Expr>],
+    interaction_partitions: &[Vec<usize>],
+    perm_local: &[AB::VarEF],
+    perm_next: &[AB::VarEF],
+    rand_elems: &[AB::RandomVar],
+    cumulative_sum: AB::VarEF,
+) where
+    AB: InteractionBuilder + PermutationAirBuilderWithExposedValues,
+{
     let phi_local = *perm_local.last().unwrap();
     let phi_next = *perm_next.last().unwrap();
 
-    let alphas = generate_rlc_elements(rand_elems[0].into(), &all_interactions);
-    let betas = generate_betas(rand_elems[1].into(), &all_interactions);
+    let alphas = generate_rlc_elements(rand_elems[0].into(), all_interactions);
+    let betas = generate_betas(rand_elems[1].into(), all_interactions);
 
     let phi_lhs = phi_next.into() - phi_local.into();
     let mut phi_rhs = AB::ExprEF::ZERO;
@@ -529,14 +921,20 @@ pub fn eval_fri_log_up_phase<AB>(
 ///
 /// If `max_constraint_degree == 0`, then `num_chunks = interactions.len()` and no chunking is done.
 ///
+/// If `batch_size` is given, the interactions are instead packed into chunks of `batch_size`
+/// interactions, the last one possibly smaller, whatever the resulting degree. The degree is
+/// then validated at keygen, see [fri_log_up_constraint_degree].
+///
 /// ## Note
 /// This function is only intended for use in preprocessing, and is not used in proving.
 ///
 /// ## Panics
-/// If `max_constraint_degree > 0` and there are interactions that cannot fit in a singleton chunk.
+/// If `max_constraint_degree > 0`, `batch_size` is `None` and there are interactions that cannot
+/// fit in a singleton chunk.
 pub(crate) fn find_interaction_chunks<F: Field>(
     interactions: &[SymbolicInteraction<F>],
     max_constraint_degree: usize,
+    batch_size: Option<usize>,
 ) -> FriLogUpProvingKey {
     if interactions.is_empty() {
         return FriLogUpProvingKey::default();
@@ -562,6 +960,14 @@ pub(crate) fn find_interaction_chunks<F: Field>(
             field_cmp
         }
     });
+    if let Some(batch_size) = batch_size {
+        return FriLogUpProvingKey {
+            interaction_partitions: interaction_idxs
+                .chunks(batch_size)
+                .map(|chunk| chunk.to_vec())
+                .collect(),
+        };
+    }
     // Now we greedily pack
     let mut running_sum_field_degree = 0;
     let mut numerator_max_degree = 0;
@@ -605,3 +1011,73 @@ pub(crate) fn find_interaction_chunks<F: Field>(
         interaction_partitions,
     }
 }
+
+/// The partition of the interactions of an AIR among the after challenge columns of a repetition
+/// of the log-up argument with `params`: see [find_shared_denominators] if it commits inverses,
+/// and [find_interaction_chunks] otherwise.
+pub(crate) fn find_interaction_partitions<F: Field>(
+    interactions: &[SymbolicInteraction<F>],
+    max_constraint_degree: usize,
+    params: FriLogUpParams,
+) -> FriLogUpProvingKey {
+    if params.commit_inverses {
+        find_shared_denominators(interactions)
+    } else {
+        find_interaction_chunks(interactions, max_constraint_degree, params.batch_size)
+    }
+}
+
+/// Partitions the interactions by log-up denominator, i.e. by bus and fields, in order of first
+/// occurrence.
+pub(crate) fn find_shared_denominators<F: Field>(
+    interactions: &[SymbolicInteraction<F>],
+) -> FriLogUpProvingKey {
+    let mut interaction_partitions: Vec<Vec<usize>> = vec![];
+    for (interaction_idx, interaction) in interactions.iter().enumerate() {
+        // Expressions are compared structurally: their hash only covers the top node.
+        let part = interaction_partitions.iter_mut().find(|part| {
+            let first = &interactions[part[0]];
+            first.bus_index == interaction.bus_index && first.fields == interaction.fields
+        });
+        match part {
+            Some(part) => part.push(interaction_idx),
+            None => interaction_partitions.push(vec![interaction_idx]),
+        }
+    }
+    FriLogUpProvingKey {
+        interaction_partitions,
+    }
+}
+
+/// The degree of the dominating log-up constraint when the interactions are chunked according to
+/// `interaction_partitions`, see [find_interaction_chunks].
+pub(crate) fn fri_log_up_constraint_degree<F: Field>(
+    interactions: &[SymbolicInteraction<F>],
+    interaction_partitions: &[Vec<usize>],
+) -> usize {
+    interaction_partitions
+        .iter()
+        .map(|part| {
+            let field_degrees = part
+                .iter()
+                .map(|&i| {
+                    interactions[i]
+                        .fields
+                        .iter()
+                        .map(|f| f.degree_multiple())
+                        .max()
+                        .unwrap_or(0)
+                })
+                .collect_vec();
+            let denom_degree: usize = field_degrees.iter().sum();
+            let num_degree = zip(part, &field_degrees)
+                .map(|(&i, field_degree)| {
+                    interactions[i].count.degree_multiple() + denom_degree - field_degree
+                })
+                .max()
+                .unwrap_or(0);
+            max(num_degree, denom_degree + 1)
+        })
+        .max()
+        .unwrap_or(0)
+}
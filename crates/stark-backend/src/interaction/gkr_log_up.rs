@@ -0,0 +1,624 @@
+//! Log-up argument proven with GKR instead of committed accumulator columns.
+//!
+//! For each interaction of an AIR with a trace of height `2^n`, the numerators `±count` and the
+//! denominators `alpha^(bus + 1) + sum_j beta^j * f_j` over the rows are the input layer of a
+//! LogUp GKR instance. The batch GKR proof shows that the fractions of all instances sum to zero,
+//! and reduces this to claims on the multilinear extensions of the numerators and denominators at
+//! a random point `r`.
+//!
+//! The claims are tied to the main traces by a challenge phase with two columns per AIR, whatever
+//! its number of interactions: the evaluations `eq(r, row)`, and the running sum of
+//! `eq(r, row) * g(row)` where `g = sum_i gamma^(2i) * numerator_i + gamma^(2i + 1) * denominator_i`.
+//! The sum over all rows is exposed and checked against the GKR claims, and the verifier checks
+//! the opened `eq` column against [eval_eq_column].
+
+use std::{array, borrow::Borrow, iter::zip, marker::PhantomData};
+
+use itertools::{izip, Itertools};
+use p3_challenger::{CanObserve, CanSample, CanSampleBits, FieldChallenger};
+use p3_commit::PolynomialSpace;
+use p3_field::{batch_multiplicative_inverse, ExtensionField, Field, FieldAlgebra};
+use p3_matrix::{dense::RowMajorMatrix, Matrix};
+use p3_maybe_rayon::prelude::*;
+use p3_util::log2_strict_usize;
+use thiserror::Error;
+
+use super::{PairTraceView, SymbolicInteraction};
+use crate::{
+    air_builders::symbolic::{symbolic_expression::SymbolicEvaluator, SymbolicConstraints},
+    gkr::{self, Gate, GkrArtifact, GkrBatchProof, Layer},
+    interaction::{
+        public_lookup::PublicLookupTable,
+        trace::Evaluator,
+        utils::{generate_betas, generate_rlc_elements},
+        InteractionBuilder, InteractionType, RapPhaseProverData, RapPhaseSeq, RapPhaseSeqKind,
+        RapPhaseVerifierData,
+    },
+    poly::{
+        multi::{hypercube_eq_evals, Mle},
+        uni::Fraction,
+    },
+    rap::PermutationAirBuilderWithExposedValues,
+    utils::metrics_span,
+};
+
+#[derive(Default)]
+pub struct GkrLogUpPhase<F, Challenge, Challenger> {
+    _marker: PhantomData<(F, Challenge, Challenger)>,
+}
+
+impl<F, Challenge, Challenger> GkrLogUpPhase<F, Challenge, Challenger> {
+    pub fn new() -> Self {
+        Self {
+            _marker: PhantomData,
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum GkrLogUpError {
+    #[error("missing GKR proof")]
+    MissingProof,
+    #[error("invalid GKR proof: {0}")]
+    InvalidGkrProof(String),
+    #[error("GKR instances do not match the interactions and trace heights")]
+    InstanceMismatch,
+    #[error("non-zero log-up sum")]
+    NonZeroSum,
+    #[error("exposed value of AIR {air_idx} does not match the GKR claims")]
+    ClaimMismatch { air_idx: usize },
+    #[error("public lookup tables are not supported by GKR log-up")]
+    PublicLookupTablesUnsupported,
+}
+
+/// Numerators and denominators of the log-up fractions of an interaction over the rows of a
+/// trace.
+type Fractions<Challenge> = (Vec<Challenge>, Vec<Challenge>);
+
+impl<F, Challenge, Challenger> RapPhaseSeq<F, Challenge, Challenger>
+    for GkrLogUpPhase<F, Challenge, Challenger>
+where
+    F: Field,
+    Challenge: ExtensionField<F>,
+    Challenger: FieldChallenger<F>,
+{
+    type PartialProof = GkrBatchProof<Challenge>;
+    type PartialProvingKey = ();
+    type Error = GkrLogUpError;
+    const ID: RapPhaseSeqKind = RapPhaseSeqKind::GkrLogUp;
+
+    fn generate_pk_per_air(
+        &self,
+        symbolic_constraints_per_air: &[SymbolicConstraints<F>],
+        _max_constraint_degree: usize,
+    ) -> Vec<Self::PartialProvingKey> {
+        vec![(); symbolic_constraints_per_air.len()]
+    }
+
+    fn partially_prove(
+        &self,
+        challenger: &mut Challenger,
+        constraints_per_air: &[&SymbolicConstraints<F>],
+        _params_per_air: &[&Self::PartialProvingKey],
+        // Keys with public lookup tables are rejected by `check_proof_input`, and by the verifier.
+        _public_lookup_tables: &[PublicLookupTable<F>],
+        trace_view_per_air: &[PairTraceView<F>],
+        commit_phase: &mut impl FnMut(&mut Challenger, &[Option<RowMajorMatrix<Challenge>>]),
+    ) -> Option<(Self::PartialProof, Vec<RapPhaseProverData<Challenge>>)> {
+        let has_any_interactions = constraints_per_air
+            .iter()
+            .any(|constraints| !constraints.interactions.is_empty());
+
+        if !has_any_interactions {
+            return None;
+        }
+
+        let [alpha, beta]: [Challenge; 2] =
+            array::from_fn(|_| challenger.sample_ext_element::<Challenge>());
+
+        let fractions_per_air = zip(constraints_per_air, trace_view_per_air)
+            .map(|(constraints, trace_view)| {
+                Self::generate_fractions(&constraints.interactions, trace_view, alpha, beta)
+            })
+            .collect_vec();
+        let input_layers = fractions_per_air
+            .iter()
+            .flatten()
+            .map(|(numerators, denominators)| {
+                assert!(
+                    numerators.len() > 1,
+                    "GKR log-up requires traces with interactions to have height at least 2"
+                );
+                Layer::LogUpGeneric {
+                    numerators: Mle::new(numerators.clone()),
+                    denominators: Mle::new(denominators.clone()),
+                }
+            })
+            .collect_vec();
+
+        let (gkr_proof, GkrArtifact { ood_point, .. }) = metrics_span("prove_gkr_time_ms", || {
+            gkr::prove_batch(
+                &mut ExtensionChallenger::<F, _>::new(challenger),
+                input_layers,
+            )
+        });
+
+        let gamma = challenger.sample_ext_element::<Challenge>();
+
+        let (after_challenge_trace_per_air, sum_per_air): (Vec<_>, Vec<_>) =
+            metrics_span("generate_perm_trace_time_ms", || {
+                fractions_per_air
+                    .iter()
+                    .map(|fractions| {
+                        if fractions.is_empty() {
+                            return (None, None);
+                        }
+                        let log_height = log2_strict_usize(fractions[0].0.len());
+                        let eq_point = &ood_point[ood_point.len() - log_height..];
+                        let (trace, sum) =
+                            Self::generate_after_challenge_trace(fractions, eq_point, gamma);
+                        (Some(trace), Some(sum))
+                    })
+                    .unzip()
+            });
+
+        // Challenger needs to observe what is exposed
+        for sum in sum_per_air.iter().flatten() {
+            challenger.observe_slice(sum.as_base_slice());
+        }
+        commit_phase(challenger, &after_challenge_trace_per_air);
+
+        let exposed_values_per_air = sum_per_air
+            .iter()
+            .map(|sum| sum.map(|sum| vec![sum]))
+            .collect_vec();
+
+        Some((
+            gkr_proof,
+            vec![RapPhaseProverData {
+                challenges: vec![alpha, beta, gamma],
+                after_challenge_trace_per_air,
+                exposed_values_per_air,
+            }],
+        ))
+    }
+
+    fn partially_verify<Commitment: Clone>(
+        &self,
+        challenger: &mut Challenger,
+        partial_proof: Option<&Self::PartialProof>,
+        public_lookup_tables: &[PublicLookupTable<F>],
+        num_interactions_per_air: &[usize],
+        log_trace_height_per_air: &[usize],
+        exposed_values_per_phase_per_air: &[Vec<Vec<Challenge>>],
+        commitment_per_phase: &[Commitment],
+        _permutation_opened_values: &[Vec<Vec<Vec<Challenge>>>],
+    ) -> (RapPhaseVerifierData<Challenge>, Result<(), Self::Error>)
+    where
+        Challenger: CanObserve<Commitment>,
+    {
+        // Keygen rejects public lookup tables, but a key may be altered afterwards. The rest of
+        // the phase is still verified, so that the verifier data is complete.
+        let tables_result = if public_lookup_tables.is_empty() {
+            Ok(())
+        } else {
+            Err(GkrLogUpError::PublicLookupTablesUnsupported)
+        };
+        if exposed_values_per_phase_per_air
+            .iter()
+            .all(|exposed_values_per_phase| exposed_values_per_phase.is_empty())
+        {
+            return (
+                RapPhaseVerifierData {
+                    challenges_per_phase: vec![],
+                    eq_points_per_air: vec![],
+                },
+                tables_result,
+            );
+        }
+
+        let [alpha, beta]: [Challenge; 2] =
+            array::from_fn(|_| challenger.sample_ext_element::<Challenge>());
+
+        let num_instances = num_interactions_per_air.iter().sum::<usize>();
+        let gkr_result = partial_proof
+            .ok_or(GkrLogUpError::MissingProof)
+            .and_then(|proof| {
+                let artifact = gkr::partially_verify_batch(
+                    vec![Gate::LogUp; num_instances],
+                    proof,
+                    &mut ExtensionChallenger::<F, _>::new(challenger),
+                )
+                .map_err(|err| GkrLogUpError::InvalidGkrProof(err.to_string()))?;
+                Ok((proof, artifact))
+            });
+
+        let gamma = challenger.sample_ext_element::<Challenge>();
+
+        for exposed_values_per_phase in exposed_values_per_phase_per_air.iter() {
+            if let Some(exposed_values) = exposed_values_per_phase.first() {
+                for exposed_value in exposed_values {
+                    challenger.observe_slice(exposed_value.as_base_slice());
+                }
+            }
+        }
+
+        challenger.observe(commitment_per_phase[0].clone());
+
+        let result = gkr_result.and_then(|(proof, artifact)| {
+            Self::check_gkr_claims(
+                proof,
+                &artifact,
+                gamma,
+                num_interactions_per_air,
+                log_trace_height_per_air,
+                exposed_values_per_phase_per_air,
+            )?;
+            let eq_points_per_air = zip(num_interactions_per_air, log_trace_height_per_air)
+                .map(|(&num_interactions, &log_height)| {
+                    (num_interactions != 0).then(|| {
+                        artifact.ood_point[artifact.ood_point.len() - log_height..].to_vec()
+                    })
+                })
+                .collect_vec();
+            Ok(eq_points_per_air)
+        });
+        let (eq_points_per_air, result) = match result {
+            Ok(eq_points_per_air) => (eq_points_per_air, Ok(())),
+            Err(err) => (vec![], Err(err)),
+        };
+
+        let verifier_data = RapPhaseVerifierData {
+            challenges_per_phase: vec![vec![alpha, beta, gamma]],
+            eq_points_per_air,
+        };
+        (verifier_data, tables_result.and(result))
+    }
+
+    fn is_sum_mismatch(&self, error: &GkrLogUpError) -> bool {
+        matches!(error, GkrLogUpError::NonZeroSum)
+    }
+}
+
+pub const GKR_LU_NUM_CHALLENGES: usize = 3;
+pub const GKR_LU_NUM_EXPOSED_VALUES: usize = 1;
+/// Width of the after challenge trace of an AIR with interactions: the `eq` column and the
+/// running sum.
+pub const GKR_LU_WIDTH: usize = 2;
+
+impl<F, Challenge, Challenger> GkrLogUpPhase<F, Challenge, Challenger>
+where
+    F: Field,
+    Challenge: ExtensionField<F>,
+    Challenger: FieldChallenger<F>,
+{
+    /// Returns the numerators and denominators of the log-up fractions of each interaction over
+    /// the rows of the trace. These are the input layers of the GKR instances of the AIR.
+    fn generate_fractions(
+        all_interactions: &[SymbolicInteraction<F>],
+        trace_view: &PairTraceView<F>,
+        alpha: Challenge,
+        beta: Challenge,
+    ) -> Vec<Fractions<Challenge>> {
+        if all_interactions.is_empty() {
+            return vec![];
+        }
+        let alphas = generate_rlc_elements(alpha, all_interactions);
+        let betas = generate_betas(beta, all_interactions);
+
+        let height = 1usize << trace_view.log_trace_height;
+        let preprocessed = trace_view.preprocessed.as_ref().map(|m| m.as_view());
+        let partitioned_main = trace_view
+            .partitioned_main
+            .iter()
+            .map(|m| m.as_view())
+            .collect_vec();
+        let fractions_per_row: Vec<Vec<(Challenge, Challenge)>> = (0..height)
+            .into_par_iter()
+            .map(|local_index| {
+                let evaluator = Evaluator {
+                    preprocessed: &preprocessed,
+                    partitioned_main: &partitioned_main,
+                    public_values: &trace_view.public_values,
+                    periodic_columns: &trace_view.periodic_columns,
+                    height,
+                    local_index,
+                };
+                all_interactions
+                    .iter()
+                    .map(|interaction| {
+                        let mut numerator =
+                            Challenge::from_base(evaluator.eval_expr(&interaction.count));
+                        if interaction.interaction_type == InteractionType::Receive {
+                            numerator = -numerator;
+                        }
+                        let denominator = zip(&interaction.fields, &betas)
+                            .fold(alphas[interaction.bus_index], |acc, (field, &beta)| {
+                                acc + beta * evaluator.eval_expr(field)
+                            });
+                        (numerator, denominator)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        (0..all_interactions.len())
+            .map(|i| fractions_per_row.iter().map(|row| row[i]).unzip())
+            .collect()
+    }
+
+    /// Returns the after challenge trace with columns `[eq, acc]` and the sum exposed to the
+    /// verifier, where `eq` is `eq(eq_point, row)` and `acc` is the sum of `eq * g` over the
+    /// previous rows, see [eval_gkr_log_up_phase].
+    fn generate_after_challenge_trace(
+        fractions: &[Fractions<Challenge>],
+        eq_point: &[Challenge],
+        gamma: Challenge,
+    ) -> (RowMajorMatrix<Challenge>, Challenge) {
+        let height = fractions[0].0.len();
+        let gammas = gamma.powers().take(2 * fractions.len()).collect_vec();
+        let eq_evals = hypercube_eq_evals(eq_point, Challenge::ONE);
+
+        let mut values = Challenge::zero_vec(height * GKR_LU_WIDTH);
+        let mut sum = Challenge::ZERO;
+        for (row, (row_values, &eq)) in values
+            .chunks_exact_mut(GKR_LU_WIDTH)
+            .zip(&eq_evals)
+            .enumerate()
+        {
+            let g: Challenge = zip(fractions, gammas.chunks_exact(2))
+                .map(|((numerators, denominators), gammas)| {
+                    gammas[0] * numerators[row] + gammas[1] * denominators[row]
+                })
+                .sum();
+            row_values[0] = eq;
+            row_values[1] = sum;
+            sum += eq * g;
+        }
+        (RowMajorMatrix::new(values, GKR_LU_WIDTH), sum)
+    }
+
+    /// Checks that the fractions of all GKR instances sum to zero, and that the sum exposed by
+    /// each AIR is the combination of the claims of its instances at the GKR point.
+    fn check_gkr_claims(
+        proof: &GkrBatchProof<Challenge>,
+        artifact: &GkrArtifact<Challenge>,
+        gamma: Challenge,
+        num_interactions_per_air: &[usize],
+        log_trace_height_per_air: &[usize],
+        exposed_values_per_phase_per_air: &[Vec<Vec<Challenge>>],
+    ) -> Result<(), GkrLogUpError> {
+        let expected_n_variables = zip(num_interactions_per_air, log_trace_height_per_air)
+            .flat_map(|(&num_interactions, &log_height)| vec![log_height; num_interactions])
+            .collect_vec();
+        if artifact.n_variables_by_instance != expected_n_variables {
+            return Err(GkrLogUpError::InstanceMismatch);
+        }
+
+        let sum = proof
+            .output_claims_by_instance
+            .iter()
+            .map(|claims| match claims[..] {
+                [numerator, denominator] => Ok(Fraction::new(numerator, denominator)),
+                _ => Err(GkrLogUpError::InstanceMismatch),
+            })
+            .sum::<Result<Fraction<Challenge>, _>>()?;
+        if !sum.is_zero() {
+            return Err(GkrLogUpError::NonZeroSum);
+        }
+
+        let mut claims_by_instance = artifact.claims_to_verify_by_instance.iter();
+        for (air_idx, (&num_interactions, exposed_values_per_phase)) in
+            zip(num_interactions_per_air, exposed_values_per_phase_per_air).enumerate()
+        {
+            if num_interactions == 0 {
+                continue;
+            }
+            let exposed_sum = exposed_values_per_phase
+                .first()
+                .and_then(|exposed_values| exposed_values.first());
+            let claimed_sum = claims_by_instance
+                .by_ref()
+                .take(num_interactions)
+                .zip(gamma.powers().step_by(2))
+                .map(|(claims, gamma_pow)| gamma_pow * (claims[0] + gamma * claims[1]))
+                .sum::<Challenge>();
+            if exposed_sum != Some(&claimed_sum) {
+                return Err(GkrLogUpError::ClaimMismatch { air_idx });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Evaluates at `zeta` the polynomial of degree less than the size of `domain` whose value at the
+/// `i`-th point of `domain` is `eq(point, i)`, with the bits of `i` in big-endian order.
+///
+/// This takes time linear in the size of `domain`. The values `eq(point, i)` factor over the bits
+/// of `i`, but the Lagrange basis of a multiplicative coset does not: its `i`-th polynomial at
+/// `zeta` is `Z(zeta) * x_i / (height * (zeta - x_i))`, and summing it against a product over the
+/// bits of `i` is a Fourier transform mixing all of them. There is no product formula in the
+/// coordinates of `point`, unlike for the multilinear extension of the column. The verifier only
+/// calls this after checking the trace height against the bound of the verifying key.
+pub fn eval_eq_column<D, Challenge>(domain: &D, point: &[Challenge], zeta: Challenge) -> Challenge
+where
+    D: PolynomialSpace,
+    Challenge: ExtensionField<D::Val>,
+{
+    let height = domain.size();
+    assert_eq!(height, 1 << point.len());
+    let eq_evals = hypercube_eq_evals(point, Challenge::ONE);
+    // On the coset `x_0 * <g>`, the Lagrange basis polynomial of the point `x_i` is
+    // `Z(X) * x_i / (height * (X - x_i))`, where `Z` is the vanishing polynomial divided by `x_0^height`.
+    let points = std::iter::successors(Some(domain.first_point()), |&x| domain.next_point(x))
+        .take(height)
+        .collect_vec();
+    let denominators = points
+        .iter()
+        .map(|&x| zeta - Challenge::from_base(x))
+        .collect_vec();
+    let sum: Challenge = izip!(
+        eq_evals,
+        points,
+        batch_multiplicative_inverse(&denominators)
+    )
+    .map(|(eq, x, inverse)| eq * inverse * x)
+    .sum();
+    sum * domain.zp_at_point(zeta) * D::Val::from_canonical_usize(height).inverse()
+}
+
+/// The after challenge phase of the GKR log-up argument, with columns `[eq, acc]`, see the
+/// [module documentation](self).
+///
+/// The single constraint `acc' - acc = eq * g - is_last_row * sum` is enforced on every row,
+/// including the last one whose next row is the first. Summing it over the rows shows that
+/// `sum` is the sum of `eq * g` over the trace, which the verifier checks against the GKR
+/// claims. The constraint has degree one more than the maximum degree of the fields and counts
+/// of the interactions.
+pub fn eval_gkr_log_up_phase<AB>(builder: &mut AB)
+where
+    AB: InteractionBuilder + PermutationAirBuilderWithExposedValues,
+{
+    let exposed_values = builder.permutation_exposed_values();
+    assert_eq!(
+        exposed_values.len(),
+        GKR_LU_NUM_EXPOSED_VALUES,
+        "Should have one exposed value for the sum"
+    );
+    let sum: AB::ExprEF = exposed_values[0].into();
+
+    let rand_elems = builder.permutation_randomness();
+    let alpha: AB::ExprEF = rand_elems[0].into();
+    let beta: AB::ExprEF = rand_elems[1].into();
+    let gamma: AB::ExprEF = rand_elems[2].into();
+
+    let perm = builder.permutation();
+    let (perm_local, perm_next) = (perm.row_slice(0), perm.row_slice(1));
+    let perm_local: &[AB::VarEF] = (*perm_local).borrow();
+    let perm_next: &[AB::VarEF] = (*perm_next).borrow();
+    debug_assert_eq!(perm_local.len(), GKR_LU_WIDTH);
+    let eq: AB::ExprEF = perm_local[0].into();
+    let acc: AB::ExprEF = perm_local[1].into();
+    let acc_next: AB::ExprEF = perm_next[1].into();
+
+    let all_interactions = builder.all_interactions().to_vec();
+    let alphas = generate_rlc_elements(alpha, &all_interactions);
+    let betas = generate_betas(beta, &all_interactions);
+
+    let mut gamma_powers = gamma.powers();
+    let mut g = AB::ExprEF::ZERO;
+    for interaction in &all_interactions {
+        assert!(!interaction.fields.is_empty(), "fields should not be empty");
+        let mut numerator: AB::ExprEF = interaction.count.clone().into();
+        if interaction.interaction_type == InteractionType::Receive {
+            numerator = -numerator;
+        }
+        let mut denominator = alphas[interaction.bus_index].clone();
+        for (field, beta) in interaction.fields.iter().zip(betas.iter()) {
+            denominator += beta.clone() * field.clone();
+        }
+        g += gamma_powers.next().unwrap() * numerator;
+        g += gamma_powers.next().unwrap() * denominator;
+    }
+
+    let is_last_row = builder.is_last_row();
+    builder.assert_eq_ext(acc_next - acc, eq * g - sum * is_last_row);
+}
+
+/// Challenger over the extension field, which observes and samples extension field elements
+/// through a challenger over the base field. The GKR and sum-check protocols are implemented over
+/// a single field.
+struct ExtensionChallenger<'a, F, Challenger> {
+    inner: &'a mut Challenger,
+    _marker: PhantomData<F>,
+}
+
+impl<'a, F, Challenger> ExtensionChallenger<'a, F, Challenger> {
+    fn new(inner: &'a mut Challenger) -> Self {
+        Self {
+            inner,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F, Challenge, Challenger> CanObserve<Challenge> for ExtensionChallenger<'_, F, Challenger>
+where
+    F: Field,
+    Challenge: ExtensionField<F>,
+    Challenger: FieldChallenger<F>,
+{
+    fn observe(&mut self, value: Challenge) {
+        self.inner.observe_ext_element(value);
+    }
+}
+
+impl<F, Challenge, Challenger> CanSample<Challenge> for ExtensionChallenger<'_, F, Challenger>
+where
+    F: Field,
+    Challenge: ExtensionField<F>,
+    Challenger: FieldChallenger<F>,
+{
+    fn sample(&mut self) -> Challenge {
+        self.inner.sample_ext_element()
+    }
+}
+
+impl<F, Challenger> CanSampleBits<usize> for ExtensionChallenger<'_, F, Challenger>
+where
+    F: Field,
+    Challenger: FieldChallenger<F>,
+{
+    fn sample_bits(&mut self, bits: usize) -> usize {
+        self.inner.sample_bits(bits)
+    }
+}
+
+impl<F, Challenge, Challenger> FieldChallenger<Challenge> for ExtensionChallenger<'_, F, Challenger>
+where
+    F: Field,
+    Challenge: ExtensionField<F>,
+    Challenger: FieldChallenger<F>,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use p3_baby_bear::BabyBear;
+    use p3_commit::TwoAdicMultiplicativeCoset;
+    use p3_field::{extension::BinomialExtensionField, FieldExtensionAlgebra};
+    use rand::Rng;
+
+    use super::*;
+
+    type F = BabyBear;
+    type EF = BinomialExtensionField<BabyBear, 4>;
+
+    #[test]
+    fn test_eval_eq_column() {
+        let mut rng = rand::thread_rng();
+        let point: Vec<EF> = (0..3).map(|_| rng.gen()).collect();
+        let zeta: EF = rng.gen();
+        let eq_evals = hypercube_eq_evals(&point, EF::ONE);
+        for shift in [F::ONE, F::GENERATOR] {
+            let domain = TwoAdicMultiplicativeCoset { log_n: 3, shift };
+            let points = std::iter::successors(Some(shift), |&x| domain.next_point(x))
+                .take(8)
+                .collect_vec();
+            // Lagrange interpolation from the definition.
+            let expected: EF = zip(&eq_evals, &points)
+                .enumerate()
+                .map(|(i, (&eq, &x_i))| {
+                    let basis: EF = points
+                        .iter()
+                        .enumerate()
+                        .filter(|&(j, _)| j != i)
+                        .map(|(_, &x_j)| (zeta - EF::from_base(x_j)) / EF::from_base(x_i - x_j))
+                        .product();
+                    eq * basis
+                })
+                .sum();
+            assert_eq!(eval_eq_column(&domain, &point, zeta), expected);
+        }
+    }
+}
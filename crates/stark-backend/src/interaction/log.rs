@@ -0,0 +1,152 @@
+//! Log of the messages sent and received on the buses, recorded by the debug prover path.
+//!
+//! Recording is opt-in: [start_interaction_log] starts a log for the current thread, which every
+//! later run of the debug builder on this thread appends to, until [take_interaction_log].
+
+use std::{any::Any, cell::RefCell, collections::BTreeMap};
+
+use p3_field::Field;
+use p3_matrix::dense::RowMajorMatrixView;
+
+use super::{debug::for_each_row_interaction, InteractionType, SymbolicInteraction};
+
+/// Default maximum number of messages logged on each bus.
+pub const DEFAULT_MAX_ENTRIES_PER_BUS: usize = 10_000;
+
+thread_local! {
+    static INTERACTION_LOG: RefCell<Option<Box<dyn Any>>> = const { RefCell::new(None) };
+}
+
+/// A message sent or received by an AIR on a single row.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LoggedInteraction<F> {
+    pub air_name: String,
+    pub row: usize,
+    pub bus_index: usize,
+    pub interaction_type: InteractionType,
+    pub count: F,
+    pub fields: Vec<F>,
+}
+
+/// The messages of each bus, in the order they were recorded: by AIR, then row, then
+/// interaction. Messages with a zero count are not logged.
+#[derive(Clone, Debug)]
+pub struct InteractionLog<F> {
+    max_entries_per_bus: usize,
+    at_bus: BTreeMap<usize, Vec<LoggedInteraction<F>>>,
+    num_dropped: BTreeMap<usize, usize>,
+}
+
+impl<F: Field> InteractionLog<F> {
+    /// An empty log keeping the first `max_entries_per_bus` messages of each bus.
+    pub fn new(max_entries_per_bus: usize) -> Self {
+        Self {
+            max_entries_per_bus,
+            at_bus: BTreeMap::new(),
+            num_dropped: BTreeMap::new(),
+        }
+    }
+
+    /// Appends `entry` to the messages of its bus, unless the bus is full.
+    pub fn record(&mut self, entry: LoggedInteraction<F>) {
+        let bus_index = entry.bus_index;
+        let messages = self.at_bus.entry(bus_index).or_default();
+        if messages.len() < self.max_entries_per_bus {
+            messages.push(entry);
+        } else {
+            *self.num_dropped.entry(bus_index).or_default() += 1;
+        }
+    }
+
+    /// The logged messages on bus `bus_index`.
+    pub fn messages(&self, bus_index: usize) -> &[LoggedInteraction<F>] {
+        self.at_bus.get(&bus_index).map_or(&[], Vec::as_slice)
+    }
+
+    /// All logged messages, ordered by bus.
+    pub fn iter(&self) -> impl Iterator<Item = &LoggedInteraction<F>> {
+        self.at_bus.values().flatten()
+    }
+
+    /// The logged messages on bus `bus_index` satisfying `predicate`.
+    pub fn find(
+        &self,
+        bus_index: usize,
+        predicate: impl Fn(&LoggedInteraction<F>) -> bool,
+    ) -> Vec<&LoggedInteraction<F>> {
+        self.messages(bus_index)
+            .iter()
+            .filter(|entry| predicate(entry))
+            .collect()
+    }
+
+    /// The logged messages on bus `bus_index` whose field `field_idx` is `value`.
+    pub fn with_field(
+        &self,
+        bus_index: usize,
+        field_idx: usize,
+        value: F,
+    ) -> Vec<&LoggedInteraction<F>> {
+        self.find(bus_index, |entry| {
+            entry.fields.get(field_idx) == Some(&value)
+        })
+    }
+
+    /// Number of messages on bus `bus_index` that were not logged because the bus was full.
+    pub fn num_dropped(&self, bus_index: usize) -> usize {
+        self.num_dropped.get(&bus_index).copied().unwrap_or(0)
+    }
+}
+
+/// Starts logging the messages of the debug prover path on the current thread, keeping the first
+/// `max_entries_per_bus` messages of each bus. Any log already started is discarded.
+pub fn start_interaction_log<F: Field>(max_entries_per_bus: usize) {
+    INTERACTION_LOG.with(|log| {
+        *log.borrow_mut() = Some(Box::new(InteractionLog::<F>::new(max_entries_per_bus)));
+    });
+}
+
+/// Stops logging on the current thread and returns the log, if one was started with field `F`.
+pub fn take_interaction_log<F: Field>() -> Option<InteractionLog<F>> {
+    INTERACTION_LOG.with(|log| {
+        let log = log.borrow_mut().take()?;
+        log.downcast().ok().map(|log| *log)
+    })
+}
+
+/// Appends the messages of an AIR to the log of the current thread, if one was started.
+pub(crate) fn record_interactions<F: Field>(
+    air_name: &str,
+    interactions: &[SymbolicInteraction<F>],
+    preprocessed: &Option<RowMajorMatrixView<F>>,
+    partitioned_main: &[RowMajorMatrixView<F>],
+    public_values: &[F],
+    periodic_columns: &[Vec<F>],
+) {
+    INTERACTION_LOG.with(|log| {
+        let mut log = log.borrow_mut();
+        let Some(log) = log
+            .as_mut()
+            .and_then(|log| log.downcast_mut::<InteractionLog<F>>())
+        else {
+            return;
+        };
+        for_each_row_interaction(
+            interactions,
+            preprocessed,
+            partitioned_main,
+            public_values,
+            periodic_columns,
+            |row, interaction, fields, count| {
+                log.record(LoggedInteraction {
+                    air_name: air_name.to_string(),
+                    row,
+                    bus_index: interaction.bus_index,
+                    interaction_type: interaction.interaction_type,
+                    count,
+                    fields,
+                });
+            },
+        );
+    });
+}
@@ -1,4 +1,4 @@
-use std::fmt::Debug;
+use std::fmt::{Debug, Display};
 
 use p3_air::AirBuilder;
 use p3_challenger::CanObserve;
@@ -7,15 +7,27 @@ use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
 use crate::{
     air_builders::symbolic::{symbolic_expression::SymbolicExpression, SymbolicConstraints},
-    interaction::fri_log_up::{STARK_LU_NUM_CHALLENGES, STARK_LU_NUM_EXPOSED_VALUES},
+    interaction::{
+        fri_log_up::{FriLogUpParams, STARK_LU_NUM_CHALLENGES, STARK_LU_NUM_EXPOSED_VALUES},
+        gkr_log_up::{GKR_LU_NUM_CHALLENGES, GKR_LU_NUM_EXPOSED_VALUES},
+        public_lookup::PublicLookupTable,
+    },
     prover::types::PairView,
 };
 
 /// Interaction debugging tools
+pub mod bus;
 pub mod debug;
 pub mod fri_log_up;
+pub mod gkr_log_up;
+#[cfg(feature = "interaction-log")]
+pub mod log;
+pub mod prune;
+pub mod public_lookup;
 pub mod rap;
 pub mod trace;
+#[cfg(test)]
+pub(crate) mod two_phase;
 mod utils;
 
 #[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
@@ -92,6 +104,11 @@ pub struct RapPhaseProverData<Challenge> {
 pub struct RapPhaseVerifierData<Challenge> {
     /// Challenges from the challenger in this phase that determine RAP constraints and exposed values.
     pub challenges_per_phase: Vec<Vec<Challenge>>,
+
+    /// For each AIR, the point `r` such that the first column of its after challenge trace must
+    /// be `eq(r, row)`, if any. The verifier checks the opened value of this column, see
+    /// [gkr_log_up::eval_eq_column]. Empty if no AIR has such a column.
+    pub eq_points_per_air: Vec<Option<Vec<Challenge>>>,
 }
 
 #[derive(Debug)]
@@ -111,10 +128,12 @@ pub struct RapPhaseShape {
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[repr(u8)]
 pub enum RapPhaseSeqKind {
-    // GkrLogUp,
     /// Up to one phase with prover/verifier given by [[fri_log_up::FriLogUpPhase]] and
     /// constraints given by [[fri_log_up::eval_fri_log_up_phase]].
     FriLogUp,
+    /// Up to one phase with prover/verifier given by [[gkr_log_up::GkrLogUpPhase]] and
+    /// constraints given by [[gkr_log_up::eval_gkr_log_up_phase]].
+    GkrLogUp,
 }
 
 impl RapPhaseSeqKind {
@@ -125,10 +144,52 @@ impl RapPhaseSeqKind {
                 num_exposed_values: STARK_LU_NUM_EXPOSED_VALUES,
                 extra_opening_rots: vec![],
             }],
+            RapPhaseSeqKind::GkrLogUp => vec![RapPhaseShape {
+                num_challenges: GKR_LU_NUM_CHALLENGES,
+                num_exposed_values: GKR_LU_NUM_EXPOSED_VALUES,
+                extra_opening_rots: vec![],
+            }],
+        }
+    }
+}
+
+/// The challenge phases the constraints of a RAP are generated for, see [RapPhaseSeq::PHASES].
+/// Keys only record the [RapPhaseSeqKind], so phase sequences defined in tests have no encoding.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RapPhases {
+    Kind(RapPhaseSeqKind),
+    /// The FRI log-up phase, then the phase of [[two_phase::TwoPhase]], to test multiple
+    /// challenge phases.
+    #[cfg(test)]
+    TestTwoPhase,
+}
+
+impl RapPhases {
+    /// The kind recorded in keys. A sequence defined in tests records the kind of its first
+    /// phase.
+    pub fn kind(&self) -> RapPhaseSeqKind {
+        match self {
+            RapPhases::Kind(kind) => *kind,
+            #[cfg(test)]
+            RapPhases::TestTwoPhase => RapPhaseSeqKind::FriLogUp,
+        }
+    }
+
+    pub fn shape(&self) -> Vec<RapPhaseShape> {
+        match self {
+            RapPhases::Kind(kind) => kind.shape(),
+            #[cfg(test)]
+            RapPhases::TestTwoPhase => two_phase::shape(),
         }
     }
 }
 
+impl From<RapPhaseSeqKind> for RapPhases {
+    fn from(kind: RapPhaseSeqKind) -> Self {
+        RapPhases::Kind(kind)
+    }
+}
+
 /// Defines a particular protocol for the "after challenge" phase in a RAP.
 ///
 /// A [RapPhaseSeq] is defined by the proving and verifying methods implemented in this trait,
@@ -137,9 +198,12 @@ pub trait RapPhaseSeq<F, Challenge, Challenger> {
     type PartialProof: Clone + Serialize + DeserializeOwned;
     /// Preprocessed data necessary for the RAP partial proving
     type PartialProvingKey: Clone + Serialize + DeserializeOwned;
-    type Error: Debug;
+    type Error: Debug + Display;
 
     const ID: RapPhaseSeqKind;
+    /// The phases the constraints of the AIRs are generated for, those of [ID](Self::ID) unless
+    /// the sequence is only defined in tests.
+    const PHASES: RapPhases = RapPhases::Kind(Self::ID);
 
     /// The protocol parameters for the challenge phases may depend on the AIR constraints.
     fn generate_pk_per_air(
@@ -148,32 +212,49 @@ pub trait RapPhaseSeq<F, Challenge, Challenger> {
         max_constraint_degree: usize,
     ) -> Vec<Self::PartialProvingKey>;
 
+    /// The parameters of the FRI log-up constraints, for the phases that use them.
+    fn log_up_params(&self) -> FriLogUpParams {
+        FriLogUpParams::default()
+    }
+
     /// Partially prove the challenge phases,
     ///
     /// Samples challenges, generates after challenge traces and exposed values, and proves any
-    /// extra-STARK part of the protocol.
+    /// extra-STARK part of the protocol. Returns the data of each challenge phase, in order.
+    ///
+    /// The after challenge traces of each phase must be passed to `commit_phase` before the
+    /// challenges of the next phase are sampled. It commits to the traces and observes the
+    /// commitment with the challenger.
     ///
     /// "Partial" refers to the fact that some STARK parts of the protocol---namely, the constraints
     /// on the after challenge traces returned in `RapPhaseProverData`---are handled external to
     /// this function.
+    ///
+    /// The `public_lookup_tables` receive messages on their buses, outside of any AIR.
     fn partially_prove(
         &self,
         challenger: &mut Challenger,
         constraints_per_air: &[&SymbolicConstraints<F>],
         params_per_air: &[&Self::PartialProvingKey],
+        public_lookup_tables: &[PublicLookupTable<F>],
         trace_view_per_air: &[PairTraceView<F>],
-    ) -> Option<(Self::PartialProof, RapPhaseProverData<Challenge>)>;
+        commit_phase: &mut impl FnMut(&mut Challenger, &[Option<RowMajorMatrix<Challenge>>]),
+    ) -> Option<(Self::PartialProof, Vec<RapPhaseProverData<Challenge>>)>;
 
     /// Partially verifies the challenge phases.
     ///
     /// Assumes the shape of `exposed_values_per_air_per_phase` is verified externally.
     ///
-    /// An implementation of this function must sample challenges for the challenge phases and then
-    /// observe the exposed values and commitment.
+    /// An implementation of this function must, for each challenge phase in order, sample the
+    /// challenges of the phase and then observe its exposed values and commitment.
+    #[allow(clippy::too_many_arguments)]
     fn partially_verify<Commitment: Clone>(
         &self,
         challenger: &mut Challenger,
         partial_proof: Option<&Self::PartialProof>,
+        public_lookup_tables: &[PublicLookupTable<F>],
+        num_interactions_per_air: &[usize],
+        log_trace_height_per_air: &[usize],
         exposed_values_per_air_per_phase: &[Vec<Vec<Challenge>>],
         commitments_per_phase: &[Commitment],
         // per commitment, per matrix, per rotation, per column
@@ -181,6 +262,13 @@ pub trait RapPhaseSeq<F, Challenge, Challenger> {
     ) -> (RapPhaseVerifierData<Challenge>, Result<(), Self::Error>)
     where
         Challenger: CanObserve<Commitment>;
+
+    /// Whether `error` of [partially_verify](Self::partially_verify) is the failure of the final
+    /// check that the log-up sums of the AIRs cancel out, which the verifier reports with a
+    /// [LogUpSumReport](crate::verifier::LogUpSumReport).
+    fn is_sum_mismatch(&self, _error: &Self::Error) -> bool {
+        false
+    }
 }
 
 type PairTraceView<'a, F> = PairView<&'a RowMajorMatrix<F>, F>;
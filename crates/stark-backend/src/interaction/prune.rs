@@ -0,0 +1,134 @@
+//! Removal of the interactions that do not contribute to the bus argument.
+
+use p3_field::Field;
+use serde::{Deserialize, Serialize};
+
+use super::SymbolicInteraction;
+use crate::air_builders::symbolic::symbolic_expression::SymbolicExpression;
+
+/// The interactions of an AIR removed at keygen.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrunedInteractions {
+    /// Number of interactions dropped because their count is the constant zero.
+    pub num_zero_count: usize,
+    /// Number of interactions merged into an earlier interaction with the same bus, fields and
+    /// type.
+    pub num_duplicates: usize,
+}
+
+impl PrunedInteractions {
+    pub fn total(&self) -> usize {
+        self.num_zero_count + self.num_duplicates
+    }
+}
+
+/// Drops the interactions whose count is the constant zero and merges the interactions with the
+/// same bus, fields and type into the first of them, by summing their counts. The sum of the
+/// messages on each bus is unchanged.
+pub(crate) fn prune_interactions<F: Field>(
+    interactions: Vec<SymbolicInteraction<F>>,
+) -> (Vec<SymbolicInteraction<F>>, PrunedInteractions) {
+    let mut pruned = PrunedInteractions::default();
+    let mut kept: Vec<SymbolicInteraction<F>> = Vec::with_capacity(interactions.len());
+    for interaction in interactions {
+        if is_constant_zero(&interaction.count) {
+            pruned.num_zero_count += 1;
+            continue;
+        }
+        // Expressions are compared structurally: their hash only covers the top node.
+        let duplicate = kept.iter_mut().find(|kept| {
+            kept.bus_index == interaction.bus_index
+                && kept.interaction_type == interaction.interaction_type
+                && kept.fields == interaction.fields
+        });
+        match duplicate {
+            Some(kept) => {
+                kept.count = kept.count.clone() + interaction.count;
+                pruned.num_duplicates += 1;
+            }
+            None => kept.push(interaction),
+        }
+    }
+    (kept, pruned)
+}
+
+/// Whether `expr` evaluates to zero on every row, as can be seen without knowing any variable.
+fn is_constant_zero<F: Field>(expr: &SymbolicExpression<F>) -> bool {
+    match expr {
+        SymbolicExpression::Constant(c) => c.is_zero(),
+        SymbolicExpression::Add { x, y, .. } | SymbolicExpression::Sub { x, y, .. } => {
+            is_constant_zero(x) && is_constant_zero(y)
+        }
+        SymbolicExpression::Neg { x, .. } => is_constant_zero(x),
+        SymbolicExpression::Mul { x, y, .. } => is_constant_zero(x) || is_constant_zero(y),
+        SymbolicExpression::Variable(_)
+        | SymbolicExpression::IsFirstRow
+        | SymbolicExpression::IsLastRow
+        | SymbolicExpression::IsTransition => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use p3_baby_bear::BabyBear;
+    use p3_field::FieldAlgebra;
+
+    use super::*;
+    use crate::{
+        air_builders::symbolic::symbolic_variable::{Entry, SymbolicVariable},
+        interaction::InteractionType,
+    };
+
+    type F = BabyBear;
+
+    fn var(index: usize) -> SymbolicExpression<F> {
+        SymbolicVariable::new(
+            Entry::Main {
+                part_index: 0,
+                offset: 0,
+            },
+            index,
+        )
+        .into()
+    }
+
+    fn interaction(
+        bus_index: usize,
+        field: usize,
+        count: SymbolicExpression<F>,
+        interaction_type: InteractionType,
+    ) -> SymbolicInteraction<F> {
+        SymbolicInteraction {
+            fields: vec![var(field)],
+            count,
+            bus_index,
+            interaction_type,
+        }
+    }
+
+    #[test]
+    fn test_prune_interactions() {
+        let zero = SymbolicExpression::Constant(F::ZERO);
+        let interactions = vec![
+            interaction(0, 0, var(1), InteractionType::Send),
+            interaction(0, 0, zero.clone() * var(1), InteractionType::Send),
+            interaction(0, 0, var(2), InteractionType::Send),
+            // Different type, bus or fields are not duplicates.
+            interaction(0, 0, var(2), InteractionType::Receive),
+            interaction(1, 0, var(2), InteractionType::Send),
+            interaction(0, 3, var(2), InteractionType::Send),
+            interaction(1, 0, zero, InteractionType::Receive),
+        ];
+        let (kept, pruned) = prune_interactions(interactions);
+        assert_eq!(
+            pruned,
+            PrunedInteractions {
+                num_zero_count: 2,
+                num_duplicates: 1,
+            }
+        );
+        assert_eq!(kept.len(), 4);
+        assert_eq!(kept[0].count, var(1) + var(2));
+        assert_eq!(kept[1].interaction_type, InteractionType::Receive);
+    }
+}
@@ -0,0 +1,109 @@
+//! Public lookup tables: small constant tables on a bus whose side of the log-up argument is
+//! evaluated by the verifier, instead of being proven by a table AIR.
+
+use std::{collections::HashMap, iter::zip};
+
+use itertools::Itertools;
+use p3_field::{batch_multiplicative_inverse, ExtensionField, Field, FieldAlgebra};
+use serde::{Deserialize, Serialize};
+
+use super::{debug::for_each_row_interaction, InteractionType, PairTraceView, SymbolicInteraction};
+
+/// A public table receiving the messages of a bus. Every message on the bus must be a row of the
+/// table, which receives each row with a multiplicity revealed by the prover.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PublicLookupTable<F> {
+    pub bus_index: usize,
+    /// The messages of the table, which all have the same number of fields.
+    pub rows: Vec<Vec<F>>,
+}
+
+impl<F: Field> PublicLookupTable<F> {
+    /// Number of fields of the messages of the table, 0 if the table has no rows.
+    pub fn arity(&self) -> usize {
+        self.rows.first().map_or(0, Vec::len)
+    }
+
+    /// Whether the table has rows, which all have the same number of fields, as keygen requires.
+    pub fn is_well_formed(&self) -> bool {
+        !self.rows.is_empty() && self.rows.iter().all(|row| row.len() == self.arity())
+    }
+
+    /// Returns the multiplicity of each row: the count of the messages sent on the bus with its
+    /// fields, minus the count of the messages received.
+    ///
+    /// ## Panics
+    /// If a message on the bus is not a row of the table.
+    pub(crate) fn multiplicities(
+        &self,
+        interactions_per_air: &[&[SymbolicInteraction<F>]],
+        trace_view_per_air: &[PairTraceView<F>],
+    ) -> Vec<F> {
+        let row_indices: HashMap<&[F], usize> = self
+            .rows
+            .iter()
+            .enumerate()
+            .map(|(i, row)| (row.as_slice(), i))
+            .collect();
+        let mut multiplicities = F::zero_vec(self.rows.len());
+        for (interactions, trace_view) in zip(interactions_per_air, trace_view_per_air) {
+            let interactions = interactions
+                .iter()
+                .filter(|interaction| interaction.bus_index == self.bus_index)
+                .cloned()
+                .collect_vec();
+            let preprocessed = trace_view.preprocessed.map(|m| m.as_view());
+            let partitioned_main = trace_view
+                .partitioned_main
+                .iter()
+                .map(|m| m.as_view())
+                .collect_vec();
+            for_each_row_interaction(
+                &interactions,
+                &preprocessed,
+                &partitioned_main,
+                &trace_view.public_values,
+                &trace_view.periodic_columns,
+                |row, interaction, fields, count| {
+                    let Some(&i) = row_indices.get(fields.as_slice()) else {
+                        panic!(
+                            "message {fields:?} on row {row} is not in the public lookup table of \
+                             bus {}",
+                            self.bus_index
+                        );
+                    };
+                    match interaction.interaction_type {
+                        InteractionType::Send => multiplicities[i] += count,
+                        InteractionType::Receive => multiplicities[i] -= count,
+                    }
+                },
+            );
+        }
+        multiplicities
+    }
+
+    /// Returns the log-up sum of the messages received by the table: the sum over the rows of
+    /// `multiplicity / (alpha^(bus_index + 1) + sum_j beta^j * row_j)`.
+    pub fn log_up_sum<Challenge: ExtensionField<F>>(
+        &self,
+        multiplicities: &[F],
+        alpha: Challenge,
+        beta: Challenge,
+    ) -> Challenge {
+        let bus_alpha = alpha.exp_u64(self.bus_index as u64 + 1);
+        let denoms = self
+            .rows
+            .iter()
+            .map(|row| {
+                bus_alpha
+                    + row
+                        .iter()
+                        .rev()
+                        .fold(Challenge::ZERO, |acc, &field| acc * beta + field)
+            })
+            .collect_vec();
+        zip(batch_multiplicative_inverse(&denoms), multiplicities)
+            .map(|(inverse, &multiplicity)| inverse * multiplicity)
+            .sum()
+    }
+}
@@ -3,10 +3,13 @@
 
 use p3_air::{Air, AirBuilder};
 
-use super::{InteractionBuilder, RapPhaseSeqKind, SymbolicInteraction};
+use super::{InteractionBuilder, RapPhaseSeqKind, RapPhases, SymbolicInteraction};
 use crate::{
-    interaction::fri_log_up::eval_fri_log_up_phase,
-    rap::{PermutationAirBuilderWithExposedValues, Rap},
+    interaction::{
+        fri_log_up::{eval_fri_log_up_phase, FriLogUpParams},
+        gkr_log_up::eval_gkr_log_up_phase,
+    },
+    rap::{MultiPhaseAirBuilder, Rap},
 };
 
 /// Used internally to select RAP phase evaluation function.
@@ -16,28 +19,43 @@ pub(crate) trait InteractionPhaseAirBuilder: InteractionBuilder {
     fn symbolic_interactions(&self) -> Vec<SymbolicInteraction<<Self as AirBuilder>::F>>;
     /// The maximum constraint degree allowed in a RAP.
     fn max_constraint_degree(&self) -> usize;
-    fn rap_phase_seq_kind(&self) -> RapPhaseSeqKind;
+    /// The parameters of the FRI log-up constraints of the RAP phase.
+    fn log_up_params(&self) -> FriLogUpParams;
+    fn rap_phases(&self) -> RapPhases;
 }
 
 impl<AB, A> Rap<AB> for A
 where
     A: Air<AB>,
-    AB: InteractionBuilder + PermutationAirBuilderWithExposedValues + InteractionPhaseAirBuilder,
+    AB: InteractionBuilder + MultiPhaseAirBuilder + InteractionPhaseAirBuilder,
 {
     fn eval(&self, builder: &mut AB) {
         // Constraints for the main trace:
         Air::eval(self, builder);
         builder.finalize_interactions();
         if builder.num_interactions() != 0 {
-            match builder.rap_phase_seq_kind() {
-                RapPhaseSeqKind::FriLogUp => {
+            match builder.rap_phases() {
+                RapPhases::Kind(RapPhaseSeqKind::FriLogUp) => {
                     let symbolic_interactions = builder.symbolic_interactions();
                     eval_fri_log_up_phase(
                         builder,
                         &symbolic_interactions,
                         builder.max_constraint_degree(),
+                        builder.log_up_params(),
                     );
                 }
+                RapPhases::Kind(RapPhaseSeqKind::GkrLogUp) => eval_gkr_log_up_phase(builder),
+                #[cfg(test)]
+                RapPhases::TestTwoPhase => {
+                    let symbolic_interactions = builder.symbolic_interactions();
+                    eval_fri_log_up_phase(
+                        builder,
+                        &symbolic_interactions,
+                        builder.max_constraint_degree(),
+                        builder.log_up_params(),
+                    );
+                    super::two_phase::eval_second_phase(builder);
+                }
             }
         }
     }
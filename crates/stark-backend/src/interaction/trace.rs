@@ -10,6 +10,7 @@ pub(super) struct Evaluator<'a, F: Field> {
     pub preprocessed: &'a Option<RowMajorMatrixView<'a, F>>,
     pub partitioned_main: &'a [RowMajorMatrixView<'a, F>],
     pub public_values: &'a [F],
+    pub periodic_columns: &'a [Vec<F>],
     pub height: usize,
     pub local_index: usize,
 }
@@ -30,6 +31,10 @@ impl<F: Field> SymbolicEvaluator<F, F> for Evaluator<'_, F> {
                 self.partitioned_main[part_index].get((n + offset) % height, index)
             }
             Entry::Public => self.public_values[index],
+            Entry::Periodic => {
+                let values = &self.periodic_columns[index];
+                values[n % values.len()]
+            }
             _ => unreachable!("There should be no after challenge variables"),
         }
     }
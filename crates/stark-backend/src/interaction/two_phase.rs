@@ -0,0 +1,381 @@
+//! A sequence of two challenge phases, to test AIRs with more than one challenge phase.
+//!
+//! The first phase is the FRI log-up phase. The second phase samples a challenge `gamma` and
+//! commits, for each AIR with interactions, to the column `t` with `t[i] = S * gamma^i`, where `S`
+//! is the cumulative sum of the AIR. It exposes the last value of `t`, which the verifier checks
+//! against `S * gamma^(height - 1)`.
+
+use itertools::{izip, Itertools};
+use p3_air::{AirBuilder, ExtensionBuilder};
+use p3_challenger::{CanObserve, FieldChallenger};
+use p3_field::{ExtensionField, Field, FieldAlgebra, FieldExtensionAlgebra};
+use p3_matrix::{dense::RowMajorMatrix, Matrix};
+use thiserror::Error;
+
+use super::{
+    fri_log_up::{
+        FriLogUpError, FriLogUpParams, FriLogUpPartialProof, FriLogUpPhase, FriLogUpProvingKey,
+    },
+    public_lookup::PublicLookupTable,
+    PairTraceView, RapPhaseProverData, RapPhaseSeq, RapPhaseSeqKind, RapPhaseShape,
+    RapPhaseVerifierData, RapPhases,
+};
+use crate::{
+    air_builders::symbolic::SymbolicConstraints,
+    interaction::fri_log_up::{STARK_LU_NUM_CHALLENGES, STARK_LU_NUM_EXPOSED_VALUES},
+    rap::MultiPhaseAirBuilder,
+};
+
+/// Width of the after challenge trace of the second phase.
+pub(crate) const SECOND_PHASE_WIDTH: usize = 1;
+
+pub(crate) fn shape() -> Vec<RapPhaseShape> {
+    vec![
+        RapPhaseShape {
+            num_challenges: STARK_LU_NUM_CHALLENGES,
+            num_exposed_values: STARK_LU_NUM_EXPOSED_VALUES,
+            extra_opening_rots: vec![],
+        },
+        RapPhaseShape {
+            num_challenges: 1,
+            num_exposed_values: 1,
+            extra_opening_rots: vec![],
+        },
+    ]
+}
+
+pub(crate) struct TwoPhase<F, Challenge, Challenger> {
+    log_up: FriLogUpPhase<F, Challenge, Challenger>,
+}
+
+impl<F, Challenge, Challenger> TwoPhase<F, Challenge, Challenger> {
+    pub fn new() -> Self {
+        Self {
+            log_up: FriLogUpPhase::new(FriLogUpParams::default()),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub(crate) enum TwoPhaseError {
+    #[error(transparent)]
+    LogUp(#[from] FriLogUpError),
+    #[error("last value of the second phase does not match the cumulative sum")]
+    WrongLastValue,
+}
+
+impl<F, Challenge, Challenger> RapPhaseSeq<F, Challenge, Challenger>
+    for TwoPhase<F, Challenge, Challenger>
+where
+    F: Field,
+    Challenge: ExtensionField<F>,
+    Challenger: FieldChallenger<F>,
+{
+    type PartialProof = FriLogUpPartialProof<F>;
+    type PartialProvingKey = FriLogUpProvingKey;
+    type Error = TwoPhaseError;
+    const ID: RapPhaseSeqKind = RapPhaseSeqKind::FriLogUp;
+    const PHASES: RapPhases = RapPhases::TestTwoPhase;
+
+    fn generate_pk_per_air(
+        &self,
+        symbolic_constraints_per_air: &[SymbolicConstraints<F>],
+        max_constraint_degree: usize,
+    ) -> Vec<Self::PartialProvingKey> {
+        self.log_up
+            .generate_pk_per_air(symbolic_constraints_per_air, max_constraint_degree)
+    }
+
+    fn partially_prove(
+        &self,
+        challenger: &mut Challenger,
+        constraints_per_air: &[&SymbolicConstraints<F>],
+        params_per_air: &[&Self::PartialProvingKey],
+        public_lookup_tables: &[PublicLookupTable<F>],
+        trace_view_per_air: &[PairTraceView<F>],
+        commit_phase: &mut impl FnMut(&mut Challenger, &[Option<RowMajorMatrix<Challenge>>]),
+    ) -> Option<(Self::PartialProof, Vec<RapPhaseProverData<Challenge>>)> {
+        let (partial_proof, mut phases) = self.log_up.partially_prove(
+            challenger,
+            constraints_per_air,
+            params_per_air,
+            public_lookup_tables,
+            trace_view_per_air,
+            commit_phase,
+        )?;
+
+        let gamma = challenger.sample_ext_element::<Challenge>();
+        let after_challenge_trace_per_air =
+            izip!(&phases[0].exposed_values_per_air, trace_view_per_air)
+                .map(|(exposed_values, trace_view)| {
+                    exposed_values.as_ref().map(|exposed_values| {
+                        let values = gamma
+                            .powers()
+                            .take(1 << trace_view.log_trace_height)
+                            .map(|power| exposed_values[0] * power)
+                            .collect();
+                        RowMajorMatrix::new(values, SECOND_PHASE_WIDTH)
+                    })
+                })
+                .collect_vec();
+        let exposed_values_per_air = after_challenge_trace_per_air
+            .iter()
+            .map(|trace| {
+                trace
+                    .as_ref()
+                    .map(|trace| vec![*trace.values.last().unwrap()])
+            })
+            .collect_vec();
+        for exposed_value in exposed_values_per_air.iter().flatten().flatten() {
+            challenger.observe_slice(exposed_value.as_base_slice());
+        }
+        commit_phase(challenger, &after_challenge_trace_per_air);
+
+        phases.push(RapPhaseProverData {
+            challenges: vec![gamma],
+            after_challenge_trace_per_air,
+            exposed_values_per_air,
+        });
+        Some((partial_proof, phases))
+    }
+
+    fn partially_verify<Commitment: Clone>(
+        &self,
+        challenger: &mut Challenger,
+        partial_proof: Option<&Self::PartialProof>,
+        public_lookup_tables: &[PublicLookupTable<F>],
+        num_interactions_per_air: &[usize],
+        log_trace_height_per_air: &[usize],
+        exposed_values_per_phase_per_air: &[Vec<Vec<Challenge>>],
+        commitment_per_phase: &[Commitment],
+        permutation_opened_values: &[Vec<Vec<Vec<Challenge>>>],
+    ) -> (RapPhaseVerifierData<Challenge>, Result<(), Self::Error>)
+    where
+        Challenger: CanObserve<Commitment>,
+    {
+        let first_phase_exposed_values = exposed_values_per_phase_per_air
+            .iter()
+            .map(|exposed_values_per_phase| {
+                exposed_values_per_phase.iter().take(1).cloned().collect()
+            })
+            .collect_vec();
+        let (mut verifier_data, result) = self.log_up.partially_verify(
+            challenger,
+            partial_proof,
+            public_lookup_tables,
+            num_interactions_per_air,
+            log_trace_height_per_air,
+            &first_phase_exposed_values,
+            &commitment_per_phase[..commitment_per_phase.len().min(1)],
+            permutation_opened_values,
+        );
+        if verifier_data.challenges_per_phase.is_empty() {
+            return (verifier_data, result.map_err(TwoPhaseError::LogUp));
+        }
+
+        let gamma = challenger.sample_ext_element::<Challenge>();
+        for exposed_values_per_phase in exposed_values_per_phase_per_air {
+            for exposed_value in exposed_values_per_phase.iter().skip(1).flatten() {
+                challenger.observe_slice(exposed_value.as_base_slice());
+            }
+        }
+        challenger.observe(commitment_per_phase[1].clone());
+        verifier_data.challenges_per_phase.push(vec![gamma]);
+
+        let last_values_match = izip!(exposed_values_per_phase_per_air, log_trace_height_per_air)
+            .all(|(exposed_values_per_phase, &log_height)| {
+                match exposed_values_per_phase.as_slice() {
+                    [] => true,
+                    [sums, last_values] => {
+                        last_values[0] == sums[0] * gamma.exp_u64((1 << log_height) - 1)
+                    }
+                    _ => false,
+                }
+            });
+        let result = result.map_err(TwoPhaseError::LogUp).and_then(|()| {
+            if last_values_match {
+                Ok(())
+            } else {
+                Err(TwoPhaseError::WrongLastValue)
+            }
+        });
+        (verifier_data, result)
+    }
+
+    fn is_sum_mismatch(&self, error: &TwoPhaseError) -> bool {
+        matches!(
+            error,
+            TwoPhaseError::LogUp(FriLogUpError::NonZeroCumulativeSum)
+        )
+    }
+}
+
+/// Constraints of the second phase: `t` starts at the cumulative sum of the first phase, is
+/// multiplied by `gamma` on each row, and ends at the exposed value.
+pub(crate) fn eval_second_phase<AB: MultiPhaseAirBuilder>(builder: &mut AB) {
+    let sum: AB::ExprEF = builder.phase_exposed_values(0)[0].into();
+    let gamma: AB::ExprEF = builder.phase_challenges(1)[0].into();
+    let last_value: AB::ExprEF = builder.phase_exposed_values(1)[0].into();
+
+    let trace = builder.phase_trace(1);
+    let (local, next) = (trace.row_slice(0), trace.row_slice(1));
+    let t: AB::ExprEF = local[0].into();
+    let t_next: AB::ExprEF = next[0].into();
+
+    let is_first_row = builder.is_first_row();
+    let is_transition = builder.is_transition();
+    let is_last_row = builder.is_last_row();
+    builder.assert_zero_ext((t.clone() - sum) * is_first_row);
+    builder.assert_zero_ext((t_next - t.clone() * gamma) * is_transition);
+    builder.assert_zero_ext((t - last_value) * is_last_row);
+}
+
+mod tests {
+    use std::sync::Arc;
+
+    use openvm_stark_sdk::utils::create_seeded_rng;
+    use p3_air::{Air, BaseAir};
+    use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
+    use p3_challenger::DuplexChallenger;
+    use p3_commit::ExtensionMmcs;
+    use p3_dft::Radix2DitParallel;
+    use p3_field::extension::BinomialExtensionField;
+    use p3_fri::{FriConfig, TwoAdicFriPcs};
+    use p3_merkle_tree::MerkleTreeMmcs;
+    use p3_symmetric::{PaddingFreeSponge, TruncatedPermutation};
+
+    use super::*;
+    use crate::{
+        config::StarkConfig,
+        engine::StarkEngine,
+        interaction::{InteractionBuilder, InteractionType},
+        keygen::types::{CommitmentRound, MultiStarkProvingKey, VkFeature},
+        proof::Proof,
+        prover::types::{AirProofInput, ProofInput},
+        rap::{BaseAirWithPublicValues, PartitionedBaseAir},
+    };
+
+    type F = BabyBear;
+    type EF = BinomialExtensionField<F, 4>;
+    type Perm = Poseidon2BabyBear<16>;
+    type Hash = PaddingFreeSponge<Perm, 16, 8, 8>;
+    type Compress = TruncatedPermutation<Perm, 2, 8, 16>;
+    type ValMmcs = MerkleTreeMmcs<<F as Field>::Packing, <F as Field>::Packing, Hash, Compress, 8>;
+    type ChallengeMmcs = ExtensionMmcs<F, EF, ValMmcs>;
+    type Challenger = DuplexChallenger<F, Perm, 16, 8>;
+    type Pcs = TwoAdicFriPcs<F, Radix2DitParallel<F>, ValMmcs, ChallengeMmcs>;
+    type SC = StarkConfig<Pcs, TwoPhase<F, EF, Challenger>, EF, Challenger>;
+
+    const HEIGHT: usize = 8;
+
+    struct TwoPhaseEngine {
+        config: SC,
+        perm: Perm,
+    }
+
+    impl StarkEngine<SC> for TwoPhaseEngine {
+        fn config(&self) -> &SC {
+            &self.config
+        }
+
+        fn max_constraint_degree(&self) -> Option<usize> {
+            Some(3)
+        }
+
+        fn new_challenger(&self) -> Challenger {
+            Challenger::new(self.perm.clone())
+        }
+    }
+
+    fn engine() -> TwoPhaseEngine {
+        let perm = Perm::new_from_rng_128(&mut create_seeded_rng());
+        let val_mmcs = ValMmcs::new(Hash::new(perm.clone()), Compress::new(perm.clone()));
+        let fri_config = FriConfig {
+            log_blowup: 1,
+            log_final_poly_len: 0,
+            num_queries: 40,
+            proof_of_work_bits: 0,
+            mmcs: ChallengeMmcs::new(val_mmcs.clone()),
+        };
+        let pcs = Pcs::new(Radix2DitParallel::default(), val_mmcs, fri_config);
+        TwoPhaseEngine {
+            config: SC::new(pcs, TwoPhase::new()),
+            perm,
+        }
+    }
+
+    /// Sends or receives the value `v` with multiplicity `count` on bus 0.
+    ///
+    /// Main columns: `count, v`.
+    struct LookupAir {
+        is_send: bool,
+    }
+
+    impl<T> BaseAir<T> for LookupAir {
+        fn width(&self) -> usize {
+            2
+        }
+    }
+    impl<T> BaseAirWithPublicValues<T> for LookupAir {}
+    impl<T> PartitionedBaseAir<T> for LookupAir {}
+
+    impl<AB: InteractionBuilder> Air<AB> for LookupAir {
+        fn eval(&self, builder: &mut AB) {
+            let main = builder.main();
+            let local = main.row_slice(0);
+            let interaction_type = if self.is_send {
+                InteractionType::Send
+            } else {
+                InteractionType::Receive
+            };
+            builder.push_interaction(0, [local[1]], local[0], interaction_type);
+        }
+    }
+
+    fn prove(engine: &TwoPhaseEngine) -> (MultiStarkProvingKey<SC>, Proof<SC>) {
+        let mut keygen_builder = engine.keygen_builder();
+        let sender_id = keygen_builder.add_air(Arc::new(LookupAir { is_send: true }));
+        let receiver_id = keygen_builder.add_air(Arc::new(LookupAir { is_send: false }));
+        let pk = keygen_builder.generate_pk();
+        let trace = RowMajorMatrix::new(
+            (0..HEIGHT)
+                .flat_map(|row| [F::ONE, F::from_canonical_usize(3 * row)])
+                .collect(),
+            2,
+        );
+        let proof = engine.prove(
+            &pk,
+            ProofInput::new(vec![
+                (sender_id, AirProofInput::simple_no_pis(trace.clone())),
+                (receiver_id, AirProofInput::simple_no_pis(trace)),
+            ]),
+        );
+        (pk, proof)
+    }
+
+    #[test]
+    fn test_two_challenge_phases() {
+        let engine = engine();
+        let (pk, proof) = prove(&engine);
+        engine
+            .verify(&pk.get_vk(), &proof)
+            .expect("Verification failed");
+
+        assert!(pk.features.contains(VkFeature::MultipleChallengePhases));
+        assert!(pk
+            .commitment_rounds
+            .contains(&CommitmentRound::AfterChallenge { phase: 1 }));
+        assert_eq!(proof.commitments.after_challenge.len(), 2);
+        for air_proof in &proof.core.per_air {
+            assert_eq!(air_proof.exposed_values_after_challenge.len(), 2);
+        }
+    }
+
+    #[test]
+    fn test_two_challenge_phases_wrong_exposed_value() {
+        let engine = engine();
+        let (pk, mut proof) = prove(&engine);
+        proof.core.per_air[0].exposed_values_after_challenge[1][0] += EF::ONE;
+        assert!(engine.verify(&pk.get_vk(), &proof).is_err());
+    }
+}
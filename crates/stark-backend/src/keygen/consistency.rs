@@ -0,0 +1,120 @@
+//! Check that a proving key and a verifying key were generated by the same keygen, to diagnose
+//! keys that drifted apart, e.g. when one side was regenerated after a change to an AIR.
+
+use itertools::Itertools;
+use p3_field::PrimeField64;
+
+use super::{
+    types::{MultiStarkProvingKey, MultiStarkVerifyingKey, StarkVerifyingKey},
+    AirKeyMismatch, AirKeyMismatchKind, KeyMismatch, VkField,
+};
+use crate::{
+    config::{Com, StarkGenericConfig, Val},
+    interaction::InteractionType,
+};
+
+/// Checks that `vk` is the verifying key of `pk`, see
+/// [MultiStarkProvingKey::get_vk]. Proofs of `pk` only verify against a consistent `vk`.
+///
+/// Compares the number of AIRs and of challenge phases, then for each AIR its trace widths, its
+/// interactions, its preprocessed commitments and its
+/// [fingerprint](StarkVerifyingKey::fingerprint), and reports every differing AIR. The names of
+/// the AIRs are only used for the report. Finally compares the fields shared by all AIRs, see
+/// [VkField].
+pub fn check_pk_vk_consistency<SC: StarkGenericConfig>(
+    pk: &MultiStarkProvingKey<SC>,
+    vk: &MultiStarkVerifyingKey<SC>,
+) -> Result<(), KeyMismatch>
+where
+    Val<SC>: PrimeField64,
+    Com<SC>: PartialEq,
+{
+    let pk_vk = pk.get_vk();
+    if pk_vk.per_air.len() != vk.per_air.len() {
+        return Err(KeyMismatch::NumAirs {
+            pk: pk_vk.per_air.len(),
+            vk: vk.per_air.len(),
+        });
+    }
+    let airs = pk_vk
+        .per_air
+        .iter()
+        .zip(&vk.per_air)
+        .enumerate()
+        .filter_map(|(air_id, (pk_vk, vk))| {
+            air_mismatch_kind(pk_vk, vk).map(|kind| AirKeyMismatch {
+                air_id,
+                air_name: pk.per_air[air_id].air_name.clone(),
+                kind,
+            })
+        })
+        .collect_vec();
+    if !airs.is_empty() {
+        return Err(KeyMismatch::Airs(airs));
+    }
+    let (pk_phases, vk_phases) = (pk_vk.full_view().num_phases(), vk.full_view().num_phases());
+    if pk_phases != vk_phases {
+        return Err(KeyMismatch::NumChallengePhases {
+            pk: pk_phases,
+            vk: vk_phases,
+        });
+    }
+    let fields = [
+        (pk_vk.commitment_rounds != vk.commitment_rounds).then_some(VkField::CommitmentRounds),
+        (pk_vk.matrix_ordering != vk.matrix_ordering).then_some(VkField::MatrixOrdering),
+        (pk_vk.features != vk.features).then_some(VkField::Features),
+        (pk_vk.bus_registry != vk.bus_registry).then_some(VkField::BusRegistry),
+        (pk_vk.public_lookup_tables != vk.public_lookup_tables)
+            .then_some(VkField::PublicLookupTables),
+    ]
+    .into_iter()
+    .flatten()
+    .collect_vec();
+    if !fields.is_empty() {
+        return Err(KeyMismatch::Fields(fields));
+    }
+    Ok(())
+}
+
+/// The first difference between the verifying keys of an AIR, if any.
+fn air_mismatch_kind<F: PrimeField64, Com: PartialEq>(
+    pk_vk: &StarkVerifyingKey<F, Com>,
+    vk: &StarkVerifyingKey<F, Com>,
+) -> Option<AirKeyMismatchKind> {
+    if pk_vk.params.width != vk.params.width {
+        return Some(AirKeyMismatchKind::Widths);
+    }
+    if interaction_set(pk_vk) != interaction_set(vk) {
+        return Some(AirKeyMismatchKind::Interactions);
+    }
+    let preprocessed_commits = |vk: &StarkVerifyingKey<F, Com>| {
+        (
+            vk.preprocessed_data.as_ref().map(|data| &data.commit),
+            vk.preprocessed_per_height
+                .as_ref()
+                .map(|per_height| per_height.commits.iter().collect_vec()),
+        )
+    };
+    if preprocessed_commits(pk_vk) != preprocessed_commits(vk) {
+        return Some(AirKeyMismatchKind::PreprocessedCommitment);
+    }
+    if pk_vk.fingerprint() != vk.fingerprint() {
+        return Some(AirKeyMismatchKind::Fingerprint);
+    }
+    None
+}
+
+/// The bus, type and number of fields of each interaction.
+fn interaction_set<F, Com>(vk: &StarkVerifyingKey<F, Com>) -> Vec<(usize, InteractionType, usize)> {
+    vk.symbolic_constraints
+        .interactions
+        .iter()
+        .map(|interaction| {
+            (
+                interaction.bus_index,
+                interaction.interaction_type,
+                interaction.fields.len(),
+            )
+        })
+        .collect()
+}
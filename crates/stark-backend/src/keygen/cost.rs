@@ -0,0 +1,194 @@
+//! Estimates of the proving cost of an AIR, derived from its verifying key before any trace is
+//! generated.
+
+use p3_field::FieldExtensionAlgebra;
+use p3_util::log2_strict_usize;
+
+use super::types::StarkProvingKey;
+use crate::{
+    air_builders::symbolic::{symbolic_variable::Entry, SymbolicExpressionNode},
+    config::{StarkGenericConfig, Val},
+};
+
+/// Height independent cost parameters of proving a single AIR. Evaluate for a concrete trace
+/// height with [estimate](Self::estimate).
+///
+/// Operation counts are per row of the domain they are evaluated on: the trace domain for trace
+/// generation, and the quotient domain, of size `quotient_degree` times the trace height, for
+/// the constraint evaluation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProverCostModel {
+    /// Degree of the challenge field over the base field.
+    pub ext_degree: usize,
+    /// Number of quotient chunks of the AIR.
+    pub quotient_degree: usize,
+    /// Number of base field columns of the main traces, including custom round matrices.
+    pub main_width: usize,
+    /// Number of extension field columns of the after challenge traces.
+    pub after_challenge_width: usize,
+    /// Base field multiplications per row to evaluate the constraints that do not depend on
+    /// challenges.
+    pub quotient_base_muls_per_row: usize,
+    /// Base field additions, subtractions and negations per row to evaluate the constraints that
+    /// do not depend on challenges.
+    pub quotient_base_adds_per_row: usize,
+    /// Extension field multiplications per row to evaluate the constraints that depend on
+    /// challenges, i.e. the log-up constraints, and to fold all constraints together.
+    pub quotient_ext_muls_per_row: usize,
+    /// Extension field additions per row to evaluate the constraints that depend on challenges,
+    /// and to fold all constraints together.
+    pub quotient_ext_adds_per_row: usize,
+    /// Extension field multiplications per row to generate the log-up trace.
+    pub logup_ext_muls_per_row: usize,
+    /// Extension field additions per row to generate the log-up trace.
+    pub logup_ext_adds_per_row: usize,
+}
+
+/// Estimated proving cost of a single AIR for a concrete trace height, by phase.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ProverCostEstimate {
+    /// DFT butterflies to compute the low degree extensions of the main traces.
+    pub main_dft_butterflies: usize,
+    /// Extension field multiplications to generate the log-up trace.
+    pub logup_ext_muls: usize,
+    /// Extension field additions to generate the log-up trace.
+    pub logup_ext_adds: usize,
+    /// DFT butterflies to compute the low degree extensions of the after challenge traces.
+    pub after_challenge_dft_butterflies: usize,
+    /// Base field multiplications to evaluate the constraints on the quotient domain.
+    pub quotient_base_muls: usize,
+    /// Base field additions to evaluate the constraints on the quotient domain.
+    pub quotient_base_adds: usize,
+    /// Extension field multiplications to evaluate the constraints on the quotient domain.
+    pub quotient_ext_muls: usize,
+    /// Extension field additions to evaluate the constraints on the quotient domain.
+    pub quotient_ext_adds: usize,
+    /// DFT butterflies to compute the low degree extensions of the quotient chunks.
+    pub quotient_dft_butterflies: usize,
+}
+
+impl ProverCostEstimate {
+    /// Total cost in base field operations, counting a DFT butterfly as 3 operations, an
+    /// extension field multiplication as `ext_degree^2` operations and an extension field
+    /// addition as `ext_degree` operations. Hashing for the commitments is not included.
+    pub fn total_base_field_ops(&self, ext_degree: usize) -> usize {
+        let butterflies = self.main_dft_butterflies
+            + self.after_challenge_dft_butterflies
+            + self.quotient_dft_butterflies;
+        let ext_muls = self.logup_ext_muls + self.quotient_ext_muls;
+        let ext_adds = self.logup_ext_adds + self.quotient_ext_adds;
+        3 * butterflies
+            + self.quotient_base_muls
+            + self.quotient_base_adds
+            + ext_degree * ext_degree * ext_muls
+            + ext_degree * ext_adds
+    }
+}
+
+impl ProverCostModel {
+    /// Estimates the proving cost for a trace of height `height`, committed with a low degree
+    /// extension of blowup `2^log_blowup`.
+    pub fn estimate(&self, height: usize, log_blowup: usize) -> ProverCostEstimate {
+        let quotient_height = height * self.quotient_degree;
+        let lde_butterflies = lde_butterflies(height, log_blowup);
+        ProverCostEstimate {
+            main_dft_butterflies: self.main_width * lde_butterflies,
+            logup_ext_muls: self.logup_ext_muls_per_row * height,
+            logup_ext_adds: self.logup_ext_adds_per_row * height,
+            after_challenge_dft_butterflies: self.after_challenge_width
+                * self.ext_degree
+                * lde_butterflies,
+            quotient_base_muls: self.quotient_base_muls_per_row * quotient_height,
+            quotient_base_adds: self.quotient_base_adds_per_row * quotient_height,
+            quotient_ext_muls: self.quotient_ext_muls_per_row * quotient_height,
+            quotient_ext_adds: self.quotient_ext_adds_per_row * quotient_height,
+            quotient_dft_butterflies: self.quotient_degree * self.ext_degree * lde_butterflies,
+        }
+    }
+}
+
+/// Butterflies of the inverse DFT of a column of height `height`, followed by the coset DFT of
+/// its extension to height `height << log_blowup`.
+fn lde_butterflies(height: usize, log_blowup: usize) -> usize {
+    let log_height = log2_strict_usize(height);
+    let lde_height = height << log_blowup;
+    height / 2 * log_height + lde_height / 2 * (log_height + log_blowup)
+}
+
+impl<SC: StarkGenericConfig> StarkProvingKey<SC> {
+    /// Returns the cost model of proving this AIR, derived from its symbolic constraints.
+    pub fn cost_model(&self) -> ProverCostModel {
+        let ext_degree = <SC::Challenge as FieldExtensionAlgebra<Val<SC>>>::D;
+        let params = &self.vk.params;
+        let dag = &self.vk.symbolic_constraints.constraints;
+
+        let mut model = ProverCostModel {
+            ext_degree,
+            quotient_degree: self.vk.quotient_degree as usize,
+            main_width: params.width.main_widths().iter().sum::<usize>()
+                + params.width.custom.iter().map(|&(_, w)| w).sum::<usize>(),
+            after_challenge_width: params.width.after_challenge.iter().sum(),
+            quotient_base_muls_per_row: 0,
+            quotient_base_adds_per_row: 0,
+            // Folding each constraint into the accumulator takes one multiplication by the
+            // folding challenge and one addition.
+            quotient_ext_muls_per_row: dag.constraint_idx.len(),
+            quotient_ext_adds_per_row: dag.constraint_idx.len(),
+            logup_ext_muls_per_row: 0,
+            logup_ext_adds_per_row: 0,
+        };
+        // Whether each node depends on a challenge, in which case it is evaluated in the
+        // extension field.
+        let mut is_ext = Vec::with_capacity(dag.nodes.len());
+        for node in &dag.nodes {
+            let (ext, muls, adds) = match *node {
+                SymbolicExpressionNode::Variable(var) => (
+                    matches!(
+                        var.entry,
+                        Entry::Permutation { .. } | Entry::Challenge { .. } | Entry::Exposed { .. }
+                    ),
+                    0,
+                    0,
+                ),
+                SymbolicExpressionNode::IsFirstRow
+                | SymbolicExpressionNode::IsLastRow
+                | SymbolicExpressionNode::IsTransition
+                | SymbolicExpressionNode::Constant(_) => (false, 0, 0),
+                SymbolicExpressionNode::Add {
+                    left_idx,
+                    right_idx,
+                    ..
+                }
+                | SymbolicExpressionNode::Sub {
+                    left_idx,
+                    right_idx,
+                    ..
+                } => (is_ext[left_idx] || is_ext[right_idx], 0, 1),
+                SymbolicExpressionNode::Neg { idx, .. } => (is_ext[idx], 0, 1),
+                SymbolicExpressionNode::Mul {
+                    left_idx,
+                    right_idx,
+                    ..
+                } => (is_ext[left_idx] || is_ext[right_idx], 1, 0),
+            };
+            if ext {
+                model.quotient_ext_muls_per_row += muls;
+                model.quotient_ext_adds_per_row += adds;
+            } else {
+                model.quotient_base_muls_per_row += muls;
+                model.quotient_base_adds_per_row += adds;
+            }
+            is_ext.push(ext);
+        }
+
+        // Each interaction is compressed with powers of a challenge into a denominator, which is
+        // inverted with a batch inversion costing about 3 multiplications per element. The
+        // inverse is multiplied by the count and added to the running sum.
+        for interaction in &self.vk.symbolic_constraints.interactions {
+            let num_fields = interaction.fields.len();
+            model.logup_ext_muls_per_row += num_fields + 3 + 1;
+            model.logup_ext_adds_per_row += num_fields + 1;
+        }
+        model
+    }
+}
@@ -0,0 +1,261 @@
+//! Deterministic digest of a verifying key, to pin the system of AIRs a proof is for.
+//!
+//! The digest is independent of serde: the key is encoded in a canonical byte format, which is
+//! absorbed by the challenger of the config along with the preprocessed commitments and the PCS
+//! parameters, and the digest is squeezed from the challenger. It can therefore be recomputed by
+//! a recursive verifier with the same hash as the rest of the transcript.
+//!
+//! Included, for each AIR: the presence of a preprocessed trace and its commitment, the log
+//! heights of a preprocessed trace committed per height and the commitment of each committed
+//! height, the trace widths, the numbers of public values, challenges and exposed values, the
+//! extra rotations, the configuration public values, the maximum log height, whether the AIR may
+//! be absent, the constraint DAG in the format of `SymbolicExpressionDag::to_bytes`, the
+//! interactions, the quotient degree, the RAP phase and its log-up parameters, and the periodic
+//! columns. For the whole key: the commitment rounds without the names of the custom rounds, the
+//! matrix ordering, the features and the public lookup tables.
+//!
+//! Not included: the names of the AIRs and of the custom rounds, and the bus registry, which are
+//! only used for diagnostics.
+
+use std::{
+    array,
+    hash::{Hash, Hasher},
+};
+
+use p3_challenger::{CanObserve, CanSample};
+use p3_field::{FieldAlgebra, PrimeField64};
+use rustc_hash::FxHasher;
+
+use super::types::{
+    AllowedValues, CommitmentRound, MatrixOrdering, MultiStarkVerifyingKey, StarkVerifyingKey,
+    VkFeature,
+};
+use crate::{
+    air_builders::symbolic::write_varint,
+    config::{Com, StarkGenericConfig, Val},
+    interaction::InteractionType,
+};
+
+/// Version of the canonical encoding of a verifying key. Bumped whenever the encoding changes,
+/// which changes every digest.
+pub const VK_DIGEST_VERSION: u8 = 4;
+
+/// Number of field elements of a verifying key digest.
+pub const VK_DIGEST_WIDTH: usize = 8;
+
+/// The parameters of a PCS that affect verification but are not recorded in a verifying key,
+/// e.g. the FRI parameters, as absorbed by [digest](MultiStarkVerifyingKey::digest).
+pub trait PcsDigestParams {
+    /// The parameters, in a fixed order.
+    fn digest_params(&self) -> Vec<usize>;
+}
+
+impl<SC: StarkGenericConfig> MultiStarkVerifyingKey<SC>
+where
+    Val<SC>: PrimeField64,
+{
+    /// Returns the digest of the key, squeezed from `challenger` after observing the canonical
+    /// encoding of the key, see [canonical_bytes](Self::canonical_bytes), its preprocessed
+    /// commitments, and `pcs_params`.
+    ///
+    /// `challenger` must be fresh, e.g. from
+    /// [StarkEngine::new_challenger](crate::engine::StarkEngine::new_challenger).
+    pub fn digest(
+        &self,
+        challenger: &mut SC::Challenger,
+        pcs_params: &impl PcsDigestParams,
+    ) -> [Val<SC>; VK_DIGEST_WIDTH] {
+        let bytes = self.canonical_bytes();
+        challenger.observe(Val::<SC>::from_canonical_usize(bytes.len()));
+        // Chunks of 3 bytes fit in any field of at least 24 bits.
+        for chunk in bytes.chunks(3) {
+            let value = chunk
+                .iter()
+                .rev()
+                .fold(0u32, |acc, &byte| (acc << 8) | byte as u32);
+            challenger.observe(Val::<SC>::from_canonical_u32(value));
+        }
+        for commit in self
+            .per_air
+            .iter()
+            .filter_map(|vk| vk.preprocessed_data.as_ref())
+        {
+            <SC::Challenger as CanObserve<Com<SC>>>::observe(challenger, commit.commit.clone());
+        }
+        for (_, commit) in self
+            .per_air
+            .iter()
+            .filter_map(|vk| vk.preprocessed_per_height.as_ref())
+            .flat_map(|per_height| &per_height.commits)
+        {
+            <SC::Challenger as CanObserve<Com<SC>>>::observe(challenger, commit.clone());
+        }
+        let pcs_params = pcs_params.digest_params();
+        challenger.observe(Val::<SC>::from_canonical_usize(pcs_params.len()));
+        for param in pcs_params {
+            challenger.observe(Val::<SC>::from_canonical_usize(param));
+        }
+        array::from_fn(|_| <SC::Challenger as CanSample<Val<SC>>>::sample(challenger))
+    }
+
+    /// The canonical encoding of the key absorbed by [digest](Self::digest), without the
+    /// preprocessed commitments. Lengths and integers are unsigned LEB128 varints, and field
+    /// elements are their canonical value as 8 little-endian bytes.
+    pub fn canonical_bytes(&self) -> Vec<u8> {
+        let mut out = vec![VK_DIGEST_VERSION];
+        write_varint(&mut out, self.per_air.len());
+        for vk in &self.per_air {
+            write_air_vk(&mut out, vk);
+        }
+        write_varint(&mut out, self.commitment_rounds.len());
+        for round in &self.commitment_rounds {
+            match round {
+                CommitmentRound::Preprocessed => out.push(0),
+                CommitmentRound::CachedMain => out.push(1),
+                CommitmentRound::CommonMain => out.push(2),
+                CommitmentRound::Custom { index, .. } => {
+                    out.push(3);
+                    write_varint(&mut out, *index);
+                }
+                CommitmentRound::AfterChallenge { phase } => {
+                    out.push(4);
+                    write_varint(&mut out, *phase);
+                }
+                CommitmentRound::Quotient => out.push(5),
+            }
+        }
+        out.push(match self.matrix_ordering {
+            MatrixOrdering::Natural => 0,
+            MatrixOrdering::HeightDescending => 1,
+        });
+        let features = VkFeature::ALL
+            .into_iter()
+            .filter(|&feature| self.features.contains(feature))
+            .fold(0usize, |acc, feature| acc | (1 << feature as u8));
+        write_varint(&mut out, features);
+        write_varint(&mut out, self.public_lookup_tables.len());
+        for table in &self.public_lookup_tables {
+            write_varint(&mut out, table.bus_index);
+            write_varint(&mut out, table.rows.len());
+            write_varint(&mut out, table.arity());
+            for row in &table.rows {
+                write_field_elements(&mut out, row);
+            }
+        }
+        out
+    }
+}
+
+impl<F: PrimeField64, Com> StarkVerifyingKey<F, Com> {
+    /// Non-cryptographic fingerprint of the key of a single AIR, hashing the AIR's part of
+    /// [canonical_bytes](MultiStarkVerifyingKey::canonical_bytes). The preprocessed commitment is
+    /// not included. Only meant to tell which AIRs differ between two keys, e.g. after
+    /// [replace_air](crate::keygen::MultiStarkKeygenBuilder::replace_air).
+    pub fn fingerprint(&self) -> u64 {
+        let mut bytes = vec![];
+        write_air_vk(&mut bytes, self);
+        let mut hasher = FxHasher::default();
+        bytes.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+fn write_air_vk<F: PrimeField64, Com>(out: &mut Vec<u8>, vk: &StarkVerifyingKey<F, Com>) {
+    let params = &vk.params;
+    let width = &params.width;
+    out.push(vk.preprocessed_data.is_some() as u8);
+    write_option(out, width.preprocessed);
+    write_list(out, &width.cached_mains);
+    write_varint(out, width.common_main);
+    write_list(out, &width.after_challenge);
+    write_varint(out, width.custom.len());
+    for &(round, width) in &width.custom {
+        write_varint(out, round);
+        write_varint(out, width);
+    }
+    write_varint(out, params.num_public_values);
+    write_list(out, &params.num_exposed_values_after_challenge);
+    write_list(out, &params.num_challenges_to_sample);
+    write_list(out, &params.extra_rotations);
+    write_varint(out, params.configuration_public_values.len());
+    for (index, allowed) in &params.configuration_public_values {
+        write_varint(out, *index);
+        match allowed {
+            AllowedValues::Set(values) => {
+                out.push(0);
+                write_varint(out, values.len());
+                for value in values {
+                    out.extend_from_slice(&value.to_le_bytes());
+                }
+            }
+            AllowedValues::Range { start, end } => {
+                out.push(1);
+                out.extend_from_slice(&start.to_le_bytes());
+                out.extend_from_slice(&end.to_le_bytes());
+            }
+        }
+    }
+    write_option(out, params.max_log_height);
+    out.push(params.may_be_absent as u8);
+
+    let constraints = vk.symbolic_constraints.constraints.to_bytes();
+    write_varint(out, constraints.len());
+    out.extend_from_slice(&constraints);
+    write_varint(out, vk.symbolic_constraints.interactions.len());
+    for interaction in &vk.symbolic_constraints.interactions {
+        write_varint(out, interaction.bus_index);
+        out.push(match interaction.interaction_type {
+            InteractionType::Send => 0,
+            InteractionType::Receive => 1,
+        });
+        write_list(out, &interaction.fields);
+        write_varint(out, interaction.count);
+    }
+
+    out.push(vk.quotient_degree);
+    out.push(vk.rap_phase_seq_kind as u8);
+    write_option(out, vk.log_up_params.batch_size);
+    write_varint(out, vk.log_up_params.num_repetitions);
+    out.push(vk.log_up_params.commit_inverses as u8);
+    write_varint(out, vk.periodic_columns.len());
+    for column in &vk.periodic_columns {
+        write_varint(out, column.len());
+        write_field_elements(out, column);
+    }
+    match &vk.preprocessed_per_height {
+        None => out.push(0),
+        Some(per_height) => {
+            out.push(1);
+            write_list(out, &per_height.log_heights);
+            let committed = per_height
+                .commits
+                .iter()
+                .map(|&(h, _)| h)
+                .collect::<Vec<_>>();
+            write_list(out, &committed);
+        }
+    }
+}
+
+fn write_list(out: &mut Vec<u8>, values: &[usize]) {
+    write_varint(out, values.len());
+    for &value in values {
+        write_varint(out, value);
+    }
+}
+
+fn write_option(out: &mut Vec<u8>, value: Option<usize>) {
+    match value {
+        None => out.push(0),
+        Some(value) => {
+            out.push(1);
+            write_varint(out, value);
+        }
+    }
+}
+
+fn write_field_elements<F: PrimeField64>(out: &mut Vec<u8>, values: &[F]) {
+    for value in values {
+        out.extend_from_slice(&value.as_canonical_u64().to_le_bytes());
+    }
+}
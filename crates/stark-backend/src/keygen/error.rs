@@ -0,0 +1,564 @@
+use std::fmt;
+
+use itertools::Itertools;
+use thiserror::Error;
+
+use super::types::AllowedValues;
+use crate::interaction::RapPhaseSeqKind;
+
+/// The preprocessed data stored in a proving key no longer matches the preprocessed trace
+/// generated by the corresponding AIRs.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum StaleProvingKeyError {
+    /// The number of AIRs checked against the proving key is not its number of AIRs.
+    #[error("stale proving key: {actual} AIR(s) given for a proving key of {expected} AIR(s)")]
+    AirCountMismatch { expected: usize, actual: usize },
+    /// The preprocessed trace of some AIRs differs from the one in the proving key.
+    #[error(
+        "stale proving key: preprocessed trace no longer matches AIR(s) [{}]",
+        .stale_airs
+            .iter()
+            .map(|(air_id, air_name)| format!("{air_name} (air_id = {air_id})"))
+            .join(", ")
+    )]
+    StaleAirs {
+        /// `(air_id, air_name)` of every AIR whose preprocessed data is stale.
+        stale_airs: Vec<(usize, String)>,
+    },
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum KeygenError {
+    /// The constraints of an AIR, including the log-up constraints, have a higher degree than
+    /// the PCS supports.
+    #[error(
+        "AIR {air_name} (air_id = {air_id}) has constraint degree {degree}, which exceeds the \
+         maximum supported constraint degree {max_degree}; use log_blowup >= {min_log_blowup}"
+    )]
+    ConstraintDegreeTooHigh {
+        air_id: usize,
+        air_name: String,
+        degree: usize,
+        max_degree: usize,
+        /// The minimum FRI `log_blowup` whose maximum constraint degree `2^log_blowup + 1`
+        /// accepts `degree`.
+        min_log_blowup: usize,
+    },
+    /// The log-up constraints of an AIR, with the interaction batch size of the RAP phase, have a
+    /// higher degree than the PCS supports.
+    #[error(
+        "AIR {air_name} (air_id = {air_id}) has log-up constraint degree {degree} with \
+         interaction batch size {batch_size}, which exceeds the maximum supported constraint \
+         degree {max_degree}"
+    )]
+    InteractionBatchTooLarge {
+        air_id: usize,
+        air_name: String,
+        batch_size: usize,
+        degree: usize,
+        max_degree: usize,
+    },
+    /// An interaction of an AIR on a registered bus does not have the number of fields of the
+    /// messages on the bus.
+    #[error(
+        "AIR {air_name} (air_id = {air_id}) has an interaction with {arity} fields on bus \
+         {bus_name} (index {bus_index}), whose messages have {expected_arity} fields"
+    )]
+    BusArityMismatch {
+        air_id: usize,
+        air_name: String,
+        bus_index: usize,
+        bus_name: String,
+        expected_arity: usize,
+        arity: usize,
+    },
+    /// An AIR interacts by raw index on a bus that is not registered, while other buses are, so
+    /// the raw index may be the index of a named bus.
+    #[error(
+        "AIR {air_name} (air_id = {air_id}) has an interaction on bus {bus_index}, which is not \
+         registered while other buses are; register it with add_raw_bus"
+    )]
+    UnregisteredBus {
+        air_id: usize,
+        air_name: String,
+        bus_index: usize,
+    },
+    /// Public lookup tables were added but the RAP phase is not FRI log-up.
+    #[error("public lookup tables are only supported by FRI log-up, not {rap_phase_seq_kind:?}")]
+    PublicLookupTablesUnsupported { rap_phase_seq_kind: RapPhaseSeqKind },
+    /// Zero knowledge was enabled but the RAP phase is GKR log-up.
+    #[error("zero knowledge is not supported by {rap_phase_seq_kind:?}")]
+    ZeroKnowledgeUnsupported { rap_phase_seq_kind: RapPhaseSeqKind },
+    /// An AIR has more interactions than allowed by the [InteractionLimits].
+    ///
+    /// [InteractionLimits]: super::InteractionLimits
+    #[error(
+        "AIR {air_name} (air_id = {air_id}) has {num_interactions} interactions, which exceeds \
+         the limit of {max_interactions} interactions per AIR"
+    )]
+    TooManyInteractionsPerAir {
+        air_id: usize,
+        air_name: String,
+        num_interactions: usize,
+        max_interactions: usize,
+    },
+    /// The AIRs have more interactions in total than allowed by the [InteractionLimits].
+    ///
+    /// [InteractionLimits]: super::InteractionLimits
+    #[error(
+        "the AIRs have {num_interactions} interactions in total, which exceeds the limit of \
+         {max_interactions} interactions"
+    )]
+    TooManyInteractions {
+        num_interactions: usize,
+        max_interactions: usize,
+    },
+    /// An interaction of an AIR has more fields than allowed by the [InteractionLimits].
+    ///
+    /// [InteractionLimits]: super::InteractionLimits
+    #[error(
+        "AIR {air_name} (air_id = {air_id}) has an interaction with {num_fields} fields on bus \
+         {bus_index}, which exceeds the limit of {max_fields} fields per message"
+    )]
+    TooManyMessageFields {
+        air_id: usize,
+        air_name: String,
+        bus_index: usize,
+        num_fields: usize,
+        max_fields: usize,
+    },
+    /// The main width of an AIR is not the sum of the widths of its cached and common main
+    /// traces, which are the widths its constraints are symbolically evaluated on.
+    #[error(
+        "AIR {air_name} (air_id = {air_id}) has width {width}, but its cached main widths \
+         {cached_main_widths:?} and common main width {common_main_width} add up to a different \
+         width"
+    )]
+    MainWidthMismatch {
+        air_id: usize,
+        air_name: String,
+        width: usize,
+        cached_main_widths: Vec<usize>,
+        common_main_width: usize,
+    },
+    /// The preprocessed trace of an AIR does not have a power of two height.
+    #[error(
+        "AIR {air_name} (air_id = {air_id}) has a preprocessed trace of height {height}, which \
+         is not a power of two"
+    )]
+    PreprocessedHeightNotPowerOfTwo {
+        air_id: usize,
+        air_name: String,
+        height: usize,
+    },
+    /// An AIR declares a preprocessed shape and also has a preprocessed trace.
+    #[error(
+        "AIR {air_name} (air_id = {air_id}) has both a preprocessed shape and a preprocessed \
+         trace"
+    )]
+    PreprocessedShapeWithTrace { air_id: usize, air_name: String },
+    /// An AIR declares a preprocessed shape without any log height.
+    #[error("AIR {air_name} (air_id = {air_id}) has a preprocessed shape without any log height")]
+    EmptyPreprocessedShape { air_id: usize, air_name: String },
+    /// A configuration public value of an AIR is not one of its public values.
+    #[error(
+        "AIR {air_name} (air_id = {air_id}) has configuration public value {index}, but only \
+         {num_public_values} public values"
+    )]
+    ConfigurationPublicValueOutOfBounds {
+        air_id: usize,
+        air_name: String,
+        index: usize,
+        num_public_values: usize,
+    },
+    /// A public value of an AIR is declared as a configuration public value more than once.
+    #[error(
+        "AIR {air_name} (air_id = {air_id}) declares configuration public value {index} more \
+         than once"
+    )]
+    DuplicateConfigurationPublicValue {
+        air_id: usize,
+        air_name: String,
+        index: usize,
+    },
+    /// A configuration public value of an AIR has no allowed values.
+    #[error(
+        "AIR {air_name} (air_id = {air_id}) has configuration public value {index} without any \
+         allowed values"
+    )]
+    NoAllowedValues {
+        air_id: usize,
+        air_name: String,
+        index: usize,
+    },
+    /// A periodic column of an AIR does not have a power of two period.
+    #[error(
+        "AIR {air_name} (air_id = {air_id}) has periodic column {column} with period {period}, \
+         which is not a power of two"
+    )]
+    PeriodNotPowerOfTwo {
+        air_id: usize,
+        air_name: String,
+        column: usize,
+        period: usize,
+    },
+    /// The quotient degree override of an AIR is not a power of two.
+    #[error(
+        "AIR {air_name} (air_id = {air_id}) has quotient degree override {degree}, which is not a \
+         power of two"
+    )]
+    QuotientDegreeNotPowerOfTwo {
+        air_id: usize,
+        air_name: String,
+        degree: usize,
+    },
+    /// The quotient degree override of an AIR is below the quotient degree its constraints
+    /// require.
+    #[error(
+        "AIR {air_name} (air_id = {air_id}) has quotient degree override {degree}, but its \
+         constraints require a quotient degree of at least {min_degree}"
+    )]
+    QuotientDegreeTooLow {
+        air_id: usize,
+        air_name: String,
+        degree: usize,
+        min_degree: usize,
+    },
+    /// The quotient degree override of an AIR exceeds the quotient degree supported by the PCS.
+    #[error(
+        "AIR {air_name} (air_id = {air_id}) has quotient degree override {degree}, which exceeds \
+         the maximum supported quotient degree {max_degree}"
+    )]
+    QuotientDegreeTooHigh {
+        air_id: usize,
+        air_name: String,
+        degree: usize,
+        max_degree: usize,
+    },
+    #[error(
+        "AIR {air_name} (air_id = {air_id}) may be absent from proofs, but the count of its \
+         interaction {index} is not gated on a column of its main trace"
+    )]
+    UngatedInteraction {
+        air_id: usize,
+        air_name: String,
+        index: usize,
+    },
+}
+
+/// A configuration public value is not one of the allowed values declared in the verifying key.
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error(
+    "public value {index} of AIR {air_name} (air_id = {air_id}) is {value}, which is not one of \
+     the allowed values {allowed}"
+)]
+pub struct DisallowedPublicValueError {
+    pub air_id: usize,
+    pub air_name: String,
+    /// Index of the configuration value in the public values of the AIR.
+    pub index: usize,
+    pub value: String,
+    pub allowed: AllowedValues,
+}
+
+/// A verifying key relies on features that the verifier does not support.
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+#[error("unsupported vk features: [{}]", .features.join(", "))]
+pub struct UnsupportedVkFeaturesError {
+    /// Names of the unsupported features, see [VkFeatureSet::names](super::types::VkFeatureSet::names).
+    pub features: Vec<String>,
+}
+
+/// An AIR with a preprocessed trace committed per height has a trace height whose preprocessed
+/// trace is not committed in the key.
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+#[error(
+    "AIR {air_name} (air_id = {air_id}) has no preprocessed commitment for trace height {height}"
+)]
+pub struct MissingPreprocessedHeightError {
+    pub air_id: usize,
+    pub air_name: String,
+    pub height: usize,
+}
+
+/// The trace of an AIR is taller than the maximum trace height recorded in the verifying key.
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error(
+    "trace of AIR {air_name} (air_id = {air_id}) has height {height}, above the maximum height \
+     2^{max_log_height}"
+)]
+pub struct TraceHeightTooLargeError {
+    pub air_id: usize,
+    pub air_name: String,
+    pub height: usize,
+    pub max_log_height: usize,
+}
+
+/// A pre-committed cached main trace of a proof input that does not fit its AIR, see
+/// [check_cached_mains](super::types::MultiStarkProvingKey::check_cached_mains).
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum CachedMainError {
+    #[error(
+        "AIR {air_name} (air_id = {air_id}) has {expected} cached main traces, but {actual} were \
+         committed"
+    )]
+    Count {
+        air_id: usize,
+        air_name: String,
+        expected: usize,
+        actual: usize,
+    },
+    #[error(
+        "cached main trace {index} of AIR {air_name} (air_id = {air_id}) has width {width}, but \
+         the AIR expects {expected}"
+    )]
+    Width {
+        air_id: usize,
+        air_name: String,
+        index: usize,
+        width: usize,
+        expected: usize,
+    },
+    #[error(
+        "cached main trace {index} of AIR {air_name} (air_id = {air_id}) has height {height}, but \
+         the trace of the AIR has height {expected}"
+    )]
+    Height {
+        air_id: usize,
+        air_name: String,
+        index: usize,
+        height: usize,
+        expected: usize,
+    },
+    #[error(
+        "cached main traces of AIR {air_name} (air_id = {air_id}) are pre-committed, but the key \
+         is zero-knowledge"
+    )]
+    ZeroKnowledge { air_id: usize, air_name: String },
+}
+
+/// A main trace partition of an AIR.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MainTraceKind {
+    Cached,
+    Common,
+    Custom,
+}
+
+impl fmt::Display for MainTraceKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Cached => write!(f, "cached main"),
+            Self::Common => write!(f, "common main"),
+            Self::Custom => write!(f, "custom main"),
+        }
+    }
+}
+
+/// A proof input that does not fit the proving key, see
+/// [check_proof_input](super::types::MultiStarkProvingKey::check_proof_input).
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ProofInputError {
+    /// An AIR id of the proof input, proven or absent, is not an AIR of the proving key.
+    #[error("proof input has AIR id {air_id}, but the proving key has {num_airs} AIRs")]
+    UnknownAir { air_id: usize, num_airs: usize },
+    #[error("AIR {air_name} (air_id = {air_id}) has two inputs")]
+    DuplicateAir { air_id: usize, air_name: String },
+    #[error(
+        "AIR {air_name} (air_id = {air_id}) has {expected} {kind} traces, but {actual} were given"
+    )]
+    TraceCount {
+        air_id: usize,
+        air_name: String,
+        kind: MainTraceKind,
+        expected: usize,
+        actual: usize,
+    },
+    #[error(
+        "{kind} trace {index} of AIR {air_name} (air_id = {air_id}) has width {width}, but the \
+         AIR expects {expected}"
+    )]
+    Width {
+        air_id: usize,
+        air_name: String,
+        kind: MainTraceKind,
+        index: usize,
+        width: usize,
+        expected: usize,
+    },
+    #[error(
+        "{kind} trace {index} of AIR {air_name} (air_id = {air_id}) has height {height}, but the \
+         trace of the AIR has height {expected}"
+    )]
+    Height {
+        air_id: usize,
+        air_name: String,
+        kind: MainTraceKind,
+        index: usize,
+        height: usize,
+        expected: usize,
+    },
+    #[error(
+        "trace of AIR {air_name} (air_id = {air_id}) has height {height}, which is not a power of \
+         two"
+    )]
+    HeightNotPowerOfTwo {
+        air_id: usize,
+        air_name: String,
+        height: usize,
+    },
+    /// The domain the trace is committed on, `2^log_blowup` times its height, would not be a
+    /// subgroup of the field.
+    #[error(
+        "trace of AIR {air_name} (air_id = {air_id}) has height {height}, whose committed domain \
+         with log blowup {log_blowup} is above the two-adicity 2^{two_adicity} of the field"
+    )]
+    HeightAboveTwoAdicity {
+        air_id: usize,
+        air_name: String,
+        height: usize,
+        log_blowup: usize,
+        two_adicity: usize,
+    },
+    #[error(
+        "AIR {air_name} (air_id = {air_id}) has {expected} public values, but {actual} were given"
+    )]
+    PublicValuesCount {
+        air_id: usize,
+        air_name: String,
+        expected: usize,
+        actual: usize,
+    },
+    #[error(transparent)]
+    CachedMain(#[from] CachedMainError),
+    #[error(transparent)]
+    TraceHeightTooLarge(#[from] TraceHeightTooLargeError),
+    /// The proving key has public lookup tables, but the RAP phase of the config is not FRI
+    /// log-up.
+    #[error("public lookup tables are only supported by FRI log-up, not {rap_phase_seq_kind:?}")]
+    PublicLookupTablesUnsupported { rap_phase_seq_kind: RapPhaseSeqKind },
+    /// The trace of an AIR of a zero-knowledge key is too low for its random values to hide its
+    /// openings, see `check_zero_knowledge_heights` of
+    /// [MultiStarkProvingKey](super::types::MultiStarkProvingKey).
+    #[error(
+        "trace of AIR {air_name} (air_id = {air_id}) has height {height}, but zero-knowledge \
+         proofs need a height of at least {min_height}"
+    )]
+    ZeroKnowledgeHeight {
+        air_id: usize,
+        air_name: String,
+        height: usize,
+        min_height: usize,
+    },
+}
+
+/// An AIR marked absent in a proof input that cannot be absent, see
+/// [check_absent_airs](super::types::MultiStarkProvingKey::check_absent_airs).
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum AbsentAirError {
+    #[error(
+        "AIR {air_name} (air_id = {air_id}) is absent, but was not allowed to be absent at keygen"
+    )]
+    NotAllowed { air_id: usize, air_name: String },
+    #[error("AIR {air_name} (air_id = {air_id}) is absent, but has an input")]
+    HasInput { air_id: usize, air_name: String },
+    /// The key has an AIR that may be absent, so every AIR must be either proven or absent.
+    #[error("AIR {air_name} (air_id = {air_id}) has no input, but is not marked absent")]
+    Missing { air_id: usize, air_name: String },
+}
+
+/// A proving key and a verifying key that were not generated by the same keygen, see
+/// [check_pk_vk_consistency](super::consistency::check_pk_vk_consistency).
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum KeyMismatch {
+    #[error("the proving key has {pk} AIRs, but the verifying key has {vk} AIRs")]
+    NumAirs { pk: usize, vk: usize },
+    #[error("the proving key has {pk} challenge phases, but the verifying key has {vk}")]
+    NumChallengePhases { pk: usize, vk: usize },
+    /// The keys of these AIRs differ, in increasing order of AIR id.
+    #[error(
+        "the proving and verifying keys differ on AIR(s) [{}]",
+        .0.iter().map(ToString::to_string).join(", ")
+    )]
+    Airs(Vec<AirKeyMismatch>),
+    /// The keys differ on these fields shared by all AIRs, in the order of [VkField].
+    #[error(
+        "the proving and verifying keys differ on [{}]",
+        .0.iter().map(ToString::to_string).join(", ")
+    )]
+    Fields(Vec<VkField>),
+}
+
+/// A field of a [MultiStarkVerifyingKey](super::types::MultiStarkVerifyingKey) shared by all
+/// AIRs.
+#[derive(Clone, Copy, Debug, Error, PartialEq, Eq)]
+pub enum VkField {
+    #[error("commitment rounds")]
+    CommitmentRounds,
+    #[error("matrix ordering")]
+    MatrixOrdering,
+    #[error("features")]
+    Features,
+    #[error("bus registry")]
+    BusRegistry,
+    #[error("public lookup tables")]
+    PublicLookupTables,
+}
+
+/// The verifying key of an AIR in a proving key differs from the one in a verifying key.
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+#[error("{air_name} (air_id = {air_id}): {kind}")]
+pub struct AirKeyMismatch {
+    pub air_id: usize,
+    /// Name of the AIR in the proving key.
+    pub air_name: String,
+    /// The first difference found.
+    pub kind: AirKeyMismatchKind,
+}
+
+/// What differs between the keys of an AIR, checked in this order.
+#[derive(Clone, Copy, Debug, Error, PartialEq, Eq)]
+pub enum AirKeyMismatchKind {
+    #[error("different trace widths")]
+    Widths,
+    /// The buses, types or numbers of fields of the interactions differ.
+    #[error("different interactions")]
+    Interactions,
+    #[error("different preprocessed commitments")]
+    PreprocessedCommitment,
+    /// Anything else in the verifying key, e.g. the constraints, see
+    /// [StarkVerifyingKey::fingerprint](super::types::StarkVerifyingKey::fingerprint).
+    #[error("different fingerprints")]
+    Fingerprint,
+}
+
+/// An error decoding a verifying key from JSON, see
+/// [MultiStarkVerifyingKey::from_json](super::types::MultiStarkVerifyingKey::from_json).
+#[cfg(feature = "vk-json")]
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum VkJsonError {
+    #[error("invalid verifying key JSON: {0}")]
+    Json(String),
+    #[error("unsupported verifying key JSON schema version {0}")]
+    UnsupportedSchemaVersion(u64),
+    #[error("unknown feature {0}")]
+    UnknownFeature(String),
+    /// A field element is not the decimal encoding of a canonical value.
+    #[error("invalid field element {0:?}")]
+    InvalidFieldElement(String),
+    #[error("invalid commitment {0:?}")]
+    InvalidCommitment(String),
+    /// A node of the constraints of an AIR refers to a node that is not strictly before it, or
+    /// a constraint or interaction refers to a node that does not exist.
+    #[error("AIR {air_name} (air_id = {air_id}) has invalid node index {index}")]
+    InvalidNodeIndex {
+        air_id: usize,
+        air_name: String,
+        index: usize,
+    },
+    #[error("bus {0} is registered twice")]
+    DuplicateBus(String),
+    /// The public lookup table of a bus has no rows, or rows with different numbers of fields.
+    #[error("public lookup table of bus {0} has no rows or rows of different lengths")]
+    InvalidPublicLookupTable(usize),
+}
@@ -0,0 +1,883 @@
+//! Stable JSON encoding of a verifying key, for verifiers written in other languages.
+//!
+//! Unlike the serde encoding of [MultiStarkVerifyingKey], the schema is independent of the Rust
+//! types of the key and only changes along with [VK_JSON_SCHEMA_VERSION]. Conventions:
+//!
+//! - field elements, including the allowed values of configuration public values, are strings
+//!   with their canonical value in decimal, e.g. `"2013265920"`
+//! - commitments are `0x`-prefixed lowercase hex strings of the words of the digest in order,
+//!   each in little-endian, e.g. 4 bytes per word for BabyBear digests
+//! - optional values are `null` when absent
+//! - enum variants are named in snake case: variants with data are objects whose `"type"` field
+//!   names the variant, and the other enums are strings
+//!
+//! The top-level object has the fields:
+//!
+//! - `schema_version`: [VK_JSON_SCHEMA_VERSION]
+//! - `pcs_params`: `[{ "name", "value" }]`, the parameters of the PCS that are not recorded in
+//!   the key, e.g. the FRI parameters, as given to
+//!   [to_json](MultiStarkVerifyingKey::to_json)
+//! - `features`: the names of the [VkFeature]s the key relies on
+//! - `matrix_ordering`: `"natural"` or `"height_descending"`, see [MatrixOrdering]
+//! - `commitment_rounds`: the [CommitmentRound]s in the order their commitments are observed,
+//!   e.g. `{ "type": "custom", "index": 0, "name": "memory" }` or
+//!   `{ "type": "after_challenge", "phase": 0 }`
+//! - `buses`: `[{ "name", "arity" }]`, the registered buses by index, `null` at the indices
+//!   where no bus is registered
+//! - `public_lookup_tables`: `[{ "bus_index", "rows" }]`, the rows being arrays of field
+//!   elements
+//! - `airs`: one object per AIR, by AIR id, described below
+//!
+//! Each AIR has the fields:
+//!
+//! - `name`: the name of the AIR, for diagnostics only
+//! - `preprocessed_commit`: the commitment of the preprocessed trace, if any
+//! - `preprocessed_per_height`: `{ "log_heights", "commits": [{ "log_height", "commit" }] }` if
+//!   the preprocessed trace is committed per trace height
+//! - `widths`: `{ "preprocessed", "cached_mains", "common_main", "after_challenge", "custom" }`
+//!   where `after_challenge` has one width per challenge phase, in extension field elements, and
+//!   `custom` is `[{ "round", "width" }]`
+//! - `num_public_values`
+//! - `num_challenges_to_sample` and `num_exposed_values_after_challenge`: one number per
+//!   challenge phase
+//! - `extra_rotations`, `max_log_height`, `quotient_degree`
+//! - `may_be_absent`: whether proofs may record the AIR as absent, `false` if missing
+//! - `configuration_public_values`: `[{ "index", "allowed" }]`, `allowed` being
+//!   `{ "type": "set", "values" }` or `{ "type": "range", "start", "end" }`
+//! - `rap_phase`: `"fri_log_up"` or `"gkr_log_up"`, and `log_up`:
+//!   `{ "batch_size", "num_repetitions", "commit_inverses" }`
+//! - `periodic_columns`: the period of each periodic column, as field elements
+//! - `nodes`: the constraint DAG in topological order. A node is a variable, e.g.
+//!   `{ "type": "main", "part_index": 0, "offset": 1, "index": 2 }`, a selector
+//!   (`is_first_row`, `is_last_row`, `is_transition`), `{ "type": "constant", "value" }`, or an
+//!   operation `add`, `sub` and `mul` with `left`, `right` and `degree`, or `neg` with `arg` and
+//!   `degree`, where `left`, `right` and `arg` are indices of earlier nodes
+//! - `constraints`: the indices of the nodes constrained to be zero
+//! - `interactions`: `[{ "bus_index", "type", "fields", "count" }]` where `type` is `"send"` or
+//!   `"receive"` and `fields` and `count` are node indices
+
+use p3_field::PrimeField64;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use super::{
+    types::{
+        AllowedValues, CommitmentRound, MatrixOrdering, MultiStarkVerifyingKey, StarkVerifyingKey,
+        StarkVerifyingParams, TraceWidth, VerifierPerHeightPreprocessedData,
+        VerifierSinglePreprocessedData, VkFeature, VkFeatureSet,
+    },
+    VkJsonError,
+};
+use crate::{
+    air_builders::symbolic::{
+        symbolic_variable::{Entry, SymbolicVariable},
+        SymbolicConstraintsDag, SymbolicExpressionDag, SymbolicExpressionNode,
+    },
+    config::{Com, StarkGenericConfig, Val},
+    encoding,
+    interaction::{
+        bus::{BusInfo, BusRegistry},
+        fri_log_up::FriLogUpParams,
+        public_lookup::PublicLookupTable,
+        Interaction, InteractionType, RapPhaseSeqKind,
+    },
+};
+
+/// Version of the JSON schema of verifying keys. Bumped whenever the schema changes.
+pub const VK_JSON_SCHEMA_VERSION: u64 = 3;
+
+/// Oldest schema version still decoded. Version 1 has no `may_be_absent` field, and versions 1
+/// and 2 have no `null` buses.
+pub const MIN_VK_JSON_SCHEMA_VERSION: u64 = 1;
+
+impl<SC: StarkGenericConfig> MultiStarkVerifyingKey<SC>
+where
+    Val<SC>: PrimeField64,
+    Com<SC>: Serialize + DeserializeOwned,
+{
+    /// Encodes the key as pretty-printed JSON, see the [module](self) documentation for the
+    /// schema. `pcs_params` are the named parameters of the PCS that affect verification but are
+    /// not recorded in the key, e.g. the FRI parameters.
+    pub fn to_json(&self, pcs_params: &[(&str, usize)]) -> String {
+        let json = VkJson {
+            schema_version: VK_JSON_SCHEMA_VERSION,
+            pcs_params: pcs_params
+                .iter()
+                .map(|&(name, value)| PcsParamJson {
+                    name: name.to_string(),
+                    value,
+                })
+                .collect(),
+            features: self.features.names(),
+            matrix_ordering: match self.matrix_ordering {
+                MatrixOrdering::Natural => MatrixOrderingJson::Natural,
+                MatrixOrdering::HeightDescending => MatrixOrderingJson::HeightDescending,
+            },
+            commitment_rounds: self
+                .commitment_rounds
+                .iter()
+                .map(|round| match round {
+                    CommitmentRound::Preprocessed => CommitmentRoundJson::Preprocessed,
+                    CommitmentRound::CachedMain => CommitmentRoundJson::CachedMain,
+                    CommitmentRound::CommonMain => CommitmentRoundJson::CommonMain,
+                    CommitmentRound::Custom { index, name } => CommitmentRoundJson::Custom {
+                        index: *index,
+                        name: name.clone(),
+                    },
+                    CommitmentRound::AfterChallenge { phase } => {
+                        CommitmentRoundJson::AfterChallenge { phase: *phase }
+                    }
+                    CommitmentRound::Quotient => CommitmentRoundJson::Quotient,
+                })
+                .collect(),
+            buses: self
+                .bus_registry
+                .slots()
+                .iter()
+                .map(|bus| {
+                    bus.as_ref().map(|bus| BusJson {
+                        name: bus.name.clone(),
+                        arity: bus.arity,
+                    })
+                })
+                .collect(),
+            public_lookup_tables: self
+                .public_lookup_tables
+                .iter()
+                .map(|table| PublicLookupTableJson {
+                    bus_index: table.bus_index,
+                    rows: table.rows.iter().map(|row| fields_to_json(row)).collect(),
+                })
+                .collect(),
+            airs: self.per_air.iter().map(air_to_json).collect(),
+        };
+        serde_json::to_string_pretty(&json).expect("failed to encode verifying key as JSON")
+    }
+
+    /// Decodes a key encoded by [to_json](Self::to_json), and returns it with the PCS
+    /// parameters. Malformed input, including an unsupported schema version, is rejected with
+    /// an error.
+    pub fn from_json(json: &str) -> Result<(Self, Vec<(String, usize)>), VkJsonError> {
+        let value: serde_json::Value =
+            serde_json::from_str(json).map_err(|err| VkJsonError::Json(err.to_string()))?;
+        // The version is checked first, so that a later schema is not reported as malformed.
+        match value.get("schema_version").and_then(|v| v.as_u64()) {
+            Some(MIN_VK_JSON_SCHEMA_VERSION..=VK_JSON_SCHEMA_VERSION) => {}
+            Some(version) => return Err(VkJsonError::UnsupportedSchemaVersion(version)),
+            None => return Err(VkJsonError::Json("missing schema_version".to_string())),
+        }
+        let json: VkJson =
+            serde_json::from_value(value).map_err(|err| VkJsonError::Json(err.to_string()))?;
+
+        let features = json
+            .features
+            .iter()
+            .map(|name| {
+                VkFeature::ALL
+                    .into_iter()
+                    .find(|feature| feature.name() == name)
+                    .ok_or_else(|| VkJsonError::UnknownFeature(name.clone()))
+            })
+            .collect::<Result<VkFeatureSet, _>>()?;
+        let mut buses: Vec<Option<BusInfo>> = Vec::with_capacity(json.buses.len());
+        for bus in json.buses {
+            if let Some(bus) = &bus {
+                if buses
+                    .iter()
+                    .flatten()
+                    .any(|registered| registered.name == bus.name)
+                {
+                    return Err(VkJsonError::DuplicateBus(bus.name.clone()));
+                }
+            }
+            buses.push(bus.map(|bus| BusInfo {
+                name: bus.name,
+                arity: bus.arity,
+            }));
+        }
+        let bus_registry = BusRegistry::from_buses(buses);
+        let vk = Self {
+            per_air: json
+                .airs
+                .into_iter()
+                .enumerate()
+                .map(|(air_id, air)| air_from_json(air_id, air))
+                .collect::<Result<_, _>>()?,
+            commitment_rounds: json
+                .commitment_rounds
+                .into_iter()
+                .map(|round| match round {
+                    CommitmentRoundJson::Preprocessed => CommitmentRound::Preprocessed,
+                    CommitmentRoundJson::CachedMain => CommitmentRound::CachedMain,
+                    CommitmentRoundJson::CommonMain => CommitmentRound::CommonMain,
+                    CommitmentRoundJson::Custom { index, name } => {
+                        CommitmentRound::Custom { index, name }
+                    }
+                    CommitmentRoundJson::AfterChallenge { phase } => {
+                        CommitmentRound::AfterChallenge { phase }
+                    }
+                    CommitmentRoundJson::Quotient => CommitmentRound::Quotient,
+                })
+                .collect(),
+            matrix_ordering: match json.matrix_ordering {
+                MatrixOrderingJson::Natural => MatrixOrdering::Natural,
+                MatrixOrderingJson::HeightDescending => MatrixOrdering::HeightDescending,
+            },
+            features,
+            bus_registry,
+            public_lookup_tables: json
+                .public_lookup_tables
+                .into_iter()
+                .map(|table| {
+                    let table = PublicLookupTable {
+                        bus_index: table.bus_index,
+                        rows: table
+                            .rows
+                            .iter()
+                            .map(|row| fields_from_json(row))
+                            .collect::<Result<_, _>>()?,
+                    };
+                    if !table.is_well_formed() {
+                        return Err(VkJsonError::InvalidPublicLookupTable(table.bus_index));
+                    }
+                    Ok(table)
+                })
+                .collect::<Result<_, VkJsonError>>()?,
+        };
+        let pcs_params = json
+            .pcs_params
+            .into_iter()
+            .map(|param| (param.name, param.value))
+            .collect();
+        Ok((vk, pcs_params))
+    }
+}
+
+fn air_to_json<F: PrimeField64, Com: Serialize>(vk: &StarkVerifyingKey<F, Com>) -> AirJson {
+    let params = &vk.params;
+    let width = &params.width;
+    let dag = &vk.symbolic_constraints;
+    AirJson {
+        name: vk.air_name.clone(),
+        preprocessed_commit: vk
+            .preprocessed_data
+            .as_ref()
+            .map(|data| commit_to_json(&data.commit)),
+        preprocessed_per_height: vk.preprocessed_per_height.as_ref().map(|per_height| {
+            PerHeightJson {
+                log_heights: per_height.log_heights.clone(),
+                commits: per_height
+                    .commits
+                    .iter()
+                    .map(|(log_height, commit)| HeightCommitJson {
+                        log_height: *log_height,
+                        commit: commit_to_json(commit),
+                    })
+                    .collect(),
+            }
+        }),
+        widths: WidthsJson {
+            preprocessed: width.preprocessed,
+            cached_mains: width.cached_mains.clone(),
+            common_main: width.common_main,
+            after_challenge: width.after_challenge.clone(),
+            custom: width
+                .custom
+                .iter()
+                .map(|&(round, width)| CustomWidthJson { round, width })
+                .collect(),
+        },
+        num_public_values: params.num_public_values,
+        num_challenges_to_sample: params.num_challenges_to_sample.clone(),
+        num_exposed_values_after_challenge: params.num_exposed_values_after_challenge.clone(),
+        extra_rotations: params.extra_rotations.clone(),
+        max_log_height: params.max_log_height,
+        may_be_absent: params.may_be_absent,
+        quotient_degree: vk.quotient_degree,
+        configuration_public_values: params
+            .configuration_public_values
+            .iter()
+            .map(|(index, allowed)| ConfigurationPublicValueJson {
+                index: *index,
+                allowed: match allowed {
+                    AllowedValues::Set(values) => AllowedValuesJson::Set {
+                        values: values.iter().map(u64::to_string).collect(),
+                    },
+                    AllowedValues::Range { start, end } => AllowedValuesJson::Range {
+                        start: start.to_string(),
+                        end: end.to_string(),
+                    },
+                },
+            })
+            .collect(),
+        rap_phase: match vk.rap_phase_seq_kind {
+            RapPhaseSeqKind::FriLogUp => RapPhaseJson::FriLogUp,
+            RapPhaseSeqKind::GkrLogUp => RapPhaseJson::GkrLogUp,
+        },
+        log_up: LogUpJson {
+            batch_size: vk.log_up_params.batch_size,
+            num_repetitions: vk.log_up_params.num_repetitions,
+            commit_inverses: vk.log_up_params.commit_inverses,
+        },
+        periodic_columns: vk
+            .periodic_columns
+            .iter()
+            .map(|column| fields_to_json(column))
+            .collect(),
+        nodes: dag.constraints.nodes.iter().map(node_to_json).collect(),
+        constraints: dag.constraints.constraint_idx.clone(),
+        interactions: dag
+            .interactions
+            .iter()
+            .map(|interaction| InteractionJson {
+                bus_index: interaction.bus_index,
+                interaction_type: match interaction.interaction_type {
+                    InteractionType::Send => InteractionTypeJson::Send,
+                    InteractionType::Receive => InteractionTypeJson::Receive,
+                },
+                fields: interaction.fields.clone(),
+                count: interaction.count,
+            })
+            .collect(),
+    }
+}
+
+fn air_from_json<F: PrimeField64, Com: Serialize + DeserializeOwned>(
+    air_id: usize,
+    air: AirJson,
+) -> Result<StarkVerifyingKey<F, Com>, VkJsonError> {
+    let air_name = air.name.clone();
+    let invalid_index = |index| VkJsonError::InvalidNodeIndex {
+        air_id,
+        air_name: air_name.clone(),
+        index,
+    };
+    let mut nodes = Vec::with_capacity(air.nodes.len());
+    for (node_idx, node) in air.nodes.into_iter().enumerate() {
+        nodes.push(node_from_json(node_idx, node).map_err(|err| match err {
+            NodeError::Index(index) => invalid_index(index),
+            NodeError::Json(err) => err,
+        })?);
+    }
+    let num_nodes = nodes.len();
+    let check_index = |index: usize| {
+        if index < num_nodes {
+            Ok(index)
+        } else {
+            Err(invalid_index(index))
+        }
+    };
+    let constraint_idx = air
+        .constraints
+        .into_iter()
+        .map(check_index)
+        .collect::<Result<_, _>>()?;
+    let interactions = air
+        .interactions
+        .into_iter()
+        .map(|interaction| {
+            Ok(Interaction {
+                fields: interaction
+                    .fields
+                    .into_iter()
+                    .map(check_index)
+                    .collect::<Result<_, _>>()?,
+                count: check_index(interaction.count)?,
+                bus_index: interaction.bus_index,
+                interaction_type: match interaction.interaction_type {
+                    InteractionTypeJson::Send => InteractionType::Send,
+                    InteractionTypeJson::Receive => InteractionType::Receive,
+                },
+            })
+        })
+        .collect::<Result<_, VkJsonError>>()?;
+
+    Ok(StarkVerifyingKey {
+        preprocessed_data: air
+            .preprocessed_commit
+            .map(|commit| {
+                Ok::<_, VkJsonError>(VerifierSinglePreprocessedData {
+                    commit: commit_from_json(&commit)?,
+                })
+            })
+            .transpose()?,
+        params: StarkVerifyingParams {
+            width: TraceWidth {
+                preprocessed: air.widths.preprocessed,
+                cached_mains: air.widths.cached_mains,
+                common_main: air.widths.common_main,
+                after_challenge: air.widths.after_challenge,
+                custom: air
+                    .widths
+                    .custom
+                    .into_iter()
+                    .map(|custom| (custom.round, custom.width))
+                    .collect(),
+            },
+            num_public_values: air.num_public_values,
+            num_exposed_values_after_challenge: air.num_exposed_values_after_challenge,
+            num_challenges_to_sample: air.num_challenges_to_sample,
+            extra_rotations: air.extra_rotations,
+            configuration_public_values: air
+                .configuration_public_values
+                .into_iter()
+                .map(|value| {
+                    let allowed = match value.allowed {
+                        AllowedValuesJson::Set { values } => AllowedValues::Set(
+                            values
+                                .iter()
+                                .map(|value| canonical_from_json::<F>(value))
+                                .collect::<Result<_, _>>()?,
+                        ),
+                        AllowedValuesJson::Range { start, end } => AllowedValues::Range {
+                            start: canonical_from_json::<F>(&start)?,
+                            end: canonical_from_json::<F>(&end)?,
+                        },
+                    };
+                    Ok((value.index, allowed))
+                })
+                .collect::<Result<_, VkJsonError>>()?,
+            max_log_height: air.max_log_height,
+            may_be_absent: air.may_be_absent,
+        },
+        symbolic_constraints: SymbolicConstraintsDag {
+            constraints: SymbolicExpressionDag {
+                nodes,
+                constraint_idx,
+            },
+            interactions,
+        },
+        quotient_degree: air.quotient_degree,
+        rap_phase_seq_kind: match air.rap_phase {
+            RapPhaseJson::FriLogUp => RapPhaseSeqKind::FriLogUp,
+            RapPhaseJson::GkrLogUp => RapPhaseSeqKind::GkrLogUp,
+        },
+        log_up_params: FriLogUpParams {
+            batch_size: air.log_up.batch_size,
+            num_repetitions: air.log_up.num_repetitions,
+            commit_inverses: air.log_up.commit_inverses,
+        },
+        periodic_columns: air
+            .periodic_columns
+            .iter()
+            .map(|column| fields_from_json(column))
+            .collect::<Result<_, _>>()?,
+        preprocessed_per_height: air
+            .preprocessed_per_height
+            .map(|per_height| {
+                Ok::<_, VkJsonError>(VerifierPerHeightPreprocessedData {
+                    log_heights: per_height.log_heights,
+                    commits: per_height
+                        .commits
+                        .iter()
+                        .map(|height| Ok((height.log_height, commit_from_json(&height.commit)?)))
+                        .collect::<Result<_, VkJsonError>>()?,
+                })
+            })
+            .transpose()?,
+        air_name: air.name,
+    })
+}
+
+fn node_to_json<F: PrimeField64>(node: &SymbolicExpressionNode<F>) -> NodeJson {
+    match node {
+        SymbolicExpressionNode::Variable(var) => {
+            let index = var.index;
+            match var.entry {
+                Entry::Preprocessed { offset } => NodeJson::Preprocessed { offset, index },
+                Entry::Main { part_index, offset } => NodeJson::Main {
+                    part_index,
+                    offset,
+                    index,
+                },
+                Entry::Permutation { phase, offset } => NodeJson::Permutation {
+                    phase,
+                    offset,
+                    index,
+                },
+                Entry::Public => NodeJson::Public { index },
+                Entry::Challenge { phase } => NodeJson::Challenge { phase, index },
+                Entry::Exposed { phase } => NodeJson::Exposed { phase, index },
+                Entry::Periodic => NodeJson::Periodic { index },
+            }
+        }
+        SymbolicExpressionNode::IsFirstRow => NodeJson::IsFirstRow,
+        SymbolicExpressionNode::IsLastRow => NodeJson::IsLastRow,
+        SymbolicExpressionNode::IsTransition => NodeJson::IsTransition,
+        SymbolicExpressionNode::Constant(value) => NodeJson::Constant {
+            value: field_to_json(*value),
+        },
+        SymbolicExpressionNode::Add {
+            left_idx,
+            right_idx,
+            degree_multiple,
+        } => NodeJson::Add {
+            left: *left_idx,
+            right: *right_idx,
+            degree: *degree_multiple,
+        },
+        SymbolicExpressionNode::Sub {
+            left_idx,
+            right_idx,
+            degree_multiple,
+        } => NodeJson::Sub {
+            left: *left_idx,
+            right: *right_idx,
+            degree: *degree_multiple,
+        },
+        SymbolicExpressionNode::Neg {
+            idx,
+            degree_multiple,
+        } => NodeJson::Neg {
+            arg: *idx,
+            degree: *degree_multiple,
+        },
+        SymbolicExpressionNode::Mul {
+            left_idx,
+            right_idx,
+            degree_multiple,
+        } => NodeJson::Mul {
+            left: *left_idx,
+            right: *right_idx,
+            degree: *degree_multiple,
+        },
+    }
+}
+
+enum NodeError {
+    /// A child index that is not strictly before the node.
+    Index(usize),
+    Json(VkJsonError),
+}
+
+fn node_from_json<F: PrimeField64>(
+    node_idx: usize,
+    node: NodeJson,
+) -> Result<SymbolicExpressionNode<F>, NodeError> {
+    let child = |idx: usize| {
+        if idx < node_idx {
+            Ok(idx)
+        } else {
+            Err(NodeError::Index(idx))
+        }
+    };
+    let variable =
+        |entry, index| SymbolicExpressionNode::Variable(SymbolicVariable::new(entry, index));
+    Ok(match node {
+        NodeJson::Preprocessed { offset, index } => variable(Entry::Preprocessed { offset }, index),
+        NodeJson::Main {
+            part_index,
+            offset,
+            index,
+        } => variable(Entry::Main { part_index, offset }, index),
+        NodeJson::Permutation {
+            phase,
+            offset,
+            index,
+        } => variable(Entry::Permutation { phase, offset }, index),
+        NodeJson::Public { index } => variable(Entry::Public, index),
+        NodeJson::Challenge { phase, index } => variable(Entry::Challenge { phase }, index),
+        NodeJson::Exposed { phase, index } => variable(Entry::Exposed { phase }, index),
+        NodeJson::Periodic { index } => variable(Entry::Periodic, index),
+        NodeJson::IsFirstRow => SymbolicExpressionNode::IsFirstRow,
+        NodeJson::IsLastRow => SymbolicExpressionNode::IsLastRow,
+        NodeJson::IsTransition => SymbolicExpressionNode::IsTransition,
+        NodeJson::Constant { value } => {
+            SymbolicExpressionNode::Constant(field_from_json(&value).map_err(NodeError::Json)?)
+        }
+        NodeJson::Add {
+            left,
+            right,
+            degree,
+        } => SymbolicExpressionNode::Add {
+            left_idx: child(left)?,
+            right_idx: child(right)?,
+            degree_multiple: degree,
+        },
+        NodeJson::Sub {
+            left,
+            right,
+            degree,
+        } => SymbolicExpressionNode::Sub {
+            left_idx: child(left)?,
+            right_idx: child(right)?,
+            degree_multiple: degree,
+        },
+        NodeJson::Neg { arg, degree } => SymbolicExpressionNode::Neg {
+            idx: child(arg)?,
+            degree_multiple: degree,
+        },
+        NodeJson::Mul {
+            left,
+            right,
+            degree,
+        } => SymbolicExpressionNode::Mul {
+            left_idx: child(left)?,
+            right_idx: child(right)?,
+            degree_multiple: degree,
+        },
+    })
+}
+
+fn field_to_json<F: PrimeField64>(value: F) -> String {
+    value.as_canonical_u64().to_string()
+}
+
+fn fields_to_json<F: PrimeField64>(values: &[F]) -> Vec<String> {
+    values.iter().map(|&value| field_to_json(value)).collect()
+}
+
+/// Parses the canonical value of an element of `F`.
+fn canonical_from_json<F: PrimeField64>(value: &str) -> Result<u64, VkJsonError> {
+    // `u64::from_str` accepts a leading `+` and leading zeros, which are not canonical.
+    value
+        .parse::<u64>()
+        .ok()
+        .filter(|&v| v < F::ORDER_U64 && v.to_string() == value)
+        .ok_or_else(|| VkJsonError::InvalidFieldElement(value.to_string()))
+}
+
+fn field_from_json<F: PrimeField64>(value: &str) -> Result<F, VkJsonError> {
+    canonical_from_json::<F>(value).map(F::from_canonical_u64)
+}
+
+fn fields_from_json<F: PrimeField64>(values: &[String]) -> Result<Vec<F>, VkJsonError> {
+    values.iter().map(|value| field_from_json(value)).collect()
+}
+
+fn commit_to_json<Com: Serialize>(commit: &Com) -> String {
+    let bytes = encoding::to_bytes(commit).expect("failed to encode commitment");
+    let mut hex = String::with_capacity(2 + 2 * bytes.len());
+    hex.push_str("0x");
+    for byte in bytes {
+        hex.push_str(&format!("{byte:02x}"));
+    }
+    hex
+}
+
+fn commit_from_json<Com: Serialize + DeserializeOwned>(hex: &str) -> Result<Com, VkJsonError> {
+    let invalid = || VkJsonError::InvalidCommitment(hex.to_string());
+    let digits = hex.strip_prefix("0x").ok_or_else(invalid)?;
+    if digits.len() % 2 != 0
+        || !digits
+            .bytes()
+            .all(|c| matches!(c, b'0'..=b'9' | b'a'..=b'f'))
+    {
+        return Err(invalid());
+    }
+    let bytes = (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).unwrap())
+        .collect::<Vec<_>>();
+    encoding::from_bytes(&bytes).map_err(|_| invalid())
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct VkJson {
+    schema_version: u64,
+    pcs_params: Vec<PcsParamJson>,
+    features: Vec<String>,
+    matrix_ordering: MatrixOrderingJson,
+    commitment_rounds: Vec<CommitmentRoundJson>,
+    buses: Vec<Option<BusJson>>,
+    public_lookup_tables: Vec<PublicLookupTableJson>,
+    airs: Vec<AirJson>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PcsParamJson {
+    name: String,
+    value: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum MatrixOrderingJson {
+    Natural,
+    HeightDescending,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum CommitmentRoundJson {
+    Preprocessed,
+    CachedMain,
+    CommonMain,
+    Custom { index: usize, name: String },
+    AfterChallenge { phase: usize },
+    Quotient,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BusJson {
+    name: String,
+    arity: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PublicLookupTableJson {
+    bus_index: usize,
+    rows: Vec<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AirJson {
+    name: String,
+    preprocessed_commit: Option<String>,
+    preprocessed_per_height: Option<PerHeightJson>,
+    widths: WidthsJson,
+    num_public_values: usize,
+    num_challenges_to_sample: Vec<usize>,
+    num_exposed_values_after_challenge: Vec<usize>,
+    extra_rotations: Vec<usize>,
+    max_log_height: Option<usize>,
+    #[serde(default)]
+    may_be_absent: bool,
+    quotient_degree: u8,
+    configuration_public_values: Vec<ConfigurationPublicValueJson>,
+    rap_phase: RapPhaseJson,
+    log_up: LogUpJson,
+    periodic_columns: Vec<Vec<String>>,
+    nodes: Vec<NodeJson>,
+    constraints: Vec<usize>,
+    interactions: Vec<InteractionJson>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PerHeightJson {
+    log_heights: Vec<usize>,
+    commits: Vec<HeightCommitJson>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct HeightCommitJson {
+    log_height: usize,
+    commit: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WidthsJson {
+    preprocessed: Option<usize>,
+    cached_mains: Vec<usize>,
+    common_main: usize,
+    after_challenge: Vec<usize>,
+    custom: Vec<CustomWidthJson>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CustomWidthJson {
+    round: usize,
+    width: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigurationPublicValueJson {
+    index: usize,
+    allowed: AllowedValuesJson,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum AllowedValuesJson {
+    Set { values: Vec<String> },
+    Range { start: String, end: String },
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum RapPhaseJson {
+    FriLogUp,
+    GkrLogUp,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LogUpJson {
+    batch_size: Option<usize>,
+    num_repetitions: usize,
+    commit_inverses: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum NodeJson {
+    Preprocessed {
+        offset: usize,
+        index: usize,
+    },
+    Main {
+        part_index: usize,
+        offset: usize,
+        index: usize,
+    },
+    Permutation {
+        phase: usize,
+        offset: usize,
+        index: usize,
+    },
+    Public {
+        index: usize,
+    },
+    Challenge {
+        phase: usize,
+        index: usize,
+    },
+    Exposed {
+        phase: usize,
+        index: usize,
+    },
+    Periodic {
+        index: usize,
+    },
+    IsFirstRow,
+    IsLastRow,
+    IsTransition,
+    Constant {
+        value: String,
+    },
+    Add {
+        left: usize,
+        right: usize,
+        degree: usize,
+    },
+    Sub {
+        left: usize,
+        right: usize,
+        degree: usize,
+    },
+    Neg {
+        arg: usize,
+        degree: usize,
+    },
+    Mul {
+        left: usize,
+        right: usize,
+        degree: usize,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct InteractionJson {
+    bus_index: usize,
+    #[serde(rename = "type")]
+    interaction_type: InteractionTypeJson,
+    fields: Vec<usize>,
+    count: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum InteractionTypeJson {
+    Send,
+    Receive,
+}
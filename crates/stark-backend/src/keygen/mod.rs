@@ -1,29 +1,92 @@
-use std::{iter::zip, sync::Arc};
+use std::{
+    collections::{BTreeMap, HashSet},
+    iter::{self, zip},
+    sync::Arc,
+};
 
 use itertools::Itertools;
 use p3_commit::Pcs;
-use p3_field::FieldExtensionAlgebra;
-use p3_matrix::Matrix;
+use p3_field::{Field, FieldAlgebra, FieldExtensionAlgebra};
+use p3_matrix::{dense::RowMajorMatrix, Matrix};
+use p3_maybe_rayon::prelude::*;
+use p3_util::log2_ceil_usize;
 use tracing::instrument;
 
 use crate::{
-    air_builders::symbolic::{get_symbolic_builder, SymbolicRapBuilder},
+    air_builders::symbolic::{
+        get_symbolic_builder, symbolic_variable::Entry, SymbolicConstraints,
+        SymbolicConstraintsDag, SymbolicExpressionNode, SymbolicRapBuilder,
+    },
     config::{Com, RapPartialProvingKey, StarkGenericConfig, Val},
-    interaction::{RapPhaseSeq, RapPhaseSeqKind},
+    interaction::{
+        bus::{BusIndex, BusRegistry},
+        fri_log_up::{find_interaction_chunks, fri_log_up_constraint_degree, FriLogUpParams},
+        public_lookup::PublicLookupTable,
+        RapPhaseSeq, RapPhaseSeqKind, RapPhases,
+    },
     keygen::types::{
-        MultiStarkProvingKey, ProverOnlySinglePreprocessedData, StarkProvingKey, StarkVerifyingKey,
-        TraceWidth, VerifierSinglePreprocessedData,
+        preprocessed_content_hash, two_adicity, CommitmentRound, MatrixOrdering,
+        MultiStarkProvingKey, PreprocessedShape, ProverOnlySinglePreprocessedData, StarkProvingKey,
+        StarkVerifyingKey, TraceWidth, VerifierPerHeightPreprocessedData,
+        VerifierSinglePreprocessedData, VkFeature,
     },
+    prover::cpu::interleave_rows,
     rap::AnyRap,
 };
 
+pub mod consistency;
+pub mod cost;
+pub mod digest;
+mod error;
+#[cfg(feature = "vk-json")]
+pub mod json;
+pub mod summary;
 pub mod types;
 pub(crate) mod view;
 
+pub use error::*;
+
 struct AirKeygenBuilder<SC: StarkGenericConfig> {
     air: Arc<dyn AnyRap<SC>>,
+    /// Name given when the AIR was added, instead of the [name](AnyRap::name) of the AIR.
+    name: Option<String>,
     rap_phase_seq_kind: RapPhaseSeqKind,
+    log_up_params: FriLogUpParams,
+    /// Computed by [MultiStarkKeygenBuilder::try_generate_pk], so that the preprocessed traces
+    /// of all AIRs are generated in parallel.
     prep_keygen_data: PrepKeygenData<SC>,
+    /// (custom round index, width) of each custom round matrix of the AIR
+    custom_widths: Vec<(usize, usize)>,
+    max_log_height: Option<usize>,
+    quotient_degree_override: Option<usize>,
+    may_be_absent: bool,
+}
+
+/// A matrix of a custom commitment round, belonging to the AIR with id `air_id`.
+#[derive(Clone, Copy, Debug)]
+pub struct CustomMatrixSpec {
+    pub air_id: usize,
+    pub width: usize,
+}
+
+/// Limits on the interactions of a system of AIRs, e.g. those of a recursive verifier circuit.
+/// Checked by [MultiStarkKeygenBuilder::try_generate_pk]. `None` means no limit.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct InteractionLimits {
+    pub max_interactions_per_air: Option<usize>,
+    pub max_total_interactions: Option<usize>,
+    /// Maximum number of fields of the message of an interaction.
+    pub max_fields_per_message: Option<usize>,
+}
+
+/// Where a custom commitment round is committed, relative to the main trace commitments.
+/// All custom rounds are committed before any challenge is sampled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CustomRoundPosition {
+    /// After the preprocessed commitments and before the cached main commitments.
+    BeforeMain,
+    /// After the common main commitment and before the first challenge phase.
+    AfterMain,
 }
 
 /// Stateful builder to create multi-stark proving and verifying keys
@@ -33,6 +96,16 @@ pub struct MultiStarkKeygenBuilder<'a, SC: StarkGenericConfig> {
     /// Information for partitioned AIRs.
     partitioned_airs: Vec<AirKeygenBuilder<SC>>,
     max_constraint_degree: usize,
+    max_supported_constraint_degree: Option<usize>,
+    /// (name, position) of each custom round, in the order they were added
+    custom_rounds: Vec<(String, CustomRoundPosition)>,
+    matrix_ordering: MatrixOrdering,
+    prune_interactions: bool,
+    bus_registry: BusRegistry,
+    public_lookup_tables: Vec<PublicLookupTable<Val<SC>>>,
+    interaction_limits: InteractionLimits,
+    parallel: bool,
+    zero_knowledge: bool,
 }
 
 impl<'a, SC: StarkGenericConfig> MultiStarkKeygenBuilder<'a, SC> {
@@ -41,9 +114,25 @@ impl<'a, SC: StarkGenericConfig> MultiStarkKeygenBuilder<'a, SC> {
             config,
             partitioned_airs: vec![],
             max_constraint_degree: 0,
+            max_supported_constraint_degree: None,
+            custom_rounds: vec![],
+            matrix_ordering: MatrixOrdering::Natural,
+            prune_interactions: true,
+            bus_registry: BusRegistry::default(),
+            public_lookup_tables: vec![],
+            interaction_limits: InteractionLimits::default(),
+            parallel: true,
+            zero_knowledge: false,
         }
     }
 
+    /// Sets the order of the matrices within the common main and custom commitment rounds.
+    /// The ordering is recorded in the verifying key, so keys with different orderings are not
+    /// interchangeable.
+    pub fn set_matrix_ordering(&mut self, matrix_ordering: MatrixOrdering) {
+        self.matrix_ordering = matrix_ordering;
+    }
+
     /// The builder will **try** to keep the max constraint degree across all AIRs below this value.
     /// If it is given AIRs that exceed this value, it will still include them.
     ///
@@ -52,29 +141,464 @@ impl<'a, SC: StarkGenericConfig> MultiStarkKeygenBuilder<'a, SC> {
         self.max_constraint_degree = max_constraint_degree;
     }
 
+    /// Sets the maximum constraint degree supported by the PCS, e.g. the maximum constraint degree
+    /// of the FRI parameters. Unlike [set_max_constraint_degree](Self::set_max_constraint_degree),
+    /// this is a hard limit: [try_generate_pk](Self::try_generate_pk) returns an error if the
+    /// constraints of an AIR, including the log-up constraints, exceed it.
+    pub fn set_max_supported_constraint_degree(&mut self, max_supported_constraint_degree: usize) {
+        self.max_supported_constraint_degree = Some(max_supported_constraint_degree);
+    }
+
+    /// Sets whether to prune the interactions of each AIR, which is enabled by default. Pruning
+    /// drops the interactions whose count is the constant zero and merges the interactions with
+    /// the same bus, fields and type by summing their counts, which leaves the messages on each
+    /// bus unchanged. The removed interactions are reported in
+    /// [StarkProvingKey::pruned_interactions].
+    ///
+    /// Disabling it keeps the interactions as the AIRs push them, e.g. for debugging.
+    pub fn set_prune_interactions(&mut self, prune_interactions: bool) {
+        self.prune_interactions = prune_interactions;
+    }
+
+    /// Sets the limits on the interactions of the AIRs. The interactions are counted after
+    /// pruning, see [set_prune_interactions](Self::set_prune_interactions).
+    pub fn set_interaction_limits(&mut self, interaction_limits: InteractionLimits) {
+        self.interaction_limits = interaction_limits;
+    }
+
+    /// Sets whether the per-AIR work of [try_generate_pk](Self::try_generate_pk) runs in
+    /// parallel, which is enabled by default and requires the `parallel` feature. The keys are
+    /// identical either way.
+    pub fn set_parallel(&mut self, parallel: bool) {
+        self.parallel = parallel;
+    }
+
+    /// Sets whether the proofs are zero-knowledge, which is disabled by default. The mode is
+    /// recorded in the verifying key.
+    ///
+    /// In zero-knowledge mode, the prover commits each main and after challenge trace of height
+    /// `n` as a matrix of height `2n` whose even rows are the rows of the trace and whose odd rows
+    /// are random. The committed polynomial is the trace polynomial plus the vanishing polynomial
+    /// of the trace domain times a random polynomial of degree below `n`: the constraints still
+    /// hold on the trace domain, so the padding rows need no masking, and up to `n` evaluations
+    /// outside the trace domain are uniformly random. The prover therefore rejects traces lower
+    /// than the number of points they are opened at, FRI queries included, see
+    /// [check_zero_knowledge_heights](MultiStarkProvingKey::check_zero_knowledge_heights). The
+    /// preprocessed traces are public: they are committed with zero odd rows, so that every
+    /// committed trace is twice as high.
+    ///
+    /// The quotient of an AIR with constraint degree `d` then has degree below `(2d - 1) n`, so
+    /// the quotient degree of the AIR is `2d - 1` rounded up to a power of two. Before it is
+    /// split into chunks, a random polynomial is added to the quotient, and is opened at `zeta`
+    /// for the verifier to subtract. The committed traces are twice as high, so the FRI blowup
+    /// must be at least half the quotient degree: the maximum supported constraint degree, see
+    /// [set_max_supported_constraint_degree](Self::set_max_supported_constraint_degree), is one
+    /// lower than without zero knowledge.
+    ///
+    /// The proofs are only hiding if the config salts the leaves of its Merkle trees, so only an
+    /// engine with [zero_knowledge](crate::engine::StarkEngine::zero_knowledge) parameters proves
+    /// for a zero-knowledge key.
+    ///
+    /// The cached main traces must be committed by the prover rather than pre-committed. The
+    /// GKR log-up challenge phase is not supported, since the verifier checks the opening of its
+    /// eq columns directly: [try_generate_pk](Self::try_generate_pk) returns an error.
+    pub fn set_zero_knowledge(&mut self, zero_knowledge: bool) {
+        self.zero_knowledge = zero_knowledge;
+    }
+
+    /// Registers a bus whose messages have `arity` fields, and returns its index. The arity of the
+    /// interactions on the bus is checked by [try_generate_pk](Self::try_generate_pk), and the
+    /// name of the bus is recorded in the verifying key.
+    ///
+    /// Buses are indexed in registration order, after the buses added by
+    /// [add_raw_bus](Self::add_raw_bus). Once a bus is registered, the buses AIRs interact on by
+    /// raw index must be added with [add_raw_bus](Self::add_raw_bus), so that they never share
+    /// the index of a named bus.
+    ///
+    /// ## Panics
+    /// If a bus named `name` is already registered.
+    pub fn add_bus(&mut self, name: impl Into<String>, arity: usize) -> BusIndex {
+        self.bus_registry.register(name, arity)
+    }
+
+    /// Registers the bus with raw index `bus_index`, whose messages have `arity` fields, for AIRs
+    /// interacting on buses by raw index alongside named buses. The bus is named
+    /// `bus_{bus_index}`.
+    ///
+    /// ## Panics
+    /// If a bus is already registered at `bus_index`, e.g. a bus added by
+    /// [add_bus](Self::add_bus).
+    pub fn add_raw_bus(&mut self, bus_index: usize, arity: usize) -> BusIndex {
+        self.bus_registry.register_raw(bus_index, arity)
+    }
+
+    pub fn bus_registry(&self) -> &BusRegistry {
+        &self.bus_registry
+    }
+
+    /// Adds a public table of `rows` receiving the messages of bus `bus_index`. The verifier
+    /// evaluates the side of the log-up argument of the table itself from the multiplicities
+    /// revealed by the prover, so no AIR has to receive the messages. Every message sent on the
+    /// bus must be a row of the table.
+    ///
+    /// Only supported by FRI log-up. The rows are observed by the verifier, so the table should
+    /// be small.
+    ///
+    /// ## Panics
+    /// If `rows` is empty or its rows do not all have the same number of fields, if the bus
+    /// already has a table, or if the bus is registered with another arity.
+    pub fn add_public_lookup_table(&mut self, bus_index: usize, rows: Vec<Vec<Val<SC>>>) {
+        assert!(!rows.is_empty(), "public lookup table has no rows");
+        let arity = rows[0].len();
+        assert!(
+            rows.iter().all(|row| row.len() == arity),
+            "rows of public lookup table have different numbers of fields"
+        );
+        assert!(
+            self.public_lookup_tables
+                .iter()
+                .all(|table| table.bus_index != bus_index),
+            "bus {bus_index} already has a public lookup table"
+        );
+        if let Some(bus) = self.bus_registry.get(bus_index) {
+            assert_eq!(
+                bus.arity, arity,
+                "public lookup table of bus {} has {arity} fields, but the bus has arity {}",
+                bus.name, bus.arity
+            );
+        }
+        self.public_lookup_tables
+            .push(PublicLookupTable { bus_index, rows });
+    }
+
     /// Default way to add a single Interactive AIR.
     /// Returns `air_id`
     #[instrument(level = "debug", skip_all)]
     pub fn add_air(&mut self, air: Arc<dyn AnyRap<SC>>) -> usize {
         self.partitioned_airs.push(AirKeygenBuilder::new(
-            self.config.pcs(),
             SC::RapPhaseSeq::ID,
+            self.config.rap_phase_seq().log_up_params(),
             air,
         ));
         self.partitioned_airs.len() - 1
     }
 
+    /// Same as [add_air](Self::add_air), but the AIR is called `name` instead of its
+    /// [name](AnyRap::name) in the keys, diagnostics, metrics and errors. Useful to tell apart
+    /// several AIRs of the same type.
+    pub fn add_named_air(&mut self, name: impl Into<String>, air: Arc<dyn AnyRap<SC>>) -> usize {
+        let air_id = self.add_air(air);
+        self.partitioned_airs[air_id].name = Some(name.into());
+        air_id
+    }
+
+    /// Replaces the AIR with id `air_id` by `air`, keeping its id, its name if it was added with
+    /// [add_named_air](Self::add_named_air), its custom round matrices, its maximum log height
+    /// and its quotient degree override. Nothing is computed from an AIR before
+    /// [try_generate_pk](Self::try_generate_pk), so only the new AIR is used for keygen.
+    ///
+    /// ## Panics
+    /// If there is no AIR with id `air_id`.
+    pub fn replace_air(&mut self, air_id: usize, air: Arc<dyn AnyRap<SC>>) {
+        self.check_air_id(air_id);
+        self.partitioned_airs[air_id].air = air;
+    }
+
+    /// Removes the AIR with id `air_id` and its custom round matrices. The ids are compacted:
+    /// the id of every AIR added after it decreases by one, so ids obtained before the removal
+    /// must be updated.
+    ///
+    /// A custom round left without matrices is removed too, and the index of every custom round
+    /// added after it decreases by one.
+    ///
+    /// ## Panics
+    /// If there is no AIR with id `air_id`.
+    pub fn remove_air(&mut self, air_id: usize) {
+        self.check_air_id(air_id);
+        let removed = self.partitioned_airs.remove(air_id);
+        for &(index, _) in removed.custom_widths.iter().rev() {
+            let is_empty = self.partitioned_airs.iter().all(|keygen_builder| {
+                keygen_builder
+                    .custom_widths
+                    .iter()
+                    .all(|&(round, _)| round != index)
+            });
+            if !is_empty {
+                continue;
+            }
+            self.custom_rounds.remove(index);
+            for keygen_builder in &mut self.partitioned_airs {
+                for (round, _) in &mut keygen_builder.custom_widths {
+                    if *round > index {
+                        *round -= 1;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Bounds the trace height of the AIR with id `air_id` by `2^max_log_height`. The bound is
+    /// recorded in the verifying key: the prover rejects taller traces, and the verifier rejects
+    /// proofs with taller traces before any hashing.
+    ///
+    /// By default, the trace height is bounded by the two-adicity of the field, see
+    /// [StarkVerifyingKey::max_log_height].
+    ///
+    /// ## Panics
+    /// If there is no AIR with id `air_id`, or if `max_log_height` is above the two-adicity of
+    /// the field.
+    pub fn set_max_log_height(&mut self, air_id: usize, max_log_height: usize) {
+        self.check_air_id(air_id);
+        let two_adicity = two_adicity::<Val<SC>>();
+        assert!(
+            max_log_height <= two_adicity,
+            "max log height {max_log_height} is above the two-adicity {two_adicity} of the field"
+        );
+        self.partitioned_airs[air_id].max_log_height = Some(max_log_height);
+    }
+
+    /// Sets the quotient degree of the AIR with id `air_id`, instead of the smallest quotient
+    /// degree its constraints allow, e.g. so that several AIRs have the same number of quotient
+    /// chunks. The quotient degree is recorded in the verifying key.
+    ///
+    /// [try_generate_pk](Self::try_generate_pk) returns an error if `degree` is not a power of
+    /// two, is below the quotient degree the constraints of the AIR require, or exceeds the
+    /// quotient degree supported by the PCS, which is one less than the
+    /// [maximum supported constraint degree](Self::set_max_supported_constraint_degree), or twice
+    /// that in [zero-knowledge mode](Self::set_zero_knowledge). Without a maximum supported
+    /// constraint degree, the PCS bound is unknown, so `degree` must be the quotient degree the
+    /// constraints require. The keygen builder of a
+    /// [StarkEngine](crate::engine::StarkEngine) sets the bound from its PCS.
+    ///
+    /// ## Panics
+    /// If there is no AIR with id `air_id`.
+    pub fn set_quotient_degree_override(&mut self, air_id: usize, degree: usize) {
+        self.check_air_id(air_id);
+        self.partitioned_airs[air_id].quotient_degree_override = Some(degree);
+    }
+
+    /// Allows the AIR with id `air_id` to be absent from proofs, with no trace at all, see
+    /// [ProofInput::absent_air_ids](crate::prover::types::ProofInput::absent_air_ids). An absent
+    /// AIR sends and receives nothing, so [try_generate_pk](Self::try_generate_pk) returns an
+    /// error if the count of an interaction of the AIR does not vanish on a row of zeros of its
+    /// main trace. Proofs for a key with an AIR that may be absent must still account for every
+    /// AIR, each either proven or absent.
+    ///
+    /// ## Panics
+    /// If there is no AIR with id `air_id`.
+    pub fn set_may_be_absent(&mut self, air_id: usize) {
+        self.check_air_id(air_id);
+        self.partitioned_airs[air_id].may_be_absent = true;
+    }
+
+    /// Returns the `(air_id, name)` of each AIR, in increasing order of id.
+    pub fn airs(&self) -> Vec<(usize, String)> {
+        self.partitioned_airs
+            .iter()
+            .map(|keygen_builder| keygen_builder.name())
+            .enumerate()
+            .collect()
+    }
+
+    /// Returns the AIR with id `air_id`, if any.
+    pub fn air(&self, air_id: usize) -> Option<&Arc<dyn AnyRap<SC>>> {
+        self.partitioned_airs
+            .get(air_id)
+            .map(|keygen_builder| &keygen_builder.air)
+    }
+
+    fn check_air_id(&self, air_id: usize) {
+        assert!(
+            air_id < self.partitioned_airs.len(),
+            "no AIR with id {air_id}: {} AIRs are registered",
+            self.partitioned_airs.len()
+        );
+    }
+
+    /// Adds a custom commitment round with one matrix for each of `matrix_specs`. The round is
+    /// committed at `position` in the sequence of commitment rounds, so its commitment is observed
+    /// before any challenge is sampled.
+    ///
+    /// The matrices are additional partitions of the main trace of their AIRs, and are available
+    /// to the AIR constraints via
+    /// [custom_mains](crate::air_builders::PartitionedAirBuilder::custom_mains), ordered by
+    /// custom round index. The prover provides them in
+    /// [AirProofRawInput::custom_mains](crate::prover::types::AirProofRawInput::custom_mains).
+    ///
+    /// The AIRs must already have been added. Returns the index of the custom round.
+    pub fn add_custom_round(
+        &mut self,
+        name: impl Into<String>,
+        matrix_specs: Vec<CustomMatrixSpec>,
+        position: CustomRoundPosition,
+    ) -> usize {
+        let index = self.custom_rounds.len();
+        assert!(!matrix_specs.is_empty(), "custom round has no matrices");
+        assert!(
+            matrix_specs.iter().map(|spec| spec.air_id).all_unique(),
+            "custom round has multiple matrices for the same AIR"
+        );
+        for spec in matrix_specs {
+            assert!(spec.width > 0, "custom round matrix has zero width");
+            self.partitioned_airs[spec.air_id]
+                .custom_widths
+                .push((index, spec.width));
+        }
+        self.custom_rounds.push((name.into(), position));
+        index
+    }
+
     /// Consume the builder and generate proving key.
     /// The verifying key can be obtained from the proving key.
-    pub fn generate_pk(mut self) -> MultiStarkProvingKey<SC> {
-        let air_max_constraint_degree = self
-            .partitioned_airs
-            .iter()
-            .map(|keygen_builder| {
-                let max_constraint_degree = keygen_builder.max_constraint_degree();
+    ///
+    /// # Panics
+    /// If [try_generate_pk](Self::try_generate_pk) returns an error.
+    pub fn generate_pk(self) -> MultiStarkProvingKey<SC> {
+        self.try_generate_pk().unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Consume the builder and generate proving key.
+    /// The verifying key can be obtained from the proving key.
+    ///
+    /// Returns an error if the constraint degree of an AIR exceeds the maximum supported
+    /// constraint degree, see
+    /// [set_max_supported_constraint_degree](Self::set_max_supported_constraint_degree). This
+    /// includes the degree of the log-up constraints when the RAP phase fixes the interaction
+    /// batch size.
+    ///
+    /// Also returns an error if an interaction on a registered bus does not have the arity of
+    /// the bus, see [add_bus](Self::add_bus), or on a bus with a public lookup table the arity
+    /// of the table, see [add_public_lookup_table](Self::add_public_lookup_table), if an
+    /// interaction is on a bus that is not registered while other buses are, see
+    /// [add_raw_bus](Self::add_raw_bus), or if the interactions exceed the [InteractionLimits].
+    ///
+    /// The declarations of each AIR are checked before anything is committed: the main trace
+    /// widths, the preprocessed trace or shape, the configuration public values and the
+    /// periodic columns. See [KeygenError] for every inconsistency reported.
+    ///
+    /// Also returns an error if a quotient degree override is invalid, see
+    /// [set_quotient_degree_override](Self::set_quotient_degree_override).
+    pub fn try_generate_pk(mut self) -> Result<MultiStarkProvingKey<SC>, KeygenError> {
+        if !self.public_lookup_tables.is_empty() && SC::RapPhaseSeq::ID != RapPhaseSeqKind::FriLogUp
+        {
+            return Err(KeygenError::PublicLookupTablesUnsupported {
+                rap_phase_seq_kind: SC::RapPhaseSeq::ID,
+            });
+        }
+        if self.zero_knowledge && SC::RapPhaseSeq::ID == RapPhaseSeqKind::GkrLogUp {
+            return Err(KeygenError::ZeroKnowledgeUnsupported {
+                rap_phase_seq_kind: SC::RapPhaseSeq::ID,
+            });
+        }
+        for (air_id, keygen_builder) in self.partitioned_airs.iter().enumerate() {
+            check_air_declarations(air_id, keygen_builder)?;
+        }
+        // The preprocessed traces are generated in parallel, but committed one at a time since
+        // the PCS is not required to be `Sync`. Each commitment is parallel internally.
+        let preprocessed_traces = map_maybe_parallel(
+            self.parallel,
+            self.partitioned_airs.iter().collect_vec(),
+            |keygen_builder| keygen_builder.air.preprocessed_trace(),
+        );
+        for (air_id, (keygen_builder, trace)) in
+            zip(&mut self.partitioned_airs, preprocessed_traces).enumerate()
+        {
+            keygen_builder.prep_keygen_data = compute_prep_data_for_air(
+                self.config.pcs(),
+                air_id,
+                keygen_builder,
+                trace,
+                self.zero_knowledge,
+            )?;
+        }
+        // First pass: get symbolic constraints and interactions but RAP phase constraints are not final
+        let prune_interactions = self.prune_interactions;
+        let symbolic_constraints_per_air = map_maybe_parallel(
+            self.parallel,
+            self.partitioned_airs.iter().collect_vec(),
+            |keygen_builder| {
+                keygen_builder
+                    .get_symbolic_builder(None, prune_interactions)
+                    .constraints()
+            },
+        );
+        for (air_id, (keygen_builder, constraints)) in
+            zip(&self.partitioned_airs, &symbolic_constraints_per_air).enumerate()
+        {
+            for interaction in &constraints.interactions {
+                let expected_arity = match self.bus_registry.get(interaction.bus_index) {
+                    Some(bus) => bus.arity,
+                    None => match self
+                        .public_lookup_tables
+                        .iter()
+                        .find(|table| table.bus_index == interaction.bus_index)
+                    {
+                        Some(table) => table.arity(),
+                        None if self.bus_registry.is_empty() => continue,
+                        None => {
+                            return Err(KeygenError::UnregisteredBus {
+                                air_id,
+                                air_name: keygen_builder.name(),
+                                bus_index: interaction.bus_index,
+                            })
+                        }
+                    },
+                };
+                if interaction.fields.len() != expected_arity {
+                    return Err(KeygenError::BusArityMismatch {
+                        air_id,
+                        air_name: keygen_builder.name(),
+                        bus_index: interaction.bus_index,
+                        bus_name: self.bus_registry.name(interaction.bus_index),
+                        expected_arity,
+                        arity: interaction.fields.len(),
+                    });
+                }
+            }
+        }
+        self.check_interaction_limits(&symbolic_constraints_per_air)?;
+        if let Some(max_degree) = self.max_supported_constraint_degree {
+            // The committed traces are twice as high in zero-knowledge mode, which the quotient
+            // domain of a constraint of the maximum supported degree exceeds.
+            let zk = self.zero_knowledge as usize;
+            let max_degree = max_degree.saturating_sub(zk);
+            for (air_id, (keygen_builder, constraints)) in
+                zip(&self.partitioned_airs, &symbolic_constraints_per_air).enumerate()
+            {
+                let rap_phase_degree = rap_phase_constraint_degree(
+                    SC::RapPhaseSeq::PHASES,
+                    constraints,
+                    keygen_builder.log_up_params,
+                );
+                if let Some(batch_size) = keygen_builder.log_up_params.batch_size {
+                    if rap_phase_degree > max_degree {
+                        return Err(KeygenError::InteractionBatchTooLarge {
+                            air_id,
+                            air_name: keygen_builder.name(),
+                            batch_size,
+                            degree: rap_phase_degree,
+                            max_degree,
+                        });
+                    }
+                }
+                let degree = constraints.max_constraint_degree().max(rap_phase_degree);
+                if degree > max_degree {
+                    return Err(KeygenError::ConstraintDegreeTooHigh {
+                        air_id,
+                        air_name: keygen_builder.name(),
+                        degree,
+                        max_degree,
+                        min_log_blowup: log2_ceil_usize(degree.max(2) - 1 + zk),
+                    });
+                }
+            }
+        }
+        let air_max_constraint_degree = zip(&self.partitioned_airs, &symbolic_constraints_per_air)
+            .map(|(keygen_builder, constraints)| {
+                let max_constraint_degree = constraints.max_constraint_degree();
                 tracing::debug!(
                     "{} has constraint degree {}",
-                    keygen_builder.air.name(),
+                    keygen_builder.name(),
                     max_constraint_degree
                 );
                 max_constraint_degree
@@ -95,28 +619,52 @@ impl<'a, SC: StarkGenericConfig> MultiStarkKeygenBuilder<'a, SC> {
             );
             self.max_constraint_degree = air_max_constraint_degree;
         }
-        // First pass: get symbolic constraints and interactions but RAP phase constraints are not final
-        let symbolic_constraints_per_air = self
-            .partitioned_airs
-            .iter()
-            .map(|keygen_builder| keygen_builder.get_symbolic_builder(None).constraints())
-            .collect_vec();
         // Note: due to the need to go through a trait, there is some duplicate computation
         // (e.g., FRI logup will calculate the interaction chunking both here and in the second pass below)
         let rap_partial_pk_per_air = self
             .config
             .rap_phase_seq()
             .generate_pk_per_air(&symbolic_constraints_per_air, self.max_constraint_degree);
-        let pk_per_air: Vec<_> = zip(self.partitioned_airs, rap_partial_pk_per_air)
-            .map(|(keygen_builder, rap_partial_pk)| {
+        let max_constraint_degree = self.max_constraint_degree;
+        let zero_knowledge = self.zero_knowledge;
+        let quotient_degree_overrides = self
+            .partitioned_airs
+            .iter()
+            .map(|keygen_builder| keygen_builder.quotient_degree_override)
+            .collect_vec();
+        let mut pk_per_air = map_maybe_parallel(
+            self.parallel,
+            zip(self.partitioned_airs, rap_partial_pk_per_air).collect_vec(),
+            |(keygen_builder, rap_partial_pk)| {
                 // Second pass: get final constraints, where RAP phase constraints may have changed
-                keygen_builder.generate_pk(rap_partial_pk, self.max_constraint_degree)
-            })
-            .collect();
+                keygen_builder.generate_pk(
+                    rap_partial_pk,
+                    max_constraint_degree,
+                    prune_interactions,
+                    zero_knowledge,
+                )
+            },
+        );
+        for (air_id, (pk, degree)) in zip(&mut pk_per_air, quotient_degree_overrides).enumerate() {
+            if let Some(degree) = degree {
+                pk.vk.quotient_degree = check_quotient_degree_override(
+                    air_id,
+                    pk,
+                    degree,
+                    self.max_supported_constraint_degree,
+                    self.zero_knowledge,
+                )?;
+            }
+        }
+        for (air_id, pk) in pk_per_air.iter().enumerate() {
+            if pk.vk.params.may_be_absent {
+                check_gated_interactions(air_id, pk)?;
+            }
+        }
 
         for pk in pk_per_air.iter() {
             let width = &pk.vk.params.width;
-            tracing::info!("{:<20} | Quotient Deg = {:<2} | Prep Cols = {:<2} | Main Cols = {:<8} | Perm Cols = {:<4} | {:4} Constraints | {:3} Interactions On Buses {:?}",
+            tracing::info!("{:<20} | Quotient Deg = {:<2} | Prep Cols = {:<2} | Main Cols = {:<8} | Perm Cols = {:<4} | {:4} Constraints | {:3} Interactions On Buses {:?} | {} Zero-Count and {} Duplicate Interactions Pruned",
                 pk.air_name,
                 pk.vk.quotient_degree,
                 width.preprocessed.unwrap_or(0),
@@ -128,8 +676,10 @@ impl<'a, SC: StarkGenericConfig> MultiStarkKeygenBuilder<'a, SC> {
                     .symbolic_constraints
                     .interactions
                     .iter()
-                    .map(|i| i.bus_index)
-                    .collect_vec()
+                    .map(|i| self.bus_registry.name(i.bus_index))
+                    .collect_vec(),
+                pk.pruned_interactions.num_zero_count,
+                pk.pruned_interactions.num_duplicates,
             );
             #[cfg(feature = "bench-metrics")]
             {
@@ -140,50 +690,215 @@ impl<'a, SC: StarkGenericConfig> MultiStarkKeygenBuilder<'a, SC> {
                     .absolute(pk.vk.symbolic_constraints.constraints.constraint_idx.len() as u64);
                 metrics::counter!("interactions", &labels)
                     .absolute(pk.vk.symbolic_constraints.interactions.len() as u64);
+                metrics::counter!("pruned_interactions", &labels)
+                    .absolute(pk.pruned_interactions.total() as u64);
             }
         }
 
-        MultiStarkProvingKey {
+        let custom_rounds_at = |position: CustomRoundPosition| {
+            self.custom_rounds
+                .iter()
+                .enumerate()
+                .filter(move |(_, (_, pos))| *pos == position)
+                .map(|(index, (name, _))| CommitmentRound::Custom {
+                    index,
+                    name: name.clone(),
+                })
+        };
+        let commitment_rounds = iter::once(CommitmentRound::Preprocessed)
+            .chain(custom_rounds_at(CustomRoundPosition::BeforeMain))
+            .chain([CommitmentRound::CachedMain, CommitmentRound::CommonMain])
+            .chain(custom_rounds_at(CustomRoundPosition::AfterMain))
+            .chain(
+                (0..SC::RapPhaseSeq::PHASES.shape().len())
+                    .map(|phase| CommitmentRound::AfterChallenge { phase }),
+            )
+            .chain(iter::once(CommitmentRound::Quotient))
+            .collect();
+
+        let features = [
+            (!self.custom_rounds.is_empty(), VkFeature::CustomRounds),
+            (
+                self.matrix_ordering != MatrixOrdering::Natural,
+                VkFeature::MatrixOrdering,
+            ),
+            (
+                pk_per_air
+                    .iter()
+                    .any(|pk| !pk.vk.params.extra_rotations.is_empty()),
+                VkFeature::ExtraRotations,
+            ),
+            (
+                pk_per_air
+                    .iter()
+                    .any(|pk| !pk.vk.params.configuration_public_values.is_empty()),
+                VkFeature::ConfigurationPublicValues,
+            ),
+            (
+                pk_per_air
+                    .iter()
+                    .any(|pk| !pk.vk.periodic_columns.is_empty()),
+                VkFeature::PeriodicColumns,
+            ),
+            (
+                SC::RapPhaseSeq::ID == RapPhaseSeqKind::GkrLogUp
+                    && pk_per_air.iter().any(|pk| pk.vk.has_interaction()),
+                VkFeature::GkrLogUp,
+            ),
+            (
+                pk_per_air
+                    .iter()
+                    .any(|pk| pk.vk.params.width.after_challenge.len() > 1),
+                VkFeature::MultipleChallengePhases,
+            ),
+            (
+                !self.public_lookup_tables.is_empty(),
+                VkFeature::PublicLookupTables,
+            ),
+            (
+                pk_per_air
+                    .iter()
+                    .any(|pk| pk.vk.preprocessed_per_height.is_some()),
+                VkFeature::PreprocessedPerHeight,
+            ),
+            (
+                pk_per_air
+                    .iter()
+                    .any(|pk| pk.vk.params.max_log_height.is_some()),
+                VkFeature::MaxTraceHeight,
+            ),
+            (
+                pk_per_air.iter().any(|pk| pk.vk.params.may_be_absent),
+                VkFeature::AbsentAirs,
+            ),
+            (self.zero_knowledge, VkFeature::ZeroKnowledge),
+        ]
+        .into_iter()
+        .filter(|&(used, _)| used)
+        .map(|(_, feature)| feature)
+        .collect();
+
+        Ok(MultiStarkProvingKey {
             per_air: pk_per_air,
             max_constraint_degree: self.max_constraint_degree,
+            commitment_rounds,
+            matrix_ordering: self.matrix_ordering,
+            features,
+            bus_registry: self.bus_registry,
+            public_lookup_tables: self.public_lookup_tables,
+        })
+    }
+}
+
+impl<SC: StarkGenericConfig> MultiStarkKeygenBuilder<'_, SC> {
+    fn check_interaction_limits(
+        &self,
+        symbolic_constraints_per_air: &[SymbolicConstraints<Val<SC>>],
+    ) -> Result<(), KeygenError> {
+        let limits = &self.interaction_limits;
+        for (air_id, (keygen_builder, constraints)) in
+            zip(&self.partitioned_airs, symbolic_constraints_per_air).enumerate()
+        {
+            let num_interactions = constraints.interactions.len();
+            if let Some(max_interactions) = limits.max_interactions_per_air {
+                if num_interactions > max_interactions {
+                    return Err(KeygenError::TooManyInteractionsPerAir {
+                        air_id,
+                        air_name: keygen_builder.name(),
+                        num_interactions,
+                        max_interactions,
+                    });
+                }
+            }
+            if let Some(max_fields) = limits.max_fields_per_message {
+                if let Some(interaction) = constraints
+                    .interactions
+                    .iter()
+                    .find(|interaction| interaction.fields.len() > max_fields)
+                {
+                    return Err(KeygenError::TooManyMessageFields {
+                        air_id,
+                        air_name: keygen_builder.name(),
+                        bus_index: interaction.bus_index,
+                        num_fields: interaction.fields.len(),
+                        max_fields,
+                    });
+                }
+            }
+        }
+        if let Some(max_interactions) = limits.max_total_interactions {
+            let num_interactions = symbolic_constraints_per_air
+                .iter()
+                .map(|constraints| constraints.interactions.len())
+                .sum();
+            if num_interactions > max_interactions {
+                return Err(KeygenError::TooManyInteractions {
+                    num_interactions,
+                    max_interactions,
+                });
+            }
         }
+        Ok(())
     }
 }
 
 impl<SC: StarkGenericConfig> AirKeygenBuilder<SC> {
-    fn new(pcs: &SC::Pcs, rap_phase_seq_kind: RapPhaseSeqKind, air: Arc<dyn AnyRap<SC>>) -> Self {
-        let prep_keygen_data = compute_prep_data_for_air(pcs, air.as_ref());
+    fn new(
+        rap_phase_seq_kind: RapPhaseSeqKind,
+        log_up_params: FriLogUpParams,
+        air: Arc<dyn AnyRap<SC>>,
+    ) -> Self {
         AirKeygenBuilder {
             air,
+            name: None,
             rap_phase_seq_kind,
-            prep_keygen_data,
+            log_up_params,
+            prep_keygen_data: PrepKeygenData {
+                verifier_data: None,
+                prover_data: None,
+                shape: None,
+            },
+            custom_widths: vec![],
+            max_log_height: None,
+            quotient_degree_override: None,
+            may_be_absent: false,
         }
     }
 
-    fn max_constraint_degree(&self) -> usize {
-        self.get_symbolic_builder(None)
-            .constraints()
-            .max_constraint_degree()
-    }
-
     fn generate_pk(
         self,
         rap_partial_pk: RapPartialProvingKey<SC>,
         max_constraint_degree: usize,
+        prune_interactions: bool,
+        zero_knowledge: bool,
     ) -> StarkProvingKey<SC> {
-        let air_name = self.air.name();
+        let air_name = self.name();
 
-        let symbolic_builder = self.get_symbolic_builder(Some(max_constraint_degree));
-        let params = symbolic_builder.params();
+        let symbolic_builder =
+            self.get_symbolic_builder(Some(max_constraint_degree), prune_interactions);
+        let mut params = symbolic_builder.params();
+        let pruned_interactions = symbolic_builder.pruned_interactions();
         let symbolic_constraints = symbolic_builder.constraints();
-        let log_quotient_degree = symbolic_constraints.get_log_quotient_degree();
+        let log_quotient_degree = if zero_knowledge {
+            symbolic_constraints.get_zk_log_quotient_degree()
+        } else {
+            symbolic_constraints.get_log_quotient_degree()
+        };
         let quotient_degree = 1 << log_quotient_degree;
+        let symbolic_constraints: SymbolicConstraintsDag<Val<SC>> = symbolic_constraints.into();
+        params.extra_rotations = symbolic_constraints.constraints.extra_rotations();
+        params.configuration_public_values = self.air.configuration_public_values();
+        params.max_log_height = self.max_log_height;
+        params.may_be_absent = self.may_be_absent;
+        // Checked by `check_air_declarations`.
+        let periodic_columns = self.air.periodic_columns();
 
         let Self {
             prep_keygen_data:
                 PrepKeygenData {
                     verifier_data: prep_verifier_data,
                     prover_data: prep_prover_data,
+                    shape: prep_shape,
                 },
             ..
         } = self;
@@ -191,74 +906,366 @@ impl<SC: StarkGenericConfig> AirKeygenBuilder<SC> {
         let vk: StarkVerifyingKey<Val<SC>, Com<SC>> = StarkVerifyingKey {
             preprocessed_data: prep_verifier_data,
             params,
-            symbolic_constraints: symbolic_constraints.into(),
+            symbolic_constraints,
             quotient_degree,
             rap_phase_seq_kind: self.rap_phase_seq_kind,
+            log_up_params: self.log_up_params,
+            periodic_columns,
+            preprocessed_per_height: prep_shape.map(|shape| VerifierPerHeightPreprocessedData {
+                log_heights: shape.log_heights,
+                commits: vec![],
+            }),
+            air_name: air_name.clone(),
         };
         StarkProvingKey {
             air_name,
             vk,
             preprocessed_data: prep_prover_data,
+            preprocessed_per_height: BTreeMap::new(),
             rap_partial_pk,
+            pruned_interactions,
         }
     }
 
+    fn name(&self) -> String {
+        self.name.clone().unwrap_or_else(|| self.air.name())
+    }
+
     fn get_symbolic_builder(
         &self,
         max_constraint_degree: Option<usize>,
+        prune_interactions: bool,
     ) -> SymbolicRapBuilder<Val<SC>> {
         let width = TraceWidth {
             preprocessed: self.prep_keygen_data.width(),
             cached_mains: self.air.cached_main_widths(),
             common_main: self.air.common_main_width(),
             after_challenge: vec![],
+            custom: self.custom_widths.clone(),
         };
         get_symbolic_builder(
             self.air.as_ref(),
             &width,
             &[],
             &[],
-            SC::RapPhaseSeq::ID,
+            SC::RapPhaseSeq::PHASES,
             max_constraint_degree.unwrap_or(0),
+            self.log_up_params,
+            prune_interactions,
         )
     }
 }
 
+/// The max degree of the constraints added by the RAP phases of an AIR with `constraints`. If
+/// the FRI log-up interactions are batched without a fixed batch size, this is the smallest
+/// possible one, i.e. when every interaction is in its own log-up chunk.
+fn rap_phase_constraint_degree<F: Field>(
+    rap_phases: RapPhases,
+    constraints: &SymbolicConstraints<F>,
+    log_up_params: FriLogUpParams,
+) -> usize {
+    if constraints.interactions.is_empty() {
+        return 0;
+    }
+    match rap_phases {
+        RapPhases::Kind(RapPhaseSeqKind::FriLogUp) if log_up_params.commit_inverses => {
+            // See `eval_fri_log_up_inverse_repetition`: each denominator is multiplied by its
+            // inverse, and each count by the inverse of its denominator.
+            let (max_field_degree, max_count_degree) = constraints.max_interaction_degrees();
+            max_field_degree.max(max_count_degree) + 1
+        }
+        RapPhases::Kind(RapPhaseSeqKind::FriLogUp) if log_up_params.batch_size.is_some() => {
+            let interaction_partitions =
+                find_interaction_chunks(&constraints.interactions, 0, log_up_params.batch_size)
+                    .interaction_partitions();
+            fri_log_up_constraint_degree(&constraints.interactions, &interaction_partitions)
+        }
+        RapPhases::Kind(RapPhaseSeqKind::FriLogUp) => {
+            // See `eval_fri_log_up_phase`: the row constraint of a chunk with one interaction
+            // has degree max(field_degree + 1, count_degree).
+            let (max_field_degree, max_count_degree) = constraints.max_interaction_degrees();
+            (max_field_degree + 1).max(max_count_degree)
+        }
+        RapPhases::Kind(RapPhaseSeqKind::GkrLogUp) => {
+            // See `eval_gkr_log_up_phase`: the fractions of all interactions are multiplied by
+            // the `eq` column.
+            let (max_field_degree, max_count_degree) = constraints.max_interaction_degrees();
+            max_field_degree.max(max_count_degree) + 1
+        }
+        #[cfg(test)]
+        RapPhases::TestTwoPhase => {
+            let (max_field_degree, max_count_degree) = constraints.max_interaction_degrees();
+            (max_field_degree + 1).max(max_count_degree).max(2)
+        }
+    }
+}
+
+/// Maps `f` over `items`, in parallel if `parallel` is set. The results are in the order of
+/// `items` either way.
+fn map_maybe_parallel<T: Send, R: Send>(
+    parallel: bool,
+    items: Vec<T>,
+    f: impl Fn(T) -> R + Send + Sync,
+) -> Vec<R> {
+    if parallel {
+        items.into_par_iter().map(f).collect()
+    } else {
+        items.into_iter().map(f).collect()
+    }
+}
+
 pub(super) struct PrepKeygenData<SC: StarkGenericConfig> {
     pub verifier_data: Option<VerifierSinglePreprocessedData<Com<SC>>>,
     pub prover_data: Option<ProverOnlySinglePreprocessedData<SC>>,
+    /// The declared shape of a preprocessed trace committed per height, in which case there is
+    /// no verifier or prover data at keygen.
+    pub shape: Option<PreprocessedShape>,
 }
 
 impl<SC: StarkGenericConfig> PrepKeygenData<SC> {
     pub fn width(&self) -> Option<usize> {
-        self.prover_data.as_ref().map(|d| d.trace.width())
+        match &self.shape {
+            Some(shape) => Some(shape.width),
+            None => self.prover_data.as_ref().map(|d| d.trace.width()),
+        }
+    }
+}
+
+/// Checks that the widths, configuration public values and periodic columns declared by `air`
+/// are consistent.
+fn check_air_declarations<SC: StarkGenericConfig>(
+    air_id: usize,
+    keygen_builder: &AirKeygenBuilder<SC>,
+) -> Result<(), KeygenError> {
+    let air = keygen_builder.air.as_ref();
+    let width = air.width();
+    let cached_main_widths = air.cached_main_widths();
+    let common_main_width = air.common_main_width();
+    if cached_main_widths.iter().sum::<usize>() + common_main_width != width {
+        return Err(KeygenError::MainWidthMismatch {
+            air_id,
+            air_name: keygen_builder.name(),
+            width,
+            cached_main_widths,
+            common_main_width,
+        });
+    }
+    let num_public_values = air.num_public_values();
+    let mut indices = HashSet::new();
+    for (index, allowed) in air.configuration_public_values() {
+        if index >= num_public_values {
+            return Err(KeygenError::ConfigurationPublicValueOutOfBounds {
+                air_id,
+                air_name: keygen_builder.name(),
+                index,
+                num_public_values,
+            });
+        }
+        if !indices.insert(index) {
+            return Err(KeygenError::DuplicateConfigurationPublicValue {
+                air_id,
+                air_name: keygen_builder.name(),
+                index,
+            });
+        }
+        if allowed.is_empty() {
+            return Err(KeygenError::NoAllowedValues {
+                air_id,
+                air_name: keygen_builder.name(),
+                index,
+            });
+        }
+    }
+    for (column, values) in air.periodic_columns().iter().enumerate() {
+        if !values.len().is_power_of_two() {
+            return Err(KeygenError::PeriodNotPowerOfTwo {
+                air_id,
+                air_name: keygen_builder.name(),
+                column,
+                period: values.len(),
+            });
+        }
     }
+    Ok(())
 }
 
+/// Returns the quotient degree override `degree` of the AIR with id `air_id`, whose proving key
+/// `pk` has the quotient degree its constraints require.
+fn check_quotient_degree_override<SC: StarkGenericConfig>(
+    air_id: usize,
+    pk: &StarkProvingKey<SC>,
+    degree: usize,
+    max_supported_constraint_degree: Option<usize>,
+    zero_knowledge: bool,
+) -> Result<u8, KeygenError> {
+    let min_degree = pk.vk.quotient_degree as usize;
+    // The quotient degree is stored as a `u8`. The committed traces are twice as high in
+    // zero-knowledge mode, so their LDEs cover twice the quotient degree.
+    let max_degree = max_supported_constraint_degree.map_or(min_degree, |max_degree| {
+        (max_degree.saturating_sub(1) << zero_knowledge as usize).min(u8::MAX as usize)
+    });
+    if !degree.is_power_of_two() {
+        Err(KeygenError::QuotientDegreeNotPowerOfTwo {
+            air_id,
+            air_name: pk.air_name.clone(),
+            degree,
+        })
+    } else if degree < min_degree {
+        Err(KeygenError::QuotientDegreeTooLow {
+            air_id,
+            air_name: pk.air_name.clone(),
+            degree,
+            min_degree,
+        })
+    } else if degree > max_degree {
+        Err(KeygenError::QuotientDegreeTooHigh {
+            air_id,
+            air_name: pk.air_name.clone(),
+            degree,
+            max_degree,
+        })
+    } else {
+        Ok(degree as u8)
+    }
+}
+
+/// Checks that the count of every interaction of the AIR with id `air_id`, which may be absent
+/// from proofs, is gated on a main trace column: it vanishes on a row of zeros, whatever the
+/// preprocessed trace, the row selectors and the challenges.
+fn check_gated_interactions<SC: StarkGenericConfig>(
+    air_id: usize,
+    pk: &StarkProvingKey<SC>,
+) -> Result<(), KeygenError> {
+    let symbolic_constraints = &pk.vk.symbolic_constraints;
+    let gated = gated_on_main(&symbolic_constraints.constraints.nodes);
+    match symbolic_constraints
+        .interactions
+        .iter()
+        .position(|interaction| !gated[interaction.count])
+    {
+        Some(index) => Err(KeygenError::UngatedInteraction {
+            air_id,
+            air_name: pk.air_name.clone(),
+            index,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Whether each of `nodes`, in topological order, vanishes when every main trace variable is
+/// zero. Conservative: an expression that cancels out, such as `x - x` for a selector `x`, is not
+/// recognized.
+fn gated_on_main<F: Field>(nodes: &[SymbolicExpressionNode<F>]) -> Vec<bool> {
+    let mut gated: Vec<bool> = Vec::with_capacity(nodes.len());
+    for node in nodes {
+        let is_gated = match node {
+            SymbolicExpressionNode::Variable(var) => matches!(var.entry, Entry::Main { .. }),
+            SymbolicExpressionNode::Constant(c) => c.is_zero(),
+            SymbolicExpressionNode::IsFirstRow
+            | SymbolicExpressionNode::IsLastRow
+            | SymbolicExpressionNode::IsTransition => false,
+            SymbolicExpressionNode::Add {
+                left_idx,
+                right_idx,
+                ..
+            }
+            | SymbolicExpressionNode::Sub {
+                left_idx,
+                right_idx,
+                ..
+            } => gated[*left_idx] && gated[*right_idx],
+            SymbolicExpressionNode::Neg { idx, .. } => gated[*idx],
+            SymbolicExpressionNode::Mul {
+                left_idx,
+                right_idx,
+                ..
+            } => gated[*left_idx] || gated[*right_idx],
+        };
+        gated.push(is_gated);
+    }
+    gated
+}
+
+/// Commits `preprocessed_trace`, the preprocessed trace generated by the AIR of
+/// `keygen_builder`.
 fn compute_prep_data_for_air<SC: StarkGenericConfig>(
     pcs: &SC::Pcs,
-    air: &dyn AnyRap<SC>,
-) -> PrepKeygenData<SC> {
-    let preprocessed_trace = air.preprocessed_trace();
-    let vpdata_opt = preprocessed_trace.map(|trace| {
-        let domain = pcs.natural_domain_for_degree(trace.height());
-        let (commit, data) = pcs.commit(vec![(domain, trace.clone())]);
-        let vdata = VerifierSinglePreprocessedData { commit };
-        let pdata = ProverOnlySinglePreprocessedData {
-            trace: Arc::new(trace),
-            data: Arc::new(data),
-        };
-        (vdata, pdata)
-    });
-    if let Some((vdata, pdata)) = vpdata_opt {
-        PrepKeygenData {
-            prover_data: Some(pdata),
-            verifier_data: Some(vdata),
+    air_id: usize,
+    keygen_builder: &AirKeygenBuilder<SC>,
+    preprocessed_trace: Option<RowMajorMatrix<Val<SC>>>,
+    zero_knowledge: bool,
+) -> Result<PrepKeygenData<SC>, KeygenError> {
+    let air = keygen_builder.air.as_ref();
+    if let Some(shape) = air.preprocessed_shape() {
+        if preprocessed_trace.is_some() {
+            return Err(KeygenError::PreprocessedShapeWithTrace {
+                air_id,
+                air_name: keygen_builder.name(),
+            });
         }
-    } else {
-        PrepKeygenData {
-            prover_data: None,
-            verifier_data: None,
+        if shape.log_heights.is_empty() {
+            return Err(KeygenError::EmptyPreprocessedShape {
+                air_id,
+                air_name: keygen_builder.name(),
+            });
         }
+        return Ok(PrepKeygenData {
+            verifier_data: None,
+            prover_data: None,
+            shape: Some(shape),
+        });
     }
+    Ok(match preprocessed_trace {
+        Some(trace) => {
+            if !trace.height().is_power_of_two() {
+                return Err(KeygenError::PreprocessedHeightNotPowerOfTwo {
+                    air_id,
+                    air_name: keygen_builder.name(),
+                    height: trace.height(),
+                });
+            }
+            let (vdata, pdata) = commit_preprocessed_trace(pcs, air, trace, zero_knowledge);
+            PrepKeygenData {
+                verifier_data: Some(vdata),
+                prover_data: Some(pdata),
+                shape: None,
+            }
+        }
+        None => PrepKeygenData {
+            verifier_data: None,
+            prover_data: None,
+            shape: None,
+        },
+    })
+}
+
+/// Commits the preprocessed `trace` of `air` on its own.
+///
+/// In zero-knowledge mode, the trace is committed with twice its height like the other traces,
+/// with zero odd rows since the preprocessed trace is public.
+pub(crate) fn commit_preprocessed_trace<SC: StarkGenericConfig>(
+    pcs: &SC::Pcs,
+    air: &dyn AnyRap<SC>,
+    trace: RowMajorMatrix<Val<SC>>,
+    zero_knowledge: bool,
+) -> (
+    VerifierSinglePreprocessedData<Com<SC>>,
+    ProverOnlySinglePreprocessedData<SC>,
+) {
+    let committed = if zero_knowledge {
+        let zeros = RowMajorMatrix::new(vec![Val::<SC>::ZERO; trace.values.len()], trace.width());
+        interleave_rows(&trace, &zeros)
+    } else {
+        trace.clone()
+    };
+    let domain = pcs.natural_domain_for_degree(committed.height());
+    let (commit, data) = pcs.commit(vec![(domain, committed)]);
+    let vdata = VerifierSinglePreprocessedData { commit };
+    let pdata = ProverOnlySinglePreprocessedData {
+        content_hash: preprocessed_content_hash(&trace),
+        version: air.preprocessed_version(),
+        trace: Arc::new(trace),
+        data: Arc::new(data),
+    };
+    (vdata, pdata)
 }
@@ -0,0 +1,175 @@
+//! Summary of the keys of a system of AIRs: the widths, constraints and interactions of each AIR,
+//! as a table.
+
+use std::fmt;
+
+use itertools::Itertools;
+use p3_field::{Field, FieldExtensionAlgebra};
+
+use super::types::{MultiStarkProvingKey, MultiStarkVerifyingKey, StarkVerifyingKey};
+use crate::{
+    air_builders::symbolic::SymbolicConstraints,
+    config::{Com, StarkGenericConfig, Val},
+};
+
+/// Summary of the key of a single AIR. Widths are numbers of base field columns.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AirSummary {
+    pub air_name: String,
+    pub preprocessed_width: usize,
+    pub cached_main_widths: Vec<usize>,
+    pub common_main_width: usize,
+    /// Total width of the after challenge traces of all phases.
+    pub after_challenge_width: usize,
+    pub num_constraints: usize,
+    /// Max degree of the constraints, including the constraints of the RAP phases.
+    pub max_constraint_degree: usize,
+    pub quotient_degree: usize,
+    pub num_interactions: usize,
+    /// Total number of fields of the messages of the interactions.
+    pub num_interaction_fields: usize,
+}
+
+/// Summary of the keys of a system of AIRs, see [MultiStarkProvingKey::summary]. Displayed as a
+/// table with one row per AIR and the totals at the bottom.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeySummary {
+    pub per_air: Vec<AirSummary>,
+}
+
+impl KeySummary {
+    /// Sums of the columns of all AIRs, except for the degrees, which are maxima. The
+    /// cached main widths are summed into a single width, and the name is `"Total"`.
+    pub fn totals(&self) -> AirSummary {
+        let sum = |f: fn(&AirSummary) -> usize| -> usize { self.per_air.iter().map(f).sum() };
+        let max = |f: fn(&AirSummary) -> usize| self.per_air.iter().map(f).max().unwrap_or(0);
+        AirSummary {
+            air_name: "Total".to_string(),
+            preprocessed_width: sum(|air| air.preprocessed_width),
+            cached_main_widths: vec![sum(|air| air.cached_main_widths.iter().sum())],
+            common_main_width: sum(|air| air.common_main_width),
+            after_challenge_width: sum(|air| air.after_challenge_width),
+            num_constraints: sum(|air| air.num_constraints),
+            max_constraint_degree: max(|air| air.max_constraint_degree),
+            quotient_degree: max(|air| air.quotient_degree),
+            num_interactions: sum(|air| air.num_interactions),
+            num_interaction_fields: sum(|air| air.num_interaction_fields),
+        }
+    }
+}
+
+impl<SC: StarkGenericConfig> MultiStarkVerifyingKey<SC> {
+    /// Summary of the widths, constraints and interactions of each AIR.
+    pub fn summary(&self) -> KeySummary {
+        summary::<SC>(self.per_air.iter())
+    }
+}
+
+impl<SC: StarkGenericConfig> MultiStarkProvingKey<SC> {
+    /// Same as [MultiStarkVerifyingKey::summary], without taking the verifying key.
+    pub fn summary(&self) -> KeySummary {
+        summary::<SC>(self.per_air.iter().map(|pk| &pk.vk))
+    }
+}
+
+fn summary<'a, SC: StarkGenericConfig>(
+    per_air: impl Iterator<Item = &'a StarkVerifyingKey<Val<SC>, Com<SC>>>,
+) -> KeySummary {
+    let ext_degree = <SC::Challenge as FieldExtensionAlgebra<Val<SC>>>::D;
+    KeySummary {
+        per_air: per_air.map(|vk| air_summary(vk, ext_degree)).collect(),
+    }
+}
+
+fn air_summary<F: Field, Com>(vk: &StarkVerifyingKey<F, Com>, ext_degree: usize) -> AirSummary {
+    let width = &vk.params.width;
+    let interactions = &vk.symbolic_constraints.interactions;
+    AirSummary {
+        air_name: vk.air_name.clone(),
+        preprocessed_width: width.preprocessed.unwrap_or(0),
+        cached_main_widths: width.cached_mains.clone(),
+        common_main_width: width.common_main,
+        after_challenge_width: width.after_challenge.iter().sum::<usize>() * ext_degree,
+        num_constraints: vk.symbolic_constraints.constraints.constraint_idx.len(),
+        max_constraint_degree: SymbolicConstraints::from(&vk.symbolic_constraints)
+            .max_constraint_degree(),
+        quotient_degree: vk.quotient_degree as usize,
+        num_interactions: interactions.len(),
+        num_interaction_fields: interactions
+            .iter()
+            .map(|interaction| interaction.fields.len())
+            .sum(),
+    }
+}
+
+impl fmt::Display for KeySummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const HEADER: [&str; 10] = [
+            "AIR",
+            "Prep",
+            "Cached Main",
+            "Common Main",
+            "After Challenge",
+            "Constraints",
+            "Max Degree",
+            "Quotient Degree",
+            "Interactions",
+            "Interaction Fields",
+        ];
+        let cells = |air: &AirSummary, cached_main: String| {
+            [
+                air.air_name.clone(),
+                air.preprocessed_width.to_string(),
+                cached_main,
+                air.common_main_width.to_string(),
+                air.after_challenge_width.to_string(),
+                air.num_constraints.to_string(),
+                air.max_constraint_degree.to_string(),
+                air.quotient_degree.to_string(),
+                air.num_interactions.to_string(),
+                air.num_interaction_fields.to_string(),
+            ]
+        };
+        let totals = self.totals();
+        let rows = self
+            .per_air
+            .iter()
+            .map(|air| cells(air, format!("{:?}", air.cached_main_widths)))
+            .chain([cells(&totals, totals.cached_main_widths[0].to_string())])
+            .collect_vec();
+        let widths: [usize; 10] = std::array::from_fn(|i| {
+            rows.iter()
+                .map(|row| row[i].len())
+                .chain([HEADER[i].len()])
+                .max()
+                .unwrap()
+        });
+        let write_row = |f: &mut fmt::Formatter<'_>, row: &[String]| {
+            let line = row
+                .iter()
+                .zip(widths)
+                .enumerate()
+                .map(|(i, (cell, width))| {
+                    if i == 0 {
+                        format!("{cell:<width$}")
+                    } else {
+                        format!("{cell:>width$}")
+                    }
+                })
+                .join(" | ");
+            writeln!(f, "| {line} |")
+        };
+        let separator = format!(
+            "|{}|",
+            widths.iter().map(|&width| "-".repeat(width + 2)).join("|")
+        );
+        write_row(f, &HEADER.map(String::from))?;
+        writeln!(f, "{separator}")?;
+        let (total_row, air_rows) = rows.split_last().unwrap();
+        for row in air_rows {
+            write_row(f, row)?;
+        }
+        writeln!(f, "{separator}")?;
+        write_row(f, total_row)
+    }
+}
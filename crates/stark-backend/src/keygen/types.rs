@@ -1,26 +1,54 @@
 // Keygen API for STARK backend
 // Changes:
 // - All AIRs can be optional
-use std::sync::Arc;
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    fmt,
+    hash::{Hash, Hasher},
+    iter::zip,
+    sync::Arc,
+};
 
 use derivative::Derivative;
-use p3_matrix::dense::RowMajorMatrix;
+use itertools::Itertools;
+use p3_field::{Field, PrimeField64};
+use p3_matrix::{dense::RowMajorMatrix, Matrix};
+use p3_util::log2_ceil_usize;
+use rustc_hash::FxHasher;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    air_builders::symbolic::SymbolicConstraintsDag,
+    air_builders::symbolic::{
+        deserialize_constraints_dag, serialize_constraints_dag, SymbolicConstraintsDag,
+    },
     config::{Com, PcsProverData, RapPartialProvingKey, StarkGenericConfig, Val},
-    interaction::RapPhaseSeqKind,
+    interaction::{
+        bus::BusRegistry, fri_log_up::FriLogUpParams, prune::PrunedInteractions,
+        public_lookup::PublicLookupTable, RapPhaseSeq, RapPhaseSeqKind,
+    },
+    keygen::{
+        commit_preprocessed_trace, AbsentAirError, CachedMainError, DisallowedPublicValueError,
+        MainTraceKind, MissingPreprocessedHeightError, ProofInputError, StaleProvingKeyError,
+        TraceHeightTooLargeError, UnsupportedVkFeaturesError,
+    },
+    prover::types::ProofInput,
+    rap::AnyRap,
+    AirRef,
 };
 
 /// Widths of different parts of trace matrix
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TraceWidth {
     pub preprocessed: Option<usize>,
     pub cached_mains: Vec<usize>,
     pub common_main: usize,
     /// Width counted by extension field elements, _not_ base field elements
     pub after_challenge: Vec<usize>,
+    /// For each custom commitment round with a matrix of this AIR, the index of the custom round
+    /// and the width of the matrix, ordered by custom round index.
+    #[serde(default)]
+    pub custom: Vec<(usize, usize)>,
 }
 
 impl TraceWidth {
@@ -45,6 +73,304 @@ pub struct StarkVerifyingParams {
     pub num_exposed_values_after_challenge: Vec<usize>,
     /// For only this RAP, how many challenges are needed in each trace challenge phase
     pub num_challenges_to_sample: Vec<usize>,
+    /// Sorted row offsets greater than 1 referenced by the constraints on the preprocessed and
+    /// main traces. The traces are opened at these rotations in addition to the local and next rows.
+    #[serde(default)]
+    pub extra_rotations: Vec<usize>,
+    /// For each configuration public value, its index in the public values and its allowed
+    /// values. See [BaseAirWithPublicValues::configuration_public_values].
+    ///
+    /// [BaseAirWithPublicValues::configuration_public_values]: crate::rap::BaseAirWithPublicValues::configuration_public_values
+    #[serde(default)]
+    pub configuration_public_values: Vec<(usize, AllowedValues)>,
+    /// The maximum log height of the trace, if bounded. See
+    /// [MultiStarkKeygenBuilder::set_max_log_height].
+    ///
+    /// [MultiStarkKeygenBuilder::set_max_log_height]: crate::keygen::MultiStarkKeygenBuilder::set_max_log_height
+    #[serde(default)]
+    pub max_log_height: Option<usize>,
+    /// Whether proofs may record the AIR as absent. See
+    /// [MultiStarkKeygenBuilder::set_may_be_absent].
+    ///
+    /// [MultiStarkKeygenBuilder::set_may_be_absent]: crate::keygen::MultiStarkKeygenBuilder::set_may_be_absent
+    #[serde(default)]
+    pub may_be_absent: bool,
+}
+
+/// The allowed values of a configuration public value, as canonical integers.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AllowedValues {
+    /// One of the given values.
+    Set(Vec<u64>),
+    /// A value in the inclusive range `start..=end`.
+    Range { start: u64, end: u64 },
+}
+
+impl AllowedValues {
+    /// Whether no value is allowed, i.e. the set is empty or `start > end`.
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Self::Set(values) => values.is_empty(),
+            Self::Range { start, end } => start > end,
+        }
+    }
+
+    /// Whether the canonical integer of `value` is allowed. A range is checked against its bounds
+    /// without enumerating its values, since the bounds of a deserialized key are untrusted.
+    pub fn contains<F: PrimeField64>(&self, value: F) -> bool {
+        let value = value.as_canonical_u64();
+        match self {
+            Self::Set(values) => values.contains(&value),
+            Self::Range { start, end } => (*start..=*end).contains(&value),
+        }
+    }
+}
+
+impl fmt::Display for AllowedValues {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Set(values) => write!(f, "{{{}}}", values.iter().join(", ")),
+            Self::Range { start, end } => write!(f, "[{start}, {end}]"),
+        }
+    }
+}
+
+/// A commitment round of the protocol. The rounds of a verifying key are listed in the order in
+/// which their commitments are observed by the challenger.
+///
+/// A round without any matrix from the AIRs in a proof is skipped, except for the common main
+/// round which is always committed.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CommitmentRound {
+    /// One commitment per preprocessed trace.
+    Preprocessed,
+    /// One commitment per cached main trace.
+    CachedMain,
+    /// One commitment to the common main traces of all AIRs.
+    CommonMain,
+    /// One commitment to the matrices of all AIRs in a custom round, ordered by AIR id.
+    /// `index` is the index of the round in the order the custom rounds were added during keygen.
+    Custom { index: usize, name: String },
+    /// One commitment to the after challenge traces of all AIRs in challenge phase `phase`.
+    AfterChallenge { phase: usize },
+    /// One commitment to the quotient polynomials of all AIRs.
+    Quotient,
+}
+
+impl CommitmentRound {
+    /// The commitment rounds of a system without custom rounds.
+    pub fn standard_rounds() -> Vec<Self> {
+        vec![
+            Self::Preprocessed,
+            Self::CachedMain,
+            Self::CommonMain,
+            Self::AfterChallenge { phase: 0 },
+            Self::Quotient,
+        ]
+    }
+}
+
+impl fmt::Display for CommitmentRound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Preprocessed => write!(f, "preprocessed"),
+            Self::CachedMain => write!(f, "cached main"),
+            Self::CommonMain => write!(f, "common main"),
+            Self::Custom { name, .. } => write!(f, "custom round {name}"),
+            Self::AfterChallenge { phase } => write!(f, "after challenge phase {phase}"),
+            Self::Quotient => write!(f, "quotient"),
+        }
+    }
+}
+
+/// Order of the matrices within a commitment round with one matrix per AIR, i.e. the common main
+/// round and the custom rounds. The order does not change the statement proven, only the layout
+/// of the commitment and of the opened values in the proof.
+///
+/// The order of a round depends on the trace heights, so it is determined per proof from the
+/// ordering recorded in the verifying key. Both prover and verifier remap matrices and opened
+/// values accordingly.
+///
+/// The `compare_matrix_orderings` example of the sdk measures the commitment time and the proof
+/// size of each ordering.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MatrixOrdering {
+    /// Matrices are committed in increasing order of AIR id.
+    #[default]
+    Natural,
+    /// Matrices are committed by decreasing height, then decreasing width. Ties keep the
+    /// natural order.
+    HeightDescending,
+}
+
+impl MatrixOrdering {
+    /// Given the `(height, width)` of the matrices of a round in natural order, returns the
+    /// natural index of the matrix committed at each position of the round.
+    pub fn commit_order(&self, dims: &[(usize, usize)]) -> Vec<usize> {
+        let mut order = (0..dims.len()).collect_vec();
+        match self {
+            Self::Natural => {}
+            Self::HeightDescending => {
+                order.sort_by_key(|&i| std::cmp::Reverse(dims[i]));
+            }
+        }
+        order
+    }
+
+    /// Given the `(height, width)` of the matrices of a round in natural order, returns the
+    /// position in the round of each matrix. This is the inverse of
+    /// [commit_order](Self::commit_order).
+    pub fn commit_positions(&self, dims: &[(usize, usize)]) -> Vec<usize> {
+        let mut positions = vec![0; dims.len()];
+        for (position, i) in self.commit_order(dims).into_iter().enumerate() {
+            positions[i] = position;
+        }
+        positions
+    }
+}
+
+/// A protocol feature that a verifying key may rely on. Only features that change what the
+/// verifier has to do are listed, and a verifier must reject a key relying on a feature it does
+/// not know.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum VkFeature {
+    /// Some commitment rounds are custom rounds.
+    CustomRounds = 0,
+    /// The matrices of a round are not committed in [MatrixOrdering::Natural] order.
+    MatrixOrdering = 1,
+    /// Some traces are opened at rotations beyond the next row.
+    ExtraRotations = 2,
+    /// Some public values are restricted to allowed values.
+    ConfigurationPublicValues = 3,
+    /// Some AIRs have periodic columns.
+    PeriodicColumns = 4,
+    /// Interactions are proven with the GKR log-up challenge phase.
+    GkrLogUp = 5,
+    /// Some AIRs have more than one challenge phase.
+    MultipleChallengePhases = 6,
+    /// Some buses are looked up into public tables evaluated by the verifier.
+    PublicLookupTables = 7,
+    /// Some AIRs have a preprocessed trace committed per trace height.
+    PreprocessedPerHeight = 8,
+    /// Some AIRs have a bounded trace height.
+    MaxTraceHeight = 9,
+    /// Some AIRs may be absent from proofs.
+    AbsentAirs = 10,
+    /// The committed traces and the quotients are randomized, see
+    /// [set_zero_knowledge](crate::keygen::MultiStarkKeygenBuilder::set_zero_knowledge).
+    ZeroKnowledge = 11,
+}
+
+impl VkFeature {
+    /// All features known to this version.
+    pub const ALL: [Self; 12] = [
+        Self::CustomRounds,
+        Self::MatrixOrdering,
+        Self::ExtraRotations,
+        Self::ConfigurationPublicValues,
+        Self::PeriodicColumns,
+        Self::GkrLogUp,
+        Self::MultipleChallengePhases,
+        Self::PublicLookupTables,
+        Self::PreprocessedPerHeight,
+        Self::MaxTraceHeight,
+        Self::AbsentAirs,
+        Self::ZeroKnowledge,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::CustomRounds => "custom_rounds",
+            Self::MatrixOrdering => "matrix_ordering",
+            Self::ExtraRotations => "extra_rotations",
+            Self::ConfigurationPublicValues => "configuration_public_values",
+            Self::PeriodicColumns => "periodic_columns",
+            Self::GkrLogUp => "gkr_log_up",
+            Self::MultipleChallengePhases => "multiple_challenge_phases",
+            Self::PublicLookupTables => "public_lookup_tables",
+            Self::PreprocessedPerHeight => "preprocessed_per_height",
+            Self::MaxTraceHeight => "max_trace_height",
+            Self::AbsentAirs => "absent_airs",
+            Self::ZeroKnowledge => "zero_knowledge",
+        }
+    }
+}
+
+/// The set of non-default features a verifying key relies on, populated by keygen.
+///
+/// The set is stored as a bitmask rather than a list of [VkFeature]s so that a key using
+/// features added in a later version still deserializes, and is rejected with the unknown
+/// feature bits named.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct VkFeatureSet(u64);
+
+impl VkFeatureSet {
+    /// The features supported by the verifier of this version.
+    pub fn supported() -> Self {
+        VkFeature::ALL.into_iter().collect()
+    }
+
+    pub fn from_bits(bits: u64) -> Self {
+        Self(bits)
+    }
+
+    pub fn bits(&self) -> u64 {
+        self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn contains(&self, feature: VkFeature) -> bool {
+        self.0 & (1 << feature as u8) != 0
+    }
+
+    pub fn insert(&mut self, feature: VkFeature) {
+        self.0 |= 1 << feature as u8;
+    }
+
+    pub fn remove(&mut self, feature: VkFeature) {
+        self.0 &= !(1 << feature as u8);
+    }
+
+    /// Names of the features in the set, in bit order. Bits without a known feature are named
+    /// `unknown(<bit>)`.
+    pub fn names(&self) -> Vec<String> {
+        (0..u64::BITS as u8)
+            .filter(|bit| self.0 & (1 << bit) != 0)
+            .map(
+                |bit| match VkFeature::ALL.into_iter().find(|&f| f as u8 == bit) {
+                    Some(feature) => feature.name().to_string(),
+                    None => format!("unknown({bit})"),
+                },
+            )
+            .collect()
+    }
+
+    /// Checks that every feature in the set is in `supported`.
+    pub fn check_supported(&self, supported: Self) -> Result<(), UnsupportedVkFeaturesError> {
+        let unsupported = Self(self.0 & !supported.0);
+        if unsupported.is_empty() {
+            Ok(())
+        } else {
+            Err(UnsupportedVkFeaturesError {
+                features: unsupported.names(),
+            })
+        }
+    }
+}
+
+impl FromIterator<VkFeature> for VkFeatureSet {
+    fn from_iter<I: IntoIterator<Item = VkFeature>>(iter: I) -> Self {
+        let mut set = Self::default();
+        for feature in iter {
+            set.insert(feature);
+        }
+        set
+    }
 }
 
 /// Verifier data for preprocessed trace for a single AIR.
@@ -56,8 +382,43 @@ pub struct VerifierSinglePreprocessedData<Com> {
     pub commit: Com,
 }
 
+/// The shape of a preprocessed trace that depends on the trace height, see
+/// [PartitionedBaseAir::preprocessed_shape].
+///
+/// [PartitionedBaseAir::preprocessed_shape]: crate::rap::PartitionedBaseAir::preprocessed_shape
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PreprocessedShape {
+    /// Width of the preprocessed trace at every height.
+    pub width: usize,
+    /// The log heights of the traces the AIR supports.
+    pub log_heights: Vec<usize>,
+}
+
+/// Verifier data for a preprocessed trace committed per trace height for a single AIR.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct VerifierPerHeightPreprocessedData<Com> {
+    /// The log heights of the traces the AIR supports.
+    pub log_heights: Vec<usize>,
+    /// Commitment to the preprocessed trace of each log height committed so far, by increasing
+    /// log height.
+    pub commits: Vec<(usize, Com)>,
+}
+
+impl<Com> VerifierPerHeightPreprocessedData<Com> {
+    pub fn commit(&self, log_height: usize) -> Option<&Com> {
+        self.commits
+            .iter()
+            .find(|(h, _)| *h == log_height)
+            .map(|(_, commit)| commit)
+    }
+}
+
 /// Verifying key for a single STARK (corresponding to single AIR matrix)
 #[derive(Clone, Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "Val: PrimeField64, Com: Serialize",
+    deserialize = "Val: PrimeField64, Com: Deserialize<'de>"
+))]
 #[repr(C)]
 pub struct StarkVerifyingKey<Val, Com> {
     /// Preprocessed trace data, if any
@@ -65,12 +426,39 @@ pub struct StarkVerifyingKey<Val, Com> {
     /// Parameters of the STARK
     pub params: StarkVerifyingParams,
     /// Symbolic constraints of the AIR in all challenge phases. This is
-    /// a serialization of the constraints in the AIR.
+    /// a serialization of the constraints in the AIR. The expression DAG is serialized in the
+    /// compact binary format of `SymbolicExpressionDag::to_bytes`.
+    #[serde(
+        serialize_with = "serialize_constraints_dag",
+        deserialize_with = "deserialize_constraints_dag"
+    )]
     pub symbolic_constraints: SymbolicConstraintsDag<Val>,
     /// The factor to multiple the trace degree by to get the degree of the quotient polynomial. Determined from the max constraint degree of the AIR constraints.
     /// This is equivalently the number of chunks the quotient polynomial is split into.
     pub quotient_degree: u8,
     pub rap_phase_seq_kind: RapPhaseSeqKind,
+    /// The parameters of the FRI log-up constraints of the RAP phase: the number of interactions
+    /// batched into each log-up column, if fixed, and the number of repetitions of the argument.
+    /// See [RapPhaseSeq::log_up_params].
+    ///
+    /// [RapPhaseSeq::log_up_params]: crate::interaction::RapPhaseSeq::log_up_params
+    #[serde(default)]
+    pub log_up_params: FriLogUpParams,
+    /// The sequence repeated by each periodic column of the AIR. See
+    /// [PartitionedBaseAir::periodic_columns].
+    ///
+    /// [PartitionedBaseAir::periodic_columns]: crate::rap::PartitionedBaseAir::periodic_columns
+    #[serde(default)]
+    pub periodic_columns: Vec<Vec<Val>>,
+    /// The preprocessed trace committed per trace height, if the AIR declares a
+    /// [PreprocessedShape]. The commitment of the height of a proof takes the place of
+    /// `preprocessed_data`, which is then `None`.
+    #[serde(default)]
+    pub preprocessed_per_height: Option<VerifierPerHeightPreprocessedData<Com>>,
+    /// Name of the AIR, for display purposes only. It is not part of the
+    /// [digest](MultiStarkVerifyingKey::digest).
+    #[serde(default)]
+    pub air_name: String,
 }
 
 /// Common verifying key for multiple AIRs.
@@ -80,19 +468,51 @@ pub struct StarkVerifyingKey<Val, Com> {
 #[derive(Derivative, Serialize, Deserialize)]
 #[derivative(Clone(bound = "Com<SC>: Clone"))]
 #[serde(bound(
-    serialize = "Com<SC>: Serialize",
-    deserialize = "Com<SC>: Deserialize<'de>"
+    serialize = "Val<SC>: PrimeField64, Com<SC>: Serialize",
+    deserialize = "Val<SC>: PrimeField64, Com<SC>: Deserialize<'de>"
 ))]
 pub struct MultiStarkVerifyingKey<SC: StarkGenericConfig> {
     pub per_air: Vec<StarkVerifyingKey<Val<SC>, Com<SC>>>,
+    /// The commitment rounds, in the order their commitments are observed.
+    #[serde(default = "CommitmentRound::standard_rounds")]
+    pub commitment_rounds: Vec<CommitmentRound>,
+    /// Order of the matrices within the common main and custom rounds.
+    #[serde(default)]
+    pub matrix_ordering: MatrixOrdering,
+    /// The non-default features the key relies on.
+    #[serde(default)]
+    pub features: VkFeatureSet,
+    /// The named buses, for diagnostics.
+    #[serde(default)]
+    pub bus_registry: BusRegistry,
+    /// The public tables whose lookups are evaluated by the verifier.
+    #[serde(default)]
+    pub public_lookup_tables: Vec<PublicLookupTable<Val<SC>>>,
+}
+
+/// A verifying key restricted to a subset of the AIRs of a [MultiStarkVerifyingKey], see
+/// [MultiStarkVerifyingKey::prune]. It only verifies proofs of exactly this subset.
+#[derive(Derivative, Serialize, Deserialize)]
+#[derivative(Clone(bound = "Com<SC>: Clone"))]
+#[serde(bound(
+    serialize = "Val<SC>: PrimeField64, Com<SC>: Serialize",
+    deserialize = "Val<SC>: PrimeField64, Com<SC>: Deserialize<'de>"
+))]
+pub struct PrunedVerifyingKey<SC: StarkGenericConfig> {
+    /// Ids of the retained AIRs in the full key, in increasing order.
+    pub air_ids: Vec<usize>,
+    /// The key with only the retained AIRs: `vk.per_air[i]` is the AIR with id `air_ids[i]`.
+    /// The data shared by all AIRs, such as the commitment rounds and the public lookup tables,
+    /// is kept as is.
+    pub vk: MultiStarkVerifyingKey<SC>,
 }
 
 /// Proving key for a single STARK (corresponding to single AIR matrix)
 #[derive(Serialize, Deserialize, Derivative)]
 #[derivative(Clone(bound = "Com<SC>: Clone"))]
 #[serde(bound(
-    serialize = "PcsProverData<SC>: Serialize",
-    deserialize = "PcsProverData<SC>: Deserialize<'de>"
+    serialize = "Val<SC>: PrimeField64, PcsProverData<SC>: Serialize",
+    deserialize = "Val<SC>: PrimeField64, PcsProverData<SC>: Deserialize<'de>"
 ))]
 pub struct StarkProvingKey<SC: StarkGenericConfig> {
     /// Type name of the AIR, for display purposes only
@@ -101,8 +521,20 @@ pub struct StarkProvingKey<SC: StarkGenericConfig> {
     pub vk: StarkVerifyingKey<Val<SC>, Com<SC>>,
     /// Prover only data for preprocessed trace
     pub preprocessed_data: Option<ProverOnlySinglePreprocessedData<SC>>,
+    /// Prover only data for the preprocessed trace of each log height committed so far, if the
+    /// AIR declares a [PreprocessedShape]. See
+    /// [MultiStarkProvingKey::prepare_preprocessed_heights]. It is serialized with the key, so
+    /// a key written after proving proves the heights committed so far without committing them
+    /// again. Sorted by log height, so that the serialization of the key does not depend on the
+    /// order in which the heights were committed.
+    #[serde(default)]
+    pub preprocessed_per_height: BTreeMap<usize, ProverOnlySinglePreprocessedData<SC>>,
     /// Partial proving key for RAP partial proving in challenge phases
     pub rap_partial_pk: RapPartialProvingKey<SC>,
+    /// The interactions removed at keygen, see
+    /// [MultiStarkKeygenBuilder::set_prune_interactions](crate::keygen::MultiStarkKeygenBuilder::set_prune_interactions).
+    #[serde(default)]
+    pub pruned_interactions: PrunedInteractions,
 }
 
 /// Common proving key for multiple AIRs.
@@ -112,13 +544,85 @@ pub struct StarkProvingKey<SC: StarkGenericConfig> {
 #[derive(Serialize, Deserialize, Derivative)]
 #[derivative(Clone(bound = "Com<SC>: Clone"))]
 #[serde(bound(
-    serialize = "PcsProverData<SC>: Serialize",
-    deserialize = "PcsProverData<SC>: Deserialize<'de>"
+    serialize = "Val<SC>: PrimeField64, PcsProverData<SC>: Serialize",
+    deserialize = "Val<SC>: PrimeField64, PcsProverData<SC>: Deserialize<'de>"
 ))]
 pub struct MultiStarkProvingKey<SC: StarkGenericConfig> {
     pub per_air: Vec<StarkProvingKey<SC>>,
     /// Maximum degree of constraints across all AIRs
     pub max_constraint_degree: usize,
+    /// The commitment rounds, in the order their commitments are observed.
+    #[serde(default = "CommitmentRound::standard_rounds")]
+    pub commitment_rounds: Vec<CommitmentRound>,
+    /// Order of the matrices within the common main and custom rounds.
+    #[serde(default)]
+    pub matrix_ordering: MatrixOrdering,
+    /// The non-default features the key relies on.
+    #[serde(default)]
+    pub features: VkFeatureSet,
+    /// The named buses, for diagnostics.
+    #[serde(default)]
+    pub bus_registry: BusRegistry,
+    /// The public tables whose lookups are evaluated by the verifier.
+    #[serde(default)]
+    pub public_lookup_tables: Vec<PublicLookupTable<Val<SC>>>,
+}
+
+impl<Val: PrimeField64, Com> StarkVerifyingKey<Val, Com> {
+    /// Checks that each configuration public value in `public_values` is one of its allowed
+    /// values. `air_id` is only used for the error.
+    pub fn check_configuration_public_values(
+        &self,
+        air_id: usize,
+        public_values: &[Val],
+    ) -> Result<(), DisallowedPublicValueError> {
+        for (index, allowed) in &self.params.configuration_public_values {
+            let value = public_values[*index];
+            if !allowed.contains(value) {
+                return Err(DisallowedPublicValueError {
+                    air_id,
+                    air_name: self.air_name.clone(),
+                    index: *index,
+                    value: value.to_string(),
+                    allowed: allowed.clone(),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<Val: Field, Com> StarkVerifyingKey<Val, Com> {
+    /// The log of the maximum trace height of the AIR: the bound recorded in the key, or else the
+    /// two-adicity of the field.
+    pub fn max_log_height(&self) -> usize {
+        self.params
+            .max_log_height
+            .unwrap_or_else(two_adicity::<Val>)
+    }
+
+    /// Checks that `height` is at most the maximum trace height of the AIR, see
+    /// [max_log_height](Self::max_log_height). `air_id` is only used for the error.
+    pub fn check_trace_height(
+        &self,
+        air_id: usize,
+        height: usize,
+    ) -> Result<(), TraceHeightTooLargeError> {
+        let max_log_height = self.max_log_height();
+        // The bound of a key read from untrusted bytes may not fit in a shift.
+        let max_height = u32::try_from(max_log_height)
+            .ok()
+            .and_then(|log_height| 1usize.checked_shl(log_height));
+        match max_height {
+            Some(max_height) if height > max_height => Err(TraceHeightTooLargeError {
+                air_id,
+                air_name: self.air_name.clone(),
+                height,
+                max_log_height,
+            }),
+            _ => Ok(()),
+        }
+    }
 }
 
 impl<Val, Com> StarkVerifyingKey<Val, Com> {
@@ -133,12 +637,541 @@ impl<Val, Com> StarkVerifyingKey<Val, Com> {
     pub fn has_interaction(&self) -> bool {
         !self.symbolic_constraints.interactions.is_empty()
     }
+
+    /// Returns the commitment to the preprocessed trace of a trace of `height`, if the AIR has a
+    /// preprocessed trace committed per height and `height` is committed.
+    pub fn preprocessed_commit_at_height(&self, height: usize) -> Option<&Com> {
+        // The height is untrusted, e.g. read from a proof.
+        if !height.is_power_of_two() {
+            return None;
+        }
+        let log_height = height.trailing_zeros() as usize;
+        self.preprocessed_per_height.as_ref()?.commit(log_height)
+    }
+
+    /// Returns the width of the matrix of this AIR in custom round `index`, if any.
+    pub fn custom_width(&self, index: usize) -> Option<usize> {
+        self.params
+            .width
+            .custom
+            .iter()
+            .find(|&&(round, _)| round == index)
+            .map(|&(_, width)| width)
+    }
+}
+
+impl<SC: StarkGenericConfig> MultiStarkVerifyingKey<SC> {
+    /// Returns the name of the AIR with id `air_id`, for diagnostics.
+    ///
+    /// # Panics
+    /// If there is no AIR with id `air_id`.
+    pub fn air_name(&self, air_id: usize) -> &str {
+        &self.per_air[air_id].air_name
+    }
+
+    /// Returns the key restricted to the AIRs `air_ids`, which only verifies proofs of exactly
+    /// these AIRs, see
+    /// [MultiTraceStarkVerifier::verify_pruned](crate::verifier::MultiTraceStarkVerifier::verify_pruned).
+    ///
+    /// # Panics
+    /// If `air_ids` is empty, not strictly increasing, or has an id out of bounds.
+    pub fn prune(&self, air_ids: &[usize]) -> PrunedVerifyingKey<SC> {
+        assert!(!air_ids.is_empty(), "no AIR to retain");
+        assert!(
+            air_ids.iter().tuple_windows().all(|(a, b)| a < b),
+            "AIR ids to retain must be strictly increasing"
+        );
+        assert!(
+            air_ids.iter().all(|&air_id| air_id < self.per_air.len()),
+            "AIR id out of bounds: the key has {} AIRs",
+            self.per_air.len()
+        );
+        PrunedVerifyingKey {
+            air_ids: air_ids.to_vec(),
+            vk: MultiStarkVerifyingKey {
+                per_air: air_ids
+                    .iter()
+                    .map(|&air_id| self.per_air[air_id].clone())
+                    .collect(),
+                commitment_rounds: self.commitment_rounds.clone(),
+                matrix_ordering: self.matrix_ordering,
+                features: self.features,
+                bus_registry: self.bus_registry.clone(),
+                public_lookup_tables: self.public_lookup_tables.clone(),
+            },
+        }
+    }
+
+    /// Returns the verifying key for a proof with the given `(air_id, height)` of each AIR: the
+    /// preprocessed commitment of each AIR with a preprocessed trace committed per height is the
+    /// commitment of its height. The key is only cloned if there is such an AIR. AIR ids out of
+    /// bounds are ignored.
+    ///
+    /// Returns an error if a height is not committed in the key.
+    pub fn at_heights(
+        &self,
+        air_heights: &[(usize, usize)],
+    ) -> Result<Cow<'_, Self>, MissingPreprocessedHeightError> {
+        let mut vk = Cow::Borrowed(self);
+        for &(air_id, height) in air_heights {
+            let Some(air_vk) = self.per_air.get(air_id) else {
+                continue;
+            };
+            if air_vk.preprocessed_per_height.is_none() {
+                continue;
+            }
+            let commit = air_vk
+                .preprocessed_commit_at_height(height)
+                .ok_or_else(|| MissingPreprocessedHeightError {
+                    air_id,
+                    air_name: air_vk.air_name.clone(),
+                    height,
+                })?;
+            vk.to_mut().per_air[air_id].preprocessed_data = Some(VerifierSinglePreprocessedData {
+                commit: commit.clone(),
+            });
+        }
+        Ok(vk)
+    }
 }
 
 impl<SC: StarkGenericConfig> MultiStarkProvingKey<SC> {
     pub fn get_vk(&self) -> MultiStarkVerifyingKey<SC> {
         MultiStarkVerifyingKey {
             per_air: self.per_air.iter().map(|pk| pk.vk.clone()).collect(),
+            commitment_rounds: self.commitment_rounds.clone(),
+            matrix_ordering: self.matrix_ordering,
+            features: self.features,
+            bus_registry: self.bus_registry.clone(),
+            public_lookup_tables: self.public_lookup_tables.clone(),
+        }
+    }
+
+    /// Commits the preprocessed trace of each `(air_id, height)` in `air_heights` that is not
+    /// committed yet, for the AIRs that declare a [PreprocessedShape]. Other AIRs are ignored.
+    /// `airs[air_id]` is the AIR the proving key was generated from.
+    ///
+    /// The commitments are also recorded in the verifying key, so the verifying key must be
+    /// taken with [get_vk](Self::get_vk) after committing every height that proofs use.
+    ///
+    /// # Panics
+    /// If a height is not one of the heights the AIR supports, or if the preprocessed trace
+    /// generated by the AIR does not have the declared shape.
+    pub fn prepare_preprocessed_heights(
+        &mut self,
+        pcs: &SC::Pcs,
+        airs: &[AirRef<SC>],
+        air_heights: &[(usize, usize)],
+    ) {
+        for &(air_id, height) in air_heights {
+            let pk = &mut self.per_air[air_id];
+            let Some(per_height) = pk.vk.preprocessed_per_height.as_mut() else {
+                continue;
+            };
+            let log_height = height.trailing_zeros() as usize;
+            assert!(
+                height.is_power_of_two() && per_height.log_heights.contains(&log_height),
+                "{}: unsupported trace height {height}",
+                pk.air_name
+            );
+            if pk.preprocessed_per_height.contains_key(&log_height) {
+                continue;
+            }
+            let air = airs[air_id].as_ref();
+            let trace = air
+                .preprocessed_trace_for_log_height(log_height)
+                .unwrap_or_else(|| {
+                    panic!(
+                        "{}: no preprocessed trace for log height {log_height}",
+                        pk.air_name
+                    )
+                });
+            assert_eq!(
+                (trace.width(), trace.height()),
+                (pk.vk.params.width.preprocessed.unwrap(), height),
+                "{}: preprocessed trace does not have the declared shape",
+                pk.air_name
+            );
+            let zero_knowledge = self.features.contains(VkFeature::ZeroKnowledge);
+            let (vdata, pdata) = commit_preprocessed_trace(pcs, air, trace, zero_knowledge);
+            per_height.commits.push((log_height, vdata.commit));
+            per_height
+                .commits
+                .sort_by_key(|&(log_height, _)| log_height);
+            pk.preprocessed_per_height.insert(log_height, pdata);
+        }
+    }
+
+    /// Checks that the trace of each AIR of `proof_input` is at most the maximum trace height of
+    /// the AIR.
+    pub fn check_trace_heights(
+        &self,
+        proof_input: &ProofInput<SC>,
+    ) -> Result<(), TraceHeightTooLargeError> {
+        for (air_id, input) in &proof_input.per_air {
+            self.per_air[*air_id]
+                .vk
+                .check_trace_height(*air_id, input.raw.height())?;
+        }
+        Ok(())
+    }
+
+    /// Checks that the pre-committed cached main traces of each AIR of `proof_input`, if any,
+    /// have the number and widths of cached main traces of the AIR and the height of its other
+    /// traces, so that committed data is never reused for a trace it was not computed from.
+    ///
+    /// A zero-knowledge key has no pre-committed cached main traces, since the prover randomizes
+    /// the traces it commits, see
+    /// [set_zero_knowledge](crate::keygen::MultiStarkKeygenBuilder::set_zero_knowledge).
+    pub fn check_cached_mains(&self, proof_input: &ProofInput<SC>) -> Result<(), CachedMainError> {
+        for (air_id, input) in &proof_input.per_air {
+            if input.cached_mains_pdata.is_empty() {
+                continue;
+            }
+            let vk = &self.per_air[*air_id].vk;
+            let air_name = || vk.air_name.clone();
+            if self.features.contains(VkFeature::ZeroKnowledge) {
+                return Err(CachedMainError::ZeroKnowledge {
+                    air_id: *air_id,
+                    air_name: air_name(),
+                });
+            }
+            let widths = &vk.params.width.cached_mains;
+            let cached_mains = &input.raw.cached_mains;
+            if cached_mains.len() != widths.len() || input.cached_mains_pdata.len() != widths.len()
+            {
+                return Err(CachedMainError::Count {
+                    air_id: *air_id,
+                    air_name: air_name(),
+                    expected: widths.len(),
+                    actual: input.cached_mains_pdata.len(),
+                });
+            }
+            let expected_height = input
+                .raw
+                .common_main
+                .as_ref()
+                .map_or(cached_mains[0].height(), |trace| trace.height());
+            for (index, (trace, &expected)) in zip(cached_mains, widths).enumerate() {
+                if trace.width() != expected {
+                    return Err(CachedMainError::Width {
+                        air_id: *air_id,
+                        air_name: air_name(),
+                        index,
+                        width: trace.width(),
+                        expected,
+                    });
+                }
+                if trace.height() != expected_height {
+                    return Err(CachedMainError::Height {
+                        air_id: *air_id,
+                        air_name: air_name(),
+                        index,
+                        height: trace.height(),
+                        expected: expected_height,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that each AIR marked absent in `proof_input` was allowed to be absent at keygen and
+    /// has no input, and, if the key has an AIR that may be absent, that every AIR without an input
+    /// is marked absent.
+    pub fn check_absent_airs(&self, proof_input: &ProofInput<SC>) -> Result<(), AbsentAirError> {
+        for &air_id in &proof_input.absent_air_ids {
+            let pk = &self.per_air[air_id];
+            if !pk.vk.params.may_be_absent {
+                return Err(AbsentAirError::NotAllowed {
+                    air_id,
+                    air_name: pk.air_name.clone(),
+                });
+            }
+            if proof_input.per_air.iter().any(|(id, _)| *id == air_id) {
+                return Err(AbsentAirError::HasInput {
+                    air_id,
+                    air_name: pk.air_name.clone(),
+                });
+            }
+        }
+        if self.features.contains(VkFeature::AbsentAirs) {
+            if let Some((air_id, pk)) = self.per_air.iter().enumerate().find(|(air_id, _)| {
+                !proof_input.absent_air_ids.contains(air_id)
+                    && proof_input.per_air.iter().all(|(id, _)| id != air_id)
+            }) {
+                return Err(AbsentAirError::Missing {
+                    air_id,
+                    air_name: pk.air_name.clone(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that each AIR id of `proof_input`, proven or absent, is an AIR of the key, with at
+    /// most one input, and that the traces and public values of each AIR of `proof_input` have
+    /// the shape the AIR was keygen'd with: the number and widths of the main trace partitions, a
+    /// common height that is a power of two within the maximum trace height of the AIR and whose
+    /// committed domain with a PCS of blowup `2^log_blowup` is within the two-adicity of the
+    /// field, the number of public values, and the pre-committed cached main traces, see
+    /// [check_cached_mains](Self::check_cached_mains).
+    pub fn check_proof_input(
+        &self,
+        proof_input: &ProofInput<SC>,
+        log_blowup: usize,
+    ) -> Result<(), ProofInputError> {
+        let num_airs = self.per_air.len();
+        let mut has_input = vec![false; num_airs];
+        for &air_id in proof_input.per_air.iter().map(|(air_id, _)| air_id) {
+            if air_id >= num_airs {
+                return Err(ProofInputError::UnknownAir { air_id, num_airs });
+            }
+            if has_input[air_id] {
+                return Err(ProofInputError::DuplicateAir {
+                    air_id,
+                    air_name: self.per_air[air_id].air_name.clone(),
+                });
+            }
+            has_input[air_id] = true;
+        }
+        if let Some(&air_id) = proof_input
+            .absent_air_ids
+            .iter()
+            .find(|&&air_id| air_id >= num_airs)
+        {
+            return Err(ProofInputError::UnknownAir { air_id, num_airs });
+        }
+        if !self.public_lookup_tables.is_empty() && SC::RapPhaseSeq::ID != RapPhaseSeqKind::FriLogUp
+        {
+            return Err(ProofInputError::PublicLookupTablesUnsupported {
+                rap_phase_seq_kind: SC::RapPhaseSeq::ID,
+            });
+        }
+        self.check_cached_mains(proof_input)?;
+        let two_adicity = two_adicity::<Val<SC>>();
+        let zero_knowledge = self.features.contains(VkFeature::ZeroKnowledge);
+        for (air_id, input) in &proof_input.per_air {
+            let air_id = *air_id;
+            let vk = &self.per_air[air_id].vk;
+            let air_name = || vk.air_name.clone();
+            let width = &vk.params.width;
+            let raw = &input.raw;
+            let common_mains = raw.common_main.as_slice();
+            let custom_widths = width.custom.iter().map(|&(_, width)| width).collect_vec();
+            let partitions = [
+                (
+                    MainTraceKind::Cached,
+                    raw.cached_mains.iter().map(|trace| &**trace).collect_vec(),
+                    width.cached_mains.clone(),
+                ),
+                (
+                    MainTraceKind::Common,
+                    common_mains.iter().collect_vec(),
+                    (width.common_main != 0)
+                        .then_some(width.common_main)
+                        .into_iter()
+                        .collect_vec(),
+                ),
+                (
+                    MainTraceKind::Custom,
+                    raw.custom_mains.iter().map(|trace| &**trace).collect_vec(),
+                    custom_widths,
+                ),
+            ];
+            let mut expected_height = None;
+            for (kind, traces, widths) in &partitions {
+                if traces.len() != widths.len() {
+                    return Err(ProofInputError::TraceCount {
+                        air_id,
+                        air_name: air_name(),
+                        kind: *kind,
+                        expected: widths.len(),
+                        actual: traces.len(),
+                    });
+                }
+                for (index, (trace, &expected)) in zip(traces, widths).enumerate() {
+                    if trace.width() != expected {
+                        return Err(ProofInputError::Width {
+                            air_id,
+                            air_name: air_name(),
+                            kind: *kind,
+                            index,
+                            width: trace.width(),
+                            expected,
+                        });
+                    }
+                    let expected = *expected_height.get_or_insert(trace.height());
+                    if trace.height() != expected {
+                        return Err(ProofInputError::Height {
+                            air_id,
+                            air_name: air_name(),
+                            kind: *kind,
+                            index,
+                            height: trace.height(),
+                            expected,
+                        });
+                    }
+                }
+            }
+            if let Some(height) = expected_height {
+                // The blowup is at least the quotient degree, whatever the PCS.
+                let log_blowup = log_blowup.max(log2_ceil_usize(vk.quotient_degree as usize));
+                check_trace_height(
+                    air_id,
+                    &vk.air_name,
+                    height,
+                    log_blowup + zero_knowledge as usize,
+                    two_adicity,
+                )?;
+            }
+            if raw.public_values.len() != vk.params.num_public_values {
+                return Err(ProofInputError::PublicValuesCount {
+                    air_id,
+                    air_name: air_name(),
+                    expected: vk.params.num_public_values,
+                    actual: raw.public_values.len(),
+                });
+            }
+        }
+        self.check_trace_heights(proof_input)?;
+        Ok(())
+    }
+
+    /// Checks that each trace of `proof_input` is high enough for the proofs of a zero-knowledge
+    /// key to hide it, for a PCS opening each committed matrix at `num_queries` query points
+    /// besides its out-of-domain points.
+    ///
+    /// A trace of height `n` is committed with `n` random values, see
+    /// [set_zero_knowledge](crate::keygen::MultiStarkKeygenBuilder::set_zero_knowledge), which
+    /// hide at most `n` openings: `n` must be at least `num_queries` plus the number of rows
+    /// opened out of domain, i.e. `2` plus the extra rotations. The quotient of degree `d` is
+    /// masked with `d n` random values and committed as `d + 1` matrices, each opened at
+    /// `num_queries + 1` points, so `d n` must be at least `(d + 1) (num_queries + 1)`.
+    pub fn check_zero_knowledge_heights(
+        &self,
+        proof_input: &ProofInput<SC>,
+        num_queries: usize,
+    ) -> Result<(), ProofInputError> {
+        for (air_id, input) in &proof_input.per_air {
+            let vk = &self.per_air[*air_id].vk;
+            let num_rows_opened = 2 + vk.params.extra_rotations.len();
+            let quotient_degree = vk.quotient_degree.max(1) as usize;
+            let min_height = (num_queries + num_rows_opened)
+                .max(((quotient_degree + 1) * (num_queries + 1)).div_ceil(quotient_degree));
+            let height = input.raw.height();
+            if height < min_height {
+                return Err(ProofInputError::ZeroKnowledgeHeight {
+                    air_id: *air_id,
+                    air_name: vk.air_name.clone(),
+                    height,
+                    min_height,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// The `(air_id, trace height)` of each AIR of `proof_input` with a preprocessed trace
+    /// committed per height.
+    pub fn preprocessed_air_heights(&self, proof_input: &ProofInput<SC>) -> Vec<(usize, usize)> {
+        proof_input
+            .per_air
+            .iter()
+            .filter(|(air_id, _)| self.per_air[*air_id].vk.preprocessed_per_height.is_some())
+            .map(|(air_id, input)| (*air_id, input.raw.height()))
+            .collect()
+    }
+
+    /// Returns the proving key for a proof with the given `(air_id, height)` of each AIR: the
+    /// preprocessed data of each AIR with a preprocessed trace committed per height is the data
+    /// of its height. The key is only cloned if there is such an AIR.
+    ///
+    /// Returns an error if a height was not committed with
+    /// [prepare_preprocessed_heights](Self::prepare_preprocessed_heights).
+    pub fn at_heights(
+        &self,
+        air_heights: &[(usize, usize)],
+    ) -> Result<Cow<'_, Self>, MissingPreprocessedHeightError> {
+        let mut pk = Cow::Borrowed(self);
+        for &(air_id, height) in air_heights {
+            let air_pk = &self.per_air[air_id];
+            if air_pk.vk.preprocessed_per_height.is_none() {
+                continue;
+            }
+            let commit = air_pk.vk.preprocessed_commit_at_height(height);
+            // A commitment was found only if the height is a power of two.
+            let data = air_pk
+                .preprocessed_per_height
+                .get(&(height.trailing_zeros() as usize));
+            let (Some(commit), Some(data)) = (commit, data) else {
+                return Err(MissingPreprocessedHeightError {
+                    air_id,
+                    air_name: air_pk.air_name.clone(),
+                    height,
+                });
+            };
+            let air_pk = &mut pk.to_mut().per_air[air_id];
+            air_pk.vk.preprocessed_data = Some(VerifierSinglePreprocessedData {
+                commit: commit.clone(),
+            });
+            air_pk.preprocessed_data = Some(data.clone());
+        }
+        Ok(pk)
+    }
+
+    /// Checks that the preprocessed data stored in the proving key is consistent with `airs`,
+    /// where `airs[air_id]` is the AIR the proving key was generated from.
+    ///
+    /// The preprocessed trace of an AIR is only re-derived when its
+    /// [preprocessed_version](crate::rap::BaseAirWithPublicValues::preprocessed_version)
+    /// differs from the version stored in the proving key.
+    pub fn check_preprocessed_consistency(
+        &self,
+        airs: &[AirRef<SC>],
+    ) -> Result<(), StaleProvingKeyError> {
+        if airs.len() != self.per_air.len() {
+            return Err(StaleProvingKeyError::AirCountMismatch {
+                expected: self.per_air.len(),
+                actual: airs.len(),
+            });
+        }
+        let stale_airs = zip(airs, &self.per_air)
+            .enumerate()
+            .filter(|(_, (air, pk))| !pk.is_preprocessed_consistent(air.as_ref()))
+            .map(|(air_id, (_, pk))| (air_id, pk.air_name.clone()))
+            .collect_vec();
+        if stale_airs.is_empty() {
+            Ok(())
+        } else {
+            Err(StaleProvingKeyError::StaleAirs { stale_airs })
+        }
+    }
+}
+
+impl<SC: StarkGenericConfig> StarkProvingKey<SC> {
+    /// Returns whether the preprocessed data of this proving key matches `air`.
+    /// The preprocessed trace is only re-derived if the preprocessed versions differ.
+    pub fn is_preprocessed_consistent(&self, air: &dyn AnyRap<SC>) -> bool {
+        let version = air.preprocessed_version();
+        let stored_version = self.preprocessed_data.as_ref().and_then(|d| d.version);
+        if version == stored_version {
+            return true;
+        }
+        tracing::debug!(
+            "Re-deriving preprocessed trace of {}: version changed from {:?} to {:?}",
+            self.air_name,
+            stored_version,
+            version
+        );
+        match (&self.preprocessed_data, air.preprocessed_trace()) {
+            (Some(stored), Some(trace)) => {
+                stored.trace.width() == trace.width()
+                    && stored.trace.height() == trace.height()
+                    && stored.content_hash == preprocessed_content_hash(&trace)
+            }
+            (None, None) => true,
+            _ => false,
         }
     }
 }
@@ -146,6 +1179,37 @@ impl<SC: StarkGenericConfig> MultiStarkVerifyingKey<SC> {
     pub fn num_challenges_per_phase(&self) -> Vec<usize> {
         self.full_view().num_challenges_per_phase()
     }
+
+    /// Returns the number of interactions and the message sizes of the AIRs.
+    pub fn interaction_stats(&self) -> InteractionStats {
+        let num_interactions_per_air = self
+            .per_air
+            .iter()
+            .map(|vk| vk.symbolic_constraints.interactions.len())
+            .collect_vec();
+        InteractionStats {
+            total_num_interactions: num_interactions_per_air.iter().sum(),
+            num_interactions_per_air,
+            max_num_fields: self
+                .per_air
+                .iter()
+                .flat_map(|vk| &vk.symbolic_constraints.interactions)
+                .map(|interaction| interaction.fields.len())
+                .max()
+                .unwrap_or(0),
+        }
+    }
+}
+
+/// Number of interactions and message sizes of a system of AIRs, see
+/// [MultiStarkVerifyingKey::interaction_stats].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InteractionStats {
+    /// Number of interactions of each AIR, indexed by AIR id.
+    pub num_interactions_per_air: Vec<usize>,
+    pub total_num_interactions: usize,
+    /// Maximum number of fields of the message of an interaction.
+    pub max_num_fields: usize,
 }
 
 /// Prover only data for preprocessed trace for a single AIR.
@@ -161,4 +1225,99 @@ pub struct ProverOnlySinglePreprocessedData<SC: StarkGenericConfig> {
     pub trace: Arc<RowMajorMatrix<Val<SC>>>,
     /// Prover data, such as a Merkle tree, for the trace commitment.
     pub data: Arc<PcsProverData<SC>>,
+    /// Hash of the preprocessed trace contents, see [preprocessed_content_hash].
+    #[serde(default)]
+    pub content_hash: u64,
+    /// The AIR's preprocessed version token at keygen time.
+    #[serde(default)]
+    pub version: Option<u64>,
+}
+
+/// Deterministic, non-cryptographic hash of the contents of a preprocessed trace.
+/// Only used to detect proving keys that are stale with respect to their AIR.
+pub fn preprocessed_content_hash<F: Field>(trace: &RowMajorMatrix<F>) -> u64 {
+    let mut hasher = FxHasher::default();
+    trace.width().hash(&mut hasher);
+    trace.values.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// The largest `k` such that `2^k` divides the order of the multiplicative group of `F`, i.e. the
+/// log of the largest trace height the field supports.
+pub(crate) fn two_adicity<F: Field>() -> usize {
+    (F::order() - 1u32).trailing_zeros().unwrap_or(0) as usize
+}
+
+/// Checks that `height`, the common height of the traces of the AIR `air_id`, is a power of two
+/// whose committed domain, `2^log_blowup` times larger, is within the `two_adicity` of the field.
+/// In zero-knowledge mode, `log_blowup` includes the doubling of the committed traces.
+fn check_trace_height(
+    air_id: usize,
+    air_name: &str,
+    height: usize,
+    log_blowup: usize,
+    two_adicity: usize,
+) -> Result<(), ProofInputError> {
+    if !height.is_power_of_two() {
+        return Err(ProofInputError::HeightNotPowerOfTwo {
+            air_id,
+            air_name: air_name.to_string(),
+            height,
+        });
+    }
+    if height.trailing_zeros() as usize + log_blowup > two_adicity {
+        return Err(ProofInputError::HeightAboveTwoAdicity {
+            air_id,
+            air_name: air_name.to_string(),
+            height,
+            log_blowup,
+            two_adicity,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use p3_baby_bear::BabyBear;
+
+    use super::*;
+
+    #[test]
+    fn test_two_adicity() {
+        assert_eq!(two_adicity::<BabyBear>(), 27);
+    }
+
+    #[test]
+    fn test_check_trace_height() {
+        let two_adicity = two_adicity::<BabyBear>();
+        for (height, log_blowup) in [(1, 0), (2, 1), (1 << 20, 3), (1 << two_adicity, 0)] {
+            assert_eq!(
+                check_trace_height(3, "Air", height, log_blowup, two_adicity),
+                Ok(())
+            );
+        }
+        for height in [0, 3, 6, (1 << two_adicity) + 1] {
+            assert_eq!(
+                check_trace_height(3, "Air", height, 1, two_adicity),
+                Err(ProofInputError::HeightNotPowerOfTwo {
+                    air_id: 3,
+                    air_name: "Air".to_string(),
+                    height,
+                })
+            );
+        }
+        for (log_height, log_blowup) in [(two_adicity + 1, 0), (40, 0), (two_adicity, 1), (25, 3)] {
+            assert_eq!(
+                check_trace_height(3, "Air", 1 << log_height, log_blowup, two_adicity),
+                Err(ProofInputError::HeightAboveTwoAdicity {
+                    air_id: 3,
+                    air_name: "Air".to_string(),
+                    height: 1 << log_height,
+                    log_blowup,
+                    two_adicity,
+                })
+            );
+        }
+    }
 }
@@ -2,12 +2,19 @@ use itertools::Itertools;
 
 use crate::{
     config::{Com, StarkGenericConfig, Val},
-    keygen::types::{MultiStarkVerifyingKey, StarkVerifyingKey},
+    interaction::public_lookup::PublicLookupTable,
+    keygen::types::{
+        CommitmentRound, MatrixOrdering, MultiStarkVerifyingKey, StarkVerifyingKey, VkFeatureSet,
+    },
 };
 
 #[derive(Clone, derive_new::new)]
 pub(crate) struct MultiStarkVerifyingKeyView<'a, Val, Com> {
     pub per_air: Vec<&'a StarkVerifyingKey<Val, Com>>,
+    pub commitment_rounds: &'a [CommitmentRound],
+    pub matrix_ordering: MatrixOrdering,
+    pub public_lookup_tables: &'a [PublicLookupTable<Val>],
+    pub features: VkFeatureSet,
 }
 
 impl<SC: StarkGenericConfig> MultiStarkVerifyingKey<SC> {
@@ -18,6 +25,10 @@ impl<SC: StarkGenericConfig> MultiStarkVerifyingKey<SC> {
     pub(crate) fn view(&self, air_ids: &[usize]) -> MultiStarkVerifyingKeyView<Val<SC>, Com<SC>> {
         MultiStarkVerifyingKeyView {
             per_air: air_ids.iter().map(|&id| &self.per_air[id]).collect(),
+            commitment_rounds: &self.commitment_rounds,
+            matrix_ordering: self.matrix_ordering,
+            public_lookup_tables: &self.public_lookup_tables,
+            features: self.features,
         }
     }
 }
@@ -40,6 +51,32 @@ impl<Val, Com: Clone> MultiStarkVerifyingKeyView<'_, Val, Com> {
         self.preprocessed_commits().into_iter().flatten().collect()
     }
 
+    /// Returns the indices of the custom rounds with a matrix from at least one AIR, in
+    /// increasing order. Only these rounds are committed.
+    pub fn present_custom_rounds(&self) -> Vec<usize> {
+        let num_custom_rounds = self
+            .commitment_rounds
+            .iter()
+            .filter(|round| matches!(round, CommitmentRound::Custom { .. }))
+            .count();
+        (0..num_custom_rounds)
+            .filter(|&index| {
+                self.per_air
+                    .iter()
+                    .any(|vk| vk.custom_width(index).is_some())
+            })
+            .collect()
+    }
+
+    /// The custom round with index `index`, for error messages.
+    pub fn custom_round(&self, index: usize) -> CommitmentRound {
+        self.commitment_rounds
+            .iter()
+            .find(|round| matches!(round, CommitmentRound::Custom { index: i, .. } if *i == index))
+            .cloned()
+            .unwrap_or_else(|| panic!("no custom round with index {index}"))
+    }
+
     pub fn num_phases(&self) -> usize {
         self.per_air
             .iter()
@@ -17,6 +17,7 @@ pub mod air_builders;
 mod chip;
 /// Helper types associated to generic STARK config.
 pub mod config;
+mod encoding;
 /// Trait for STARK backend engine proving keygen, proviing, verifying API functions.
 pub mod engine;
 /// GKR batch prover for Grand Product and LogUp lookup arguments.
@@ -41,12 +42,13 @@ pub mod verifier;
 pub use chip::{Chip, ChipUsageGetter, Stateful};
 pub use rap::AirRef;
 
-// Use jemalloc as global allocator for performance
-#[cfg(all(feature = "jemalloc", unix, not(test)))]
+// Use jemalloc as global allocator for performance. With `mem-metrics`, the binary installs
+// `prover::memory::TrackingAllocator` instead, which wraps it.
+#[cfg(all(feature = "jemalloc", unix, not(feature = "mem-metrics"), not(test)))]
 #[global_allocator]
 static GLOBAL: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
 
 // Use mimalloc as global allocator
-#[cfg(all(feature = "mimalloc", not(test)))]
+#[cfg(all(feature = "mimalloc", not(feature = "mem-metrics"), not(test)))]
 #[global_allocator]
 static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
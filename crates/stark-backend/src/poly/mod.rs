@@ -1,2 +1,3 @@
 pub mod multi;
+pub mod periodic;
 pub mod uni;
@@ -104,6 +104,27 @@ pub fn hypercube_eq<F: Field>(x: &[F], y: &[F]) -> F {
         .product()
 }
 
+/// Returns evaluations of the function `x -> eq(x, y) * v` for each `x` in `{0, 1}^n`, where `n`
+/// is the length of `y`.
+///
+/// Evaluations are in lexicographic order, the same order as the evaluations of an [Mle].
+pub fn hypercube_eq_evals<F: Field>(y: &[F], v: F) -> Vec<F> {
+    let mut evals = Vec::with_capacity(1 << y.len());
+    evals.push(v);
+
+    for &y_i in y.iter().rev() {
+        for j in 0..evals.len() {
+            // `lhs[j] = eq(0, y_i) * c[i]`
+            // `rhs[j] = eq(1, y_i) * c[i]`
+            let tmp = evals[j] * y_i;
+            evals.push(tmp);
+            evals[j] -= tmp;
+        }
+    }
+
+    evals
+}
+
 /// Computes `hypercube_eq(0, assignment) * eval0 + hypercube_eq(1, assignment) * eval1`.
 pub fn fold_mle_evals<F, EF>(assignment: EF, eval0: F, eval1: F) -> EF
 where
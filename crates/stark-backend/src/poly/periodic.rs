@@ -0,0 +1,102 @@
+//! Periodic columns, evaluated from the sequence they repeat without a commitment.
+
+use p3_commit::PolynomialSpace;
+use p3_field::{ExtensionField, Field, FieldAlgebra};
+
+/// The polynomial of a periodic column over a trace domain.
+///
+/// On a trace domain `s * <g>` of size `n`, the column repeating a sequence of length `p` is the
+/// polynomial `P((x / s)^(n / p))`, where `P` of degree less than `p` interpolates the sequence on
+/// the subgroup `<g^(n / p)>` of order `p`. It has degree less than `n`, like any trace column.
+#[derive(Clone, Debug)]
+pub struct PeriodicColumn<F> {
+    /// Coefficients of `P`, with the constant term first.
+    coeffs: Vec<F>,
+    /// `n / p`
+    exponent: u64,
+    shift_inverse: F,
+}
+
+impl<F: Field> PeriodicColumn<F> {
+    /// Interpolates the column repeating `values` on `trace_domain`.
+    ///
+    /// Returns `None` if the length of `values` is not a power of two dividing the size of
+    /// `trace_domain`.
+    pub fn new<D: PolynomialSpace<Val = F>>(values: &[F], trace_domain: &D) -> Option<Self> {
+        let period = values.len();
+        let height = trace_domain.size();
+        if !period.is_power_of_two() || period > height {
+            return None;
+        }
+        let exponent = (height / period) as u64;
+        let shift = trace_domain.first_point();
+        let generator = trace_domain.next_point(shift).unwrap() * shift.inverse();
+        // Inverse DFT over the subgroup of order `period`. Periods are short, so the quadratic
+        // algorithm is fine.
+        let root_inverse = generator.exp_u64(exponent).inverse();
+        let period_inverse = F::from_canonical_usize(period).inverse();
+        let coeffs = root_inverse
+            .powers()
+            .take(period)
+            .map(|root_pow| {
+                values
+                    .iter()
+                    .zip(root_pow.powers())
+                    .map(|(&value, pow)| value * pow)
+                    .sum::<F>()
+                    * period_inverse
+            })
+            .collect();
+        Some(Self {
+            coeffs,
+            exponent,
+            shift_inverse: shift.inverse(),
+        })
+    }
+
+    pub fn period(&self) -> usize {
+        self.coeffs.len()
+    }
+
+    /// Evaluates the column at `point`.
+    pub fn evaluate<EF: ExtensionField<F>>(&self, point: EF) -> EF {
+        let y = (point * self.shift_inverse).exp_u64(self.exponent);
+        self.coeffs
+            .iter()
+            .rfold(EF::ZERO, |acc, &coeff| acc * y + coeff)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use p3_baby_bear::BabyBear;
+    use p3_commit::TwoAdicMultiplicativeCoset;
+    use p3_field::{extension::BinomialExtensionField, FieldExtensionAlgebra};
+
+    use super::*;
+    use crate::utils::rotate_point;
+
+    type F = BabyBear;
+    type EF = BinomialExtensionField<BabyBear, 4>;
+
+    #[test]
+    fn test_periodic_column_on_trace_domain() {
+        let values = [3, 1, 4, 1].map(F::from_canonical_u32);
+        for shift in [F::ONE, F::GENERATOR] {
+            let domain = TwoAdicMultiplicativeCoset { log_n: 4, shift };
+            let column = PeriodicColumn::new(&values, &domain).unwrap();
+            assert_eq!(column.period(), 4);
+            for row in 0..16 {
+                let point = rotate_point(&domain, EF::from_base(shift), row);
+                assert_eq!(column.evaluate(point), EF::from_base(values[row % 4]));
+            }
+        }
+
+        let domain = TwoAdicMultiplicativeCoset {
+            log_n: 1,
+            shift: F::ONE,
+        };
+        assert!(PeriodicColumn::new(&values, &domain).is_none());
+        assert!(PeriodicColumn::new(&values[..3], &domain).is_none());
+    }
+}
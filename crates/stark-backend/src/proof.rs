@@ -1,36 +1,376 @@
 use derivative::Derivative;
-use serde::{Deserialize, Serialize};
+use p3_field::FieldAlgebra;
+use p3_util::log2_ceil_usize;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-use crate::config::{Com, PcsProof, RapPhaseSeqPartialProof, StarkGenericConfig, Val};
+use crate::{
+    config::{Com, PcsProof, RapPhaseSeqPartialProof, StarkGenericConfig, Val},
+    encoding,
+    interaction::RapPhaseSeqKind,
+    keygen::types::MultiStarkVerifyingKey,
+    verifier::{validate_proof_shape, PcsShape, PublicValuesView, VerificationError},
+};
 
 /// The full proof for multiple RAPs where trace matrices are committed into
 /// multiple commitments, where each commitment is multi-matrix.
 ///
 /// Includes the quotient commitments and FRI opening proofs for the constraints as well.
-#[derive(Serialize, Deserialize, Derivative)]
-#[serde(bound = "")]
+///
+/// The parts of the proof that do not depend on the commitments and PCS of the config are in its
+/// [ProofCore], which can be read without naming the config.
+#[derive(Derivative)]
 #[derivative(Clone(bound = "Com<SC>: Clone"))]
 pub struct Proof<SC: StarkGenericConfig> {
     /// The PCS commitments
     pub commitments: Commitments<Com<SC>>,
-    /// Opening proofs separated by partition, but this may change
-    pub opening: OpeningProof<PcsProof<SC>, SC::Challenge>,
-    /// Proof data for each AIR
-    pub per_air: Vec<AirProofData<Val<SC>, SC::Challenge>>,
+    /// The AIRs of the proof, their claimed values and the opened values
+    pub core: ProofCore<Val<SC>, SC::Challenge>,
+    /// The opening proof of the PCS, e.g. the FRI proof
+    pub pcs_proof: PcsProof<SC>,
     /// Partial proof for rap phase if it exists
     pub rap_phase_seq_proof: Option<RapPhaseSeqPartialProof<SC>>,
 }
 
+/// The parts of a [Proof] over the base field `F` and extension field `EF` that do not depend on
+/// the commitments and PCS of its config, so that proofs of configs sharing their fields can be
+/// read by the same code.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ProofCore<F, EF> {
+    /// Proof data for each AIR
+    pub per_air: Vec<AirProofData<F, EF>>,
+    /// The values of the traces opened by the PCS
+    pub opened_values: OpenedValues<EF>,
+    /// Sorted ids of the AIRs of the key that are absent from the proof, see
+    /// [ProofInput::absent_air_ids](crate::prover::types::ProofInput::absent_air_ids).
+    #[serde(default)]
+    pub absent_air_ids: Vec<usize>,
+}
+
+/// The serde layout of [Proof], which predates [ProofCore], with the opened values next to the
+/// PCS proof. Serializing by reference keeps the layout without cloning the proof.
+#[derive(Serialize, Deserialize)]
+struct ProofLayout<Commitments, PcsProof, OpenedValues, PerAir, RapPhaseSeqProof, AbsentAirIds> {
+    commitments: Commitments,
+    opening: OpeningLayout<PcsProof, OpenedValues>,
+    per_air: PerAir,
+    rap_phase_seq_proof: RapPhaseSeqProof,
+    #[serde(default)]
+    absent_air_ids: AbsentAirIds,
+}
+
+#[derive(Serialize, Deserialize)]
+struct OpeningLayout<PcsProof, OpenedValues> {
+    proof: PcsProof,
+    values: OpenedValues,
+}
+
+impl<SC: StarkGenericConfig> Serialize for Proof<SC> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ProofLayout {
+            commitments: &self.commitments,
+            opening: OpeningLayout {
+                proof: &self.pcs_proof,
+                values: &self.core.opened_values,
+            },
+            per_air: &self.core.per_air,
+            rap_phase_seq_proof: &self.rap_phase_seq_proof,
+            absent_air_ids: &self.core.absent_air_ids,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, SC: StarkGenericConfig> Deserialize<'de> for Proof<SC> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let layout: ProofLayout<
+            Commitments<Com<SC>>,
+            PcsProof<SC>,
+            OpenedValues<SC::Challenge>,
+            Vec<AirProofData<Val<SC>, SC::Challenge>>,
+            Option<RapPhaseSeqPartialProof<SC>>,
+            Vec<usize>,
+        > = ProofLayout::deserialize(deserializer)?;
+        Ok(Proof {
+            commitments: layout.commitments,
+            core: ProofCore {
+                per_air: layout.per_air,
+                opened_values: layout.opening.values,
+                absent_air_ids: layout.absent_air_ids,
+            },
+            pcs_proof: layout.opening.proof,
+            rap_phase_seq_proof: layout.rap_phase_seq_proof,
+        })
+    }
+}
+
 impl<SC: StarkGenericConfig> Proof<SC> {
+    /// The parts of the proof that do not depend on the commitments and PCS of the config.
+    ///
+    /// The values are **unverified**: they are only the claims of the proof until it verifies.
+    pub fn core(&self) -> &ProofCore<Val<SC>, SC::Challenge> {
+        &self.core
+    }
+
+    /// Drops the commitments and the PCS proof, keeping the parts of the proof that other configs
+    /// with the same fields can read.
+    pub fn into_core(self) -> ProofCore<Val<SC>, SC::Challenge> {
+        self.core
+    }
+
+    /// Checks that the shape of the proof, i.e. the lengths of all its vectors and its trace
+    /// heights, is consistent with `vk` and, if given, `pcs_shape` without hashing, see
+    /// [validate_proof_shape]. The verifier runs the same check before touching the transcript,
+    /// so this is only needed to reject a malformed proof before verifying it, e.g. when it is
+    /// received.
+    pub fn validate_shape(
+        &self,
+        vk: &MultiStarkVerifyingKey<SC>,
+        pcs_shape: Option<&PcsShape<SC>>,
+    ) -> Result<(), VerificationError> {
+        validate_proof_shape(vk, self, pcs_shape)
+    }
+
+    /// The public values of each AIR of the proof, by AIR id or by the AIR names of `vk`.
+    ///
+    /// # Panics
+    /// If an AIR id of the proof is not an AIR of `vk`.
+    pub fn public_values_view<'a>(
+        &'a self,
+        vk: &'a MultiStarkVerifyingKey<SC>,
+    ) -> PublicValuesView<'a, Val<SC>> {
+        let per_air = self
+            .core
+            .per_air
+            .iter()
+            .map(|air_proof| {
+                assert!(
+                    air_proof.air_id < vk.per_air.len(),
+                    "proof has AIR id {}, but the verifying key has {} AIRs",
+                    air_proof.air_id,
+                    vk.per_air.len()
+                );
+                (air_proof.air_id, air_proof.public_values.as_slice())
+            })
+            .collect();
+        PublicValuesView::new(
+            vk.per_air.iter().map(|vk| vk.air_name.as_str()).collect(),
+            per_air,
+        )
+    }
+
+    /// See [ProofCore::public_values].
+    pub fn public_values(&self) -> impl Iterator<Item = (usize, &[Val<SC>])> {
+        self.core.public_values()
+    }
+
+    /// See [ProofCore::log_trace_heights].
+    pub fn log_trace_heights(&self) -> Vec<(usize, usize)> {
+        self.core.log_trace_heights()
+    }
+
+    /// The commitments of the proof that are not preprocessed.
+    ///
+    /// The commitments are **unverified**: the proof does not open them consistently until it
+    /// verifies.
+    pub fn commitments(&self) -> &Commitments<Com<SC>> {
+        &self.commitments
+    }
+
+    /// A summary of the AIRs and rounds of the proof, for indexers that do not verify it.
+    ///
+    /// The summary is **unverified**: it only describes the claims of the proof until it
+    /// verifies.
+    pub fn metadata(&self) -> ProofMetadata {
+        let per_air = &self.core.per_air;
+        ProofMetadata {
+            air_ids: self.get_air_ids(),
+            trace_heights: per_air.iter().map(|p| p.degree).collect(),
+            absent_air_ids: self.core.absent_air_ids.clone(),
+            num_public_values: per_air.iter().map(|p| p.public_values.len()).collect(),
+            num_main_commitments: self.commitments.main_trace.len(),
+            num_after_challenge_commitments: self.commitments.after_challenge.len(),
+            num_custom_commitments: self.commitments.custom.len(),
+        }
+    }
+
+    /// The number of bytes of each section of the binary serde encoding of the proof, with
+    /// fixed-width integers and `u64` lengths as in bincode with its default options. The
+    /// sections add up to the length of the encoding. The sizes of the sdk serialization of
+    /// proofs differ.
+    pub fn size_breakdown(&self) -> ProofSizeBreakdown {
+        ProofSizeBreakdown {
+            commitments: serialized_size(&self.commitments),
+            pcs_proof: serialized_size(&self.pcs_proof),
+            opened_values: serialized_size(&self.core.opened_values),
+            per_air: serialized_size(&self.core.per_air),
+            rap_phase_seq_proof: serialized_size(&self.rap_phase_seq_proof),
+            absent_air_ids: serialized_size(&self.core.absent_air_ids),
+        }
+    }
+
     pub fn get_air_ids(&self) -> Vec<usize> {
-        self.per_air.iter().map(|p| p.air_id).collect()
+        self.core.get_air_ids()
     }
     pub fn get_public_values(&self) -> Vec<Vec<Val<SC>>> {
+        self.core.get_public_values()
+    }
+
+    /// See [ProofCore::exposed_values].
+    pub fn exposed_values(&self, air_id: usize, phase: usize) -> &[SC::Challenge] {
+        self.core.exposed_values(air_id, phase)
+    }
+
+    /// See [ProofCore::exposed_values_iter].
+    pub fn exposed_values_iter(&self) -> impl Iterator<Item = (usize, usize, &[SC::Challenge])> {
+        self.core.exposed_values_iter()
+    }
+
+    /// The sum over the AIRs of the proof of the cumulative sums of the FRI log-up argument, one
+    /// for each repetition of the argument. The argument batches all buses together, so every sum
+    /// is zero when the messages balance on every bus.
+    ///
+    /// Empty if no AIR of the proof has interactions.
+    ///
+    /// Returns [VerificationError::InvalidAirIds] if an AIR id of the proof is not an AIR of
+    /// `mvk`, and [VerificationError::RapPhaseSeqKindMismatch] if an AIR of the proof with
+    /// interactions does not use [RapPhaseSeqKind::FriLogUp] in `mvk`.
+    pub fn log_up_total_sums(
+        &self,
+        mvk: &MultiStarkVerifyingKey<SC>,
+    ) -> Result<Vec<SC::Challenge>, VerificationError> {
+        let mut total_sums: Vec<SC::Challenge> = vec![];
+        for air_proof in &self.core.per_air {
+            let Some(vk) = mvk.per_air.get(air_proof.air_id) else {
+                return Err(VerificationError::InvalidAirIds(self.get_air_ids()));
+            };
+            if !vk.has_interaction() {
+                continue;
+            }
+            if vk.rap_phase_seq_kind != RapPhaseSeqKind::FriLogUp {
+                return Err(VerificationError::RapPhaseSeqKindMismatch {
+                    air_id: air_proof.air_id,
+                    air_name: vk.air_name.clone(),
+                    expected: RapPhaseSeqKind::FriLogUp,
+                    found: vk.rap_phase_seq_kind,
+                });
+            }
+            let cumulative_sums = self.exposed_values(air_proof.air_id, 0);
+            total_sums.resize(cumulative_sums.len(), SC::Challenge::ZERO);
+            for (total_sum, &cumulative_sum) in total_sums.iter_mut().zip(cumulative_sums) {
+                *total_sum += cumulative_sum;
+            }
+        }
+        Ok(total_sums)
+    }
+}
+
+impl<F, EF> ProofCore<F, EF> {
+    /// Iterates over the AIR id and the public values of each AIR of the proof, in the order of
+    /// the proof.
+    ///
+    /// The values are **unverified**: they are only the claims of the proof until it verifies.
+    pub fn public_values(&self) -> impl Iterator<Item = (usize, &[F])> {
+        self.per_air
+            .iter()
+            .map(|air_proof| (air_proof.air_id, air_proof.public_values.as_slice()))
+    }
+
+    /// The AIR id and the log2 of the trace height of each AIR of the proof, in the order of the
+    /// proof. A height that is not a power of two, which a valid proof never has, is rounded up.
+    ///
+    /// The heights are **unverified**: they are only the claims of the proof until it verifies.
+    pub fn log_trace_heights(&self) -> Vec<(usize, usize)> {
+        self.per_air
+            .iter()
+            .map(|air_proof| (air_proof.air_id, log2_ceil_usize(air_proof.degree)))
+            .collect()
+    }
+
+    pub fn get_air_ids(&self) -> Vec<usize> {
+        self.per_air.iter().map(|p| p.air_id).collect()
+    }
+    pub fn get_public_values(&self) -> Vec<Vec<F>>
+    where
+        F: Clone,
+    {
         self.per_air
             .iter()
             .map(|p| p.public_values.clone())
             .collect()
     }
+
+    /// The values exposed by the AIR with id `air_id` in the challenge phase `phase`. Empty if the
+    /// AIR is not in the proof or does not take part in the phase.
+    pub fn exposed_values(&self, air_id: usize, phase: usize) -> &[EF] {
+        self.per_air
+            .iter()
+            .find(|air_proof| air_proof.air_id == air_id)
+            .and_then(|air_proof| air_proof.exposed_values_after_challenge.get(phase))
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+
+    /// Iterates over `(air_id, phase, values)` for the values exposed by each AIR of the proof in
+    /// each challenge phase it takes part in, in the order of the proof.
+    pub fn exposed_values_iter(&self) -> impl Iterator<Item = (usize, usize, &[EF])> {
+        self.per_air.iter().flat_map(|air_proof| {
+            air_proof
+                .exposed_values_after_challenge
+                .iter()
+                .enumerate()
+                .map(|(phase, values)| (air_proof.air_id, phase, values.as_slice()))
+        })
+    }
+}
+
+/// A summary of a [Proof], see [Proof::metadata]. Its values are **unverified**.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProofMetadata {
+    /// AIR ids of the proof, in the order of the proof.
+    pub air_ids: Vec<usize>,
+    /// Trace height of each AIR of the proof.
+    pub trace_heights: Vec<usize>,
+    /// Ids of the AIRs of the key that are absent from the proof.
+    pub absent_air_ids: Vec<usize>,
+    /// Number of public values of each AIR of the proof.
+    pub num_public_values: Vec<usize>,
+    /// Number of main trace commitments: one for each cached main trace, and one for all common
+    /// main traces.
+    pub num_main_commitments: usize,
+    /// Number of challenge phases with a commitment.
+    pub num_after_challenge_commitments: usize,
+    pub num_custom_commitments: usize,
+}
+
+/// The number of bytes of each section of the binary serde encoding of a [Proof], see
+/// [Proof::size_breakdown].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ProofSizeBreakdown {
+    pub commitments: usize,
+    /// The opening proof of the PCS, e.g. the FRI proof.
+    pub pcs_proof: usize,
+    pub opened_values: usize,
+    /// The trace heights, public values and exposed values of each AIR.
+    pub per_air: usize,
+    /// The partial proof of the challenge phase, e.g. the proof of work of the log-up phase.
+    pub rap_phase_seq_proof: usize,
+    pub absent_air_ids: usize,
+}
+
+impl ProofSizeBreakdown {
+    /// The length of the encoding of the proof.
+    pub fn total(&self) -> usize {
+        self.commitments
+            + self.pcs_proof
+            + self.opened_values
+            + self.per_air
+            + self.rap_phase_seq_proof
+            + self.absent_air_ids
+    }
+}
+
+fn serialized_size<T: Serialize>(value: &T) -> usize {
+    encoding::serialized_size(value).expect("failed to serialize proof")
 }
 
 /// All commitments to a multi-matrix STARK that are not preprocessed.
@@ -46,6 +386,10 @@ pub struct Commitments<Com> {
     pub after_challenge: Vec<Com>,
     /// Shared commitment for all quotient polynomial evaluations
     pub quotient: Com,
+    /// One commitment for each custom round with at least one matrix, ordered by custom round
+    /// index.
+    #[serde(default)]
+    pub custom: Vec<Com>,
 }
 
 /// PCS opening proof with opened values for multi-matrix AIR.
@@ -67,12 +411,44 @@ pub struct OpenedValues<Challenge> {
     pub after_challenge: Vec<Vec<AdjacentOpenedValues<Challenge>>>,
     /// For each RAP, for each quotient chunk in quotient poly, the opened values
     pub quotient: Vec<Vec<Vec<Challenge>>>,
+    /// For each custom round commitment, for each matrix in the commitment, the opened values
+    #[serde(default)]
+    pub custom: Vec<Vec<AdjacentOpenedValues<Challenge>>>,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct AdjacentOpenedValues<Challenge> {
     pub local: Vec<Challenge>,
     pub next: Vec<Challenge>,
+    /// Opened values at each of the `extra_rotations` of the AIR, in order.
+    #[serde(default)]
+    pub rotated: Vec<Vec<Challenge>>,
+}
+
+impl<Challenge> OpenedValues<Challenge> {
+    /// The number of opened values, in all rounds.
+    pub fn num_values(&self) -> usize {
+        let num_adjacent_values = |values: &[AdjacentOpenedValues<Challenge>]| {
+            values
+                .iter()
+                .map(AdjacentOpenedValues::num_values)
+                .sum::<usize>()
+        };
+        num_adjacent_values(&self.preprocessed)
+            + [&self.main, &self.after_challenge, &self.custom]
+                .into_iter()
+                .flatten()
+                .map(|values| num_adjacent_values(values))
+                .sum::<usize>()
+            + self.quotient.iter().flatten().map(Vec::len).sum::<usize>()
+    }
+}
+
+impl<Challenge> AdjacentOpenedValues<Challenge> {
+    /// The number of opened values, at all rows.
+    pub fn num_values(&self) -> usize {
+        self.local.len() + self.next.len() + self.rotated.iter().map(Vec::len).sum::<usize>()
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize)]
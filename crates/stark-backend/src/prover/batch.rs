@@ -0,0 +1,133 @@
+//! Proving many independent proofs with the same proving key.
+
+use cfg_if::cfg_if;
+use itertools::Itertools;
+use p3_field::PrimeField64;
+#[cfg(feature = "parallel")]
+use p3_maybe_rayon::prelude::*;
+
+use crate::{
+    config::{StarkGenericConfig, Val},
+    engine::StarkEngine,
+    keygen::types::MultiStarkProvingKey,
+    proof::Proof,
+    prover::{
+        pool::{ProverConfig, ProverPoolError},
+        types::ProofInput,
+    },
+};
+
+/// Default number of proofs proved at the same time by a [BatchProver].
+pub const DEFAULT_MAX_IN_FLIGHT: usize = 2;
+
+/// Proves many independent proofs with the same proving key, on a dedicated thread pool.
+///
+/// Up to [max_in_flight](Self::set_max_in_flight) proofs are proved at the same time, so that
+/// the parts of a proof that do not use all threads, e.g. the transcript and the FRI queries,
+/// overlap with the trace commitments and quotient computation of the others. Only the engine,
+/// the key and the pool are shared: each proof allocates its own buffers, as with
+/// [StarkEngine::prove].
+///
+/// The threads of the pool are configured by a [ProverConfig], see
+/// [with_config](Self::with_config). The proofs are identical to proving each input with
+/// [StarkEngine::prove].
+pub struct BatchProver<'a, SC: StarkGenericConfig, E> {
+    engine: &'a E,
+    mpk: &'a MultiStarkProvingKey<SC>,
+    max_in_flight: usize,
+    #[cfg(feature = "parallel")]
+    pool: rayon::ThreadPool,
+}
+
+impl<'a, SC, E> BatchProver<'a, SC, E>
+where
+    SC: StarkGenericConfig,
+    Val<SC>: PrimeField64,
+    E: StarkEngine<SC> + Sync,
+    MultiStarkProvingKey<SC>: Sync,
+    ProofInput<SC>: Send,
+    Proof<SC>: Send,
+{
+    /// Creates a batch prover proving with `mpk` on a pool of `num_threads` threads, where 0
+    /// means the number of threads of the global rayon pool. Without the `parallel` feature,
+    /// `num_threads` is ignored and the proofs are proved one at a time.
+    pub fn new(engine: &'a E, mpk: &'a MultiStarkProvingKey<SC>, num_threads: usize) -> Self {
+        let config = ProverConfig {
+            num_threads,
+            ..Default::default()
+        };
+        Self::with_config(engine, mpk, &config)
+            .expect("failed to build the thread pool of the batch prover")
+    }
+
+    /// Same as [new](Self::new), but the threads of the pool are configured by `config`.
+    /// Returns an error if the pool cannot be built, see [ProverConfig::build_pool]. Without the
+    /// `parallel` feature, `config` is only validated.
+    pub fn with_config(
+        engine: &'a E,
+        mpk: &'a MultiStarkProvingKey<SC>,
+        config: &ProverConfig,
+    ) -> Result<Self, ProverPoolError> {
+        #[cfg(not(feature = "parallel"))]
+        config.validate()?;
+        Ok(Self {
+            engine,
+            mpk,
+            max_in_flight: DEFAULT_MAX_IN_FLIGHT,
+            #[cfg(feature = "parallel")]
+            pool: config.build_pool("batch-prover")?,
+        })
+    }
+
+    /// Sets the number of proofs proved at the same time, [DEFAULT_MAX_IN_FLIGHT] by default.
+    /// More proofs in flight keep the threads busier at the cost of memory.
+    ///
+    /// # Panics
+    /// If `max_in_flight` is 0.
+    pub fn set_max_in_flight(&mut self, max_in_flight: usize) {
+        assert!(max_in_flight > 0, "max_in_flight must be positive");
+        self.max_in_flight = max_in_flight;
+    }
+
+    /// Proves `input` on the pool of the batch prover. The proof is identical to proving `input`
+    /// with [StarkEngine::prove].
+    ///
+    /// # Panics
+    /// Under the same conditions as [StarkEngine::prove].
+    pub fn prove(&self, input: ProofInput<SC>) -> Proof<SC> {
+        cfg_if! {
+            if #[cfg(feature = "parallel")] {
+                self.pool.install(|| self.engine.prove(self.mpk, input))
+            } else {
+                self.engine.prove(self.mpk, input)
+            }
+        }
+    }
+
+    /// Proves each of `inputs`, returning the proofs in the order of `inputs`. The inputs are
+    /// proved in chunks of [max_in_flight](Self::set_max_in_flight) proofs in parallel, and a
+    /// chunk starts once the previous one is done.
+    ///
+    /// # Panics
+    /// Under the same conditions as [StarkEngine::prove] for any of the inputs.
+    pub fn prove_concurrently(&self, inputs: Vec<ProofInput<SC>>) -> Vec<Proof<SC>> {
+        let mut proofs = Vec::with_capacity(inputs.len());
+        for chunk in &inputs.into_iter().chunks(self.max_in_flight) {
+            let chunk = chunk.collect_vec();
+            #[cfg(feature = "parallel")]
+            proofs.extend(self.pool.install(|| {
+                chunk
+                    .into_par_iter()
+                    .map(|input| self.engine.prove(self.mpk, input))
+                    .collect::<Vec<_>>()
+            }));
+            #[cfg(not(feature = "parallel"))]
+            proofs.extend(
+                chunk
+                    .into_iter()
+                    .map(|input| self.engine.prove(self.mpk, input)),
+            );
+        }
+        proofs
+    }
+}
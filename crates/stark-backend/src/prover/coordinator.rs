@@ -1,23 +1,25 @@
-use std::{iter, marker::PhantomData};
+use std::{iter, marker::PhantomData, mem};
 
 use itertools::{izip, Itertools};
 use p3_challenger::CanObserve;
-use p3_field::FieldAlgebra;
+use p3_field::{FieldAlgebra, PrimeField64};
 use p3_util::log2_strict_usize;
 use tracing::instrument;
 
+#[cfg(feature = "mem-metrics")]
+use super::memory::MemoryRecorder;
 use super::{
     hal::{ProverBackend, ProverDevice},
     types::{DeviceMultiStarkProvingKey, HalProof, ProvingContext},
-    Prover,
+    Prover, ProvingError, ProvingOptions, ProvingPhase,
 };
 use crate::{
     config::{Com, StarkGenericConfig, Val},
-    keygen::view::MultiStarkVerifyingKeyView,
+    keygen::{types::CommitmentRound, view::MultiStarkVerifyingKeyView},
     proof::{AirProofData, Commitments},
     prover::{
         hal::MatrixDimensions,
-        metrics::trace_metrics,
+        metrics::{trace_metrics, Stopwatch},
         types::{PairView, SingleCommitPreimage},
     },
     utils::metrics_span,
@@ -33,6 +35,9 @@ pub struct Coordinator<SC: StarkGenericConfig, PB, PD> {
     pub backend: PB,
     pub device: PD,
     challenger: SC::Challenger,
+    options: ProvingOptions,
+    #[cfg(feature = "mem-metrics")]
+    memory: MemoryRecorder,
     phantom: PhantomData<(SC, PB)>,
 }
 
@@ -42,14 +47,30 @@ impl<SC: StarkGenericConfig, PB, PD> Coordinator<SC, PB, PD> {
             backend,
             device,
             challenger,
+            options: ProvingOptions::default(),
+            #[cfg(feature = "mem-metrics")]
+            memory: MemoryRecorder::default(),
             phantom: PhantomData,
         }
     }
+
+    /// Sets the progress callback and cancellation flag of the proofs, see [ProvingOptions].
+    pub fn set_options(&mut self, options: ProvingOptions) {
+        self.options = options;
+    }
+
+    /// Starts recording the memory of `phase` before the proof, e.g. of
+    /// [ProvingPhase::MainTraceCommit] to count the commitments of the cached main traces.
+    #[cfg(feature = "mem-metrics")]
+    pub(crate) fn begin_memory_phase(&mut self, phase: ProvingPhase) {
+        self.memory.begin(phase);
+    }
 }
 
 impl<SC, PB, PD> Prover for Coordinator<SC, PB, PD>
 where
     SC: StarkGenericConfig,
+    Val<SC>: PrimeField64,
     PB: ProverBackend<
         Val = Val<SC>,
         Challenge = SC::Challenge,
@@ -58,7 +79,7 @@ where
     >,
     PD: ProverDevice<PB>,
 {
-    type Proof = HalProof<PB>;
+    type Proof = Result<HalProof<PB>, ProvingError>;
     type ProvingKeyView<'a>
         = &'a DeviceMultiStarkProvingKey<'a, PB>
     where
@@ -74,22 +95,41 @@ where
     /// Assumes the main traces have been generated and committed already.
     ///
     /// The [DeviceMultiStarkProvingKey] should already be filtered to only include the relevant AIR's proving keys.
+    ///
+    /// Returns [ProvingError::Cancelled] if the cancellation flag of the options is set.
     #[instrument(name = "Coordinator::prove", level = "info", skip_all)]
     fn prove<'a>(
         &'a mut self,
         mpk: Self::ProvingKeyView<'a>,
-        ctx: Self::ProvingContext<'a>,
+        mut ctx: Self::ProvingContext<'a>,
     ) -> Self::Proof {
         #[cfg(feature = "bench-metrics")]
         let start = std::time::Instant::now();
+        let total = Stopwatch::start(&self.options);
         assert!(mpk.validate(&ctx), "Invalid proof input");
+        for ((air_id, air_ctx), pk) in ctx.per_air.iter().zip(&mpk.per_air) {
+            if let Err(err) = pk
+                .vk
+                .check_configuration_public_values(*air_id, &air_ctx.public_values)
+            {
+                panic!("Invalid proof input for {}: {err}", pk.air_name);
+            }
+        }
 
+        let absent_air_ids = mem::take(&mut ctx.absent_air_ids);
         let num_air = ctx.per_air.len();
         #[allow(clippy::type_complexity)]
-        let (cached_commits_per_air, cached_views_per_air, common_main_per_air, pvs_per_air): (
+        let (
+            cached_commits_per_air,
+            cached_views_per_air,
+            common_main_per_air,
+            custom_mains_per_air,
+            pvs_per_air,
+        ): (
             Vec<Vec<PB::Commitment>>,
             Vec<Vec<SingleCommitPreimage<&'a PB::Matrix, &'a PB::PcsData>>>,
             Vec<Option<PB::Matrix>>,
+            Vec<Vec<PB::Matrix>>,
             Vec<Vec<PB::Val>>,
         ) = ctx
             .into_iter()
@@ -100,19 +140,117 @@ where
                     cached_commits,
                     cached_views,
                     ctx.common_main,
+                    ctx.custom_mains,
                     ctx.public_values,
                 )
             })
             .multiunzip();
 
         // ==================== All trace commitments that do not require challenges ====================
-        // Commit all common main traces in a commitment. Traces inside are ordered by AIR id.
+        #[cfg(feature = "mem-metrics")]
+        self.memory.begin(ProvingPhase::MainTraceCommit);
+        let mut stopwatch = Stopwatch::start(&self.options);
+        self.options
+            .checkpoint(ProvingPhase::MainTraceCommit, 0.0)?;
+        // Commit all common main traces in a commitment. Traces inside are ordered by the matrix
+        // ordering of the key.
+        let common_main_dims = common_main_per_air
+            .iter()
+            .flatten()
+            .map(|trace| (trace.height(), trace.width()))
+            .collect_vec();
+        let common_main_order = mpk.matrix_ordering.commit_order(&common_main_dims);
+        let common_main_positions = mpk.matrix_ordering.commit_positions(&common_main_dims);
         let (common_main_traces, (common_main_commit, common_main_pcs_data)) =
             metrics_span("main_trace_commit_time_ms", || {
-                let traces = common_main_per_air.into_iter().flatten().collect_vec();
+                let traces = permute(
+                    common_main_per_air.into_iter().flatten().collect_vec(),
+                    &common_main_order,
+                );
                 let prover_data = self.device.commit(&traces);
                 (traces, prover_data)
             });
+        let mut common_main_idx = 0;
+        let common_main_views_per_air = mpk
+            .per_air
+            .iter()
+            .map(|pk| {
+                pk.vk.has_common_main().then(|| {
+                    let matrix_idx = common_main_positions[common_main_idx];
+                    common_main_idx += 1;
+                    SingleCommitPreimage {
+                        trace: &common_main_traces[matrix_idx],
+                        data: &common_main_pcs_data,
+                        matrix_idx: matrix_idx as u32,
+                    }
+                })
+            })
+            .collect_vec();
+
+        // Commit the matrices of each custom round in a commitment, skipping rounds without
+        // matrices. Matrices inside are ordered by the matrix ordering of the key.
+        let num_custom_rounds = mpk
+            .commitment_rounds
+            .iter()
+            .filter(|round| matches!(round, CommitmentRound::Custom { .. }))
+            .count();
+        let mut custom_traces_per_round: Vec<Vec<PB::Matrix>> =
+            (0..num_custom_rounds).map(|_| vec![]).collect();
+        for (pk, custom_mains) in izip!(&mpk.per_air, custom_mains_per_air) {
+            for (&(index, _), trace) in izip!(&pk.vk.params.width.custom, custom_mains) {
+                custom_traces_per_round[index].push(trace);
+            }
+        }
+        let (custom_orders, custom_positions): (Vec<_>, Vec<_>) = custom_traces_per_round
+            .iter()
+            .map(|traces| {
+                let dims = traces
+                    .iter()
+                    .map(|trace| (trace.height(), trace.width()))
+                    .collect_vec();
+                (
+                    mpk.matrix_ordering.commit_order(&dims),
+                    mpk.matrix_ordering.commit_positions(&dims),
+                )
+            })
+            .unzip();
+        let custom_traces_per_round = izip!(custom_traces_per_round, &custom_orders)
+            .map(|(traces, order)| permute(traces, order))
+            .collect_vec();
+        let custom_round_data: Vec<Option<(PB::Commitment, PB::PcsData)>> =
+            metrics_span("custom_round_commit_time_ms", || {
+                custom_traces_per_round
+                    .iter()
+                    .map(|traces| (!traces.is_empty()).then(|| self.device.commit(traces)))
+                    .collect()
+            });
+        let mut custom_matrix_idx = vec![0usize; num_custom_rounds];
+        let custom_views_per_air = mpk
+            .per_air
+            .iter()
+            .map(|pk| {
+                pk.vk
+                    .params
+                    .width
+                    .custom
+                    .iter()
+                    .map(|&(index, _)| {
+                        let matrix_idx = custom_positions[index][custom_matrix_idx[index]];
+                        custom_matrix_idx[index] += 1;
+                        SingleCommitPreimage {
+                            trace: &custom_traces_per_round[index][matrix_idx],
+                            data: &custom_round_data[index].as_ref().unwrap().1,
+                            matrix_idx: matrix_idx as u32,
+                        }
+                    })
+                    .collect_vec()
+            })
+            .collect_vec();
+        let custom_commitments = custom_round_data
+            .iter()
+            .flatten()
+            .map(|(commit, _)| commit.clone())
+            .collect_vec();
 
         // Commitments order:
         // - for each air:
@@ -127,23 +265,43 @@ where
             .collect();
 
         // All commitments that don't require challenges have been made, so we collect them into trace views:
-        let mut common_main_idx = 0;
         let mut log_trace_height_per_air: Vec<u8> = Vec::with_capacity(num_air);
         let mut pair_trace_view_per_air = Vec::with_capacity(num_air);
-        for (pk, cached_views, pvs) in izip!(&mpk.per_air, &cached_views_per_air, &pvs_per_air) {
+        for (pk, cached_views, common_main_view, custom_views, pvs) in izip!(
+            &mpk.per_air,
+            &cached_views_per_air,
+            &common_main_views_per_air,
+            &custom_views_per_air,
+            &pvs_per_air
+        ) {
             let mut main_trace_views: Vec<&PB::Matrix> =
                 cached_views.iter().map(|view| view.trace).collect_vec();
-            if pk.vk.has_common_main() {
-                main_trace_views.push(&common_main_traces[common_main_idx]);
-                common_main_idx += 1;
-            }
+            main_trace_views.extend(common_main_view.as_ref().map(|view| view.trace));
+            main_trace_views.extend(custom_views.iter().map(|view| view.trace));
             let trace_height = main_trace_views.first().expect("no main trace").height();
+            for (index, view) in custom_views.iter().enumerate() {
+                assert_eq!(
+                    view.trace.height(),
+                    trace_height,
+                    "{}: custom main trace {index} does not have the height of the main trace",
+                    pk.air_name
+                );
+            }
             let log_trace_height: u8 = log2_strict_usize(trace_height).try_into().unwrap();
+            for values in &pk.vk.periodic_columns {
+                assert!(
+                    values.len() <= trace_height,
+                    "{}: period {} of a periodic column exceeds the trace height {trace_height}",
+                    pk.air_name,
+                    values.len()
+                );
+            }
             let pair_trace_view = PairView {
                 log_trace_height,
                 preprocessed: pk.preprocessed_data.as_ref().map(|d| &d.trace),
                 partitioned_main: main_trace_views,
                 public_values: pvs.to_vec(),
+                periodic_columns: pk.vk.periodic_columns.clone(),
             };
             log_trace_height_per_air.push(log_trace_height);
             pair_trace_view_per_air.push(pair_trace_view);
@@ -153,16 +311,48 @@ where
         trace_metrics(&mpk.per_air, &log_trace_height_per_air).emit();
 
         // ============ Challenger observations before additional RAP phases =============
+        // Observe the ids of the AIRs of the proof, which are sorted and distinct:
+        self.challenger
+            .observe(Val::<SC>::from_canonical_usize(mpk.air_ids.len()));
+        for &air_id in &mpk.air_ids {
+            self.challenger
+                .observe(Val::<SC>::from_canonical_usize(air_id));
+        }
+        // Observe the ids of the absent AIRs, which are sorted and distinct:
+        self.challenger
+            .observe(Val::<SC>::from_canonical_usize(absent_air_ids.len()));
+        for &air_id in &absent_air_ids {
+            self.challenger
+                .observe(Val::<SC>::from_canonical_usize(air_id));
+        }
         // Observe public values:
         for pvs in &pvs_per_air {
             self.challenger.observe_slice(pvs);
         }
 
-        // Observes preprocessed and main commitments:
+        // Observes the commitments of all rounds before the challenge phases, in order:
         let mvk = mpk.vk_view();
         let preprocessed_commits = mvk.flattened_preprocessed_commits();
-        self.challenger.observe_slice(&preprocessed_commits);
-        self.challenger.observe_slice(&main_trace_commitments);
+        for round in mpk.commitment_rounds {
+            match round {
+                CommitmentRound::Preprocessed => {
+                    self.challenger.observe_slice(&preprocessed_commits);
+                }
+                CommitmentRound::CachedMain => {
+                    self.challenger
+                        .observe_slice(&main_trace_commitments[..main_trace_commitments.len() - 1]);
+                }
+                CommitmentRound::CommonMain => {
+                    self.challenger.observe(common_main_commit.clone());
+                }
+                CommitmentRound::Custom { index, .. } => {
+                    if let Some((commit, _)) = &custom_round_data[*index] {
+                        self.challenger.observe(commit.clone());
+                    }
+                }
+                CommitmentRound::AfterChallenge { .. } | CommitmentRound::Quotient => {}
+            }
+        }
         // Observe trace domain size per air:
         self.challenger.observe_slice(
             &log_trace_height_per_air
@@ -173,15 +363,44 @@ where
         );
 
         // ==================== Partially prove all RAP phases that require challenges ====================
+        #[cfg(feature = "mem-metrics")]
+        self.memory.begin(ProvingPhase::AfterChallengeTrace);
+        let main_trace_commit = stopwatch.lap();
+        self.options
+            .checkpoint(ProvingPhase::AfterChallengeTrace, 0.0)?;
         let (rap_partial_proof, prover_data_after) = self.device.partially_prove(
             &mut self.challenger,
             &mpk.per_air,
+            mpk.public_lookup_tables,
             pair_trace_view_per_air,
         );
-        // Challenger observes additional commitments if any exist:
-        for (commit, _) in &prover_data_after.committed_pcs_data_per_phase {
-            self.challenger.observe(commit.clone());
-        }
+        // The after challenge phases are the last to read the raw main traces, so only the
+        // committed data is kept, and the traces owned by the prover are dropped. The cached main
+        // traces are borrowed from the proving context and dropped with it.
+        let cached_views_per_air = cached_views_per_air
+            .iter()
+            .map(|views| {
+                views
+                    .iter()
+                    .map(SingleCommitPreimage::without_trace)
+                    .collect_vec()
+            })
+            .collect_vec();
+        let common_main_views_per_air = common_main_views_per_air
+            .iter()
+            .map(|view| view.as_ref().map(SingleCommitPreimage::without_trace))
+            .collect_vec();
+        let custom_views_per_air = custom_views_per_air
+            .iter()
+            .map(|views| {
+                views
+                    .iter()
+                    .map(SingleCommitPreimage::without_trace)
+                    .collect_vec()
+            })
+            .collect_vec();
+        drop(common_main_traces);
+        drop(custom_traces_per_round);
 
         // Collect exposed_values_per_air for the proof:
         // - transpose per_phase, per_air -> per_air, per_phase
@@ -214,14 +433,20 @@ where
         // ==================== Quotient polynomial computation and commitment, if any ====================
         // Note[jpw]: Currently we always call this step, we could add a flag to skip it for protocols that
         // do not require quotient poly.
+        #[cfg(feature = "mem-metrics")]
+        self.memory.begin(ProvingPhase::Quotient);
+        let after_challenge_trace = stopwatch.lap();
+        self.options.checkpoint(ProvingPhase::Quotient, 0.0)?;
         let (quotient_commit, quotient_data) = self.device.eval_and_commit_quotient(
             &mut self.challenger,
             &mpk.per_air,
             &pvs_per_air,
             &cached_views_per_air,
-            &common_main_pcs_data,
+            &common_main_views_per_air,
+            &custom_views_per_air,
             &prover_data_after,
-        );
+            &self.options,
+        )?;
         // Observe quotient commitment
         self.challenger.observe(quotient_commit.clone());
 
@@ -230,6 +455,10 @@ where
             .into_iter()
             .unzip();
         // ==================== Polynomial Opening Proofs ====================
+        #[cfg(feature = "mem-metrics")]
+        self.memory.begin(ProvingPhase::Opening);
+        stopwatch = Stopwatch::start(&self.options);
+        self.options.checkpoint(ProvingPhase::Opening, 0.0)?;
         let opening = metrics_span("pcs_opening_time_ms", || {
             let mut quotient_degrees = Vec::with_capacity(mpk.per_air.len());
             let mut preprocessed = Vec::new();
@@ -237,32 +466,62 @@ where
             for pk in &mpk.per_air {
                 quotient_degrees.push(pk.vk.quotient_degree);
                 if let Some(data) = pk.preprocessed_data.as_ref().map(|d| &d.data) {
-                    preprocessed.push(data);
+                    preprocessed.push((data, pk.vk.params.extra_rotations.as_slice()));
                 }
             }
 
-            let main = cached_views_per_air
-                .into_iter()
-                .flatten()
-                .map(|cv| cv.data)
-                .chain(iter::once(&common_main_pcs_data))
+            // Each cached main commitment has a single matrix, and the common main commitment
+            // has one matrix per AIR with a common main trace.
+            let common_main_rotations = mpk
+                .per_air
+                .iter()
+                .filter(|pk| pk.vk.has_common_main())
+                .map(|pk| pk.vk.params.extra_rotations.as_slice())
+                .collect_vec();
+            let common_main_rotations = permute(common_main_rotations, &common_main_order);
+            let main = izip!(&mpk.per_air, cached_views_per_air)
+                .flat_map(|(pk, cached_views)| {
+                    cached_views
+                        .into_iter()
+                        .map(move |cv| (cv.data, vec![pk.vk.params.extra_rotations.as_slice()]))
+                })
+                .chain(iter::once((&common_main_pcs_data, common_main_rotations)))
+                .collect();
+            // Each custom round commitment has one matrix per AIR in the round.
+            let custom = custom_round_data
+                .iter()
+                .enumerate()
+                .filter_map(|(index, data)| {
+                    let (_, data) = data.as_ref()?;
+                    let rotations = mpk
+                        .per_air
+                        .iter()
+                        .filter(|pk| pk.vk.custom_width(index).is_some())
+                        .map(|pk| pk.vk.params.extra_rotations.as_slice())
+                        .collect_vec();
+                    Some((data, permute(rotations, &custom_orders[index])))
+                })
                 .collect();
             self.device.open(
                 &mut self.challenger,
                 preprocessed,
                 main,
+                custom,
                 pcs_data_after,
                 quotient_data,
                 &quotient_degrees,
             )
         });
 
+        let opening_timing = stopwatch.elapsed();
+
         // ==================== Collect data into proof ====================
         // Collect the commitments
         let commitments = Commitments {
             main_trace: main_trace_commitments,
             after_challenge: commitments_after,
             quotient: quotient_commit,
+            custom: custom_commitments,
         };
         let proof = HalProof {
             commitments,
@@ -283,16 +542,42 @@ where
             )
             .collect(),
             rap_partial_proof,
+            absent_air_ids,
         };
 
         #[cfg(feature = "bench-metrics")]
         ::metrics::gauge!("stark_prove_excluding_trace_time_ms")
             .set(start.elapsed().as_millis() as f64);
 
-        proof
+        #[cfg(feature = "mem-metrics")]
+        {
+            let profile = mem::take(&mut self.memory).finish();
+            tracing::info!("{profile}");
+            #[cfg(feature = "bench-metrics")]
+            profile.emit();
+            if let Some(memory_profile) = &self.options.memory_profile {
+                *memory_profile.lock().unwrap() = profile;
+            }
+        }
+
+        // The quotient evaluation and commitment are recorded by the device.
+        self.options.record_metrics(|metrics| {
+            metrics.main_trace_commit = main_trace_commit;
+            metrics.after_challenge_trace = after_challenge_trace;
+            metrics.opening = opening_timing;
+            metrics.total = total.elapsed();
+        });
+
+        Ok(proof)
     }
 }
 
+/// Reorders `items` so that position `i` holds `items[order[i]]`.
+fn permute<T>(items: Vec<T>, order: &[usize]) -> Vec<T> {
+    let mut items = items.into_iter().map(Some).collect_vec();
+    order.iter().map(|&i| items[i].take().unwrap()).collect()
+}
+
 impl<'a, PB: ProverBackend> DeviceMultiStarkProvingKey<'a, PB> {
     pub(crate) fn validate(&self, ctx: &ProvingContext<PB>) -> bool {
         ctx.per_air.len() == self.air_ids.len()
@@ -302,9 +587,29 @@ impl<'a, PB: ProverBackend> DeviceMultiStarkProvingKey<'a, PB> {
                 .zip(&self.air_ids)
                 .all(|((id1, _), id2)| id1 == id2)
             && ctx.per_air.iter().tuple_windows().all(|(a, b)| a.0 < b.0)
+            && ctx
+                .per_air
+                .iter()
+                .zip(&self.per_air)
+                .all(|((_, ctx), pk)| ctx.custom_mains.len() == pk.vk.params.width.custom.len())
+            && ctx
+                .absent_air_ids
+                .iter()
+                .tuple_windows()
+                .all(|(a, b)| a < b)
+            && ctx
+                .absent_air_ids
+                .iter()
+                .all(|air_id| !self.air_ids.contains(air_id))
     }
 
     pub(crate) fn vk_view(&self) -> MultiStarkVerifyingKeyView<'a, PB::Val, PB::Commitment> {
-        MultiStarkVerifyingKeyView::new(self.per_air.iter().map(|pk| pk.vk).collect())
+        MultiStarkVerifyingKeyView::new(
+            self.per_air.iter().map(|pk| pk.vk).collect(),
+            self.commitment_rounds,
+            self.matrix_ordering,
+            self.public_lookup_tables,
+            self.features,
+        )
     }
 }
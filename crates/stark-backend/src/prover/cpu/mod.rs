@@ -1,11 +1,16 @@
-use std::{iter::zip, marker::PhantomData, ops::Deref, sync::Arc};
+use std::{
+    iter::zip,
+    marker::PhantomData,
+    ops::Deref,
+    sync::{Arc, Mutex},
+};
 
 use derivative::Derivative;
 use itertools::{izip, zip_eq, Itertools};
-use opener::OpeningProver;
-use p3_challenger::FieldChallenger;
+use opener::{OpeningProver, OpeningWitness};
+use p3_challenger::{CanObserve, FieldChallenger};
 use p3_commit::{Pcs, PolynomialSpace};
-use p3_field::FieldExtensionAlgebra;
+use p3_field::{Field, FieldExtensionAlgebra};
 use p3_matrix::{dense::RowMajorMatrix, Matrix};
 use p3_util::log2_strict_usize;
 use quotient::QuotientCommitter;
@@ -14,7 +19,7 @@ use super::{
     hal::{self, DeviceDataTransporter, MatrixDimensions, ProverBackend, ProverDevice},
     types::{
         DeviceMultiStarkProvingKey, DeviceStarkProvingKey, PairView, ProverDataAfterRapPhases,
-        RapView, SingleCommitPreimage,
+        SingleCommitPreimage,
     },
 };
 use crate::{
@@ -23,10 +28,19 @@ use crate::{
         Com, PcsProof, PcsProverData, RapPartialProvingKey, RapPhaseSeqPartialProof,
         StarkGenericConfig, Val,
     },
-    interaction::RapPhaseSeq,
-    keygen::{types::MultiStarkProvingKey, view::MultiStarkVerifyingKeyView},
+    interaction::{public_lookup::PublicLookupTable, RapPhaseSeq},
+    keygen::{
+        types::{MatrixOrdering, MultiStarkProvingKey, VkFeatureSet},
+        view::MultiStarkVerifyingKeyView,
+    },
     proof::OpeningProof,
-    prover::{hal::TraceCommitter, types::RapSinglePhaseView},
+    prover::{
+        hal::TraceCommitter,
+        metrics::Stopwatch,
+        types::RapSinglePhaseView,
+        view::{BitReversed, RapViewBuilder},
+        ProvingError, ProvingOptions,
+    },
     utils::metrics_span,
 };
 
@@ -52,6 +66,29 @@ pub struct CpuBackend<SC> {
 #[derivative(Clone(bound = ""), Copy(bound = ""))]
 pub struct CpuDevice<'a, SC> {
     config: &'a SC,
+    /// The source of the random values of the committed traces and the quotients, if they are
+    /// randomized, see [with_zero_knowledge](Self::with_zero_knowledge).
+    #[new(default)]
+    zero_knowledge: Option<&'a dyn ZkRandomness>,
+}
+
+/// A source of the random values of zero-knowledge mode, see [CpuDevice::with_zero_knowledge].
+/// The values must be cryptographically secure, e.g. drawn from the randomness of the operating
+/// system.
+pub trait ZkRandomness: Send + Sync {
+    /// Fills `dest` with uniformly random values.
+    fn fill_u64(&self, dest: &mut [u64]);
+}
+
+/// What an engine needs to prove for a zero-knowledge key, see
+/// [StarkEngine::zero_knowledge](crate::engine::StarkEngine::zero_knowledge).
+#[derive(Clone, Copy)]
+pub struct ZeroKnowledge<'a> {
+    pub randomness: &'a dyn ZkRandomness,
+    /// The number of FRI queries of the config. Each query opens each committed matrix at one
+    /// point, which the random values of the matrix must hide along with its out-of-domain
+    /// openings.
+    pub num_queries: usize,
 }
 
 impl<SC: StarkGenericConfig> ProverBackend for CpuBackend<SC> {
@@ -75,6 +112,9 @@ pub struct PcsData<SC: StarkGenericConfig> {
     pub data: Arc<PcsProverData<SC>>,
     /// A mixed matrix commitment scheme commits to multiple trace matrices within a single commitment.
     /// This is the ordered list of log2 heights of all committed trace matrices.
+    ///
+    /// In zero-knowledge mode, these are the heights of the traces, and the committed matrices
+    /// are twice as high, see [CpuDevice::with_zero_knowledge].
     pub log_trace_heights: Vec<u8>,
 }
 
@@ -93,10 +133,61 @@ impl<SC> CpuDevice<'_, SC> {
     }
 }
 
-impl<SC: StarkGenericConfig> CpuDevice<'_, SC> {
+impl<'a, SC: StarkGenericConfig> CpuDevice<'a, SC> {
     pub fn pcs(&self) -> &SC::Pcs {
         self.config.pcs()
     }
+
+    /// Randomizes the committed traces and the quotients with the values of `randomness`, for
+    /// the proofs of a zero-knowledge key, see
+    /// [set_zero_knowledge](crate::keygen::MultiStarkKeygenBuilder::set_zero_knowledge). They are
+    /// not randomized if `randomness` is `None`.
+    ///
+    /// In zero-knowledge mode, each trace of height `n` is committed as a matrix of height `2n`
+    /// whose even rows are the rows of the trace and whose odd rows are random, over the domain
+    /// of height `2n`. The trace domain is still the domain of height `n`, which the committed
+    /// polynomial agrees with the trace on.
+    pub fn with_zero_knowledge(mut self, randomness: Option<&'a dyn ZkRandomness>) -> Self {
+        self.zero_knowledge = randomness;
+        self
+    }
+}
+
+/// A matrix of uniformly random values drawn from `randomness`, for zero-knowledge mode. The
+/// reduction of a `u64` into the field is biased by less than `p / 2^64`.
+pub(crate) fn random_matrix<F: Field>(
+    randomness: &dyn ZkRandomness,
+    height: usize,
+    width: usize,
+) -> RowMajorMatrix<F> {
+    let mut words = vec![0; height * width];
+    randomness.fill_u64(&mut words);
+    let values = words.into_iter().map(F::from_wrapped_u64).collect();
+    RowMajorMatrix::new(values, width)
+}
+
+/// Interleaves the rows of `trace` with random rows, see [CpuDevice::with_zero_knowledge].
+fn randomize_trace<F: Field>(
+    randomness: &dyn ZkRandomness,
+    trace: &RowMajorMatrix<F>,
+) -> RowMajorMatrix<F> {
+    interleave_rows(
+        trace,
+        &random_matrix(randomness, trace.height(), trace.width()),
+    )
+}
+
+/// The matrix of twice the height of `trace` whose even rows are the rows of `trace` and odd rows
+/// the rows of `odd`, of the same dimensions.
+pub(crate) fn interleave_rows<F: Field>(
+    trace: &RowMajorMatrix<F>,
+    odd: &RowMajorMatrix<F>,
+) -> RowMajorMatrix<F> {
+    let width = trace.width();
+    let values = zip(trace.rows(), odd.rows())
+        .flat_map(|(row, odd_row)| row.chain(odd_row))
+        .collect();
+    RowMajorMatrix::new(values, width)
 }
 
 impl<SC: StarkGenericConfig> ProverDevice<CpuBackend<SC>> for CpuDevice<'_, SC> {}
@@ -109,9 +200,13 @@ impl<SC: StarkGenericConfig> TraceCommitter<CpuBackend<SC>> for CpuDevice<'_, SC
             .map(|matrix| {
                 let height = matrix.height();
                 let log_height: u8 = log2_strict_usize(height).try_into().unwrap();
+                let matrix = match self.zero_knowledge {
+                    Some(randomness) => randomize_trace(randomness, matrix),
+                    None => matrix.as_ref().clone(),
+                };
                 // Recomputing the domain is lightweight
-                let domain = pcs.natural_domain_for_degree(height);
-                (log_height, (domain, matrix.as_ref().clone()))
+                let domain = pcs.natural_domain_for_degree(matrix.height());
+                (log_height, (domain, matrix))
             })
             .unzip();
         let (commit, data) = pcs.commit(traces_with_domains);
@@ -130,6 +225,7 @@ impl<SC: StarkGenericConfig> hal::RapPartialProver<CpuBackend<SC>> for CpuDevice
         &self,
         challenger: &mut SC::Challenger,
         pk_views: &[DeviceStarkProvingKey<'a, CpuBackend<SC>>],
+        public_lookup_tables: &[PublicLookupTable<Val<SC>>],
         trace_views: Vec<PairView<&'a Arc<RowMajorMatrix<Val<SC>>>, Val<SC>>>,
     ) -> (
         Option<RapPhaseSeqPartialProof<SC>>,
@@ -153,75 +249,80 @@ impl<SC: StarkGenericConfig> hal::RapPartialProver<CpuBackend<SC>> for CpuDevice
                 preprocessed: v.preprocessed.as_ref().map(|p| p.as_ref()),
                 partitioned_main: v.partitioned_main.iter().map(|m| m.as_ref()).collect(),
                 public_values: v.public_values.clone(),
+                periodic_columns: v.periodic_columns.clone(),
             })
             .collect_vec();
-        let (rap_phase_seq_proof, rap_phase_seq_data) = self
+        // Each phase's after challenge traces are committed together, and the commitment is
+        // observed before the next phase samples its challenges.
+        let mut committed_pcs_data_per_phase: Vec<(Com<SC>, PcsData<SC>)> = vec![];
+        let mut commit_phase =
+            |challenger: &mut SC::Challenger,
+             after_challenge_trace_per_air: &[Option<RowMajorMatrix<SC::Challenge>>]| {
+                let flattened_traces: Vec<_> = after_challenge_trace_per_air
+                    .iter()
+                    .flatten()
+                    .map(|trace| Arc::new(trace.flatten_to_base()))
+                    .collect();
+                // Only commit if there are after challenge traces
+                if !flattened_traces.is_empty() {
+                    let (commit, data) = metrics_span("perm_trace_commit_time_ms", || {
+                        self.commit(&flattened_traces)
+                    });
+                    challenger.observe(commit.clone());
+                    committed_pcs_data_per_phase.push((commit, data));
+                }
+            };
+        let (rap_phase_seq_proof, phases) = self
             .config()
             .rap_phase_seq()
             .partially_prove(
                 challenger,
                 &constraints_per_air.iter().collect_vec(),
                 &rap_pk_per_air,
+                public_lookup_tables,
                 &trace_views,
+                &mut commit_phase,
             )
-            .map_or((None, None), |(p, d)| (Some(p), Some(d)));
-
-        let mvk_view = MultiStarkVerifyingKeyView::new(pk_views.iter().map(|pk| pk.vk).collect());
-
-        let num_airs = pk_views.len();
-        let mut perm_matrix_idx = 0usize;
-        let rap_views_per_phase;
-        let perm_trace_per_air = if let Some(phase_data) = rap_phase_seq_data {
-            assert_eq!(mvk_view.num_phases(), 1);
-            assert_eq!(
-                mvk_view.num_challenges_in_phase(0),
-                phase_data.challenges.len()
-            );
-            let perm_views = zip_eq(
-                &phase_data.after_challenge_trace_per_air,
-                phase_data.exposed_values_per_air,
-            )
-            .map(|(perm_trace, exposed_values)| {
-                let mut matrix_idx = None;
-                if perm_trace.is_some() {
-                    matrix_idx = Some(perm_matrix_idx);
-                    perm_matrix_idx += 1;
-                }
-                RapSinglePhaseView {
-                    inner: matrix_idx,
-                    challenges: phase_data.challenges.clone(),
-                    exposed_values: exposed_values.unwrap_or_default(),
-                }
-            })
-            .collect_vec();
-            rap_views_per_phase = vec![perm_views]; // 1 challenge phase
-            phase_data.after_challenge_trace_per_air
-        } else {
-            assert_eq!(mvk_view.num_phases(), 0);
-            rap_views_per_phase = vec![];
-            vec![None; num_airs]
-        };
+            .map_or((None, vec![]), |(p, d)| (Some(p), d));
 
-        // Commit to permutation traces: this means only 1 challenge round right now
-        // One shared commit for all permutation traces
-        let committed_pcs_data_per_phase: Vec<(Com<SC>, PcsData<SC>)> =
-            metrics_span("perm_trace_commit_time_ms", || {
-                let flattened_traces: Vec<_> = perm_trace_per_air
-                    .into_iter()
-                    .flat_map(|perm_trace| {
-                        perm_trace.map(|trace| Arc::new(trace.flatten_to_base()))
-                    })
-                    .collect();
-                // Only commit if there are permutation traces
-                if !flattened_traces.is_empty() {
-                    let (commit, data) = self.commit(&flattened_traces);
-                    Some((commit, data))
-                } else {
-                    None
-                }
-            })
+        // The commitment rounds and the features are not needed to get the challenge phase
+        // parameters.
+        let mvk_view = MultiStarkVerifyingKeyView::new(
+            pk_views.iter().map(|pk| pk.vk).collect(),
+            &[],
+            MatrixOrdering::Natural,
+            public_lookup_tables,
+            VkFeatureSet::default(),
+        );
+        assert_eq!(mvk_view.num_phases(), phases.len());
+
+        let rap_views_per_phase = phases
             .into_iter()
-            .collect();
+            .enumerate()
+            .map(|(phase_idx, phase_data)| {
+                assert_eq!(
+                    mvk_view.num_challenges_in_phase(phase_idx),
+                    phase_data.challenges.len()
+                );
+                let mut matrix_idx = 0usize;
+                zip_eq(
+                    &phase_data.after_challenge_trace_per_air,
+                    phase_data.exposed_values_per_air,
+                )
+                .map(|(trace, exposed_values)| {
+                    let inner = trace.as_ref().map(|_| {
+                        matrix_idx += 1;
+                        matrix_idx - 1
+                    });
+                    RapSinglePhaseView {
+                        inner,
+                        challenges: phase_data.challenges.clone(),
+                        exposed_values: exposed_values.unwrap_or_default(),
+                    }
+                })
+                .collect_vec()
+            })
+            .collect_vec();
         let prover_view = ProverDataAfterRapPhases {
             committed_pcs_data_per_phase,
             rap_views_per_phase,
@@ -236,112 +337,108 @@ impl<SC: StarkGenericConfig> hal::QuotientCommitter<CpuBackend<SC>> for CpuDevic
         challenger: &mut SC::Challenger,
         pk_views: &[DeviceStarkProvingKey<CpuBackend<SC>>],
         public_values: &[Vec<Val<SC>>],
-        cached_views_per_air: &[Vec<
-            SingleCommitPreimage<&Arc<RowMajorMatrix<Val<SC>>>, &PcsData<SC>>,
-        >],
-        common_main_pcs_data: &PcsData<SC>,
+        cached_views_per_air: &[Vec<SingleCommitPreimage<(), &PcsData<SC>>>],
+        common_main_views_per_air: &[Option<SingleCommitPreimage<(), &PcsData<SC>>>],
+        custom_views_per_air: &[Vec<SingleCommitPreimage<(), &PcsData<SC>>>],
         prover_data_after: &ProverDataAfterRapPhases<CpuBackend<SC>>,
-    ) -> (Com<SC>, PcsData<SC>) {
+        options: &ProvingOptions,
+    ) -> Result<(Com<SC>, PcsData<SC>), ProvingError> {
+        let stopwatch = Stopwatch::start(options);
         let pcs = self.pcs();
         // Generate `alpha` challenge
         let alpha: SC::Challenge = challenger.sample_ext_element();
         tracing::debug!("alpha: {alpha:?}");
-        // Prepare extended views:
-        let mut common_main_idx = 0;
-        let extended_views = izip!(pk_views, cached_views_per_air, public_values)
-            .enumerate()
-            .map(|(i, (pk, cached_views, pvs))| {
+        // Prepare extended views. Each view is consumed by the quotient values of its RAP, so it is
+        // dropped as soon as they are computed:
+        let extended_views = izip!(
+            pk_views,
+            cached_views_per_air,
+            common_main_views_per_air,
+            custom_views_per_air,
+            public_values
+        )
+        .enumerate()
+        .map(
+            |(i, (pk, cached_views, common_main_view, custom_views, pvs))| {
                 let quotient_degree = pk.vk.quotient_degree;
-                let log_trace_height = if pk.vk.has_common_main() {
-                    common_main_pcs_data.log_trace_heights[common_main_idx]
-                } else {
-                    log2_strict_usize(cached_views[0].trace.height()) as u8
-                };
+                let cv = common_main_view.as_ref().unwrap_or(&cached_views[0]);
+                let log_trace_height = cv.data.log_trace_heights[cv.matrix_idx as usize];
                 let trace_domain = pcs.natural_domain_for_degree(1usize << log_trace_height);
                 let quotient_domain = trace_domain
                     .create_disjoint_domain(trace_domain.size() * quotient_degree as usize);
-                // **IMPORTANT**: the return type of `get_evaluations_on_domain` is a matrix view. DO NOT call to_row_major_matrix as this will allocate new memory
-                let preprocessed = pk.preprocessed_data.as_ref().map(|cv| {
-                    pcs.get_evaluations_on_domain(
-                        &cv.data.data,
-                        cv.matrix_idx as usize,
-                        quotient_domain,
-                    )
-                });
-                let mut partitioned_main: Vec<_> = cached_views
+                // **IMPORTANT**: the extended views are matrix views of the LDEs. DO NOT call to_row_major_matrix as this will allocate new memory
+                let extend = |data, idx| {
+                    BitReversed::on_quotient_domain::<SC>(pcs, data, idx, quotient_domain)
+                };
+                let mut builder =
+                    RapViewBuilder::new(pk.vk, log_trace_height).public_values(pvs.to_vec());
+                if let Some(cv) = &pk.preprocessed_data {
+                    builder = builder.preprocessed(extend(&cv.data.data, cv.matrix_idx as usize));
+                }
+                for cv in cached_views
                     .iter()
-                    .map(|cv| {
-                        pcs.get_evaluations_on_domain(
-                            &cv.data.data,
-                            cv.matrix_idx as usize,
-                            quotient_domain,
-                        )
-                    })
-                    .collect();
-                if pk.vk.has_common_main() {
-                    partitioned_main.push(pcs.get_evaluations_on_domain(
-                        &common_main_pcs_data.data,
-                        common_main_idx,
-                        quotient_domain,
-                    ));
-                    common_main_idx += 1;
+                    .chain(common_main_view)
+                    .chain(custom_views)
+                {
+                    builder = builder.main(extend(&cv.data.data, cv.matrix_idx as usize));
                 }
-                let pair = PairView {
-                    log_trace_height,
-                    preprocessed,
-                    partitioned_main,
-                    public_values: pvs.to_vec(),
-                };
-                let mut per_phase = zip(
+                for (phase, ((_, pcs_data), rap_views)) in zip(
                     &prover_data_after.committed_pcs_data_per_phase,
                     &prover_data_after.rap_views_per_phase,
                 )
-                .map(|((_, pcs_data), rap_views)| -> Option<_> {
-                    let rap_view = rap_views.get(i)?;
-                    let matrix_idx = rap_view.inner?;
-                    let extended_matrix =
-                        pcs.get_evaluations_on_domain(&pcs_data.data, matrix_idx, quotient_domain);
-                    Some(RapSinglePhaseView {
-                        inner: Some(extended_matrix),
-                        challenges: rap_view.challenges.clone(),
-                        exposed_values: rap_view.exposed_values.clone(),
-                    })
-                })
-                .collect_vec();
-                while let Some(last) = per_phase.last() {
-                    if last.is_none() {
-                        per_phase.pop();
-                    } else {
-                        break;
-                    }
+                .enumerate()
+                {
+                    let Some(rap_view) = rap_views.get(i) else {
+                        continue;
+                    };
+                    let Some(matrix_idx) = rap_view.inner else {
+                        continue;
+                    };
+                    builder = builder.phase(
+                        phase,
+                        extend(&pcs_data.data, matrix_idx),
+                        rap_view.challenges.clone(),
+                        rap_view.exposed_values.clone(),
+                    );
                 }
-                let per_phase = per_phase
-                    .into_iter()
-                    .map(|v| v.unwrap_or_default())
-                    .collect();
-
-                RapView { pair, per_phase }
-            })
-            .collect_vec();
+                builder.build().unwrap_or_else(|err| panic!("{err}"))
+            },
+        )
+        .collect_vec();
 
-        let (constraints, quotient_degrees): (Vec<_>, Vec<_>) = pk_views
+        let (air_names, constraints, quotient_degrees): (Vec<_>, Vec<_>, Vec<_>) = pk_views
             .iter()
             .map(|pk| {
                 (
+                    pk.air_name,
                     &pk.vk.symbolic_constraints.constraints,
                     pk.vk.quotient_degree,
                 )
             })
-            .unzip();
-        let qc = QuotientCommitter::new(self.pcs(), alpha);
+            .multiunzip();
+        let qc = QuotientCommitter::new(self.pcs(), alpha).with_zero_knowledge(self.zero_knowledge);
         let quotient_values = metrics_span("quotient_poly_compute_time_ms", || {
-            qc.quotient_values(&constraints, extended_views, &quotient_degrees)
-        });
+            qc.quotient_values(
+                &air_names,
+                &constraints,
+                extended_views,
+                &quotient_degrees,
+                options,
+            )
+        })?;
+        let quotient_evaluation = stopwatch.elapsed();
 
         // Commit to quotient polynomials. One shared commit for all quotient polynomials
-        metrics_span("quotient_poly_commit_time_ms", || {
+        let stopwatch = Stopwatch::start(options);
+        let quotient = metrics_span("quotient_poly_commit_time_ms", || {
             qc.commit(quotient_values)
-        })
+        });
+        let quotient_commit = stopwatch.elapsed();
+        options.record_metrics(|metrics| {
+            metrics.quotient_evaluation = quotient_evaluation;
+            metrics.quotient_commit = quotient_commit;
+        });
+        Ok(quotient)
     }
 }
 
@@ -350,12 +447,15 @@ impl<SC: StarkGenericConfig> hal::OpeningProver<CpuBackend<SC>> for CpuDevice<'_
         &self,
         challenger: &mut SC::Challenger,
         // For each preprocessed trace commitment, the prover data and
-        // the log height of the matrix, in order
-        preprocessed: Vec<&PcsData<SC>>,
+        // the extra rotations of the matrix, in order
+        preprocessed: Vec<(&PcsData<SC>, &[usize])>,
         // For each main trace commitment, the prover data and
-        // the log height of each matrix, in order
+        // the extra rotations of each matrix, in order
         // Note: this is all one challenge phase.
-        main: Vec<&PcsData<SC>>,
+        main: Vec<(&PcsData<SC>, Vec<&[usize]>)>,
+        // For each custom round commitment, the prover data and
+        // the extra rotations of each matrix, in order
+        custom: Vec<(&PcsData<SC>, Vec<&[usize]>)>,
         // `after_phase[i]` has shared commitment prover data for all matrices in phase `i + 1`.
         after_phase: Vec<PcsData<SC>>,
         // Quotient poly commitment prover data
@@ -363,6 +463,38 @@ impl<SC: StarkGenericConfig> hal::OpeningProver<CpuBackend<SC>> for CpuDevice<'_
         // Quotient degree for each RAP committed in quotient_data, in order
         quotient_degrees: &[u8],
     ) -> OpeningProof<PcsProof<SC>, SC::Challenge> {
+        self.open_rounds(
+            challenger,
+            preprocessed,
+            main,
+            custom,
+            after_phase,
+            quotient_data,
+            quotient_degrees,
+            false,
+        )
+        .0
+    }
+}
+
+impl<SC: StarkGenericConfig> CpuDevice<'_, SC> {
+    /// Same as [open](hal::OpeningProver::open), but also returns the [OpeningWitness] if
+    /// `with_witness` is set.
+    #[allow(clippy::too_many_arguments)]
+    fn open_rounds(
+        &self,
+        challenger: &mut SC::Challenger,
+        preprocessed: Vec<(&PcsData<SC>, &[usize])>,
+        main: Vec<(&PcsData<SC>, Vec<&[usize]>)>,
+        custom: Vec<(&PcsData<SC>, Vec<&[usize]>)>,
+        after_phase: Vec<PcsData<SC>>,
+        quotient_data: PcsData<SC>,
+        quotient_degrees: &[u8],
+        with_witness: bool,
+    ) -> (
+        OpeningProof<PcsProof<SC>, SC::Challenge>,
+        Option<OpeningWitness<SC::Challenge>>,
+    ) {
         // Draw `zeta` challenge
         let zeta: SC::Challenge = challenger.sample_ext_element();
         tracing::debug!("zeta: {zeta:?}");
@@ -372,18 +504,22 @@ impl<SC: StarkGenericConfig> hal::OpeningProver<CpuBackend<SC>> for CpuDevice<'_
         let opener = OpeningProver::<SC>::new(pcs, zeta);
         let preprocessed = preprocessed
             .iter()
-            .map(|v| {
+            .map(|&(v, rotations)| {
                 assert_eq!(v.log_trace_heights.len(), 1);
-                (v.data.as_ref(), domain(v.log_trace_heights[0]))
-            })
-            .collect();
-        let main = main
-            .iter()
-            .map(|v| {
-                let domains = v.log_trace_heights.iter().copied().map(domain).collect();
-                (v.data.as_ref(), domains)
+                (v.data.as_ref(), domain(v.log_trace_heights[0]), rotations)
             })
             .collect();
+        let [main, custom] = [main, custom].map(|rounds| {
+            rounds
+                .into_iter()
+                .map(|(v, rotations)| {
+                    let domains =
+                        zip_eq(v.log_trace_heights.iter().copied().map(domain), rotations)
+                            .collect();
+                    (v.data.as_ref(), domains)
+                })
+                .collect_vec()
+        });
         let after_phase: Vec<_> = after_phase
             .iter()
             .map(|v| {
@@ -391,17 +527,116 @@ impl<SC: StarkGenericConfig> hal::OpeningProver<CpuBackend<SC>> for CpuDevice<'_
                 (v.data.as_ref(), domains)
             })
             .collect();
-        opener.open(
+        // In zero-knowledge mode, the random mask of each quotient is committed after its chunks.
+        let num_quotient_matrices = quotient_degrees
+            .iter()
+            .map(|&degree| degree + self.zero_knowledge.is_some() as u8)
+            .collect_vec();
+        opener.open_rounds(
             challenger,
             preprocessed,
             main,
+            custom,
             after_phase,
             &quotient_data.data,
-            quotient_degrees,
+            &num_quotient_matrices,
+            with_witness,
         )
     }
 }
 
+/// A [CpuDevice] that also records the points and values of the PCS opening of each proof, see
+/// [prove_with_opening_witness](crate::engine::StarkEngine::prove_with_opening_witness).
+pub(crate) struct OpeningWitnessDevice<'a, SC: StarkGenericConfig> {
+    pub device: CpuDevice<'a, SC>,
+    pub witness: &'a Mutex<Option<OpeningWitness<SC::Challenge>>>,
+}
+
+impl<SC: StarkGenericConfig> ProverDevice<CpuBackend<SC>> for OpeningWitnessDevice<'_, SC> {}
+
+impl<SC: StarkGenericConfig> TraceCommitter<CpuBackend<SC>> for OpeningWitnessDevice<'_, SC> {
+    fn commit(&self, traces: &[Arc<RowMajorMatrix<Val<SC>>>]) -> (Com<SC>, PcsData<SC>) {
+        self.device.commit(traces)
+    }
+}
+
+impl<SC: StarkGenericConfig> hal::RapPartialProver<CpuBackend<SC>>
+    for OpeningWitnessDevice<'_, SC>
+{
+    fn partially_prove<'a>(
+        &self,
+        challenger: &mut SC::Challenger,
+        pk_views: &[DeviceStarkProvingKey<'a, CpuBackend<SC>>],
+        public_lookup_tables: &[PublicLookupTable<Val<SC>>],
+        trace_views: Vec<PairView<&'a Arc<RowMajorMatrix<Val<SC>>>, Val<SC>>>,
+    ) -> (
+        Option<RapPhaseSeqPartialProof<SC>>,
+        ProverDataAfterRapPhases<CpuBackend<SC>>,
+    ) {
+        hal::RapPartialProver::partially_prove(
+            &self.device,
+            challenger,
+            pk_views,
+            public_lookup_tables,
+            trace_views,
+        )
+    }
+}
+
+impl<SC: StarkGenericConfig> hal::QuotientCommitter<CpuBackend<SC>>
+    for OpeningWitnessDevice<'_, SC>
+{
+    fn eval_and_commit_quotient(
+        &self,
+        challenger: &mut SC::Challenger,
+        pk_views: &[DeviceStarkProvingKey<CpuBackend<SC>>],
+        public_values: &[Vec<Val<SC>>],
+        cached_views_per_air: &[Vec<SingleCommitPreimage<(), &PcsData<SC>>>],
+        common_main_views_per_air: &[Option<SingleCommitPreimage<(), &PcsData<SC>>>],
+        custom_views_per_air: &[Vec<SingleCommitPreimage<(), &PcsData<SC>>>],
+        prover_data_after: &ProverDataAfterRapPhases<CpuBackend<SC>>,
+        options: &ProvingOptions,
+    ) -> Result<(Com<SC>, PcsData<SC>), ProvingError> {
+        hal::QuotientCommitter::eval_and_commit_quotient(
+            &self.device,
+            challenger,
+            pk_views,
+            public_values,
+            cached_views_per_air,
+            common_main_views_per_air,
+            custom_views_per_air,
+            prover_data_after,
+            options,
+        )
+    }
+}
+
+impl<SC: StarkGenericConfig> hal::OpeningProver<CpuBackend<SC>> for OpeningWitnessDevice<'_, SC> {
+    fn open(
+        &self,
+        challenger: &mut SC::Challenger,
+        preprocessed: Vec<(&PcsData<SC>, &[usize])>,
+        main: Vec<(&PcsData<SC>, Vec<&[usize]>)>,
+        custom: Vec<(&PcsData<SC>, Vec<&[usize]>)>,
+        after_phase: Vec<PcsData<SC>>,
+        quotient_data: PcsData<SC>,
+        quotient_degrees: &[u8],
+    ) -> OpeningProof<PcsProof<SC>, SC::Challenge> {
+        let (opening, witness) = self.device.open_rounds(
+            challenger,
+            preprocessed,
+            main,
+            custom,
+            after_phase,
+            quotient_data,
+            quotient_degrees,
+            true,
+        );
+        *self.witness.lock().unwrap() = witness;
+        opening
+    }
+}
+
 impl<SC> DeviceDataTransporter<SC, CpuBackend<SC>> for CpuBackend<SC>
 where
     SC: StarkGenericConfig,
@@ -441,7 +676,14 @@ where
                 }
             })
             .collect();
-        DeviceMultiStarkProvingKey::new(air_ids, per_air)
+        DeviceMultiStarkProvingKey::new(
+            air_ids,
+            per_air,
+            &mpk.commitment_rounds,
+            mpk.matrix_ordering,
+            &mpk.public_lookup_tables,
+            mpk.features,
+        )
     }
     fn transport_matrix_to_device(
         &self,
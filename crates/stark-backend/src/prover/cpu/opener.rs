@@ -1,12 +1,14 @@
-use std::fmt::Debug;
+use std::{fmt::Debug, iter};
 
-use itertools::Itertools;
+use itertools::{zip_eq, Itertools};
 use p3_commit::{Pcs, PolynomialSpace};
+use serde::{Deserialize, Serialize};
 use tracing::instrument;
 
 use crate::{
     config::{Domain, PcsProof, PcsProverData, StarkGenericConfig},
     proof::{AdjacentOpenedValues, OpenedValues, OpeningProof},
+    utils::rotate_point,
 };
 
 pub struct OpeningProver<'pcs, SC: StarkGenericConfig> {
@@ -22,39 +24,97 @@ impl<'pcs, SC: StarkGenericConfig> OpeningProver<'pcs, SC> {
     /// Opening proof for multiple RAP matrices, where
     /// - (for now) each preprocessed trace matrix has a separate commitment
     /// - main trace matrices can have multiple commitments
+    /// - each custom round has a single commitment
     /// - for each after_challenge phase, all matrices in the phase share a commitment
     /// - quotient poly chunks are all committed together
-    #[instrument(name = "PCS opening proofs", skip_all)]
+    ///
+    /// Each preprocessed, main and custom round matrix is opened at the local and next rows, as
+    /// well as at the extra rotations of its AIR.
     pub fn open(
         &self,
         challenger: &mut SC::Challenger,
-        // For each preprocessed trace commitment, the prover data and
-        // the domain of the matrix, in order
-        preprocessed: Vec<(&PcsProverData<SC>, Domain<SC>)>,
+        preprocessed: Vec<(&PcsProverData<SC>, Domain<SC>, &[usize])>,
+        main: Vec<(&PcsProverData<SC>, Vec<(Domain<SC>, &[usize])>)>,
+        custom: Vec<(&PcsProverData<SC>, Vec<(Domain<SC>, &[usize])>)>,
+        after_challenge: Vec<(&PcsProverData<SC>, Vec<Domain<SC>>)>,
+        quotient_data: &PcsProverData<SC>,
+        quotient_degrees: &[u8],
+    ) -> OpeningProof<PcsProof<SC>, SC::Challenge> {
+        self.open_rounds(
+            challenger,
+            preprocessed,
+            main,
+            custom,
+            after_challenge,
+            quotient_data,
+            quotient_degrees,
+            false,
+        )
+        .0
+    }
+
+    /// Same as [open](Self::open), but also returns the points each matrix is opened at and the
+    /// opened values if `with_witness` is set, see [OpeningWitness]. The witness copies the
+    /// points and the values, so it is only built when requested.
+    #[allow(clippy::too_many_arguments)]
+    #[instrument(name = "PCS opening proofs", skip_all)]
+    pub(crate) fn open_rounds(
+        &self,
+        challenger: &mut SC::Challenger,
+        // For each preprocessed trace commitment, the prover data, the domain of the matrix,
+        // and the extra rotations of the matrix, in order
+        preprocessed: Vec<(&PcsProverData<SC>, Domain<SC>, &[usize])>,
         // For each main trace commitment, the prover data and
-        // the domain of each matrix, in order
-        main: Vec<(&PcsProverData<SC>, Vec<Domain<SC>>)>,
+        // the domain and extra rotations of each matrix, in order
+        main: Vec<(&PcsProverData<SC>, Vec<(Domain<SC>, &[usize])>)>,
+        // For each custom round commitment, the prover data and
+        // the domain and extra rotations of each matrix, in order
+        custom: Vec<(&PcsProverData<SC>, Vec<(Domain<SC>, &[usize])>)>,
         // after_challenge[i] has shared commitment prover data for all matrices in that phase, and domains of those matrices, in order
         after_challenge: Vec<(&PcsProverData<SC>, Vec<Domain<SC>>)>,
         // Quotient poly commitment prover data
         quotient_data: &PcsProverData<SC>,
         // Quotient degree for each RAP committed in quotient_data, in order
         quotient_degrees: &[u8],
-    ) -> OpeningProof<PcsProof<SC>, SC::Challenge> {
+        with_witness: bool,
+    ) -> (
+        OpeningProof<PcsProof<SC>, SC::Challenge>,
+        Option<OpeningWitness<SC::Challenge>>,
+    ) {
         let preprocessed: Vec<_> = preprocessed
             .into_iter()
-            .map(|(data, domain)| (data, vec![domain]))
+            .map(|(data, domain, rotations)| (data, vec![(domain, rotations)]))
+            .collect();
+        let after_challenge: Vec<_> = after_challenge
+            .into_iter()
+            .map(|(data, domains)| {
+                let domains = domains
+                    .into_iter()
+                    .map(|domain| (domain, &[] as &[usize]))
+                    .collect_vec();
+                (data, domains)
+            })
             .collect();
 
         let zeta = self.zeta;
         let mut rounds = preprocessed
             .iter()
             .chain(main.iter())
+            .chain(custom.iter())
             .chain(after_challenge.iter())
             .map(|(data, domains)| {
                 let points_per_mat = domains
                     .iter()
-                    .map(|domain| vec![zeta, domain.next_point(zeta).unwrap()])
+                    .map(|(domain, rotations)| {
+                        [zeta, domain.next_point(zeta).unwrap()]
+                            .into_iter()
+                            .chain(
+                                rotations
+                                    .iter()
+                                    .map(|&rotation| rotate_point(domain, zeta, rotation)),
+                            )
+                            .collect_vec()
+                    })
                     .collect_vec();
                 (*data, points_per_mat)
             })
@@ -65,8 +125,37 @@ impl<'pcs, SC: StarkGenericConfig> OpeningProver<'pcs, SC> {
         let quotient_opening_points = vec![vec![zeta]; num_chunks];
         rounds.push((quotient_data, quotient_opening_points));
 
+        // The kind and the points of each round, in the order of `rounds`
+        let witness_rounds = with_witness.then(|| {
+            let kinds = [
+                (OpeningRoundKind::Preprocessed, preprocessed.len()),
+                (OpeningRoundKind::Main, main.len()),
+                (OpeningRoundKind::Custom, custom.len()),
+                (OpeningRoundKind::AfterChallenge, after_challenge.len()),
+                (OpeningRoundKind::Quotient, 1),
+            ]
+            .into_iter()
+            .flat_map(|(kind, num_rounds)| iter::repeat_n(kind, num_rounds));
+            let points_per_round = rounds
+                .iter()
+                .map(|(_, points_per_mat)| points_per_mat.clone());
+            zip_eq(kinds, points_per_round).collect_vec()
+        });
+
         let (mut opening_values, opening_proof) = self.pcs.open(rounds, challenger);
 
+        let witness = witness_rounds.map(|witness_rounds| OpeningWitness {
+            zeta,
+            rounds: zip_eq(witness_rounds, opening_values.clone())
+                .map(|((kind, points_per_mat), values_per_mat)| OpenedRound {
+                    kind,
+                    matrices: zip_eq(points_per_mat, values_per_mat)
+                        .map(|(points, values)| OpenedMatrix { points, values })
+                        .collect(),
+                })
+                .collect(),
+        });
+
         // Unflatten opening_values
         let mut quotient_openings = opening_values.pop().expect("Should have quotient opening");
 
@@ -82,6 +171,17 @@ impl<'pcs, SC: StarkGenericConfig> OpeningProver<'pcs, SC> {
             "Incorrect number of after challenge trace openings"
         );
 
+        let custom_openings = opening_values
+            .split_off(preprocessed.len() + main.len())
+            .into_iter()
+            .map(collect_trace_openings)
+            .collect_vec();
+        assert_eq!(
+            custom_openings.len(),
+            custom.len(),
+            "Incorrect number of custom round openings"
+        );
+
         let main_openings = opening_values
             .split_off(preprocessed.len())
             .into_iter()
@@ -122,25 +222,79 @@ impl<'pcs, SC: StarkGenericConfig> OpeningProver<'pcs, SC> {
             })
             .collect_vec();
 
-        OpeningProof {
+        let opening = OpeningProof {
             proof: opening_proof,
             values: OpenedValues {
                 preprocessed: preprocessed_openings,
                 main: main_openings,
                 after_challenge: after_challenge_openings,
                 quotient: quotient_openings,
+                custom: custom_openings,
             },
-        }
+        };
+        (opening, witness)
     }
 }
 
+/// The commitment opened by an [OpenedRound].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OpeningRoundKind {
+    /// The preprocessed trace of an AIR.
+    Preprocessed,
+    /// A cached main trace, or the common main traces of all AIRs.
+    Main,
+    /// The traces of all AIRs in a custom round.
+    Custom,
+    /// The after challenge traces of all AIRs in a challenge phase.
+    AfterChallenge,
+    /// The quotient polynomial chunks of all AIRs.
+    Quotient,
+}
+
+/// The points a committed matrix is opened at, and the claimed evaluations of its columns.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OpenedMatrix<Challenge> {
+    /// `zeta`, then the point of the next row and the points of the extra rotations of the AIR for
+    /// a trace matrix. Only `zeta` for a quotient chunk.
+    pub points: Vec<Challenge>,
+    /// `values[i][j]` is the evaluation of column `j` at `points[i]`.
+    pub values: Vec<Vec<Challenge>>,
+}
+
+/// The opening of the matrices of a single commitment, in the order they are committed.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OpenedRound<Challenge> {
+    pub kind: OpeningRoundKind,
+    pub matrices: Vec<OpenedMatrix<Challenge>>,
+}
+
+/// The points and opened values of every commitment of a proof, as inputs of a recursive
+/// verifier. The rounds are in the order the verifier checks them against the PCS opening proof:
+/// the preprocessed commitments, the main commitments with the common main last, the custom
+/// rounds, the challenge phases and the quotient.
+///
+/// The values are the ones opened by the PCS, which are also in
+/// [OpenedValues](crate::proof::OpenedValues) in a different layout.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OpeningWitness<Challenge> {
+    /// The out-of-domain point sampled from the transcript.
+    pub zeta: Challenge,
+    pub rounds: Vec<OpenedRound<Challenge>>,
+}
+
 fn collect_trace_openings<Challenge: Debug>(
     ops: Vec<Vec<Vec<Challenge>>>,
 ) -> Vec<AdjacentOpenedValues<Challenge>> {
     ops.into_iter()
         .map(|op| {
-            let [local, next] = op.try_into().expect("Should have 2 openings");
-            AdjacentOpenedValues { local, next }
+            let mut op = op.into_iter();
+            let local = op.next().expect("Should have local opening");
+            let next = op.next().expect("Should have next opening");
+            AdjacentOpenedValues {
+                local,
+                next,
+                rotated: op.collect(),
+            }
         })
         .collect()
 }
@@ -1,7 +1,6 @@
 use std::ops::{Add, Mul, Neg, Sub};
 
-use derivative::Derivative;
-use p3_field::FieldAlgebra;
+use p3_field::{FieldExtensionAlgebra, PackedField};
 
 use crate::{
     air_builders::symbolic::{
@@ -12,54 +11,59 @@ use crate::{
     config::{PackedChallenge, PackedVal, StarkGenericConfig, Val},
 };
 
-pub(super) struct ViewPair<T> {
-    local: Vec<T>,
-    next: Option<Vec<T>>,
+/// Rows of a matrix at each rotation from the current row. Rows at rotations
+/// not referenced by any constraint are `None`.
+pub(super) struct RowWindow<T> {
+    rows: Vec<Option<Vec<T>>>,
 }
 
-impl<T> ViewPair<T> {
-    pub fn new(local: Vec<T>, next: Option<Vec<T>>) -> Self {
-        Self { local, next }
+impl<T> RowWindow<T> {
+    pub fn new(rows: Vec<Option<Vec<T>>>) -> Self {
+        Self { rows }
     }
 
     /// SAFETY: no matrix bounds checks are done.
     pub unsafe fn get(&self, row_offset: usize, column_idx: usize) -> &T {
-        match row_offset {
-            0 => self.local.get_unchecked(column_idx),
-            1 => self
-                .next
-                .as_ref()
-                .unwrap_unchecked()
-                .get_unchecked(column_idx),
-            _ => panic!("row offset {row_offset} not supported"),
-        }
+        self.rows
+            .get_unchecked(row_offset)
+            .as_ref()
+            .unwrap_unchecked()
+            .get_unchecked(column_idx)
     }
 }
 
-/// A struct for quotient polynomial evaluation. This evaluates `WIDTH` rows of the quotient polynomial
-/// simultaneously using SIMD (if target arch allows it) via `PackedVal` and `PackedChallenge` types.
-pub(super) struct ProverConstraintEvaluator<'a, SC: StarkGenericConfig> {
-    pub preprocessed: ViewPair<PackedVal<SC>>,
-    pub partitioned_main: Vec<ViewPair<PackedVal<SC>>>,
-    pub after_challenge: Vec<ViewPair<PackedChallenge<SC>>>,
-    pub challenges: &'a [Vec<PackedChallenge<SC>>],
-    pub is_first_row: PackedVal<SC>,
-    pub is_last_row: PackedVal<SC>,
-    pub is_transition: PackedVal<SC>,
+/// A struct for quotient polynomial evaluation. This evaluates `P::WIDTH` rows of the quotient
+/// polynomial simultaneously using SIMD (if target arch allows it) via `PackedVal` and
+/// `PackedChallenge` types. With `P = Val<SC>` and `PE = SC::Challenge`, it evaluates a single
+/// row.
+pub(super) struct ProverConstraintEvaluator<
+    'a,
+    SC: StarkGenericConfig,
+    P = PackedVal<SC>,
+    PE = PackedChallenge<SC>,
+> {
+    pub preprocessed: RowWindow<P>,
+    pub partitioned_main: Vec<RowWindow<P>>,
+    pub after_challenge: Vec<RowWindow<PE>>,
+    pub challenges: &'a [Vec<PE>],
+    pub is_first_row: P,
+    pub is_last_row: P,
+    pub is_transition: P,
     pub public_values: &'a [Val<SC>],
-    pub exposed_values_after_challenge: &'a [Vec<PackedChallenge<SC>>],
+    /// Values of the periodic columns
+    pub periodic_values: Vec<P>,
+    pub exposed_values_after_challenge: &'a [Vec<PE>],
 }
 
 /// In order to avoid extension field arithmetic as much as possible, we evaluate into
 /// the smallest packed expression possible.
-#[derive(Derivative, Copy)]
-#[derivative(Clone(bound = ""))]
-enum PackedExpr<SC: StarkGenericConfig> {
-    Val(PackedVal<SC>),
-    Challenge(PackedChallenge<SC>),
+#[derive(Clone, Copy)]
+enum PackedExpr<P, PE> {
+    Val(P),
+    Challenge(PE),
 }
 
-impl<SC: StarkGenericConfig> Add for PackedExpr<SC> {
+impl<P: PackedField, PE: FieldExtensionAlgebra<P>> Add for PackedExpr<P, PE> {
     type Output = Self;
 
     fn add(self, other: Self) -> Self {
@@ -72,14 +76,14 @@ impl<SC: StarkGenericConfig> Add for PackedExpr<SC> {
     }
 }
 
-impl<SC: StarkGenericConfig> Sub for PackedExpr<SC> {
+impl<P: PackedField, PE: FieldExtensionAlgebra<P>> Sub for PackedExpr<P, PE> {
     type Output = Self;
 
     fn sub(self, other: Self) -> Self {
         match (self, other) {
             (PackedExpr::Val(x), PackedExpr::Val(y)) => PackedExpr::Val(x - y),
             (PackedExpr::Val(x), PackedExpr::Challenge(y)) => {
-                let x: PackedChallenge<SC> = x.into();
+                let x: PE = x.into();
                 // We could alternative do (-y) + x
                 PackedExpr::Challenge(x - y)
             }
@@ -89,7 +93,7 @@ impl<SC: StarkGenericConfig> Sub for PackedExpr<SC> {
     }
 }
 
-impl<SC: StarkGenericConfig> Mul for PackedExpr<SC> {
+impl<P: PackedField, PE: FieldExtensionAlgebra<P>> Mul for PackedExpr<P, PE> {
     type Output = Self;
 
     fn mul(self, other: Self) -> Self {
@@ -102,7 +106,7 @@ impl<SC: StarkGenericConfig> Mul for PackedExpr<SC> {
     }
 }
 
-impl<SC: StarkGenericConfig> Neg for PackedExpr<SC> {
+impl<P: PackedField, PE: FieldExtensionAlgebra<P>> Neg for PackedExpr<P, PE> {
     type Output = Self;
 
     fn neg(self) -> Self {
@@ -113,27 +117,30 @@ impl<SC: StarkGenericConfig> Neg for PackedExpr<SC> {
     }
 }
 
-impl<SC> SymbolicEvaluator<Val<SC>, PackedExpr<SC>> for ProverConstraintEvaluator<'_, SC>
+impl<SC, P, PE> SymbolicEvaluator<Val<SC>, PackedExpr<P, PE>>
+    for ProverConstraintEvaluator<'_, SC, P, PE>
 where
     SC: StarkGenericConfig,
+    P: PackedField<Scalar = Val<SC>>,
+    PE: FieldExtensionAlgebra<P> + Copy,
 {
-    fn eval_const(&self, c: Val<SC>) -> PackedExpr<SC> {
+    fn eval_const(&self, c: Val<SC>) -> PackedExpr<P, PE> {
         PackedExpr::Val(c.into())
     }
-    fn eval_is_first_row(&self) -> PackedExpr<SC> {
+    fn eval_is_first_row(&self) -> PackedExpr<P, PE> {
         PackedExpr::Val(self.is_first_row)
     }
-    fn eval_is_last_row(&self) -> PackedExpr<SC> {
+    fn eval_is_last_row(&self) -> PackedExpr<P, PE> {
         PackedExpr::Val(self.is_last_row)
     }
-    fn eval_is_transition(&self) -> PackedExpr<SC> {
+    fn eval_is_transition(&self) -> PackedExpr<P, PE> {
         PackedExpr::Val(self.is_transition)
     }
 
     /// SAFETY: we only use this trait implementation when we have already done
     /// a previous scan to ensure all matrix bounds are satisfied,
     /// so no bounds checks are done here.
-    fn eval_var(&self, symbolic_var: SymbolicVariable<Val<SC>>) -> PackedExpr<SC> {
+    fn eval_var(&self, symbolic_var: SymbolicVariable<Val<SC>>) -> PackedExpr<P, PE> {
         let index = symbolic_var.index;
         match symbolic_var.entry {
             Entry::Preprocessed { offset } => unsafe {
@@ -145,18 +152,21 @@ where
             Entry::Public => unsafe {
                 PackedExpr::Val((*self.public_values.get_unchecked(index)).into())
             },
-            Entry::Permutation { offset } => unsafe {
-                let perm = self.after_challenge.get_unchecked(0);
+            Entry::Periodic => unsafe {
+                PackedExpr::Val(*self.periodic_values.get_unchecked(index))
+            },
+            Entry::Permutation { phase, offset } => unsafe {
+                let perm = self.after_challenge.get_unchecked(phase);
                 PackedExpr::Challenge(*perm.get(offset, index))
             },
-            Entry::Challenge => unsafe {
-                PackedExpr::Challenge(*self.challenges.get_unchecked(0).get_unchecked(index))
+            Entry::Challenge { phase } => unsafe {
+                PackedExpr::Challenge(*self.challenges.get_unchecked(phase).get_unchecked(index))
             },
-            Entry::Exposed => unsafe {
+            Entry::Exposed { phase } => unsafe {
                 PackedExpr::Challenge(
                     *self
                         .exposed_values_after_challenge
-                        .get_unchecked(0)
+                        .get_unchecked(phase)
                         .get_unchecked(index),
                 )
             },
@@ -164,16 +174,21 @@ where
     }
 }
 
-impl<SC: StarkGenericConfig> ProverConstraintEvaluator<'_, SC> {
+impl<SC, P, PE> ProverConstraintEvaluator<'_, SC, P, PE>
+where
+    SC: StarkGenericConfig,
+    P: PackedField<Scalar = Val<SC>>,
+    PE: FieldExtensionAlgebra<P> + Copy,
+{
     /// `alpha_powers` are in **reversed** order, with highest power coming first.
     // Note: this could be split into multiple functions if additional constraints need to be folded in
     pub fn accumulate(
         &self,
         constraints: &SymbolicExpressionDag<Val<SC>>,
-        alpha_powers: &[PackedChallenge<SC>],
-    ) -> PackedChallenge<SC> {
+        alpha_powers: &[PE],
+    ) -> PE {
         let evaluated_nodes = self.eval_nodes(&constraints.nodes);
-        let mut accumulator = PackedChallenge::<SC>::ZERO;
+        let mut accumulator = PE::ZERO;
         for (&alpha_pow, &node_idx) in alpha_powers.iter().zip(&constraints.constraint_idx) {
             match evaluated_nodes[node_idx] {
                 PackedExpr::Val(x) => accumulator += alpha_pow * x,
@@ -183,3 +198,361 @@ impl<SC: StarkGenericConfig> ProverConstraintEvaluator<'_, SC> {
         accumulator
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+    use openvm_stark_sdk::utils::create_seeded_rng;
+    use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
+    use p3_challenger::DuplexChallenger;
+    use p3_commit::{ExtensionMmcs, PolynomialSpace, TwoAdicMultiplicativeCoset};
+    use p3_dft::Radix2DitParallel;
+    use p3_field::{
+        extension::BinomialExtensionField, Field, FieldAlgebra, FieldExtensionAlgebra, PackedValue,
+    };
+    use p3_fri::TwoAdicFriPcs;
+    use p3_matrix::{
+        dense::{RowMajorMatrix, RowMajorMatrixView},
+        Matrix,
+    };
+    use p3_merkle_tree::MerkleTreeMmcs;
+    use p3_symmetric::{PaddingFreeSponge, TruncatedPermutation};
+    use rand::{rngs::StdRng, Rng};
+
+    use super::*;
+    use crate::{
+        air_builders::symbolic::{
+            interpreter::{eval_symbolic_constraints, SymbolicRowValues},
+            symbolic_expression::SymbolicExpression,
+            SymbolicConstraints, SymbolicConstraintsDag,
+        },
+        config::StarkConfig,
+        interaction::fri_log_up::FriLogUpPhase,
+        prover::cpu::quotient::single::compute_single_rap_quotient_values,
+    };
+
+    type F = BabyBear;
+    type EF = BinomialExtensionField<F, 4>;
+    type Perm = Poseidon2BabyBear<16>;
+    type Hash = PaddingFreeSponge<Perm, 16, 8, 8>;
+    type Compress = TruncatedPermutation<Perm, 2, 8, 16>;
+    type ValMmcs = MerkleTreeMmcs<<F as Field>::Packing, <F as Field>::Packing, Hash, Compress, 8>;
+    type ChallengeMmcs = ExtensionMmcs<F, EF, ValMmcs>;
+    type Challenger = DuplexChallenger<F, Perm, 16, 8>;
+    type Pcs = TwoAdicFriPcs<F, Radix2DitParallel<F>, ValMmcs, ChallengeMmcs>;
+    type SC = StarkConfig<Pcs, FriLogUpPhase<F, EF, Challenger>, EF, Challenger>;
+
+    const MAX_ROTATION: usize = 2;
+    const PREPROCESSED_WIDTH: usize = 2;
+    const MAIN_WIDTHS: [usize; 2] = [3, 4];
+    const PERM_WIDTH: usize = 2;
+    const NUM_PUBLIC_VALUES: usize = 3;
+    const NUM_CHALLENGES: usize = 2;
+    const NUM_EXPOSED_VALUES: usize = 1;
+    const NUM_PERIODIC_COLUMNS: usize = 2;
+
+    fn random_leaf(rng: &mut StdRng) -> SymbolicExpression<F> {
+        let var = |entry: Entry, index: usize| -> SymbolicExpression<F> {
+            SymbolicVariable::new(entry, index).into()
+        };
+        match rng.gen_range(0..10) {
+            0 | 1 => {
+                let part_index = rng.gen_range(0..MAIN_WIDTHS.len());
+                let entry = Entry::Main {
+                    part_index,
+                    offset: rng.gen_range(0..=MAX_ROTATION),
+                };
+                var(entry, rng.gen_range(0..MAIN_WIDTHS[part_index]))
+            }
+            2 => {
+                let entry = Entry::Preprocessed {
+                    offset: rng.gen_range(0..=MAX_ROTATION),
+                };
+                var(entry, rng.gen_range(0..PREPROCESSED_WIDTH))
+            }
+            3 => var(Entry::Public, rng.gen_range(0..NUM_PUBLIC_VALUES)),
+            4 => {
+                let entry = Entry::Permutation {
+                    phase: 0,
+                    offset: rng.gen_range(0..=1),
+                };
+                var(entry, rng.gen_range(0..PERM_WIDTH))
+            }
+            5 => var(
+                Entry::Challenge { phase: 0 },
+                rng.gen_range(0..NUM_CHALLENGES),
+            ),
+            6 => var(
+                Entry::Exposed { phase: 0 },
+                rng.gen_range(0..NUM_EXPOSED_VALUES),
+            ),
+            7 => [
+                SymbolicExpression::IsFirstRow,
+                SymbolicExpression::IsLastRow,
+                SymbolicExpression::IsTransition,
+            ][rng.gen_range(0..3)]
+            .clone(),
+            8 => var(Entry::Periodic, rng.gen_range(0..NUM_PERIODIC_COLUMNS)),
+            _ => SymbolicExpression::Constant(rng.gen()),
+        }
+    }
+
+    fn random_expr(rng: &mut StdRng, depth: usize) -> SymbolicExpression<F> {
+        if depth == 0 || rng.gen_bool(0.2) {
+            return random_leaf(rng);
+        }
+        match rng.gen_range(0..4) {
+            0 => random_expr(rng, depth - 1) + random_expr(rng, depth - 1),
+            1 => random_expr(rng, depth - 1) - random_expr(rng, depth - 1),
+            2 => random_expr(rng, depth - 1) * random_expr(rng, depth - 1),
+            _ => -random_expr(rng, depth - 1),
+        }
+    }
+
+    /// Random packed rows of a window, each lane holding an independent row.
+    fn random_window<T: Copy>(
+        rng: &mut StdRng,
+        height: usize,
+        width: usize,
+        mut sample: impl FnMut(&mut StdRng) -> T,
+    ) -> Vec<Vec<T>> {
+        (0..height)
+            .map(|_| (0..width).map(|_| sample(rng)).collect())
+            .collect()
+    }
+
+    fn random_packed_val(rng: &mut StdRng) -> PackedVal<SC> {
+        PackedVal::<SC>::from_fn(|_| rng.gen())
+    }
+
+    fn random_packed_challenge(rng: &mut StdRng) -> PackedChallenge<SC> {
+        PackedChallenge::<SC>::from_base_fn(|_| random_packed_val(rng))
+    }
+
+    fn val_lane(x: &PackedVal<SC>, lane: usize) -> F {
+        x.as_slice()[lane]
+    }
+
+    fn challenge_lane(x: &PackedChallenge<SC>, lane: usize) -> EF {
+        EF::from_base_fn(|i| val_lane(&x.as_base_slice()[i], lane))
+    }
+
+    /// Flattens the values of `lane` in a packed window into a row-major matrix.
+    fn window_lane<P, T>(window: &[Vec<P>], lane: usize, f: impl Fn(&P, usize) -> T) -> Vec<T> {
+        window
+            .iter()
+            .flat_map(|row| row.iter().map(|x| f(x, lane)).collect_vec())
+            .collect()
+    }
+
+    /// Compares the packed quotient evaluator against the reference interpreter on random
+    /// constraints and random rows.
+    #[test]
+    fn test_evaluator_matches_interpreter() {
+        let mut rng = create_seeded_rng();
+        for _ in 0..10 {
+            let constraints = (0..20).map(|_| random_expr(&mut rng, 6)).collect_vec();
+            let dag = SymbolicConstraintsDag::from(SymbolicConstraints {
+                constraints,
+                interactions: vec![],
+            })
+            .constraints;
+
+            let window_height = MAX_ROTATION + 1;
+            let preprocessed = random_window(
+                &mut rng,
+                window_height,
+                PREPROCESSED_WIDTH,
+                random_packed_val,
+            );
+            let partitioned_main = MAIN_WIDTHS
+                .iter()
+                .map(|&width| random_window(&mut rng, window_height, width, random_packed_val))
+                .collect_vec();
+            let perm = random_window(&mut rng, 2, PERM_WIDTH, random_packed_challenge);
+            let challenges = vec![(0..NUM_CHALLENGES)
+                .map(|_| random_packed_challenge(&mut rng))
+                .collect_vec()];
+            let exposed_values = vec![(0..NUM_EXPOSED_VALUES)
+                .map(|_| random_packed_challenge(&mut rng))
+                .collect_vec()];
+            let public_values: Vec<F> = (0..NUM_PUBLIC_VALUES).map(|_| rng.gen()).collect();
+            let periodic_values = (0..NUM_PERIODIC_COLUMNS)
+                .map(|_| random_packed_val(&mut rng))
+                .collect_vec();
+            let [is_first_row, is_last_row, is_transition] =
+                [(); 3].map(|_| random_packed_val(&mut rng));
+
+            let to_row_window =
+                |window: &Vec<Vec<_>>| RowWindow::new(window.iter().cloned().map(Some).collect());
+            let evaluator = ProverConstraintEvaluator::<SC> {
+                preprocessed: to_row_window(&preprocessed),
+                partitioned_main: partitioned_main.iter().map(to_row_window).collect(),
+                after_challenge: vec![RowWindow::new(perm.iter().cloned().map(Some).collect())],
+                challenges: &challenges,
+                is_first_row,
+                is_last_row,
+                is_transition,
+                public_values: &public_values,
+                periodic_values: periodic_values.clone(),
+                exposed_values_after_challenge: &exposed_values,
+            };
+            let evaluated_nodes = evaluator.eval_nodes(&dag.nodes);
+
+            for lane in 0..<PackedVal<SC> as PackedValue>::WIDTH {
+                let preprocessed = window_lane(&preprocessed, lane, val_lane);
+                let partitioned_main = partitioned_main
+                    .iter()
+                    .map(|window| window_lane(window, lane, val_lane))
+                    .collect_vec();
+                let perm = window_lane(&perm, lane, challenge_lane);
+                let challenges = vec![window_lane(&challenges, lane, challenge_lane)];
+                let exposed_values = vec![window_lane(&exposed_values, lane, challenge_lane)];
+                let periodic_values = periodic_values
+                    .iter()
+                    .map(|x| val_lane(x, lane))
+                    .collect_vec();
+                let values = SymbolicRowValues {
+                    preprocessed: RowMajorMatrixView::new(&preprocessed, PREPROCESSED_WIDTH),
+                    partitioned_main: partitioned_main
+                        .iter()
+                        .zip(MAIN_WIDTHS)
+                        .map(|(values, width)| RowMajorMatrixView::new(values, width))
+                        .collect(),
+                    after_challenge: vec![RowMajorMatrixView::new(&perm, PERM_WIDTH)],
+                    challenges: &challenges,
+                    public_values: &public_values,
+                    periodic_values: &periodic_values,
+                    exposed_values_after_challenge: &exposed_values,
+                    is_first_row: val_lane(&is_first_row, lane),
+                    is_last_row: val_lane(&is_last_row, lane),
+                    is_transition: val_lane(&is_transition, lane),
+                };
+
+                let expected = eval_symbolic_constraints(&dag, &values);
+                let actual = dag
+                    .constraint_idx
+                    .iter()
+                    .map(|&idx| match evaluated_nodes[idx] {
+                        PackedExpr::Val(x) => EF::from(val_lane(&x, lane)),
+                        PackedExpr::Challenge(x) => challenge_lane(&x, lane),
+                    });
+                for (constraint_idx, (expected, actual)) in
+                    expected.into_iter().zip(actual).enumerate()
+                {
+                    assert_eq!(
+                        expected, actual,
+                        "constraint {constraint_idx} mismatch on lane {lane}"
+                    );
+                }
+            }
+        }
+    }
+
+    fn random_matrix(rng: &mut StdRng, height: usize, width: usize) -> RowMajorMatrix<F> {
+        RowMajorMatrix::new((0..height * width).map(|_| rng.gen()).collect(), width)
+    }
+
+    /// Compares the quotient values against the reference interpreter for traces of height 1, 2
+    /// and 4, whose quotient domains may be smaller than a packing and are then evaluated one
+    /// point at a time. On a trace of height 1, the next row is the local row.
+    #[test]
+    fn test_quotient_values_match_interpreter_on_short_traces() {
+        const QUOTIENT_DEGREE: usize = 2;
+        let mut rng = create_seeded_rng();
+        let constraints = (0..20).map(|_| random_expr(&mut rng, 6)).collect_vec();
+        let dag = SymbolicConstraintsDag::from(SymbolicConstraints {
+            constraints,
+            interactions: vec![],
+        })
+        .constraints;
+        let mut random_challenge = || EF::from_base_fn(|_| rng.gen::<F>());
+        let challenges = vec![(0..NUM_CHALLENGES)
+            .map(|_| random_challenge())
+            .collect_vec()];
+        let exposed_values = vec![(0..NUM_EXPOSED_VALUES)
+            .map(|_| random_challenge())
+            .collect_vec()];
+        let alpha = random_challenge();
+        let public_values: Vec<F> = (0..NUM_PUBLIC_VALUES).map(|_| rng.gen()).collect();
+        // Columns of period 1, which divides every trace height.
+        let periodic_values: Vec<F> = (0..NUM_PERIODIC_COLUMNS).map(|_| rng.gen()).collect();
+        let periodic_columns = periodic_values.iter().map(|&x| vec![x]).collect_vec();
+
+        for log_height in 0..=2 {
+            let trace_domain = TwoAdicMultiplicativeCoset {
+                log_n: log_height,
+                shift: F::ONE,
+            };
+            let quotient_domain =
+                trace_domain.create_disjoint_domain(QUOTIENT_DEGREE << log_height);
+            let quotient_size = quotient_domain.size();
+            // Row `i + 1` of the trace is row `i + QUOTIENT_DEGREE` of the quotient domain.
+            let next_step = QUOTIENT_DEGREE;
+            let preprocessed = random_matrix(&mut rng, quotient_size, PREPROCESSED_WIDTH);
+            let partitioned_main = MAIN_WIDTHS
+                .map(|width| random_matrix(&mut rng, quotient_size, width))
+                .to_vec();
+            let ext_degree = <EF as FieldExtensionAlgebra<F>>::D;
+            let perm = random_matrix(&mut rng, quotient_size, PERM_WIDTH * ext_degree);
+
+            let quotient_values = compute_single_rap_quotient_values::<SC, _>(
+                &dag,
+                trace_domain,
+                quotient_domain,
+                Some(preprocessed.as_view()),
+                partitioned_main.iter().map(|m| m.as_view()).collect(),
+                vec![perm.as_view()],
+                &challenges,
+                alpha,
+                &public_values,
+                &periodic_columns,
+                &exposed_values,
+            );
+            assert_eq!(quotient_values.len(), quotient_size);
+
+            let sels = trace_domain.selectors_on_coset(quotient_domain);
+            let window = |mat: &RowMajorMatrix<F>, i: usize| {
+                (0..=MAX_ROTATION)
+                    .flat_map(|rotation| {
+                        mat.row_slice((i + rotation * next_step) % quotient_size)
+                            .to_vec()
+                    })
+                    .collect_vec()
+            };
+            for (i, &actual) in quotient_values.iter().enumerate() {
+                let preprocessed = window(&preprocessed, i);
+                let partitioned_main = partitioned_main
+                    .iter()
+                    .map(|mat| window(mat, i))
+                    .collect_vec();
+                let perm = window(&perm, i)
+                    .chunks_exact(ext_degree)
+                    .map(EF::from_base_slice)
+                    .collect_vec();
+                let values = SymbolicRowValues {
+                    preprocessed: RowMajorMatrixView::new(&preprocessed, PREPROCESSED_WIDTH),
+                    partitioned_main: partitioned_main
+                        .iter()
+                        .zip(MAIN_WIDTHS)
+                        .map(|(values, width)| RowMajorMatrixView::new(values, width))
+                        .collect(),
+                    after_challenge: vec![RowMajorMatrixView::new(&perm, PERM_WIDTH)],
+                    challenges: &challenges,
+                    public_values: &public_values,
+                    periodic_values: &periodic_values,
+                    exposed_values_after_challenge: &exposed_values,
+                    is_first_row: sels.is_first_row[i],
+                    is_last_row: sels.is_last_row[i],
+                    is_transition: sels.is_transition[i],
+                };
+                // The constraints are folded with the highest power of alpha first.
+                let expected = eval_symbolic_constraints(&dag, &values)
+                    .into_iter()
+                    .fold(EF::ZERO, |acc, value| acc * alpha + value)
+                    * sels.inv_zeroifier[i];
+                assert_eq!(actual, expected, "log height {log_height}, point {i}");
+            }
+        }
+    }
+}
@@ -2,17 +2,20 @@ use std::sync::Arc;
 
 use itertools::{izip, multiunzip, Itertools};
 use p3_commit::{Pcs, PolynomialSpace};
-use p3_field::FieldAlgebra;
+use p3_field::Field;
 use p3_matrix::{dense::RowMajorMatrix, Matrix};
 use p3_util::log2_strict_usize;
 use tracing::instrument;
 
 use self::single::compute_single_rap_quotient_values;
-use super::PcsData;
+use super::{random_matrix, PcsData, ZkRandomness};
 use crate::{
     air_builders::symbolic::SymbolicExpressionDag,
-    config::{Com, Domain, PackedChallenge, StarkGenericConfig, Val},
-    prover::types::RapView,
+    config::{Com, Domain, StarkGenericConfig, Val},
+    prover::{
+        metrics::Stopwatch, types::RapView, view::BitReversed, ProvingError, ProvingOptions,
+        ProvingPhase,
+    },
 };
 
 mod evaluator;
@@ -21,41 +24,80 @@ pub(crate) mod single;
 pub struct QuotientCommitter<'pcs, SC: StarkGenericConfig> {
     pcs: &'pcs SC::Pcs,
     alpha: SC::Challenge,
+    zero_knowledge: Option<&'pcs dyn ZkRandomness>,
 }
 
 impl<'pcs, SC: StarkGenericConfig> QuotientCommitter<'pcs, SC> {
     pub fn new(pcs: &'pcs SC::Pcs, alpha: SC::Challenge) -> Self {
-        Self { pcs, alpha }
+        Self {
+            pcs,
+            alpha,
+            zero_knowledge: None,
+        }
+    }
+
+    /// Sets whether a random mask, drawn from `randomness`, is added to each quotient polynomial
+    /// before it is split into chunks, in which case the mask is committed after the chunks of
+    /// its quotient.
+    pub fn with_zero_knowledge(mut self, randomness: Option<&'pcs dyn ZkRandomness>) -> Self {
+        self.zero_knowledge = randomness;
+        self
     }
 
     /// Constructs quotient domains and computes the evaluation of the quotient polynomials
     /// on the quotient domains of each RAP.
     ///
     /// ## Assumptions
-    /// - `constraints`, `extended_views`, `quotient_degrees` have equal lengths and the length equals number of RAPs.
+    /// - `air_names`, `constraints`, `extended_views`, `quotient_degrees` have equal lengths and the length equals number of RAPs.
     /// - `quotient_degrees` is the factor to **multiply** the trace degree by to get the degree of the quotient polynomial. This should be determined from the constraint degree of the RAP.
-    /// - `extended_views` is a view of the trace polynomials evaluated on the quotient domain, with rows bit reversed to account for the fact that the quotient domain is different for each RAP.
+    /// - `extended_views` is a view of the trace polynomials evaluated on the quotient domain, with rows bit reversed to account for the fact that the quotient domain is different for each RAP, see [BitReversed]. The views are checked against the verifying key by [RapViewBuilder](crate::prover::view::RapViewBuilder).
+    ///
+    /// Calls [ProvingOptions::checkpoint] after each RAP, and stops if the proof was cancelled.
+    /// Records the time of each RAP in the metrics of `options`.
     #[instrument(name = "compute quotient values", level = "info", skip_all)]
     pub fn quotient_values(
         &self,
+        air_names: &[&str],
         constraints: &[&SymbolicExpressionDag<Val<SC>>],
-        extended_views: Vec<RapView<impl Matrix<Val<SC>>, Val<SC>, SC::Challenge>>,
+        extended_views: Vec<RapView<BitReversed<impl Matrix<Val<SC>>>, Val<SC>, SC::Challenge>>,
         quotient_degrees: &[u8],
-    ) -> QuotientData<SC> {
+        options: &ProvingOptions,
+    ) -> Result<QuotientData<SC>, ProvingError> {
+        assert_eq!(constraints.len(), air_names.len());
         assert_eq!(constraints.len(), extended_views.len());
         assert_eq!(constraints.len(), quotient_degrees.len());
-        let inner = izip!(constraints, extended_views, quotient_degrees)
-            .map(|(constraints, extended_view, &quotient_degree)| {
-                self.single_rap_quotient_values(constraints, extended_view, quotient_degree)
-            })
-            .collect();
-        QuotientData { inner }
+        let num_airs = constraints.len();
+        let inner = izip!(air_names, constraints, extended_views, quotient_degrees)
+            .enumerate()
+            .map(
+                |(i, (&air_name, constraints, extended_view, &quotient_degree))| {
+                    let stopwatch = Stopwatch::start(options);
+                    let data = tracing::info_span!("single RAP quotient values", air_name)
+                        .in_scope(|| {
+                            self.single_rap_quotient_values(
+                                constraints,
+                                extended_view,
+                                quotient_degree,
+                            )
+                        });
+                    let elapsed = stopwatch.elapsed().wall;
+                    options.record_metrics(|metrics| {
+                        metrics
+                            .quotient_evaluation_per_air
+                            .push((air_name.to_string(), elapsed));
+                    });
+                    options.checkpoint(ProvingPhase::Quotient, (i + 1) as f32 / num_airs as f32)?;
+                    Ok(data)
+                },
+            )
+            .collect::<Result<_, _>>()?;
+        Ok(QuotientData { inner })
     }
 
-    pub(super) fn single_rap_quotient_values(
+    pub(crate) fn single_rap_quotient_values(
         &self,
         constraints: &SymbolicExpressionDag<Val<SC>>,
-        view: RapView<impl Matrix<Val<SC>>, Val<SC>, SC::Challenge>,
+        view: RapView<BitReversed<impl Matrix<Val<SC>>>, Val<SC>, SC::Challenge>,
         quotient_degree: u8,
     ) -> SingleQuotientData<SC> {
         let log_trace_height = view.pair.log_trace_height;
@@ -64,6 +106,14 @@ impl<'pcs, SC: StarkGenericConfig> QuotientCommitter<'pcs, SC> {
             .natural_domain_for_degree(1usize << log_trace_height);
         let quotient_domain =
             trace_domain.create_disjoint_domain(trace_domain.size() * quotient_degree as usize);
+        // Without constraints, e.g. for a lookup table without interactions, the quotient is zero.
+        if constraints.constraint_idx.is_empty() {
+            return SingleQuotientData {
+                quotient_degree: quotient_degree as usize,
+                quotient_domain,
+                quotient_values: SC::Challenge::zero_vec(quotient_domain.size()),
+            };
+        }
 
         let (after_challenge_lde_on_quotient_domain, challenges, exposed_values_after_challenge): (
             Vec<_>,
@@ -72,15 +122,10 @@ impl<'pcs, SC: StarkGenericConfig> QuotientCommitter<'pcs, SC> {
         ) = multiunzip(view.per_phase.into_iter().map(|view| {
             (
                 view.inner
-                    .expect("gap in challenge phase not supported yet"),
-                view.challenges
-                    .into_iter()
-                    .map(PackedChallenge::<SC>::from_f)
-                    .collect_vec(),
-                view.exposed_values
-                    .into_iter()
-                    .map(PackedChallenge::<SC>::from_f)
-                    .collect_vec(),
+                    .expect("gap in challenge phase not supported yet")
+                    .into_inner(),
+                view.challenges,
+                view.exposed_values,
             )
         }));
 
@@ -88,12 +133,17 @@ impl<'pcs, SC: StarkGenericConfig> QuotientCommitter<'pcs, SC> {
             constraints,
             trace_domain,
             quotient_domain,
-            view.pair.preprocessed,
-            view.pair.partitioned_main,
+            view.pair.preprocessed.map(BitReversed::into_inner),
+            view.pair
+                .partitioned_main
+                .into_iter()
+                .map(BitReversed::into_inner)
+                .collect(),
             after_challenge_lde_on_quotient_domain,
             &challenges,
             self.alpha,
             &view.pair.public_values,
+            &view.pair.periodic_columns,
             &exposed_values_after_challenge,
         );
         SingleQuotientData {
@@ -106,7 +156,7 @@ impl<'pcs, SC: StarkGenericConfig> QuotientCommitter<'pcs, SC> {
     #[instrument(name = "commit to quotient poly chunks", skip_all)]
     pub fn commit(&self, data: QuotientData<SC>) -> (Com<SC>, PcsData<SC>) {
         let (log_trace_heights, quotient_domains_and_chunks): (Vec<_>, Vec<_>) = data
-            .split()
+            .split(self.zero_knowledge)
             .into_iter()
             .map(|q| {
                 (
@@ -133,18 +183,21 @@ pub(super) struct QuotientData<SC: StarkGenericConfig> {
 
 impl<SC: StarkGenericConfig> QuotientData<SC> {
     /// Splits the quotient polynomials from multiple AIRs into chunks of size equal to the trace domain size.
-    pub fn split(self) -> impl IntoIterator<Item = QuotientChunk<SC>> {
-        self.inner.into_iter().flat_map(|data| data.split())
+    pub fn split(self, zero_knowledge: Option<&dyn ZkRandomness>) -> Vec<QuotientChunk<SC>> {
+        self.inner
+            .into_iter()
+            .flat_map(|data| data.split(zero_knowledge))
+            .collect()
     }
 }
 
 /// The quotient polynomial from a single matrix RAP, evaluated on the quotient domain.
-pub(super) struct SingleQuotientData<SC: StarkGenericConfig> {
+pub(crate) struct SingleQuotientData<SC: StarkGenericConfig> {
     quotient_degree: usize,
     /// Quotient domain
     quotient_domain: Domain<SC>,
     /// Evaluations of the quotient polynomial on the quotient domain
-    quotient_values: Vec<SC::Challenge>,
+    pub(crate) quotient_values: Vec<SC::Challenge>,
 }
 
 impl<SC: StarkGenericConfig> SingleQuotientData<SC> {
@@ -152,17 +205,36 @@ impl<SC: StarkGenericConfig> SingleQuotientData<SC> {
     /// first flattened from vector of extension field elements to matrix of base field elements,
     /// and then split into chunks of size equal to the trace domain size (quotient domain size
     /// divided by `quotient_degree`).
-    pub fn split(self) -> impl IntoIterator<Item = QuotientChunk<SC>> {
+    ///
+    /// In zero-knowledge mode, the chunks would reveal more than the value of the quotient at
+    /// `zeta`, so random evaluations are added to the quotient before it is split, and are
+    /// returned after the chunks, on the whole quotient domain, for the verifier to subtract
+    /// their value at `zeta`.
+    pub fn split(
+        self,
+        zero_knowledge: Option<&dyn ZkRandomness>,
+    ) -> impl IntoIterator<Item = QuotientChunk<SC>> {
         let quotient_degree = self.quotient_degree;
         let quotient_domain = self.quotient_domain;
         // Flatten from extension field elements to base field elements
-        let quotient_flat = RowMajorMatrix::new_col(self.quotient_values).flatten_to_base();
+        let mut quotient_flat = RowMajorMatrix::new_col(self.quotient_values).flatten_to_base();
+        let mask = zero_knowledge.map(|randomness| {
+            let mask = random_matrix(randomness, quotient_flat.height(), quotient_flat.width());
+            for (value, &random) in quotient_flat.values.iter_mut().zip_eq(&mask.values) {
+                *value += random;
+            }
+            QuotientChunk {
+                domain: quotient_domain,
+                chunk: mask,
+            }
+        });
         let quotient_chunks = quotient_domain.split_evals(quotient_degree, quotient_flat);
         let qc_domains = quotient_domain.split_domains(quotient_degree);
         qc_domains
             .into_iter()
             .zip_eq(quotient_chunks)
             .map(|(domain, chunk)| QuotientChunk { domain, chunk })
+            .chain(mask)
     }
 }
 
@@ -172,6 +244,9 @@ impl<SC: StarkGenericConfig> SingleQuotientData<SC> {
 ///
 /// This represents a single chunk, where the vector of extension field elements is
 /// further flattened to a matrix of base field elements.
+///
+/// In zero-knowledge mode, the random mask of the quotient is represented the same way, on the
+/// whole quotient domain.
 pub struct QuotientChunk<SC: StarkGenericConfig> {
     /// Chunk of quotient domain, which is a coset of the trace domain
     pub domain: Domain<SC>,
@@ -1,19 +1,20 @@
-use std::cmp::min;
+use std::iter;
 
 use itertools::Itertools;
 use p3_commit::PolynomialSpace;
-use p3_field::{FieldAlgebra, FieldExtensionAlgebra, PackedValue};
+use p3_field::{FieldAlgebra, FieldExtensionAlgebra, PackedField, PackedValue};
 use p3_matrix::Matrix;
 use p3_maybe_rayon::prelude::*;
 use p3_util::log2_strict_usize;
 use tracing::instrument;
 
-use super::evaluator::{ProverConstraintEvaluator, ViewPair};
+use super::evaluator::{ProverConstraintEvaluator, RowWindow};
 use crate::{
     air_builders::symbolic::{
         symbolic_variable::Entry, SymbolicExpressionDag, SymbolicExpressionNode,
     },
     config::{Domain, PackedChallenge, PackedVal, StarkGenericConfig, Val},
+    poly::periodic::PeriodicColumn,
 };
 
 // Starting reference: p3_uni_stark::prover::quotient_values
@@ -21,6 +22,9 @@ use crate::{
 /// Computes evaluation of DEEP quotient polynomial on the quotient domain for a single RAP (single trace matrix).
 ///
 /// Designed to be general enough to support RAP with multiple rounds of challenges.
+///
+/// The points of the quotient domain are evaluated `PackedVal::<SC>::WIDTH` at a time. A quotient
+/// domain with fewer points, e.g. for a trace of height 1 or 2, is evaluated one point at a time.
 #[allow(clippy::too_many_arguments)]
 #[instrument(
     name = "compute single RAP quotient polynomial",
@@ -35,11 +39,13 @@ pub fn compute_single_rap_quotient_values<'a, SC, M>(
     partitioned_main_lde_on_quotient_domain: Vec<M>,
     after_challenge_lde_on_quotient_domain: Vec<M>,
     // For each challenge round, the challenges drawn
-    challenges: &'a [Vec<PackedChallenge<SC>>],
+    challenges: &'a [Vec<SC::Challenge>],
     alpha: SC::Challenge,
     public_values: &'a [Val<SC>],
+    // The sequence repeated by each periodic column
+    periodic_columns: &[Vec<Val<SC>>],
     // Values exposed to verifier after challenge round i
-    exposed_values_after_challenge: &'a [Vec<PackedChallenge<SC>>],
+    exposed_values_after_challenge: &'a [Vec<SC::Challenge>],
 ) -> Vec<SC::Challenge>
 where
     SC: StarkGenericConfig,
@@ -56,40 +62,44 @@ where
         .as_ref()
         .map(|m| m.width())
         .unwrap_or(0);
-    let mut sels = trace_domain.selectors_on_coset(quotient_domain);
+    let sels = trace_domain.selectors_on_coset(quotient_domain);
 
     let qdb = log2_strict_usize(quotient_size) - log2_strict_usize(trace_domain.size());
     let next_step = 1 << qdb;
 
     let ext_degree = SC::Challenge::D;
 
-    let mut alpha_powers = alpha
-        .powers()
-        .take(constraints.constraint_idx.len())
-        .map(PackedChallenge::<SC>::from_f)
+    // A periodic column of period `p` is a polynomial in `x^(n / p)` for trace height `n`, so on
+    // the quotient domain its values repeat every `p * quotient_degree` points.
+    let periodic_values_on_quotient_domain = periodic_columns
+        .iter()
+        .map(|values| {
+            let column = PeriodicColumn::new(values, &trace_domain)
+                .expect("period of a periodic column must divide the trace height");
+            let num_values = column.period() << qdb;
+            iter::successors(Some(quotient_domain.first_point()), |&x| {
+                quotient_domain.next_point(x)
+            })
+            .take(num_values)
+            .map(|x| column.evaluate(x))
+            .collect_vec()
+        })
         .collect_vec();
-    // We want alpha powers to have highest power first, because of how accumulator "folding" works
-    // So this will be alpha^{num_constraints - 1}, ..., alpha^0
-    alpha_powers.reverse();
-
-    // assert!(quotient_size >= PackedVal::<SC>::WIDTH);
-    // We take PackedVal::<SC>::WIDTH worth of values at a time from a quotient_size slice, so we need to
-    // pad with default values in the case where quotient_size is smaller than PackedVal::<SC>::WIDTH.
-    for _ in quotient_size..PackedVal::<SC>::WIDTH {
-        sels.is_first_row.push(Val::<SC>::default());
-        sels.is_last_row.push(Val::<SC>::default());
-        sels.is_transition.push(Val::<SC>::default());
-        sels.inv_zeroifier.push(Val::<SC>::default());
-    }
 
-    // Scan constraints to see if we need `next` row and also check index bounds
-    // so we don't need to check them per row.
-    let mut rotation = 0;
+    // Scan constraints to see which rotations are needed and also check index bounds
+    // so we don't need to check them per row. The local row is always needed.
+    let mut rotation_used = vec![true];
+    let mut use_rotation = |rotation: usize| {
+        if rotation >= rotation_used.len() {
+            rotation_used.resize(rotation + 1, false);
+        }
+        rotation_used[rotation] = true;
+    };
     for node in &constraints.nodes {
         if let SymbolicExpressionNode::Variable(var) = node {
             match var.entry {
                 Entry::Preprocessed { offset } => {
-                    rotation = rotation.max(offset);
+                    use_rotation(offset);
                     assert!(var.index < preprocessed_width);
                     assert!(
                         preprocessed_trace_on_quotient_domain
@@ -100,7 +110,7 @@ where
                     );
                 }
                 Entry::Main { part_index, offset } => {
-                    rotation = rotation.max(offset);
+                    use_rotation(offset);
                     assert!(
                         var.index < partitioned_main_lde_on_quotient_domain[part_index].width()
                     );
@@ -108,143 +118,219 @@ where
                 Entry::Public => {
                     assert!(var.index < public_values.len());
                 }
-                Entry::Permutation { offset } => {
-                    rotation = rotation.max(offset);
-                    let ext_width = after_challenge_lde_on_quotient_domain
-                        .first()
-                        .expect("Challenge phase not supported")
-                        .width()
-                        / ext_degree;
+                Entry::Periodic => {
+                    assert!(var.index < periodic_columns.len());
+                }
+                Entry::Permutation { phase, offset } => {
+                    use_rotation(offset);
+                    let ext_width =
+                        after_challenge_lde_on_quotient_domain[phase].width() / ext_degree;
                     assert!(var.index < ext_width);
                 }
-                Entry::Challenge => {
-                    assert!(
-                        var.index
-                            < challenges
-                                .first()
-                                .expect("Challenge phase not supported")
-                                .len()
-                    );
+                Entry::Challenge { phase } => {
+                    assert!(var.index < challenges[phase].len());
                 }
-                Entry::Exposed => {
-                    assert!(
-                        var.index
-                            < exposed_values_after_challenge
-                                .first()
-                                .expect("Challenge phase not supported")
-                                .len()
-                    );
+                Entry::Exposed { phase } => {
+                    assert!(var.index < exposed_values_after_challenge[phase].len());
                 }
             }
         }
     }
-    let needs_next = rotation > 0;
-
-    (0..quotient_size)
-        .into_par_iter()
-        .step_by(PackedVal::<SC>::WIDTH)
-        .flat_map_iter(|i_start| {
-            let wrap = |i| i % quotient_size;
-            let i_range = i_start..i_start + PackedVal::<SC>::WIDTH;
-
-            let [row_idx_local, row_idx_next] = [0, next_step].map(|shift| {
-                (0..PackedVal::<SC>::WIDTH)
-                    .map(|offset| wrap(i_start + offset + shift))
-                    .collect::<Vec<_>>()
-            });
-            let row_idx_local = Some(row_idx_local);
-            let row_idx_next = needs_next.then_some(row_idx_next);
-
-            let is_first_row = *PackedVal::<SC>::from_slice(&sels.is_first_row[i_range.clone()]);
-            let is_last_row = *PackedVal::<SC>::from_slice(&sels.is_last_row[i_range.clone()]);
-            let is_transition = *PackedVal::<SC>::from_slice(&sels.is_transition[i_range.clone()]);
-            let inv_zeroifier = *PackedVal::<SC>::from_slice(&sels.inv_zeroifier[i_range.clone()]);
-
-            // Vertically pack rows of each matrix,
-            // skipping `next` if above scan showed no constraints need it:
-
-            let [preprocessed_local, preprocessed_next] =
-                [&row_idx_local, &row_idx_next].map(|wrapped_idx| {
-                    wrapped_idx.as_ref().map(|wrapped_idx| {
-                        (0..preprocessed_width)
-                            .map(|col| {
-                                PackedVal::<SC>::from_fn(|offset| {
-                                    preprocessed_trace_on_quotient_domain
-                                        .as_ref()
-                                        .unwrap()
-                                        .get(wrapped_idx[offset], col)
-                                })
-                            })
-                            .collect_vec()
-                    })
-                });
-            let preprocessed_pair = ViewPair::new(preprocessed_local.unwrap(), preprocessed_next);
 
-            let partitioned_main_pairs = partitioned_main_lde_on_quotient_domain
+    let evaluation = QuotientEvaluation::<SC, M> {
+        constraints,
+        quotient_size,
+        next_step,
+        rotation_used,
+        is_first_row: sels.is_first_row,
+        is_last_row: sels.is_last_row,
+        is_transition: sels.is_transition,
+        inv_zeroifier: sels.inv_zeroifier,
+        periodic_values_on_quotient_domain,
+        preprocessed: preprocessed_trace_on_quotient_domain,
+        partitioned_main: partitioned_main_lde_on_quotient_domain,
+        after_challenge: after_challenge_lde_on_quotient_domain,
+        challenges,
+        alpha,
+        public_values,
+        exposed_values_after_challenge,
+    };
+    if quotient_size >= PackedVal::<SC>::WIDTH {
+        evaluation.evaluate::<PackedVal<SC>, PackedChallenge<SC>>()
+    } else {
+        evaluation.evaluate::<Val<SC>, SC::Challenge>()
+    }
+}
+
+/// The inputs of [compute_single_rap_quotient_values], after the bounds checks.
+struct QuotientEvaluation<'a, SC: StarkGenericConfig, M> {
+    constraints: &'a SymbolicExpressionDag<Val<SC>>,
+    quotient_size: usize,
+    /// Row `i + 1` of the trace is row `i + next_step` of the quotient domain.
+    next_step: usize,
+    /// Whether any constraint uses each rotation.
+    rotation_used: Vec<bool>,
+    is_first_row: Vec<Val<SC>>,
+    is_last_row: Vec<Val<SC>>,
+    is_transition: Vec<Val<SC>>,
+    inv_zeroifier: Vec<Val<SC>>,
+    periodic_values_on_quotient_domain: Vec<Vec<Val<SC>>>,
+    preprocessed: Option<M>,
+    partitioned_main: Vec<M>,
+    after_challenge: Vec<M>,
+    challenges: &'a [Vec<SC::Challenge>],
+    alpha: SC::Challenge,
+    public_values: &'a [Val<SC>],
+    exposed_values_after_challenge: &'a [Vec<SC::Challenge>],
+}
+
+impl<SC, M> QuotientEvaluation<'_, SC, M>
+where
+    SC: StarkGenericConfig,
+    M: Matrix<Val<SC>>,
+{
+    /// Evaluates the quotient on the quotient domain, `P::WIDTH` points at a time. The size of
+    /// the quotient domain must be a multiple of `P::WIDTH`.
+    fn evaluate<P, PE>(&self) -> Vec<SC::Challenge>
+    where
+        P: PackedField<Scalar = Val<SC>>,
+        PE: FieldExtensionAlgebra<P, F = SC::Challenge> + Copy + Send + Sync,
+    {
+        let quotient_size = self.quotient_size;
+        debug_assert_eq!(quotient_size % P::WIDTH, 0);
+        let ext_degree = SC::Challenge::D;
+        let pack = |values: &[Vec<SC::Challenge>]| {
+            values
                 .iter()
-                .map(|lde| {
+                .map(|values| values.iter().map(|&x| PE::from_f(x)).collect_vec())
+                .collect_vec()
+        };
+        let challenges = pack(self.challenges);
+        let exposed_values_after_challenge = pack(self.exposed_values_after_challenge);
+
+        let mut alpha_powers = self
+            .alpha
+            .powers()
+            .take(self.constraints.constraint_idx.len())
+            .map(PE::from_f)
+            .collect_vec();
+        // We want alpha powers to have highest power first, because of how accumulator "folding" works
+        // So this will be alpha^{num_constraints - 1}, ..., alpha^0
+        alpha_powers.reverse();
+
+        (0..quotient_size)
+            .into_par_iter()
+            .step_by(P::WIDTH)
+            .flat_map_iter(|i_start| {
+                let wrap = |i| i % quotient_size;
+                let i_range = i_start..i_start + P::WIDTH;
+
+                // Row `i + rotation` of the trace is row `i + rotation * next_step` of the LDE.
+                // On a trace of height 1, `next_step` is the size of the quotient domain, so the
+                // next row is the local row.
+                let row_idx_per_rotation = self
+                    .rotation_used
+                    .iter()
+                    .enumerate()
+                    .map(|(rotation, &used)| {
+                        used.then(|| {
+                            (0..P::WIDTH)
+                                .map(|offset| wrap(i_start + offset + rotation * self.next_step))
+                                .collect::<Vec<_>>()
+                        })
+                    })
+                    .collect_vec();
+
+                let is_first_row = *P::from_slice(&self.is_first_row[i_range.clone()]);
+                let is_last_row = *P::from_slice(&self.is_last_row[i_range.clone()]);
+                let is_transition = *P::from_slice(&self.is_transition[i_range.clone()]);
+                let inv_zeroifier = *P::from_slice(&self.inv_zeroifier[i_range.clone()]);
+
+                let periodic_values = self
+                    .periodic_values_on_quotient_domain
+                    .iter()
+                    .map(|values| P::from_fn(|offset| values[(i_start + offset) % values.len()]))
+                    .collect_vec();
+
+                // Vertically pack rows of each matrix,
+                // skipping rotations that the above scan showed no constraints need:
+                let window = |lde: &M| {
                     let width = lde.width();
-                    let [local, next] = [&row_idx_local, &row_idx_next].map(|wrapped_idx| {
-                        wrapped_idx.as_ref().map(|wrapped_idx| {
-                            (0..width)
-                                .map(|col| {
-                                    PackedVal::<SC>::from_fn(|offset| {
-                                        lde.get(wrapped_idx[offset], col)
+                    let rows = row_idx_per_rotation
+                        .iter()
+                        .map(|wrapped_idx| {
+                            wrapped_idx.as_ref().map(|wrapped_idx| {
+                                (0..width)
+                                    .map(|col| {
+                                        P::from_fn(|offset| lde.get(wrapped_idx[offset], col))
                                     })
-                                })
-                                .collect_vec()
+                                    .collect_vec()
+                            })
                         })
-                    });
-                    ViewPair::new(local.unwrap(), next)
-                })
-                .collect_vec();
+                        .collect_vec();
+                    RowWindow::new(rows)
+                };
+                let preprocessed_window = self
+                    .preprocessed
+                    .as_ref()
+                    .map(window)
+                    .unwrap_or_else(|| RowWindow::new(vec![]));
+                let partitioned_main_windows =
+                    self.partitioned_main.iter().map(window).collect_vec();
 
-            let after_challenge_pairs = after_challenge_lde_on_quotient_domain
-                .iter()
-                .map(|lde| {
-                    // Width in base field with extension field elements flattened
-                    let base_width = lde.width();
-                    let [local, next] = [&row_idx_local, &row_idx_next].map(|wrapped_idx| {
-                        wrapped_idx.as_ref().map(|wrapped_idx| {
-                            (0..base_width)
-                                .step_by(ext_degree)
-                                .map(|col| {
-                                    PackedChallenge::<SC>::from_base_fn(|i| {
-                                        PackedVal::<SC>::from_fn(|offset| {
-                                            lde.get(wrapped_idx[offset], col + i)
+                let after_challenge_windows = self
+                    .after_challenge
+                    .iter()
+                    .map(|lde| {
+                        // Width in base field with extension field elements flattened
+                        let base_width = lde.width();
+                        let rows = row_idx_per_rotation
+                            .iter()
+                            .map(|wrapped_idx| {
+                                wrapped_idx.as_ref().map(|wrapped_idx| {
+                                    (0..base_width)
+                                        .step_by(ext_degree)
+                                        .map(|col| {
+                                            PE::from_base_fn(|i| {
+                                                P::from_fn(|offset| {
+                                                    lde.get(wrapped_idx[offset], col + i)
+                                                })
+                                            })
                                         })
-                                    })
+                                        .collect_vec()
                                 })
-                                .collect_vec()
+                            })
+                            .collect_vec();
+                        RowWindow::new(rows)
+                    })
+                    .collect_vec();
+
+                let evaluator: ProverConstraintEvaluator<SC, P, PE> = ProverConstraintEvaluator {
+                    preprocessed: preprocessed_window,
+                    partitioned_main: partitioned_main_windows,
+                    after_challenge: after_challenge_windows,
+                    challenges: &challenges,
+                    is_first_row,
+                    is_last_row,
+                    is_transition,
+                    public_values: self.public_values,
+                    periodic_values,
+                    exposed_values_after_challenge: &exposed_values_after_challenge,
+                };
+                let accumulator = evaluator.accumulate(self.constraints, &alpha_powers);
+                // quotient(x) = constraints(x) / Z_H(x)
+                let quotient: PE = accumulator * inv_zeroifier;
+
+                // "Transpose" D packed base coefficients into WIDTH scalar extension coefficients.
+                (0..P::WIDTH).map(move |idx_in_packing| {
+                    let quotient_value = (0..ext_degree)
+                        .map(|coeff_idx| {
+                            quotient.as_base_slice()[coeff_idx].as_slice()[idx_in_packing]
                         })
-                    });
-                    ViewPair::new(local.unwrap(), next)
+                        .collect::<Vec<_>>();
+                    SC::Challenge::from_base_slice(&quotient_value)
                 })
-                .collect_vec();
-
-            let evaluator: ProverConstraintEvaluator<SC> = ProverConstraintEvaluator {
-                preprocessed: preprocessed_pair,
-                partitioned_main: partitioned_main_pairs,
-                after_challenge: after_challenge_pairs,
-                challenges,
-                is_first_row,
-                is_last_row,
-                is_transition,
-                public_values,
-                exposed_values_after_challenge,
-            };
-            let accumulator = evaluator.accumulate(constraints, &alpha_powers);
-            // quotient(x) = constraints(x) / Z_H(x)
-            let quotient: PackedChallenge<SC> = accumulator * inv_zeroifier;
-
-            // "Transpose" D packed base coefficients into WIDTH scalar extension coefficients.
-            let width = min(PackedVal::<SC>::WIDTH, quotient_size);
-            (0..width).map(move |idx_in_packing| {
-                let quotient_value = (0..<SC::Challenge as FieldExtensionAlgebra<Val<SC>>>::D)
-                    .map(|coeff_idx| quotient.as_base_slice()[coeff_idx].as_slice()[idx_in_packing])
-                    .collect::<Vec<_>>();
-                SC::Challenge::from_base_slice(&quotient_value)
             })
-        })
-        .collect()
+            .collect()
+    }
 }
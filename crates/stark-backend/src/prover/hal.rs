@@ -9,12 +9,16 @@ use p3_challenger::CanObserve;
 use p3_matrix::dense::RowMajorMatrix;
 use serde::{de::DeserializeOwned, Serialize};
 
-use super::types::{
-    DeviceMultiStarkProvingKey, DeviceStarkProvingKey, PairView, ProverDataAfterRapPhases,
-    SingleCommitPreimage,
+use super::{
+    types::{
+        DeviceMultiStarkProvingKey, DeviceStarkProvingKey, PairView, ProverDataAfterRapPhases,
+        SingleCommitPreimage,
+    },
+    ProvingError, ProvingOptions,
 };
 use crate::{
     config::{Com, StarkGenericConfig, Val},
+    interaction::public_lookup::PublicLookupTable,
     keygen::types::MultiStarkProvingKey,
 };
 
@@ -80,10 +84,14 @@ pub trait RapPartialProver<PB: ProverBackend> {
     /// The `trace_views` are the views of the respective trace matrices, evaluated on the trace domain.
     /// Currently this function does not provide a view of any already committed data associated
     /// with the trace views, although that data is available.
+    ///
+    /// The implementation must observe the commitment of each challenge phase before sampling the
+    /// challenges of the next phase.
     fn partially_prove<'a>(
         &self,
         challenger: &mut PB::Challenger,
         pk_views: &[DeviceStarkProvingKey<'a, PB>],
+        public_lookup_tables: &[PublicLookupTable<PB::Val>],
         trace_views: Vec<PairView<&'a PB::Matrix, PB::Val>>,
     ) -> (PB::RapPartialProof, ProverDataAfterRapPhases<PB>);
 }
@@ -98,22 +106,34 @@ pub trait QuotientCommitter<PB: ProverBackend> {
     /// The lengths of
     /// - `pk_views`: proving key per AIR
     /// - `public_values`: public values per AIR
-    /// - `cached_views_per_air`: committed trace views per AIR (if any)
+    /// - `cached_views_per_air`: committed trace data per AIR (if any)
+    /// - `common_main_views_per_air`: committed common main trace data per AIR (if any)
+    /// - `custom_views_per_air`: committed custom round matrix data per AIR (if any)
     ///
     /// must be equal, and all equal to the number of AIRs.
     ///
+    /// The views only refer to the committed data: the raw main traces are dropped after the
+    /// after challenge phases, which are the last to read them.
+    ///
     /// Quotient polynomials for multiple RAP matrices are committed together into a single commitment.
     /// The quotient polynomials can be committed together even if the corresponding trace matrices
     /// are committed separately.
+    ///
+    /// The implementation should call [ProvingOptions::checkpoint] with
+    /// [ProvingPhase::Quotient](super::ProvingPhase::Quotient) after the quotient polynomial of each AIR is evaluated, and stop
+    /// on error.
+    #[allow(clippy::too_many_arguments)]
     fn eval_and_commit_quotient(
         &self,
         challenger: &mut PB::Challenger,
         pk_views: &[DeviceStarkProvingKey<PB>],
         public_values: &[Vec<PB::Val>],
-        cached_views_per_air: &[Vec<SingleCommitPreimage<&PB::Matrix, &PB::PcsData>>],
-        common_main_pcs_data: &PB::PcsData,
+        cached_views_per_air: &[Vec<SingleCommitPreimage<(), &PB::PcsData>>],
+        common_main_views_per_air: &[Option<SingleCommitPreimage<(), &PB::PcsData>>],
+        custom_views_per_air: &[Vec<SingleCommitPreimage<(), &PB::PcsData>>],
         prover_data_after: &ProverDataAfterRapPhases<PB>,
-    ) -> (PB::Commitment, PB::PcsData);
+        options: &ProvingOptions,
+    ) -> Result<(PB::Commitment, PB::PcsData), ProvingError>;
 }
 
 /// Polynomial commitment scheme (PCS) opening proof generator.
@@ -121,6 +141,7 @@ pub trait OpeningProver<PB: ProverBackend> {
     /// Opening proof for multiple RAP matrices, where
     /// - (for now) each preprocessed trace matrix has a separate commitment
     /// - main trace matrices can have multiple commitments
+    /// - each custom round has a single commitment
     /// - for each after_challenge phase, all matrices in the phase share a commitment
     /// - quotient poly chunks are all committed together
     // Note[jpw]: pass `preprocessed, main` by reference because there is cached data
@@ -129,12 +150,15 @@ pub trait OpeningProver<PB: ProverBackend> {
         &self,
         challenger: &mut PB::Challenger,
         // For each preprocessed trace commitment, the prover data and
-        // the log height of the matrix, in order
-        preprocessed: Vec<&PB::PcsData>,
+        // the extra rotations of the matrix, in order
+        preprocessed: Vec<(&PB::PcsData, &[usize])>,
         // For each main trace commitment, the prover data and
-        // the log height of each matrix, in order
+        // the extra rotations of each matrix, in order
         // Note: this is all one challenge phase.
-        main: Vec<&PB::PcsData>,
+        main: Vec<(&PB::PcsData, Vec<&[usize]>)>,
+        // For each custom round commitment, the prover data and
+        // the extra rotations of each matrix, in order
+        custom: Vec<(&PB::PcsData, Vec<&[usize]>)>,
         // `after_phase[i]` has shared commitment prover data for all matrices in phase `i + 1`.
         after_phase: Vec<PB::PcsData>,
         // Quotient poly commitment prover data
@@ -28,6 +28,7 @@ impl<SC: StarkGenericConfig> AirProofInputTestHelper<SC> for AirProofInput<SC> {
             raw: AirProofRawInput {
                 cached_mains: cached_traces.into_iter().map(Arc::new).collect(),
                 common_main: Some(common_trace),
+                custom_mains: vec![],
                 public_values: vec![],
             },
         }
@@ -40,6 +41,7 @@ impl<SC: StarkGenericConfig> AirProofInput<SC> {
             raw: AirProofRawInput {
                 cached_mains: vec![],
                 common_main: Some(trace),
+                custom_mains: vec![],
                 public_values,
             },
         }
@@ -0,0 +1,281 @@
+//! Memory used by each phase of a proof.
+//!
+//! With the `mem-metrics` feature, a binary counts the memory it allocates by installing
+//! [TrackingAllocator] as its global allocator:
+//!
+//! ```ignore
+//! #[global_allocator]
+//! static GLOBAL: TrackingAllocator = TrackingAllocator;
+//! ```
+//!
+//! It wraps the allocator selected by the other features (jemalloc, mimalloc, or the system
+//! allocator) and counts the bytes that are allocated and live. The backend never installs it, as
+//! the global allocator is the choice of the binary, and the profiles are all zeros without it.
+//! The prover reads the counts at the start and the end of each [ProvingPhase], so a
+//! [MemoryProfile] captures all buffers of a phase: the cached trace commitments, the trace LDEs
+//! and PCS commit buffers, the after challenge traces, and the quotient values.
+//!
+//! The counts are kept per thread pool: the threads of a pool built by
+//! [ProverConfig::build_pool](super::pool::ProverConfig::build_pool) count the allocations they
+//! make, and the frees, into the counters of their pool, and all other threads into process-wide
+//! counters. A proof on a dedicated pool thus only counts its own allocations, besides those of
+//! the other proofs on the same pool, e.g. the proofs in flight of a
+//! [BatchProver](super::batch::BatchProver).
+
+use std::{
+    alloc::{GlobalAlloc, Layout},
+    cell::Cell,
+    fmt::Display,
+    ptr,
+    sync::{
+        atomic::{AtomicIsize, AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use cfg_if::cfg_if;
+use serde::{Deserialize, Serialize};
+
+use super::{metrics::format_number_with_underscores, ProvingPhase};
+
+cfg_if! {
+    if #[cfg(all(feature = "jemalloc", unix))] {
+        use tikv_jemallocator::Jemalloc as Inner;
+    } else if #[cfg(feature = "mimalloc")] {
+        use mimalloc::MiMalloc as Inner;
+    } else {
+        use std::alloc::System as Inner;
+    }
+}
+
+/// The counters of the threads outside of the pools of the provers.
+static PROCESS: MemoryCounters = MemoryCounters::new();
+
+thread_local! {
+    /// The counters of the pool of the thread, if any, see [MemoryCounters::enter]. A raw pointer
+    /// needs no destructor, so the allocator may read it at any time.
+    static POOL: Cell<*const MemoryCounters> = const { Cell::new(ptr::null()) };
+}
+
+/// The memory counters of a thread pool, see the [module](self) documentation.
+#[derive(Debug, Default)]
+pub(crate) struct MemoryCounters {
+    /// Bytes allocated minus bytes freed by the threads. Negative if they freed memory allocated
+    /// elsewhere, e.g. the traces of a proof input.
+    live: AtomicIsize,
+    /// Maximum of `live` since the start of the current phase.
+    peak: AtomicIsize,
+    /// Bytes allocated since the start of the current phase.
+    allocated: AtomicUsize,
+}
+
+impl MemoryCounters {
+    const fn new() -> Self {
+        Self {
+            live: AtomicIsize::new(0),
+            peak: AtomicIsize::new(0),
+            allocated: AtomicUsize::new(0),
+        }
+    }
+
+    /// Counts the allocations of the current thread into `counters`, until [exit](Self::exit).
+    pub(crate) fn enter(counters: Arc<Self>) {
+        Self::exit();
+        POOL.with(|pool| pool.set(Arc::into_raw(counters)));
+    }
+
+    /// Counts the allocations of the current thread into the process-wide counters again.
+    pub(crate) fn exit() {
+        let counters = POOL.with(|pool| pool.replace(ptr::null()));
+        if !counters.is_null() {
+            // SAFETY: the pointer was set by `enter` from an `Arc` it owns.
+            drop(unsafe { Arc::from_raw(counters) });
+        }
+    }
+
+    /// Runs `f` with the counters of the current thread. The counters of a pool outlive its
+    /// threads.
+    fn with_current<R>(f: impl FnOnce(&MemoryCounters) -> R) -> R {
+        let counters = POOL.try_with(Cell::get).unwrap_or(ptr::null());
+        // SAFETY: a non-null pointer is owned by the thread until `exit`.
+        match unsafe { counters.as_ref() } {
+            Some(counters) => f(counters),
+            None => f(&PROCESS),
+        }
+    }
+
+    fn on_alloc(&self, size: usize) {
+        self.allocated.fetch_add(size, Ordering::Relaxed);
+        let live = self.live.fetch_add(size as isize, Ordering::Relaxed) + size as isize;
+        self.peak.fetch_max(live, Ordering::Relaxed);
+    }
+
+    fn on_dealloc(&self, size: usize) {
+        self.live.fetch_sub(size as isize, Ordering::Relaxed);
+    }
+}
+
+/// Memory used by a single phase of a proof, in bytes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PhaseMemory {
+    /// Total size of the allocations made during the phase, including the ones freed before the
+    /// end of the phase.
+    pub allocated: usize,
+    /// Maximum size of the memory allocated at the same time during the phase, on top of the
+    /// memory allocated at the start of the phase.
+    pub peak: usize,
+    /// Size of the memory allocated at the start of the phase, e.g. the buffers kept from the
+    /// previous phases, minus the memory freed, as counted by the threads of the proof. Only
+    /// differences between phases are meaningful.
+    pub live_at_start: isize,
+}
+
+/// Memory used by each phase of a proof, see the [module](self) documentation.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemoryProfile {
+    /// The phases of the proof, in the order they ran.
+    pub per_phase: Vec<(ProvingPhase, PhaseMemory)>,
+}
+
+impl MemoryProfile {
+    pub fn get(&self, phase: ProvingPhase) -> Option<&PhaseMemory> {
+        self.per_phase
+            .iter()
+            .find_map(|(p, memory)| (*p == phase).then_some(memory))
+    }
+}
+
+impl Display for MemoryProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (phase, memory) in &self.per_phase {
+            writeln!(
+                f,
+                "{:<20} | Allocated = {:<15} | Peak = {:<15}",
+                format!("{phase:?}"),
+                format_number_with_underscores(memory.allocated),
+                format_number_with_underscores(memory.peak),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Records the memory used by each phase of a single proof, with the counters of the thread
+/// that starts the first phase.
+#[derive(Default)]
+pub(crate) struct MemoryRecorder {
+    profile: MemoryProfile,
+    /// The counters of the pool of the proof, `None` for the process-wide counters.
+    counters: Option<Arc<MemoryCounters>>,
+    /// The current phase and the bytes live at its start.
+    current: Option<(ProvingPhase, isize)>,
+}
+
+impl MemoryRecorder {
+    /// Ends the current phase, if any, and starts `phase`. Does nothing if `phase` is the
+    /// current phase, e.g. [ProvingPhase::MainTraceCommit] started before the cached main traces
+    /// are committed.
+    pub(crate) fn begin(&mut self, phase: ProvingPhase) {
+        if matches!(self.current, Some((current, _)) if current == phase) {
+            return;
+        }
+        if self.current.is_none() && self.profile.per_phase.is_empty() {
+            self.counters = POOL.with(|pool| {
+                let counters = pool.get();
+                // SAFETY: a non-null pointer is owned by the thread, so the count stays positive.
+                (!counters.is_null()).then(|| unsafe {
+                    Arc::increment_strong_count(counters);
+                    Arc::from_raw(counters)
+                })
+            });
+        }
+        self.end();
+        let counters = self.counters();
+        let live = counters.live.load(Ordering::Relaxed);
+        counters.peak.store(live, Ordering::Relaxed);
+        counters.allocated.store(0, Ordering::Relaxed);
+        self.current = Some((phase, live));
+    }
+
+    /// Ends the current phase and returns the profile of the proof.
+    pub(crate) fn finish(mut self) -> MemoryProfile {
+        self.end();
+        self.profile
+    }
+
+    fn counters(&self) -> &MemoryCounters {
+        self.counters.as_deref().unwrap_or(&PROCESS)
+    }
+
+    fn end(&mut self) {
+        if let Some((phase, live_at_start)) = self.current.take() {
+            let counters = self.counters();
+            let peak = counters.peak.load(Ordering::Relaxed) - live_at_start;
+            let memory = PhaseMemory {
+                allocated: counters.allocated.load(Ordering::Relaxed),
+                peak: peak.max(0) as usize,
+                live_at_start,
+            };
+            self.profile.per_phase.push((phase, memory));
+        }
+    }
+}
+
+/// Global allocator counting the memory used by each phase of a proof, see the [module](self)
+/// documentation.
+pub struct TrackingAllocator;
+
+unsafe impl GlobalAlloc for TrackingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = Inner.alloc(layout);
+        if !ptr.is_null() {
+            MemoryCounters::with_current(|counters| counters.on_alloc(layout.size()));
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = Inner.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            MemoryCounters::with_current(|counters| counters.on_alloc(layout.size()));
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        Inner.dealloc(ptr, layout);
+        MemoryCounters::with_current(|counters| counters.on_dealloc(layout.size()));
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = Inner.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            MemoryCounters::with_current(|counters| {
+                // Only the growth is a new allocation.
+                if new_size > layout.size() {
+                    counters.on_alloc(new_size - layout.size());
+                } else {
+                    counters.on_dealloc(layout.size() - new_size);
+                }
+            });
+        }
+        new_ptr
+    }
+}
+
+#[cfg(feature = "bench-metrics")]
+mod emit {
+    use metrics::gauge;
+
+    use super::MemoryProfile;
+
+    impl MemoryProfile {
+        pub fn emit(&self) {
+            for (phase, memory) in &self.per_phase {
+                let labels = [("phase", format!("{phase:?}"))];
+                gauge!("phase_allocated_bytes", &labels).set(memory.allocated as f64);
+                gauge!("phase_peak_bytes", &labels).set(memory.peak as f64);
+            }
+        }
+    }
+}
@@ -1,9 +1,12 @@
-use std::fmt::Display;
+use std::{
+    fmt::Display,
+    time::{Duration, Instant},
+};
 
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 
-use super::{hal::ProverBackend, types::DeviceStarkProvingKey};
+use super::{hal::ProverBackend, types::DeviceStarkProvingKey, ProvingOptions};
 use crate::keygen::types::TraceWidth;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -108,6 +111,98 @@ pub fn trace_metrics<PB: ProverBackend>(
     }
 }
 
+/// Time spent in each part of a proof, see
+/// [prove_with_metrics](crate::engine::StarkEngine::prove_with_metrics).
+///
+/// The parts follow the [ProvingPhase](super::ProvingPhase)s of the proof, with the quotient
+/// phase split into evaluation and commitment. The cached main traces that are not pre-committed
+/// are committed before the proof starts, so they are not included.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofMetrics {
+    /// Commitment of the common main traces and of the custom rounds, and the observations of
+    /// the transcript before the challenge phases.
+    pub main_trace_commit: Timing,
+    /// Generation and commitment of the after challenge traces.
+    pub after_challenge_trace: Timing,
+    /// Evaluation of the quotient polynomials.
+    pub quotient_evaluation: Timing,
+    /// Commitment of the quotient polynomials.
+    pub quotient_commit: Timing,
+    /// Polynomial opening proofs.
+    pub opening: Timing,
+    /// The whole proof.
+    pub total: Timing,
+    /// Wall time of the quotient evaluation of each AIR, with the AIR name, in the order of the
+    /// AIRs of the proof. Empty if the prover device does not report it.
+    pub quotient_evaluation_per_air: Vec<(String, Duration)>,
+}
+
+/// Wall and CPU time of a part of a proof.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Timing {
+    pub wall: Duration,
+    /// CPU time of all threads of the process, which includes anything else running in the
+    /// process at the same time. `None` where the CPU time is not available.
+    pub cpu: Option<Duration>,
+}
+
+/// Measures the [Timing] since it was started. Without requested
+/// [metrics](super::ProvingOptions::metrics), it reads no clock and measures nothing.
+pub(crate) struct Stopwatch {
+    /// The wall and CPU times at the start, if metrics are requested.
+    start: Option<(Instant, Option<Duration>)>,
+}
+
+impl Stopwatch {
+    pub fn start(options: &ProvingOptions) -> Self {
+        Self {
+            start: options.metrics.is_some().then(now),
+        }
+    }
+
+    /// The time elapsed since the start, or zero if metrics are not requested.
+    pub fn elapsed(&self) -> Timing {
+        let Some((wall, cpu)) = self.start else {
+            return Timing::default();
+        };
+        Timing {
+            wall: wall.elapsed(),
+            cpu: process_cpu_time()
+                .zip(cpu)
+                .map(|(now, start)| now.saturating_sub(start)),
+        }
+    }
+
+    /// Returns the time elapsed since the start and restarts.
+    pub fn lap(&mut self) -> Timing {
+        let timing = self.elapsed();
+        if let Some(start) = &mut self.start {
+            *start = now();
+        }
+        timing
+    }
+}
+
+fn now() -> (Instant, Option<Duration>) {
+    (Instant::now(), process_cpu_time())
+}
+
+#[cfg(unix)]
+fn process_cpu_time() -> Option<Duration> {
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // SAFETY: `ts` is a valid pointer to a `timespec`.
+    let ret = unsafe { libc::clock_gettime(libc::CLOCK_PROCESS_CPUTIME_ID, &mut ts) };
+    (ret == 0).then(|| Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32))
+}
+
+#[cfg(not(unix))]
+fn process_cpu_time() -> Option<Duration> {
+    None
+}
+
 pub fn format_number_with_underscores(n: usize) -> String {
     let num_str = n.to_string();
     let mut result = String::new();
@@ -6,15 +6,38 @@
 //! Currently includes full prover implementations for:
 //! - CPU
 
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+
 use cpu::{CpuBackend, CpuDevice};
+#[cfg(feature = "mem-metrics")]
+use memory::MemoryProfile;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use self::metrics::ProofMetrics;
+use crate::keygen::ProofInputError;
 
+/// Proving many independent proofs with the same proving key
+pub mod batch;
 /// Host prover implementation that uses custom device kernels
 pub mod coordinator;
 /// CPU implementation of proving backend
 pub mod cpu;
 pub mod hal;
+/// Memory used by each phase of a proof
+#[cfg(feature = "mem-metrics")]
+pub mod memory;
+/// Dedicated thread pools of provers
+pub mod pool;
+/// Proving with traces staged as they are generated
+pub mod staged;
 /// Types used by the prover
 pub mod types;
+/// Views of the traces of a RAP on its quotient domain
+pub mod view;
 
 /// Testing helper
 pub mod helper; // [jpw]: maybe this should be moved to sdk
@@ -39,5 +62,79 @@ pub trait Prover {
     ) -> Self::Proof;
 }
 
-pub type MultiTraceStarkProver<'a, SC> =
-    coordinator::Coordinator<SC, CpuBackend<SC>, CpuDevice<'a, SC>>;
+/// The phases of a proof, in the order they run, see [ProvingOptions::progress].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ProvingPhase {
+    /// Commitment of the common main traces and of the custom rounds. The cached main traces
+    /// that are not pre-committed are committed before this phase, but their memory is counted
+    /// in it.
+    MainTraceCommit,
+    /// Generation and commitment of the after challenge traces, e.g. for log-up.
+    AfterChallengeTrace,
+    /// Evaluation and commitment of the quotient polynomials.
+    Quotient,
+    /// Polynomial opening proofs.
+    Opening,
+}
+
+/// Called with the current phase of a proof and the fraction of the phase done, in `[0, 1]`.
+pub type ProgressCallback = Box<dyn Fn(ProvingPhase, f32) + Send + Sync>;
+
+/// Options of a proof, see
+/// [prove_with_options](crate::engine::StarkEngine::prove_with_options). None of the options
+/// affects the proof.
+#[derive(Default)]
+pub struct ProvingOptions {
+    /// Called with fraction 0 at the start of each phase, and during the [ProvingPhase::Quotient]
+    /// phase after the quotient polynomial of each AIR is evaluated.
+    pub progress: Option<ProgressCallback>,
+    /// Checked each time `progress` would be called. Once set, the prover stops and returns
+    /// [ProvingError::Cancelled].
+    pub cancel: Option<Arc<AtomicBool>>,
+    /// Set to the memory used by each phase at the end of the proof.
+    #[cfg(feature = "mem-metrics")]
+    pub memory_profile: Option<Arc<Mutex<MemoryProfile>>>,
+    /// Set to the time spent in each part of the proof, see [ProofMetrics].
+    pub metrics: Option<Arc<Mutex<ProofMetrics>>>,
+}
+
+impl ProvingOptions {
+    /// Reports that `fraction` of `phase` is done, then returns an error if the proof was
+    /// cancelled.
+    pub fn checkpoint(&self, phase: ProvingPhase, fraction: f32) -> Result<(), ProvingError> {
+        if let Some(progress) = &self.progress {
+            progress(phase, fraction);
+        }
+        match &self.cancel {
+            Some(cancel) if cancel.load(Ordering::Relaxed) => Err(ProvingError::Cancelled),
+            _ => Ok(()),
+        }
+    }
+
+    /// Updates the [metrics](Self::metrics) of the proof with `f`, if they are requested.
+    pub fn record_metrics(&self, f: impl FnOnce(&mut ProofMetrics)) {
+        if let Some(metrics) = &self.metrics {
+            f(&mut metrics.lock().unwrap());
+        }
+    }
+}
+
+/// An error proving, see [ProvingOptions].
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ProvingError {
+    #[error("the proof was cancelled")]
+    Cancelled,
+    /// The proof input does not fit the proving key, see
+    /// [check_proof_input](crate::keygen::types::MultiStarkProvingKey::check_proof_input).
+    #[error(transparent)]
+    InvalidInput(#[from] ProofInputError),
+    /// The key is zero-knowledge, but the engine has no source of randomness for it, see
+    /// [StarkEngine::zero_knowledge](crate::engine::StarkEngine::zero_knowledge).
+    #[error("the engine cannot prove for a zero-knowledge key")]
+    ZeroKnowledgeUnsupported,
+}
+
+/// Prover with the host buffers of the [CpuBackend], whose heavy operations are computed by `PD`,
+/// see [ProverDevice](hal::ProverDevice).
+pub type MultiTraceStarkProver<'a, SC, PD = CpuDevice<'a, SC>> =
+    coordinator::Coordinator<SC, CpuBackend<SC>, PD>;
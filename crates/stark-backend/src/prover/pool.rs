@@ -0,0 +1,85 @@
+//! Dedicated thread pools of provers.
+
+use thiserror::Error;
+
+/// Threads of a dedicated pool a prover runs its parallel sections on, instead of the global
+/// rayon pool, e.g. to keep concurrent provers from interfering or to pin a prover to some cores.
+/// Used by [BatchProver](super::batch::BatchProver::with_config) and
+/// [StarkEngine::prove_on_pool](crate::engine::StarkEngine::prove_on_pool).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProverConfig {
+    /// Number of threads, where 0 means the number of threads of the global rayon pool.
+    pub num_threads: usize,
+    /// Stack size of each thread in bytes, the default of rayon if `None`.
+    pub stack_size: Option<usize>,
+    /// Cores the threads are pinned to: thread `i` is pinned to core
+    /// `pin_cores[i % pin_cores.len()]`. The threads are not pinned if `None`. Pinning requires
+    /// the `pin-cores` feature.
+    pub pin_cores: Option<Vec<usize>>,
+}
+
+/// An error building the thread pool of a [ProverConfig].
+#[derive(Debug, Error)]
+pub enum ProverPoolError {
+    #[error("pin_cores must not be empty")]
+    EmptyPinCores,
+    #[error("pinning threads to cores requires the `pin-cores` feature")]
+    PinningUnsupported,
+    #[cfg(feature = "parallel")]
+    #[error(transparent)]
+    Build(#[from] rayon::ThreadPoolBuildError),
+}
+
+impl ProverConfig {
+    /// Checks that the threads can be pinned as configured.
+    pub fn validate(&self) -> Result<(), ProverPoolError> {
+        match &self.pin_cores {
+            Some(pin_cores) if pin_cores.is_empty() => Err(ProverPoolError::EmptyPinCores),
+            Some(_) if !cfg!(feature = "pin-cores") => Err(ProverPoolError::PinningUnsupported),
+            _ => Ok(()),
+        }
+    }
+
+    /// Builds the thread pool, whose thread `i` is named `{name}-{i}`, after checking the config
+    /// with [validate](Self::validate).
+    ///
+    /// Pinning a thread may fail, e.g. if the core is not available to the process, in which case
+    /// a warning is logged and the thread is not pinned.
+    ///
+    /// With the `mem-metrics` feature, the threads count their allocations into counters of the
+    /// pool, so the memory profiles of the proofs on the pool exclude the allocations of other
+    /// threads, see the [memory](super::memory) module.
+    #[cfg(feature = "parallel")]
+    pub fn build_pool(&self, name: &str) -> Result<rayon::ThreadPool, ProverPoolError> {
+        self.validate()?;
+        let name = name.to_string();
+        let mut builder = rayon::ThreadPoolBuilder::new()
+            .num_threads(self.num_threads)
+            .thread_name(move |i| format!("{name}-{i}"));
+        if let Some(stack_size) = self.stack_size {
+            builder = builder.stack_size(stack_size);
+        }
+        #[cfg(feature = "pin-cores")]
+        let pin_cores = self.pin_cores.clone();
+        #[cfg(feature = "mem-metrics")]
+        let counters = std::sync::Arc::new(super::memory::MemoryCounters::default());
+        builder = builder.start_handler(move |i| {
+            #[cfg(feature = "pin-cores")]
+            if let Some(pin_cores) = &pin_cores {
+                let id = pin_cores[i % pin_cores.len()];
+                if !core_affinity::set_for_current(core_affinity::CoreId { id }) {
+                    tracing::warn!("failed to pin prover thread {i} to core {id}");
+                }
+            }
+            #[cfg(not(feature = "pin-cores"))]
+            let _ = i;
+            #[cfg(feature = "mem-metrics")]
+            super::memory::MemoryCounters::enter(counters.clone());
+        });
+        #[cfg(feature = "mem-metrics")]
+        {
+            builder = builder.exit_handler(|_| super::memory::MemoryCounters::exit());
+        }
+        Ok(builder.build()?)
+    }
+}
@@ -0,0 +1,184 @@
+//! Proving with traces handed to the prover as they are generated.
+
+use std::{
+    iter::zip,
+    mem,
+    panic::{self, AssertUnwindSafe},
+    sync::{Condvar, Mutex, MutexGuard, PoisonError},
+};
+
+use itertools::Itertools;
+use p3_field::PrimeField64;
+
+use crate::{
+    config::{StarkGenericConfig, Val},
+    engine::StarkEngine,
+    keygen::types::{MultiStarkProvingKey, VkFeature},
+    proof::Proof,
+    prover::{
+        hal::TraceCommitter,
+        types::{AirProofInput, ProofInput},
+    },
+};
+
+/// Prover of a proof whose AIR inputs are staged one at a time with
+/// [stage_trace](Self::stage_trace), possibly from several threads while other traces are still
+/// being generated.
+///
+/// The cached main traces of an AIR are committed as soon as the AIR is staged, on the staging
+/// thread, unless the key is zero-knowledge, in which case the prover commits them with the
+/// other traces. The common main traces of all AIRs share a single commitment, so it cannot start
+/// before the last AIR is staged and is computed by [prove](Self::prove). An AIR whose trace
+/// takes long to generate overlaps with the commitments of the others when its trace is a cached
+/// main trace.
+///
+/// Commitments and transcript observations follow the AIR ids whatever the staging order, so the
+/// proof is identical to proving all inputs at once with [StarkEngine::prove].
+pub struct StagedProver<'a, SC: StarkGenericConfig, E> {
+    engine: &'a E,
+    mpk: &'a MultiStarkProvingKey<SC>,
+    /// The AIRs of the proof, in increasing order.
+    air_ids: Vec<usize>,
+    staging: Mutex<Staging<SC>>,
+    all_staged: Condvar,
+}
+
+struct Staging<SC: StarkGenericConfig> {
+    /// The staged input of each AIR of `air_ids`.
+    inputs: Vec<Option<AirProofInput<SC>>>,
+    /// The AIR whose staging panicked, if any, so that [StagedProver::prove] does not wait for
+    /// it forever.
+    panicked: Option<usize>,
+}
+
+impl<'a, SC, E> StagedProver<'a, SC, E>
+where
+    SC: StarkGenericConfig,
+    Val<SC>: PrimeField64,
+    E: StarkEngine<SC>,
+{
+    /// Creates a prover of a proof with `mpk` for the AIRs `air_ids`, in any order.
+    ///
+    /// # Panics
+    /// If an AIR id is not an AIR of `mpk` or is repeated.
+    pub fn new(
+        engine: &'a E,
+        mpk: &'a MultiStarkProvingKey<SC>,
+        air_ids: impl IntoIterator<Item = usize>,
+    ) -> Self {
+        let air_ids = air_ids.into_iter().sorted().collect_vec();
+        if let Some(&air_id) = air_ids.last() {
+            assert!(
+                air_id < mpk.per_air.len(),
+                "air_id = {air_id} is not in the key"
+            );
+        }
+        if let Some((air_id, _)) = air_ids.iter().tuple_windows().find(|(a, b)| a == b) {
+            panic!("air_id = {air_id} is repeated");
+        }
+        Self {
+            engine,
+            mpk,
+            staging: Mutex::new(Staging {
+                inputs: (0..air_ids.len()).map(|_| None).collect(),
+                panicked: None,
+            }),
+            air_ids,
+            all_staged: Condvar::new(),
+        }
+    }
+
+    /// Stages the input of the AIR `air_id`, first committing its cached main traces unless they
+    /// are pre-committed or the key is zero-knowledge. May be called from several threads at
+    /// once, in any order.
+    ///
+    /// If committing the cached main traces panics, the panic is propagated to the caller and to
+    /// [prove](Self::prove).
+    ///
+    /// # Panics
+    /// If `air_id` is not an AIR of the proof, or was already staged.
+    pub fn stage_trace(&self, air_id: usize, mut input: AirProofInput<SC>) {
+        let index = self
+            .air_ids
+            .binary_search(&air_id)
+            .unwrap_or_else(|_| panic!("air_id = {air_id} is not an AIR of the proof"));
+        // The traces of a zero-knowledge key are randomized when the prover commits them.
+        if input.cached_mains_pdata.len() != input.raw.cached_mains.len()
+            && !self.mpk.features.contains(VkFeature::ZeroKnowledge)
+        {
+            let committed = panic::catch_unwind(AssertUnwindSafe(|| {
+                let prover = self.engine.prover();
+                input
+                    .raw
+                    .cached_mains
+                    .iter()
+                    .map(|trace| {
+                        let (commitment, data) = prover.device.commit(&[trace.clone()]);
+                        (commitment, data.data)
+                    })
+                    .collect()
+            }));
+            match committed {
+                Ok(pdata) => input.cached_mains_pdata = pdata,
+                Err(payload) => {
+                    let mut staging = self.lock_staging();
+                    staging.panicked = Some(air_id);
+                    drop(staging);
+                    self.all_staged.notify_all();
+                    panic::resume_unwind(payload);
+                }
+            }
+        }
+        let mut staging = self.lock_staging();
+        if staging.inputs[index].is_some() {
+            drop(staging);
+            panic!(
+                "AIR {} (air_id = {air_id}) is already staged",
+                self.mpk.per_air[air_id].air_name
+            );
+        }
+        staging.inputs[index] = Some(input);
+        if staging.inputs.iter().all(Option::is_some) {
+            self.all_staged.notify_all();
+        }
+    }
+
+    /// Waits until every AIR of the proof is staged, then proves. The staged inputs are consumed,
+    /// so the AIRs of another proof may be staged afterwards.
+    ///
+    /// # Panics
+    /// If the staging of an AIR panicked, in which case the staged inputs are discarded, or under
+    /// the same conditions as [StarkEngine::prove].
+    pub fn prove(&self) -> Proof<SC> {
+        let mut staging = self
+            .all_staged
+            .wait_while(self.lock_staging(), |staging| {
+                staging.panicked.is_none() && staging.inputs.iter().any(Option::is_none)
+            })
+            .unwrap_or_else(PoisonError::into_inner);
+        let inputs = mem::replace(
+            &mut staging.inputs,
+            (0..self.air_ids.len()).map(|_| None).collect(),
+        );
+        if let Some(air_id) = staging.panicked.take() {
+            drop(staging);
+            panic!(
+                "staging AIR {} (air_id = {air_id}) panicked",
+                self.mpk.per_air[air_id].air_name
+            );
+        }
+        drop(staging);
+        let per_air = zip(
+            self.air_ids.iter().copied(),
+            inputs.into_iter().map(Option::unwrap),
+        )
+        .collect();
+        self.engine.prove(self.mpk, ProofInput::new(per_air))
+    }
+
+    /// The staging state. The lock is never held while panicking, but a poisoned lock is still
+    /// usable since the state is updated at once.
+    fn lock_staging(&self) -> MutexGuard<'_, Staging<SC>> {
+        self.staging.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
@@ -8,20 +8,43 @@ use serde::{Deserialize, Serialize};
 use super::hal::ProverBackend;
 use crate::{
     config::{Com, PcsProof, PcsProverData, RapPhaseSeqPartialProof, StarkGenericConfig, Val},
-    keygen::types::StarkVerifyingKey,
-    proof::{AirProofData, Commitments, OpeningProof, Proof},
+    interaction::public_lookup::PublicLookupTable,
+    keygen::types::{CommitmentRound, MatrixOrdering, StarkVerifyingKey, VkFeatureSet},
+    proof::{AirProofData, Commitments, OpeningProof, Proof, ProofCore},
 };
 
 /// A view of the proving key after it has been transferred to device.
 pub struct DeviceMultiStarkProvingKey<'a, PB: ProverBackend> {
     pub(super) air_ids: Vec<usize>,
     pub per_air: Vec<DeviceStarkProvingKey<'a, PB>>,
+    /// The commitment rounds, in the order their commitments are observed.
+    pub commitment_rounds: &'a [CommitmentRound],
+    /// Order of the matrices within the common main and custom rounds.
+    pub matrix_ordering: MatrixOrdering,
+    /// The public lookup tables, which do not belong to any AIR.
+    pub public_lookup_tables: &'a [PublicLookupTable<PB::Val>],
+    /// The non-default features the key relies on.
+    pub features: VkFeatureSet,
 }
 
 impl<'a, PB: ProverBackend> DeviceMultiStarkProvingKey<'a, PB> {
-    pub fn new(air_ids: Vec<usize>, per_air: Vec<DeviceStarkProvingKey<'a, PB>>) -> Self {
+    pub fn new(
+        air_ids: Vec<usize>,
+        per_air: Vec<DeviceStarkProvingKey<'a, PB>>,
+        commitment_rounds: &'a [CommitmentRound],
+        matrix_ordering: MatrixOrdering,
+        public_lookup_tables: &'a [PublicLookupTable<PB::Val>],
+        features: VkFeatureSet,
+    ) -> Self {
         assert_eq!(air_ids.len(), per_air.len());
-        Self { air_ids, per_air }
+        Self {
+            air_ids,
+            per_air,
+            commitment_rounds,
+            matrix_ordering,
+            public_lookup_tables,
+            features,
+        }
     }
 }
 
@@ -47,10 +70,25 @@ pub struct SingleCommitPreimage<Matrix, PcsData> {
     pub matrix_idx: u32,
 }
 
+impl<Matrix, PcsData: Clone> SingleCommitPreimage<Matrix, PcsData> {
+    /// The preimage without the trace, only referring to the committed data, for uses after the
+    /// trace itself is dropped.
+    pub fn without_trace(&self) -> SingleCommitPreimage<(), PcsData> {
+        SingleCommitPreimage {
+            trace: (),
+            data: self.data.clone(),
+            matrix_idx: self.matrix_idx,
+        }
+    }
+}
+
 #[derive(derive_new::new)]
 pub struct ProvingContext<'a, PB: ProverBackend> {
     /// (AIR id, AIR input)
     pub per_air: Vec<(usize, AirProvingContext<'a, PB>)>,
+    /// Sorted ids of the AIRs of the key that are absent from the proof.
+    #[new(default)]
+    pub absent_air_ids: Vec<usize>,
 }
 
 impl<'a, PB: ProverBackend> ProvingContext<'a, PB> {
@@ -84,6 +122,8 @@ pub struct AirProvingContext<'a, PB: ProverBackend> {
     )>,
     /// Common main trace matrix
     pub common_main: Option<PB::Matrix>,
+    /// Matrices of the custom commitment rounds of the AIR, ordered by custom round index.
+    pub custom_mains: Vec<PB::Matrix>,
     /// Public values
     // [jpw] This is on host for now because it seems more convenient for the challenger to be on host.
     pub public_values: Vec<PB::Val>,
@@ -105,6 +145,8 @@ pub struct PairView<T, Val> {
     pub partitioned_main: Vec<T>,
     /// Public values
     pub public_values: Vec<Val>,
+    /// The sequence repeated by each periodic column
+    pub periodic_columns: Vec<Vec<Val>>,
 }
 
 /// The full RAP trace consists of horizontal concatenation of multiple matrices of the same height:
@@ -176,6 +218,8 @@ pub struct HalProof<PB: ProverBackend> {
     pub per_air: Vec<AirProofData<PB::Val, PB::Challenge>>,
     /// Partial proof for rap phase if it exists
     pub rap_partial_proof: PB::RapPartialProof,
+    /// Sorted ids of the AIRs of the key that are absent from the proof
+    pub absent_air_ids: Vec<usize>,
 }
 
 impl<PB, SC: StarkGenericConfig> From<HalProof<PB>> for Proof<SC>
@@ -185,10 +229,15 @@ where
     PB::RapPartialProof: Into<Option<RapPhaseSeqPartialProof<SC>>>,
 {
     fn from(proof: HalProof<PB>) -> Self {
+        let opening: OpeningProof<_, _> = proof.opening.into();
         Proof {
             commitments: proof.commitments,
-            opening: proof.opening.into(),
-            per_air: proof.per_air,
+            core: ProofCore {
+                per_air: proof.per_air,
+                opened_values: opening.values,
+                absent_air_ids: proof.absent_air_ids,
+            },
+            pcs_proof: opening.proof,
             rap_phase_seq_proof: proof.rap_partial_proof.into(),
         }
     }
@@ -201,6 +250,33 @@ where
 pub struct ProofInput<SC: StarkGenericConfig> {
     /// (AIR id, AIR input)
     pub per_air: Vec<(usize, AirProofInput<SC>)>,
+    /// AIRs of the key that are absent from the proof: they have no trace, no after challenge
+    /// traces and no quotient, and their absence is recorded in the proof. Each must be allowed
+    /// to be absent, see
+    /// [set_may_be_absent](crate::keygen::MultiStarkKeygenBuilder::set_may_be_absent).
+    #[new(default)]
+    pub absent_air_ids: Vec<usize>,
+    /// Commitments observed at the start of the transcript, e.g. of the previous proof of a
+    /// chain, see [observe_transcript_prefix]. The prefix is not recorded in the proof, and the
+    /// verifier must be given the same prefix.
+    ///
+    /// [observe_transcript_prefix]: crate::verifier::observe_transcript_prefix
+    #[new(default)]
+    pub transcript_prefix: Vec<Com<SC>>,
+}
+
+impl<SC: StarkGenericConfig> ProofInput<SC> {
+    /// Marks the AIRs `air_ids` as absent, see [absent_air_ids](Self::absent_air_ids).
+    pub fn with_absent_airs(mut self, air_ids: impl IntoIterator<Item = usize>) -> Self {
+        self.absent_air_ids.extend(air_ids);
+        self
+    }
+
+    /// Appends `prefix` to the [transcript_prefix](Self::transcript_prefix).
+    pub fn with_transcript_prefix(mut self, prefix: impl IntoIterator<Item = Com<SC>>) -> Self {
+        self.transcript_prefix.extend(prefix);
+        self
+    }
 }
 
 #[derive(Serialize, Deserialize, Derivative)]
@@ -231,6 +307,31 @@ pub struct AirProofInput<SC: StarkGenericConfig> {
     pub raw: AirProofRawInput<Val<SC>>,
 }
 
+impl<SC: StarkGenericConfig> AirProofInput<SC> {
+    /// Input of an AIR whose cached main traces were committed beforehand, e.g. with
+    /// [StarkEngine::commit_trace](crate::engine::StarkEngine::commit_trace). The prover reuses
+    /// their committed data and only observes their commitments.
+    pub fn with_cached_mains(
+        cached_mains: Vec<CommittedTraceData<SC>>,
+        common_main: Option<RowMajorMatrix<Val<SC>>>,
+        public_values: Vec<Val<SC>>,
+    ) -> Self {
+        let (cached_mains_pdata, cached_mains) = cached_mains
+            .into_iter()
+            .map(|data| ((data.commitment, data.pcs_data), data.trace))
+            .unzip();
+        Self {
+            cached_mains_pdata,
+            raw: AirProofRawInput {
+                cached_mains,
+                common_main,
+                custom_mains: vec![],
+                public_values,
+            },
+        }
+    }
+}
+
 /// Raw input for proving a single AIR.
 #[derive(Clone, Debug)]
 pub struct AirProofRawInput<F: Field> {
@@ -238,6 +339,8 @@ pub struct AirProofRawInput<F: Field> {
     pub cached_mains: Vec<Arc<RowMajorMatrix<F>>>,
     /// Common main trace matrix
     pub common_main: Option<RowMajorMatrix<F>>,
+    /// Matrices of the custom commitment rounds of the AIR, ordered by custom round index.
+    pub custom_mains: Vec<Arc<RowMajorMatrix<F>>>,
     /// Public values
     pub public_values: Vec<F>,
 }
@@ -0,0 +1,283 @@
+//! Views of the traces of a RAP on its quotient domain, checked against the verifying key of the
+//! AIR before the quotient polynomial is evaluated.
+
+use std::fmt;
+
+use p3_commit::Pcs;
+use p3_field::{ExtensionField, Field, FieldExtensionAlgebra};
+use p3_matrix::Matrix;
+use thiserror::Error;
+
+use super::types::{PairView, RapSinglePhaseView, RapView};
+use crate::{
+    config::{Domain, PcsProverData, StarkGenericConfig},
+    keygen::types::StarkVerifyingKey,
+};
+
+/// Evaluations of a trace on the quotient domain of its RAP, in the order the quotient committer
+/// expects: the PCS stores the LDE of the trace with its rows in bit-reversed order, and row `i`
+/// of the view is the evaluation at the `i`-th point of the quotient domain, read by reversing
+/// the bits of the row index.
+///
+/// The view can only be obtained from the PCS with [on_quotient_domain](Self::on_quotient_domain),
+/// so that a trace evaluated on its trace domain, or in another row order, cannot be mistaken
+/// for it.
+pub struct BitReversed<M>(M);
+
+impl<M> BitReversed<M> {
+    /// The evaluations of matrix `idx` of the committed `data` on `quotient_domain`, see
+    /// [Pcs::get_evaluations_on_domain]. The view borrows the LDE of `data` without allocating.
+    pub fn on_quotient_domain<'a, SC>(
+        pcs: &SC::Pcs,
+        data: &'a PcsProverData<SC>,
+        idx: usize,
+        quotient_domain: Domain<SC>,
+    ) -> Self
+    where
+        SC: StarkGenericConfig,
+        SC::Pcs: Pcs<SC::Challenge, SC::Challenger, EvaluationsOnDomain<'a> = M>,
+    {
+        Self(pcs.get_evaluations_on_domain(data, idx, quotient_domain))
+    }
+
+    pub fn inner(&self) -> &M {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> M {
+        self.0
+    }
+}
+
+/// A matrix of a [RapView].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RapViewMatrix {
+    Preprocessed,
+    /// The main trace partition with this index: the cached main traces, then the common main
+    /// trace, then the matrices of the custom rounds.
+    Main(usize),
+    /// The after challenge trace of this challenge phase.
+    AfterChallenge(usize),
+}
+
+impl fmt::Display for RapViewMatrix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Preprocessed => write!(f, "preprocessed trace"),
+            Self::Main(index) => write!(f, "main trace {index}"),
+            Self::AfterChallenge(phase) => write!(f, "after challenge trace of phase {phase}"),
+        }
+    }
+}
+
+/// A [RapView] that does not fit the verifying key of its AIR, see [RapViewBuilder::build].
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("RAP view of AIR {air_name}: {kind}")]
+pub struct RapViewError {
+    pub air_name: String,
+    pub kind: RapViewErrorKind,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum RapViewErrorKind {
+    #[error("{0} is missing")]
+    Missing(RapViewMatrix),
+    #[error("{0} is given, but the AIR has none")]
+    Unexpected(RapViewMatrix),
+    #[error("{matrix} has width {width}, but the AIR expects {expected}")]
+    Width {
+        matrix: RapViewMatrix,
+        width: usize,
+        expected: usize,
+    },
+    #[error("{matrix} has height {height}, but the quotient domain has size {expected}")]
+    Height {
+        matrix: RapViewMatrix,
+        height: usize,
+        expected: usize,
+    },
+    #[error("{count} public values are given, but the AIR expects {expected}")]
+    PublicValues { count: usize, expected: usize },
+    #[error("{count} challenges are given in phase {phase}, but the AIR uses {expected}")]
+    Challenges {
+        phase: usize,
+        count: usize,
+        expected: usize,
+    },
+    #[error("{count} exposed values are given in phase {phase}, but the AIR expects {expected}")]
+    ExposedValues {
+        phase: usize,
+        count: usize,
+        expected: usize,
+    },
+}
+
+/// Builder of the [RapView] of an AIR on its quotient domain, whose matrices, public values,
+/// challenges and exposed values are checked against the verifying key of the AIR by
+/// [build](Self::build).
+pub struct RapViewBuilder<'a, M, Val, Challenge, Com> {
+    vk: &'a StarkVerifyingKey<Val, Com>,
+    log_trace_height: u8,
+    preprocessed: Option<BitReversed<M>>,
+    partitioned_main: Vec<BitReversed<M>>,
+    public_values: Vec<Val>,
+    per_phase: Vec<RapSinglePhaseView<BitReversed<M>, Challenge>>,
+}
+
+impl<'a, M, Val, Challenge, Com> RapViewBuilder<'a, M, Val, Challenge, Com>
+where
+    M: Matrix<Val>,
+    Val: Field,
+    Challenge: ExtensionField<Val>,
+{
+    /// Starts the view of the AIR of `vk` with a trace of height `2^log_trace_height`.
+    pub fn new(vk: &'a StarkVerifyingKey<Val, Com>, log_trace_height: u8) -> Self {
+        Self {
+            vk,
+            log_trace_height,
+            preprocessed: None,
+            partitioned_main: vec![],
+            public_values: vec![],
+            per_phase: vec![],
+        }
+    }
+
+    pub fn preprocessed(mut self, trace: BitReversed<M>) -> Self {
+        self.preprocessed = Some(trace);
+        self
+    }
+
+    /// Adds the next main trace partition, see [RapViewMatrix::Main].
+    pub fn main(mut self, trace: BitReversed<M>) -> Self {
+        self.partitioned_main.push(trace);
+        self
+    }
+
+    pub fn public_values(mut self, public_values: Vec<Val>) -> Self {
+        self.public_values = public_values;
+        self
+    }
+
+    /// Sets the after challenge trace of challenge phase `phase`, with the challenges sampled in
+    /// the phase and the values exposed after it. The phases before it that are not set have no
+    /// trace, which [build](Self::build) reports if the AIR has one.
+    pub fn phase(
+        mut self,
+        phase: usize,
+        trace: BitReversed<M>,
+        challenges: Vec<Challenge>,
+        exposed_values: Vec<Challenge>,
+    ) -> Self {
+        if self.per_phase.len() <= phase {
+            self.per_phase
+                .resize_with(phase + 1, RapSinglePhaseView::default);
+        }
+        self.per_phase[phase] = RapSinglePhaseView {
+            inner: Some(trace),
+            challenges,
+            exposed_values,
+        };
+        self
+    }
+
+    /// Checks that the view has the traces of the AIR, with their widths and the height of the
+    /// quotient domain, the number of public values, at least the number of challenges the AIR
+    /// uses in each phase, and the number of exposed values of each phase.
+    pub fn build(self) -> Result<RapView<BitReversed<M>, Val, Challenge>, RapViewError> {
+        let vk = self.vk;
+        let err = |kind| RapViewError {
+            air_name: vk.air_name.clone(),
+            kind,
+        };
+        let params = &vk.params;
+        let height = (1usize << self.log_trace_height) * vk.quotient_degree as usize;
+        let check = |matrix, trace: Option<&BitReversed<M>>, expected: Option<usize>| {
+            let trace = trace.map(BitReversed::inner);
+            match (trace, expected) {
+                (None, None) => Ok(()),
+                (None, Some(_)) => Err(err(RapViewErrorKind::Missing(matrix))),
+                (Some(_), None) => Err(err(RapViewErrorKind::Unexpected(matrix))),
+                (Some(trace), Some(expected)) if trace.width() != expected => {
+                    Err(err(RapViewErrorKind::Width {
+                        matrix,
+                        width: trace.width(),
+                        expected,
+                    }))
+                }
+                (Some(trace), Some(_)) if trace.height() != height => {
+                    Err(err(RapViewErrorKind::Height {
+                        matrix,
+                        height: trace.height(),
+                        expected: height,
+                    }))
+                }
+                (Some(_), Some(_)) => Ok(()),
+            }
+        };
+
+        check(
+            RapViewMatrix::Preprocessed,
+            self.preprocessed.as_ref(),
+            params.width.preprocessed,
+        )?;
+        let mut main_widths = params.width.main_widths();
+        main_widths.extend(params.width.custom.iter().map(|&(_, width)| width));
+        for index in 0..main_widths.len().max(self.partitioned_main.len()) {
+            check(
+                RapViewMatrix::Main(index),
+                self.partitioned_main.get(index),
+                main_widths.get(index).copied(),
+            )?;
+        }
+        let ext_degree = <Challenge as FieldExtensionAlgebra<Val>>::D;
+        for phase in 0..params.width.after_challenge.len().max(self.per_phase.len()) {
+            let view = self.per_phase.get(phase);
+            check(
+                RapViewMatrix::AfterChallenge(phase),
+                view.and_then(|view| view.inner.as_ref()),
+                params
+                    .width
+                    .after_challenge
+                    .get(phase)
+                    .map(|width| width * ext_degree),
+            )?;
+            // A phase without a trace uses no challenges and exposes no values.
+            let Some(view) = view.filter(|view| view.inner.is_some()) else {
+                continue;
+            };
+            let expected = params.num_challenges_to_sample[phase];
+            if view.challenges.len() < expected {
+                return Err(err(RapViewErrorKind::Challenges {
+                    phase,
+                    count: view.challenges.len(),
+                    expected,
+                }));
+            }
+            let expected = params.num_exposed_values_after_challenge[phase];
+            if view.exposed_values.len() != expected {
+                return Err(err(RapViewErrorKind::ExposedValues {
+                    phase,
+                    count: view.exposed_values.len(),
+                    expected,
+                }));
+            }
+        }
+        if self.public_values.len() != params.num_public_values {
+            return Err(err(RapViewErrorKind::PublicValues {
+                count: self.public_values.len(),
+                expected: params.num_public_values,
+            }));
+        }
+
+        Ok(RapView {
+            pair: PairView {
+                log_trace_height: self.log_trace_height,
+                preprocessed: self.preprocessed,
+                partitioned_main: self.partitioned_main,
+                public_values: self.public_values,
+                periodic_columns: vk.periodic_columns.clone(),
+            },
+            per_phase: self.per_phase,
+        })
+    }
+}
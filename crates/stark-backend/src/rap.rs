@@ -7,10 +7,12 @@ use std::{
 };
 
 use p3_air::{BaseAir, PermutationAirBuilder};
+use p3_matrix::dense::RowMajorMatrix;
 
 use crate::{
     air_builders::{debug::DebugConstraintBuilder, symbolic::SymbolicRapBuilder},
     config::{StarkGenericConfig, Val},
+    keygen::types::{AllowedValues, PreprocessedShape},
 };
 
 /// An AIR with 0 or more public values.
@@ -19,6 +21,27 @@ pub trait BaseAirWithPublicValues<F>: BaseAir<F> {
     fn num_public_values(&self) -> usize {
         0
     }
+
+    /// Configuration public values select a per-proof configuration of the AIR, such as a
+    /// number of rounds, that the constraints were only analyzed for a few values of.
+    ///
+    /// Returns, for each configuration public value, its index in the public values and its
+    /// allowed values. The allowed values are recorded in the verifying key, and the prover and
+    /// verifier reject proofs with any other value.
+    fn configuration_public_values(&self) -> Vec<(usize, AllowedValues)> {
+        vec![]
+    }
+
+    /// Optional token identifying the version of the preprocessed trace generation.
+    ///
+    /// The token is stored in the proving key at keygen. When the token of the AIR differs
+    /// from the stored one, the preprocessed trace is re-derived and compared against the
+    /// proving key before proving. See [MultiStarkProvingKey::check_preprocessed_consistency].
+    ///
+    /// [MultiStarkProvingKey::check_preprocessed_consistency]: crate::keygen::types::MultiStarkProvingKey::check_preprocessed_consistency
+    fn preprocessed_version(&self) -> Option<u64> {
+        None
+    }
 }
 
 /// An AIR with 1 or more main trace partitions.
@@ -31,6 +54,50 @@ pub trait PartitionedBaseAir<F>: BaseAir<F> {
     fn common_main_width(&self) -> usize {
         self.width()
     }
+    /// The largest row offset the AIR constraints may reference in the preprocessed and main
+    /// traces. The trace matrices given to the AIR builder contain the rows at offsets
+    /// `0..=max_rotation()` from the current row, wrapping around the trace.
+    ///
+    /// Traces of any power of two height are supported, including 1 and 2. On a trace of height
+    /// 1, the only row is both the first and the last row, so transition constraints do not
+    /// apply, and every rotation is the current row: a constraint without a selector applies with
+    /// `next == local`. On a trace of height 2, the next row of the last row is the first row.
+    ///
+    /// By default, an AIR only references the current and next rows.
+    fn max_rotation(&self) -> usize {
+        1
+    }
+    /// Periodic columns of the AIR, each given by the sequence it repeats. The length of a
+    /// sequence is its period, which must be a power of two dividing the trace height.
+    ///
+    /// Periodic columns are not committed: the sequences are stored in the verifying key, and
+    /// the prover and verifier evaluate the columns themselves. The AIR constraints access the
+    /// values at the current row via [PeriodicAirBuilder](crate::air_builders::PeriodicAirBuilder).
+    ///
+    /// By default, an AIR has no periodic columns.
+    fn periodic_columns(&self) -> Vec<Vec<F>> {
+        vec![]
+    }
+    /// Declares a preprocessed trace that depends on the trace height, instead of the single
+    /// [preprocessed_trace](BaseAir::preprocessed_trace). Keygen only records the declared
+    /// width, and the trace of each height is generated by
+    /// [preprocessed_trace_for_log_height](Self::preprocessed_trace_for_log_height) and committed
+    /// the first time a proof at that height is requested. The verifying key holds the
+    /// commitment of every committed height.
+    ///
+    /// See [MultiStarkProvingKey::prepare_preprocessed_heights].
+    ///
+    /// By default, an AIR does not declare a preprocessed shape.
+    ///
+    /// [MultiStarkProvingKey::prepare_preprocessed_heights]: crate::keygen::types::MultiStarkProvingKey::prepare_preprocessed_heights
+    fn preprocessed_shape(&self) -> Option<PreprocessedShape> {
+        None
+    }
+    /// The preprocessed trace with `2^log_height` rows, for each log height of the
+    /// [preprocessed_shape](Self::preprocessed_shape).
+    fn preprocessed_trace_for_log_height(&self, _log_height: usize) -> Option<RowMajorMatrix<F>> {
+        None
+    }
 }
 
 /// An AIR that works with a particular `AirBuilder` which allows preprocessing
@@ -62,6 +129,20 @@ pub trait PermutationAirBuilderWithExposedValues: PermutationAirBuilder {
     fn permutation_exposed_values(&self) -> &[Self::VarEF];
 }
 
+/// AIR builder with the traces, challenges and exposed values of every challenge phase.
+///
+/// The accessors of [PermutationAirBuilder] and [PermutationAirBuilderWithExposedValues] are
+/// those of phase `0`. Phase `i` draws its challenges after the after challenge trace of phase
+/// `i - 1` is committed, so its constraints may use the challenges of all earlier phases.
+pub trait MultiPhaseAirBuilder: PermutationAirBuilderWithExposedValues {
+    /// The after challenge trace of challenge phase `phase`.
+    fn phase_trace(&self, phase: usize) -> Self::MP;
+    /// The challenges sampled in challenge phase `phase`.
+    fn phase_challenges(&self, phase: usize) -> &[Self::RandomVar];
+    /// The values exposed after challenge phase `phase`.
+    fn phase_exposed_values(&self, phase: usize) -> &[Self::VarEF];
+}
+
 /// Shared reference to any Interactive Air.
 /// This type is the main interface for keygen.
 pub type AirRef<SC> = Arc<dyn AnyRap<SC>>;
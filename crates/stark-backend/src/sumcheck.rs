@@ -11,6 +11,7 @@ use std::iter::zip;
 use itertools::Itertools;
 use p3_challenger::FieldChallenger;
 use p3_field::Field;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 use crate::poly::{multi::MultivariatePolyOracle, uni::UnivariatePolynomial};
@@ -180,7 +181,7 @@ pub fn partially_verify<F: Field>(
     Ok((assignment, claim))
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SumcheckProof<F> {
     pub round_polys: Vec<UnivariatePolynomial<F>>,
 }
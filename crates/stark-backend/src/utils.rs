@@ -1,7 +1,8 @@
 use std::borrow::Cow;
 
 use cfg_if::cfg_if;
-use p3_field::Field;
+use p3_commit::PolynomialSpace;
+use p3_field::{ExtensionField, Field};
 use tracing::instrument;
 
 use crate::air_builders::debug::USE_DEBUG_BUILDER;
@@ -34,6 +35,15 @@ pub fn batch_multiplicative_inverse_allowing_zero<F: Field>(values: Vec<F>) -> V
     result
 }
 
+/// Returns `g^rotation * point`, where `g` is the generator of the trace `domain`.
+pub fn rotate_point<D, Ext>(domain: &D, point: Ext, rotation: usize) -> Ext
+where
+    D: PolynomialSpace,
+    Ext: ExtensionField<D::Val>,
+{
+    (0..rotation).fold(point, |point, _| domain.next_point(point).unwrap())
+}
+
 /// Disables the debug builder so there are not debug assert panics.
 /// Commonly used in negative tests to prevent panics.
 pub fn disable_debug_builder() {
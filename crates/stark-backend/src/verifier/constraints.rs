@@ -1,35 +1,43 @@
-use std::marker::PhantomData;
+use std::{iter::zip, marker::PhantomData};
 
 use itertools::Itertools;
 use p3_commit::PolynomialSpace;
 use p3_field::{Field, FieldAlgebra, FieldExtensionAlgebra};
-use p3_matrix::{dense::RowMajorMatrixView, stack::VerticalPair};
+use p3_matrix::dense::RowMajorMatrix;
 use tracing::instrument;
 
 use super::{
-    error::VerificationError,
+    error::{ProofShapeError, VerificationError},
     folder::{GenericVerifierConstraintFolder, VerifierConstraintFolder},
 };
 use crate::{
     air_builders::symbolic::SymbolicExpressionDag,
     config::{Domain, StarkGenericConfig, Val},
+    poly::periodic::PeriodicColumn,
     proof::AdjacentOpenedValues,
 };
 
 #[allow(clippy::too_many_arguments)]
 #[instrument(skip_all, level = "trace")]
 pub fn verify_single_rap_constraints<SC>(
+    // The id and name of the AIR, for errors
+    air_id: usize,
+    air_name: &str,
     constraints: &SymbolicExpressionDag<Val<SC>>,
+    extra_rotations: &[usize],
     preprocessed_values: Option<&AdjacentOpenedValues<SC::Challenge>>,
     partitioned_main_values: Vec<&AdjacentOpenedValues<SC::Challenge>>,
     after_challenge_values: Vec<&AdjacentOpenedValues<SC::Challenge>>,
     quotient_chunks: &[Vec<SC::Challenge>],
+    // Opening of the random mask added to the quotient in zero-knowledge mode, flattened
+    quotient_mask: Option<&[SC::Challenge]>,
     domain: Domain<SC>, // trace domain
     qc_domains: &[Domain<SC>],
     zeta: SC::Challenge,
     alpha: SC::Challenge,
     challenges: &[Vec<SC::Challenge>],
     public_values: &[Val<SC>],
+    periodic_columns: &[Vec<Val<SC>>],
     exposed_values_after_challenge: &[Vec<SC::Challenge>],
 ) -> Result<(), VerificationError>
 where
@@ -61,6 +69,13 @@ where
                 .sum::<SC::Challenge>()
         })
         .sum::<SC::Challenge>();
+    let quotient = quotient
+        - quotient_mask.map_or(SC::Challenge::ZERO, |mask| {
+            mask.iter()
+                .enumerate()
+                .map(|(e_i, &c)| SC::Challenge::monomial(e_i) * c)
+                .sum::<SC::Challenge>()
+        });
 
     let unflatten = |v: &[SC::Challenge]| {
         v.chunks_exact(SC::Challenge::D)
@@ -76,47 +91,76 @@ where
 
     let sels = domain.selectors_at_point(zeta);
 
-    let (preprocessed_local, preprocessed_next) = preprocessed_values
-        .as_ref()
-        .map(|values| (values.local.as_slice(), values.next.as_slice()))
-        .unwrap_or((&[], &[]));
-    let preprocessed = VerticalPair::new(
-        RowMajorMatrixView::new_row(preprocessed_local),
-        RowMajorMatrixView::new_row(preprocessed_next),
-    );
+    // Row `i` of each window holds the values opened at rotation `i`. Rows at rotations that
+    // were not opened are never referenced by the constraints and are left as zero.
+    let window_height = extra_rotations.last().map_or(2, |&rotation| rotation + 1);
+    let ragged = |num_rotations| ProofShapeError::RaggedOpenedValues {
+        air_id,
+        air_name: air_name.to_string(),
+        num_rotations,
+    };
+    let to_window = |values: &AdjacentOpenedValues<SC::Challenge>| {
+        let width = values.local.len();
+        if values.next.len() != width
+            || values.rotated.len() != extra_rotations.len()
+            || values.rotated.iter().any(|row| row.len() != width)
+        {
+            return Err(ragged(extra_rotations.len()));
+        }
+        let mut window = vec![SC::Challenge::ZERO; window_height * width];
+        let rows = [(0, &values.local), (1, &values.next)]
+            .into_iter()
+            .chain(zip(extra_rotations.iter().copied(), &values.rotated));
+        for (rotation, row) in rows {
+            window[rotation * width..(rotation + 1) * width].copy_from_slice(row);
+        }
+        Ok(RowMajorMatrix::new(window, width))
+    };
 
+    let preprocessed = preprocessed_values
+        .map(to_window)
+        .transpose()?
+        .unwrap_or_else(|| RowMajorMatrix::new(vec![], 0));
     let partitioned_main: Vec<_> = partitioned_main_values
         .into_iter()
-        .map(|values| {
-            VerticalPair::new(
-                RowMajorMatrixView::new_row(&values.local),
-                RowMajorMatrixView::new_row(&values.next),
-            )
-        })
-        .collect();
+        .map(to_window)
+        .try_collect()?;
 
-    let after_challenge_ext_values: Vec<_> = after_challenge_values
+    // After challenge traces are only opened at the local and next rows.
+    let after_challenge: Vec<_> = after_challenge_values
         .into_iter()
         .map(|values| {
+            if values.next.len() != values.local.len() || !values.rotated.is_empty() {
+                return Err(ragged(0));
+            }
             let [local, next] = [&values.local, &values.next]
                 .map(|flattened_ext_values| unflatten(flattened_ext_values));
-            (local, next)
+            let width = local.len();
+            Ok(RowMajorMatrix::new([local, next].concat(), width))
         })
-        .collect();
-    let after_challenge = after_challenge_ext_values
+        .try_collect()?;
+
+    // Periodic columns are not committed, so the verifier evaluates them at `zeta` itself.
+    let periodic_values: Vec<_> = periodic_columns
         .iter()
-        .map(|(local, next)| {
-            VerticalPair::new(
-                RowMajorMatrixView::new_row(local),
-                RowMajorMatrixView::new_row(next),
-            )
+        .enumerate()
+        .map(|(column, values)| {
+            PeriodicColumn::new(values, &domain)
+                .map(|column| column.evaluate(zeta))
+                .ok_or_else(|| ProofShapeError::PeriodicColumn {
+                    air_id,
+                    air_name: air_name.to_string(),
+                    column,
+                    period: values.len(),
+                    height: domain.size(),
+                })
         })
-        .collect();
+        .try_collect()?;
 
     let mut folder: VerifierConstraintFolder<'_, SC> = GenericVerifierConstraintFolder {
-        preprocessed,
-        partitioned_main,
-        after_challenge,
+        preprocessed: preprocessed.as_view(),
+        partitioned_main: partitioned_main.iter().map(|m| m.as_view()).collect(),
+        after_challenge: after_challenge.iter().map(|m| m.as_view()).collect(),
         is_first_row: sels.is_first_row,
         is_last_row: sels.is_last_row,
         is_transition: sels.is_transition,
@@ -124,6 +168,7 @@ where
         accumulator: SC::Challenge::ZERO,
         challenges,
         public_values,
+        periodic_values: &periodic_values,
         exposed_values_after_challenge,
         _marker: PhantomData,
     };
@@ -133,7 +178,15 @@ where
     // Finally, check that
     //     folded_constraints(zeta) / Z_H(zeta) = quotient(zeta)
     if folded_constraints * sels.inv_zeroifier != quotient {
-        return Err(VerificationError::OodEvaluationMismatch);
+        tracing::debug!(
+            "AIR {air_name} (air_id = {air_id}): constraints(zeta) / Z_H(zeta) = {:?}, but \
+             quotient(zeta) = {quotient:?}",
+            folded_constraints * sels.inv_zeroifier
+        );
+        return Err(VerificationError::OodEvaluationMismatch {
+            air_id,
+            air_name: air_name.to_string(),
+        });
     }
 
     Ok(())
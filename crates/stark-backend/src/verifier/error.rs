@@ -1,16 +1,246 @@
 use thiserror::Error;
 
+use super::LogUpSumReport;
+use crate::{
+    interaction::{fri_log_up::FriLogUpParams, RapPhaseSeqKind},
+    keygen::{
+        types::CommitmentRound, DisallowedPublicValueError, MissingPreprocessedHeightError,
+        TraceHeightTooLargeError, UnsupportedVkFeaturesError,
+    },
+};
+
 #[derive(Debug, Error, PartialEq, Eq)]
 pub enum VerificationError {
-    #[error("invalid proof shape")]
-    InvalidProofShape,
-    /// An error occurred while verifying the claimed openings.
-    #[error("invalid opening argument: {0}")]
-    InvalidOpeningArgument(String),
+    /// The proof does not have the shape the verifying key expects.
+    #[error("invalid proof shape: {0}")]
+    InvalidProofShape(#[from] ProofShapeError),
+    /// An error occurred while verifying the claimed openings. The PCS is opaque to the verifier,
+    /// so `error` is the error of the PCS, e.g. the Merkle or FRI check that failed, rendered
+    /// with `Debug`.
+    #[error(
+        "invalid opening argument{}: {error}",
+        query.map(|query| format!(" at query {query}")).unwrap_or_default()
+    )]
+    InvalidOpeningArgument {
+        /// The index of the first query of the opening proof the PCS rejects, if the verifier has
+        /// the shape of the PCS to locate it, see
+        /// [with_pcs_shape](super::MultiTraceStarkVerifier::with_pcs_shape), and the PCS rejects
+        /// a query rather than, e.g., the proof of work.
+        query: Option<usize>,
+        error: String,
+    },
     /// Out-of-domain evaluation mismatch, i.e. `constraints(zeta)` did not match
-    /// `quotient(zeta) Z_H(zeta)`.
-    #[error("out-of-domain evaluation mismatch")]
-    OodEvaluationMismatch,
-    #[error("challenge phase error")]
-    ChallengePhaseError,
+    /// `quotient(zeta) Z_H(zeta)` for an AIR.
+    #[error("AIR {air_name} (air_id = {air_id}): out-of-domain evaluation mismatch")]
+    OodEvaluationMismatch { air_id: usize, air_name: String },
+    /// The opened value at `zeta` of the eq column of an AIR in the GKR log-up phase does not
+    /// match the evaluation of the eq polynomial.
+    #[error("AIR {air_name} (air_id = {air_id}): eq column opening mismatch")]
+    EqColumnMismatch { air_id: usize, air_name: String },
+    /// The challenge phase rejected the proof, e.g. because the log-up sums do not cancel out.
+    /// Holds the rendered error of the challenge phase.
+    #[error("challenge phase error: {0}")]
+    ChallengePhaseError(String),
+    /// The challenge phase rejected the proof because the log-up sums of the AIRs do not cancel
+    /// out, i.e. the messages sent and received on the buses differ. Holds the rendered error of
+    /// the challenge phase, and the breakdown of the sums.
+    #[error("challenge phase error: {error}\n{report}")]
+    LogUpSumMismatch {
+        error: String,
+        report: LogUpSumReport,
+    },
+    /// An AIR with interactions has a verifying key for a different challenge phase than the one
+    /// of the configuration.
+    #[error(
+        "AIR {air_name} (air_id = {air_id}) has a verifying key for challenge phase {found:?}, \
+         but the configuration uses {expected:?}"
+    )]
+    RapPhaseSeqKindMismatch {
+        air_id: usize,
+        air_name: String,
+        expected: RapPhaseSeqKind,
+        found: RapPhaseSeqKind,
+    },
+    /// An AIR with interactions has a verifying key with different log-up parameters, i.e.
+    /// interaction batch size or number of repetitions, than the challenge phase of the
+    /// configuration.
+    #[error(
+        "AIR {air_name} (air_id = {air_id}) has a verifying key with log-up parameters \
+         {found:?}, but the configuration uses {expected:?}"
+    )]
+    LogUpParamsMismatch {
+        air_id: usize,
+        air_name: String,
+        expected: FriLogUpParams,
+        found: FriLogUpParams,
+    },
+    /// The proof verifies, but the application check of its public values rejected them, see
+    /// [verify_with_public_values_check](crate::engine::StarkEngine::verify_with_public_values_check).
+    #[error("public values rejected: {0}")]
+    PublicValuesRejected(String),
+    /// A configuration public value is not allowed by the verifying key.
+    #[error(transparent)]
+    DisallowedPublicValue(#[from] DisallowedPublicValueError),
+    /// The verifying key relies on features this verifier does not support.
+    #[error(transparent)]
+    UnsupportedVkFeatures(#[from] UnsupportedVkFeaturesError),
+    /// The verifying key has no preprocessed commitment for the trace height of an AIR.
+    #[error(transparent)]
+    MissingPreprocessedHeight(#[from] MissingPreprocessedHeightError),
+    /// The trace of an AIR is taller than the verifying key allows.
+    #[error(transparent)]
+    TraceHeightTooLarge(#[from] TraceHeightTooLargeError),
+    /// The AIR ids of the proof are not distinct ids of the verifying key in increasing order.
+    #[error("proof is for AIRs {0:?}, which are not distinct AIRs of the key in increasing order")]
+    InvalidAirIds(Vec<usize>),
+    /// The proof is not for exactly the AIRs of a pruned verifying key.
+    #[error("proof is for AIRs {found:?}, but the pruned verifying key is for AIRs {expected:?}")]
+    AirSubsetMismatch {
+        expected: Vec<usize>,
+        found: Vec<usize>,
+    },
+    /// The absent AIR ids of the proof are not distinct ids of the verifying key in increasing
+    /// order, disjoint from the AIR ids of the proof.
+    #[error(
+        "proof has absent AIRs {0:?}, which are not distinct AIRs of the key in increasing order \
+         without a trace in the proof"
+    )]
+    InvalidAbsentAirIds(Vec<usize>),
+    /// An AIR is absent from the proof, but the verifying key does not allow it to be absent.
+    #[error(
+        "AIR {air_name} (air_id = {air_id}) is absent, but the verifying key does not allow it"
+    )]
+    AirNotAllowedAbsent { air_id: usize, air_name: String },
+    /// The verifying key has an AIR that may be absent, but these AIRs are neither in the proof
+    /// nor absent, in increasing order of AIR id.
+    #[error("AIR(s) {air_names:?} (air_ids = {air_ids:?}) are neither in the proof nor absent")]
+    MissingAirs {
+        air_ids: Vec<usize>,
+        air_names: Vec<String>,
+    },
+    /// The proof exceeds the limits of the verifier, see
+    /// [with_limits](super::MultiTraceStarkVerifier::with_limits).
+    #[error("verifier limit exceeded: {0}")]
+    LimitExceeded(#[from] VerifierLimitError),
+}
+
+/// A proof above one of the [VerifierLimits](super::VerifierLimits) of the verifier.
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum VerifierLimitError {
+    #[error("proof is {found} bytes, above the limit of {max}")]
+    ProofBytes { max: usize, found: usize },
+    #[error("proof has a total log trace height of {found}, above the limit of {max}")]
+    TotalLogHeight { max: usize, found: usize },
+    #[error("proof has {found} AIRs, above the limit of {max}")]
+    Airs { max: usize, found: usize },
+    #[error("proof has {found} opened values, above the limit of {max}")]
+    OpenedValues { max: usize, found: usize },
+}
+
+/// A part of a proof that does not have the shape the verifying key expects, see
+/// [validate_proof_shape](super::validate_proof_shape).
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum ProofShapeError {
+    #[error("proof has no AIRs")]
+    NoAirs,
+    #[error(
+        "AIR {air_name} (air_id = {air_id}) has trace height {height}, which is not a power of two"
+    )]
+    TraceHeightNotPowerOfTwo {
+        air_id: usize,
+        air_name: String,
+        height: usize,
+    },
+    /// The domain the trace is committed on, `2^log_blowup` times its height, would not be a
+    /// subgroup of the field.
+    #[error(
+        "AIR {air_name} (air_id = {air_id}) has trace height {height}, whose committed domain \
+         with log blowup {log_blowup} is above the two-adicity 2^{two_adicity} of the field"
+    )]
+    TraceHeightAboveTwoAdicity {
+        air_id: usize,
+        air_name: String,
+        height: usize,
+        log_blowup: usize,
+        two_adicity: usize,
+    },
+    #[error("AIR {air_name} (air_id = {air_id}) has {found} public values, expected {expected}")]
+    NumPublicValues {
+        air_id: usize,
+        air_name: String,
+        expected: usize,
+        found: usize,
+    },
+    /// The number of values exposed by an AIR after each challenge phase.
+    #[error(
+        "AIR {air_name} (air_id = {air_id}) exposes {found:?} values after the challenge phases, \
+         expected {expected:?}"
+    )]
+    NumExposedValues {
+        air_id: usize,
+        air_name: String,
+        expected: Vec<usize>,
+        found: Vec<usize>,
+    },
+    /// A list of the proof with the wrong length. `field` is the path of the list in
+    /// [Proof](crate::proof::Proof).
+    #[error("proof has {found} entries in `{field}`, expected {expected}")]
+    NumEntries {
+        field: &'static str,
+        expected: usize,
+        found: usize,
+    },
+    #[error(
+        "proof has opened values for {found} matrices of the {round} commitment, expected \
+         {expected}"
+    )]
+    NumOpenedMatrices {
+        round: CommitmentRound,
+        expected: usize,
+        found: usize,
+    },
+    #[error(
+        "opened values of AIR {air_name} (air_id = {air_id}) in the {round} commitment are not \
+         {width} wide at the local, next and {num_rotations} rotated rows"
+    )]
+    OpenedValues {
+        air_id: usize,
+        air_name: String,
+        round: CommitmentRound,
+        width: usize,
+        num_rotations: usize,
+    },
+    /// The opened values of an AIR are not of the same width at every opened row, or are not
+    /// opened at its extra rotations.
+    #[error(
+        "opened values of AIR {air_name} (air_id = {air_id}) are not of the same width at the \
+         local, next and {num_rotations} rotated rows"
+    )]
+    RaggedOpenedValues {
+        air_id: usize,
+        air_name: String,
+        num_rotations: usize,
+    },
+    /// The opened quotient of an AIR, including the random mask in zero-knowledge mode.
+    #[error(
+        "opened quotient of AIR {air_name} (air_id = {air_id}) is not {num_chunks} chunks of \
+         {chunk_len} values"
+    )]
+    QuotientChunks {
+        air_id: usize,
+        air_name: String,
+        num_chunks: usize,
+        chunk_len: usize,
+    },
+    #[error(
+        "periodic column {column} of AIR {air_name} (air_id = {air_id}) has period {period}, \
+         which does not divide the trace height {height}"
+    )]
+    PeriodicColumn {
+        air_id: usize,
+        air_name: String,
+        column: usize,
+        period: usize,
+        height: usize,
+    },
 }
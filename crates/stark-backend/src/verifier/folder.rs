@@ -13,7 +13,7 @@ use crate::{
             symbolic_variable::{Entry, SymbolicVariable},
             SymbolicExpressionDag,
         },
-        ViewPair,
+        ViewWindow,
     },
     config::{StarkGenericConfig, Val},
 };
@@ -31,9 +31,9 @@ pub type VerifierConstraintFolder<'a, SC> = GenericVerifierConstraintFolder<
 ///
 /// `Var` is still a challenge type because this is a verifier.
 pub struct GenericVerifierConstraintFolder<'a, F, EF, PubVar, Var, Expr> {
-    pub preprocessed: ViewPair<'a, Var>,
-    pub partitioned_main: Vec<ViewPair<'a, Var>>,
-    pub after_challenge: Vec<ViewPair<'a, Var>>,
+    pub preprocessed: ViewWindow<'a, Var>,
+    pub partitioned_main: Vec<ViewWindow<'a, Var>>,
+    pub after_challenge: Vec<ViewWindow<'a, Var>>,
     pub challenges: &'a [Vec<Var>],
     pub is_first_row: Var,
     pub is_last_row: Var,
@@ -41,6 +41,7 @@ pub struct GenericVerifierConstraintFolder<'a, F, EF, PubVar, Var, Expr> {
     pub alpha: Var,
     pub accumulator: Expr,
     pub public_values: &'a [PubVar],
+    pub periodic_values: &'a [Var],
     pub exposed_values_after_challenge: &'a [Vec<Var>],
     pub _marker: PhantomData<(F, EF)>,
 }
@@ -100,22 +101,12 @@ where
                 self.partitioned_main[part_index].get(offset, index).into()
             }
             Entry::Public => self.public_values[index].into(),
-            Entry::Permutation { offset } => self
-                .after_challenge
-                .first()
-                .expect("Challenge phase not supported")
-                .get(offset, index)
-                .into(),
-            Entry::Challenge => self
-                .challenges
-                .first()
-                .expect("Challenge phase not supported")[index]
-                .into(),
-            Entry::Exposed => self
-                .exposed_values_after_challenge
-                .first()
-                .expect("Challenge phase not supported")[index]
-                .into(),
+            Entry::Periodic => self.periodic_values[index].into(),
+            Entry::Permutation { phase, offset } => {
+                self.after_challenge[phase].get(offset, index).into()
+            }
+            Entry::Challenge { phase } => self.challenges[phase][index].into(),
+            Entry::Exposed { phase } => self.exposed_values_after_challenge[phase][index].into(),
         }
     }
     // NOTE: do not use the eval_expr function as it can have exponential complexity!
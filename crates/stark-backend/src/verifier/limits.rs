@@ -0,0 +1,116 @@
+use p3_field::FieldExtensionAlgebra;
+use p3_util::log2_ceil_usize;
+use serde::{Deserialize, Serialize};
+
+use super::error::VerifierLimitError;
+use crate::{
+    config::{StarkGenericConfig, Val},
+    encoding,
+    keygen::types::{MultiStarkVerifyingKey, VkFeature},
+    proof::Proof,
+};
+
+/// Default bound on the size of a serialized proof. The size of the opening proof depends on the
+/// PCS, which is opaque to the verifier, so this bound is not derived from the verifying key.
+pub const DEFAULT_MAX_PROOF_BYTES: usize = 1 << 27;
+
+/// Bounds on the size of a proof, checked before any other work on the proof so that a verifier
+/// of untrusted proofs does not allocate or hash more than a legitimate proof warrants, see
+/// [with_limits](super::MultiTraceStarkVerifier::with_limits).
+///
+/// The verifying key already bounds the shape of a proof, see
+/// [validate_proof_shape](super::validate_proof_shape), and [from_vk](Self::from_vk) derives the
+/// limits it implies. Tighter limits reject large claims of a proof, e.g. trace heights at the
+/// two-adicity of the field for every AIR, before the verifier hashes anything.
+///
+/// A serialized proof should be checked against `max_proof_bytes` before it is decoded, so that
+/// the decoder does not allocate for an oversized input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerifierLimits {
+    /// Maximum size of the proof, in bytes of its binary serde encoding, see
+    /// [Proof::size_breakdown].
+    pub max_proof_bytes: usize,
+    /// Maximum sum over the AIRs of the proof of the log2 of their trace heights.
+    pub max_total_log_height: usize,
+    /// Maximum number of AIRs of the proof.
+    pub max_airs: usize,
+    /// Maximum number of opened values of the proof, counted in elements of the challenge field.
+    pub max_opened_values: usize,
+}
+
+impl VerifierLimits {
+    /// The limits implied by `vk` for a proof of all its AIRs, with [DEFAULT_MAX_PROOF_BYTES].
+    /// A proof passing the shape checks of the verifier never exceeds them.
+    pub fn from_vk<SC: StarkGenericConfig>(vk: &MultiStarkVerifyingKey<SC>) -> Self {
+        let ext_degree = <SC::Challenge as FieldExtensionAlgebra<Val<SC>>>::D;
+        let zero_knowledge = vk.features.contains(VkFeature::ZeroKnowledge);
+        let mut max_total_log_height = 0;
+        let mut max_opened_values = 0;
+        for air_vk in &vk.per_air {
+            let params = &air_vk.params;
+            let width = &params.width;
+            // The bound of a key read from untrusted bytes may be arbitrarily large.
+            max_total_log_height = air_vk.max_log_height().saturating_add(max_total_log_height);
+            // The local and next rows, and the extra rotations of the AIR. The after challenge
+            // traces are only opened at the local and next rows.
+            let num_rows = 2 + params.extra_rotations.len();
+            let base_width = width.preprocessed.unwrap_or(0)
+                + width.cached_mains.iter().sum::<usize>()
+                + width.common_main
+                + width.custom.iter().map(|&(_, width)| width).sum::<usize>();
+            let after_challenge_width = width.after_challenge.iter().sum::<usize>() * ext_degree;
+            let num_quotient_chunks = air_vk.quotient_degree as usize + zero_knowledge as usize;
+            max_opened_values += base_width * num_rows
+                + after_challenge_width * 2
+                + num_quotient_chunks * ext_degree;
+        }
+        Self {
+            max_proof_bytes: DEFAULT_MAX_PROOF_BYTES,
+            max_total_log_height,
+            max_airs: vk.per_air.len(),
+            max_opened_values,
+        }
+    }
+
+    /// Checks `proof` against the limits. The size of the proof is checked last, since it is the
+    /// only limit that walks the whole proof.
+    pub fn check<SC: StarkGenericConfig>(
+        &self,
+        proof: &Proof<SC>,
+    ) -> Result<(), VerifierLimitError> {
+        let num_airs = proof.core.per_air.len();
+        if num_airs > self.max_airs {
+            return Err(VerifierLimitError::Airs {
+                max: self.max_airs,
+                found: num_airs,
+            });
+        }
+        let total_log_height = proof
+            .core
+            .per_air
+            .iter()
+            .map(|air_proof| log2_ceil_usize(air_proof.degree))
+            .sum::<usize>();
+        if total_log_height > self.max_total_log_height {
+            return Err(VerifierLimitError::TotalLogHeight {
+                max: self.max_total_log_height,
+                found: total_log_height,
+            });
+        }
+        let num_opened_values = proof.core.opened_values.num_values();
+        if num_opened_values > self.max_opened_values {
+            return Err(VerifierLimitError::OpenedValues {
+                max: self.max_opened_values,
+                found: num_opened_values,
+            });
+        }
+        let proof_bytes = encoding::serialized_size(proof).expect("failed to serialize proof");
+        if proof_bytes > self.max_proof_bytes {
+            return Err(VerifierLimitError::ProofBytes {
+                max: self.max_proof_bytes,
+                found: proof_bytes,
+            });
+        }
+        Ok(())
+    }
+}
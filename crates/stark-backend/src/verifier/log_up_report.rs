@@ -0,0 +1,137 @@
+use std::{collections::BTreeMap, fmt, iter::zip};
+
+use itertools::izip;
+
+use crate::{
+    interaction::{bus::BusRegistry, InteractionType},
+    keygen::view::MultiStarkVerifyingKeyView,
+};
+
+/// The breakdown of a failed log-up sum check, see [VerificationError::LogUpSumMismatch].
+///
+/// [VerificationError::LogUpSumMismatch]: super::VerificationError::LogUpSumMismatch
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LogUpSumReport {
+    /// The contribution of each AIR of the proof, in increasing AIR id order.
+    pub per_air: Vec<AirLogUpContribution>,
+    /// The buses with interactions of only one side among the AIRs of the proof, in increasing
+    /// bus index order. The messages of these buses cannot cancel out, whatever the traces.
+    pub unbalanced_buses: Vec<UnbalancedBus>,
+}
+
+/// The values an AIR exposes after the challenge phases, i.e. its log-up sums.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AirLogUpContribution {
+    pub air_id: usize,
+    pub air_name: String,
+    /// The exposed values of each challenge phase in order, rendered with `Display`.
+    pub exposed_values: Vec<String>,
+}
+
+/// A bus only sent to, or only received from, by the AIRs of a proof.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnbalancedBus {
+    pub bus_index: usize,
+    /// The name of the bus in the [BusRegistry] of the key.
+    pub bus_name: String,
+    /// The only side of the bus among the AIRs of the proof. The public lookup tables of the key
+    /// receive on their buses.
+    pub interaction_type: InteractionType,
+    /// The AIRs with interactions on the bus.
+    pub air_ids: Vec<usize>,
+}
+
+impl LogUpSumReport {
+    /// Builds the report of the AIRs of `mvk`, with ids `air_ids`, from their exposed values per
+    /// challenge phase.
+    pub(crate) fn new<F, Com, Challenge: fmt::Display>(
+        mvk: &MultiStarkVerifyingKeyView<F, Com>,
+        bus_registry: &BusRegistry,
+        air_ids: &[usize],
+        exposed_values_per_air_per_phase: &[Vec<Vec<Challenge>>],
+    ) -> Self {
+        let per_air = izip!(air_ids, &mvk.per_air, exposed_values_per_air_per_phase)
+            .map(
+                |(&air_id, vk, exposed_values_per_phase)| AirLogUpContribution {
+                    air_id,
+                    air_name: vk.air_name.clone(),
+                    exposed_values: exposed_values_per_phase
+                        .iter()
+                        .flatten()
+                        .map(|value| value.to_string())
+                        .collect(),
+                },
+            )
+            .collect();
+
+        // The AIRs sending and receiving on each bus.
+        let mut sides = BTreeMap::<usize, (Vec<usize>, Vec<usize>)>::new();
+        for (&air_id, vk) in zip(air_ids, &mvk.per_air) {
+            for interaction in &vk.symbolic_constraints.interactions {
+                let (senders, receivers) = sides.entry(interaction.bus_index).or_default();
+                let side = match interaction.interaction_type {
+                    InteractionType::Send => senders,
+                    InteractionType::Receive => receivers,
+                };
+                if side.last() != Some(&air_id) {
+                    side.push(air_id);
+                }
+            }
+        }
+        let unbalanced_buses = sides
+            .into_iter()
+            .filter_map(|(bus_index, (senders, receivers))| {
+                let has_table = mvk
+                    .public_lookup_tables
+                    .iter()
+                    .any(|table| table.bus_index == bus_index);
+                let (interaction_type, air_ids) =
+                    match (senders.is_empty(), receivers.is_empty() && !has_table) {
+                        (false, true) => (InteractionType::Send, senders),
+                        (true, false) => (InteractionType::Receive, receivers),
+                        _ => return None,
+                    };
+                Some(UnbalancedBus {
+                    bus_index,
+                    bus_name: bus_registry.name(bus_index),
+                    interaction_type,
+                    air_ids,
+                })
+            })
+            .collect();
+        Self {
+            per_air,
+            unbalanced_buses,
+        }
+    }
+}
+
+impl fmt::Display for LogUpSumReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "  Exposed values per AIR:")?;
+        for air in &self.per_air {
+            write!(
+                f,
+                "\n    AIR {} (air_id = {}): [{}]",
+                air.air_name,
+                air.air_id,
+                air.exposed_values.join(", ")
+            )?;
+        }
+        if !self.unbalanced_buses.is_empty() {
+            write!(f, "\n  Buses that cannot balance:")?;
+        }
+        for bus in &self.unbalanced_buses {
+            let side = match bus.interaction_type {
+                InteractionType::Send => "only sent to, by",
+                InteractionType::Receive => "only received from, by",
+            };
+            write!(
+                f,
+                "\n    Bus {} ({}): {side} AIRs {:?}",
+                bus.bus_index, bus.bus_name, bus.air_ids
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -1,15 +1,24 @@
+use std::{collections::HashMap, iter::zip};
+
 use itertools::{izip, Itertools};
 use p3_challenger::{CanObserve, FieldChallenger};
 use p3_commit::{Pcs, PolynomialSpace};
-use p3_field::FieldAlgebra;
+use p3_field::{FieldAlgebra, FieldExtensionAlgebra, PrimeField64};
+use p3_maybe_rayon::prelude::*;
 use p3_util::log2_strict_usize;
 use tracing::instrument;
 
 use crate::{
     config::{Com, Domain, StarkGenericConfig, Val},
-    interaction::RapPhaseSeq,
-    keygen::{types::MultiStarkVerifyingKey, view::MultiStarkVerifyingKeyView},
+    interaction::{bus::BusRegistry, gkr_log_up::eval_eq_column, RapPhaseSeq},
+    keygen::{
+        types::{
+            CommitmentRound, MultiStarkVerifyingKey, PrunedVerifyingKey, VkFeature, VkFeatureSet,
+        },
+        view::MultiStarkVerifyingKeyView,
+    },
     proof::{AdjacentOpenedValues, Proof},
+    utils::rotate_point,
     verifier::constraints::verify_single_rap_constraints,
 };
 
@@ -17,19 +26,114 @@ pub mod constraints;
 mod error;
 /// Constraint folder
 pub mod folder;
+mod limits;
+mod log_up_report;
+mod public_values;
+mod shape;
 
 pub use error::*;
 pub use folder::GenericVerifierConstraintFolder;
+pub use limits::{VerifierLimits, DEFAULT_MAX_PROOF_BYTES};
+pub use log_up_report::{AirLogUpContribution, LogUpSumReport, UnbalancedBus};
+pub use public_values::PublicValuesView;
+use shape::check_num_exposed_values;
+pub use shape::{validate_proof_shape, PcsShape};
+
+/// Observes `prefix`, e.g. the commitments of the previous proof of a chain of proofs, to bind
+/// the rest of the transcript to it. The prefix is not recorded in the proof: the prover and the
+/// verifier observe it with a fresh challenger before proving and verifying.
+///
+/// Observes the length of the prefix and then each commitment. An empty prefix is not observed,
+/// so the transcript is unchanged.
+pub fn observe_transcript_prefix<SC: StarkGenericConfig>(
+    challenger: &mut SC::Challenger,
+    prefix: &[Com<SC>],
+) {
+    if prefix.is_empty() {
+        return;
+    }
+    challenger.observe(Val::<SC>::from_canonical_usize(prefix.len()));
+    for commit in prefix {
+        challenger.observe(commit.clone());
+    }
+}
 
 /// Verifies a partitioned proof of multi-matrix AIRs.
 pub struct MultiTraceStarkVerifier<'c, SC: StarkGenericConfig> {
     config: &'c SC,
+    constraints_before_opening: bool,
+    early_queries: usize,
+    limits: Option<VerifierLimits>,
+    key_limits: bool,
+    pcs_shape: Option<PcsShape<SC>>,
 }
 
 impl<'c, SC: StarkGenericConfig> MultiTraceStarkVerifier<'c, SC> {
     pub fn new(config: &'c SC) -> Self {
-        Self { config }
+        Self {
+            config,
+            constraints_before_opening: false,
+            early_queries: 0,
+            limits: None,
+            key_limits: false,
+            pcs_shape: None,
+        }
+    }
+
+    /// Checks the out-of-domain constraints before the PCS opening proof, and reports a failed
+    /// challenge phase immediately, so that proofs failing these cheaper checks are rejected
+    /// without verifying the opening proof. To also check a few queries of the opening proof
+    /// first, see [with_early_queries](Self::with_early_queries). In both orders, the proof shape
+    /// is validated before touching the transcript, see [validate_proof_shape].
+    ///
+    /// Every check is still done before a proof is accepted, so the set of accepted proofs is the
+    /// same in both orders. Only the error returned for a rejected proof may differ.
+    pub fn with_constraints_before_opening(mut self, constraints_before_opening: bool) -> Self {
+        self.constraints_before_opening = constraints_before_opening;
+        self
+    }
+
+    /// Checks the opening proof with only its first `num_queries` queries before the out-of-domain
+    /// constraints and the full opening proof, so that a bad opening proof is rejected after its
+    /// proof of work and a few queries. The PCS samples the index of each query from the
+    /// transcript, so the first queries are a random subset of the queries. This needs the shape
+    /// of the PCS to truncate the opening proof, see [with_pcs_shape](Self::with_pcs_shape), and
+    /// is skipped without it.
+    ///
+    /// The early queries are checked again with the full opening proof, so they only reject
+    /// proofs early: the set of accepted proofs is the same with any number of early queries.
+    pub fn with_early_queries(mut self, num_queries: usize) -> Self {
+        self.early_queries = num_queries;
+        self
     }
+
+    /// Rejects the proofs above `limits` before any other check, so that the verifier does no
+    /// work proportional to the claims of an oversized proof. Without limits, a proof is only
+    /// bounded by the verifying key, which [VerifierLimits::from_vk] starts from. Verifiers of
+    /// untrusted proofs should set limits sized for the proofs they expect.
+    pub fn with_limits(mut self, limits: VerifierLimits) -> Self {
+        self.limits = Some(limits);
+        self
+    }
+
+    /// Also rejects the proofs above the limits implied by the key they are verified against,
+    /// see [VerifierLimits::from_vk]. These are checked once the shape of the proof is validated,
+    /// so that a malformed proof is reported with the more specific shape error. A proof of a valid
+    /// shape is within all of them but `max_proof_bytes`, which bounds the opening proof before
+    /// the PCS verifies it.
+    pub fn with_key_limits(mut self) -> Self {
+        self.key_limits = true;
+        self
+    }
+
+    /// Checks the trace heights against the blowup of the PCS and the number of query proofs of
+    /// the opening proof with the proof shape, see [validate_proof_shape], so that the PCS is
+    /// never handed a domain outside the two-adicity of the field or a truncated opening proof.
+    pub fn with_pcs_shape(mut self, pcs_shape: PcsShape<SC>) -> Self {
+        self.pcs_shape = Some(pcs_shape);
+        self
+    }
+
     /// Verify collection of InteractiveAIRs and check the permutation
     /// cumulative sum is equal to zero across all AIRs.
     #[instrument(name = "MultiTraceStarkVerifier::verify", level = "debug", skip_all)]
@@ -38,16 +142,241 @@ impl<'c, SC: StarkGenericConfig> MultiTraceStarkVerifier<'c, SC> {
         challenger: &mut SC::Challenger,
         mvk: &MultiStarkVerifyingKey<SC>,
         proof: &Proof<SC>,
-    ) -> Result<(), VerificationError> {
-        let mvk = mvk.view(&proof.get_air_ids());
-        self.verify_raps(challenger, &mvk, proof)?;
+    ) -> Result<(), VerificationError>
+    where
+        Val<SC>: PrimeField64,
+    {
+        let air_ids = proof.get_air_ids();
+        self.verify_with_air_ids(challenger, mvk, proof, &air_ids)
+    }
+
+    /// Verifies each of `proofs` with `mvk`, in parallel with the `parallel` feature, and returns
+    /// the result of each proof in the order of `proofs`. Each proof is verified with a fresh
+    /// challenger from `new_challenger`, and the result is the same as with [verify](Self::verify).
+    ///
+    /// Only two steps depend on the key alone, and are shared by all proofs: the features of the
+    /// key are checked once, and the key at the trace heights of the proofs, see
+    /// [MultiStarkVerifyingKey::at_heights], is computed once for all proofs with the same heights
+    /// of the AIRs with a preprocessed trace committed per height. The constraints are evaluated
+    /// from the symbolic DAGs of the key as they are, so there is no preprocessing of them to
+    /// share, and the speedup over verifying the proofs one at a time comes from the parallelism.
+    pub fn verify_batch(
+        &self,
+        new_challenger: impl Fn() -> SC::Challenger + Sync,
+        mvk: &MultiStarkVerifyingKey<SC>,
+        proofs: &[Proof<SC>],
+    ) -> Vec<Result<(), VerificationError>>
+    where
+        Val<SC>: PrimeField64,
+        SC: Sync,
+        MultiStarkVerifyingKey<SC>: Sync,
+        Proof<SC>: Sync,
+    {
+        if let Err(err) = mvk.features.check_supported(VkFeatureSet::supported()) {
+            return proofs.iter().map(|_| Err(err.clone().into())).collect();
+        }
+        let air_heights = proofs
+            .iter()
+            .map(|proof| self.check_air_ids(mvk, proof))
+            .collect_vec();
+        // Only the heights of the AIRs with a preprocessed trace committed per height change the
+        // key.
+        let per_height_air_heights = |air_heights: &[(usize, usize)]| {
+            air_heights
+                .iter()
+                .copied()
+                .filter(|&(air_id, _)| mvk.per_air[air_id].preprocessed_per_height.is_some())
+                .collect_vec()
+        };
+        let mut keys_at_heights = HashMap::new();
+        for air_heights in air_heights.iter().flatten() {
+            let air_heights = per_height_air_heights(air_heights);
+            if !keys_at_heights.contains_key(&air_heights) {
+                let key = mvk.at_heights(&air_heights);
+                keys_at_heights.insert(air_heights, key);
+            }
+        }
+        let keys = air_heights
+            .into_iter()
+            .map(|air_heights| {
+                let key = &keys_at_heights[&per_height_air_heights(&air_heights?)];
+                match key {
+                    Ok(key) => Ok(&**key),
+                    Err(err) => Err(VerificationError::from(err.clone())),
+                }
+            })
+            .collect_vec();
+        keys.into_par_iter()
+            .zip(proofs)
+            .map(|(mvk, proof)| {
+                let mut challenger = new_challenger();
+                self.verify_at_heights(&mut challenger, mvk?, proof, &proof.get_air_ids())
+            })
+            .collect()
+    }
+
+    /// Verifies `proof`, whose AIRs have ids `transcript_air_ids` for the transcript, which
+    /// differ from their ids in `mvk` for a pruned key.
+    fn verify_with_air_ids(
+        &self,
+        challenger: &mut SC::Challenger,
+        mvk: &MultiStarkVerifyingKey<SC>,
+        proof: &Proof<SC>,
+        transcript_air_ids: &[usize],
+    ) -> Result<(), VerificationError>
+    where
+        Val<SC>: PrimeField64,
+    {
+        mvk.features.check_supported(VkFeatureSet::supported())?;
+        let air_heights = self.check_air_ids(mvk, proof)?;
+        let mvk = mvk.at_heights(&air_heights)?;
+        self.verify_at_heights(challenger, &mvk, proof, transcript_air_ids)
+    }
+
+    /// Checks the limits of the verifier, the AIR ids, the absent AIR ids and the trace heights of
+    /// `proof` against `mvk`, and returns the `(air_id, height)` of each AIR of the proof.
+    fn check_air_ids(
+        &self,
+        mvk: &MultiStarkVerifyingKey<SC>,
+        proof: &Proof<SC>,
+    ) -> Result<Vec<(usize, usize)>, VerificationError> {
+        if let Some(limits) = &self.limits {
+            limits.check(proof)?;
+        }
+        // The proof may be for any subset of the AIRs of the key, listed once each in increasing
+        // order so that the transcript is canonical, unless the key has an AIR that may be absent,
+        // see below.
+        let air_ids = proof.get_air_ids();
+        if !air_ids.iter().tuple_windows().all(|(a, b)| a < b)
+            || air_ids.iter().any(|&air_id| air_id >= mvk.per_air.len())
+        {
+            return Err(VerificationError::InvalidAirIds(air_ids));
+        }
+        // An absent AIR has no trace, so it contributes nothing to the constraints or the bus
+        // sums. Keygen checks that an AIR allowed to be absent only interacts when gated by its
+        // trace, so that a proof with the AIR absent is the same as one with an empty trace.
+        let absent_air_ids = &proof.core.absent_air_ids;
+        if !absent_air_ids.iter().tuple_windows().all(|(a, b)| a < b)
+            || absent_air_ids
+                .iter()
+                .any(|air_id| *air_id >= mvk.per_air.len() || air_ids.contains(air_id))
+        {
+            return Err(VerificationError::InvalidAbsentAirIds(
+                absent_air_ids.clone(),
+            ));
+        }
+        if let Some(&air_id) = absent_air_ids
+            .iter()
+            .find(|&&air_id| !mvk.per_air[air_id].params.may_be_absent)
+        {
+            return Err(VerificationError::AirNotAllowedAbsent {
+                air_id,
+                air_name: mvk.air_name(air_id).to_string(),
+            });
+        }
+        // Otherwise a proof could leave out an AIR that may be absent instead of marking it
+        // absent, so every AIR must be either proven or absent.
+        if mvk.features.contains(VkFeature::AbsentAirs) {
+            let missing_air_ids = (0..mvk.per_air.len())
+                .filter(|air_id| !air_ids.contains(air_id) && !absent_air_ids.contains(air_id))
+                .collect_vec();
+            if !missing_air_ids.is_empty() {
+                return Err(VerificationError::MissingAirs {
+                    air_names: missing_air_ids
+                        .iter()
+                        .map(|&air_id| mvk.air_name(air_id).to_string())
+                        .collect(),
+                    air_ids: missing_air_ids,
+                });
+            }
+        }
+        // Checked before any hashing, since recursive verifiers are sized for the bound.
+        for air_proof in &proof.core.per_air {
+            mvk.per_air[air_proof.air_id].check_trace_height(air_proof.air_id, air_proof.degree)?;
+        }
+        Ok(proof
+            .core
+            .per_air
+            .iter()
+            .map(|air_proof| (air_proof.air_id, air_proof.degree))
+            .collect())
+    }
+
+    /// Verifies `proof` with `mvk` already at the trace heights of the proof, see
+    /// [MultiStarkVerifyingKey::at_heights], once its AIR ids are checked.
+    fn verify_at_heights(
+        &self,
+        challenger: &mut SC::Challenger,
+        mvk: &MultiStarkVerifyingKey<SC>,
+        proof: &Proof<SC>,
+        transcript_air_ids: &[usize],
+    ) -> Result<(), VerificationError>
+    where
+        Val<SC>: PrimeField64,
+    {
+        let air_ids = proof.get_air_ids();
+        // The expected shape of the proof depends on the challenge phase of the key, so a key for
+        // another challenge phase is reported first.
+        self.check_rap_phase_params(&mvk.view(&air_ids), proof)?;
+        validate_proof_shape(mvk, proof, self.pcs_shape.as_ref())?;
+        if self.key_limits {
+            VerifierLimits::from_vk(mvk).check(proof)?;
+        }
+        challenger.observe(Val::<SC>::from_canonical_usize(transcript_air_ids.len()));
+        for &air_id in transcript_air_ids {
+            challenger.observe(Val::<SC>::from_canonical_usize(air_id));
+        }
+        let absent_air_ids = &proof.core.absent_air_ids;
+        challenger.observe(Val::<SC>::from_canonical_usize(absent_air_ids.len()));
+        for &air_id in absent_air_ids {
+            challenger.observe(Val::<SC>::from_canonical_usize(air_id));
+        }
+        self.verify_raps(challenger, &mvk.view(&air_ids), &mvk.bus_registry, proof)?;
         Ok(())
     }
 
+    /// Same as [verify](Self::verify) with a key pruned to a subset of AIRs, see
+    /// [MultiStarkVerifyingKey::prune]. The proof must be for exactly the AIRs of the pruned key,
+    /// with no absent AIRs.
+    pub fn verify_pruned(
+        &self,
+        challenger: &mut SC::Challenger,
+        pvk: &PrunedVerifyingKey<SC>,
+        proof: &Proof<SC>,
+    ) -> Result<(), VerificationError>
+    where
+        Val<SC>: PrimeField64,
+    {
+        let air_ids = proof.get_air_ids();
+        if air_ids != pvk.air_ids {
+            return Err(VerificationError::AirSubsetMismatch {
+                expected: pvk.air_ids.clone(),
+                found: air_ids,
+            });
+        }
+        if !proof.core.absent_air_ids.is_empty() {
+            return Err(VerificationError::InvalidAbsentAirIds(
+                proof.core.absent_air_ids.clone(),
+            ));
+        }
+        // The AIR with id `pvk.air_ids[i]` has id `i` in the pruned key, but the transcript has
+        // the ids of the full key.
+        let mut proof = proof.clone();
+        for (air_id, air_proof) in proof.core.per_air.iter_mut().enumerate() {
+            air_proof.air_id = air_id;
+        }
+        self.verify_with_air_ids(challenger, &pvk.vk, &proof, &air_ids)
+    }
+
     /// Verify general RAPs without checking any relations (e.g., cumulative sum) between exposed values of different RAPs.
     ///
+    /// The ids of the AIRs of the proof must already have been observed by `challenger`, see
+    /// [verify](Self::verify).
+    ///
     /// Public values is a global list shared across all AIRs.
     ///
+    /// `bus_registry` names the buses in the [LogUpSumReport] of a failed log-up sum check.
+    ///
     /// - `num_challenges_to_sample[i]` is the number of challenges to sample in the trace challenge phase corresponding to `proof.commitments.after_challenge[i]`. This must have length equal
     /// to `proof.commitments.after_challenge`.
     #[instrument(level = "debug", skip_all)]
@@ -55,22 +384,108 @@ impl<'c, SC: StarkGenericConfig> MultiTraceStarkVerifier<'c, SC> {
         &self,
         challenger: &mut SC::Challenger,
         mvk: &MultiStarkVerifyingKeyView<Val<SC>, Com<SC>>,
+        bus_registry: &BusRegistry,
         proof: &Proof<SC>,
-    ) -> Result<(), VerificationError> {
+    ) -> Result<(), VerificationError>
+    where
+        Val<SC>: PrimeField64,
+    {
         let public_values = proof.get_public_values();
+        for ((vk, pis), air_proof) in zip(zip(&mvk.per_air, &public_values), &proof.core.per_air) {
+            if pis.len() != vk.params.num_public_values {
+                return Err(ProofShapeError::NumPublicValues {
+                    air_id: air_proof.air_id,
+                    air_name: vk.air_name.clone(),
+                    expected: vk.params.num_public_values,
+                    found: pis.len(),
+                }
+                .into());
+            }
+            // An AIR without interactions has no after challenge trace, so it must not expose
+            // values that would enter the bus argument.
+            let num_exposed_values: &[usize] = if vk.has_interaction() {
+                &vk.params.num_exposed_values_after_challenge
+            } else {
+                &[]
+            };
+            check_num_exposed_values(
+                air_proof.air_id,
+                &vk.air_name,
+                num_exposed_values,
+                &air_proof.exposed_values_after_challenge,
+            )?;
+            vk.check_configuration_public_values(air_proof.air_id, pis)?;
+        }
+        self.check_rap_phase_params(mvk, proof)?;
         // Challenger must observe public values
         for pis in &public_values {
             challenger.observe_slice(pis);
         }
 
-        for preprocessed_commit in mvk.flattened_preprocessed_commits() {
-            challenger.observe(preprocessed_commit);
+        // Only the custom rounds with a matrix from at least one AIR are committed.
+        let custom_rounds = mvk.present_custom_rounds();
+        let num_main_commits = proof.commitments.main_trace.len();
+        if num_main_commits == 0 {
+            return Err(ProofShapeError::NumEntries {
+                field: "commitments.main_trace",
+                expected: 1,
+                found: 0,
+            }
+            .into());
+        }
+        for (field, expected, found) in [
+            (
+                "core.opened_values.main",
+                num_main_commits,
+                proof.core.opened_values.main.len(),
+            ),
+            (
+                "commitments.custom",
+                custom_rounds.len(),
+                proof.commitments.custom.len(),
+            ),
+            (
+                "core.opened_values.custom",
+                custom_rounds.len(),
+                proof.core.opened_values.custom.len(),
+            ),
+        ] {
+            if found != expected {
+                return Err(ProofShapeError::NumEntries {
+                    field,
+                    expected,
+                    found,
+                }
+                .into());
+            }
         }
 
-        // Observe main trace commitments
-        challenger.observe_slice(&proof.commitments.main_trace);
+        // Observe the commitments of all rounds before the challenge phases, in the order
+        // recorded in the verifying key
+        for round in mvk.commitment_rounds {
+            match round {
+                CommitmentRound::Preprocessed => {
+                    for preprocessed_commit in mvk.flattened_preprocessed_commits() {
+                        challenger.observe(preprocessed_commit);
+                    }
+                }
+                CommitmentRound::CachedMain => {
+                    challenger.observe_slice(&proof.commitments.main_trace[..num_main_commits - 1]);
+                }
+                CommitmentRound::CommonMain => {
+                    challenger.observe(proof.commitments.main_trace[num_main_commits - 1].clone());
+                }
+                CommitmentRound::Custom { index, .. } => {
+                    if let Some(i) = custom_rounds.iter().position(|k| k == index) {
+                        challenger.observe(proof.commitments.custom[i].clone());
+                    }
+                }
+                CommitmentRound::AfterChallenge { .. } | CommitmentRound::Quotient => {}
+            }
+        }
         challenger.observe_slice(
             &proof
+                .core
                 .per_air
                 .iter()
                 .map(|ap| Val::<SC>::from_canonical_usize(log2_strict_usize(ap.degree)))
@@ -80,13 +495,14 @@ impl<'c, SC: StarkGenericConfig> MultiTraceStarkVerifier<'c, SC> {
         // Verification of challenge phase (except openings, which are done next).
         let rap_phase = self.config.rap_phase_seq();
         let exposed_values_per_air_per_phase = proof
+            .core
             .per_air
             .iter()
             .map(|proof| proof.exposed_values_after_challenge.clone())
             .collect_vec();
         let permutation_opened_values = proof
-            .opening
-            .values
+            .core
+            .opened_values
             .after_challenge
             .iter()
             .map(|after_challenge_per_matrix| {
@@ -99,22 +515,47 @@ impl<'c, SC: StarkGenericConfig> MultiTraceStarkVerifier<'c, SC> {
             })
             .collect_vec();
 
-        assert!(
-            proof.commitments.after_challenge.len() <= 1,
-            "at most one challenge phase currently supported"
-        );
-
+        let num_interactions_per_air = mvk
+            .per_air
+            .iter()
+            .map(|vk| vk.symbolic_constraints.interactions.len())
+            .collect_vec();
+        let log_trace_height_per_air = proof
+            .core
+            .per_air
+            .iter()
+            .map(|air_proof| log2_strict_usize(air_proof.degree))
+            .collect_vec();
         let (after_challenge_data, rap_phase_seq_result) = rap_phase.partially_verify(
             challenger,
             proof.rap_phase_seq_proof.as_ref(),
+            mvk.public_lookup_tables,
+            &num_interactions_per_air,
+            &log_trace_height_per_air,
             &exposed_values_per_air_per_phase,
             &proof.commitments.after_challenge,
             &permutation_opened_values,
         );
         // We don't want to bail on error yet; `OodEvaluationMismatch` should take precedence over
         // `ChallengePhaseError`, but we won't know if the former happens until later.
-        let rap_phase_seq_result =
-            rap_phase_seq_result.map_err(|_| VerificationError::ChallengePhaseError);
+        let rap_phase_seq_result = rap_phase_seq_result.map_err(|err| {
+            if rap_phase.is_sum_mismatch(&err) {
+                VerificationError::LogUpSumMismatch {
+                    error: err.to_string(),
+                    report: LogUpSumReport::new(
+                        mvk,
+                        bus_registry,
+                        &proof.get_air_ids(),
+                        &exposed_values_per_air_per_phase,
+                    ),
+                }
+            } else {
+                VerificationError::ChallengePhaseError(err.to_string())
+            }
+        });
+        if self.constraints_before_opening && rap_phase_seq_result.is_err() {
+            return rap_phase_seq_result;
+        }
 
         // Draw `alpha` challenge
         let alpha: SC::Challenge = challenger.sample_ext_element();
@@ -128,178 +569,406 @@ impl<'c, SC: StarkGenericConfig> MultiTraceStarkVerifier<'c, SC> {
         tracing::debug!("zeta: {zeta:?}");
 
         let pcs = self.config.pcs();
+        let zero_knowledge = mvk.features.contains(VkFeature::ZeroKnowledge);
         // Build domains
-        let (domains, quotient_chunks_domains): (Vec<_>, Vec<Vec<_>>) = mvk
-            .per_air
-            .iter()
-            .zip_eq(&proof.per_air)
-            .map(|(vk, air_proof)| {
-                let degree = air_proof.degree;
-                let quotient_degree = vk.quotient_degree;
-                let domain = pcs.natural_domain_for_degree(degree);
-                let quotient_domain =
-                    domain.create_disjoint_domain(degree * quotient_degree as usize);
-                let qc_domains = quotient_domain.split_domains(quotient_degree as usize);
-                (domain, qc_domains)
-            })
-            .unzip();
+        let (domains, quotient_domains, quotient_chunks_domains): (Vec<_>, Vec<_>, Vec<Vec<_>>) =
+            mvk.per_air
+                .iter()
+                .zip_eq(&proof.core.per_air)
+                .map(|(vk, air_proof)| {
+                    let degree = air_proof.degree;
+                    let quotient_degree = vk.quotient_degree;
+                    let domain = pcs.natural_domain_for_degree(degree);
+                    let quotient_domain =
+                        domain.create_disjoint_domain(degree * quotient_degree as usize);
+                    let qc_domains = quotient_domain.split_domains(quotient_degree as usize);
+                    (domain, quotient_domain, qc_domains)
+                })
+                .multiunzip();
         // Verify all opening proofs
-        let opened_values = &proof.opening.values;
+        let opened_values = &proof.core.opened_values;
         let trace_domain_and_openings =
             |domain: Domain<SC>,
              zeta: SC::Challenge,
-             values: &AdjacentOpenedValues<SC::Challenge>| {
-                (
-                    domain,
-                    vec![
-                        (zeta, values.local.clone()),
-                        (domain.next_point(zeta).unwrap(), values.next.clone()),
-                    ],
-                )
+             values: &AdjacentOpenedValues<SC::Challenge>,
+             extra_rotations: &[usize]| {
+                let points = [zeta, domain.next_point(zeta).unwrap()].into_iter().chain(
+                    extra_rotations
+                        .iter()
+                        .map(|&rotation| rotate_point(&domain, zeta, rotation)),
+                );
+                let values = [values.local.clone(), values.next.clone()]
+                    .into_iter()
+                    .chain(values.rotated.iter().cloned());
+                (domain, zip(points, values).collect_vec())
             };
+        // In zero-knowledge mode, every trace is committed with twice its height, see
+        // `CpuDevice::with_zero_knowledge` and `MultiStarkKeygenBuilder::set_zero_knowledge`. It is
+        // still opened at the points of the trace domain.
+        let committed_trace_domain_and_openings =
+            |domain: Domain<SC>,
+             zeta: SC::Challenge,
+             values: &AdjacentOpenedValues<SC::Challenge>,
+             extra_rotations: &[usize]| {
+                let (domain, openings) =
+                    trace_domain_and_openings(domain, zeta, values, extra_rotations);
+                if zero_knowledge {
+                    (pcs.natural_domain_for_degree(2 * domain.size()), openings)
+                } else {
+                    (domain, openings)
+                }
+            };
+        // The order of the matrices in the common main and custom rounds depends on the trace
+        // heights. `order[i]` is the natural index of the matrix at position `i` of a round, and
+        // `positions` is the inverse.
+        let round_order = |dims: Vec<(usize, usize)>| {
+            (
+                mvk.matrix_ordering.commit_order(&dims),
+                mvk.matrix_ordering.commit_positions(&dims),
+            )
+        };
+        let (common_main_order, common_main_positions) = round_order(
+            izip!(&mvk.per_air, &proof.core.per_air)
+                .filter(|(vk, _)| vk.has_common_main())
+                .map(|(vk, air_proof)| (air_proof.degree, vk.params.width.common_main))
+                .collect(),
+        );
+        let (custom_orders, custom_positions): (Vec<_>, Vec<_>) = custom_rounds
+            .iter()
+            .map(|&index| {
+                round_order(
+                    izip!(&mvk.per_air, &proof.core.per_air)
+                        .filter_map(|(vk, air_proof)| {
+                            Some((air_proof.degree, vk.custom_width(index)?))
+                        })
+                        .collect(),
+                )
+            })
+            .unzip();
         // Build the opening rounds
         // 1. First the preprocessed trace openings
         // Assumption: each AIR with preprocessed trace has its own commitment and opening values
-        let mut rounds: Vec<_> = mvk
-            .preprocessed_commits()
-            .into_iter()
-            .zip_eq(&domains)
-            .flat_map(|(commit, domain)| commit.map(|commit| (commit, *domain)))
+        let mut rounds: Vec<_> = izip!(mvk.preprocessed_commits(), &domains, &mvk.per_air)
+            .flat_map(|(commit, domain, vk)| {
+                commit.map(|commit| (commit, *domain, vk.params.extra_rotations.as_slice()))
+            })
             .zip_eq(&opened_values.preprocessed)
-            .map(|((commit, domain), values)| {
-                let domain_and_openings = trace_domain_and_openings(domain, zeta, values);
+            .map(|((commit, domain, extra_rotations), values)| {
+                let domain_and_openings =
+                    committed_trace_domain_and_openings(domain, zeta, values, extra_rotations);
                 (commit, vec![domain_and_openings])
             })
             .collect();
 
         // 2. Then the main trace openings
-
-        let num_main_commits = opened_values.main.len();
-        assert_eq!(num_main_commits, proof.commitments.main_trace.len());
         let mut main_commit_idx = 0;
         // All commits except the last one are cached main traces.
         izip!(&mvk.per_air, &domains).for_each(|(vk, domain)| {
             for _ in 0..vk.num_cached_mains() {
                 let commit = proof.commitments.main_trace[main_commit_idx].clone();
                 let value = &opened_values.main[main_commit_idx][0];
-                let domains_and_openings = vec![trace_domain_and_openings(*domain, zeta, value)];
+                let domains_and_openings = vec![committed_trace_domain_and_openings(
+                    *domain,
+                    zeta,
+                    value,
+                    &vk.params.extra_rotations,
+                )];
                 rounds.push((commit.clone(), domains_and_openings));
                 main_commit_idx += 1;
             }
         });
-        // In the last commit, each matrix corresponds to an AIR with a common main trace.
+        // In the last commit, each matrix corresponds to an AIR with a common main trace. The
+        // matrices are ordered by the matrix ordering of the key.
         {
             let values_per_mat = &opened_values.main[main_commit_idx];
             let commit = proof.commitments.main_trace[main_commit_idx].clone();
-            let domains_and_openings = mvk
+            let domains_and_rotations = mvk
                 .per_air
                 .iter()
                 .zip_eq(&domains)
-                .filter_map(|(vk, domain)| vk.has_common_main().then_some(*domain))
+                .filter_map(|(vk, domain)| {
+                    vk.has_common_main()
+                        .then_some((*domain, vk.params.extra_rotations.as_slice()))
+                })
+                .collect_vec();
+            let domains_and_openings = common_main_order
+                .iter()
+                .map(|&i| domains_and_rotations[i])
                 .zip_eq(values_per_mat)
-                .map(|(domain, values)| trace_domain_and_openings(domain, zeta, values))
+                .map(|((domain, extra_rotations), values)| {
+                    committed_trace_domain_and_openings(domain, zeta, values, extra_rotations)
+                })
                 .collect_vec();
             rounds.push((commit.clone(), domains_and_openings));
         }
 
-        // 3. Then after_challenge trace openings, at most 1 phase for now.
-        // All AIRs with interactions should an after challenge trace.
-        let after_challenge_domain_per_air = mvk
-            .per_air
-            .iter()
-            .zip_eq(&domains)
-            .filter_map(|(vk, domain)| vk.has_interaction().then_some(*domain))
-            .collect_vec();
-        if after_challenge_domain_per_air.is_empty() {
-            assert_eq!(proof.commitments.after_challenge.len(), 0);
-            assert_eq!(opened_values.after_challenge.len(), 0);
-        } else {
-            let after_challenge_commit = proof.commitments.after_challenge[0].clone();
-            let domains_and_openings = after_challenge_domain_per_air
-                .into_iter()
-                .zip_eq(&opened_values.after_challenge[0])
-                .map(|(domain, values)| trace_domain_and_openings(domain, zeta, values))
+        // 3. Then the custom round openings. Each custom round commitment has one matrix per AIR
+        // in the round, ordered by the matrix ordering of the key. Each matrix is opened on the
+        // trace domain of its AIR, so the PCS rejects a matrix of another height.
+        for (&index, order, commit, values_per_mat) in izip!(
+            &custom_rounds,
+            &custom_orders,
+            &proof.commitments.custom,
+            &opened_values.custom
+        ) {
+            let domains_and_rotations = izip!(&mvk.per_air, &domains)
+                .filter(|(vk, _)| vk.custom_width(index).is_some())
+                .map(|(vk, domain)| (*domain, vk.params.extra_rotations.as_slice()))
                 .collect_vec();
-            rounds.push((after_challenge_commit, domains_and_openings));
+            if domains_and_rotations.len() != values_per_mat.len() {
+                return Err(ProofShapeError::NumOpenedMatrices {
+                    round: mvk.custom_round(index),
+                    expected: domains_and_rotations.len(),
+                    found: values_per_mat.len(),
+                }
+                .into());
+            }
+            let domains_and_openings = order
+                .iter()
+                .map(|&i| domains_and_rotations[i])
+                .zip(values_per_mat)
+                .map(|((domain, extra_rotations), values)| {
+                    committed_trace_domain_and_openings(domain, zeta, values, extra_rotations)
+                })
+                .collect_vec();
+            rounds.push((commit.clone(), domains_and_openings));
         }
 
-        let quotient_domains_and_openings = opened_values
-            .quotient
+        // 4. Then after_challenge trace openings, one round per challenge phase.
+        // An AIR has an after challenge trace in each phase its width is given for.
+        for (phase, (commit, values_per_mat)) in proof
+            .commitments
+            .after_challenge
             .iter()
-            .zip_eq(&quotient_chunks_domains)
-            .flat_map(|(chunk, quotient_chunks_domains_per_air)| {
+            .zip_eq(&opened_values.after_challenge)
+            .enumerate()
+        {
+            let domains_and_openings = mvk
+                .per_air
+                .iter()
+                .zip_eq(&domains)
+                .filter_map(|(vk, domain)| {
+                    (phase < vk.params.width.after_challenge.len()).then_some(*domain)
+                })
+                .zip_eq(values_per_mat)
+                .map(|(domain, values)| {
+                    committed_trace_domain_and_openings(domain, zeta, values, &[])
+                })
+                .collect_vec();
+            rounds.push((commit.clone(), domains_and_openings));
+        }
+
+        // In zero-knowledge mode, the random mask of each quotient is committed after its chunks,
+        // on the whole quotient domain.
+        let quotient_domains_and_openings = izip!(
+            &opened_values.quotient,
+            &quotient_chunks_domains,
+            &quotient_domains
+        )
+        .flat_map(
+            |(chunk, quotient_chunks_domains_per_air, &quotient_domain)| {
+                let mask_domain = zero_knowledge.then_some(quotient_domain);
                 chunk
                     .iter()
-                    .zip_eq(quotient_chunks_domains_per_air)
-                    .map(|(values, &domain)| (domain, vec![(zeta, values.clone())]))
-            })
-            .collect_vec();
+                    .zip_eq(
+                        quotient_chunks_domains_per_air
+                            .iter()
+                            .copied()
+                            .chain(mask_domain),
+                    )
+                    .map(|(values, domain)| (domain, vec![(zeta, values.clone())]))
+            },
+        )
+        .collect_vec();
         rounds.push((
             proof.commitments.quotient.clone(),
             quotient_domains_and_openings,
         ));
 
-        pcs.verify(rounds, &proof.opening.proof, challenger)
-            .map_err(|e| VerificationError::InvalidOpeningArgument(format!("{:?}", e)))?;
-
-        let mut preprocessed_idx = 0usize; // preprocessed commit idx
-        let num_phases = mvk.num_phases();
-        let mut after_challenge_idx = vec![0usize; num_phases];
-        let mut cached_main_commit_idx = 0;
-        let mut common_main_matrix_idx = 0;
+        // The constraint checks do not use the challenger, so they can be done before or after
+        // verifying the opening proof.
+        let verify_constraints = || -> Result<(), VerificationError> {
+            let mut preprocessed_idx = 0usize; // preprocessed commit idx
+            let num_phases = mvk.num_phases();
+            let mut after_challenge_idx = vec![0usize; num_phases];
+            let mut cached_main_commit_idx = 0;
+            let mut common_main_matrix_idx = 0;
+            let mut custom_matrix_idx = vec![0usize; custom_rounds.len()];
 
-        // Verify each RAP's constraints
-        for (domain, qc_domains, quotient_chunks, vk, air_proof) in izip!(
-            domains,
-            quotient_chunks_domains,
-            &opened_values.quotient,
-            &mvk.per_air,
-            &proof.per_air
-        ) {
-            let preprocessed_values = vk.preprocessed_data.as_ref().map(|_| {
-                let values = &opened_values.preprocessed[preprocessed_idx];
-                preprocessed_idx += 1;
-                values
-            });
-            let mut partitioned_main_values = Vec::with_capacity(vk.num_cached_mains());
-            for _ in 0..vk.num_cached_mains() {
-                partitioned_main_values.push(&opened_values.main[cached_main_commit_idx][0]);
-                cached_main_commit_idx += 1;
-            }
-            if vk.has_common_main() {
-                partitioned_main_values
-                    .push(&opened_values.main.last().unwrap()[common_main_matrix_idx]);
-                common_main_matrix_idx += 1;
-            }
-            // loop through challenge phases of this single RAP
-            let after_challenge_values = if vk.has_interaction() {
-                (0..num_phases)
+            // Verify each RAP's constraints
+            for (air_idx, (&domain, qc_domains, quotient_chunks, vk, air_proof)) in izip!(
+                &domains,
+                &quotient_chunks_domains,
+                &opened_values.quotient,
+                &mvk.per_air,
+                &proof.core.per_air
+            )
+            .enumerate()
+            {
+                let preprocessed_values = vk.preprocessed_data.as_ref().map(|_| {
+                    let values = &opened_values.preprocessed[preprocessed_idx];
+                    preprocessed_idx += 1;
+                    values
+                });
+                let mut partitioned_main_values = Vec::with_capacity(vk.num_cached_mains());
+                for _ in 0..vk.num_cached_mains() {
+                    partitioned_main_values.push(&opened_values.main[cached_main_commit_idx][0]);
+                    cached_main_commit_idx += 1;
+                }
+                if vk.has_common_main() {
+                    let position = common_main_positions[common_main_matrix_idx];
+                    partitioned_main_values.push(&opened_values.main.last().unwrap()[position]);
+                    common_main_matrix_idx += 1;
+                }
+                for &(index, _) in &vk.params.width.custom {
+                    let i = custom_rounds.iter().position(|&k| k == index).unwrap();
+                    let position = custom_positions[i][custom_matrix_idx[i]];
+                    partitioned_main_values.push(&opened_values.custom[i][position]);
+                    custom_matrix_idx[i] += 1;
+                }
+                // loop through challenge phases of this single RAP
+                let after_challenge_values = (0..vk.params.width.after_challenge.len())
                     .map(|phase_idx| {
                         let matrix_idx = after_challenge_idx[phase_idx];
                         after_challenge_idx[phase_idx] += 1;
                         &opened_values.after_challenge[phase_idx][matrix_idx]
                     })
-                    .collect_vec()
-            } else {
-                vec![]
-            };
-            verify_single_rap_constraints::<SC>(
-                &vk.symbolic_constraints.constraints,
-                preprocessed_values,
-                partitioned_main_values,
-                after_challenge_values,
-                quotient_chunks,
-                domain,
-                &qc_domains,
-                zeta,
-                alpha,
-                &after_challenge_data.challenges_per_phase,
-                &air_proof.public_values,
-                &air_proof.exposed_values_after_challenge,
-            )?;
+                    .collect_vec();
+                // The first after challenge column of an AIR with an eq point is not constrained
+                // by the AIR, so its opening is checked directly.
+                if let Some(Some(eq_point)) = after_challenge_data.eq_points_per_air.get(air_idx) {
+                    let opened_eq = after_challenge_values
+                        .first()
+                        .and_then(|values| values.local.get(..SC::Challenge::D))
+                        .ok_or_else(|| ProofShapeError::OpenedValues {
+                            air_id: air_proof.air_id,
+                            air_name: vk.air_name.clone(),
+                            round: CommitmentRound::AfterChallenge { phase: 0 },
+                            width: vk
+                                .params
+                                .width
+                                .after_challenge
+                                .first()
+                                .copied()
+                                .unwrap_or(0)
+                                * SC::Challenge::D,
+                            num_rotations: 0,
+                        })?
+                        .iter()
+                        .enumerate()
+                        .map(|(e_i, &c)| SC::Challenge::monomial(e_i) * c)
+                        .sum::<SC::Challenge>();
+                    if opened_eq != eval_eq_column(&domain, eq_point, zeta) {
+                        return Err(VerificationError::EqColumnMismatch {
+                            air_id: air_proof.air_id,
+                            air_name: vk.air_name.clone(),
+                        });
+                    }
+                }
+                let (quotient_chunks, quotient_mask) = if zero_knowledge {
+                    let (mask, chunks) = quotient_chunks.split_last().ok_or_else(|| {
+                        ProofShapeError::QuotientChunks {
+                            air_id: air_proof.air_id,
+                            air_name: vk.air_name.clone(),
+                            num_chunks: vk.quotient_degree as usize + 1,
+                            chunk_len: SC::Challenge::D,
+                        }
+                    })?;
+                    (chunks, Some(mask.as_slice()))
+                } else {
+                    (quotient_chunks.as_slice(), None)
+                };
+                verify_single_rap_constraints::<SC>(
+                    air_proof.air_id,
+                    &vk.air_name,
+                    &vk.symbolic_constraints.constraints,
+                    &vk.params.extra_rotations,
+                    preprocessed_values,
+                    partitioned_main_values,
+                    after_challenge_values,
+                    quotient_chunks,
+                    quotient_mask,
+                    domain,
+                    qc_domains,
+                    zeta,
+                    alpha,
+                    &after_challenge_data.challenges_per_phase,
+                    &air_proof.public_values,
+                    &vk.periodic_columns,
+                    &air_proof.exposed_values_after_challenge,
+                )?;
+            }
+            Ok(())
+        };
+
+        if let Some(pcs_shape) = self.pcs_shape.as_ref().filter(|_| self.early_queries > 0) {
+            let num_queries = self
+                .early_queries
+                .min((pcs_shape.num_query_proofs)(&proof.pcs_proof));
+            let early_proof = (pcs_shape.truncate_query_proofs)(&proof.pcs_proof, num_queries);
+            if let Err(err) = pcs.verify(rounds.clone(), &early_proof, &mut challenger.clone()) {
+                let query = pcs_shape.first_rejected_query(pcs, &rounds, &early_proof, challenger);
+                return Err(VerificationError::InvalidOpeningArgument {
+                    query,
+                    error: format!("{err:?}"),
+                });
+            }
+        }
+        if self.constraints_before_opening {
+            verify_constraints()?;
+        }
+        // The PCS error does not say which query failed, which the shape of the PCS locates by
+        // verifying the opening proof again.
+        let pcs_retry = self
+            .pcs_shape
+            .as_ref()
+            .map(|pcs_shape| (pcs_shape, rounds.clone(), challenger.clone()));
+        if let Err(err) = pcs.verify(rounds, &proof.pcs_proof, challenger) {
+            let query = pcs_retry.and_then(|(pcs_shape, rounds, challenger)| {
+                pcs_shape.first_rejected_query(pcs, &rounds, &proof.pcs_proof, &challenger)
+            });
+            return Err(VerificationError::InvalidOpeningArgument {
+                query,
+                error: format!("{err:?}"),
+            });
+        }
+        if !self.constraints_before_opening {
+            verify_constraints()?;
         }
 
         // If we made it this far, use the `rap_phase_result` as the final result.
         rap_phase_seq_result
     }
+
+    /// Checks that the AIRs with interactions of `mvk`, the AIRs of `proof`, have verifying keys
+    /// for the challenge phase of the configuration and its log-up parameters.
+    fn check_rap_phase_params(
+        &self,
+        mvk: &MultiStarkVerifyingKeyView<Val<SC>, Com<SC>>,
+        proof: &Proof<SC>,
+    ) -> Result<(), VerificationError> {
+        let log_up_params = self.config.rap_phase_seq().log_up_params();
+        for (vk, air_proof) in zip(&mvk.per_air, &proof.core.per_air) {
+            if !vk.has_interaction() {
+                continue;
+            }
+            if vk.rap_phase_seq_kind != SC::RapPhaseSeq::ID {
+                return Err(VerificationError::RapPhaseSeqKindMismatch {
+                    air_id: air_proof.air_id,
+                    air_name: vk.air_name.clone(),
+                    expected: SC::RapPhaseSeq::ID,
+                    found: vk.rap_phase_seq_kind,
+                });
+            }
+            if vk.log_up_params != log_up_params {
+                return Err(VerificationError::LogUpParamsMismatch {
+                    air_id: air_proof.air_id,
+                    air_name: vk.air_name.clone(),
+                    expected: log_up_params,
+                    found: vk.log_up_params,
+                });
+            }
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,54 @@
+use itertools::Itertools;
+
+/// The public values of each AIR of a proof, by AIR id or by AIR name, for application checks
+/// after verification, see
+/// [verify_with_public_values_check](crate::engine::StarkEngine::verify_with_public_values_check)
+/// and [Proof::public_values_view](crate::proof::Proof::public_values_view).
+pub struct PublicValuesView<'a, F> {
+    /// Name of each AIR of the key.
+    air_names: Vec<&'a str>,
+    /// AIR id and public values of each AIR of the proof, in increasing AIR id order.
+    per_air: Vec<(usize, &'a [F])>,
+}
+
+impl<'a, F> PublicValuesView<'a, F> {
+    pub(crate) fn new(air_names: Vec<&'a str>, per_air: Vec<(usize, &'a [F])>) -> Self {
+        Self { air_names, per_air }
+    }
+
+    /// The public values of the AIR with id `air_id`, or `None` if the AIR is not in the proof.
+    pub fn get(&self, air_id: usize) -> Option<&'a [F]> {
+        self.per_air
+            .iter()
+            .find(|(id, _)| *id == air_id)
+            .map(|&(_, values)| values)
+    }
+
+    /// The public values of the AIR named `air_name`, see
+    /// [add_named_air](crate::keygen::MultiStarkKeygenBuilder::add_named_air), or `None` if the
+    /// AIR is not in the proof or several AIRs of the key have this name.
+    pub fn get_by_name(&self, air_name: &str) -> Option<&'a [F]> {
+        let mut air_ids = self.air_names.iter().positions(|&name| name == air_name);
+        match (air_ids.next(), air_ids.next()) {
+            (Some(air_id), None) => self.get(air_id),
+            _ => None,
+        }
+    }
+
+    /// Same as [get](Self::get), with an error naming the AIR if it is not in the proof, to be
+    /// returned by an application check.
+    pub fn require(&self, air_id: usize) -> Result<&'a [F], String> {
+        self.get(air_id).ok_or_else(|| {
+            let air_name = self.air_names.get(air_id).copied().unwrap_or("<unknown>");
+            format!("AIR {air_name} (air_id = {air_id}) is not in the proof")
+        })
+    }
+
+    /// Iterates over the AIR id, name and public values of each AIR of the proof, in increasing
+    /// AIR id order.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &'a str, &'a [F])> + '_ {
+        self.per_air
+            .iter()
+            .map(|&(air_id, values)| (air_id, self.air_names[air_id], values))
+    }
+}
@@ -0,0 +1,441 @@
+use derivative::Derivative;
+use itertools::{izip, Itertools};
+use p3_commit::Pcs;
+use p3_field::{Field, FieldExtensionAlgebra};
+use p3_util::log2_ceil_usize;
+
+use super::error::{ProofShapeError, VerificationError};
+use crate::{
+    config::{Com, Domain, PcsProof, StarkGenericConfig, Val},
+    keygen::types::{CommitmentRound, MultiStarkVerifyingKey, StarkVerifyingKey, VkFeature},
+    proof::{AdjacentOpenedValues, Proof},
+};
+
+/// The shape of the opening proofs of the PCS of a config, which is otherwise opaque to the
+/// verifier, see [with_pcs_shape](super::MultiTraceStarkVerifier::with_pcs_shape) and
+/// [StarkEngine::pcs_shape](crate::engine::StarkEngine::pcs_shape).
+#[derive(Derivative)]
+#[derivative(Clone(bound = ""), Copy(bound = ""), Debug(bound = ""))]
+pub struct PcsShape<SC: StarkGenericConfig> {
+    /// The log2 of the blowup of the PCS: a trace of height `2^k` is committed on a domain of
+    /// size `2^(k + log_blowup)`, which must be a subgroup of the field.
+    pub log_blowup: usize,
+    /// The number of queries of an opening proof.
+    pub num_queries: usize,
+    /// Counts the queries of an opening proof.
+    pub num_query_proofs: fn(&PcsProof<SC>) -> usize,
+    /// A copy of an opening proof with only its first queries, with which the verifier locates
+    /// the first query of a rejected opening proof.
+    pub truncate_query_proofs: fn(&PcsProof<SC>, usize) -> PcsProof<SC>,
+}
+
+impl<SC: StarkGenericConfig> PcsShape<SC> {
+    /// The maximum constraint degree the PCS supports, `2^log_blowup + 1`: the quotient of such a
+    /// constraint splits into `2^log_blowup` chunks, each of the degree of a trace.
+    pub fn max_constraint_degree(&self) -> usize {
+        (1 << self.log_blowup) + 1
+    }
+
+    /// The index of the first query of `proof` that `pcs` rejects, or `None` if `pcs` rejects the
+    /// proof before any query, e.g. for its proof of work.
+    ///
+    /// The PCS samples the index of each query in order and checks the queries one after the
+    /// other, so the proof with its first `n` queries is rejected exactly when one of these
+    /// queries is. The first rejected query is found by bisection on `n`.
+    pub(crate) fn first_rejected_query(
+        &self,
+        pcs: &SC::Pcs,
+        rounds: &PcsRounds<SC>,
+        proof: &PcsProof<SC>,
+        challenger: &SC::Challenger,
+    ) -> Option<usize> {
+        let rejects = |num_queries| {
+            let proof = (self.truncate_query_proofs)(proof, num_queries);
+            pcs.verify(rounds.clone(), &proof, &mut challenger.clone())
+                .is_err()
+        };
+        if rejects(0) {
+            return None;
+        }
+        // The proof with its first `accepted` queries is accepted, the one with its first
+        // `rejected` queries is not.
+        let (mut accepted, mut rejected) = (0, (self.num_query_proofs)(proof));
+        if !rejects(rejected) {
+            return None;
+        }
+        while rejected - accepted > 1 {
+            let mid = (accepted + rejected) / 2;
+            if rejects(mid) {
+                rejected = mid;
+            } else {
+                accepted = mid;
+            }
+        }
+        Some(accepted)
+    }
+}
+
+/// The commitments of the opening proof of a PCS, with the domain of each matrix and its claimed
+/// evaluations at each point.
+pub(crate) type PcsRounds<SC> = Vec<(
+    Com<SC>,
+    Vec<(
+        Domain<SC>,
+        Vec<(
+            <SC as StarkGenericConfig>::Challenge,
+            Vec<<SC as StarkGenericConfig>::Challenge>,
+        )>,
+    )>,
+)>;
+
+/// Checks that the shape of `proof` is consistent with the verifying key: the AIR ids, trace
+/// heights within the two-adicity of the field, number of public and exposed values, number of
+/// commitments (including custom rounds), the number and widths of all opened values, and the
+/// presence of the partial proof of the challenge phase.
+///
+/// With the shape of the PCS, the committed domains of the traces, `2^log_blowup` times their
+/// heights, must also be within the two-adicity, and the opening proof must have one query proof
+/// per query. Without it, the blowup is only known to be at least the quotient degree of each
+/// AIR.
+///
+/// Every length must match exactly, so that a proof cannot carry surplus data that the verifier
+/// would ignore, e.g. extra opened values, or a partial proof of the challenge phase when no AIR
+/// of the proof has interactions.
+///
+/// This check does not hash or do any field arithmetic, so it is the cheapest way to reject a
+/// malformed proof. The verifier runs it before anything else, so that the rest of the
+/// verification never indexes past the opened values of a malformed proof.
+pub fn validate_proof_shape<SC: StarkGenericConfig>(
+    mvk: &MultiStarkVerifyingKey<SC>,
+    proof: &Proof<SC>,
+    pcs_shape: Option<&PcsShape<SC>>,
+) -> Result<(), VerificationError> {
+    let air_ids = proof.get_air_ids();
+    if air_ids.is_empty() {
+        return Err(ProofShapeError::NoAirs.into());
+    }
+    if !air_ids.iter().tuple_windows().all(|(a, b)| a < b)
+        || air_ids.iter().any(|&air_id| air_id >= mvk.per_air.len())
+    {
+        return Err(VerificationError::InvalidAirIds(air_ids));
+    }
+    let vks = air_ids
+        .iter()
+        .map(|&air_id| &mvk.per_air[air_id])
+        .collect_vec();
+    let ext_degree = <SC::Challenge as FieldExtensionAlgebra<Val<SC>>>::D;
+    let zero_knowledge = mvk.features.contains(VkFeature::ZeroKnowledge);
+    let two_adicity = (Val::<SC>::order() - 1u32).trailing_zeros().unwrap_or(0) as usize;
+
+    for (vk, air_proof) in izip!(&vks, &proof.core.per_air) {
+        let (air_id, air_name) = (air_proof.air_id, vk.air_name.clone());
+        let params = &vk.params;
+        if !air_proof.degree.is_power_of_two() {
+            return Err(ProofShapeError::TraceHeightNotPowerOfTwo {
+                air_id,
+                air_name,
+                height: air_proof.degree,
+            }
+            .into());
+        }
+        // In zero-knowledge mode, the trace is committed with twice its height.
+        let log_height = air_proof.degree.trailing_zeros() as usize;
+        let log_quotient_degree = log2_ceil_usize(vk.quotient_degree as usize);
+        let log_blowup = pcs_shape.map_or(log_quotient_degree, |shape| {
+            shape.log_blowup.max(log_quotient_degree)
+        });
+        if log_height + zero_knowledge as usize + log_blowup > two_adicity {
+            return Err(ProofShapeError::TraceHeightAboveTwoAdicity {
+                air_id,
+                air_name,
+                height: air_proof.degree,
+                log_blowup,
+                two_adicity,
+            }
+            .into());
+        }
+        if air_proof.public_values.len() != params.num_public_values {
+            return Err(ProofShapeError::NumPublicValues {
+                air_id,
+                air_name,
+                expected: params.num_public_values,
+                found: air_proof.public_values.len(),
+            }
+            .into());
+        }
+        let num_exposed_values: &[usize] = if vk.has_interaction() {
+            &params.num_exposed_values_after_challenge
+        } else {
+            &[]
+        };
+        check_num_exposed_values(
+            air_id,
+            &air_name,
+            num_exposed_values,
+            &air_proof.exposed_values_after_challenge,
+        )?;
+    }
+
+    // Each cached main trace has its own commitment, followed by one commitment for all
+    // common main traces.
+    let num_main_commits = vks.iter().map(|vk| vk.num_cached_mains()).sum::<usize>() + 1;
+    // The challenge phase only has a partial proof if an AIR of the proof has interactions.
+    let num_partial_proofs = vks.iter().any(|vk| vk.has_interaction()) as usize;
+    let num_phases = vks
+        .iter()
+        .filter(|vk| vk.has_interaction())
+        .map(|vk| vk.params.width.after_challenge.len())
+        .max()
+        .unwrap_or(0);
+    let mvk_view = mvk.view(&air_ids);
+    let custom_rounds = mvk_view.present_custom_rounds();
+    let commitments = &proof.commitments;
+    let opened_values = &proof.core.opened_values;
+    let num_preprocessed = vks
+        .iter()
+        .filter(|vk| vk.preprocessed_data.is_some())
+        .count();
+    for (field, expected, found) in [
+        (
+            "commitments.main_trace",
+            num_main_commits,
+            commitments.main_trace.len(),
+        ),
+        (
+            "commitments.custom",
+            custom_rounds.len(),
+            commitments.custom.len(),
+        ),
+        (
+            "commitments.after_challenge",
+            num_phases,
+            commitments.after_challenge.len(),
+        ),
+        (
+            "rap_phase_seq_proof",
+            num_partial_proofs,
+            proof.rap_phase_seq_proof.is_some() as usize,
+        ),
+        (
+            "core.opened_values.preprocessed",
+            num_preprocessed,
+            opened_values.preprocessed.len(),
+        ),
+        (
+            "core.opened_values.main",
+            num_main_commits,
+            opened_values.main.len(),
+        ),
+        (
+            "core.opened_values.custom",
+            custom_rounds.len(),
+            opened_values.custom.len(),
+        ),
+        (
+            "core.opened_values.after_challenge",
+            num_phases,
+            opened_values.after_challenge.len(),
+        ),
+        (
+            "core.opened_values.quotient",
+            vks.len(),
+            opened_values.quotient.len(),
+        ),
+    ]
+    .into_iter()
+    .chain(pcs_shape.map(|shape| {
+        (
+            "pcs_proof.query_proofs",
+            shape.num_queries,
+            (shape.num_query_proofs)(&proof.pcs_proof),
+        )
+    })) {
+        if found != expected {
+            return Err(ProofShapeError::NumEntries {
+                field,
+                expected,
+                found,
+            }
+            .into());
+        }
+    }
+
+    // An AIR has an after challenge trace in each phase its width is given for.
+    for (phase, values_per_mat) in opened_values.after_challenge.iter().enumerate() {
+        let expected = vks
+            .iter()
+            .filter(|vk| vk.has_interaction() && phase < vk.params.width.after_challenge.len())
+            .count();
+        if values_per_mat.len() != expected {
+            return Err(ProofShapeError::NumOpenedMatrices {
+                round: CommitmentRound::AfterChallenge { phase },
+                expected,
+                found: values_per_mat.len(),
+            }
+            .into());
+        }
+    }
+    let mut preprocessed_values = opened_values.preprocessed.iter();
+    let mut cached_main_values = opened_values.main.iter();
+    let mut after_challenge_values = opened_values
+        .after_challenge
+        .iter()
+        .map(|per_phase| per_phase.iter())
+        .collect_vec();
+    for (vk, air_proof, quotient_chunks) in
+        izip!(&vks, &proof.core.per_air, &opened_values.quotient)
+    {
+        let check = |values: &AdjacentOpenedValues<SC::Challenge>,
+                     round: CommitmentRound,
+                     width: usize,
+                     num_rotations: usize| {
+            check_opened_values(vk, air_proof.air_id, values, round, width, num_rotations)
+        };
+        let width = &vk.params.width;
+        let rotations = vk.params.extra_rotations.len();
+        if let Some(width) = width.preprocessed {
+            let values = preprocessed_values.next().unwrap();
+            check(values, CommitmentRound::Preprocessed, width, rotations)?;
+        }
+        for &width in &width.cached_mains {
+            let values = cached_main_values.next().unwrap();
+            if values.len() != 1 {
+                return Err(ProofShapeError::NumOpenedMatrices {
+                    round: CommitmentRound::CachedMain,
+                    expected: 1,
+                    found: values.len(),
+                }
+                .into());
+            }
+            check(&values[0], CommitmentRound::CachedMain, width, rotations)?;
+        }
+        if vk.has_interaction() {
+            for (phase, (phase_values, &width)) in
+                izip!(&mut after_challenge_values, &width.after_challenge).enumerate()
+            {
+                let values = phase_values.next().unwrap();
+                let round = CommitmentRound::AfterChallenge { phase };
+                check(values, round, width * ext_degree, 0)?;
+            }
+        }
+        check_quotient_chunks(
+            vk,
+            air_proof.air_id,
+            quotient_chunks,
+            ext_degree,
+            zero_knowledge,
+        )?;
+    }
+
+    // The common main and custom rounds have one matrix per AIR in the round, in the order
+    // given by the matrix ordering of the key.
+    let check_round =
+        |values: &[AdjacentOpenedValues<SC::Challenge>],
+         round: CommitmentRound,
+         matrix_width: &dyn Fn(&StarkVerifyingKey<Val<SC>, Com<SC>>) -> Option<usize>|
+         -> Result<(), VerificationError> {
+            let (dims, airs): (Vec<_>, Vec<_>) = izip!(&vks, &proof.core.per_air)
+                .filter_map(|(vk, air_proof)| {
+                    let width = matrix_width(vk)?;
+                    Some(((air_proof.degree, width), (vk, air_proof.air_id, width)))
+                })
+                .unzip();
+            if values.len() != dims.len() {
+                return Err(ProofShapeError::NumOpenedMatrices {
+                    round,
+                    expected: dims.len(),
+                    found: values.len(),
+                }
+                .into());
+            }
+            for (values, i) in izip!(values, mvk.matrix_ordering.commit_order(&dims)) {
+                let (vk, air_id, width) = airs[i];
+                let rotations = vk.params.extra_rotations.len();
+                check_opened_values(vk, air_id, values, round.clone(), width, rotations)?;
+            }
+            Ok(())
+        };
+    check_round(
+        &opened_values.main[num_main_commits - 1],
+        CommitmentRound::CommonMain,
+        &|vk| vk.has_common_main().then_some(vk.params.width.common_main),
+    )?;
+    for (&index, values) in izip!(&custom_rounds, &opened_values.custom) {
+        check_round(values, mvk_view.custom_round(index), &|vk| {
+            vk.custom_width(index)
+        })?;
+    }
+    Ok(())
+}
+
+pub(super) fn check_num_exposed_values<Challenge>(
+    air_id: usize,
+    air_name: &str,
+    expected: &[usize],
+    exposed_values_after_challenge: &[Vec<Challenge>],
+) -> Result<(), VerificationError> {
+    let found = exposed_values_after_challenge
+        .iter()
+        .map(|values| values.len())
+        .collect_vec();
+    if found != expected {
+        return Err(ProofShapeError::NumExposedValues {
+            air_id,
+            air_name: air_name.to_string(),
+            expected: expected.to_vec(),
+            found,
+        }
+        .into());
+    }
+    Ok(())
+}
+
+fn check_opened_values<Val, Com, Challenge>(
+    vk: &StarkVerifyingKey<Val, Com>,
+    air_id: usize,
+    values: &AdjacentOpenedValues<Challenge>,
+    round: CommitmentRound,
+    width: usize,
+    num_rotations: usize,
+) -> Result<(), VerificationError> {
+    if values.local.len() != width
+        || values.next.len() != width
+        || values.rotated.len() != num_rotations
+        || values.rotated.iter().any(|row| row.len() != width)
+    {
+        return Err(ProofShapeError::OpenedValues {
+            air_id,
+            air_name: vk.air_name.clone(),
+            round,
+            width,
+            num_rotations,
+        }
+        .into());
+    }
+    Ok(())
+}
+
+pub(super) fn check_quotient_chunks<Val, Com, Challenge>(
+    vk: &StarkVerifyingKey<Val, Com>,
+    air_id: usize,
+    quotient_chunks: &[Vec<Challenge>],
+    ext_degree: usize,
+    zero_knowledge: bool,
+) -> Result<(), VerificationError> {
+    // In zero-knowledge mode, the opening of the random mask of the quotient follows the chunks.
+    let num_chunks = vk.quotient_degree as usize + zero_knowledge as usize;
+    if quotient_chunks.len() != num_chunks
+        || quotient_chunks
+            .iter()
+            .any(|chunk| chunk.len() != ext_degree)
+    {
+        return Err(ProofShapeError::QuotientChunks {
+            air_id,
+            air_name: vk.air_name.clone(),
+            num_chunks,
+            chunk_len: ext_degree,
+        }
+        .into());
+    }
+    Ok(())
+}
@@ -0,0 +1,205 @@
+use std::sync::Arc;
+
+use openvm_stark_backend::{
+    interaction::InteractionBuilder,
+    keygen::{
+        types::{MultiStarkProvingKey, VkFeature},
+        AbsentAirError, KeygenError,
+    },
+    p3_field::FieldAlgebra,
+    prover::types::{AirProofInput, ProofInput},
+    rap::{BaseAirWithPublicValues, PartitionedBaseAir},
+    verifier::VerificationError,
+};
+use openvm_stark_sdk::{
+    config::baby_bear_poseidon2::{default_engine, BabyBearPoseidon2Config},
+    dummy_airs::fib_lookup,
+    engine::StarkEngine,
+};
+use p3_air::{Air, BaseAir};
+use p3_matrix::Matrix;
+
+type SC = BabyBearPoseidon2Config;
+
+const N: usize = 16;
+const FIB_AIR_ID: usize = 0;
+const SEND_AIR_ID: usize = 1;
+const RECEIVE_AIR_ID: usize = 2;
+
+/// AIR 1 sends on bus 0 what AIR 2 receives, and both may be absent. AIR 0 is a Fibonacci AIR.
+fn keygen() -> MultiStarkProvingKey<SC> {
+    let mut keygen_builder = default_engine().keygen_builder();
+    let [_, send_air_id, receive_air_id] = fib_lookup::add_airs(&mut keygen_builder);
+    keygen_builder.set_may_be_absent(send_air_id);
+    keygen_builder.set_may_be_absent(receive_air_id);
+    keygen_builder.generate_pk()
+}
+
+fn proof_input(air_ids: &[usize]) -> ProofInput<SC> {
+    let per_air = air_ids
+        .iter()
+        .map(|&air_id| {
+            let input = if air_id == FIB_AIR_ID {
+                fib_lookup::fib_input(N)
+            } else {
+                // The same messages for the sender and the receiver.
+                AirProofInput::simple_no_pis(fib_lookup::messages())
+            };
+            (air_id, input)
+        })
+        .collect();
+    ProofInput::new(per_air)
+}
+
+#[test]
+fn test_absent_airs() {
+    let engine = default_engine();
+    let pk = keygen();
+    let vk = pk.get_vk();
+    assert!(vk.features.contains(VkFeature::AbsentAirs));
+
+    let proof = engine.prove(
+        &pk,
+        proof_input(&[FIB_AIR_ID]).with_absent_airs([RECEIVE_AIR_ID, SEND_AIR_ID]),
+    );
+    assert_eq!(proof.get_air_ids(), vec![FIB_AIR_ID]);
+    assert_eq!(proof.core.absent_air_ids, vec![SEND_AIR_ID, RECEIVE_AIR_ID]);
+    engine.verify(&vk, &proof).expect("Verification failed");
+
+    // Every AIR of the key is either proven or absent.
+    let mut tampered = proof.clone();
+    tampered.core.absent_air_ids = vec![SEND_AIR_ID];
+    assert_eq!(
+        engine.verify(&vk, &tampered),
+        Err(VerificationError::MissingAirs {
+            air_ids: vec![RECEIVE_AIR_ID],
+            air_names: vec![vk.air_name(RECEIVE_AIR_ID).to_string()],
+        })
+    );
+    tampered.core.absent_air_ids = vec![FIB_AIR_ID, SEND_AIR_ID, RECEIVE_AIR_ID];
+    assert_eq!(
+        engine.verify(&vk, &tampered),
+        Err(VerificationError::InvalidAbsentAirIds(vec![
+            FIB_AIR_ID,
+            SEND_AIR_ID,
+            RECEIVE_AIR_ID
+        ]))
+    );
+    tampered.core.per_air.clear();
+    assert_eq!(
+        engine.verify(&vk, &tampered),
+        Err(VerificationError::AirNotAllowedAbsent {
+            air_id: FIB_AIR_ID,
+            air_name: vk.air_name(FIB_AIR_ID).to_string(),
+        })
+    );
+
+    let proof = engine.prove(&pk, proof_input(&[FIB_AIR_ID, SEND_AIR_ID, RECEIVE_AIR_ID]));
+    assert!(proof.core.absent_air_ids.is_empty());
+    engine.verify(&vk, &proof).expect("Verification failed");
+    let mut tampered = proof;
+    tampered
+        .core
+        .per_air
+        .retain(|air_proof| air_proof.air_id == FIB_AIR_ID);
+    assert_eq!(
+        engine.verify(&vk, &tampered),
+        Err(VerificationError::MissingAirs {
+            air_ids: vec![SEND_AIR_ID, RECEIVE_AIR_ID],
+            air_names: [SEND_AIR_ID, RECEIVE_AIR_ID]
+                .map(|air_id| vk.air_name(air_id).to_string())
+                .to_vec(),
+        })
+    );
+}
+
+#[test]
+fn test_absent_air_not_allowed() {
+    let pk = keygen();
+    assert_eq!(
+        pk.check_absent_airs(&proof_input(&[SEND_AIR_ID]).with_absent_airs([FIB_AIR_ID])),
+        Err(AbsentAirError::NotAllowed {
+            air_id: FIB_AIR_ID,
+            air_name: "FibonacciAir".to_string(),
+        })
+    );
+    assert_eq!(
+        pk.check_absent_airs(
+            &proof_input(&[FIB_AIR_ID, SEND_AIR_ID]).with_absent_airs([SEND_AIR_ID])
+        ),
+        Err(AbsentAirError::HasInput {
+            air_id: SEND_AIR_ID,
+            air_name: "DummyInteractionAir".to_string(),
+        })
+    );
+    assert_eq!(
+        pk.check_absent_airs(&proof_input(&[FIB_AIR_ID, SEND_AIR_ID])),
+        Err(AbsentAirError::Missing {
+            air_id: RECEIVE_AIR_ID,
+            air_name: "DummyInteractionAir".to_string(),
+        })
+    );
+}
+
+#[test]
+#[should_panic(expected = "AIR DummyInteractionAir (air_id = 1) has no input")]
+fn test_prove_missing_air() {
+    let engine = default_engine();
+    let pk = keygen();
+    engine.prove(&pk, proof_input(&[FIB_AIR_ID]));
+}
+
+#[test]
+#[should_panic(expected = "AIR FibonacciAir (air_id = 0) is absent")]
+fn test_prove_absent_air_not_allowed() {
+    let engine = default_engine();
+    let pk = keygen();
+    engine.prove(
+        &pk,
+        proof_input(&[SEND_AIR_ID, RECEIVE_AIR_ID]).with_absent_airs([FIB_AIR_ID]),
+    );
+}
+
+/// Sends its single column on bus 0 with a count that does not depend on the trace: on every
+/// row, or on the first row only.
+struct UngatedSendAir {
+    first_row_only: bool,
+}
+
+impl<F> BaseAirWithPublicValues<F> for UngatedSendAir {}
+impl<F> PartitionedBaseAir<F> for UngatedSendAir {}
+impl<F> BaseAir<F> for UngatedSendAir {
+    fn width(&self) -> usize {
+        1
+    }
+}
+
+impl<AB: InteractionBuilder> Air<AB> for UngatedSendAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        let count = if self.first_row_only {
+            builder.is_first_row()
+        } else {
+            AB::Expr::ONE
+        };
+        builder.push_send(0, [local[0]], count);
+    }
+}
+
+#[test]
+fn test_absent_air_ungated_interaction() {
+    for first_row_only in [false, true] {
+        let mut keygen_builder = default_engine().keygen_builder();
+        let air_id = keygen_builder.add_air(Arc::new(UngatedSendAir { first_row_only }));
+        keygen_builder.set_may_be_absent(air_id);
+        assert_eq!(
+            keygen_builder.try_generate_pk().map(|_| ()),
+            Err(KeygenError::UngatedInteraction {
+                air_id,
+                air_name: "UngatedSendAir".to_string(),
+                index: 0,
+            })
+        );
+    }
+}
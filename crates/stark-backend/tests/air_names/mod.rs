@@ -0,0 +1,101 @@
+use std::sync::Arc;
+
+use openvm_stark_backend::{
+    keygen::types::{MultiStarkProvingKey, MultiStarkVerifyingKey},
+    p3_field::FieldAlgebra,
+    prover::types::{AirProofInput, ProofInput},
+};
+use openvm_stark_sdk::{
+    config::baby_bear_poseidon2::{default_engine, BabyBearPoseidon2Config},
+    dummy_airs::fib_air::{air::FibonacciAir, trace::generate_trace_rows},
+    engine::StarkEngine,
+    key_serde::KeySerde,
+};
+use p3_baby_bear::BabyBear;
+
+use crate::get_fib_number;
+
+type SC = BabyBearPoseidon2Config;
+type Val = BabyBear;
+
+const N: usize = 16;
+const NAME: &str = "fib_main";
+
+/// Proving key of a Fibonacci AIR registered as [NAME], with id 0, and of an unnamed one.
+fn keygen() -> MultiStarkProvingKey<SC> {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_named_air(NAME, Arc::new(FibonacciAir));
+    keygen_builder.add_air(Arc::new(FibonacciAir));
+    keygen_builder.generate_pk()
+}
+
+/// Proof input for the AIR with id 0, whose last row is wrong if `broken`.
+fn proof_input(broken: bool) -> ProofInput<SC> {
+    let mut trace = generate_trace_rows::<Val>(0, 1, N);
+    if broken {
+        trace.values[2 * N - 1] += Val::ONE;
+    }
+    let pis = [0, 1, get_fib_number(N)]
+        .map(Val::from_canonical_u32)
+        .to_vec();
+    ProofInput::new(vec![(0, AirProofInput::simple(trace, pis))])
+}
+
+#[test]
+fn test_air_names_in_keys() {
+    let pk = keygen();
+    let vk = pk.get_vk();
+    assert_eq!(pk.per_air[0].air_name, NAME);
+    assert_eq!(vk.air_name(0), NAME);
+    assert_eq!(vk.air_name(1), "FibonacciAir");
+
+    let new_vk = MultiStarkVerifyingKey::<SC>::from_bytes(&vk.to_bytes()).unwrap();
+    assert_eq!(new_vk.air_name(0), NAME);
+    let new_pk = MultiStarkProvingKey::<SC>::from_bytes(&pk.to_bytes()).unwrap();
+    assert_eq!(new_pk.per_air[0].air_name, NAME);
+
+    // The names are not part of the digest.
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(Arc::new(FibonacciAir));
+    keygen_builder.add_air(Arc::new(FibonacciAir));
+    let unnamed_vk = keygen_builder.generate_pk().get_vk();
+    assert_eq!(unnamed_vk.air_name(0), "FibonacciAir");
+    assert_eq!(unnamed_vk.canonical_bytes(), vk.canonical_bytes());
+}
+
+#[test]
+#[should_panic(expected = "on air fib_main, row")]
+fn test_air_name_in_constraint_failure() {
+    default_engine().prove(&keygen(), proof_input(true));
+}
+
+#[cfg(feature = "bench-metrics")]
+#[test]
+fn test_air_name_in_metrics() {
+    use metrics_util::debugging::DebuggingRecorder;
+
+    let pk = keygen();
+    let recorder = DebuggingRecorder::new();
+    let snapshotter = recorder.snapshotter();
+    metrics::with_local_recorder(&recorder, || {
+        default_engine().prove(&pk, proof_input(false));
+    });
+    let rows_labels = snapshotter
+        .snapshot()
+        .into_vec()
+        .into_iter()
+        .filter(|(key, ..)| key.key().name() == "rows")
+        .flat_map(|(key, ..)| {
+            key.key()
+                .labels()
+                .map(|label| (label.key().to_string(), label.value().to_string()))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        rows_labels,
+        vec![("air_name".to_string(), NAME.to_string())]
+    );
+}
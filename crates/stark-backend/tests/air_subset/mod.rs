@@ -0,0 +1,138 @@
+use std::sync::Arc;
+
+use openvm_stark_backend::{
+    interaction::{fri_log_up::FriLogUpError, InteractionType},
+    keygen::types::MultiStarkProvingKey,
+    prover::types::{AirProofInput, ProofInput},
+    utils::disable_debug_builder,
+    verifier::{UnbalancedBus, VerificationError},
+    AirRef,
+};
+use openvm_stark_sdk::{
+    config::baby_bear_poseidon2::{default_engine, BabyBearPoseidon2Config},
+    dummy_airs::{
+        fib_air::air::FibonacciAir, fib_lookup,
+        interaction::dummy_interaction_air::DummyInteractionAir,
+    },
+    engine::StarkEngine,
+};
+
+type SC = BabyBearPoseidon2Config;
+
+const NUM_AIRS: usize = 10;
+const N: usize = 16;
+
+/// AIR 1 sends on bus 0 what AIR 3 receives, AIR 4 sends on bus 1 what AIR 5 receives, and the
+/// other AIRs are Fibonacci AIRs.
+fn air(air_id: usize) -> AirRef<SC> {
+    match air_id {
+        1 => Arc::new(DummyInteractionAir::new(1, true, 0)),
+        3 => Arc::new(DummyInteractionAir::new(1, false, 0)),
+        4 => Arc::new(DummyInteractionAir::new(1, true, 1)),
+        5 => Arc::new(DummyInteractionAir::new(1, false, 1)),
+        _ => Arc::new(FibonacciAir),
+    }
+}
+
+fn keygen() -> MultiStarkProvingKey<SC> {
+    let mut keygen_builder = default_engine().keygen_builder();
+    for air_id in 0..NUM_AIRS {
+        keygen_builder.add_air(air(air_id));
+    }
+    keygen_builder.generate_pk()
+}
+
+fn air_proof_input(air_id: usize) -> AirProofInput<SC> {
+    match air_id {
+        // The same messages for the sender and the receiver.
+        1 | 3 | 4 | 5 => AirProofInput::simple_no_pis(fib_lookup::messages()),
+        _ => fib_lookup::fib_input(N),
+    }
+}
+
+fn proof_input(air_ids: &[usize]) -> ProofInput<SC> {
+    ProofInput::new(
+        air_ids
+            .iter()
+            .map(|&air_id| (air_id, air_proof_input(air_id)))
+            .collect(),
+    )
+}
+
+#[test]
+fn test_air_subset() {
+    let engine = default_engine();
+    let pk = keygen();
+    let vk = pk.get_vk();
+
+    let proof = engine.prove(&pk, proof_input(&[7, 3, 1]));
+    assert_eq!(proof.get_air_ids(), vec![1, 3, 7]);
+    engine.verify(&vk, &proof).expect("Verification failed");
+    // The inputs are sorted before proving, so the proof does not depend on their order.
+    assert_eq!(
+        bincode::serialize(&proof).unwrap(),
+        bincode::serialize(&engine.prove(&pk, proof_input(&[1, 3, 7]))).unwrap()
+    );
+}
+
+#[test]
+fn test_air_subset_missing_bus_partner() {
+    let engine = default_engine();
+    let pk = keygen();
+    // AIR 3 receives what AIR 1 sends.
+    disable_debug_builder();
+    let proof = engine.prove(&pk, proof_input(&[1, 7]));
+    let Err(VerificationError::LogUpSumMismatch { error, report }) =
+        engine.verify(&pk.get_vk(), &proof)
+    else {
+        panic!("expected a log-up sum mismatch");
+    };
+    assert_eq!(error, FriLogUpError::NonZeroCumulativeSum.to_string());
+    assert_eq!(
+        report
+            .per_air
+            .iter()
+            .map(|air| air.air_id)
+            .collect::<Vec<_>>(),
+        vec![1, 7]
+    );
+    assert_eq!(
+        report.unbalanced_buses,
+        vec![UnbalancedBus {
+            bus_index: 0,
+            bus_name: "bus_0".to_string(),
+            interaction_type: InteractionType::Send,
+            air_ids: vec![1],
+        }]
+    );
+}
+
+#[test]
+fn test_air_subset_ids_in_transcript() {
+    let engine = default_engine();
+    let pk = keygen();
+    let vk = pk.get_vk();
+    let proof = engine.prove(&pk, proof_input(&[1, 3, 7]));
+
+    // AIRs 7 and 8 have the same key, but the proof is for AIR 7.
+    let mut relabeled = proof.clone();
+    relabeled.core.per_air[2].air_id = 8;
+    assert!(engine.verify(&vk, &relabeled).is_err());
+
+    relabeled.core.per_air[2].air_id = NUM_AIRS;
+    assert_eq!(
+        engine.verify(&vk, &relabeled),
+        Err(VerificationError::InvalidAirIds(vec![1, 3, NUM_AIRS]))
+    );
+    relabeled.core.per_air[2].air_id = 3;
+    assert_eq!(
+        engine.verify(&vk, &relabeled),
+        Err(VerificationError::InvalidAirIds(vec![1, 3, 3]))
+    );
+}
+
+#[test]
+#[should_panic(expected = "AIR FibonacciAir (air_id = 7) has two inputs")]
+fn test_air_subset_duplicate_input() {
+    default_engine().prove(&keygen(), proof_input(&[7, 1, 7]));
+}
@@ -0,0 +1,185 @@
+use std::{sync::Arc, thread};
+
+use openvm_stark_backend::{
+    p3_field::FieldAlgebra,
+    prover::{
+        batch::BatchProver,
+        pool::{ProverConfig, ProverPoolError},
+        types::{AirProofInput, ProofInput},
+    },
+};
+use openvm_stark_sdk::{
+    config::baby_bear_poseidon2::{default_engine, BabyBearPoseidon2Config},
+    dummy_airs::fib_air::{self, air::FibonacciAir},
+    engine::StarkEngine,
+};
+use p3_baby_bear::BabyBear;
+
+use crate::{
+    fib_selector_air::{self, air::FibonacciSelectorAir},
+    get_conditional_fib_number, get_fib_number,
+};
+
+type SC = BabyBearPoseidon2Config;
+type Val = BabyBear;
+
+const NUM_PROOFS: usize = 7;
+
+/// Selectors of the Fibonacci AIR with preprocessed selectors, with AIR id 1.
+fn selectors() -> Vec<bool> {
+    (0..16).map(|i| i % 3 == 0).collect()
+}
+
+/// Input of the `i`-th proof, of a Fibonacci trace whose height depends on `i` and, for every
+/// other proof, of the AIR with preprocessed selectors.
+fn proof_input(i: usize) -> ProofInput<SC> {
+    let n = 1 << (3 + i % 3);
+    let mut per_air = vec![(
+        0,
+        AirProofInput::simple(
+            fib_air::trace::generate_trace_rows::<Val>(0, 1, n),
+            [0, 1, get_fib_number(n)]
+                .map(Val::from_canonical_u32)
+                .to_vec(),
+        ),
+    )];
+    if i % 2 == 0 {
+        let sels = selectors();
+        per_air.push((
+            1,
+            AirProofInput::simple(
+                fib_selector_air::trace::generate_trace_rows::<Val>(0, 1, &sels),
+                [0, 1, get_conditional_fib_number(&sels)]
+                    .map(Val::from_canonical_u32)
+                    .to_vec(),
+            ),
+        ));
+    }
+    ProofInput::new(per_air)
+}
+
+#[test]
+fn test_batch_prover_matches_single_proofs() {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(Arc::new(FibonacciAir));
+    keygen_builder.add_air(Arc::new(FibonacciSelectorAir::new(selectors(), false)));
+    let pk = keygen_builder.generate_pk();
+    let vk = pk.get_vk();
+
+    let expected = (0..NUM_PROOFS)
+        .map(|i| bincode::serialize(&engine.prove(&pk, proof_input(i))).unwrap())
+        .collect::<Vec<_>>();
+    for (num_threads, max_in_flight) in [(0, 1), (2, 3), (4, NUM_PROOFS + 1)] {
+        let mut batch_prover = BatchProver::new(&engine, &pk, num_threads);
+        batch_prover.set_max_in_flight(max_in_flight);
+        let proofs = batch_prover.prove_concurrently((0..NUM_PROOFS).map(proof_input).collect());
+        assert_eq!(proofs.len(), NUM_PROOFS);
+        for (proof, expected) in proofs.iter().zip(&expected) {
+            assert_eq!(&bincode::serialize(proof).unwrap(), expected);
+            engine.verify(&vk, proof).expect("Verification failed");
+        }
+    }
+    assert!(BatchProver::new(&engine, &pk, 1)
+        .prove_concurrently(vec![])
+        .is_empty());
+}
+
+#[test]
+fn test_prover_config_number_of_threads() {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(Arc::new(FibonacciAir));
+    keygen_builder.add_air(Arc::new(FibonacciSelectorAir::new(selectors(), false)));
+    let pk = keygen_builder.generate_pk();
+
+    let expected = bincode::serialize(&engine.prove(&pk, proof_input(0))).unwrap();
+    for num_threads in [1, 4] {
+        let config = ProverConfig {
+            num_threads,
+            stack_size: Some(1 << 22),
+            pin_cores: None,
+        };
+        let proof = BatchProver::with_config(&engine, &pk, &config)
+            .unwrap()
+            .prove(proof_input(0));
+        assert_eq!(bincode::serialize(&proof).unwrap(), expected);
+
+        let pool = config.build_pool("prover").unwrap();
+        let proof = engine
+            .prove_on_pool(&pool, &pk, proof_input(0), Default::default())
+            .unwrap();
+        assert_eq!(bincode::serialize(&proof).unwrap(), expected);
+    }
+}
+
+#[test]
+fn test_prover_config_errors() {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(Arc::new(FibonacciAir));
+    let pk = keygen_builder.generate_pk();
+
+    let config = ProverConfig {
+        pin_cores: Some(vec![]),
+        ..Default::default()
+    };
+    assert!(matches!(
+        BatchProver::with_config(&engine, &pk, &config).err(),
+        Some(ProverPoolError::EmptyPinCores)
+    ));
+    let config = ProverConfig {
+        pin_cores: Some(vec![0]),
+        ..Default::default()
+    };
+    assert_eq!(
+        matches!(config.validate(), Err(ProverPoolError::PinningUnsupported)),
+        !cfg!(feature = "pin-cores")
+    );
+}
+
+#[test]
+fn test_concurrent_provers_with_separate_pools() {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(Arc::new(FibonacciAir));
+    keygen_builder.add_air(Arc::new(FibonacciSelectorAir::new(selectors(), false)));
+    let pk = keygen_builder.generate_pk();
+    let vk = pk.get_vk();
+
+    let configs = [
+        ProverConfig {
+            num_threads: 2,
+            ..Default::default()
+        },
+        ProverConfig {
+            num_threads: 1,
+            pin_cores: cfg!(feature = "pin-cores").then(|| vec![0]),
+            ..Default::default()
+        },
+    ];
+    let proofs = thread::scope(|s| {
+        let handles = configs
+            .iter()
+            .map(|config| {
+                let (engine, pk) = (&engine, &pk);
+                s.spawn(move || {
+                    BatchProver::with_config(engine, pk, config)
+                        .unwrap()
+                        .prove_concurrently((0..NUM_PROOFS).map(proof_input).collect())
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+    for proof in proofs.iter().flatten() {
+        engine.verify(&vk, proof).expect("Verification failed");
+    }
+    assert_eq!(
+        bincode::serialize(&proofs[0]).unwrap(),
+        bincode::serialize(&proofs[1]).unwrap()
+    );
+}
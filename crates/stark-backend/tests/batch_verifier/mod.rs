@@ -0,0 +1,101 @@
+use std::sync::Arc;
+
+use openvm_stark_backend::{
+    config::StarkGenericConfig,
+    p3_field::FieldAlgebra,
+    prover::types::{AirProofInput, ProofInput},
+    verifier::VerificationError,
+};
+use openvm_stark_sdk::{
+    config::baby_bear_poseidon2::{default_engine, BabyBearPoseidon2Config},
+    dummy_airs::fib_air::{air::FibonacciAir, trace::generate_trace_rows},
+    engine::StarkEngine,
+};
+use p3_baby_bear::BabyBear;
+
+use crate::{
+    get_fib_number,
+    preprocessed_per_height::{keygen, proof_input as per_height_proof_input},
+};
+
+type SC = BabyBearPoseidon2Config;
+type Challenge = <SC as StarkGenericConfig>::Challenge;
+
+const NUM_PROOFS: usize = 6;
+const CORRUPTED: usize = 3;
+
+/// Input of the `i`-th proof, of a Fibonacci trace whose height depends on `i`.
+fn proof_input(i: usize) -> ProofInput<SC> {
+    let n = 1 << (3 + i % 3);
+    let pis = [0, 1, get_fib_number(n)]
+        .map(BabyBear::from_canonical_u32)
+        .to_vec();
+    ProofInput::new(vec![(
+        0,
+        AirProofInput::simple(generate_trace_rows::<BabyBear>(0, 1, n), pis),
+    )])
+}
+
+#[test]
+fn test_verify_batch_reports_each_proof() {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(Arc::new(FibonacciAir));
+    let pk = keygen_builder.generate_pk();
+    let vk = pk.get_vk();
+
+    let mut proofs = (0..NUM_PROOFS)
+        .map(|i| engine.prove(&pk, proof_input(i)))
+        .collect::<Vec<_>>();
+    proofs[CORRUPTED].core.per_air[0].public_values[2] += BabyBear::ONE;
+    let results = engine.verify_batch(&vk, &proofs);
+    assert_eq!(results.len(), NUM_PROOFS);
+    for (i, (result, proof)) in results.into_iter().zip(&proofs).enumerate() {
+        assert_eq!(result.is_err(), i == CORRUPTED, "proof {i}");
+        // The same result as verifying the proof on its own.
+        assert_eq!(result, engine.verify(&vk, proof));
+    }
+
+    // A proof for an AIR the key does not have is rejected before anything else.
+    let mut invalid = proofs[0].clone();
+    invalid.core.per_air[0].air_id = 1;
+    let results = engine.verify_batch(&vk, &[invalid, proofs[1].clone()]);
+    assert_eq!(
+        results,
+        vec![Err(VerificationError::InvalidAirIds(vec![1])), Ok(())]
+    );
+    assert!(engine.verify_batch(&vk, &[]).is_empty());
+}
+
+#[test]
+fn test_verify_batch_preprocessed_per_height() {
+    let engine = default_engine();
+    let (airs, mut pk, _) = keygen();
+    let mut proofs = [3, 5, 3]
+        .map(|log_height| {
+            engine.prove_with_preprocessed_heights(
+                &airs,
+                &mut pk,
+                per_height_proof_input(log_height),
+            )
+        })
+        .to_vec();
+    let vk = pk.get_vk();
+    let mut tampered = proofs[1].clone();
+    tampered.core.opened_values.main[0][0].local[0] += Challenge::ONE;
+    proofs.push(tampered);
+    // A height without a committed preprocessed trace.
+    let mut uncommitted = proofs[0].clone();
+    uncommitted.core.per_air[0].degree = 1 << 10;
+    proofs.push(uncommitted);
+
+    let results = engine.verify_batch(&vk, &proofs);
+    for (i, (result, proof)) in results.iter().zip(&proofs).enumerate() {
+        assert_eq!(result.is_err(), i >= 3, "proof {i}");
+        assert_eq!(*result, engine.verify(&vk, proof), "proof {i}");
+    }
+    assert!(matches!(
+        results[4],
+        Err(VerificationError::MissingPreprocessedHeight(_))
+    ));
+}
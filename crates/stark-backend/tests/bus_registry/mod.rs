@@ -0,0 +1,156 @@
+use std::sync::Arc;
+
+use openvm_stark_backend::{
+    engine::StarkEngine,
+    interaction::InteractionType,
+    keygen::{types::MultiStarkVerifyingKey, KeygenError},
+    prover::types::{AirProofInput, ProofInput},
+    utils::disable_debug_builder,
+    verifier::{UnbalancedBus, VerificationError},
+};
+use openvm_stark_sdk::{
+    config::baby_bear_poseidon2::{default_engine, BabyBearPoseidon2Config},
+    dummy_airs::interaction::dummy_interaction_air::DummyInteractionAir,
+};
+use p3_matrix::dense::RowMajorMatrix;
+
+use crate::utils::to_field_vec;
+
+#[test]
+fn test_bus_registry_names_in_vk() {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    let bus = keygen_builder.add_bus("pairs", 2);
+    let sender_id =
+        keygen_builder.add_air(Arc::new(DummyInteractionAir::new(2, true, bus.index())));
+    let receiver_id =
+        keygen_builder.add_air(Arc::new(DummyInteractionAir::new(2, false, bus.index())));
+    // Raw bus index, registered as such since named buses are registered.
+    keygen_builder.add_raw_bus(5, 1);
+    keygen_builder.add_air(Arc::new(DummyInteractionAir::new(1, true, 5)));
+    let pk = keygen_builder.generate_pk();
+
+    let trace = RowMajorMatrix::new(to_field_vec(vec![1, 2, 3, 4, 5, 6]), 3);
+    let proof = engine.prove(
+        &pk,
+        ProofInput::new(vec![
+            (sender_id, AirProofInput::simple_no_pis(trace.clone())),
+            (receiver_id, AirProofInput::simple_no_pis(trace)),
+        ]),
+    );
+    let vk = pk.get_vk();
+    engine.verify(&vk, &proof).expect("Verification failed");
+
+    assert_eq!(vk.bus_registry.name(bus.index()), "pairs");
+    assert_eq!(vk.bus_registry.get(bus.index()).unwrap().arity, 2);
+    assert_eq!(vk.bus_registry.name(5), "bus_5");
+    assert_eq!(vk.bus_registry.get(5).unwrap().arity, 1);
+    assert_eq!(vk.bus_registry.name(4), "bus_4");
+    assert!(vk.bus_registry.get(4).is_none());
+
+    let json = engine.vk_json(&vk);
+    let (decoded, _) = MultiStarkVerifyingKey::<BabyBearPoseidon2Config>::from_json(&json).unwrap();
+    assert_eq!(decoded.bus_registry, vk.bus_registry);
+}
+
+#[test]
+fn test_bus_registry_named_after_raw() {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_raw_bus(0, 1);
+    keygen_builder.add_raw_bus(2, 1);
+    // Named buses never take the index of a raw bus.
+    assert_eq!(keygen_builder.add_bus("pairs", 2).index(), 3);
+    assert_eq!(keygen_builder.add_bus("bytes", 1).index(), 4);
+}
+
+#[test]
+#[should_panic(expected = "bus index 0 is already registered as pairs")]
+fn test_bus_registry_raw_bus_collision() {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_bus("pairs", 2);
+    keygen_builder.add_raw_bus(0, 1);
+}
+
+#[test]
+fn test_bus_registry_unregistered_raw_bus() {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    let bus = keygen_builder.add_bus("pairs", 2);
+    keygen_builder.add_air(Arc::new(DummyInteractionAir::new(2, true, bus.index())));
+    // An interaction on raw index 0 is on the named bus, but raw bus 5 is not registered.
+    keygen_builder.add_air(Arc::new(DummyInteractionAir::new(2, false, 0)));
+    keygen_builder.add_air(Arc::new(DummyInteractionAir::new(1, true, 5)));
+
+    assert_eq!(
+        keygen_builder.try_generate_pk().err(),
+        Some(KeygenError::UnregisteredBus {
+            air_id: 2,
+            air_name: "DummyInteractionAir".to_string(),
+            bus_index: 5,
+        })
+    );
+}
+
+#[test]
+fn test_bus_registry_arity_mismatch() {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_bus("bytes", 1);
+    let bus = keygen_builder.add_bus("pairs", 2);
+    keygen_builder.add_air(Arc::new(DummyInteractionAir::new(2, true, bus.index())));
+    keygen_builder.add_air(Arc::new(DummyInteractionAir::new(3, false, bus.index())));
+
+    let err = keygen_builder.try_generate_pk().err().unwrap();
+    assert_eq!(
+        err,
+        KeygenError::BusArityMismatch {
+            air_id: 1,
+            air_name: "DummyInteractionAir".to_string(),
+            bus_index: 1,
+            bus_name: "pairs".to_string(),
+            expected_arity: 2,
+            arity: 3,
+        }
+    );
+    let message = err.to_string();
+    assert!(message.contains("pairs"));
+    assert!(message.contains("3 fields"));
+    assert!(message.contains("2 fields"));
+}
+
+#[test]
+fn test_bus_registry_names_orphaned_bus() {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    let bus = keygen_builder.add_bus("pairs", 2);
+    keygen_builder.add_air(Arc::new(DummyInteractionAir::new(2, true, bus.index())));
+    let receiver_id =
+        keygen_builder.add_air(Arc::new(DummyInteractionAir::new(2, false, bus.index())));
+    let pk = keygen_builder.generate_pk();
+
+    // The receiver without its sender.
+    disable_debug_builder();
+    let trace = RowMajorMatrix::new(to_field_vec(vec![1, 2, 3, 4, 5, 6]), 3);
+    let proof = engine.prove(
+        &pk,
+        ProofInput::new(vec![(receiver_id, AirProofInput::simple_no_pis(trace))]),
+    );
+    let err = engine.verify(&pk.get_vk(), &proof).unwrap_err();
+    let VerificationError::LogUpSumMismatch { report, .. } = &err else {
+        panic!("expected a log-up sum mismatch, got {err:?}");
+    };
+    assert_eq!(
+        report.unbalanced_buses,
+        vec![UnbalancedBus {
+            bus_index: bus.index(),
+            bus_name: "pairs".to_string(),
+            interaction_type: InteractionType::Receive,
+            air_ids: vec![receiver_id],
+        }]
+    );
+    assert!(err
+        .to_string()
+        .contains("Bus 0 (pairs): only received from, by AIRs [1]"));
+}
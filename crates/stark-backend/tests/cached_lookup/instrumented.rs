@@ -1,7 +1,10 @@
 use std::fs::{self, File};
 
 use openvm_stark_backend::{
-    config::StarkGenericConfig, keygen::types::MultiStarkVerifyingKey, proof::Proof,
+    config::{StarkGenericConfig, Val},
+    keygen::types::MultiStarkVerifyingKey,
+    p3_field::PrimeField64,
+    proof::Proof,
     verifier::VerificationError,
 };
 use openvm_stark_sdk::{
@@ -30,8 +33,11 @@ fn instrumented_verify<SC: StarkGenericConfig, E: StarkEngineWithHashInstrumenta
     vk: MultiStarkVerifyingKey<SC>,
     air: &DummyInteractionAir,
     proof: Proof<SC>,
-) -> StarkHashStatistics<BenchParams> {
-    let degree = proof.per_air[0].degree;
+) -> StarkHashStatistics<BenchParams>
+where
+    Val<SC>: PrimeField64,
+{
+    let degree = proof.core.per_air[0].degree;
     let log_degree = log2_ceil_usize(degree);
 
     engine.clear_instruments();
@@ -39,7 +45,10 @@ fn instrumented_verify<SC: StarkGenericConfig, E: StarkEngineWithHashInstrumenta
     let verifier = engine.verifier();
     // Do not check cumulative sum
     let res = verifier.verify(&mut challenger, &vk, &proof);
-    if matches!(res, Err(ref err) if err != &VerificationError::ChallengePhaseError) {
+    if matches!(res, Err(ref err) if !matches!(
+        err,
+        VerificationError::ChallengePhaseError(_) | VerificationError::LogUpSumMismatch { .. }
+    )) {
         panic!("{res:?}");
     };
 
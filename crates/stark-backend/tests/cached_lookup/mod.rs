@@ -1,9 +1,20 @@
 use openvm_stark_backend::{
-    engine::StarkEngine, utils::disable_debug_builder, verifier::VerificationError, Chip,
+    config::{StarkGenericConfig, Val},
+    engine::StarkEngine,
+    interaction::fri_log_up::FriLogUpError,
+    p3_field::PrimeField32,
+    prover::types::AirProofInput,
+    utils::disable_debug_builder,
+    verifier::VerificationError,
+    Chip,
 };
 use openvm_stark_sdk::{
     collect_airs_and_inputs,
-    config::{baby_bear_poseidon2::BabyBearPoseidon2Engine, FriParameters},
+    config::{
+        baby_bear_poseidon2::{BabyBearPoseidon2Config, BabyBearPoseidon2Engine},
+        koala_bear_poseidon2::{KoalaBearPoseidon2Config, KoalaBearPoseidon2Engine},
+        FriParameters,
+    },
     dummy_airs::interaction::dummy_interaction_air::{DummyInteractionChip, DummyInteractionData},
     engine::StarkFriEngine,
 };
@@ -12,11 +23,17 @@ mod instrumented;
 pub mod prove;
 
 // Lookup table is cached, everything else (including counts) is committed together
-pub fn prove_and_verify_indexless_lookups(
+pub fn prove_and_verify_indexless_lookups<SC, E>(
     sender: Vec<(u32, Vec<u32>)>,
     receiver: Vec<(u32, Vec<u32>)>,
-) -> Result<(), VerificationError> {
-    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+) -> Result<(), VerificationError>
+where
+    SC: StarkGenericConfig,
+    E: StarkFriEngine<SC>,
+    Val<SC>: PrimeField32,
+    AirProofInput<SC>: Send + Sync,
+{
+    let engine = E::new(FriParameters::standard_fast());
 
     let mut sender_chip = DummyInteractionChip::new_without_partition(sender[0].1.len(), true, 0);
     let mut receiver_chip =
@@ -36,6 +53,23 @@ pub fn prove_and_verify_indexless_lookups(
 /// tests for cached_lookup
 #[test]
 fn test_interaction_cached_trace_happy_path() {
+    interaction_cached_trace_happy_path::<BabyBearPoseidon2Config, BabyBearPoseidon2Engine>();
+    interaction_cached_trace_happy_path::<KoalaBearPoseidon2Config, KoalaBearPoseidon2Engine>();
+}
+
+#[test]
+fn test_interaction_cached_trace_neg() {
+    interaction_cached_trace_neg::<BabyBearPoseidon2Config, BabyBearPoseidon2Engine>();
+    interaction_cached_trace_neg::<KoalaBearPoseidon2Config, KoalaBearPoseidon2Engine>();
+}
+
+fn interaction_cached_trace_happy_path<SC, E>()
+where
+    SC: StarkGenericConfig,
+    E: StarkFriEngine<SC>,
+    Val<SC>: PrimeField32,
+    AirProofInput<SC>: Send + Sync,
+{
     // count fields
     //   0    1 1
     //   7    4 2
@@ -68,11 +102,16 @@ fn test_interaction_cached_trace_happy_path() {
         (0, vec![456, 5]),
     ];
 
-    prove_and_verify_indexless_lookups(sender, receiver).expect("Verification failed");
+    prove_and_verify_indexless_lookups::<SC, E>(sender, receiver).expect("Verification failed");
 }
 
-#[test]
-fn test_interaction_cached_trace_neg() {
+fn interaction_cached_trace_neg<SC, E>()
+where
+    SC: StarkGenericConfig,
+    E: StarkFriEngine<SC>,
+    Val<SC>: PrimeField32,
+    AirProofInput<SC>: Send + Sync,
+{
     // count fields
     //   0    1 1
     //   7    4 2
@@ -107,8 +146,10 @@ fn test_interaction_cached_trace_neg() {
     ];
 
     disable_debug_builder();
-    assert_eq!(
-        prove_and_verify_indexless_lookups(sender, receiver).err(),
-        Some(VerificationError::ChallengePhaseError)
-    );
+    assert!(matches!(
+        prove_and_verify_indexless_lookups::<SC, E>(sender, receiver).err(),
+        Some(VerificationError::LogUpSumMismatch { error, report })
+            if error == FriLogUpError::NonZeroCumulativeSum.to_string()
+                && report.unbalanced_buses.is_empty()
+    ));
 }
@@ -5,8 +5,13 @@ use std::{
 };
 
 use openvm_stark_backend::{
-    config::StarkGenericConfig, keygen::types::MultiStarkVerifyingKey, proof::Proof,
-    prover::types::ProofInput, utils::disable_debug_builder, Chip,
+    config::{StarkGenericConfig, Val},
+    keygen::types::MultiStarkVerifyingKey,
+    p3_field::PrimeField64,
+    proof::Proof,
+    prover::types::ProofInput,
+    utils::disable_debug_builder,
+    Chip,
 };
 use openvm_stark_sdk::{
     config::{
@@ -33,7 +38,10 @@ pub fn prove<SC: StarkGenericConfig, E: StarkEngine<SC>>(
     Arc<DummyInteractionAir>,
     Proof<SC>,
     ProverBenchmarks,
-) {
+)
+where
+    Val<SC>: PrimeField64,
+{
     let mut chip =
         DummyInteractionChip::new_with_partition(engine.config(), trace[0].1.len(), false, 0);
     let (count, fields): (Vec<_>, Vec<_>) = trace.into_iter().unzip();
@@ -58,9 +66,7 @@ pub fn prove<SC: StarkGenericConfig, E: StarkEngine<SC>>(
     } else {
         chip.generate_air_proof_input_with_id(air_id)
     };
-    let proof_input = ProofInput {
-        per_air: vec![air_proof_input],
-    };
+    let proof_input = ProofInput::new(vec![air_proof_input]);
     start = Instant::now();
 
     // Disable debug prover since we don't balance the buses
@@ -0,0 +1,96 @@
+use std::sync::Arc;
+
+use openvm_stark_backend::{
+    config::{StarkGenericConfig, Val},
+    engine::StarkEngine,
+    p3_challenger::{CanObserve, FieldChallenger},
+    p3_commit::Pcs,
+    p3_field::FieldAlgebra,
+    p3_matrix::dense::RowMajorMatrix,
+};
+use openvm_stark_sdk::config::baby_bear_poseidon2::{default_engine, BabyBearPoseidon2Config};
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::utils::generate_random_matrix;
+
+type SC = BabyBearPoseidon2Config;
+type Challenge = <SC as StarkGenericConfig>::Challenge;
+
+#[test]
+fn test_commit_matrices_of_different_heights() {
+    let engine = default_engine();
+    let pcs = engine.config().pcs();
+    let log_heights = [4, 8, 6];
+    let mut rng = StdRng::seed_from_u64(0);
+    let matrices = log_heights
+        .iter()
+        .enumerate()
+        .map(|(i, &log_height)| {
+            let values = generate_random_matrix::<Val<SC>>(&mut rng, 1 << log_height, i + 1);
+            RowMajorMatrix::new(values.concat(), i + 1)
+        })
+        .collect::<Vec<_>>();
+    let (commitment, preimages) = engine.commit_matrices(matrices.clone());
+    let data = &preimages[0].data;
+    assert_eq!(data.log_trace_heights, log_heights);
+    for (i, preimage) in preimages.iter().enumerate() {
+        assert_eq!(preimage.matrix_idx as usize, i);
+        assert_eq!(*preimage.trace, matrices[i]);
+        assert!(Arc::ptr_eq(&preimage.data.data, &data.data));
+        assert_eq!(
+            preimage.data.log_trace_heights[preimage.matrix_idx as usize],
+            log_heights[i]
+        );
+    }
+
+    // Open every matrix at a point sampled after observing the commitment.
+    let mut challenger = engine.new_challenger();
+    challenger.observe(commitment.clone());
+    let zeta: Challenge = challenger.sample_ext_element();
+    let (opened_values, proof) = pcs.open(
+        vec![(data.data.as_ref(), vec![vec![zeta]; log_heights.len()])],
+        &mut challenger,
+    );
+    let opened_values = &opened_values[0];
+    assert_eq!(
+        opened_values
+            .iter()
+            .map(|values| values[0].len())
+            .collect::<Vec<_>>(),
+        [1, 2, 3]
+    );
+
+    let verify = |opened_values: &Vec<Vec<Vec<Challenge>>>| {
+        let mut challenger = engine.new_challenger();
+        challenger.observe(commitment.clone());
+        let zeta: Challenge = challenger.sample_ext_element();
+        let domains_and_openings = log_heights
+            .iter()
+            .zip(opened_values)
+            .map(|(&log_height, values)| {
+                let domain = pcs.natural_domain_for_degree(1 << log_height);
+                (domain, vec![(zeta, values[0].clone())])
+            })
+            .collect();
+        pcs.verify(
+            vec![(commitment.clone(), domains_and_openings)],
+            &proof,
+            &mut challenger,
+        )
+    };
+    verify(opened_values).expect("Verification failed");
+
+    let mut tampered = opened_values.clone();
+    tampered[2][0][1] += Challenge::ONE;
+    assert!(verify(&tampered).is_err());
+}
+
+#[test]
+#[should_panic(expected = "matrix 1 has height 12, which is not a power of two")]
+fn test_commit_matrices_height_not_power_of_two() {
+    let engine = default_engine();
+    engine.commit_matrices(vec![
+        RowMajorMatrix::new(vec![Val::<SC>::ONE; 16], 1),
+        RowMajorMatrix::new(vec![Val::<SC>::ONE; 12], 1),
+    ]);
+}
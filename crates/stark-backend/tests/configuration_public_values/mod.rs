@@ -0,0 +1,129 @@
+//! AIR with a configuration public value `rounds` and columns
+//! | x |
+//!
+//! Constrains x == rounds on the first row. `rounds` must be 4 or 8.
+
+use std::sync::Arc;
+
+use openvm_stark_backend::{
+    keygen::{
+        types::{AllowedValues, MultiStarkVerifyingKey},
+        DisallowedPublicValueError,
+    },
+    p3_field::FieldAlgebra,
+    proof::Proof,
+    prover::types::{AirProofInput, ProofInput},
+    rap::{BaseAirWithPublicValues, PartitionedBaseAir},
+    verifier::VerificationError,
+};
+use openvm_stark_sdk::{
+    config::baby_bear_poseidon2::{default_engine, BabyBearPoseidon2Config},
+    engine::StarkEngine,
+};
+use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, BaseAir};
+use p3_baby_bear::BabyBear;
+use p3_matrix::{dense::RowMajorMatrix, Matrix};
+
+type SC = BabyBearPoseidon2Config;
+type Val = BabyBear;
+
+struct RoundsAir;
+
+impl<F> BaseAirWithPublicValues<F> for RoundsAir {
+    fn num_public_values(&self) -> usize {
+        1
+    }
+
+    fn configuration_public_values(&self) -> Vec<(usize, AllowedValues)> {
+        vec![(0, AllowedValues::Set(vec![4, 8]))]
+    }
+}
+impl<F> PartitionedBaseAir<F> for RoundsAir {}
+impl<F> BaseAir<F> for RoundsAir {
+    fn width(&self) -> usize {
+        1
+    }
+}
+
+impl<AB: AirBuilderWithPublicValues> Air<AB> for RoundsAir {
+    fn eval(&self, builder: &mut AB) {
+        let rounds = builder.public_values()[0];
+        let x = builder.main().row_slice(0)[0];
+        builder.when_first_row().assert_eq(x, rounds);
+    }
+}
+
+fn prove_rounds_air(rounds: u32) -> (MultiStarkVerifyingKey<SC>, Proof<SC>) {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    let air_id = keygen_builder.add_air(Arc::new(RoundsAir));
+    let pk = keygen_builder.generate_pk();
+    let vk = pk.get_vk();
+
+    let rounds = Val::from_canonical_u32(rounds);
+    let trace = RowMajorMatrix::new_col(vec![rounds; 8]);
+    let proof = engine.prove(
+        &pk,
+        ProofInput::new(vec![(air_id, AirProofInput::simple(trace, vec![rounds]))]),
+    );
+    (vk, proof)
+}
+
+#[test]
+fn test_configuration_public_value_allowed() {
+    let (vk, proof) = prove_rounds_air(8);
+    assert_eq!(
+        vk.per_air[0].params.configuration_public_values,
+        vec![(0, AllowedValues::Set(vec![4, 8]))]
+    );
+    default_engine()
+        .verify(&vk, &proof)
+        .expect("Verification failed");
+}
+
+#[test]
+fn test_configuration_public_value_rejected_at_verification() {
+    let (vk, mut proof) = prove_rounds_air(4);
+    proof.core.per_air[0].public_values[0] = Val::from_canonical_u32(5);
+    assert_eq!(
+        default_engine().verify(&vk, &proof),
+        Err(VerificationError::DisallowedPublicValue(
+            DisallowedPublicValueError {
+                air_id: 0,
+                air_name: "RoundsAir".to_string(),
+                index: 0,
+                value: "5".to_string(),
+                allowed: AllowedValues::Set(vec![4, 8]),
+            }
+        ))
+    );
+}
+
+#[test]
+#[should_panic(
+    expected = "public value 0 of AIR RoundsAir (air_id = 0) is 5, which is not one of the \
+                allowed values {4, 8}"
+)]
+fn test_configuration_public_value_rejected_at_prove() {
+    prove_rounds_air(5);
+}
+
+#[test]
+fn test_allowed_values_range() {
+    let allowed = AllowedValues::Range { start: 2, end: 4 };
+    assert!(allowed.contains(Val::from_canonical_u32(3)));
+    assert!(!allowed.contains(Val::from_canonical_u32(5)));
+    assert!(!allowed.is_empty());
+    assert_eq!(allowed.to_string(), "[2, 4]");
+
+    // A range is checked against its bounds, so a range over all of `u64` is cheap.
+    let allowed = AllowedValues::Range {
+        start: 0,
+        end: u64::MAX,
+    };
+    assert!(allowed.contains(-Val::ONE));
+
+    let allowed = AllowedValues::Range { start: 4, end: 2 };
+    assert!(allowed.is_empty());
+    assert!(!allowed.contains(Val::from_canonical_u32(3)));
+}
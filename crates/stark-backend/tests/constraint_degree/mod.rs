@@ -0,0 +1,86 @@
+//! AIR with columns
+//! | x | y |
+//!
+//! Constrains y == x^5, and optionally sends x^5 on a bus.
+
+use std::sync::Arc;
+
+use openvm_stark_backend::{
+    interaction::InteractionBuilder,
+    keygen::KeygenError,
+    p3_field::FieldAlgebra,
+    rap::{BaseAirWithPublicValues, PartitionedBaseAir},
+};
+use openvm_stark_sdk::{
+    config::{baby_bear_poseidon2::BabyBearPoseidon2Engine, FriParameters},
+    engine::{StarkEngine, StarkFriEngine},
+};
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_matrix::Matrix;
+
+struct QuinticAir {
+    enable_interactions: bool,
+}
+
+impl<F> BaseAirWithPublicValues<F> for QuinticAir {}
+impl<F> PartitionedBaseAir<F> for QuinticAir {}
+impl<F> BaseAir<F> for QuinticAir {
+    fn width(&self) -> usize {
+        2
+    }
+}
+
+impl<AB: InteractionBuilder> Air<AB> for QuinticAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        let x: AB::Expr = local[0].into();
+        let x5 = (0..4).fold(x.clone(), |acc, _| acc * x.clone());
+        builder.assert_eq(x5.clone(), local[1]);
+        if self.enable_interactions {
+            builder.push_send(0, [x5], AB::Expr::ONE);
+        }
+    }
+}
+
+fn keygen_quintic_air(log_blowup: usize, enable_interactions: bool) -> Result<(), KeygenError> {
+    let engine = BabyBearPoseidon2Engine::new(
+        FriParameters::standard_with_100_bits_conjectured_security(log_blowup),
+    );
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(Arc::new(QuinticAir {
+        enable_interactions,
+    }));
+    keygen_builder.try_generate_pk().map(|_| ())
+}
+
+#[test]
+fn test_constraint_degree_exceeds_fri_max() {
+    // The log-up constraint of the interaction has degree 6.
+    assert_eq!(
+        keygen_quintic_air(1, true),
+        Err(KeygenError::ConstraintDegreeTooHigh {
+            air_id: 0,
+            air_name: "QuinticAir".to_string(),
+            degree: 6,
+            max_degree: 3,
+            min_log_blowup: 3,
+        })
+    );
+    assert_eq!(
+        keygen_quintic_air(1, false),
+        Err(KeygenError::ConstraintDegreeTooHigh {
+            air_id: 0,
+            air_name: "QuinticAir".to_string(),
+            degree: 5,
+            max_degree: 3,
+            min_log_blowup: 2,
+        })
+    );
+}
+
+#[test]
+fn test_constraint_degree_within_fri_max() {
+    assert_eq!(keygen_quintic_air(3, true), Ok(()));
+    assert_eq!(keygen_quintic_air(2, false), Ok(()));
+}
@@ -0,0 +1,372 @@
+use std::time::Instant;
+
+use itertools::Itertools;
+use openvm_stark_backend::{
+    config::StarkGenericConfig,
+    engine::{StarkEngine, VerificationData},
+    keygen::types::CommitmentRound,
+    p3_field::{FieldAlgebra, FieldExtensionAlgebra},
+    proof::Proof,
+    verifier::{validate_proof_shape, ProofShapeError, VerificationError},
+};
+use openvm_stark_sdk::{
+    any_rap_arc_vec,
+    config::{
+        baby_bear_poseidon2::{BabyBearPoseidon2Config, BabyBearPoseidon2Engine},
+        FriParameters,
+    },
+    dummy_airs::interaction::dummy_interaction_air::DummyInteractionAir,
+    engine::StarkFriEngine,
+};
+use p3_baby_bear::BabyBear;
+use p3_matrix::dense::RowMajorMatrix;
+
+use crate::{
+    fib_selector_air::{air::FibonacciSelectorAir, trace::generate_trace_rows},
+    get_conditional_fib_number,
+};
+
+type SC = BabyBearPoseidon2Config;
+type Val = BabyBear;
+type Challenge = <SC as StarkGenericConfig>::Challenge;
+
+/// Proves a Fibonacci selector AIR sending its values to a dummy interaction AIR, so the proof
+/// has public values, a challenge phase and multiple main trace commitments.
+fn prove_fib_selector_with_interaction() -> VerificationData<SC> {
+    let (a, b) = (0u32, 1u32);
+    let sels: Vec<bool> = (0..8).map(|i| i % 2 == 0).collect();
+    let pis = [a, b, get_conditional_fib_number(&sels)]
+        .map(Val::from_canonical_u32)
+        .to_vec();
+    let air = FibonacciSelectorAir::new(sels.clone(), true);
+    let trace = generate_trace_rows::<Val>(a, b, &sels);
+
+    let (mut curr_a, mut curr_b) = (a, b);
+    let mut vals = vec![];
+    for sel in sels {
+        vals.push(Val::from_bool(sel));
+        if sel {
+            (curr_a, curr_b) = (curr_b, curr_a + curr_b);
+        }
+        vals.push(Val::from_canonical_u32(curr_b));
+    }
+    let sender_trace = RowMajorMatrix::new(vals, 2);
+    let sender_air = DummyInteractionAir::new(1, true, 0);
+
+    BabyBearPoseidon2Engine::run_simple_test_fast(
+        any_rap_arc_vec![air, sender_air],
+        vec![trace, sender_trace],
+        vec![pis, vec![]],
+    )
+    .expect("Verification failed")
+    .data
+}
+
+fn verify(
+    data: &VerificationData<SC>,
+    proof: &Proof<SC>,
+    constraints_before_opening: bool,
+) -> Result<(), VerificationError> {
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+    engine
+        .verifier()
+        .with_constraints_before_opening(constraints_before_opening)
+        .verify(&mut engine.new_challenger(), &data.vk, proof)
+}
+
+/// Verifies with the checks of the fast reject order: the constraints and the first
+/// `early_queries` queries of the opening proof before the full opening proof.
+fn verify_with_early_queries(
+    data: &VerificationData<SC>,
+    proof: &Proof<SC>,
+    early_queries: usize,
+) -> Result<(), VerificationError> {
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+    engine
+        .verifier()
+        .with_constraints_before_opening(true)
+        .with_early_queries(early_queries)
+        .verify(&mut engine.new_challenger(), &data.vk, proof)
+}
+
+type Mutation = Box<dyn Fn(&mut Proof<SC>)>;
+
+/// Mutations of a valid proof that keep its shape.
+fn mutations() -> Vec<Mutation> {
+    vec![
+        Box::new(|proof: &mut Proof<SC>| proof.core.per_air[0].public_values[2] += Val::ONE),
+        Box::new(|proof: &mut Proof<SC>| {
+            proof.core.per_air[1].exposed_values_after_challenge[0][0] += Challenge::ONE
+        }),
+        Box::new(|proof: &mut Proof<SC>| {
+            proof.core.opened_values.main[0][0].local[0] += Challenge::ONE
+        }),
+        Box::new(|proof: &mut Proof<SC>| {
+            proof.core.opened_values.main[0][1].next[1] += Challenge::ONE
+        }),
+        Box::new(|proof: &mut Proof<SC>| {
+            proof.core.opened_values.after_challenge[0][0].local[0] += Challenge::ONE
+        }),
+        Box::new(|proof: &mut Proof<SC>| {
+            proof.core.opened_values.after_challenge[0][1].next[0] += Challenge::ONE
+        }),
+        Box::new(|proof: &mut Proof<SC>| {
+            proof.core.opened_values.quotient[0][0][0] += Challenge::ONE
+        }),
+        Box::new(|proof: &mut Proof<SC>| proof.core.opened_values.quotient[1][0].swap(0, 1)),
+    ]
+}
+
+/// Mutations of the opening proof of a valid proof that keep its shape.
+fn opening_mutations() -> Vec<Mutation> {
+    vec![
+        Box::new(|proof: &mut Proof<SC>| proof.pcs_proof.pow_witness += Val::ONE),
+        Box::new(|proof: &mut Proof<SC>| {
+            proof.pcs_proof.query_proofs[1].commit_phase_openings[0].sibling_value += Challenge::ONE
+        }),
+        Box::new(|proof: &mut Proof<SC>| {
+            let query_proof = proof.pcs_proof.query_proofs.last_mut().unwrap();
+            query_proof.input_proof[0].opened_values[0][0] += Val::ONE
+        }),
+    ]
+}
+
+#[test]
+fn test_constraints_before_opening_accepts_valid_proof() {
+    let data = prove_fib_selector_with_interaction();
+    assert_eq!(verify(&data, &data.proof, false), Ok(()));
+    assert_eq!(verify(&data, &data.proof, true), Ok(()));
+}
+
+#[test]
+fn test_constraints_before_opening_matches_standard_verifier() {
+    let data = prove_fib_selector_with_interaction();
+    for (i, mutate) in mutations().iter().enumerate() {
+        let mut proof = data.proof.clone();
+        mutate(&mut proof);
+        let standard = verify(&data, &proof, false);
+        let reordered = verify(&data, &proof, true);
+        assert!(standard.is_err(), "mutation {i} was accepted");
+        assert_eq!(standard.is_ok(), reordered.is_ok(), "mutation {i}");
+    }
+}
+
+#[test]
+fn test_constraints_before_opening_invalid_shape() {
+    let data = prove_fib_selector_with_interaction();
+    let [fib_vk, sender_vk] = [0, 1].map(|air_id| &data.vk.per_air[air_id]);
+    let fib_name = fib_vk.air_name.clone();
+    let common_main_error = ProofShapeError::OpenedValues {
+        air_id: 0,
+        air_name: fib_name.clone(),
+        round: CommitmentRound::CommonMain,
+        width: fib_vk.params.width.common_main,
+        num_rotations: 0,
+    };
+    let mutations: Vec<(Mutation, ProofShapeError)> = vec![
+        (
+            Box::new(|proof: &mut Proof<SC>| proof.core.per_air[0].degree = 7),
+            ProofShapeError::TraceHeightNotPowerOfTwo {
+                air_id: 0,
+                air_name: fib_name.clone(),
+                height: 7,
+            },
+        ),
+        (
+            Box::new(|proof: &mut Proof<SC>| {
+                proof.core.per_air[0].public_values.pop();
+            }),
+            ProofShapeError::NumPublicValues {
+                air_id: 0,
+                air_name: fib_name.clone(),
+                expected: 3,
+                found: 2,
+            },
+        ),
+        (
+            Box::new(|proof: &mut Proof<SC>| {
+                proof.core.per_air[0].exposed_values_after_challenge.clear()
+            }),
+            ProofShapeError::NumExposedValues {
+                air_id: 0,
+                air_name: fib_name.clone(),
+                expected: vec![1],
+                found: vec![],
+            },
+        ),
+        (
+            Box::new(|proof: &mut Proof<SC>| {
+                proof.core.opened_values.main.last_mut().unwrap()[0]
+                    .local
+                    .pop();
+            }),
+            common_main_error.clone(),
+        ),
+        (
+            Box::new(|proof: &mut Proof<SC>| {
+                proof.core.opened_values.main.last_mut().unwrap()[0]
+                    .rotated
+                    .push(vec![])
+            }),
+            common_main_error,
+        ),
+        (
+            Box::new(|proof: &mut Proof<SC>| {
+                proof.core.opened_values.quotient[1].pop();
+            }),
+            ProofShapeError::QuotientChunks {
+                air_id: 1,
+                air_name: sender_vk.air_name.clone(),
+                num_chunks: sender_vk.quotient_degree as usize,
+                chunk_len: <Challenge as FieldExtensionAlgebra<Val>>::D,
+            },
+        ),
+        (
+            Box::new(|proof: &mut Proof<SC>| proof.commitments.after_challenge.clear()),
+            ProofShapeError::NumEntries {
+                field: "commitments.after_challenge",
+                expected: 1,
+                found: 0,
+            },
+        ),
+    ];
+    for (i, (mutate, expected)) in mutations.into_iter().enumerate() {
+        let mut proof = data.proof.clone();
+        mutate(&mut proof);
+        // The shape is validated first in both orders.
+        for constraints_before_opening in [false, true] {
+            assert_eq!(
+                verify(&data, &proof, constraints_before_opening),
+                Err(VerificationError::InvalidProofShape(expected.clone())),
+                "mutation {i}"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_constraints_before_opening_invalid_air_ids() {
+    let data = prove_fib_selector_with_interaction();
+    let mutations: Vec<(Mutation, Vec<usize>)> = vec![
+        (
+            Box::new(|proof: &mut Proof<SC>| proof.core.per_air[0].air_id = 2),
+            vec![2, 1],
+        ),
+        (
+            Box::new(|proof: &mut Proof<SC>| proof.core.per_air.swap(0, 1)),
+            vec![1, 0],
+        ),
+    ];
+    for (i, (mutate, air_ids)) in mutations.iter().enumerate() {
+        let mut proof = data.proof.clone();
+        mutate(&mut proof);
+        for constraints_before_opening in [false, true] {
+            assert_eq!(
+                verify(&data, &proof, constraints_before_opening),
+                Err(VerificationError::InvalidAirIds(air_ids.clone())),
+                "mutation {i}"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_constraints_before_opening_proof_shape_of_valid_proof() {
+    let data = prove_fib_selector_with_interaction();
+    let air_ids = data.proof.get_air_ids();
+    assert_eq!(air_ids, (0..2).collect_vec());
+    assert_eq!(validate_proof_shape(&data.vk, &data.proof, None), Ok(()));
+}
+
+#[test]
+fn test_early_queries_accept_valid_proof() {
+    let data = prove_fib_selector_with_interaction();
+    let num_queries = data.proof.pcs_proof.query_proofs.len();
+    for early_queries in [0, 1, 4, num_queries, num_queries + 1] {
+        assert_eq!(
+            verify_with_early_queries(&data, &data.proof, early_queries),
+            Ok(()),
+            "{early_queries} early queries"
+        );
+    }
+}
+
+#[test]
+fn test_early_queries_match_standard_verifier() {
+    let data = prove_fib_selector_with_interaction();
+    for (i, mutate) in mutations().iter().chain(&opening_mutations()).enumerate() {
+        let mut proof = data.proof.clone();
+        mutate(&mut proof);
+        let standard = verify(&data, &proof, false);
+        assert!(standard.is_err(), "mutation {i} was accepted");
+        for early_queries in [1, 4] {
+            assert_eq!(
+                standard.is_ok(),
+                verify_with_early_queries(&data, &proof, early_queries).is_ok(),
+                "mutation {i} with {early_queries} early queries"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_early_queries_reject_before_constraints() {
+    let data = prove_fib_selector_with_interaction();
+    let mut proof = data.proof.clone();
+    proof.core.opened_values.main[0][0].local[0] += Challenge::ONE;
+    assert!(matches!(
+        verify_with_early_queries(&data, &proof, 0),
+        Err(VerificationError::OodEvaluationMismatch { .. })
+    ));
+    // The claimed opening is wrong at every query, so the first one rejects it.
+    assert!(matches!(
+        verify_with_early_queries(&data, &proof, 1),
+        Err(VerificationError::InvalidOpeningArgument { query: Some(0), .. })
+    ));
+}
+
+#[test]
+fn test_early_queries_locate_query() {
+    let data = prove_fib_selector_with_interaction();
+    let mut proof = data.proof.clone();
+    proof.pcs_proof.query_proofs[1].commit_phase_openings[0].sibling_value += Challenge::ONE;
+    // With a single early query, the query is only rejected by the full opening proof.
+    for early_queries in [1, 4] {
+        assert!(matches!(
+            verify_with_early_queries(&data, &proof, early_queries),
+            Err(VerificationError::InvalidOpeningArgument { query: Some(1), .. })
+        ));
+    }
+}
+
+// Run with `RUSTFLAGS="-Ctarget-cpu=native" cargo t --release -- --ignored --nocapture bench_fast_reject`
+#[test]
+#[ignore = "bench"]
+fn bench_fast_reject() {
+    const RUNS: usize = 20;
+    let data = prove_fib_selector_with_interaction();
+    let corpus = mutations()
+        .iter()
+        .chain(&opening_mutations())
+        .map(|mutate| {
+            let mut proof = data.proof.clone();
+            mutate(&mut proof);
+            proof
+        })
+        .collect_vec();
+    let median_rejection_time = |verify: &dyn Fn(&Proof<SC>) -> Result<(), VerificationError>| {
+        let mut times = (0..RUNS)
+            .flat_map(|_| &corpus)
+            .map(|proof| {
+                let start = Instant::now();
+                assert!(verify(proof).is_err());
+                start.elapsed()
+            })
+            .collect_vec();
+        times.sort();
+        times[times.len() / 2]
+    };
+    let standard = median_rejection_time(&|proof| verify(&data, proof, false));
+    let fast = median_rejection_time(&|proof| verify_with_early_queries(&data, proof, 2));
+    println!("median rejection time: standard order {standard:?}, fast reject order {fast:?}");
+}
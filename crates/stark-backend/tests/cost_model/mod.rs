@@ -0,0 +1,168 @@
+use std::{sync::Arc, time::Instant};
+
+use itertools::Itertools;
+use openvm_stark_backend::{
+    config::StarkGenericConfig,
+    keygen::types::MultiStarkProvingKey,
+    p3_field::{FieldAlgebra, FieldExtensionAlgebra},
+    prover::types::{AirProofInput, ProofInput},
+    AirRef,
+};
+use openvm_stark_sdk::{
+    config::baby_bear_poseidon2::{default_engine, BabyBearPoseidon2Config},
+    dummy_airs::{
+        fib_air::{air::FibonacciAir, trace::generate_trace_rows},
+        interaction::dummy_interaction_air::DummyInteractionAir,
+    },
+    engine::{StarkEngine, StarkFriEngine},
+};
+use p3_baby_bear::BabyBear;
+use p3_matrix::{dense::RowMajorMatrix, Matrix};
+
+use crate::fib_triples_air;
+
+type SC = BabyBearPoseidon2Config;
+type Val = BabyBear;
+type Challenge = <SC as StarkGenericConfig>::Challenge;
+
+/// Three AIRs of increasing size: a Fibonacci AIR, a wider Fibonacci AIR on a taller trace,
+/// and an AIR with an interaction on an even taller trace.
+fn cases() -> Vec<(AirRef<SC>, RowMajorMatrix<Val>, Vec<Val>)> {
+    let fib_trace = generate_trace_rows::<Val>(0, 1, 1 << 6);
+    let fib_pis = vec![Val::ZERO, Val::ONE, *fib_trace.values.last().unwrap()];
+
+    let triples_trace = fib_triples_air::trace::generate_trace_rows::<Val>(0, 1, 1 << 10);
+    let triples_pis = vec![Val::ZERO, Val::ONE, *triples_trace.values.last().unwrap()];
+
+    let field_width = 3;
+    let interaction_trace = RowMajorMatrix::new(
+        (0..(1 << 14) * (field_width + 1))
+            .map(|i| Val::from_canonical_usize(i % 101))
+            .collect(),
+        field_width + 1,
+    );
+
+    vec![
+        (Arc::new(FibonacciAir) as AirRef<SC>, fib_trace, fib_pis),
+        (
+            Arc::new(fib_triples_air::air::FibonacciAir) as AirRef<SC>,
+            triples_trace,
+            triples_pis,
+        ),
+        (
+            Arc::new(DummyInteractionAir::new(field_width, true, 0)) as AirRef<SC>,
+            interaction_trace,
+            vec![],
+        ),
+    ]
+}
+
+/// The estimate of each case, with a key of its AIR alone.
+fn estimates(
+    engine: &impl StarkFriEngine<SC>,
+) -> Vec<(
+    usize,
+    MultiStarkProvingKey<SC>,
+    RowMajorMatrix<Val>,
+    Vec<Val>,
+)> {
+    let log_blowup = engine.fri_params().log_blowup;
+    let ext_degree = <Challenge as FieldExtensionAlgebra<Val>>::D;
+    cases()
+        .into_iter()
+        .map(|(air, trace, pis)| {
+            let mut keygen_builder = engine.keygen_builder();
+            let air_id = keygen_builder.add_air(air);
+            let pk = keygen_builder.generate_pk();
+            let estimate = pk.per_air[air_id]
+                .cost_model()
+                .estimate(trace.height(), log_blowup)
+                .total_base_field_ops(ext_degree);
+            (estimate, pk, trace, pis)
+        })
+        .collect()
+}
+
+// Only AIRs whose estimates differ by a large enough factor to dominate the noise of the
+// measurements and the fixed costs that the model does not capture are compared.
+const TOLERANCE: usize = 4;
+
+#[test]
+fn test_cost_model_separates_cases() {
+    let estimates = estimates(&default_engine())
+        .into_iter()
+        .map(|(estimate, ..)| estimate)
+        .collect_vec();
+    for (i, j) in (0..estimates.len()).tuple_combinations() {
+        assert!(
+            estimates[i] * TOLERANCE <= estimates[j],
+            "estimates {estimates:?} are not well separated"
+        );
+    }
+}
+
+// Compares wall-clock proving times, which depend on the machine and its load.
+// Run with `RUSTFLAGS="-Ctarget-cpu=native" cargo t --release -- --ignored --nocapture bench_cost_model_matches_proving_time`
+#[test]
+#[ignore = "bench"]
+fn bench_cost_model_matches_proving_time() {
+    let engine = default_engine();
+    let (estimates, times): (Vec<_>, Vec<_>) = estimates(&engine)
+        .into_iter()
+        .map(|(estimate, pk, trace, pis)| {
+            // Median of a few runs to reduce noise
+            let times = (0..3)
+                .map(|_| {
+                    let proof_input = ProofInput::new(vec![(
+                        0,
+                        AirProofInput::simple(trace.clone(), pis.clone()),
+                    )]);
+                    let start = Instant::now();
+                    engine.prove(&pk, proof_input);
+                    start.elapsed()
+                })
+                .sorted()
+                .collect_vec();
+            (estimate, times[1])
+        })
+        .unzip();
+
+    for (i, j) in (0..estimates.len()).tuple_combinations() {
+        assert!(
+            estimates[i] * TOLERANCE <= estimates[j],
+            "estimates {estimates:?} are not well separated"
+        );
+        assert!(
+            times[i] < times[j],
+            "estimated costs {estimates:?} do not match proving times {times:?}"
+        );
+    }
+}
+
+#[test]
+fn test_cost_model_counts() {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    let air_id = keygen_builder.add_air(Arc::new(DummyInteractionAir::new(3, true, 0)));
+    let pk = keygen_builder.generate_pk();
+    let model = pk.per_air[air_id].cost_model();
+    assert_eq!(model.main_width, 4);
+    assert!(model.after_challenge_width > 0);
+    assert!(model.quotient_ext_muls_per_row > 0);
+    // One interaction with 3 fields
+    assert_eq!(model.logup_ext_muls_per_row, 3 + 3 + 1);
+    assert_eq!(model.logup_ext_adds_per_row, 3 + 1);
+
+    let mut keygen_builder = engine.keygen_builder();
+    let air_id = keygen_builder.add_air(Arc::new(FibonacciAir));
+    let pk = keygen_builder.generate_pk();
+    let model = pk.per_air[air_id].cost_model();
+    assert_eq!(model.main_width, 2);
+    assert_eq!(model.after_challenge_width, 0);
+    assert_eq!(model.logup_ext_muls_per_row, 0);
+    assert!(model.quotient_base_adds_per_row > 0);
+
+    let small = model.estimate(1 << 4, 1);
+    let large = model.estimate(1 << 8, 1);
+    assert!(large.total_base_field_ops(4) > 16 * small.total_base_field_ops(4));
+}
@@ -0,0 +1,253 @@
+//! AIR with a main trace and a custom round matrix
+//! | x | (custom) y |
+//!
+//! Constrains y == x * x
+
+use std::sync::Arc;
+
+use openvm_stark_backend::{
+    air_builders::PartitionedAirBuilder,
+    keygen::{
+        types::{CommitmentRound, MultiStarkProvingKey, MultiStarkVerifyingKey},
+        CustomMatrixSpec, CustomRoundPosition, MainTraceKind, ProofInputError,
+    },
+    p3_field::FieldAlgebra,
+    proof::Proof,
+    prover::{
+        types::{AirProofInput, AirProofRawInput, ProofInput},
+        ProvingError, ProvingOptions,
+    },
+    rap::{BaseAirWithPublicValues, PartitionedBaseAir},
+    utils::disable_debug_builder,
+    verifier::{ProofShapeError, VerificationError},
+};
+use openvm_stark_sdk::{
+    config::baby_bear_poseidon2::{default_engine, BabyBearPoseidon2Config},
+    engine::StarkEngine,
+};
+use p3_air::{Air, BaseAir};
+use p3_baby_bear::BabyBear;
+use p3_matrix::{dense::RowMajorMatrix, Matrix};
+
+type SC = BabyBearPoseidon2Config;
+type Val = BabyBear;
+
+struct SquareAir;
+
+impl<F> BaseAirWithPublicValues<F> for SquareAir {}
+impl<F> PartitionedBaseAir<F> for SquareAir {}
+impl<F> BaseAir<F> for SquareAir {
+    fn width(&self) -> usize {
+        1
+    }
+}
+
+impl<AB: PartitionedAirBuilder> Air<AB> for SquareAir {
+    fn eval(&self, builder: &mut AB) {
+        let x = builder.common_main().row_slice(0)[0];
+        let y = builder.custom_mains()[0].row_slice(0)[0];
+        builder.assert_eq(x * x, y);
+    }
+}
+
+fn keygen_square_air(position: CustomRoundPosition) -> MultiStarkProvingKey<SC> {
+    let mut keygen_builder = default_engine().keygen_builder();
+    let air_id = keygen_builder.add_air(Arc::new(SquareAir));
+    let round = keygen_builder.add_custom_round(
+        "square",
+        vec![CustomMatrixSpec { air_id, width: 1 }],
+        position,
+    );
+    assert_eq!(round, 0);
+    keygen_builder.generate_pk()
+}
+
+/// The input of the AIR with main trace `0..num_rows` and custom matrix `ys`.
+fn square_air_input(num_rows: u32, ys: Vec<Val>) -> ProofInput<SC> {
+    let xs = (0..num_rows).map(Val::from_canonical_u32).collect();
+    let air_proof_input = AirProofInput {
+        cached_mains_pdata: vec![],
+        raw: AirProofRawInput {
+            cached_mains: vec![],
+            common_main: Some(RowMajorMatrix::new_col(xs)),
+            custom_mains: vec![Arc::new(RowMajorMatrix::new_col(ys))],
+            public_values: vec![],
+        },
+    };
+    ProofInput::new(vec![(0, air_proof_input)])
+}
+
+fn prove_square_air(
+    position: CustomRoundPosition,
+    ys: Vec<Val>,
+) -> (MultiStarkVerifyingKey<SC>, Proof<SC>) {
+    let pk = keygen_square_air(position);
+    let proof = default_engine().prove(&pk, square_air_input(ys.len() as u32, ys));
+    (pk.get_vk(), proof)
+}
+
+fn squares(n: u32) -> Vec<Val> {
+    (0..n).map(|x| Val::from_canonical_u32(x * x)).collect()
+}
+
+#[test]
+fn test_custom_round_after_main() {
+    let (vk, proof) = prove_square_air(CustomRoundPosition::AfterMain, squares(8));
+    assert_eq!(
+        vk.commitment_rounds,
+        vec![
+            CommitmentRound::Preprocessed,
+            CommitmentRound::CachedMain,
+            CommitmentRound::CommonMain,
+            CommitmentRound::Custom {
+                index: 0,
+                name: "square".to_string()
+            },
+            CommitmentRound::AfterChallenge { phase: 0 },
+            CommitmentRound::Quotient,
+        ]
+    );
+    assert_eq!(proof.commitments.custom.len(), 1);
+    default_engine()
+        .verify(&vk, &proof)
+        .expect("Verification failed");
+}
+
+#[test]
+fn test_custom_round_before_main() {
+    let (vk, proof) = prove_square_air(CustomRoundPosition::BeforeMain, squares(8));
+    assert_eq!(
+        vk.commitment_rounds[1],
+        CommitmentRound::Custom {
+            index: 0,
+            name: "square".to_string()
+        }
+    );
+    default_engine()
+        .verify(&vk, &proof)
+        .expect("Verification failed");
+}
+
+#[test]
+fn test_custom_round_constraint_failure() {
+    let mut ys = squares(8);
+    ys[3] = Val::ZERO;
+    disable_debug_builder();
+    let (vk, proof) = prove_square_air(CustomRoundPosition::AfterMain, ys);
+    assert_eq!(
+        default_engine().verify(&vk, &proof),
+        Err(VerificationError::OodEvaluationMismatch {
+            air_id: 0,
+            air_name: "SquareAir".to_string(),
+        })
+    );
+}
+
+#[test]
+fn test_custom_round_omitted() {
+    let (vk, mut proof) = prove_square_air(CustomRoundPosition::AfterMain, squares(8));
+    proof.commitments.custom.clear();
+    proof.core.opened_values.custom.clear();
+    assert_eq!(
+        default_engine().verify(&vk, &proof),
+        Err(VerificationError::InvalidProofShape(
+            ProofShapeError::NumEntries {
+                field: "commitments.custom",
+                expected: 1,
+                found: 0,
+            }
+        ))
+    );
+}
+
+#[test]
+fn test_custom_round_height_mismatch() {
+    let engine = default_engine();
+    let pk = keygen_square_air(CustomRoundPosition::AfterMain);
+    assert_eq!(
+        engine
+            .prove_with_options(
+                &pk,
+                square_air_input(8, squares(4)),
+                ProvingOptions::default()
+            )
+            .err(),
+        Some(ProvingError::InvalidInput(ProofInputError::Height {
+            air_id: 0,
+            air_name: "SquareAir".to_string(),
+            kind: MainTraceKind::Custom,
+            index: 0,
+            height: 4,
+            expected: 8,
+        }))
+    );
+}
+
+#[test]
+fn test_custom_round_height_mismatch_rejected() {
+    // A custom matrix twice as high as the main trace of the AIR.
+    let (vk, mut proof) = prove_square_air(CustomRoundPosition::AfterMain, squares(8));
+    let (_, tall_proof) = prove_square_air(CustomRoundPosition::AfterMain, squares(16));
+    proof.commitments.custom = tall_proof.commitments.custom;
+    proof.core.opened_values.custom = tall_proof.core.opened_values.custom;
+    assert!(default_engine().verify(&vk, &proof).is_err());
+}
+
+#[test]
+fn test_custom_round_ordering_bound_to_transcript() {
+    // Verifying against a key that observes the custom round at a different position must fail.
+    let (mut vk, proof) = prove_square_air(CustomRoundPosition::AfterMain, squares(8));
+    let custom = vk.commitment_rounds.remove(3);
+    vk.commitment_rounds.insert(1, custom);
+    assert!(default_engine().verify(&vk, &proof).is_err());
+}
+
+#[test]
+fn test_custom_round_removed_with_last_air() {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    for _ in 0..3 {
+        keygen_builder.add_air(Arc::new(SquareAir));
+    }
+    let spec = |air_id| CustomMatrixSpec { air_id, width: 1 };
+    keygen_builder.add_custom_round("first", vec![spec(0)], CustomRoundPosition::AfterMain);
+    keygen_builder.add_custom_round(
+        "shared",
+        vec![spec(1), spec(2)],
+        CustomRoundPosition::AfterMain,
+    );
+    // The round "first" has no matrices left, and "shared" still has the one of the last AIR.
+    keygen_builder.remove_air(0);
+    keygen_builder.remove_air(0);
+    let pk = keygen_builder.generate_pk();
+    let vk = pk.get_vk();
+    assert_eq!(
+        vk.commitment_rounds[3],
+        CommitmentRound::Custom {
+            index: 0,
+            name: "shared".to_string()
+        }
+    );
+    assert_eq!(
+        vk.commitment_rounds
+            .iter()
+            .filter(|round| matches!(round, CommitmentRound::Custom { .. }))
+            .count(),
+        1
+    );
+
+    let ys = squares(8);
+    let xs = (0..8).map(Val::from_canonical_u32).collect();
+    let air_proof_input = AirProofInput {
+        cached_mains_pdata: vec![],
+        raw: AirProofRawInput {
+            cached_mains: vec![],
+            common_main: Some(RowMajorMatrix::new_col(xs)),
+            custom_mains: vec![Arc::new(RowMajorMatrix::new_col(ys))],
+            public_values: vec![],
+        },
+    };
+    let proof = engine.prove(&pk, ProofInput::new(vec![(0, air_proof_input)]));
+    assert_eq!(proof.commitments.custom.len(), 1);
+    engine.verify(&vk, &proof).expect("Verification failed");
+}
@@ -0,0 +1,124 @@
+use std::{env, fs, path::Path, process::Command, sync::Arc};
+
+use openvm_stark_backend::{
+    keygen::types::MultiStarkProvingKey,
+    p3_field::FieldAlgebra,
+    prover::types::{AirProofInput, ProofInput},
+};
+use openvm_stark_sdk::{
+    config::baby_bear_poseidon2::{default_engine, BabyBearPoseidon2Config},
+    dummy_airs::{
+        fib_air::{self, air::FibonacciAir},
+        interaction::dummy_interaction_air::DummyInteractionAir,
+    },
+    engine::StarkEngine,
+};
+use p3_baby_bear::BabyBear;
+use p3_matrix::dense::RowMajorMatrix;
+
+use crate::{
+    fib_selector_air::{self, air::FibonacciSelectorAir},
+    get_conditional_fib_number, get_fib_number,
+};
+
+type SC = BabyBearPoseidon2Config;
+type Val = BabyBear;
+
+/// The child process writes its proof to the path in this variable.
+const PROOF_PATH_VAR: &str = "DETERMINISTIC_PROOF_PATH";
+
+const N: usize = 64;
+
+fn selectors() -> Vec<bool> {
+    (0..N).map(|i| i % 3 == 0).collect()
+}
+
+/// A Fibonacci AIR, a Fibonacci AIR with preprocessed selectors, and an AIR sending on bus 0 what
+/// another AIR receives, so that the proof has every kind of trace.
+fn keygen() -> MultiStarkProvingKey<SC> {
+    let mut keygen_builder = default_engine().keygen_builder();
+    keygen_builder.add_air(Arc::new(FibonacciAir));
+    keygen_builder.add_air(Arc::new(FibonacciSelectorAir::new(selectors(), false)));
+    keygen_builder.add_air(Arc::new(DummyInteractionAir::new(1, true, 0)));
+    keygen_builder.add_air(Arc::new(DummyInteractionAir::new(1, false, 0)));
+    keygen_builder.generate_pk()
+}
+
+fn proof_input() -> ProofInput<SC> {
+    let sels = selectors();
+    // | count | field |
+    let interaction_trace = || {
+        AirProofInput::simple_no_pis(RowMajorMatrix::new(
+            (0..N as u32)
+                .flat_map(|i| [i % 4, i * i])
+                .map(Val::from_canonical_u32)
+                .collect(),
+            2,
+        ))
+    };
+    ProofInput::new(vec![
+        (
+            0,
+            AirProofInput::simple(
+                fib_air::trace::generate_trace_rows::<Val>(0, 1, N),
+                [0, 1, get_fib_number(N)]
+                    .map(Val::from_canonical_u32)
+                    .to_vec(),
+            ),
+        ),
+        (
+            1,
+            AirProofInput::simple(
+                fib_selector_air::trace::generate_trace_rows::<Val>(0, 1, &sels),
+                [0, 1, get_conditional_fib_number(&sels)]
+                    .map(Val::from_canonical_u32)
+                    .to_vec(),
+            ),
+        ),
+        (2, interaction_trace()),
+        (3, interaction_trace()),
+    ])
+}
+
+fn proof_bytes() -> Vec<u8> {
+    let engine = default_engine();
+    let proof = engine.prove_deterministic(&keygen(), proof_input());
+    bincode::serialize(&proof).unwrap()
+}
+
+/// Run by [test_deterministic_proof_across_processes] in a child process, does nothing otherwise.
+#[test]
+fn test_deterministic_proof_child() {
+    if let Ok(path) = env::var(PROOF_PATH_VAR) {
+        fs::write(path, proof_bytes()).unwrap();
+    }
+}
+
+/// Proves in a child process with `num_threads` threads, which also has its own address space
+/// layout and hash seeds.
+fn prove_in_child(path: &Path, num_threads: usize) -> Vec<u8> {
+    let status = Command::new(env::current_exe().unwrap())
+        .args([
+            "--exact",
+            "deterministic_proof::test_deterministic_proof_child",
+        ])
+        .env(PROOF_PATH_VAR, path)
+        .env("RAYON_NUM_THREADS", num_threads.to_string())
+        .status()
+        .unwrap();
+    assert!(status.success());
+    fs::read(path).unwrap()
+}
+
+#[test]
+fn test_deterministic_proof_across_processes() {
+    let dir = tempfile::tempdir().unwrap();
+    let proofs = [1, 4].map(|num_threads| {
+        prove_in_child(
+            &dir.path().join(format!("proof_{num_threads}.bin")),
+            num_threads,
+        )
+    });
+    assert_eq!(proofs[0], proofs[1]);
+    assert_eq!(proofs[0], proof_bytes());
+}
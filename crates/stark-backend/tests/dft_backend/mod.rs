@@ -0,0 +1,54 @@
+use std::sync::Arc;
+
+use openvm_stark_backend::{
+    p3_field::FieldAlgebra,
+    prover::types::{AirProofInput, ProofInput},
+};
+use openvm_stark_sdk::{
+    config::{
+        baby_bear_poseidon2::{default_perm, engine_from_perm_with_dft},
+        dft::DftBackend,
+        FriParameters,
+    },
+    dummy_airs::fib_air::{self, air::FibonacciAir},
+    engine::StarkEngine,
+};
+use p3_baby_bear::BabyBear;
+
+type Val = BabyBear;
+
+#[test]
+fn test_dft_backends_give_identical_proofs() {
+    // Heights below and above the height where `DftBackend::Auto` switches algorithm.
+    for n in [1 << 3, 1 << 13] {
+        let mut expected = None;
+        for dft_backend in [
+            DftBackend::Auto,
+            DftBackend::Radix2Dit,
+            DftBackend::Radix2DitParallel,
+            DftBackend::Radix2Bowers,
+        ] {
+            let engine = engine_from_perm_with_dft(
+                default_perm(),
+                FriParameters::standard_fast(),
+                dft_backend,
+            );
+            let mut keygen_builder = engine.keygen_builder();
+            keygen_builder.add_air(Arc::new(FibonacciAir));
+            let pk = keygen_builder.generate_pk();
+            let trace = fib_air::trace::generate_trace_rows::<Val>(0, 1, n);
+            let pis = vec![Val::ZERO, Val::ONE, trace.values[trace.values.len() - 1]];
+            let input = ProofInput::new(vec![(0, AirProofInput::simple(trace, pis))]);
+            let proof = engine.prove(&pk, input);
+            engine
+                .verify(&pk.get_vk(), &proof)
+                .expect("Verification failed");
+            let bytes = bincode::serialize(&proof).unwrap();
+            assert_eq!(
+                &bytes,
+                expected.get_or_insert_with(|| bytes.clone()),
+                "{dft_backend:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,254 @@
+use std::sync::Arc;
+
+use openvm_stark_backend::{
+    config::{StarkGenericConfig, Val},
+    engine::StarkEngine,
+    interaction::{
+        gkr_log_up::{GkrLogUpError, GKR_LU_WIDTH},
+        public_lookup::PublicLookupTable,
+        InteractionBuilder, InteractionType, RapPhaseSeqKind,
+    },
+    keygen::{
+        types::{MultiStarkProvingKey, MultiStarkVerifyingKey, VkFeature},
+        ProofInputError,
+    },
+    p3_field::{Field, FieldAlgebra, PrimeField64},
+    proof::Proof,
+    prover::{
+        types::{AirProofInput, ProofInput},
+        ProvingError, ProvingOptions,
+    },
+    rap::{BaseAirWithPublicValues, PartitionedBaseAir},
+    utils::disable_debug_builder,
+    verifier::VerificationError,
+};
+use openvm_stark_sdk::{
+    config::{
+        baby_bear_poseidon2::{
+            config_from_perm_gkr, default_engine, default_perm, BabyBearPoseidon2GkrConfig,
+            Challenger,
+        },
+        FriParameters,
+    },
+    proof_serde::ProofSerde,
+};
+use p3_air::{Air, BaseAir};
+use p3_baby_bear::Poseidon2BabyBear;
+use p3_matrix::{dense::RowMajorMatrix, Matrix};
+
+type GkrSC = BabyBearPoseidon2GkrConfig;
+
+const NUM_BUSES: usize = 8;
+const SENDER_HEIGHT: usize = 8;
+const RECEIVER_HEIGHT: usize = 16;
+
+/// Sends or receives the value `v_i` with multiplicity `count` on bus `i`, for each of the
+/// `NUM_BUSES` buses.
+///
+/// Main columns: `count, [v; NUM_BUSES]`.
+struct MultiBusAir {
+    is_send: bool,
+}
+
+impl<F: Field> BaseAir<F> for MultiBusAir {
+    fn width(&self) -> usize {
+        1 + NUM_BUSES
+    }
+}
+impl<F: Field> BaseAirWithPublicValues<F> for MultiBusAir {}
+impl<F: Field> PartitionedBaseAir<F> for MultiBusAir {}
+
+impl<AB: InteractionBuilder> Air<AB> for MultiBusAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        let interaction_type = if self.is_send {
+            InteractionType::Send
+        } else {
+            InteractionType::Receive
+        };
+        for bus in 0..NUM_BUSES {
+            builder.push_interaction(bus, [local[1 + bus]], local[0], interaction_type);
+        }
+    }
+}
+
+/// Trace with the messages of the sender on the first `SENDER_HEIGHT` rows, and count zero on
+/// the other rows.
+fn generate_trace<F: Field>(height: usize) -> RowMajorMatrix<F> {
+    let values = (0..height)
+        .flat_map(|row| {
+            let count = if row < SENDER_HEIGHT { row + 1 } else { 0 };
+            std::iter::once(count).chain((0..NUM_BUSES).map(move |bus| row * NUM_BUSES + bus))
+        })
+        .map(F::from_canonical_usize)
+        .collect();
+    RowMajorMatrix::new(values, 1 + NUM_BUSES)
+}
+
+/// Proves the sender and the receiver of the `NUM_BUSES` buses, with `receiver_trace` as the
+/// trace of the receiver.
+fn prove<SC: StarkGenericConfig>(
+    engine: &impl StarkEngine<SC>,
+    receiver_trace: RowMajorMatrix<Val<SC>>,
+) -> (MultiStarkProvingKey<SC>, Proof<SC>)
+where
+    Val<SC>: PrimeField64,
+{
+    let mut keygen_builder = engine.keygen_builder();
+    let sender_id = keygen_builder.add_air(Arc::new(MultiBusAir { is_send: true }));
+    let receiver_id = keygen_builder.add_air(Arc::new(MultiBusAir { is_send: false }));
+    let pk = keygen_builder.generate_pk();
+    let proof = engine.prove(
+        &pk,
+        ProofInput::new(vec![
+            (
+                sender_id,
+                AirProofInput::simple_no_pis(generate_trace(SENDER_HEIGHT)),
+            ),
+            (receiver_id, AirProofInput::simple_no_pis(receiver_trace)),
+        ]),
+    );
+    (pk, proof)
+}
+
+/// Total width, in extension field columns, of the after challenge traces.
+fn after_challenge_width<SC: StarkGenericConfig>(pk: &MultiStarkProvingKey<SC>) -> usize {
+    pk.per_air
+        .iter()
+        .flat_map(|pk| &pk.vk.params.width.after_challenge)
+        .sum()
+}
+
+struct GkrEngine {
+    config: GkrSC,
+    perm: Poseidon2BabyBear<16>,
+}
+
+impl StarkEngine<GkrSC> for GkrEngine {
+    fn config(&self) -> &GkrSC {
+        &self.config
+    }
+
+    fn max_constraint_degree(&self) -> Option<usize> {
+        Some(FriParameters::standard_fast().max_constraint_degree())
+    }
+
+    fn new_challenger(&self) -> Challenger<Poseidon2BabyBear<16>> {
+        Challenger::new(self.perm.clone())
+    }
+}
+
+fn gkr_engine() -> GkrEngine {
+    let perm = default_perm();
+    GkrEngine {
+        config: config_from_perm_gkr(&perm, FriParameters::standard_fast()),
+        perm,
+    }
+}
+
+#[test]
+fn test_gkr_log_up_matches_fri_log_up() {
+    let fri_engine = default_engine();
+    let (fri_pk, fri_proof) = prove(&fri_engine, generate_trace(RECEIVER_HEIGHT));
+    fri_engine
+        .verify(&fri_pk.get_vk(), &fri_proof)
+        .expect("Verification failed");
+
+    let engine = gkr_engine();
+    let (gkr_pk, gkr_proof) = prove(&engine, generate_trace(RECEIVER_HEIGHT));
+    engine
+        .verify(&gkr_pk.get_vk(), &gkr_proof)
+        .expect("Verification failed");
+
+    assert!(gkr_proof.rap_phase_seq_proof.is_some());
+    assert!(gkr_pk.features.contains(VkFeature::GkrLogUp));
+    assert!(!fri_pk.features.contains(VkFeature::GkrLogUp));
+    // The GKR phase commits two columns per AIR, whatever its number of interactions.
+    assert_eq!(after_challenge_width(&gkr_pk), 2 * GKR_LU_WIDTH);
+    assert!(after_challenge_width(&gkr_pk) < after_challenge_width(&fri_pk));
+}
+
+#[test]
+fn test_gkr_log_up_proof_serde_round_trip() {
+    let engine = gkr_engine();
+    let (pk, proof) = prove(&engine, generate_trace(RECEIVER_HEIGHT));
+    let bytes = proof.to_bytes();
+    let decoded = Proof::<GkrSC>::from_bytes(&bytes).unwrap();
+    assert_eq!(decoded.to_bytes(), bytes);
+    engine
+        .verify(&pk.get_vk(), &decoded)
+        .expect("Verification failed");
+}
+
+#[test]
+fn test_gkr_log_up_unbalanced() {
+    let mut receiver_trace = generate_trace(RECEIVER_HEIGHT);
+    // Receive messages that are not sent.
+    receiver_trace.values[SENDER_HEIGHT * (1 + NUM_BUSES)] = Val::<GkrSC>::ONE;
+    disable_debug_builder();
+    let engine = gkr_engine();
+    let (pk, proof) = prove(&engine, receiver_trace);
+    assert!(matches!(
+        engine.verify(&pk.get_vk(), &proof),
+        Err(VerificationError::LogUpSumMismatch { error, report })
+            if error == GkrLogUpError::NonZeroSum.to_string()
+                && report.unbalanced_buses.is_empty()
+    ));
+}
+
+#[test]
+fn test_gkr_log_up_rejects_public_lookup_tables() {
+    let engine = gkr_engine();
+    let (mut pk, proof) = prove(&engine, generate_trace(RECEIVER_HEIGHT));
+    // Keygen rejects public lookup tables with GKR log-up, but a key may be altered afterwards.
+    pk.public_lookup_tables.push(PublicLookupTable {
+        bus_index: NUM_BUSES,
+        rows: vec![vec![Val::<GkrSC>::ZERO]],
+    });
+    assert_eq!(
+        engine.verify(&pk.get_vk(), &proof),
+        Err(VerificationError::ChallengePhaseError(
+            GkrLogUpError::PublicLookupTablesUnsupported.to_string()
+        ))
+    );
+    let proof_input = ProofInput::new(vec![
+        (
+            0,
+            AirProofInput::simple_no_pis(generate_trace(SENDER_HEIGHT)),
+        ),
+        (
+            1,
+            AirProofInput::simple_no_pis(generate_trace(RECEIVER_HEIGHT)),
+        ),
+    ]);
+    assert_eq!(
+        engine
+            .prove_with_options(&pk, proof_input, ProvingOptions::default())
+            .err(),
+        Some(ProvingError::InvalidInput(
+            ProofInputError::PublicLookupTablesUnsupported {
+                rap_phase_seq_kind: RapPhaseSeqKind::GkrLogUp,
+            }
+        ))
+    );
+}
+
+#[test]
+fn test_gkr_log_up_rejects_fri_log_up_key() {
+    let (fri_pk, _) = prove(&default_engine(), generate_trace(RECEIVER_HEIGHT));
+    let engine = gkr_engine();
+    let (_, gkr_proof) = prove(&engine, generate_trace(RECEIVER_HEIGHT));
+    // Same key, but read as a key of the GKR configuration.
+    let fri_vk: MultiStarkVerifyingKey<GkrSC> =
+        serde_json::from_str(&serde_json::to_string(&fri_pk.get_vk()).unwrap()).unwrap();
+    assert_eq!(
+        engine.verify(&fri_vk, &gkr_proof),
+        Err(VerificationError::RapPhaseSeqKindMismatch {
+            air_id: 0,
+            air_name: "MultiBusAir".to_string(),
+            expected: RapPhaseSeqKind::GkrLogUp,
+            found: RapPhaseSeqKind::FriLogUp,
+        })
+    );
+}
@@ -1,8 +1,18 @@
-use openvm_stark_backend::{p3_field::FieldAlgebra, utils::disable_debug_builder, Chip};
+use openvm_stark_backend::{
+    config::{StarkGenericConfig, Val},
+    p3_field::{FieldAlgebra, PrimeField32},
+    prover::types::AirProofInput,
+    utils::disable_debug_builder,
+    Chip,
+};
 /// Test utils
 use openvm_stark_sdk::{
     any_rap_arc_vec, config,
-    config::{baby_bear_poseidon2::BabyBearPoseidon2Engine, FriParameters},
+    config::{
+        baby_bear_poseidon2::{BabyBearPoseidon2Config, BabyBearPoseidon2Engine},
+        koala_bear_poseidon2::{KoalaBearPoseidon2Config, KoalaBearPoseidon2Engine},
+        FriParameters,
+    },
     dummy_airs::{
         fib_air::chip::FibonacciChip,
         interaction::dummy_interaction_air::{DummyInteractionChip, DummyInteractionData},
@@ -10,16 +20,114 @@ use openvm_stark_sdk::{
     engine::StarkFriEngine,
     utils,
 };
-use p3_baby_bear::BabyBear;
 
+mod absent_air;
+mod air_names;
+mod air_subset;
+mod batch_prover;
+mod batch_verifier;
+mod bus_registry;
 mod cached_lookup;
+mod commit_matrices;
+mod configuration_public_values;
+mod constraint_degree;
+mod constraints_before_opening;
+mod cost_model;
+mod custom_round;
+mod deterministic_proof;
+mod dft_backend;
 mod fib_selector_air;
 mod fib_triples_air;
+mod gkr_log_up;
 pub mod interaction;
+mod interaction_limits;
+#[cfg(feature = "interaction-log")]
+mod interaction_log;
+mod interaction_pruning;
+mod key_consistency;
+mod key_serde;
+mod keygen_errors;
+mod keygen_summary;
+mod log_up_batching;
+mod log_up_inverses;
+mod log_up_repetitions;
+mod matrix_ordering;
+mod max_trace_height;
+mod non_interacting_airs;
+mod opening_witness;
+mod parallel_keygen;
 mod partitioned_sum_air;
+mod periodic_columns;
+mod precommitted_trace;
+mod preprocessed_consistency;
+mod preprocessed_per_height;
+mod proof_core;
+mod proof_exposed_values;
+mod proof_input_errors;
+mod proof_json;
+mod proof_metadata;
+mod proof_metrics;
+mod proof_serde;
+mod prover_device;
+mod proving_options;
+mod pruned_vk;
+mod public_lookup_table;
+mod public_values_check;
+mod quotient_degree_override;
+mod range_check_table;
+mod rap_view;
+mod replace_air;
+mod rotation_air;
+mod sharded_key;
+mod short_trace;
+mod soundness;
+mod staged_prover;
+mod transcript_prefix;
+mod verification_errors;
+mod verifier_limits;
+mod vk_digest;
+mod vk_features;
+mod vk_json;
+mod vk_serialization;
+mod zero_knowledge;
 
 #[test]
 fn test_single_fib_stark() {
+    single_fib_stark::<BabyBearPoseidon2Config, BabyBearPoseidon2Engine>();
+    single_fib_stark::<KoalaBearPoseidon2Config, KoalaBearPoseidon2Engine>();
+}
+
+#[test]
+fn test_single_fib_triples_stark() {
+    single_fib_triples_stark::<BabyBearPoseidon2Config, BabyBearPoseidon2Engine>();
+    single_fib_triples_stark::<KoalaBearPoseidon2Config, KoalaBearPoseidon2Engine>();
+}
+
+#[test]
+fn test_single_fib_selector_stark() {
+    single_fib_selector_stark::<BabyBearPoseidon2Config, BabyBearPoseidon2Engine>();
+    single_fib_selector_stark::<KoalaBearPoseidon2Config, KoalaBearPoseidon2Engine>();
+}
+
+#[test]
+fn test_double_fib_starks() {
+    double_fib_starks::<BabyBearPoseidon2Config, BabyBearPoseidon2Engine>();
+    double_fib_starks::<KoalaBearPoseidon2Config, KoalaBearPoseidon2Engine>();
+}
+
+#[test]
+fn test_optional_air() {
+    optional_air::<BabyBearPoseidon2Config, BabyBearPoseidon2Engine>();
+    optional_air::<KoalaBearPoseidon2Config, KoalaBearPoseidon2Engine>();
+}
+
+fn single_fib_stark<SC, E>()
+where
+    SC: StarkGenericConfig,
+    E: StarkFriEngine<SC>,
+    Val<SC>: PrimeField32,
+    AirProofInput<SC>: Send + Sync,
+{
     use openvm_stark_sdk::dummy_airs::fib_air::{air::FibonacciAir, trace::generate_trace_rows};
 
     let log_trace_degree = 3;
@@ -29,20 +137,24 @@ fn test_single_fib_stark() {
     let b = 1u32;
     let n = 1usize << log_trace_degree;
 
-    type Val = BabyBear;
     let pis = [a, b, get_fib_number(n)]
-        .map(BabyBear::from_canonical_u32)
+        .map(Val::<SC>::from_canonical_u32)
         .to_vec();
     let air = FibonacciAir;
 
-    let trace = generate_trace_rows::<Val>(a, b, n);
+    let trace = generate_trace_rows::<Val<SC>>(a, b, n);
 
-    BabyBearPoseidon2Engine::run_simple_test_fast(any_rap_arc_vec![air], vec![trace], vec![pis])
+    E::run_simple_test_fast(any_rap_arc_vec![air], vec![trace], vec![pis])
         .expect("Verification failed");
 }
 
-#[test]
-fn test_single_fib_triples_stark() {
+fn single_fib_triples_stark<SC, E>()
+where
+    SC: StarkGenericConfig,
+    E: StarkFriEngine<SC>,
+    Val<SC>: PrimeField32,
+    AirProofInput<SC>: Send + Sync,
+{
     use fib_triples_air::{air::FibonacciAir, trace::generate_trace_rows};
 
     let log_trace_degree = 3;
@@ -52,21 +164,25 @@ fn test_single_fib_triples_stark() {
     let b = 1u32;
     let n = 1usize << log_trace_degree;
 
-    type Val = BabyBear;
     let pis = [a, b, get_fib_number(n + 1)]
-        .map(BabyBear::from_canonical_u32)
+        .map(Val::<SC>::from_canonical_u32)
         .to_vec();
 
     let air = FibonacciAir;
 
-    let trace = generate_trace_rows::<Val>(a, b, n);
+    let trace = generate_trace_rows::<Val<SC>>(a, b, n);
 
-    BabyBearPoseidon2Engine::run_simple_test_fast(any_rap_arc_vec![air], vec![trace], vec![pis])
+    E::run_simple_test_fast(any_rap_arc_vec![air], vec![trace], vec![pis])
         .expect("Verification failed");
 }
 
-#[test]
-fn test_single_fib_selector_stark() {
+fn single_fib_selector_stark<SC, E>()
+where
+    SC: StarkGenericConfig,
+    E: StarkFriEngine<SC>,
+    Val<SC>: PrimeField32,
+    AirProofInput<SC>: Send + Sync,
+{
     use fib_selector_air::{air::FibonacciSelectorAir, trace::generate_trace_rows};
 
     let log_trace_degree = 3;
@@ -76,22 +192,26 @@ fn test_single_fib_selector_stark() {
     let b = 1u32;
     let n = 1usize << log_trace_degree;
 
-    type Val = BabyBear;
     let sels: Vec<bool> = (0..n).map(|i| i % 2 == 0).collect();
     let pis = [a, b, get_conditional_fib_number(&sels)]
-        .map(BabyBear::from_canonical_u32)
+        .map(Val::<SC>::from_canonical_u32)
         .to_vec();
 
     let air = FibonacciSelectorAir::new(sels, false);
 
-    let trace = generate_trace_rows::<Val>(a, b, air.sels());
+    let trace = generate_trace_rows::<Val<SC>>(a, b, air.sels());
 
-    BabyBearPoseidon2Engine::run_simple_test_fast(any_rap_arc_vec![air], vec![trace], vec![pis])
+    E::run_simple_test_fast(any_rap_arc_vec![air], vec![trace], vec![pis])
         .expect("Verification failed");
 }
 
-#[test]
-fn test_double_fib_starks() {
+fn double_fib_starks<SC, E>()
+where
+    SC: StarkGenericConfig,
+    E: StarkFriEngine<SC>,
+    Val<SC>: PrimeField32,
+    AirProofInput<SC>: Send + Sync,
+{
     use fib_selector_air::air::FibonacciSelectorAir;
     use openvm_stark_sdk::dummy_airs::{fib_air, fib_air::air::FibonacciAir};
 
@@ -104,22 +224,21 @@ fn test_double_fib_starks() {
     let n1 = 1usize << log_n1;
     let n2 = 1usize << log_n2;
 
-    type Val = BabyBear;
     let sels: Vec<bool> = (0..n2).map(|i| i % 2 == 0).collect(); // Evens
     let pis1 = [a, b, get_fib_number(n1)]
-        .map(BabyBear::from_canonical_u32)
+        .map(Val::<SC>::from_canonical_u32)
         .to_vec();
     let pis2 = [a, b, get_conditional_fib_number(&sels)]
-        .map(BabyBear::from_canonical_u32)
+        .map(Val::<SC>::from_canonical_u32)
         .to_vec();
 
     let air1 = FibonacciAir;
     let air2 = FibonacciSelectorAir::new(sels, false);
 
-    let trace1 = fib_air::trace::generate_trace_rows::<Val>(a, b, n1);
-    let trace2 = fib_selector_air::trace::generate_trace_rows::<Val>(a, b, air2.sels());
+    let trace1 = fib_air::trace::generate_trace_rows::<Val<SC>>(a, b, n1);
+    let trace2 = fib_selector_air::trace::generate_trace_rows::<Val<SC>>(a, b, air2.sels());
 
-    BabyBearPoseidon2Engine::run_simple_test_fast(
+    E::run_simple_test_fast(
         any_rap_arc_vec![air1, air2],
         vec![trace1, trace2],
         vec![pis1, pis2],
@@ -127,11 +246,15 @@ fn test_double_fib_starks() {
     .expect("Verification failed");
 }
 
-#[test]
-fn test_optional_air() {
+fn optional_air<SC, E>()
+where
+    SC: StarkGenericConfig,
+    E: StarkFriEngine<SC>,
+    Val<SC>: PrimeField32,
+{
     use openvm_stark_backend::{engine::StarkEngine, prover::types::ProofInput};
 
-    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+    let engine = E::new(FriParameters::standard_fast());
     let fib_chip = FibonacciChip::new(0, 1, 8);
     let send_chip1 = DummyInteractionChip::new_without_partition(1, true, 0);
     let send_chip2 = DummyInteractionChip::new_with_partition(engine.config(), 1, true, 0);
@@ -164,14 +287,12 @@ fn test_optional_air() {
         });
         let proof = engine.prove(
             &pk,
-            ProofInput {
-                per_air: vec![
-                    fib_chip.generate_air_proof_input_with_id(fib_chip_id),
-                    send_chip1.generate_air_proof_input_with_id(send_chip1_id),
-                    send_chip2.generate_air_proof_input_with_id(send_chip2_id),
-                    recv_chip1.generate_air_proof_input_with_id(recv_chip1_id),
-                ],
-            },
+            ProofInput::new(vec![
+                fib_chip.generate_air_proof_input_with_id(fib_chip_id),
+                send_chip1.generate_air_proof_input_with_id(send_chip1_id),
+                send_chip2.generate_air_proof_input_with_id(send_chip2_id),
+                recv_chip1.generate_air_proof_input_with_id(recv_chip1_id),
+            ]),
         );
         let mut challenger = engine.new_challenger();
         verifier
@@ -192,12 +313,10 @@ fn test_optional_air() {
         });
         let proof = engine.prove(
             &pk,
-            ProofInput {
-                per_air: vec![
-                    send_chip1.generate_air_proof_input_with_id(send_chip1_id),
-                    recv_chip1.generate_air_proof_input_with_id(recv_chip1_id),
-                ],
-            },
+            ProofInput::new(vec![
+                send_chip1.generate_air_proof_input_with_id(send_chip1_id),
+                recv_chip1.generate_air_proof_input_with_id(recv_chip1_id),
+            ]),
         );
         let mut challenger = engine.new_challenger();
         verifier
@@ -214,9 +333,9 @@ fn test_optional_air() {
         });
         let proof = engine.prove(
             &pk,
-            ProofInput {
-                per_air: vec![recv_chip1.generate_air_proof_input_with_id(recv_chip1_id)],
-            },
+            ProofInput::new(vec![
+                recv_chip1.generate_air_proof_input_with_id(recv_chip1_id)
+            ]),
         );
         let mut challenger = engine.new_challenger();
         assert!(verifier
@@ -1,8 +1,23 @@
 use itertools::Itertools;
-use openvm_stark_backend::{p3_field::FieldAlgebra, verifier::VerificationError};
+use openvm_stark_backend::{
+    config::{StarkGenericConfig, Val},
+    interaction::{
+        bus::BusRegistry,
+        debug::{BusImbalance, RowInteraction},
+        fri_log_up::FriLogUpError,
+    },
+    p3_field::{FieldAlgebra, PrimeField32},
+    verifier::VerificationError,
+};
 use openvm_stark_sdk::{
     any_rap_arc_vec,
+    config::{
+        baby_bear_poseidon2::{BabyBearPoseidon2Config, BabyBearPoseidon2Engine},
+        koala_bear_poseidon2::{KoalaBearPoseidon2Config, KoalaBearPoseidon2Engine},
+    },
+    debug::debug_interactions,
     dummy_airs::interaction::{dummy_interaction_air::DummyInteractionAir, verify_interactions},
+    engine::StarkFriEngine,
 };
 use p3_baby_bear::BabyBear;
 use p3_matrix::dense::RowMajorMatrix;
@@ -13,10 +28,18 @@ use crate::{
     utils::to_field_vec,
 };
 
-type Val = BabyBear;
-
 #[test]
 fn test_interaction_fib_selector_happy_path() {
+    fib_selector_happy_path::<BabyBearPoseidon2Config, BabyBearPoseidon2Engine>();
+    fib_selector_happy_path::<KoalaBearPoseidon2Config, KoalaBearPoseidon2Engine>();
+}
+
+fn fib_selector_happy_path<SC, E>()
+where
+    SC: StarkGenericConfig,
+    E: StarkFriEngine<SC>,
+    Val<SC>: PrimeField32,
+{
     let log_trace_degree = 3;
 
     // Public inputs:
@@ -28,27 +51,27 @@ fn test_interaction_fib_selector_happy_path() {
     let fib_res = get_conditional_fib_number(&sels);
     let pis = vec![a, b, fib_res]
         .into_iter()
-        .map(Val::from_canonical_u32)
+        .map(Val::<SC>::from_canonical_u32)
         .collect_vec();
 
     let air = FibonacciSelectorAir::new(sels.clone(), true);
-    let trace = generate_trace_rows::<Val>(a, b, &sels);
+    let trace = generate_trace_rows::<Val<SC>>(a, b, &sels);
 
     let mut curr_a = a;
     let mut curr_b = b;
     let mut vals = vec![];
     for sel in sels {
-        vals.push(Val::from_bool(sel));
+        vals.push(Val::<SC>::from_bool(sel));
         if sel {
             let c = curr_a + curr_b;
             curr_a = curr_b;
             curr_b = c;
         }
-        vals.push(Val::from_canonical_u32(curr_b));
+        vals.push(Val::<SC>::from_canonical_u32(curr_b));
     }
     let sender_trace = RowMajorMatrix::new(vals, 2);
     let sender_air = DummyInteractionAir::new(1, true, 0);
-    verify_interactions(
+    verify_interactions::<SC, E>(
         vec![trace, sender_trace],
         any_rap_arc_vec![air, sender_air],
         vec![pis, vec![]],
@@ -58,13 +81,23 @@ fn test_interaction_fib_selector_happy_path() {
 
 #[test]
 fn test_interaction_stark_multi_rows_happy_path() {
+    stark_multi_rows_happy_path::<BabyBearPoseidon2Config, BabyBearPoseidon2Engine>();
+    stark_multi_rows_happy_path::<KoalaBearPoseidon2Config, KoalaBearPoseidon2Engine>();
+}
+
+fn stark_multi_rows_happy_path<SC, E>()
+where
+    SC: StarkGenericConfig,
+    E: StarkFriEngine<SC>,
+    Val<SC>: PrimeField32,
+{
     // Mul  Val
     //   0    1
     //   7    4
     //   3    5
     // 546  889
     let sender_trace =
-        RowMajorMatrix::new(to_field_vec::<Val>(vec![0, 1, 3, 5, 7, 4, 546, 889]), 2);
+        RowMajorMatrix::new(to_field_vec::<Val<SC>>(vec![0, 1, 3, 5, 7, 4, 546, 889]), 2);
     let sender_air = DummyInteractionAir::new(1, true, 0);
 
     // Mul  Val
@@ -83,7 +116,7 @@ fn test_interaction_stark_multi_rows_happy_path() {
         2,
     );
     let receiver_air = DummyInteractionAir::new(1, false, 0);
-    verify_interactions(
+    verify_interactions::<SC, E>(
         vec![sender_trace, receiver_trace],
         any_rap_arc_vec![sender_air, receiver_air],
         vec![vec![], vec![]],
@@ -93,6 +126,16 @@ fn test_interaction_stark_multi_rows_happy_path() {
 
 #[test]
 fn test_interaction_stark_multi_rows_neg() {
+    stark_multi_rows_neg::<BabyBearPoseidon2Config, BabyBearPoseidon2Engine>();
+    stark_multi_rows_neg::<KoalaBearPoseidon2Config, KoalaBearPoseidon2Engine>();
+}
+
+fn stark_multi_rows_neg<SC, E>()
+where
+    SC: StarkGenericConfig,
+    E: StarkFriEngine<SC>,
+    Val<SC>: PrimeField32,
+{
     // Mul  Val
     //   0    1
     //   3    5
@@ -116,16 +159,89 @@ fn test_interaction_stark_multi_rows_neg() {
         2,
     );
     let receiver_air = DummyInteractionAir::new(1, false, 0);
-    let res = verify_interactions(
+    let res = verify_interactions::<SC, E>(
         vec![sender_trace, receiver_trace],
         any_rap_arc_vec![sender_air, receiver_air],
         vec![vec![], vec![]],
     );
-    assert_eq!(res, Err(VerificationError::ChallengePhaseError));
+    assert!(matches!(
+        res,
+        Err(VerificationError::LogUpSumMismatch { error, report })
+            if error == FriLogUpError::NonZeroCumulativeSum.to_string()
+                && report.unbalanced_buses.is_empty()
+    ));
+}
+
+#[test]
+fn test_debug_interactions_reports_unbalanced_bus() {
+    // Same traces as `test_interaction_stark_multi_rows_neg` on bus 0: the value 0 is sent with
+    // count 546 on row 3 and received with count 545 on row 5.
+    let sender_trace = RowMajorMatrix::new(to_field_vec(vec![0, 1, 3, 5, 7, 4, 546, 0]), 2);
+    let receiver_trace = RowMajorMatrix::new(
+        to_field_vec(vec![1, 5, 3, 4, 4, 4, 2, 5, 0, 123, 545, 0, 0, 0, 0, 456]),
+        2,
+    );
+    // A balanced lookup on bus 1.
+    let balanced_trace = RowMajorMatrix::new(to_field_vec(vec![2, 7, 1, 8]), 2);
+    let airs = any_rap_arc_vec![
+        DummyInteractionAir::new(1, true, 0),
+        DummyInteractionAir::new(1, false, 0),
+        DummyInteractionAir::new(1, true, 1),
+        DummyInteractionAir::new(1, false, 1)
+    ];
+    let traces = vec![
+        sender_trace,
+        receiver_trace,
+        balanced_trace.clone(),
+        balanced_trace,
+    ];
+
+    let mut bus_registry = BusRegistry::default();
+    bus_registry.register("fib_values", 1);
+    let report =
+        debug_interactions::<BabyBearPoseidon2Config>(&airs, &bus_registry, &traces).unwrap_err();
+    assert_eq!(
+        report.imbalances,
+        vec![BusImbalance {
+            bus_index: 0,
+            net_per_air: vec![
+                (0, BabyBear::from_canonical_u32(556)),
+                (1, -BabyBear::from_canonical_u32(555)),
+            ],
+            unbalanced_sends: vec![RowInteraction {
+                air_idx: 0,
+                row: 3,
+                fields: vec![BabyBear::ZERO],
+                count: BabyBear::from_canonical_u32(546),
+            }],
+            unbalanced_receives: vec![RowInteraction {
+                air_idx: 1,
+                row: 5,
+                fields: vec![BabyBear::ZERO],
+                count: BabyBear::from_canonical_u32(545),
+            }],
+        }]
+    );
+    let message = report.to_string();
+    assert!(message.contains("Bus 0 (fib_values) failed to balance"));
+    assert!(!message.contains("Bus 1"));
+
+    debug_interactions::<BabyBearPoseidon2Config>(&airs[2..], &bus_registry, &traces[2..])
+        .expect("Bus 1 is balanced");
 }
 
 #[test]
 fn test_interaction_stark_all_0_sender_happy_path() {
+    stark_all_0_sender_happy_path::<BabyBearPoseidon2Config, BabyBearPoseidon2Engine>();
+    stark_all_0_sender_happy_path::<KoalaBearPoseidon2Config, KoalaBearPoseidon2Engine>();
+}
+
+fn stark_all_0_sender_happy_path<SC, E>()
+where
+    SC: StarkGenericConfig,
+    E: StarkFriEngine<SC>,
+    Val<SC>: PrimeField32,
+{
     // Mul  Val
     //   0    1
     //   0  646
@@ -133,7 +249,7 @@ fn test_interaction_stark_all_0_sender_happy_path() {
     //   0  589
     let sender_trace = RowMajorMatrix::new(to_field_vec(vec![0, 1, 0, 5, 0, 4, 0, 889]), 2);
     let sender_air = DummyInteractionAir::new(1, true, 0);
-    verify_interactions(
+    verify_interactions::<SC, E>(
         vec![sender_trace],
         any_rap_arc_vec![sender_air],
         vec![vec![]],
@@ -143,6 +259,16 @@ fn test_interaction_stark_all_0_sender_happy_path() {
 
 #[test]
 fn test_interaction_stark_multi_senders_happy_path() {
+    stark_multi_senders_happy_path::<BabyBearPoseidon2Config, BabyBearPoseidon2Engine>();
+    stark_multi_senders_happy_path::<KoalaBearPoseidon2Config, KoalaBearPoseidon2Engine>();
+}
+
+fn stark_multi_senders_happy_path<SC, E>()
+where
+    SC: StarkGenericConfig,
+    E: StarkFriEngine<SC>,
+    Val<SC>: PrimeField32,
+{
     // Mul  Val
     //   0    1
     //   6    4
@@ -172,7 +298,7 @@ fn test_interaction_stark_multi_senders_happy_path() {
         2,
     );
     let receiver_air = DummyInteractionAir::new(1, false, 0);
-    verify_interactions(
+    verify_interactions::<SC, E>(
         vec![sender_trace1, sender_trace2, receiver_trace],
         any_rap_arc_vec![sender_air, sender_air, receiver_air],
         vec![vec![]; 3],
@@ -182,6 +308,16 @@ fn test_interaction_stark_multi_senders_happy_path() {
 
 #[test]
 fn test_interaction_stark_multi_senders_neg() {
+    stark_multi_senders_neg::<BabyBearPoseidon2Config, BabyBearPoseidon2Engine>();
+    stark_multi_senders_neg::<KoalaBearPoseidon2Config, KoalaBearPoseidon2Engine>();
+}
+
+fn stark_multi_senders_neg<SC, E>()
+where
+    SC: StarkGenericConfig,
+    E: StarkFriEngine<SC>,
+    Val<SC>: PrimeField32,
+{
     // Mul  Val
     //   0    1
     //   5    4
@@ -211,16 +347,31 @@ fn test_interaction_stark_multi_senders_neg() {
         2,
     );
     let receiver_air = DummyInteractionAir::new(1, false, 0);
-    let res = verify_interactions(
+    let res = verify_interactions::<SC, E>(
         vec![sender_trace1, sender_trace2, receiver_trace],
         any_rap_arc_vec![sender_air, sender_air, receiver_air],
         vec![vec![]; 3],
     );
-    assert_eq!(res, Err(VerificationError::ChallengePhaseError));
+    assert!(matches!(
+        res,
+        Err(VerificationError::LogUpSumMismatch { error, report })
+            if error == FriLogUpError::NonZeroCumulativeSum.to_string()
+                && report.unbalanced_buses.is_empty()
+    ));
 }
 
 #[test]
 fn test_interaction_stark_multi_sender_receiver_happy_path() {
+    stark_multi_sender_receiver_happy_path::<BabyBearPoseidon2Config, BabyBearPoseidon2Engine>();
+    stark_multi_sender_receiver_happy_path::<KoalaBearPoseidon2Config, KoalaBearPoseidon2Engine>();
+}
+
+fn stark_multi_sender_receiver_happy_path<SC, E>()
+where
+    SC: StarkGenericConfig,
+    E: StarkFriEngine<SC>,
+    Val<SC>: PrimeField32,
+{
     // Mul  Val
     //   0    1
     //   6    4
@@ -254,7 +405,7 @@ fn test_interaction_stark_multi_sender_receiver_happy_path() {
     //   1  889
     let receiver_trace2 = RowMajorMatrix::new(to_field_vec(vec![1, 889]), 2);
     let receiver_air = DummyInteractionAir::new(1, false, 0);
-    verify_interactions(
+    verify_interactions::<SC, E>(
         vec![
             sender_trace1,
             sender_trace2,
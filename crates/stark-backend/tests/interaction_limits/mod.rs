@@ -0,0 +1,86 @@
+use std::sync::Arc;
+
+use openvm_stark_backend::keygen::{
+    types::{InteractionStats, MultiStarkProvingKey},
+    InteractionLimits, KeygenError,
+};
+use openvm_stark_sdk::{
+    config::baby_bear_poseidon2::{default_engine, BabyBearPoseidon2Config},
+    dummy_airs::interaction::dummy_interaction_air::DummyInteractionAir,
+    engine::StarkEngine,
+};
+
+/// Keygen of a sender of messages with 3 fields on bus 0 and 1 field on bus 1, and the receivers
+/// of both buses.
+fn keygen(
+    interaction_limits: InteractionLimits,
+) -> Result<MultiStarkProvingKey<BabyBearPoseidon2Config>, KeygenError> {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.set_interaction_limits(interaction_limits);
+    keygen_builder.add_air(Arc::new(DummyInteractionAir::new(3, true, 0)));
+    keygen_builder.add_air(Arc::new(DummyInteractionAir::new(3, false, 0)));
+    keygen_builder.add_air(Arc::new(DummyInteractionAir::new(1, true, 1)));
+    keygen_builder.add_air(Arc::new(DummyInteractionAir::new(1, false, 1)));
+    keygen_builder.try_generate_pk()
+}
+
+#[test]
+fn test_interaction_limits_within() {
+    let pk = keygen(InteractionLimits {
+        max_interactions_per_air: Some(1),
+        max_total_interactions: Some(4),
+        max_fields_per_message: Some(3),
+    })
+    .unwrap();
+    assert_eq!(
+        pk.get_vk().interaction_stats(),
+        InteractionStats {
+            num_interactions_per_air: vec![1, 1, 1, 1],
+            total_num_interactions: 4,
+            max_num_fields: 3,
+        }
+    );
+}
+
+#[test]
+fn test_interaction_limits_exceeded() {
+    assert_eq!(
+        keygen(InteractionLimits {
+            max_interactions_per_air: Some(0),
+            ..Default::default()
+        })
+        .err(),
+        Some(KeygenError::TooManyInteractionsPerAir {
+            air_id: 0,
+            air_name: "DummyInteractionAir".to_string(),
+            num_interactions: 1,
+            max_interactions: 0,
+        })
+    );
+    assert_eq!(
+        keygen(InteractionLimits {
+            max_total_interactions: Some(3),
+            ..Default::default()
+        })
+        .err(),
+        Some(KeygenError::TooManyInteractions {
+            num_interactions: 4,
+            max_interactions: 3,
+        })
+    );
+    assert_eq!(
+        keygen(InteractionLimits {
+            max_fields_per_message: Some(2),
+            ..Default::default()
+        })
+        .err(),
+        Some(KeygenError::TooManyMessageFields {
+            air_id: 0,
+            air_name: "DummyInteractionAir".to_string(),
+            bus_index: 0,
+            num_fields: 3,
+            max_fields: 2,
+        })
+    );
+}
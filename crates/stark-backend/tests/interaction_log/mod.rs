@@ -0,0 +1,89 @@
+use std::sync::Arc;
+
+use openvm_stark_backend::{
+    interaction::{
+        log::{start_interaction_log, take_interaction_log, InteractionLog, LoggedInteraction},
+        InteractionType,
+    },
+    p3_field::FieldAlgebra,
+    AirRef,
+};
+use openvm_stark_sdk::{
+    config::baby_bear_poseidon2::{BabyBearPoseidon2Config, BabyBearPoseidon2Engine},
+    dummy_airs::interaction::dummy_interaction_air::DummyInteractionAir,
+    engine::StarkFriEngine,
+};
+use p3_baby_bear::BabyBear;
+use p3_matrix::dense::RowMajorMatrix;
+
+use crate::utils::to_field_vec;
+
+type SC = BabyBearPoseidon2Config;
+type Val = BabyBear;
+
+/// Proves a sender and a receiver of the same messages with 2 fields on bus 0, and returns the
+/// log of their messages.
+fn prove_with_log(max_entries_per_bus: usize) -> InteractionLog<Val> {
+    let airs: Vec<AirRef<SC>> = vec![
+        Arc::new(DummyInteractionAir::new(2, true, 0)),
+        Arc::new(DummyInteractionAir::new(2, false, 0)),
+    ];
+    // Rows of `count, field_0, field_1`.
+    let trace = RowMajorMatrix::new(to_field_vec(vec![1, 2, 3, 0, 5, 6, 3, 7, 8, 1, 9, 10]), 3);
+    start_interaction_log::<Val>(max_entries_per_bus);
+    BabyBearPoseidon2Engine::run_simple_test_no_pis_fast(airs, vec![trace.clone(), trace])
+        .expect("Verification failed");
+    take_interaction_log().unwrap()
+}
+
+fn message(
+    interaction_type: InteractionType,
+    row: usize,
+    count: u32,
+    fields: [u32; 2],
+) -> LoggedInteraction<Val> {
+    LoggedInteraction {
+        air_name: "DummyInteractionAir".to_string(),
+        row,
+        bus_index: 0,
+        interaction_type,
+        count: Val::from_canonical_u32(count),
+        fields: to_field_vec(fields.to_vec()),
+    }
+}
+
+#[test]
+fn test_interaction_log_messages() {
+    let log = prove_with_log(100);
+    // The row with a zero count is not logged.
+    let expected = [InteractionType::Send, InteractionType::Receive].map(|interaction_type| {
+        [
+            message(interaction_type, 0, 1, [2, 3]),
+            message(interaction_type, 2, 3, [7, 8]),
+            message(interaction_type, 3, 1, [9, 10]),
+        ]
+    });
+    assert_eq!(log.messages(0), expected.concat());
+    assert!(log.messages(1).is_empty());
+    assert_eq!(log.num_dropped(0), 0);
+
+    assert_eq!(
+        log.with_field(0, 0, Val::from_canonical_u32(7)),
+        vec![&expected[0][1], &expected[1][1]]
+    );
+    assert_eq!(
+        log.find(0, |entry| entry.interaction_type
+            == InteractionType::Receive
+            && entry.count == Val::ONE)
+            .len(),
+        2
+    );
+}
+
+#[test]
+fn test_interaction_log_cap_per_bus() {
+    let log = prove_with_log(4);
+    assert_eq!(log.messages(0).len(), 4);
+    assert_eq!(log.num_dropped(0), 2);
+    assert!(take_interaction_log::<Val>().is_none());
+}
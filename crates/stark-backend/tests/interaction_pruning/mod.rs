@@ -0,0 +1,98 @@
+use std::sync::Arc;
+
+use openvm_stark_backend::{
+    config::Val,
+    engine::StarkEngine,
+    interaction::{prune::PrunedInteractions, InteractionBuilder},
+    keygen::types::MultiStarkProvingKey,
+    p3_field::{Field, FieldAlgebra},
+    prover::types::{AirProofInput, ProofInput},
+    rap::{BaseAirWithPublicValues, PartitionedBaseAir},
+};
+use openvm_stark_sdk::{
+    config::baby_bear_poseidon2::{default_engine, BabyBearPoseidon2Config},
+    dummy_airs::interaction::dummy_interaction_air::DummyInteractionAir,
+};
+use p3_air::{Air, BaseAir};
+use p3_matrix::{dense::RowMajorMatrix, Matrix};
+
+type SC = BabyBearPoseidon2Config;
+
+/// Sends `x` with multiplicity `count` on bus 0, and `x^2` on bus 1 behind a feature flag that is
+/// statically off.
+///
+/// Main columns: `count, x`.
+struct FeatureFlaggedAir;
+
+impl<F: Field> BaseAir<F> for FeatureFlaggedAir {
+    fn width(&self) -> usize {
+        2
+    }
+}
+impl<F: Field> BaseAirWithPublicValues<F> for FeatureFlaggedAir {}
+impl<F: Field> PartitionedBaseAir<F> for FeatureFlaggedAir {}
+
+impl<AB: InteractionBuilder> Air<AB> for FeatureFlaggedAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        let (count, x) = (local[0], local[1]);
+        builder.push_send(0, [x], count);
+        let feature_enabled = AB::Expr::ZERO;
+        builder.push_send(1, [x * x], feature_enabled * count);
+    }
+}
+
+fn keygen(prune_interactions: bool) -> MultiStarkProvingKey<SC> {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.set_prune_interactions(prune_interactions);
+    keygen_builder.add_air(Arc::new(FeatureFlaggedAir));
+    keygen_builder.add_air(Arc::new(DummyInteractionAir::new(1, false, 0)));
+    keygen_builder.generate_pk()
+}
+
+fn prove_and_verify(pk: &MultiStarkProvingKey<SC>) {
+    let engine = default_engine();
+    let trace = RowMajorMatrix::new(
+        [1, 10, 0, 20, 3, 30, 1, 40]
+            .map(Val::<SC>::from_canonical_u32)
+            .to_vec(),
+        2,
+    );
+    let proof = engine.prove(
+        pk,
+        ProofInput::new(vec![
+            (0, AirProofInput::simple_no_pis(trace.clone())),
+            (1, AirProofInput::simple_no_pis(trace)),
+        ]),
+    );
+    engine
+        .verify(&pk.get_vk(), &proof)
+        .expect("Verification failed");
+}
+
+#[test]
+fn test_prune_zero_count_interaction() {
+    let pk = keygen(true);
+    let unpruned_pk = keygen(false);
+    prove_and_verify(&pk);
+    prove_and_verify(&unpruned_pk);
+
+    assert_eq!(
+        pk.per_air[0].pruned_interactions,
+        PrunedInteractions {
+            num_zero_count: 1,
+            num_duplicates: 0,
+        }
+    );
+    assert_eq!(pk.per_air[0].vk.symbolic_constraints.interactions.len(), 1);
+    assert_eq!(unpruned_pk.per_air[0].pruned_interactions.total(), 0);
+    // The interaction on bus 1 has degree 2 fields, so it cannot share a log-up column with the
+    // interaction on bus 0.
+    assert_eq!(pk.per_air[0].vk.params.width.after_challenge, vec![2]);
+    assert_eq!(
+        unpruned_pk.per_air[0].vk.params.width.after_challenge,
+        vec![3]
+    );
+}
@@ -0,0 +1,158 @@
+use std::sync::Arc;
+
+use openvm_stark_backend::keygen::{
+    consistency::check_pk_vk_consistency,
+    types::{MatrixOrdering, MultiStarkProvingKey},
+    AirKeyMismatch, AirKeyMismatchKind, KeyMismatch, VkField,
+};
+use openvm_stark_sdk::{
+    config::baby_bear_poseidon2::{default_engine, BabyBearPoseidon2Config},
+    dummy_airs::{
+        fib_air::air::FibonacciAir, interaction::dummy_interaction_air::DummyInteractionAir,
+    },
+    engine::StarkEngine,
+};
+
+use crate::{fib_selector_air::air::FibonacciSelectorAir, vk_digest::SquareAir};
+
+type SC = BabyBearPoseidon2Config;
+
+/// The AIRs of a key, and how they may differ between two keygens.
+#[derive(Clone)]
+struct Airs {
+    square_offset: u32,
+    field_width: usize,
+    bus_index: usize,
+    selector_period: usize,
+    matrix_ordering: MatrixOrdering,
+}
+
+impl Default for Airs {
+    fn default() -> Self {
+        Self {
+            square_offset: 1,
+            field_width: 2,
+            bus_index: 0,
+            selector_period: 2,
+            matrix_ordering: MatrixOrdering::Natural,
+        }
+    }
+}
+
+fn keygen(airs: Airs) -> MultiStarkProvingKey<SC> {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.set_matrix_ordering(airs.matrix_ordering);
+    let sels = (0..8).map(|i| i % airs.selector_period == 0).collect();
+    keygen_builder.add_air(Arc::new(FibonacciAir));
+    keygen_builder.add_air(Arc::new(SquareAir {
+        offset: airs.square_offset,
+    }));
+    keygen_builder.add_air(Arc::new(DummyInteractionAir::new(
+        airs.field_width,
+        true,
+        airs.bus_index,
+    )));
+    keygen_builder.add_air(Arc::new(DummyInteractionAir::new(
+        airs.field_width,
+        false,
+        airs.bus_index,
+    )));
+    keygen_builder.add_air(Arc::new(FibonacciSelectorAir::new(sels, false)));
+    keygen_builder.generate_pk()
+}
+
+/// Checks the proving key of the default AIRs against the verifying key of `airs`.
+fn check(airs: Airs) -> Result<(), KeyMismatch> {
+    check_pk_vk_consistency(&keygen(Airs::default()), &keygen(airs).get_vk())
+}
+
+fn mismatch(air_id: usize, air_name: &str, kind: AirKeyMismatchKind) -> AirKeyMismatch {
+    AirKeyMismatch {
+        air_id,
+        air_name: air_name.to_string(),
+        kind,
+    }
+}
+
+#[test]
+fn test_key_consistency() {
+    let pk = keygen(Airs::default());
+    assert_eq!(check_pk_vk_consistency(&pk, &pk.get_vk()), Ok(()));
+    assert_eq!(check(Airs::default()), Ok(()));
+}
+
+#[test]
+fn test_key_consistency_mismatch() {
+    let constraint = check(Airs {
+        square_offset: 2,
+        ..Default::default()
+    });
+    assert_eq!(
+        constraint,
+        Err(KeyMismatch::Airs(vec![mismatch(
+            1,
+            "SquareAir",
+            AirKeyMismatchKind::Fingerprint
+        )]))
+    );
+    assert!(constraint
+        .unwrap_err()
+        .to_string()
+        .contains("SquareAir (air_id = 1): different fingerprints"));
+
+    assert_eq!(
+        check(Airs {
+            field_width: 3,
+            ..Default::default()
+        }),
+        Err(KeyMismatch::Airs(vec![
+            mismatch(2, "DummyInteractionAir", AirKeyMismatchKind::Widths),
+            mismatch(3, "DummyInteractionAir", AirKeyMismatchKind::Widths),
+        ]))
+    );
+    assert_eq!(
+        check(Airs {
+            bus_index: 1,
+            ..Default::default()
+        }),
+        Err(KeyMismatch::Airs(vec![
+            mismatch(2, "DummyInteractionAir", AirKeyMismatchKind::Interactions),
+            mismatch(3, "DummyInteractionAir", AirKeyMismatchKind::Interactions),
+        ]))
+    );
+    assert_eq!(
+        check(Airs {
+            selector_period: 4,
+            ..Default::default()
+        }),
+        Err(KeyMismatch::Airs(vec![mismatch(
+            4,
+            "FibonacciSelectorAir",
+            AirKeyMismatchKind::PreprocessedCommitment
+        )]))
+    );
+
+    let ordering = check(Airs {
+        matrix_ordering: MatrixOrdering::HeightDescending,
+        ..Default::default()
+    });
+    assert_eq!(
+        ordering,
+        Err(KeyMismatch::Fields(vec![
+            VkField::MatrixOrdering,
+            VkField::Features
+        ]))
+    );
+    assert_eq!(
+        ordering.unwrap_err().to_string(),
+        "the proving and verifying keys differ on [matrix ordering, features]"
+    );
+
+    let mut vk = keygen(Airs::default()).get_vk();
+    vk.per_air.pop();
+    assert_eq!(
+        check_pk_vk_consistency(&keygen(Airs::default()), &vk),
+        Err(KeyMismatch::NumAirs { pk: 5, vk: 4 })
+    );
+}
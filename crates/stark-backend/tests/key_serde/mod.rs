@@ -0,0 +1,201 @@
+use std::{env, fs, path::Path, sync::Arc};
+
+use openvm_stark_backend::{
+    keygen::types::{MultiStarkProvingKey, MultiStarkVerifyingKey},
+    p3_field::FieldAlgebra,
+    prover::types::{AirProofInput, ProofInput},
+};
+use openvm_stark_sdk::{
+    config::{
+        baby_bear_blake3::BabyBearBlake3Config,
+        baby_bear_keccak::BabyBearKeccakConfig,
+        baby_bear_poseidon2::{
+            default_engine, BabyBearPoseidon2Config, BabyBearPoseidon2GkrConfig,
+            BabyBearPoseidon2ZkConfig,
+        },
+        baby_bear_poseidon2_root::BabyBearPoseidon2RootConfig,
+        goldilocks_poseidon::GoldilocksPoseidonConfig,
+        koala_bear_poseidon2::KoalaBearPoseidon2Config,
+    },
+    dummy_airs::{
+        fib_air::{air::FibonacciAir, trace::generate_trace_rows},
+        interaction::dummy_interaction_air::DummyInteractionAir,
+    },
+    engine::StarkEngine,
+    key_serde::{config_name, KeyHeader, KeyKind, KeySerde, KeySerdeError, KEY_FORMAT_VERSION},
+};
+use p3_baby_bear::BabyBear;
+
+use crate::{fib_selector_air::air::FibonacciSelectorAir, get_fib_number};
+
+type SC = BabyBearPoseidon2Config;
+type Val = BabyBear;
+
+/// When set, [test_key_serde_fixtures] writes the fixtures that do not exist yet.
+const WRITE_KEY_FIXTURES_VAR: &str = "WRITE_KEY_FIXTURES";
+
+/// Proving key of a system with a plain AIR, an AIR with interactions and selectors, and
+/// interaction-only AIRs on both sides of a bus. The Fibonacci AIR has id 0.
+fn multi_air_pk() -> MultiStarkProvingKey<SC> {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    let sels = (0..8).map(|i| i % 2 == 0).collect();
+    keygen_builder.add_air(Arc::new(FibonacciAir));
+    keygen_builder.add_air(Arc::new(FibonacciSelectorAir::new(sels, true)));
+    keygen_builder.add_air(Arc::new(DummyInteractionAir::new(1, true, 0)));
+    keygen_builder.add_air(Arc::new(DummyInteractionAir::new(3, false, 1)));
+    keygen_builder.generate_pk()
+}
+
+fn header_len() -> usize {
+    KeyHeader {
+        version: KEY_FORMAT_VERSION,
+        kind: KeyKind::Verifying,
+        config: config_name::<SC>().to_string(),
+    }
+    .to_bytes()
+    .len()
+}
+
+#[test]
+fn test_key_serde_round_trip() {
+    let pk = multi_air_pk();
+    let vk = pk.get_vk();
+    let pk_bytes = pk.to_bytes();
+    let vk_bytes = vk.to_bytes();
+    let new_pk = MultiStarkProvingKey::<SC>::from_bytes(&pk_bytes).unwrap();
+    let new_vk = MultiStarkVerifyingKey::<SC>::from_bytes(&vk_bytes).unwrap();
+    assert_eq!(new_pk.to_bytes(), pk_bytes);
+    assert_eq!(new_vk.to_bytes(), vk_bytes);
+
+    let n = 16;
+    let pis = [0, 1, get_fib_number(n)]
+        .map(Val::from_canonical_u32)
+        .to_vec();
+    let trace = generate_trace_rows::<Val>(0, 1, n);
+    let engine = default_engine();
+    let proof = engine.prove(
+        &new_pk,
+        ProofInput::new(vec![(0, AirProofInput::simple(trace, pis))]),
+    );
+    engine.verify(&new_vk, &proof).expect("Verification failed");
+}
+
+#[test]
+fn test_key_serde_header_fixture() {
+    // Changing the header of an existing format version breaks serialized keys. Bump
+    // `KEY_FORMAT_VERSION` instead of updating this fixture.
+    let fixture = include_bytes!("../fixtures/key_header_v1.bin");
+    let header = KeyHeader {
+        version: 1,
+        kind: KeyKind::Verifying,
+        config: "test_config".to_string(),
+    };
+    assert_eq!(header.to_bytes(), fixture);
+    assert_eq!(KeyHeader::read(&fixture[..]), Ok((header, &[][..])));
+}
+
+/// The serialized keys of [multi_air_pk] are the fixtures of the current format version, so that
+/// any change of the encoding of the keys is caught, not only of their header. Changing the
+/// encoding breaks serialized keys: bump `KEY_FORMAT_VERSION` and add fixtures instead of updating
+/// these.
+#[test]
+fn test_key_serde_fixtures() {
+    let pk = multi_air_pk();
+    let vk = pk.get_vk();
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let check = |name: String, bytes: Vec<u8>| {
+        let path = dir.join(&name);
+        if env::var(WRITE_KEY_FIXTURES_VAR).is_ok() && !path.exists() {
+            fs::write(&path, &bytes).unwrap();
+        }
+        let fixture = fs::read(&path).unwrap_or_else(|err| {
+            panic!("failed to read fixture {name}: {err}; set {WRITE_KEY_FIXTURES_VAR} to write it")
+        });
+        assert!(fixture == bytes, "{name} differs from the serialized key");
+        fixture
+    };
+    let pk_fixture = check(
+        format!("multi_air_pk_v{KEY_FORMAT_VERSION}.bin"),
+        pk.to_bytes(),
+    );
+    let vk_fixture = check(
+        format!("multi_air_vk_v{KEY_FORMAT_VERSION}.bin"),
+        vk.to_bytes(),
+    );
+    let pk = MultiStarkProvingKey::<SC>::from_bytes(&pk_fixture).unwrap();
+    let vk = MultiStarkVerifyingKey::<SC>::from_bytes(&vk_fixture).unwrap();
+    assert_eq!(pk.get_vk().to_bytes(), vk_fixture);
+    assert_eq!(vk.to_bytes(), vk_fixture);
+}
+
+/// The config ids recorded in serialized keys and proofs. Changing one rejects every key and proof
+/// serialized with it.
+#[test]
+fn test_key_serde_config_ids() {
+    let ids = [
+        config_name::<BabyBearPoseidon2Config>(),
+        config_name::<BabyBearPoseidon2GkrConfig>(),
+        config_name::<BabyBearPoseidon2ZkConfig>(),
+        config_name::<BabyBearPoseidon2RootConfig>(),
+        config_name::<BabyBearBlake3Config>(),
+        config_name::<BabyBearKeccakConfig>(),
+        config_name::<GoldilocksPoseidonConfig>(),
+        config_name::<KoalaBearPoseidon2Config>(),
+    ];
+    assert_eq!(
+        ids,
+        [
+            "baby_bear_poseidon2",
+            "baby_bear_poseidon2_gkr",
+            "baby_bear_poseidon2_zk",
+            "baby_bear_poseidon2_root",
+            "baby_bear_blake3",
+            "baby_bear_keccak",
+            "goldilocks_poseidon",
+            "koala_bear_poseidon2",
+        ]
+    );
+}
+
+#[test]
+fn test_key_serde_rejected() {
+    let vk = multi_air_pk().get_vk();
+    let bytes = vk.to_bytes();
+    let decode = |bytes: &[u8]| MultiStarkVerifyingKey::<SC>::from_bytes(bytes).err();
+
+    let mut wrong_magic = bytes.clone();
+    wrong_magic[..8].copy_from_slice(b"NOTAKEY!");
+    assert_eq!(decode(&wrong_magic), Some(KeySerdeError::WrongMagic));
+    // The bincode encoding of the key without the header.
+    assert_eq!(
+        decode(&bytes[header_len()..]),
+        Some(KeySerdeError::Unversioned)
+    );
+
+    let mut next_version = bytes.clone();
+    next_version[8..12].copy_from_slice(&(KEY_FORMAT_VERSION + 1).to_le_bytes());
+    assert_eq!(
+        decode(&next_version),
+        Some(KeySerdeError::UnsupportedVersion(KEY_FORMAT_VERSION + 1))
+    );
+    assert_eq!(
+        MultiStarkProvingKey::<SC>::from_bytes(&bytes).err(),
+        Some(KeySerdeError::WrongKind {
+            expected: KeyKind::Proving,
+            found: KeyKind::Verifying,
+        })
+    );
+    assert_eq!(
+        MultiStarkVerifyingKey::<BabyBearPoseidon2GkrConfig>::from_bytes(&bytes).err(),
+        Some(KeySerdeError::ConfigMismatch {
+            expected: config_name::<BabyBearPoseidon2GkrConfig>().to_string(),
+            found: config_name::<SC>().to_string(),
+        })
+    );
+    assert_eq!(
+        decode(&bytes[..bytes.len() - 1]).map(|err| matches!(err, KeySerdeError::Payload(_))),
+        Some(true)
+    );
+    assert_eq!(decode(&bytes[..10]), Some(KeySerdeError::UnexpectedEnd));
+}
@@ -0,0 +1,267 @@
+//! Deliberately malformed AIRs, each rejected by keygen with a [KeygenError].
+
+use std::sync::Arc;
+
+use openvm_stark_backend::{
+    keygen::{
+        types::{AllowedValues, PreprocessedShape},
+        KeygenError,
+    },
+    p3_field::Field,
+    rap::{BaseAirWithPublicValues, PartitionedBaseAir},
+};
+use openvm_stark_sdk::{config::baby_bear_poseidon2::default_engine, engine::StarkEngine};
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_matrix::{dense::RowMajorMatrix, Matrix};
+
+const AIR_NAME: &str = "MalformedAir";
+
+/// Constrains the first main column to be boolean. Every declaration can be overridden.
+///
+/// By default, the main trace has width 1 and there is nothing else.
+#[derive(Clone)]
+struct MalformedAir {
+    width: usize,
+    cached_main_widths: Vec<usize>,
+    common_main_width: usize,
+    preprocessed_height: Option<usize>,
+    preprocessed_shape: Option<PreprocessedShape>,
+    num_public_values: usize,
+    configuration_public_values: Vec<(usize, AllowedValues)>,
+    periods: Vec<usize>,
+}
+
+impl Default for MalformedAir {
+    fn default() -> Self {
+        Self {
+            width: 1,
+            cached_main_widths: vec![],
+            common_main_width: 1,
+            preprocessed_height: None,
+            preprocessed_shape: None,
+            num_public_values: 0,
+            configuration_public_values: vec![],
+            periods: vec![],
+        }
+    }
+}
+
+impl<F: Field> BaseAir<F> for MalformedAir {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn preprocessed_trace(&self) -> Option<RowMajorMatrix<F>> {
+        self.preprocessed_height
+            .map(|height| RowMajorMatrix::new_col(vec![F::ZERO; height]))
+    }
+}
+
+impl<F: Field> BaseAirWithPublicValues<F> for MalformedAir {
+    fn num_public_values(&self) -> usize {
+        self.num_public_values
+    }
+
+    fn configuration_public_values(&self) -> Vec<(usize, AllowedValues)> {
+        self.configuration_public_values.clone()
+    }
+}
+
+impl<F: Field> PartitionedBaseAir<F> for MalformedAir {
+    fn cached_main_widths(&self) -> Vec<usize> {
+        self.cached_main_widths.clone()
+    }
+
+    fn common_main_width(&self) -> usize {
+        self.common_main_width
+    }
+
+    fn periodic_columns(&self) -> Vec<Vec<F>> {
+        self.periods
+            .iter()
+            .map(|&period| vec![F::ONE; period])
+            .collect()
+    }
+
+    fn preprocessed_shape(&self) -> Option<PreprocessedShape> {
+        self.preprocessed_shape.clone()
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for MalformedAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let x = main.row_slice(0)[0];
+        builder.assert_bool(x);
+    }
+}
+
+/// Runs keygen for a well-formed AIR followed by `air`, which has AIR id 1.
+fn keygen(air: MalformedAir) -> Result<(), KeygenError> {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(Arc::new(MalformedAir::default()));
+    keygen_builder.add_air(Arc::new(air));
+    keygen_builder.try_generate_pk().map(|_| ())
+}
+
+#[test]
+fn test_keygen_well_formed() {
+    assert_eq!(keygen(MalformedAir::default()), Ok(()));
+}
+
+#[test]
+fn test_keygen_main_width_mismatch() {
+    let air = MalformedAir {
+        width: 3,
+        cached_main_widths: vec![1],
+        ..Default::default()
+    };
+    assert_eq!(
+        keygen(air),
+        Err(KeygenError::MainWidthMismatch {
+            air_id: 1,
+            air_name: AIR_NAME.to_string(),
+            width: 3,
+            cached_main_widths: vec![1],
+            common_main_width: 1,
+        })
+    );
+}
+
+#[test]
+fn test_keygen_symbolic_width_mismatch() {
+    // Not partitioned: the constraints are evaluated on the common main trace only, which is
+    // narrower than the declared width.
+    let air = MalformedAir {
+        width: 2,
+        ..Default::default()
+    };
+    assert_eq!(
+        keygen(air),
+        Err(KeygenError::MainWidthMismatch {
+            air_id: 1,
+            air_name: AIR_NAME.to_string(),
+            width: 2,
+            cached_main_widths: vec![],
+            common_main_width: 1,
+        })
+    );
+}
+
+#[test]
+fn test_keygen_preprocessed_height_not_power_of_two() {
+    let air = MalformedAir {
+        preprocessed_height: Some(6),
+        ..Default::default()
+    };
+    assert_eq!(
+        keygen(air),
+        Err(KeygenError::PreprocessedHeightNotPowerOfTwo {
+            air_id: 1,
+            air_name: AIR_NAME.to_string(),
+            height: 6,
+        })
+    );
+}
+
+#[test]
+fn test_keygen_preprocessed_shape_errors() {
+    let shape = PreprocessedShape {
+        width: 1,
+        log_heights: vec![3],
+    };
+    let air = MalformedAir {
+        preprocessed_height: Some(8),
+        preprocessed_shape: Some(shape),
+        ..Default::default()
+    };
+    assert_eq!(
+        keygen(air),
+        Err(KeygenError::PreprocessedShapeWithTrace {
+            air_id: 1,
+            air_name: AIR_NAME.to_string(),
+        })
+    );
+
+    let air = MalformedAir {
+        preprocessed_shape: Some(PreprocessedShape {
+            width: 1,
+            log_heights: vec![],
+        }),
+        ..Default::default()
+    };
+    assert_eq!(
+        keygen(air),
+        Err(KeygenError::EmptyPreprocessedShape {
+            air_id: 1,
+            air_name: AIR_NAME.to_string(),
+        })
+    );
+}
+
+#[test]
+fn test_keygen_configuration_public_value_errors() {
+    let air = MalformedAir {
+        num_public_values: 2,
+        configuration_public_values: vec![(2, AllowedValues::Set(vec![1]))],
+        ..Default::default()
+    };
+    assert_eq!(
+        keygen(air),
+        Err(KeygenError::ConfigurationPublicValueOutOfBounds {
+            air_id: 1,
+            air_name: AIR_NAME.to_string(),
+            index: 2,
+            num_public_values: 2,
+        })
+    );
+
+    let air = MalformedAir {
+        num_public_values: 2,
+        configuration_public_values: vec![
+            (1, AllowedValues::Set(vec![1])),
+            (1, AllowedValues::Range { start: 0, end: 3 }),
+        ],
+        ..Default::default()
+    };
+    assert_eq!(
+        keygen(air),
+        Err(KeygenError::DuplicateConfigurationPublicValue {
+            air_id: 1,
+            air_name: AIR_NAME.to_string(),
+            index: 1,
+        })
+    );
+
+    let air = MalformedAir {
+        num_public_values: 2,
+        configuration_public_values: vec![(0, AllowedValues::Set(vec![]))],
+        ..Default::default()
+    };
+    assert_eq!(
+        keygen(air),
+        Err(KeygenError::NoAllowedValues {
+            air_id: 1,
+            air_name: AIR_NAME.to_string(),
+            index: 0,
+        })
+    );
+}
+
+#[test]
+fn test_keygen_period_not_power_of_two() {
+    let air = MalformedAir {
+        periods: vec![4, 3],
+        ..Default::default()
+    };
+    assert_eq!(
+        keygen(air),
+        Err(KeygenError::PeriodNotPowerOfTwo {
+            air_id: 1,
+            air_name: AIR_NAME.to_string(),
+            column: 1,
+            period: 3,
+        })
+    );
+}
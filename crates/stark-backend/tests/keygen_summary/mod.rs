@@ -0,0 +1,106 @@
+use std::sync::Arc;
+
+use openvm_stark_backend::keygen::{
+    summary::{AirSummary, KeySummary},
+    types::MultiStarkProvingKey,
+};
+use openvm_stark_sdk::{
+    config::baby_bear_poseidon2::{default_engine, BabyBearPoseidon2Config},
+    dummy_airs::{
+        fib_air::air::FibonacciAir, interaction::dummy_interaction_air::DummyInteractionAir,
+    },
+    engine::StarkEngine,
+};
+
+type SC = BabyBearPoseidon2Config;
+
+fn keygen() -> MultiStarkProvingKey<SC> {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(Arc::new(FibonacciAir));
+    keygen_builder.add_named_air("send", Arc::new(DummyInteractionAir::new(1, true, 0)));
+    keygen_builder.add_named_air(
+        "receive",
+        Arc::new(DummyInteractionAir::new(1, false, 0).partition()),
+    );
+    keygen_builder.generate_pk()
+}
+
+#[test]
+fn test_keygen_summary() {
+    // Changes to keygen that alter these numbers change the cost of every proof: update them
+    // deliberately.
+    let interaction_air = |air_name: &str, cached_main_widths: Vec<usize>, common_main_width| {
+        AirSummary {
+            air_name: air_name.to_string(),
+            preprocessed_width: 0,
+            cached_main_widths,
+            common_main_width,
+            // Log-up with a single chunk: one extension column for the chunk and one for the
+            // running sum.
+            after_challenge_width: 8,
+            // The chunk constraint and the running sum constraints on the first row, the
+            // transitions and the last row.
+            num_constraints: 4,
+            max_constraint_degree: 2,
+            quotient_degree: 1,
+            num_interactions: 1,
+            num_interaction_fields: 1,
+        }
+    };
+    let expected = KeySummary {
+        per_air: vec![
+            AirSummary {
+                air_name: "FibonacciAir".to_string(),
+                preprocessed_width: 0,
+                cached_main_widths: vec![],
+                common_main_width: 2,
+                after_challenge_width: 0,
+                num_constraints: 5,
+                max_constraint_degree: 2,
+                quotient_degree: 1,
+                num_interactions: 0,
+                num_interaction_fields: 0,
+            },
+            interaction_air("send", vec![], 2),
+            interaction_air("receive", vec![1], 1),
+        ],
+    };
+    let pk = keygen();
+    let summary = pk.summary();
+    assert_eq!(summary, expected);
+    assert_eq!(pk.get_vk().summary(), summary);
+
+    assert_eq!(
+        summary.totals(),
+        AirSummary {
+            air_name: "Total".to_string(),
+            preprocessed_width: 0,
+            cached_main_widths: vec![1],
+            common_main_width: 5,
+            after_challenge_width: 16,
+            num_constraints: 13,
+            max_constraint_degree: 2,
+            quotient_degree: 1,
+            num_interactions: 2,
+            num_interaction_fields: 2,
+        }
+    );
+}
+
+#[test]
+fn test_keygen_summary_display() {
+    let table = keygen().summary().to_string();
+    let lines = table.lines().collect::<Vec<_>>();
+    // Header, separator, one row per AIR, separator and totals.
+    assert_eq!(lines.len(), 7);
+    assert!(lines[0].starts_with("| AIR "));
+    assert!(lines[2].starts_with("| FibonacciAir |"));
+    assert!(lines[3].starts_with("| send "));
+    assert!(lines[4].starts_with("| receive "));
+    assert!(lines[6].starts_with("| Total "));
+    assert!(lines[6].ends_with(" 2 |"));
+    assert!(lines
+        .iter()
+        .all(|line| line.len() == lines[0].len() && line.starts_with('|')));
+}
@@ -0,0 +1,152 @@
+use std::sync::Arc;
+
+use openvm_stark_backend::{
+    engine::StarkEngine,
+    interaction::{fri_log_up::FriLogUpParams, InteractionBuilder, InteractionType},
+    keygen::{types::MultiStarkProvingKey, KeygenError},
+    p3_field::Field,
+    prover::types::{AirProofInput, ProofInput},
+    rap::{BaseAirWithPublicValues, PartitionedBaseAir},
+    verifier::VerificationError,
+};
+use openvm_stark_sdk::config::{
+    baby_bear_poseidon2::{
+        config_from_perm_with_log_up_params, default_perm, BabyBearPoseidon2Config,
+        BabyBearPoseidon2Engine,
+    },
+    FriParameters,
+};
+use p3_air::{Air, BaseAir};
+use p3_matrix::{dense::RowMajorMatrix, Matrix};
+
+type SC = BabyBearPoseidon2Config;
+
+const NUM_BUSES: usize = 8;
+const HEIGHT: usize = 16;
+
+/// Sends or receives the value `v_i` with multiplicity `count` on bus `i`, for each of the
+/// `NUM_BUSES` buses.
+///
+/// Main columns: `count, [v; NUM_BUSES]`.
+struct MultiBusAir {
+    is_send: bool,
+}
+
+impl<F: Field> BaseAir<F> for MultiBusAir {
+    fn width(&self) -> usize {
+        1 + NUM_BUSES
+    }
+}
+impl<F: Field> BaseAirWithPublicValues<F> for MultiBusAir {}
+impl<F: Field> PartitionedBaseAir<F> for MultiBusAir {}
+
+impl<AB: InteractionBuilder> Air<AB> for MultiBusAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        let interaction_type = if self.is_send {
+            InteractionType::Send
+        } else {
+            InteractionType::Receive
+        };
+        for bus in 0..NUM_BUSES {
+            builder.push_interaction(bus, [local[1 + bus]], local[0], interaction_type);
+        }
+    }
+}
+
+fn generate_trace<F: Field>() -> RowMajorMatrix<F> {
+    let values = (0..HEIGHT)
+        .flat_map(|row| {
+            std::iter::once(row % 3).chain((0..NUM_BUSES).map(move |bus| row * NUM_BUSES + bus))
+        })
+        .map(F::from_canonical_usize)
+        .collect();
+    RowMajorMatrix::new(values, 1 + NUM_BUSES)
+}
+
+/// Engine whose FRI log-up phase batches `batch_size` interactions per column.
+fn engine(log_blowup: usize, batch_size: usize) -> BabyBearPoseidon2Engine {
+    let perm = default_perm();
+    let fri_params = FriParameters::standard_with_100_bits_conjectured_security(log_blowup);
+    let log_up_params = FriLogUpParams {
+        batch_size: Some(batch_size),
+        ..Default::default()
+    };
+    BabyBearPoseidon2Engine {
+        config: config_from_perm_with_log_up_params(&perm, fri_params, log_up_params),
+        perm,
+        fri_params,
+        max_constraint_degree: fri_params.max_constraint_degree(),
+        min_security_bits: None,
+    }
+}
+
+fn keygen(engine: &BabyBearPoseidon2Engine) -> Result<MultiStarkProvingKey<SC>, KeygenError> {
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(Arc::new(MultiBusAir { is_send: true }));
+    keygen_builder.add_air(Arc::new(MultiBusAir { is_send: false }));
+    keygen_builder.try_generate_pk()
+}
+
+fn proof_input() -> ProofInput<SC> {
+    ProofInput::new(vec![
+        (0, AirProofInput::simple_no_pis(generate_trace())),
+        (1, AirProofInput::simple_no_pis(generate_trace())),
+    ])
+}
+
+#[test]
+fn test_log_up_batch_sizes() {
+    let after_challenge_widths = [1, 2, 4].map(|batch_size| {
+        let engine = engine(3, batch_size);
+        let pk = keygen(&engine).unwrap();
+        let proof = engine.prove(&pk, proof_input());
+        engine
+            .verify(&pk.get_vk(), &proof)
+            .expect("Verification failed");
+        for pk in &pk.per_air {
+            assert_eq!(pk.vk.log_up_params.batch_size, Some(batch_size));
+        }
+        pk.per_air[0].vk.params.width.after_challenge[0]
+    });
+    // One column per batch of interactions, plus the running sum.
+    assert_eq!(after_challenge_widths, [9, 5, 3]);
+}
+
+#[test]
+fn test_log_up_batch_too_large() {
+    // Four interactions of degree 1 in a batch give log-up constraints of degree 5.
+    assert_eq!(
+        keygen(&engine(1, 4)).err(),
+        Some(KeygenError::InteractionBatchTooLarge {
+            air_id: 0,
+            air_name: "MultiBusAir".to_string(),
+            batch_size: 4,
+            degree: 5,
+            max_degree: 3,
+        })
+    );
+}
+
+#[test]
+fn test_log_up_batch_size_mismatch() {
+    let pk = keygen(&engine(3, 2)).unwrap();
+    let engine = engine(3, 1);
+    let proof = engine.prove(&keygen(&engine).unwrap(), proof_input());
+    assert_eq!(
+        engine.verify(&pk.get_vk(), &proof),
+        Err(VerificationError::LogUpParamsMismatch {
+            air_id: 0,
+            air_name: "MultiBusAir".to_string(),
+            expected: FriLogUpParams {
+                batch_size: Some(1),
+                ..Default::default()
+            },
+            found: FriLogUpParams {
+                batch_size: Some(2),
+                ..Default::default()
+            },
+        })
+    );
+}
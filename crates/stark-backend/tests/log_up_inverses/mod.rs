@@ -0,0 +1,137 @@
+use std::sync::Arc;
+
+use openvm_stark_backend::{
+    engine::StarkEngine,
+    interaction::{fri_log_up::FriLogUpParams, InteractionBuilder},
+    keygen::{types::MultiStarkProvingKey, KeygenError},
+    p3_field::Field,
+    prover::types::{AirProofInput, ProofInput},
+    rap::{BaseAirWithPublicValues, PartitionedBaseAir},
+};
+use openvm_stark_sdk::config::{
+    baby_bear_poseidon2::{
+        config_from_perm_with_log_up_params, default_perm, BabyBearPoseidon2Config,
+        BabyBearPoseidon2Engine,
+    },
+    FriParameters,
+};
+use p3_air::{Air, BaseAir};
+use p3_matrix::{dense::RowMajorMatrix, Matrix};
+
+type SC = BabyBearPoseidon2Config;
+
+const NUM_BUSES: usize = 6;
+const HEIGHT: usize = 16;
+
+/// Sends the value `v_i` with multiplicity `send_count` and receives it with multiplicity
+/// `receive_count` on bus `i`, for each of the `NUM_BUSES` buses.
+///
+/// Main columns: `send_count, receive_count, [v; NUM_BUSES]`.
+struct LookupAir;
+
+impl<F: Field> BaseAir<F> for LookupAir {
+    fn width(&self) -> usize {
+        2 + NUM_BUSES
+    }
+}
+impl<F: Field> BaseAirWithPublicValues<F> for LookupAir {}
+impl<F: Field> PartitionedBaseAir<F> for LookupAir {}
+
+impl<AB: InteractionBuilder> Air<AB> for LookupAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        for bus in 0..NUM_BUSES {
+            builder.push_send(bus, [local[2 + bus]], local[0]);
+            builder.push_receive(bus, [local[2 + bus]], local[1]);
+        }
+    }
+}
+
+fn generate_trace<F: Field>() -> RowMajorMatrix<F> {
+    let values = (0..HEIGHT)
+        .flat_map(|row| {
+            [row % 3, row % 3]
+                .into_iter()
+                .chain((0..NUM_BUSES).map(move |bus| row * NUM_BUSES + bus))
+        })
+        .map(F::from_canonical_usize)
+        .collect();
+    RowMajorMatrix::new(values, 2 + NUM_BUSES)
+}
+
+/// Engine with constraints of degree at most 2, so that the FRI log-up phase cannot batch
+/// interactions.
+fn engine(log_up_params: FriLogUpParams) -> BabyBearPoseidon2Engine {
+    let perm = default_perm();
+    let fri_params = FriParameters::standard_with_100_bits_conjectured_security(1);
+    BabyBearPoseidon2Engine {
+        config: config_from_perm_with_log_up_params(&perm, fri_params, log_up_params),
+        perm,
+        fri_params,
+        max_constraint_degree: 2,
+        min_security_bits: None,
+    }
+}
+
+fn prove_and_verify(engine: &BabyBearPoseidon2Engine) -> MultiStarkProvingKey<SC> {
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(Arc::new(LookupAir));
+    let pk = keygen_builder.generate_pk();
+    let proof = engine.prove(
+        &pk,
+        ProofInput::new(vec![(0, AirProofInput::simple_no_pis(generate_trace()))]),
+    );
+    engine
+        .verify(&pk.get_vk(), &proof)
+        .expect("Verification failed");
+    pk
+}
+
+#[test]
+fn test_log_up_committed_inverses() {
+    let batched_pk = prove_and_verify(&engine(FriLogUpParams::default()));
+    // One column per interaction, plus the running sum.
+    assert_eq!(
+        batched_pk.per_air[0].vk.params.width.after_challenge,
+        vec![2 * NUM_BUSES + 1]
+    );
+    assert_eq!(batched_pk.per_air[0].vk.quotient_degree, 1);
+
+    for num_repetitions in [1, 2] {
+        let pk = prove_and_verify(&engine(FriLogUpParams {
+            num_repetitions,
+            commit_inverses: true,
+            ..Default::default()
+        }));
+        let vk = &pk.per_air[0].vk;
+        // The send and the receive of each bus share their inverse column.
+        assert_eq!(
+            vk.params.width.after_challenge,
+            vec![(NUM_BUSES + 1) * num_repetitions]
+        );
+        assert_eq!(vk.quotient_degree, 1);
+        assert!(vk.log_up_params.commit_inverses);
+    }
+}
+
+#[test]
+fn test_log_up_cannot_batch() {
+    // Two interactions of degree 1 in a batch give log-up constraints of degree 3.
+    let engine = engine(FriLogUpParams {
+        batch_size: Some(2),
+        ..Default::default()
+    });
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(Arc::new(LookupAir));
+    assert_eq!(
+        keygen_builder.try_generate_pk().err(),
+        Some(KeygenError::InteractionBatchTooLarge {
+            air_id: 0,
+            air_name: "LookupAir".to_string(),
+            batch_size: 2,
+            degree: 3,
+            max_degree: 2,
+        })
+    );
+}
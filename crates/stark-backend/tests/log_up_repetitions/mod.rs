@@ -0,0 +1,120 @@
+use std::sync::Arc;
+
+use openvm_stark_backend::{
+    config::StarkGenericConfig,
+    engine::StarkEngine,
+    interaction::fri_log_up::FriLogUpParams,
+    keygen::types::MultiStarkProvingKey,
+    p3_field::FieldAlgebra,
+    prover::types::{AirProofInput, ProofInput},
+    verifier::VerificationError,
+};
+use openvm_stark_sdk::{
+    config::{
+        baby_bear_poseidon2::{
+            config_from_perm_with_log_up_params, default_perm, BabyBearPoseidon2Config,
+            BabyBearPoseidon2Engine,
+        },
+        FriParameters,
+    },
+    dummy_airs::interaction::dummy_interaction_air::DummyInteractionAir,
+    security::{total_soundness, WorkloadBounds},
+};
+use p3_matrix::dense::RowMajorMatrix;
+
+use crate::utils::to_field_vec;
+
+type SC = BabyBearPoseidon2Config;
+type Challenge = <SC as StarkGenericConfig>::Challenge;
+
+/// Engine whose FRI log-up phase repeats the argument `num_repetitions` times.
+fn engine(num_repetitions: usize) -> BabyBearPoseidon2Engine {
+    let perm = default_perm();
+    let fri_params = FriParameters::standard_with_100_bits_conjectured_security(3);
+    let log_up_params = FriLogUpParams {
+        num_repetitions,
+        ..Default::default()
+    };
+    BabyBearPoseidon2Engine {
+        config: config_from_perm_with_log_up_params(&perm, fri_params, log_up_params),
+        perm,
+        fri_params,
+        max_constraint_degree: fri_params.max_constraint_degree(),
+        min_security_bits: None,
+    }
+}
+
+/// Keygen of a sender and a receiver of messages with 2 fields.
+fn keygen(engine: &BabyBearPoseidon2Engine) -> MultiStarkProvingKey<SC> {
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(Arc::new(DummyInteractionAir::new(2, true, 0)));
+    keygen_builder.add_air(Arc::new(DummyInteractionAir::new(2, false, 0)));
+    keygen_builder.generate_pk()
+}
+
+fn proof_input() -> ProofInput<SC> {
+    let trace = RowMajorMatrix::new(to_field_vec(vec![1, 2, 3, 4, 5, 6, 0, 7, 8, 2, 9, 10]), 3);
+    ProofInput::new(vec![
+        (0, AirProofInput::simple_no_pis(trace.clone())),
+        (1, AirProofInput::simple_no_pis(trace)),
+    ])
+}
+
+#[test]
+fn test_log_up_repetitions() {
+    let [(width_1, logup_1), (width_2, logup_2)] = [1, 2].map(|num_repetitions| {
+        let engine = engine(num_repetitions);
+        let pk = keygen(&engine);
+        let vk = pk.get_vk();
+        let proof = engine.prove(&pk, proof_input());
+        engine.verify(&vk, &proof).expect("Verification failed");
+
+        for (vk, air_proof) in vk.per_air.iter().zip(&proof.core.per_air) {
+            assert_eq!(vk.log_up_params.num_repetitions, num_repetitions);
+            assert_eq!(
+                vk.params.num_challenges_to_sample,
+                vec![2 * num_repetitions]
+            );
+            assert_eq!(
+                air_proof.exposed_values_after_challenge[0].len(),
+                num_repetitions
+            );
+        }
+        let bounds = WorkloadBounds::from_max_log_height(&vk, 20);
+        let logup = total_soundness(&vk, engine.fri_params, &bounds).logup;
+        (vk.per_air[0].params.width.after_challenge[0], logup)
+    });
+    // Each repetition has its own log-up column and running sum.
+    assert_eq!(width_1, 2);
+    assert_eq!(width_2, 4);
+    assert!((logup_2 - 2.0 * logup_1).abs() < 1e-9);
+}
+
+#[test]
+fn test_log_up_repetitions_wrong_cumulative_sum() {
+    let engine = engine(2);
+    let pk = keygen(&engine);
+    let mut proof = engine.prove(&pk, proof_input());
+    // Only the cumulative sum of the second repetition is wrong.
+    proof.core.per_air[0].exposed_values_after_challenge[0][1] += Challenge::ONE;
+    assert!(engine.verify(&pk.get_vk(), &proof).is_err());
+}
+
+#[test]
+fn test_log_up_repetitions_mismatch() {
+    let pk = keygen(&engine(2));
+    let engine = engine(1);
+    let proof = engine.prove(&keygen(&engine), proof_input());
+    assert_eq!(
+        engine.verify(&pk.get_vk(), &proof),
+        Err(VerificationError::LogUpParamsMismatch {
+            air_id: 0,
+            air_name: "DummyInteractionAir".to_string(),
+            expected: FriLogUpParams::default(),
+            found: FriLogUpParams {
+                num_repetitions: 2,
+                ..Default::default()
+            },
+        })
+    );
+}
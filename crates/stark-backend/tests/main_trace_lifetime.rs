@@ -0,0 +1,75 @@
+//! The raw main traces are dropped once the after challenge phases no longer need them. The test
+//! installs the tracking allocator and reads the process-wide memory counts, so it is in its own
+//! binary to not count the allocations of other tests.
+#![cfg(feature = "mem-metrics")]
+
+use std::{
+    mem::size_of,
+    sync::{Arc, Mutex},
+};
+
+use openvm_stark_backend::{
+    p3_field::FieldAlgebra,
+    prover::{
+        memory::{MemoryProfile, TrackingAllocator},
+        types::{AirProofInput, ProofInput},
+        ProvingOptions, ProvingPhase,
+    },
+};
+use openvm_stark_sdk::{
+    config::baby_bear_poseidon2::default_engine,
+    dummy_airs::fib_air::{air::FibonacciAir, trace::generate_trace_rows},
+    engine::StarkEngine,
+};
+use p3_baby_bear::BabyBear;
+
+#[global_allocator]
+static GLOBAL: TrackingAllocator = TrackingAllocator;
+
+const LOG_HEIGHT: usize = 16;
+
+#[test]
+fn test_main_trace_dropped_before_quotient() {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    let air_id = keygen_builder.add_air(Arc::new(FibonacciAir));
+    let pk = keygen_builder.generate_pk();
+
+    let trace = generate_trace_rows::<BabyBear>(0, 1, 1 << LOG_HEIGHT);
+    let trace_bytes = trace.values.len() * size_of::<BabyBear>();
+    let last = trace.values[trace.values.len() - 1];
+    let pis = vec![BabyBear::ZERO, BabyBear::ONE, last];
+    let input = || {
+        ProofInput::new(vec![(
+            air_id,
+            AirProofInput::simple(trace.clone(), pis.clone()),
+        )])
+    };
+
+    let memory_profile = Arc::new(Mutex::new(MemoryProfile::default()));
+    let options = ProvingOptions {
+        memory_profile: Some(memory_profile.clone()),
+        ..Default::default()
+    };
+    let proof = engine.prove_with_options(&pk, input(), options).unwrap();
+    engine
+        .verify(&pk.get_vk(), &proof)
+        .expect("Verification failed");
+    let profile = memory_profile.lock().unwrap();
+
+    // The Fibonacci AIR has no interactions, so the after challenge phases keep almost nothing,
+    // and the memory live at the start of the quotient phase is lower by about the size of the
+    // main trace.
+    let live_at_start = |phase| profile.get(phase).unwrap().live_at_start as f64;
+    let freed =
+        live_at_start(ProvingPhase::AfterChallengeTrace) - live_at_start(ProvingPhase::Quotient);
+    let ratio = freed / trace_bytes as f64;
+    assert!((0.9..1.1).contains(&ratio), "{profile}freed = {freed}");
+
+    // Dropping the traces earlier does not change the proof.
+    let unprofiled = engine.prove(&pk, input());
+    assert_eq!(
+        bincode::serialize(&proof).unwrap(),
+        bincode::serialize(&unprofiled).unwrap()
+    );
+}
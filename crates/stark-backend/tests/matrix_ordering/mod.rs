@@ -0,0 +1,89 @@
+use std::sync::Arc;
+
+use openvm_stark_backend::{
+    keygen::types::{MatrixOrdering, MultiStarkVerifyingKey},
+    p3_field::FieldAlgebra,
+    proof::Proof,
+    prover::types::{AirProofInput, ProofInput},
+};
+use openvm_stark_sdk::{
+    config::baby_bear_poseidon2::{default_engine, BabyBearPoseidon2Config},
+    dummy_airs::fib_air::{air::FibonacciAir, trace::generate_trace_rows},
+    engine::StarkEngine,
+};
+use p3_baby_bear::BabyBear;
+
+use crate::get_fib_number;
+
+type SC = BabyBearPoseidon2Config;
+type Val = BabyBear;
+
+/// Proves two Fibonacci AIRs, where the second trace is taller than the first so that the
+/// height descending ordering differs from the natural ordering.
+fn prove_fibs(matrix_ordering: MatrixOrdering) -> (MultiStarkVerifyingKey<SC>, Proof<SC>) {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.set_matrix_ordering(matrix_ordering);
+    let air_ids = [
+        keygen_builder.add_air(Arc::new(FibonacciAir)),
+        keygen_builder.add_air(Arc::new(FibonacciAir)),
+    ];
+    let pk = keygen_builder.generate_pk();
+    let vk = pk.get_vk();
+
+    let per_air = air_ids
+        .into_iter()
+        .zip([8, 32])
+        .map(|(air_id, n)| {
+            let pis = [0, 1, get_fib_number(n)]
+                .map(Val::from_canonical_u32)
+                .to_vec();
+            let trace = generate_trace_rows::<Val>(0, 1, n);
+            (air_id, AirProofInput::simple(trace, pis))
+        })
+        .collect();
+    let proof = engine.prove(&pk, ProofInput::new(per_air));
+    (vk, proof)
+}
+
+#[test]
+fn test_height_descending_commit_order() {
+    let dims = [(8, 2), (32, 2), (8, 5), (32, 1)];
+    let ordering = MatrixOrdering::HeightDescending;
+    assert_eq!(ordering.commit_order(&dims), vec![1, 3, 2, 0]);
+    assert_eq!(ordering.commit_positions(&dims), vec![3, 0, 2, 1]);
+    assert_eq!(
+        MatrixOrdering::Natural.commit_order(&dims),
+        vec![0, 1, 2, 3]
+    );
+}
+
+#[test]
+fn test_matrix_ordering_verifies() {
+    let engine = default_engine();
+    for matrix_ordering in [MatrixOrdering::Natural, MatrixOrdering::HeightDescending] {
+        let (vk, proof) = prove_fibs(matrix_ordering);
+        assert_eq!(vk.matrix_ordering, matrix_ordering);
+        engine.verify(&vk, &proof).expect("Verification failed");
+    }
+}
+
+#[test]
+fn test_matrix_ordering_cross_verification_fails() {
+    let engine = default_engine();
+    let (natural_vk, natural_proof) = prove_fibs(MatrixOrdering::Natural);
+    let (reordered_vk, reordered_proof) = prove_fibs(MatrixOrdering::HeightDescending);
+    assert!(engine.verify(&natural_vk, &reordered_proof).is_err());
+    assert!(engine.verify(&reordered_vk, &natural_proof).is_err());
+}
+
+#[test]
+fn test_matrix_ordering_keeps_proof_size() {
+    // Reordering the matrices of a round moves the opened values, but does not add or remove any.
+    let (_, natural_proof) = prove_fibs(MatrixOrdering::Natural);
+    let (_, reordered_proof) = prove_fibs(MatrixOrdering::HeightDescending);
+    assert_eq!(
+        bincode::serialized_size(&natural_proof).unwrap(),
+        bincode::serialized_size(&reordered_proof).unwrap()
+    );
+}
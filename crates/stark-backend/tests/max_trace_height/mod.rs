@@ -0,0 +1,107 @@
+use std::sync::Arc;
+
+use openvm_stark_backend::{
+    keygen::{
+        types::{MultiStarkProvingKey, VkFeature},
+        TraceHeightTooLargeError,
+    },
+    p3_field::FieldAlgebra,
+    prover::types::{AirProofInput, ProofInput},
+    verifier::VerificationError,
+};
+use openvm_stark_sdk::{
+    config::baby_bear_poseidon2::{default_engine, BabyBearPoseidon2Config},
+    dummy_airs::fib_air::{air::FibonacciAir, trace::generate_trace_rows},
+    engine::StarkEngine,
+};
+use p3_baby_bear::BabyBear;
+
+use crate::get_fib_number;
+
+type SC = BabyBearPoseidon2Config;
+type Val = BabyBear;
+
+const MAX_LOG_HEIGHT: usize = 4;
+
+fn keygen(max_log_height: Option<usize>) -> MultiStarkProvingKey<SC> {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    let air_id = keygen_builder.add_air(Arc::new(FibonacciAir));
+    if let Some(max_log_height) = max_log_height {
+        keygen_builder.set_max_log_height(air_id, max_log_height);
+    }
+    keygen_builder.generate_pk()
+}
+
+fn proof_input(log_height: usize) -> ProofInput<SC> {
+    let n = 1 << log_height;
+    let pis = [0, 1, get_fib_number(n)]
+        .map(Val::from_canonical_u32)
+        .to_vec();
+    let trace = generate_trace_rows::<Val>(0, 1, n);
+    ProofInput::new(vec![(0, AirProofInput::simple(trace, pis))])
+}
+
+#[test]
+fn test_max_trace_height() {
+    let engine = default_engine();
+    let pk = keygen(Some(MAX_LOG_HEIGHT));
+    let vk = pk.get_vk();
+    assert_eq!(vk.per_air[0].params.max_log_height, Some(MAX_LOG_HEIGHT));
+    assert!(vk.features.contains(VkFeature::MaxTraceHeight));
+
+    let proof = engine.try_prove(&pk, proof_input(MAX_LOG_HEIGHT)).unwrap();
+    engine.verify(&vk, &proof).expect("Verification failed");
+
+    let too_large = || TraceHeightTooLargeError {
+        air_id: 0,
+        air_name: "FibonacciAir".to_string(),
+        height: 1 << (MAX_LOG_HEIGHT + 1),
+        max_log_height: MAX_LOG_HEIGHT,
+    };
+    assert_eq!(
+        engine.try_prove(&pk, proof_input(MAX_LOG_HEIGHT + 1)).err(),
+        Some(too_large())
+    );
+
+    // A valid proof with a taller trace, from a key without the bound.
+    let unbounded_pk = keygen(None);
+    let tall_proof = engine.prove(&unbounded_pk, proof_input(MAX_LOG_HEIGHT + 1));
+    engine
+        .verify(&unbounded_pk.get_vk(), &tall_proof)
+        .expect("Verification failed");
+    assert_eq!(
+        engine.verify(&vk, &tall_proof),
+        Err(VerificationError::TraceHeightTooLarge(too_large()))
+    );
+}
+
+#[test]
+fn test_max_trace_height_defaults_to_two_adicity() {
+    let mut vk = keygen(None).get_vk();
+    let air_vk = &vk.per_air[0];
+    assert_eq!(air_vk.params.max_log_height, None);
+    assert_eq!(air_vk.max_log_height(), 27);
+    assert!(air_vk.check_trace_height(0, 1 << 27).is_ok());
+    assert_eq!(
+        air_vk.check_trace_height(0, (1 << 27) + 1),
+        Err(TraceHeightTooLargeError {
+            air_id: 0,
+            air_name: "FibonacciAir".to_string(),
+            height: (1 << 27) + 1,
+            max_log_height: 27,
+        })
+    );
+
+    // The bound of a key read from untrusted bytes may not fit in a shift.
+    for max_log_height in [64, 1000, usize::MAX] {
+        vk.per_air[0].params.max_log_height = Some(max_log_height);
+        assert!(vk.per_air[0].check_trace_height(0, usize::MAX).is_ok());
+    }
+}
+
+#[test]
+#[should_panic(expected = "max log height 28 is above the two-adicity 27 of the field")]
+fn test_max_trace_height_above_two_adicity() {
+    keygen(Some(28));
+}
@@ -0,0 +1,150 @@
+//! The tests install the tracking allocator, so they are in their own binary. Each proof runs
+//! on its own pool, so that it does not count the allocations of the other tests.
+#![cfg(feature = "mem-metrics")]
+
+use std::{
+    mem::size_of,
+    sync::{Arc, Mutex},
+};
+
+use openvm_stark_backend::{
+    keygen::types::MultiStarkProvingKey,
+    p3_field::FieldAlgebra,
+    prover::{
+        memory::{MemoryProfile, TrackingAllocator},
+        pool::ProverConfig,
+        types::{AirProofInput, AirProofRawInput, ProofInput},
+        ProvingOptions, ProvingPhase,
+    },
+};
+use openvm_stark_sdk::{
+    config::{
+        baby_bear_poseidon2::{BabyBearPoseidon2Config, BabyBearPoseidon2Engine},
+        FriParameters,
+    },
+    dummy_airs::{
+        fib_air::{air::FibonacciAir, trace::generate_trace_rows},
+        interaction::dummy_interaction_air::DummyInteractionAir,
+    },
+    engine::{StarkEngine, StarkFriEngine},
+};
+use p3_baby_bear::BabyBear;
+use p3_matrix::dense::RowMajorMatrix;
+
+#[global_allocator]
+static GLOBAL: TrackingAllocator = TrackingAllocator;
+
+type SC = BabyBearPoseidon2Config;
+
+/// With a blowup of 8, the maximum supported quotient degree is 8.
+const LOG_BLOWUP: usize = 3;
+const LOG_HEIGHT: usize = 14;
+
+fn engine() -> BabyBearPoseidon2Engine {
+    BabyBearPoseidon2Engine::new(FriParameters::standard_with_100_bits_conjectured_security(
+        LOG_BLOWUP,
+    ))
+}
+
+/// Proves `input` on a dedicated pool and returns the memory profile of the proof.
+fn prove_on_pool(
+    engine: &BabyBearPoseidon2Engine,
+    pk: &MultiStarkProvingKey<SC>,
+    input: ProofInput<SC>,
+) -> MemoryProfile {
+    let memory_profile = Arc::new(Mutex::new(MemoryProfile::default()));
+    let options = ProvingOptions {
+        memory_profile: Some(memory_profile.clone()),
+        ..Default::default()
+    };
+    let pool = ProverConfig::default().build_pool("profiled").unwrap();
+    let proof = engine.prove_on_pool(&pool, pk, input, options).unwrap();
+    engine
+        .verify(&pk.get_vk(), &proof)
+        .expect("Verification failed");
+    Arc::into_inner(memory_profile)
+        .unwrap()
+        .into_inner()
+        .unwrap()
+}
+
+/// Proves the same Fibonacci trace with the quotient degree overridden to `quotient_degree`.
+fn prove(quotient_degree: usize) -> MemoryProfile {
+    let engine = engine();
+    let mut keygen_builder = engine.keygen_builder();
+    let air_id = keygen_builder.add_air(Arc::new(FibonacciAir));
+    keygen_builder.set_quotient_degree_override(air_id, quotient_degree);
+    let pk = keygen_builder.generate_pk();
+
+    let trace = generate_trace_rows::<BabyBear>(0, 1, 1 << LOG_HEIGHT);
+    let last = trace.values[trace.values.len() - 1];
+    let pis = vec![BabyBear::ZERO, BabyBear::ONE, last];
+    let input = ProofInput::new(vec![(air_id, AirProofInput::simple(trace, pis))]);
+    prove_on_pool(&engine, &pk, input)
+}
+
+#[test]
+fn test_quotient_peak_scales_with_quotient_degree() {
+    let profiles = [2, 4, 8].map(prove);
+    for profile in &profiles {
+        let phases = profile
+            .per_phase
+            .iter()
+            .map(|(phase, _)| *phase)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            phases,
+            [
+                ProvingPhase::MainTraceCommit,
+                ProvingPhase::AfterChallengeTrace,
+                ProvingPhase::Quotient,
+                ProvingPhase::Opening
+            ]
+        );
+        assert!(profile.get(ProvingPhase::MainTraceCommit).unwrap().peak > 0);
+    }
+
+    let peaks = profiles.map(|profile| profile.get(ProvingPhase::Quotient).unwrap().peak);
+    assert!(peaks[0] < peaks[1] && peaks[1] < peaks[2], "{peaks:?}");
+    // The quotient values and their LDE grow linearly with the quotient degree, so doubling the
+    // degree from 4 to 8 adds about twice as much memory as doubling it from 2 to 4.
+    let ratio = (peaks[2] - peaks[1]) as f64 / (peaks[1] - peaks[0]) as f64;
+    assert!((1.6..2.4).contains(&ratio), "{peaks:?}");
+}
+
+/// The main trace commit phase counts the commitment of a cached main trace, made on the threads
+/// of the pool, but not the trace itself, allocated by the test thread.
+#[test]
+fn test_cached_trace_commit_counted_on_pool() {
+    const FIELD_WIDTH: usize = 8;
+    let engine = engine();
+    let mut keygen_builder = engine.keygen_builder();
+    let air = DummyInteractionAir::new(FIELD_WIDTH, true, 0).partition();
+    let air_id = keygen_builder.add_air(Arc::new(air));
+    let pk = keygen_builder.generate_pk();
+
+    // The counts are zero, so the interactions are balanced.
+    let height = 1 << LOG_HEIGHT;
+    let fields = RowMajorMatrix::new(vec![BabyBear::ONE; FIELD_WIDTH * height], FIELD_WIDTH);
+    let fields_bytes = fields.values.len() * size_of::<BabyBear>();
+    let input = AirProofInput {
+        cached_mains_pdata: vec![],
+        raw: AirProofRawInput {
+            cached_mains: vec![Arc::new(fields)],
+            common_main: Some(RowMajorMatrix::new(vec![BabyBear::ZERO; height], 1)),
+            custom_mains: vec![],
+            public_values: vec![],
+        },
+    };
+    let profile = prove_on_pool(&engine, &pk, ProofInput::new(vec![(air_id, input)]));
+
+    let main_trace_commit = profile.get(ProvingPhase::MainTraceCommit).unwrap();
+    assert!(
+        main_trace_commit.allocated >= fields_bytes << LOG_BLOWUP,
+        "{profile}"
+    );
+    assert!(
+        main_trace_commit.live_at_start < fields_bytes as isize,
+        "{profile}"
+    );
+}
@@ -0,0 +1,91 @@
+use std::sync::Arc;
+
+use openvm_stark_backend::{
+    config::StarkGenericConfig,
+    p3_field::{FieldAlgebra, FieldExtensionAlgebra},
+    prover::types::{AirProofInput, ProofInput},
+    verifier::{ProofShapeError, VerificationError},
+};
+use openvm_stark_sdk::{
+    config::baby_bear_poseidon2::{default_engine, BabyBearPoseidon2Config},
+    dummy_airs::{
+        fib_air::{air::FibonacciAir, trace::generate_trace_rows},
+        interaction::dummy_interaction_air::DummyInteractionAir,
+    },
+    engine::StarkEngine,
+};
+use p3_baby_bear::BabyBear;
+use p3_matrix::dense::RowMajorMatrix;
+
+use crate::{get_fib_number, utils::to_field_vec};
+
+type SC = BabyBearPoseidon2Config;
+type Val = BabyBear;
+type Challenge = <SC as StarkGenericConfig>::Challenge;
+
+#[test]
+fn test_after_challenge_commitment_skips_non_interacting_airs() {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    let fib_id = keygen_builder.add_air(Arc::new(FibonacciAir));
+    let sender_id = keygen_builder.add_air(Arc::new(DummyInteractionAir::new(2, true, 0)));
+    let other_fib_id = keygen_builder.add_air(Arc::new(FibonacciAir));
+    let receiver_id = keygen_builder.add_air(Arc::new(DummyInteractionAir::new(2, false, 0)));
+    let pk = keygen_builder.generate_pk();
+    let vk = pk.get_vk();
+
+    let n = 8;
+    let pis = [0, 1, get_fib_number(n)]
+        .map(Val::from_canonical_u32)
+        .to_vec();
+    let fib_trace = generate_trace_rows::<Val>(0, 1, n);
+    let trace = RowMajorMatrix::new(to_field_vec(vec![1, 2, 3, 4, 5, 6, 0, 7, 8, 2, 9, 10]), 3);
+    let mut proof = engine.prove(
+        &pk,
+        ProofInput::new(vec![
+            (
+                fib_id,
+                AirProofInput::simple(fib_trace.clone(), pis.clone()),
+            ),
+            (sender_id, AirProofInput::simple_no_pis(trace.clone())),
+            (other_fib_id, AirProofInput::simple(fib_trace, pis)),
+            (receiver_id, AirProofInput::simple_no_pis(trace)),
+        ]),
+    );
+    engine.verify(&vk, &proof).expect("Verification failed");
+
+    // Only the sender and the receiver have an after challenge trace.
+    for air_id in [fib_id, other_fib_id] {
+        assert!(vk.per_air[air_id].params.width.after_challenge.is_empty());
+        assert!(proof.core.per_air[air_id]
+            .exposed_values_after_challenge
+            .is_empty());
+    }
+    let widths = [sender_id, receiver_id].map(|air_id| {
+        vk.per_air[air_id].params.width.after_challenge[0]
+            * <Challenge as FieldExtensionAlgebra<Val>>::D
+    });
+    assert_eq!(proof.commitments.after_challenge.len(), 1);
+    assert_eq!(proof.core.opened_values.after_challenge.len(), 1);
+    assert_eq!(
+        proof.core.opened_values.after_challenge[0]
+            .iter()
+            .map(|values| values.local.len())
+            .collect::<Vec<_>>(),
+        widths
+    );
+
+    // The verifier does not accept exposed values for an AIR without interactions.
+    proof.core.per_air[fib_id].exposed_values_after_challenge = vec![vec![Challenge::ZERO]];
+    assert_eq!(
+        engine.verify(&vk, &proof).err(),
+        Some(VerificationError::InvalidProofShape(
+            ProofShapeError::NumExposedValues {
+                air_id: fib_id,
+                air_name: "FibonacciAir".to_string(),
+                expected: vec![],
+                found: vec![1],
+            }
+        ))
+    );
+}
@@ -0,0 +1,163 @@
+use std::sync::Arc;
+
+use openvm_stark_backend::{
+    config::{StarkGenericConfig, Val},
+    engine::StarkEngine,
+    p3_field::{FieldAlgebra, FieldExtensionAlgebra, TwoAdicField},
+    proof::{AdjacentOpenedValues, Proof},
+    prover::{
+        cpu::opener::{OpeningRoundKind, OpeningWitness},
+        types::{AirProofInput, ProofInput},
+    },
+    Chip,
+};
+use openvm_stark_sdk::{
+    config::baby_bear_poseidon2::{default_engine, BabyBearPoseidon2Config},
+    dummy_airs::interaction::dummy_interaction_air::{DummyInteractionChip, DummyInteractionData},
+};
+use p3_baby_bear::BabyBear;
+
+use crate::{
+    fib_selector_air::{air::FibonacciSelectorAir, trace::generate_trace_rows},
+    get_conditional_fib_number,
+};
+
+type SC = BabyBearPoseidon2Config;
+type Challenge = <SC as StarkGenericConfig>::Challenge;
+
+const LOG_FIB_HEIGHT: usize = 3;
+const LOG_INTERACTION_HEIGHT: usize = 2;
+
+/// Proves a Fibonacci AIR with a preprocessed trace, a sender with a cached main trace and a
+/// receiver, with AIR ids 0, 1 and 2. Returns the proof and witness of
+/// [prove_with_opening_witness](StarkEngine::prove_with_opening_witness), and the proof of
+/// [prove](StarkEngine::prove).
+fn prove() -> (Proof<SC>, OpeningWitness<Challenge>, Proof<SC>) {
+    let engine = default_engine();
+    let sels = (0..1 << LOG_FIB_HEIGHT)
+        .map(|i| i % 2 == 0)
+        .collect::<Vec<_>>();
+    let fib_air = FibonacciSelectorAir::new(sels, false);
+    let fib_trace = generate_trace_rows::<BabyBear>(0, 1, fib_air.sels());
+    let fib_pis = [0, 1, get_conditional_fib_number(fib_air.sels())]
+        .map(BabyBear::from_canonical_u32)
+        .to_vec();
+    let data = DummyInteractionData {
+        count: vec![1, 2, 4],
+        fields: vec![vec![1], vec![2], vec![3]],
+    };
+    let mut send_chip = DummyInteractionChip::new_with_partition(engine.config(), 1, true, 0);
+    let mut recv_chip = DummyInteractionChip::new_without_partition(1, false, 0);
+    send_chip.load_data(data.clone());
+    recv_chip.load_data(data);
+
+    let mut keygen_builder = engine.keygen_builder();
+    let fib_air_id = keygen_builder.add_air(Arc::new(fib_air));
+    let send_air_id = keygen_builder.add_air(send_chip.air());
+    let recv_air_id = keygen_builder.add_air(recv_chip.air());
+    let pk = keygen_builder.generate_pk();
+
+    let proof_input = ProofInput::new(vec![
+        (fib_air_id, AirProofInput::simple(fib_trace, fib_pis)),
+        send_chip.generate_air_proof_input_with_id(send_air_id),
+        recv_chip.generate_air_proof_input_with_id(recv_air_id),
+    ]);
+    let (proof, witness) = engine.prove_with_opening_witness(&pk, proof_input.clone());
+    engine
+        .verify(&pk.get_vk(), &proof)
+        .expect("Verification failed");
+    (proof, witness, engine.prove(&pk, proof_input))
+}
+
+/// The values of a trace matrix at each of its opening points.
+fn trace_values(values: &AdjacentOpenedValues<Challenge>) -> Vec<Vec<Challenge>> {
+    [values.local.clone(), values.next.clone()]
+        .into_iter()
+        .chain(values.rotated.iter().cloned())
+        .collect()
+}
+
+#[test]
+fn test_opening_witness_matches_proof() {
+    let (proof, witness, plain_proof) = prove();
+    assert_eq!(
+        bincode::serialize(&proof).unwrap(),
+        bincode::serialize(&plain_proof).unwrap()
+    );
+
+    // The rounds in the order of the verifier, with the matrices of each round.
+    let opened_values = &proof.core.opened_values;
+    let expected = opened_values
+        .preprocessed
+        .iter()
+        .map(|values| (OpeningRoundKind::Preprocessed, vec![trace_values(values)]))
+        .chain(opened_values.main.iter().map(|values_per_mat| {
+            let matrices = values_per_mat.iter().map(trace_values).collect();
+            (OpeningRoundKind::Main, matrices)
+        }))
+        .chain(opened_values.custom.iter().map(|values_per_mat| {
+            let matrices = values_per_mat.iter().map(trace_values).collect();
+            (OpeningRoundKind::Custom, matrices)
+        }))
+        .chain(opened_values.after_challenge.iter().map(|values_per_mat| {
+            let matrices = values_per_mat.iter().map(trace_values).collect();
+            (OpeningRoundKind::AfterChallenge, matrices)
+        }))
+        .chain([(
+            OpeningRoundKind::Quotient,
+            opened_values
+                .quotient
+                .iter()
+                .flatten()
+                .map(|chunk| vec![chunk.clone()])
+                .collect(),
+        )])
+        .collect::<Vec<_>>();
+    let actual = witness
+        .rounds
+        .iter()
+        .map(|round| {
+            let matrices = round
+                .matrices
+                .iter()
+                .map(|matrix| matrix.values.clone())
+                .collect::<Vec<_>>();
+            (round.kind, matrices)
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(actual, expected);
+    // The preprocessed trace, the cached and common main traces, the log-up phase and the
+    // quotient.
+    assert_eq!(
+        witness
+            .rounds
+            .iter()
+            .map(|round| round.kind)
+            .collect::<Vec<_>>(),
+        [
+            OpeningRoundKind::Preprocessed,
+            OpeningRoundKind::Main,
+            OpeningRoundKind::Main,
+            OpeningRoundKind::AfterChallenge,
+            OpeningRoundKind::Quotient,
+        ]
+    );
+
+    for round in &witness.rounds {
+        for matrix in &round.matrices {
+            assert_eq!(matrix.points.len(), matrix.values.len());
+            assert_eq!(matrix.points[0], witness.zeta);
+        }
+    }
+    let next_point = |log_height: usize| {
+        witness.zeta * Challenge::from_base(Val::<SC>::two_adic_generator(log_height))
+    };
+    let preprocessed = &witness.rounds[0].matrices[0];
+    assert_eq!(preprocessed.points[1], next_point(LOG_FIB_HEIGHT));
+    let cached_main = &witness.rounds[1].matrices[0];
+    assert_eq!(cached_main.points[1], next_point(LOG_INTERACTION_HEIGHT));
+    assert!(witness.rounds[4]
+        .matrices
+        .iter()
+        .all(|chunk| chunk.points == [witness.zeta]));
+}
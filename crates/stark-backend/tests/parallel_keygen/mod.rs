@@ -0,0 +1,44 @@
+use std::sync::Arc;
+
+use openvm_stark_backend::keygen::types::MultiStarkProvingKey;
+use openvm_stark_sdk::{
+    config::baby_bear_poseidon2::{default_engine, BabyBearPoseidon2Config},
+    dummy_airs::{
+        fib_air::air::FibonacciAir, interaction::dummy_interaction_air::DummyInteractionAir,
+    },
+    engine::StarkEngine,
+    key_serde::KeySerde,
+};
+
+use crate::fib_selector_air::air::FibonacciSelectorAir;
+
+type SC = BabyBearPoseidon2Config;
+
+/// Proving key of AIRs with and without preprocessed traces and interactions, generated in
+/// parallel or not.
+fn keygen(parallel: bool) -> MultiStarkProvingKey<SC> {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.set_parallel(parallel);
+    for i in 0..4 {
+        let sels = (0..8 << i).map(|j| j % (i + 2) == 0).collect();
+        keygen_builder.add_air(Arc::new(FibonacciAir));
+        keygen_builder.add_air(Arc::new(FibonacciSelectorAir::new(sels, i % 2 == 0)));
+        keygen_builder.add_air(Arc::new(DummyInteractionAir::new(i + 1, true, i)));
+        keygen_builder.add_air(Arc::new(
+            DummyInteractionAir::new(i + 1, false, i).partition(),
+        ));
+    }
+    keygen_builder.generate_pk()
+}
+
+#[test]
+fn test_parallel_keygen_matches_serial() {
+    let parallel_pk = keygen(true);
+    let serial_pk = keygen(false);
+    assert_eq!(parallel_pk.to_bytes(), serial_pk.to_bytes());
+    assert_eq!(
+        parallel_pk.get_vk().to_bytes(),
+        serial_pk.get_vk().to_bytes()
+    );
+}
@@ -52,6 +52,7 @@ fn prove_and_verify_sum_air(x: Vec<Val>, ys: Vec<Vec<Val>>) -> Result<(), Verifi
         raw: AirProofRawInput {
             cached_mains: vec![y_trace],
             common_main: Some(x_trace),
+            custom_mains: vec![],
             public_values: vec![],
         },
     };
@@ -91,6 +92,9 @@ fn test_partitioned_sum_air_happy_neg() {
     disable_debug_builder();
     assert_eq!(
         prove_and_verify_sum_air(x, ys),
-        Err(VerificationError::OodEvaluationMismatch)
+        Err(VerificationError::OodEvaluationMismatch {
+            air_id: 0,
+            air_name: "SumAir".to_string(),
+        })
     );
 }
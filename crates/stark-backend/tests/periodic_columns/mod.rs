@@ -0,0 +1,237 @@
+use std::sync::Arc;
+
+use itertools::Itertools;
+use openvm_stark_backend::{
+    air_builders::PeriodicAirBuilder,
+    engine::StarkEngine,
+    keygen::types::{MultiStarkProvingKey, VkFeature},
+    p3_field::{Field, FieldAlgebra},
+    proof::Proof,
+    prover::types::{AirProofInput, ProofInput},
+    rap::{BaseAirWithPublicValues, PartitionedBaseAir},
+    utils::disable_debug_builder,
+    verifier::VerificationError,
+};
+use openvm_stark_sdk::{
+    any_rap_arc_vec,
+    config::baby_bear_poseidon2::{
+        default_engine, BabyBearPoseidon2Config, BabyBearPoseidon2Engine,
+    },
+    engine::StarkFriEngine,
+};
+use p3_air::{Air, AirBuilder, BaseAir, PairBuilder};
+use p3_baby_bear::BabyBear;
+use p3_matrix::{dense::RowMajorMatrix, Matrix};
+
+type SC = BabyBearPoseidon2Config;
+type Val = BabyBear;
+
+const WIDTH: usize = 2;
+const NUM_ROUNDS: usize = 8;
+/// Matrix of the linear layer.
+const MATRIX: [[u32; WIDTH]; WIDTH] = [[2, 1], [1, 2]];
+
+/// Rounds of a small Poseidon2-like permutation of a state of `WIDTH` elements. Each block of
+/// `NUM_ROUNDS` rows computes one permutation: row `r` holds the state before round `r`, which
+/// adds the round constants of round `r`, applies the cube S-box and then the linear layer. The
+/// last row of a block holds the output, and is followed by the input of the next block.
+///
+/// The round selector and the round constants only depend on the row modulo `NUM_ROUNDS`. They
+/// are periodic columns if `periodic` is set, and preprocessed columns of height `height`
+/// otherwise.
+///
+/// Main columns: `[state; WIDTH], [cube; WIDTH]`.
+struct RoundConstantsAir {
+    periodic: bool,
+    height: usize,
+}
+
+/// The round selector and the round constants of round `round`.
+fn round_columns<F: Field>(round: usize) -> [F; 1 + WIDTH] {
+    let is_round = F::from_bool(round < NUM_ROUNDS - 1);
+    let mut row = [is_round; 1 + WIDTH];
+    for (i, round_constant) in row[1..].iter_mut().enumerate() {
+        *round_constant = F::from_canonical_usize(1 + 17 * round + 5 * i);
+    }
+    row
+}
+
+impl<F: Field> BaseAir<F> for RoundConstantsAir {
+    fn width(&self) -> usize {
+        2 * WIDTH
+    }
+
+    fn preprocessed_trace(&self) -> Option<RowMajorMatrix<F>> {
+        (!self.periodic).then(|| {
+            RowMajorMatrix::new(
+                (0..self.height)
+                    .flat_map(|row| round_columns(row % NUM_ROUNDS))
+                    .collect(),
+                1 + WIDTH,
+            )
+        })
+    }
+}
+impl<F: Field> BaseAirWithPublicValues<F> for RoundConstantsAir {}
+impl<F: Field> PartitionedBaseAir<F> for RoundConstantsAir {
+    fn periodic_columns(&self) -> Vec<Vec<F>> {
+        if !self.periodic {
+            return vec![];
+        }
+        let rounds = (0..NUM_ROUNDS).map(round_columns::<F>).collect_vec();
+        (0..1 + WIDTH)
+            .map(|col| rounds.iter().map(|row| row[col]).collect())
+            .collect()
+    }
+}
+
+impl<AB: PairBuilder + PeriodicAirBuilder> Air<AB> for RoundConstantsAir {
+    fn eval(&self, builder: &mut AB) {
+        let round: Vec<AB::Expr> = if self.periodic {
+            builder
+                .periodic_values()
+                .iter()
+                .map(|&value| value.into())
+                .collect()
+        } else {
+            let preprocessed = builder.preprocessed();
+            let local = preprocessed.row_slice(0);
+            local.iter().map(|&value| value.into()).collect()
+        };
+        let (is_round, round_constants) = (round[0].clone(), &round[1..]);
+
+        let main = builder.main();
+        let (local, next) = (main.row_slice(0), main.row_slice(1));
+        let (state, cube) = local.split_at(WIDTH);
+        for i in 0..WIDTH {
+            let x = AB::Expr::from(state[i]) + round_constants[i].clone();
+            builder.assert_eq(cube[i], x.cube());
+        }
+        for (i, coeffs) in MATRIX.iter().enumerate() {
+            let output = (0..WIDTH)
+                .map(|j| cube[j] * AB::F::from_canonical_u32(coeffs[j]))
+                .sum::<AB::Expr>();
+            builder.when(is_round.clone()).assert_eq(next[i], output);
+        }
+    }
+}
+
+fn generate_trace(height: usize) -> RowMajorMatrix<Val> {
+    let mut values = Vec::with_capacity(height * 2 * WIDTH);
+    let mut state = [Val::ZERO; WIDTH];
+    for row in 0..height {
+        let round = row % NUM_ROUNDS;
+        if round == 0 {
+            state = [row, row + 1].map(Val::from_canonical_usize);
+        }
+        let round_constants = &round_columns::<Val>(round)[1..];
+        let cube: [Val; WIDTH] = std::array::from_fn(|i| (state[i] + round_constants[i]).cube());
+        values.extend(state);
+        values.extend(cube);
+        state = MATRIX.map(|coeffs| {
+            (0..WIDTH)
+                .map(|j| cube[j] * Val::from_canonical_u32(coeffs[j]))
+                .sum()
+        });
+    }
+    RowMajorMatrix::new(values, 2 * WIDTH)
+}
+
+fn keygen(periodic: bool, height: usize) -> (usize, MultiStarkProvingKey<SC>) {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    let air_id = keygen_builder.add_air(Arc::new(RoundConstantsAir { periodic, height }));
+    (air_id, keygen_builder.generate_pk())
+}
+
+fn prove(air_id: usize, pk: &MultiStarkProvingKey<SC>, trace: RowMajorMatrix<Val>) -> Proof<SC> {
+    default_engine().prove(
+        pk,
+        ProofInput::new(vec![(air_id, AirProofInput::simple_no_pis(trace))]),
+    )
+}
+
+#[test]
+fn test_periodic_columns_match_preprocessed() {
+    let height = 1 << 6;
+    let engine = default_engine();
+
+    let (air_id, preprocessed_pk) = keygen(false, height);
+    let preprocessed_proof = prove(air_id, &preprocessed_pk, generate_trace(height));
+    engine
+        .verify(&preprocessed_pk.get_vk(), &preprocessed_proof)
+        .expect("Verification failed");
+
+    let (air_id, periodic_pk) = keygen(true, height);
+    let periodic_proof = prove(air_id, &periodic_pk, generate_trace(height));
+    engine
+        .verify(&periodic_pk.get_vk(), &periodic_proof)
+        .expect("Verification failed");
+
+    let (preprocessed_vk, periodic_vk) = (
+        &preprocessed_pk.per_air[air_id].vk,
+        &periodic_pk.per_air[air_id].vk,
+    );
+    assert_eq!(preprocessed_vk.params.width.preprocessed, Some(1 + WIDTH));
+    assert_eq!(preprocessed_proof.core.opened_values.preprocessed.len(), 1);
+    // The periodic version commits to and opens no preprocessed trace.
+    assert_eq!(periodic_vk.params.width.preprocessed, None);
+    assert!(periodic_vk.preprocessed_data.is_none());
+    assert!(periodic_proof.core.opened_values.preprocessed.is_empty());
+    assert_eq!(periodic_vk.periodic_columns.len(), 1 + WIDTH);
+    assert!(periodic_pk.features.contains(VkFeature::PeriodicColumns));
+    assert!(!preprocessed_pk
+        .features
+        .contains(VkFeature::PeriodicColumns));
+
+    assert_eq!(preprocessed_vk.quotient_degree, periodic_vk.quotient_degree);
+    assert_eq!(
+        preprocessed_vk
+            .symbolic_constraints
+            .constraints
+            .num_constraints(),
+        periodic_vk
+            .symbolic_constraints
+            .constraints
+            .num_constraints()
+    );
+}
+
+#[test]
+fn test_periodic_columns_debug_builder() {
+    for log_height in [3, 5] {
+        BabyBearPoseidon2Engine::run_simple_test_no_pis_fast(
+            any_rap_arc_vec![RoundConstantsAir {
+                periodic: true,
+                height: 1 << log_height,
+            }],
+            vec![generate_trace(1 << log_height)],
+        )
+        .expect("Verification failed");
+    }
+}
+
+#[test]
+fn test_periodic_columns_negative() {
+    let height = 1 << 5;
+    let (air_id, pk) = keygen(true, height);
+    let mut trace = generate_trace(height);
+    // Corrupt the S-box output of a round in the middle of a block.
+    trace.values[3 * 2 * WIDTH + WIDTH] += Val::ONE;
+    disable_debug_builder();
+    let proof = prove(air_id, &pk, trace);
+    assert_eq!(
+        default_engine().verify(&pk.get_vk(), &proof),
+        Err(VerificationError::OodEvaluationMismatch {
+            air_id,
+            air_name: "RoundConstantsAir".to_string(),
+        })
+    );
+}
+
+#[test]
+#[should_panic(expected = "exceeds the trace height")]
+fn test_periodic_column_longer_than_trace() {
+    let (air_id, pk) = keygen(true, NUM_ROUNDS / 2);
+    prove(air_id, &pk, generate_trace(NUM_ROUNDS / 2));
+}
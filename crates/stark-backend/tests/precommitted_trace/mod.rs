@@ -0,0 +1,156 @@
+use std::sync::Arc;
+
+use itertools::Itertools;
+use openvm_stark_backend::{
+    keygen::{types::MultiStarkProvingKey, CachedMainError},
+    p3_field::FieldAlgebra,
+    prover::{
+        helper::AirProofInputTestHelper,
+        types::{AirProofInput, CommittedTraceData, ProofInput},
+    },
+};
+use openvm_stark_sdk::{
+    config::baby_bear_poseidon2::{default_engine, BabyBearPoseidon2Config},
+    engine::StarkEngine,
+};
+use p3_baby_bear::BabyBear;
+use p3_matrix::dense::RowMajorMatrix;
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::{partitioned_sum_air::air::SumAir, utils::generate_random_matrix};
+
+type SC = BabyBearPoseidon2Config;
+type Val = BabyBear;
+
+const Y_WIDTH: usize = 3;
+
+/// The cached `y` trace of [SumAir] and the common `x` trace of the sums of its rows.
+fn traces(log_height: usize) -> (RowMajorMatrix<Val>, RowMajorMatrix<Val>) {
+    let ys = generate_random_matrix::<Val>(StdRng::seed_from_u64(0), 1 << log_height, Y_WIDTH);
+    let x = ys
+        .iter()
+        .map(|row| row.iter().fold(Val::ZERO, |sum, y| sum + *y))
+        .collect();
+    (
+        RowMajorMatrix::new(ys.into_iter().flatten().collect_vec(), Y_WIDTH),
+        RowMajorMatrix::new(x, 1),
+    )
+}
+
+fn keygen() -> MultiStarkProvingKey<SC> {
+    let mut keygen_builder = default_engine().keygen_builder();
+    keygen_builder.add_air(Arc::new(SumAir(Y_WIDTH)));
+    keygen_builder.generate_pk()
+}
+
+fn precommitted_input(y: &CommittedTraceData<SC>, x: RowMajorMatrix<Val>) -> ProofInput<SC> {
+    ProofInput::new(vec![(
+        0,
+        AirProofInput::with_cached_mains(vec![y.clone()], Some(x), vec![]),
+    )])
+}
+
+#[test]
+fn test_precommitted_trace() {
+    let engine = default_engine();
+    let pk = keygen();
+    let vk = pk.get_vk();
+    let (y, x) = traces(4);
+    let committed_y = engine.commit_trace(y.clone());
+
+    let first = engine.prove(&pk, precommitted_input(&committed_y, x.clone()));
+    let second = engine.prove(&pk, precommitted_input(&committed_y, x.clone()));
+    for proof in [&first, &second] {
+        engine.verify(&vk, proof).expect("Verification failed");
+    }
+    // Same proof as when the prover commits the cached trace itself.
+    let committed_by_prover = engine.prove(
+        &pk,
+        ProofInput::new(vec![(0, AirProofInput::cached_traces_no_pis(vec![y], x))]),
+    );
+    let bytes = bincode::serialize(&committed_by_prover).unwrap();
+    assert_eq!(bincode::serialize(&first).unwrap(), bytes);
+    assert_eq!(bincode::serialize(&second).unwrap(), bytes);
+}
+
+#[test]
+fn test_precommitted_trace_mismatch() {
+    let engine = default_engine();
+    let pk = keygen();
+    let (y, _) = traces(4);
+    let committed_y = engine.commit_trace(y);
+    let (_, short_x) = traces(3);
+    assert_eq!(
+        pk.check_cached_mains(&precommitted_input(&committed_y, short_x)),
+        Err(CachedMainError::Height {
+            air_id: 0,
+            air_name: "SumAir".to_string(),
+            index: 0,
+            height: 16,
+            expected: 8,
+        })
+    );
+
+    let narrow_y = engine.commit_trace(RowMajorMatrix::new(vec![Val::ONE; 16], 1));
+    let (_, x) = traces(4);
+    assert_eq!(
+        pk.check_cached_mains(&precommitted_input(&narrow_y, x)),
+        Err(CachedMainError::Width {
+            air_id: 0,
+            air_name: "SumAir".to_string(),
+            index: 0,
+            width: 1,
+            expected: Y_WIDTH,
+        })
+    );
+}
+
+#[test]
+#[should_panic(expected = "has height 16, but the trace of the AIR has height 8")]
+fn test_precommitted_trace_mismatch_panics() {
+    let engine = default_engine();
+    let (y, _) = traces(4);
+    let (_, short_x) = traces(3);
+    engine.prove(
+        &keygen(),
+        precommitted_input(&engine.commit_trace(y), short_x),
+    );
+}
+
+#[cfg(feature = "bench-metrics")]
+#[test]
+fn test_precommitted_trace_skips_commit() {
+    use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+
+    let engine = default_engine();
+    let pk = keygen();
+    let (y, x) = traces(4);
+    let recorder = DebuggingRecorder::new();
+    let snapshotter = recorder.snapshotter();
+    let counter = |name: &str| {
+        snapshotter
+            .snapshot()
+            .into_vec()
+            .into_iter()
+            .find(|(key, ..)| key.key().name() == name)
+            .map(|(.., value)| match value {
+                DebugValue::Counter(value) => value,
+                _ => panic!("{name} is not a counter"),
+            })
+    };
+    metrics::with_local_recorder(&recorder, || {
+        let committed_y = engine.commit_trace(y.clone());
+        engine.prove(&pk, precommitted_input(&committed_y, x.clone()));
+        engine.prove(&pk, precommitted_input(&committed_y, x.clone()));
+    });
+    assert_eq!(counter("cached_main_commits"), None);
+    assert_eq!(counter("cached_main_reuses"), Some(2));
+
+    metrics::with_local_recorder(&recorder, || {
+        engine.prove(
+            &pk,
+            ProofInput::new(vec![(0, AirProofInput::cached_traces_no_pis(vec![y], x))]),
+        );
+    });
+    assert_eq!(counter("cached_main_commits"), Some(1));
+}
@@ -0,0 +1,207 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use openvm_stark_backend::{
+    engine::StarkEngine,
+    keygen::{types::MultiStarkProvingKey, StaleProvingKeyError},
+    p3_field::Field,
+    prover::types::{AirProofInput, ProofInput},
+    rap::{BaseAirWithPublicValues, PartitionedBaseAir},
+    AirRef,
+};
+use openvm_stark_sdk::config::baby_bear_poseidon2::{
+    default_engine, BabyBearPoseidon2Config, BabyBearPoseidon2Engine,
+};
+use p3_air::{Air, AirBuilder, BaseAir, PairBuilder};
+use p3_baby_bear::BabyBear;
+use p3_matrix::{dense::RowMajorMatrix, Matrix};
+
+type SC = BabyBearPoseidon2Config;
+
+const HEIGHT: usize = 8;
+
+/// AIR constraining its main column to equal its preprocessed column `[offset, offset + 1, ...]`.
+/// Counts how many times its preprocessed trace was generated.
+struct PreprocessedCopyAir {
+    offset: usize,
+    version: Option<u64>,
+    num_derivations: Arc<AtomicUsize>,
+}
+
+impl PreprocessedCopyAir {
+    fn column<F: Field>(&self) -> Vec<F> {
+        (0..HEIGHT)
+            .map(|i| F::from_canonical_usize(self.offset + i))
+            .collect()
+    }
+}
+
+impl<F: Field> BaseAir<F> for PreprocessedCopyAir {
+    fn width(&self) -> usize {
+        1
+    }
+
+    fn preprocessed_trace(&self) -> Option<RowMajorMatrix<F>> {
+        self.num_derivations.fetch_add(1, Ordering::SeqCst);
+        Some(RowMajorMatrix::new_col(self.column()))
+    }
+}
+
+impl<F: Field> PartitionedBaseAir<F> for PreprocessedCopyAir {}
+impl<F: Field> BaseAirWithPublicValues<F> for PreprocessedCopyAir {
+    fn preprocessed_version(&self) -> Option<u64> {
+        self.version
+    }
+}
+
+impl<AB: PairBuilder> Air<AB> for PreprocessedCopyAir {
+    fn eval(&self, builder: &mut AB) {
+        let preprocessed = builder.preprocessed();
+        let main = builder.main();
+        let (prep_local, local) = (preprocessed.row_slice(0), main.row_slice(0));
+        builder.assert_eq(prep_local[0], local[0]);
+    }
+}
+
+fn keygen(engine: &BabyBearPoseidon2Engine, air: AirRef<SC>) -> MultiStarkProvingKey<SC> {
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(air);
+    keygen_builder.generate_pk()
+}
+
+fn proof_input(air: &PreprocessedCopyAir) -> ProofInput<SC> {
+    let trace = RowMajorMatrix::new_col(air.column::<BabyBear>());
+    ProofInput::new(vec![(0, AirProofInput::simple_no_pis(trace))])
+}
+
+#[test]
+fn test_stale_preprocessed_data_rejected() {
+    let engine = default_engine();
+    let num_derivations = Arc::new(AtomicUsize::new(0));
+    let old_air = PreprocessedCopyAir {
+        offset: 0,
+        version: Some(1),
+        num_derivations: num_derivations.clone(),
+    };
+    let pk = keygen(&engine, Arc::new(old_air));
+
+    // The preprocessed table generation changed, but the old proving key is still loaded.
+    let new_air = Arc::new(PreprocessedCopyAir {
+        offset: 1,
+        version: Some(2),
+        num_derivations,
+    });
+    let input = proof_input(&new_air);
+    let result = engine.prove_checked(&[new_air as AirRef<SC>], &pk, input, false);
+    assert_eq!(
+        result.err(),
+        Some(StaleProvingKeyError::StaleAirs {
+            stale_airs: vec![(0, "PreprocessedCopyAir".to_string())]
+        })
+    );
+}
+
+#[test]
+fn test_stale_preprocessed_data_forced() {
+    let engine = default_engine();
+    let num_derivations = Arc::new(AtomicUsize::new(0));
+    let old_air = PreprocessedCopyAir {
+        offset: 0,
+        version: Some(1),
+        num_derivations: num_derivations.clone(),
+    };
+    let pk = keygen(&engine, Arc::new(old_air));
+
+    let new_air = Arc::new(PreprocessedCopyAir {
+        offset: 1,
+        version: Some(2),
+        num_derivations: num_derivations.clone(),
+    });
+    let input = proof_input(&new_air);
+    let num_derivations_before = num_derivations.load(Ordering::SeqCst);
+    assert!(engine
+        .prove_checked(&[new_air as AirRef<SC>], &pk, input, true)
+        .is_ok());
+    assert_eq!(
+        num_derivations.load(Ordering::SeqCst),
+        num_derivations_before
+    );
+}
+
+#[test]
+fn test_version_change_without_table_change() {
+    let engine = default_engine();
+    let num_derivations = Arc::new(AtomicUsize::new(0));
+    let old_air = PreprocessedCopyAir {
+        offset: 0,
+        version: Some(1),
+        num_derivations: num_derivations.clone(),
+    };
+    let pk = keygen(&engine, Arc::new(old_air));
+
+    // Version bumped but the table is unchanged: re-derived once and accepted.
+    let new_air: AirRef<SC> = Arc::new(PreprocessedCopyAir {
+        offset: 0,
+        version: Some(2),
+        num_derivations: num_derivations.clone(),
+    });
+    let num_derivations_before = num_derivations.load(Ordering::SeqCst);
+    assert_eq!(pk.check_preprocessed_consistency(&[new_air]), Ok(()));
+    assert_eq!(
+        num_derivations.load(Ordering::SeqCst),
+        num_derivations_before + 1
+    );
+}
+
+#[test]
+fn test_matching_preprocessed_version() {
+    let engine = default_engine();
+    let num_derivations = Arc::new(AtomicUsize::new(0));
+    let air = Arc::new(PreprocessedCopyAir {
+        offset: 0,
+        version: Some(1),
+        num_derivations: num_derivations.clone(),
+    });
+    let pk = keygen(&engine, air.clone());
+
+    let input = proof_input(&air);
+    let num_derivations_before = num_derivations.load(Ordering::SeqCst);
+    let proof = engine
+        .prove_checked(&[air as AirRef<SC>], &pk, input, false)
+        .expect("Preprocessed data should be consistent");
+    // Versions match, so the preprocessed trace is not re-derived.
+    assert_eq!(
+        num_derivations.load(Ordering::SeqCst),
+        num_derivations_before
+    );
+    engine
+        .verify(&pk.get_vk(), &proof)
+        .expect("Verification failed");
+}
+
+#[test]
+fn test_preprocessed_consistency_air_count_mismatch() {
+    let engine = default_engine();
+    let air = Arc::new(PreprocessedCopyAir {
+        offset: 0,
+        version: Some(1),
+        num_derivations: Arc::new(AtomicUsize::new(0)),
+    });
+    let pk = keygen(&engine, air.clone());
+
+    let airs = [air.clone() as AirRef<SC>, air.clone()];
+    let mismatch = |actual| StaleProvingKeyError::AirCountMismatch {
+        expected: 1,
+        actual,
+    };
+    assert_eq!(pk.check_preprocessed_consistency(&airs), Err(mismatch(2)));
+    assert_eq!(pk.check_preprocessed_consistency(&[]), Err(mismatch(0)));
+    // The check runs before proving, so no proof is generated.
+    let input = proof_input(&air);
+    assert_eq!(
+        engine.prove_checked(&airs, &pk, input, false).err(),
+        Some(mismatch(2))
+    );
+}
@@ -0,0 +1,237 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use openvm_stark_backend::{
+    config::StarkGenericConfig,
+    engine::StarkEngine,
+    keygen::{
+        types::{MultiStarkProvingKey, PreprocessedShape, VkFeature},
+        MissingPreprocessedHeightError,
+    },
+    p3_field::{Field, FieldAlgebra},
+    prover::types::{AirProofInput, ProofInput},
+    rap::{BaseAirWithPublicValues, PartitionedBaseAir},
+    verifier::VerificationError,
+    AirRef,
+};
+use openvm_stark_sdk::{
+    config::baby_bear_poseidon2::{default_engine, BabyBearPoseidon2Config},
+    key_serde::KeySerde,
+};
+use p3_air::{Air, AirBuilder, BaseAir, PairBuilder};
+use p3_baby_bear::BabyBear;
+use p3_matrix::{dense::RowMajorMatrix, Matrix};
+
+type SC = BabyBearPoseidon2Config;
+type Val = BabyBear;
+
+const LOG_HEIGHTS: [usize; 4] = [3, 5, 10, 12];
+
+/// AIR constraining its main column to be the square of its preprocessed column, which is the
+/// row index `[0, 1, 2, ...]` at every supported height. Counts how many times a preprocessed
+/// trace was generated.
+struct SquareIndexAir {
+    num_derivations: Arc<AtomicUsize>,
+}
+
+impl<F: Field> BaseAir<F> for SquareIndexAir {
+    fn width(&self) -> usize {
+        1
+    }
+}
+
+impl<F: Field> PartitionedBaseAir<F> for SquareIndexAir {
+    fn preprocessed_shape(&self) -> Option<PreprocessedShape> {
+        Some(PreprocessedShape {
+            width: 1,
+            log_heights: LOG_HEIGHTS.to_vec(),
+        })
+    }
+
+    fn preprocessed_trace_for_log_height(&self, log_height: usize) -> Option<RowMajorMatrix<F>> {
+        self.num_derivations.fetch_add(1, Ordering::SeqCst);
+        let column = (0..1 << log_height).map(F::from_canonical_usize).collect();
+        Some(RowMajorMatrix::new_col(column))
+    }
+}
+
+impl<F: Field> BaseAirWithPublicValues<F> for SquareIndexAir {}
+
+impl<AB: PairBuilder> Air<AB> for SquareIndexAir {
+    fn eval(&self, builder: &mut AB) {
+        let preprocessed = builder.preprocessed();
+        let main = builder.main();
+        let index = preprocessed.row_slice(0)[0];
+        let x = main.row_slice(0)[0];
+        builder.assert_eq(x, index * index);
+    }
+}
+
+pub(crate) fn proof_input(log_height: usize) -> ProofInput<SC> {
+    let column = (0..1usize << log_height)
+        .map(|i| Val::from_canonical_usize(i * i))
+        .collect();
+    let trace = RowMajorMatrix::new_col(column);
+    ProofInput::new(vec![(0, AirProofInput::simple_no_pis(trace))])
+}
+
+pub(crate) fn keygen() -> (Vec<AirRef<SC>>, MultiStarkProvingKey<SC>, Arc<AtomicUsize>) {
+    let num_derivations = Arc::new(AtomicUsize::new(0));
+    let air: AirRef<SC> = Arc::new(SquareIndexAir {
+        num_derivations: num_derivations.clone(),
+    });
+    let mut keygen_builder = default_engine().keygen_builder();
+    keygen_builder.add_air(air.clone());
+    (vec![air], keygen_builder.generate_pk(), num_derivations)
+}
+
+#[test]
+fn test_preprocessed_per_height() {
+    let engine = default_engine();
+    let (airs, mut pk, num_derivations) = keygen();
+    assert_eq!(num_derivations.load(Ordering::SeqCst), 0);
+    assert_eq!(pk.per_air[0].vk.params.width.preprocessed, Some(1));
+    assert!(pk.features.contains(VkFeature::PreprocessedPerHeight));
+
+    let small_proof = engine.prove_with_preprocessed_heights(&airs, &mut pk, proof_input(3));
+    let small_vk = pk.get_vk();
+    let large_proof = engine.prove_with_preprocessed_heights(&airs, &mut pk, proof_input(5));
+    // The preprocessed trace of an already committed height is not generated again.
+    engine.prove_with_preprocessed_heights(&airs, &mut pk, proof_input(3));
+    assert_eq!(num_derivations.load(Ordering::SeqCst), 2);
+
+    let vk = pk.get_vk();
+    let per_height = vk.per_air[0].preprocessed_per_height.as_ref().unwrap();
+    assert_eq!(
+        per_height
+            .commits
+            .iter()
+            .map(|&(h, _)| h)
+            .collect::<Vec<_>>(),
+        [3, 5]
+    );
+    assert_ne!(per_height.commits[0].1, per_height.commits[1].1);
+    engine
+        .verify(&vk, &small_proof)
+        .expect("Verification failed");
+    engine
+        .verify(&vk, &large_proof)
+        .expect("Verification failed");
+    engine
+        .verify(&small_vk, &small_proof)
+        .expect("Verification failed");
+    assert_eq!(
+        engine.verify(&small_vk, &large_proof),
+        Err(VerificationError::MissingPreprocessedHeight(
+            MissingPreprocessedHeightError {
+                air_id: 0,
+                air_name: "SquareIndexAir".to_string(),
+                height: 32,
+            }
+        ))
+    );
+
+    // The proof is bound to the preprocessed commitment of its height.
+    let mut swapped_vk = vk.clone();
+    let commits = &mut swapped_vk.per_air[0]
+        .preprocessed_per_height
+        .as_mut()
+        .unwrap()
+        .commits;
+    commits[1].1 = commits[0].1.clone();
+    assert!(engine.verify(&swapped_vk, &large_proof).is_err());
+}
+
+#[test]
+fn test_preprocessed_per_height_key_serde() {
+    let engine = default_engine();
+    let (airs, mut pk, num_derivations) = keygen();
+    let proofs = [10, 12].map(|log_height| {
+        engine.prove_with_preprocessed_heights(&airs, &mut pk, proof_input(log_height))
+    });
+    let vk = pk.get_vk();
+    for proof in &proofs {
+        engine.verify(&vk, proof).expect("Verification failed");
+    }
+
+    // The committed heights are serialized with the key, so a key written after proving proves
+    // these heights without generating their preprocessed traces again.
+    let bytes = pk.to_bytes();
+    let warm_pk = MultiStarkProvingKey::<SC>::from_bytes(&bytes).unwrap();
+    assert_eq!(warm_pk.to_bytes(), bytes);
+    assert_eq!(
+        warm_pk.per_air[0]
+            .preprocessed_per_height
+            .keys()
+            .copied()
+            .collect::<Vec<_>>(),
+        [10, 12]
+    );
+    let warm_vk = warm_pk.get_vk();
+    assert_eq!(warm_vk.canonical_bytes(), vk.canonical_bytes());
+    for log_height in [10, 12] {
+        let proof = engine.prove(&warm_pk, proof_input(log_height));
+        engine
+            .verify(&warm_vk, &proof)
+            .expect("Verification failed");
+    }
+    assert_eq!(num_derivations.load(Ordering::SeqCst), 2);
+}
+
+/// The bytes of a key do not depend on the order in which its heights were committed, so that
+/// the same key is stored under the same content address and has the same digest.
+#[test]
+fn test_preprocessed_per_height_key_bytes_canonical() {
+    let engine = default_engine();
+    let [pk, reordered_pk] = [[3, 5, 10], [10, 3, 5]].map(|log_heights| {
+        let (airs, mut pk, _) = keygen();
+        for log_height in log_heights {
+            pk.prepare_preprocessed_heights(engine.config().pcs(), &airs, &[(0, 1 << log_height)]);
+        }
+        pk
+    });
+    assert_eq!(pk.to_bytes(), reordered_pk.to_bytes());
+    let (vk, reordered_vk) = (pk.get_vk(), reordered_pk.get_vk());
+    assert_eq!(vk.to_bytes(), reordered_vk.to_bytes());
+    assert_eq!(vk.canonical_bytes(), reordered_vk.canonical_bytes());
+}
+
+#[test]
+#[should_panic(expected = "unsupported trace height 16")]
+fn test_preprocessed_per_height_unsupported() {
+    let engine = default_engine();
+    let (airs, mut pk, _) = keygen();
+    engine.prove_with_preprocessed_heights(&airs, &mut pk, proof_input(4));
+}
+
+#[test]
+#[should_panic(expected = "no preprocessed commitment for trace height 8")]
+fn test_preprocessed_per_height_not_committed() {
+    let engine = default_engine();
+    let (_, pk, _) = keygen();
+    engine.prove(&pk, proof_input(3));
+}
+
+#[test]
+fn test_preprocessed_per_height_untrusted_height() {
+    let engine = default_engine();
+    let (airs, mut pk, _) = keygen();
+    engine.prove_with_preprocessed_heights(&airs, &mut pk, proof_input(3));
+    let vk = pk.get_vk();
+    let air_vk = &vk.per_air[0];
+    assert!(air_vk.preprocessed_commit_at_height(8).is_some());
+    // Heights read from a proof are not powers of two, or beyond any committed height.
+    for height in [0, 7, 9, (1 << 63) + 1, usize::MAX, 1 << 63] {
+        assert!(air_vk.preprocessed_commit_at_height(height).is_none());
+    }
+    assert_eq!(
+        vk.at_heights(&[(0, usize::MAX)]).err(),
+        Some(MissingPreprocessedHeightError {
+            air_id: 0,
+            air_name: "SquareIndexAir".to_string(),
+            height: usize::MAX,
+        })
+    );
+}
@@ -0,0 +1,127 @@
+use openvm_stark_backend::{
+    config::{Com, PcsProof, RapPhaseSeqPartialProof, StarkGenericConfig, Val},
+    engine::StarkEngine,
+    p3_field::{extension::BinomialExtensionField, FieldAlgebra},
+    proof::{AirProofData, Commitments, OpeningProof, Proof, ProofCore},
+};
+use openvm_stark_sdk::{
+    any_rap_arc_vec,
+    config::{
+        baby_bear_blake3::BabyBearBlake3Engine,
+        baby_bear_poseidon2::{BabyBearPoseidon2Config, BabyBearPoseidon2Engine},
+    },
+    dummy_airs::fib_air::{air::FibonacciAir, trace::generate_trace_rows},
+    engine::StarkFriEngine,
+};
+use p3_baby_bear::BabyBear;
+use serde::{Deserialize, Serialize};
+
+use crate::get_fib_number;
+
+type Challenge = BinomialExtensionField<BabyBear, 4>;
+
+const LOG_N: usize = 4;
+
+/// The claims of a proof of [FibonacciAir], read without naming the commitments or the PCS of
+/// the config of the proof.
+fn fib_claims(core: &ProofCore<BabyBear, Challenge>) -> (Vec<(usize, usize)>, Vec<BabyBear>) {
+    let (air_id, public_values) = core.public_values().next().unwrap();
+    assert_eq!(air_id, 0);
+    (core.log_trace_heights(), public_values.to_vec())
+}
+
+#[test]
+fn test_proof_core_across_configs() {
+    let trace = || generate_trace_rows::<BabyBear>(0, 1, 1 << LOG_N);
+    let pis = vec![
+        BabyBear::ZERO,
+        BabyBear::ONE,
+        BabyBear::from_canonical_u32(get_fib_number(1 << LOG_N)),
+    ];
+    let poseidon2_proof = BabyBearPoseidon2Engine::run_simple_test_fast(
+        any_rap_arc_vec![FibonacciAir],
+        vec![trace()],
+        vec![pis.clone()],
+    )
+    .unwrap()
+    .data
+    .proof;
+    let blake3_proof = BabyBearBlake3Engine::run_simple_test_fast(
+        any_rap_arc_vec![FibonacciAir],
+        vec![trace()],
+        vec![pis.clone()],
+    )
+    .unwrap()
+    .data
+    .proof;
+
+    assert_eq!(
+        fib_claims(poseidon2_proof.core()),
+        (vec![(0, LOG_N)], pis.clone())
+    );
+    let cores = [poseidon2_proof.into_core(), blake3_proof.into_core()];
+    assert_eq!(fib_claims(&cores[0]), fib_claims(&cores[1]));
+    // The opened values are at a point sampled from the transcript, which depends on the hasher,
+    // but their shape does not.
+    assert_eq!(
+        cores[0].opened_values.num_values(),
+        cores[1].opened_values.num_values()
+    );
+    for core in &cores {
+        assert!(core.absent_air_ids.is_empty());
+        assert!(core.exposed_values_iter().next().is_none());
+    }
+}
+
+/// The layout of [Proof] before [ProofCore] was split out of it.
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+struct ProofBeforeCore<SC: StarkGenericConfig> {
+    commitments: Commitments<Com<SC>>,
+    opening: OpeningProof<PcsProof<SC>, SC::Challenge>,
+    per_air: Vec<AirProofData<Val<SC>, SC::Challenge>>,
+    rap_phase_seq_proof: Option<RapPhaseSeqPartialProof<SC>>,
+    absent_air_ids: Vec<usize>,
+}
+
+#[test]
+fn test_proof_core_decodes_layout_before_split() {
+    type SC = BabyBearPoseidon2Config;
+    let data = BabyBearPoseidon2Engine::run_simple_test_fast(
+        any_rap_arc_vec![FibonacciAir],
+        vec![generate_trace_rows::<BabyBear>(0, 1, 1 << LOG_N)],
+        vec![vec![
+            BabyBear::ZERO,
+            BabyBear::ONE,
+            BabyBear::from_canonical_u32(get_fib_number(1 << LOG_N)),
+        ]],
+    )
+    .unwrap();
+    let proof = data.data.proof;
+    let before: ProofBeforeCore<SC> = bincode::deserialize(&bincode::serialize(&proof).unwrap())
+        .expect("the layout of the proof changed");
+    let Proof {
+        commitments,
+        core,
+        pcs_proof,
+        rap_phase_seq_proof,
+    } = proof;
+    let bytes = bincode::serialize(&ProofBeforeCore::<SC> {
+        commitments,
+        opening: OpeningProof {
+            proof: pcs_proof,
+            values: core.opened_values,
+        },
+        per_air: core.per_air,
+        rap_phase_seq_proof,
+        absent_air_ids: core.absent_air_ids,
+    })
+    .unwrap();
+    assert_eq!(bincode::serialize(&before).unwrap(), bytes);
+
+    let decoded: Proof<SC> = bincode::deserialize(&bytes).unwrap();
+    assert_eq!(bincode::serialize(&decoded).unwrap(), bytes);
+    BabyBearPoseidon2Engine::new(data.fri_params)
+        .verify(&data.data.vk, &decoded)
+        .expect("Verification failed");
+}
@@ -0,0 +1,98 @@
+use std::sync::Arc;
+
+use openvm_stark_backend::{
+    config::StarkGenericConfig,
+    engine::StarkEngine,
+    keygen::types::MultiStarkVerifyingKey,
+    p3_field::FieldAlgebra,
+    proof::Proof,
+    prover::types::{AirProofInput, ProofInput},
+    utils::disable_debug_builder,
+    verifier::VerificationError,
+};
+use openvm_stark_sdk::{
+    config::baby_bear_poseidon2::{default_engine, BabyBearPoseidon2Config},
+    dummy_airs::interaction::dummy_interaction_air::DummyInteractionAir,
+};
+use p3_matrix::dense::RowMajorMatrix;
+
+use crate::utils::to_field_vec;
+
+type SC = BabyBearPoseidon2Config;
+type Challenge = <SC as StarkGenericConfig>::Challenge;
+
+/// Proves a sender and a receiver on bus 0, with traces `(count, value)`. The sender on bus 1
+/// with id 2 is not in the proof.
+fn prove(
+    sender_trace: Vec<u32>,
+    receiver_trace: Vec<u32>,
+) -> (Proof<SC>, MultiStarkVerifyingKey<SC>) {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(Arc::new(DummyInteractionAir::new(1, true, 0)));
+    keygen_builder.add_air(Arc::new(DummyInteractionAir::new(1, false, 0)));
+    keygen_builder.add_air(Arc::new(DummyInteractionAir::new(1, true, 1)));
+    let pk = keygen_builder.generate_pk();
+    let proof = engine.prove(
+        &pk,
+        ProofInput::new(vec![
+            (
+                0,
+                AirProofInput::simple_no_pis(RowMajorMatrix::new(to_field_vec(sender_trace), 2)),
+            ),
+            (
+                1,
+                AirProofInput::simple_no_pis(RowMajorMatrix::new(to_field_vec(receiver_trace), 2)),
+            ),
+        ]),
+    );
+    (proof, pk.get_vk())
+}
+
+#[test]
+fn test_exposed_values_accessors() {
+    let (proof, vk) = prove(vec![1, 5, 2, 7], vec![2, 7, 1, 5]);
+    default_engine()
+        .verify(&vk, &proof)
+        .expect("Verification failed");
+
+    for air_id in [0, 1] {
+        assert_eq!(proof.exposed_values(air_id, 0).len(), 1);
+        // There is a single challenge phase.
+        assert!(proof.exposed_values(air_id, 1).is_empty());
+    }
+    // Not in the proof.
+    assert!(proof.exposed_values(2, 0).is_empty());
+
+    let exposed_values = proof.exposed_values_iter().collect::<Vec<_>>();
+    assert_eq!(
+        exposed_values
+            .iter()
+            .map(|&(air_id, phase, values)| (air_id, phase, values.len()))
+            .collect::<Vec<_>>(),
+        vec![(0, 0, 1), (1, 0, 1)]
+    );
+    assert_eq!(exposed_values[0].2, proof.exposed_values(0, 0));
+
+    assert_eq!(proof.log_up_total_sums(&vk), Ok(vec![Challenge::ZERO]));
+}
+
+#[test]
+fn test_log_up_total_sums_unbalanced() {
+    disable_debug_builder();
+    // The receiver receives 5 twice but it is sent once.
+    let (proof, vk) = prove(vec![1, 5, 2, 7], vec![2, 7, 2, 5]);
+    let total_sums = proof.log_up_total_sums(&vk).unwrap();
+    assert_eq!(total_sums.len(), 1);
+    assert_ne!(total_sums[0], Challenge::ZERO);
+}
+
+#[test]
+fn test_log_up_total_sums_air_not_in_key() {
+    let (mut proof, vk) = prove(vec![1, 5, 2, 7], vec![2, 7, 1, 5]);
+    proof.core.per_air[1].air_id = vk.per_air.len();
+    assert_eq!(
+        proof.log_up_total_sums(&vk),
+        Err(VerificationError::InvalidAirIds(vec![0, vk.per_air.len()]))
+    );
+}
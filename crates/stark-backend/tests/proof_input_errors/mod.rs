@@ -0,0 +1,295 @@
+use std::sync::Arc;
+
+use openvm_stark_backend::{
+    keygen::{
+        types::MultiStarkProvingKey, CachedMainError, MainTraceKind, ProofInputError,
+        TraceHeightTooLargeError,
+    },
+    p3_field::FieldAlgebra,
+    prover::{
+        types::{AirProofInput, AirProofRawInput, ProofInput},
+        ProvingError, ProvingOptions,
+    },
+};
+use openvm_stark_sdk::{
+    config::baby_bear_poseidon2::{default_engine, BabyBearPoseidon2Config},
+    dummy_airs::{
+        fib_air::{air::FibonacciAir, trace::generate_trace_rows},
+        interaction::dummy_interaction_air::DummyInteractionAir,
+    },
+    engine::StarkEngine,
+};
+use p3_baby_bear::BabyBear;
+use p3_matrix::dense::RowMajorMatrix;
+
+use crate::get_fib_number;
+
+type SC = BabyBearPoseidon2Config;
+type Val = BabyBear;
+
+const FIB_AIR_ID: usize = 0;
+const SEND_AIR_ID: usize = 1;
+const MAX_LOG_HEIGHT: usize = 4;
+
+/// A Fibonacci AIR with traces of at most `2^MAX_LOG_HEIGHT` rows, and an AIR sending messages
+/// with the fields in a cached main trace of width 1 and the counts in a common main trace of
+/// width 1.
+fn keygen() -> MultiStarkProvingKey<SC> {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(Arc::new(FibonacciAir));
+    keygen_builder.add_air(Arc::new(DummyInteractionAir::new(1, true, 0).partition()));
+    keygen_builder.set_max_log_height(FIB_AIR_ID, MAX_LOG_HEIGHT);
+    keygen_builder.generate_pk()
+}
+
+fn fib_pis(n: usize) -> Vec<Val> {
+    [0, 1, get_fib_number(n)]
+        .map(Val::from_canonical_u32)
+        .to_vec()
+}
+
+fn fib_input(n: usize) -> AirProofInput<SC> {
+    AirProofInput::simple(generate_trace_rows::<Val>(0, 1, n), fib_pis(n))
+}
+
+fn send_input(fields: RowMajorMatrix<Val>, count: RowMajorMatrix<Val>) -> AirProofInput<SC> {
+    AirProofInput {
+        cached_mains_pdata: vec![],
+        raw: AirProofRawInput {
+            cached_mains: vec![Arc::new(fields)],
+            common_main: Some(count),
+            custom_mains: vec![],
+            public_values: vec![],
+        },
+    }
+}
+
+fn column(height: usize) -> RowMajorMatrix<Val> {
+    RowMajorMatrix::new((0..height as u32).map(Val::from_canonical_u32).collect(), 1)
+}
+
+fn log_blowup() -> usize {
+    default_engine().fri_params.log_blowup
+}
+
+/// The error of proving `per_air` with `pk`.
+fn prove_err(
+    pk: &MultiStarkProvingKey<SC>,
+    per_air: Vec<(usize, AirProofInput<SC>)>,
+) -> ProofInputError {
+    let input = ProofInput::new(per_air);
+    let err = pk.check_proof_input(&input, log_blowup()).unwrap_err();
+    match default_engine().prove_with_options(pk, input, ProvingOptions::default()) {
+        Err(ProvingError::InvalidInput(proving_err)) => assert_eq!(proving_err, err),
+        Err(proving_err) => panic!("unexpected error: {proving_err}"),
+        Ok(_) => panic!("the proof input was accepted"),
+    }
+    err
+}
+
+fn fib_air_name() -> String {
+    "FibonacciAir".to_string()
+}
+
+fn send_air_name(pk: &MultiStarkProvingKey<SC>) -> String {
+    pk.per_air[SEND_AIR_ID].air_name.clone()
+}
+
+#[test]
+fn test_valid_proof_input() {
+    let engine = default_engine();
+    let pk = keygen();
+    let input = ProofInput::new(vec![
+        (FIB_AIR_ID, fib_input(8)),
+        (SEND_AIR_ID, send_input(column(8), column(8))),
+    ]);
+    assert_eq!(pk.check_proof_input(&input, log_blowup()), Ok(()));
+    // The messages of the send AIR have no receiver, so only the Fibonacci AIR is proven.
+    engine
+        .prove_then_verify(&pk, ProofInput::new(vec![(FIB_AIR_ID, fib_input(8))]))
+        .expect("Verification failed");
+}
+
+#[test]
+fn test_unknown_air() {
+    let pk = keygen();
+    assert_eq!(
+        prove_err(&pk, vec![(2, fib_input(8))]),
+        ProofInputError::UnknownAir {
+            air_id: 2,
+            num_airs: 2,
+        }
+    );
+    let input = ProofInput::new(vec![(FIB_AIR_ID, fib_input(8))]).with_absent_airs([5]);
+    assert_eq!(
+        pk.check_proof_input(&input, log_blowup()),
+        Err(ProofInputError::UnknownAir {
+            air_id: 5,
+            num_airs: 2,
+        })
+    );
+}
+
+#[test]
+fn test_duplicate_air() {
+    let pk = keygen();
+    assert_eq!(
+        prove_err(
+            &pk,
+            vec![(FIB_AIR_ID, fib_input(8)), (FIB_AIR_ID, fib_input(8))]
+        ),
+        ProofInputError::DuplicateAir {
+            air_id: FIB_AIR_ID,
+            air_name: fib_air_name(),
+        }
+    );
+}
+
+#[test]
+fn test_trace_count() {
+    let pk = keygen();
+    let mut input = fib_input(8);
+    input.raw.common_main = None;
+    assert_eq!(
+        prove_err(&pk, vec![(FIB_AIR_ID, input)]),
+        ProofInputError::TraceCount {
+            air_id: FIB_AIR_ID,
+            air_name: fib_air_name(),
+            kind: MainTraceKind::Common,
+            expected: 1,
+            actual: 0,
+        }
+    );
+
+    let mut input = send_input(column(8), column(8));
+    input.raw.cached_mains.push(Arc::new(column(8)));
+    assert_eq!(
+        prove_err(&pk, vec![(SEND_AIR_ID, input)]),
+        ProofInputError::TraceCount {
+            air_id: SEND_AIR_ID,
+            air_name: send_air_name(&pk),
+            kind: MainTraceKind::Cached,
+            expected: 1,
+            actual: 2,
+        }
+    );
+}
+
+#[test]
+fn test_trace_width() {
+    let pk = keygen();
+    let wide_trace = RowMajorMatrix::new(vec![Val::ONE; 3 * 8], 3);
+    assert_eq!(
+        prove_err(
+            &pk,
+            vec![(FIB_AIR_ID, AirProofInput::simple(wide_trace, fib_pis(8)))]
+        ),
+        ProofInputError::Width {
+            air_id: FIB_AIR_ID,
+            air_name: fib_air_name(),
+            kind: MainTraceKind::Common,
+            index: 0,
+            width: 3,
+            expected: 2,
+        }
+    );
+}
+
+#[test]
+fn test_trace_height_mismatch() {
+    let pk = keygen();
+    assert_eq!(
+        prove_err(&pk, vec![(SEND_AIR_ID, send_input(column(8), column(4)))]),
+        ProofInputError::Height {
+            air_id: SEND_AIR_ID,
+            air_name: send_air_name(&pk),
+            kind: MainTraceKind::Common,
+            index: 0,
+            height: 4,
+            expected: 8,
+        }
+    );
+}
+
+#[test]
+fn test_trace_height_not_power_of_two() {
+    let pk = keygen();
+    let trace = RowMajorMatrix::new(vec![Val::ONE; 2 * 6], 2);
+    assert_eq!(
+        prove_err(
+            &pk,
+            vec![(FIB_AIR_ID, AirProofInput::simple(trace, fib_pis(6)))]
+        ),
+        ProofInputError::HeightNotPowerOfTwo {
+            air_id: FIB_AIR_ID,
+            air_name: fib_air_name(),
+            height: 6,
+        }
+    );
+}
+
+#[test]
+fn test_public_values_count() {
+    let pk = keygen();
+    let mut input = fib_input(8);
+    input.raw.public_values.pop();
+    assert_eq!(
+        prove_err(&pk, vec![(FIB_AIR_ID, input)]),
+        ProofInputError::PublicValuesCount {
+            air_id: FIB_AIR_ID,
+            air_name: fib_air_name(),
+            expected: 3,
+            actual: 2,
+        }
+    );
+}
+
+#[test]
+fn test_precommitted_cached_main() {
+    let engine = default_engine();
+    let pk = keygen();
+    let wide_fields = engine.commit_trace(RowMajorMatrix::new(vec![Val::ONE; 2 * 8], 2));
+    let input = AirProofInput::with_cached_mains(vec![wide_fields], Some(column(8)), vec![]);
+    assert_eq!(
+        prove_err(&pk, vec![(SEND_AIR_ID, input)]),
+        ProofInputError::CachedMain(CachedMainError::Width {
+            air_id: SEND_AIR_ID,
+            air_name: send_air_name(&pk),
+            index: 0,
+            width: 2,
+            expected: 1,
+        })
+    );
+}
+
+#[test]
+fn test_trace_height_too_large() {
+    let pk = keygen();
+    let n = 1 << (MAX_LOG_HEIGHT + 1);
+    assert_eq!(
+        prove_err(&pk, vec![(FIB_AIR_ID, fib_input(n))]),
+        ProofInputError::TraceHeightTooLarge(TraceHeightTooLargeError {
+            air_id: FIB_AIR_ID,
+            air_name: fib_air_name(),
+            height: n,
+            max_log_height: MAX_LOG_HEIGHT,
+        })
+    );
+}
+
+#[test]
+#[should_panic(
+    expected = "common main trace 0 of AIR FibonacciAir (air_id = 0) has width 3, but the AIR \
+                expects 2"
+)]
+fn test_invalid_proof_input_panics() {
+    let wide_trace = RowMajorMatrix::new(vec![Val::ONE; 3 * 8], 3);
+    default_engine().prove(
+        &keygen(),
+        ProofInput::new(vec![(
+            FIB_AIR_ID,
+            AirProofInput::simple(wide_trace, fib_pis(8)),
+        )]),
+    );
+}
@@ -0,0 +1,239 @@
+use std::{fs, path::Path};
+
+use openvm_stark_backend::{
+    config::StarkGenericConfig,
+    p3_field::FieldAlgebra,
+    proof::Proof,
+    prover::types::{AirProofInput, ProofInput},
+};
+use openvm_stark_sdk::{
+    config::{
+        baby_bear_poseidon2::{
+            default_engine, BabyBearPoseidon2Config, BabyBearPoseidon2Engine, DIGEST_WIDTH,
+        },
+        FriParameters,
+    },
+    dummy_airs::fib_lookup,
+    engine::{StarkEngine, StarkFriEngine},
+    proof_json::{ProofJson, ProofJsonError, PROOF_JSON_SCHEMA_VERSION},
+    proof_serde::ProofSerde,
+};
+use p3_baby_bear::BabyBear;
+use p3_matrix::dense::RowMajorMatrix;
+use serde_json::Value;
+
+use crate::{
+    get_fib_number,
+    proof_serde::{keygen, prove},
+};
+
+type SC = BabyBearPoseidon2Config;
+type Val = BabyBear;
+type Challenge = <SC as StarkGenericConfig>::Challenge;
+
+/// A proof small enough to be read, of a Fibonacci AIR and of an AIR sending on bus 0 what
+/// another AIR receives, with few FRI queries.
+fn tiny_proof() -> Proof<SC> {
+    let engine = BabyBearPoseidon2Engine::new(FriParameters {
+        log_blowup: 1,
+        log_final_poly_len: 0,
+        num_queries: 2,
+        proof_of_work_bits: 0,
+    });
+    let mut keygen_builder = engine.keygen_builder();
+    fib_lookup::add_airs(&mut keygen_builder);
+    let pk = keygen_builder.generate_pk();
+    // | count | field |
+    let interaction_trace = || {
+        AirProofInput::simple_no_pis(RowMajorMatrix::new(
+            [1, 3, 2, 5].map(Val::from_canonical_u32).to_vec(),
+            2,
+        ))
+    };
+    let proof = engine.prove_deterministic(
+        &pk,
+        ProofInput::new(vec![
+            (0, fib_lookup::fib_input(4)),
+            (1, interaction_trace()),
+            (2, interaction_trace()),
+        ]),
+    );
+    engine
+        .verify(&pk.get_vk(), &proof)
+        .expect("Verification failed");
+    proof
+}
+
+/// The sorted keys of the object `value`.
+fn keys(value: &Value) -> Vec<&str> {
+    let mut keys = value
+        .as_object()
+        .unwrap()
+        .keys()
+        .map(|key| key.as_str())
+        .collect::<Vec<_>>();
+    keys.sort_unstable();
+    keys
+}
+
+#[test]
+fn test_proof_json_round_trip() {
+    let engine = default_engine();
+    let pk = keygen();
+    let mut proof = prove(&pk);
+    let decoded = Proof::<SC>::from_json(&proof.to_json()).unwrap();
+    assert_eq!(decoded.to_bytes(), proof.to_bytes());
+    engine
+        .verify(&pk.get_vk(), &decoded)
+        .expect("Verification failed");
+
+    // A proof that does not verify still round-trips, so that it can be inspected.
+    proof.core.opened_values.main[0][0].local[0] += Challenge::ONE;
+    proof.core.per_air[0].degree = 3;
+    let decoded = Proof::<SC>::from_json_value(proof.to_json_value()).unwrap();
+    assert_eq!(decoded.to_bytes(), proof.to_bytes());
+}
+
+/// The JSON of [tiny_proof] is the snapshot in the fixtures. A change of the snapshot is a change
+/// of the schema: bump [PROOF_JSON_SCHEMA_VERSION] and add a snapshot instead of updating this
+/// one.
+#[test]
+fn test_proof_json_snapshot() {
+    let proof = tiny_proof();
+    let json = proof.to_json_value();
+    assert_eq!(json["schema_version"], PROOF_JSON_SCHEMA_VERSION);
+    assert_eq!(
+        keys(&json),
+        [
+            "absent_air_ids",
+            "airs",
+            "challenge_phase_proof",
+            "commitments",
+            "fri",
+            "opened_values",
+            "schema_version",
+        ]
+    );
+    assert_eq!(
+        keys(&json["commitments"]),
+        ["after_challenge", "custom", "main_trace", "quotient"]
+    );
+    assert_eq!(
+        keys(&json["airs"][0]),
+        [
+            "air_id",
+            "exposed_values",
+            "height",
+            "log_height",
+            "public_values"
+        ]
+    );
+    assert_eq!(json["airs"][0]["height"], 4);
+    assert_eq!(json["airs"][0]["log_height"], 2);
+    assert_eq!(
+        json["airs"][0]["public_values"][2],
+        get_fib_number(4).to_string()
+    );
+    assert_eq!(
+        keys(&json["opened_values"]),
+        [
+            "after_challenge",
+            "custom",
+            "main",
+            "preprocessed",
+            "quotient"
+        ]
+    );
+    assert_eq!(
+        keys(&json["opened_values"]["main"][0][0]),
+        ["local", "next", "rotated"]
+    );
+    assert_eq!(
+        keys(&json["fri"]),
+        [
+            "commit_phase_commits",
+            "final_poly",
+            "pow_witness",
+            "queries"
+        ]
+    );
+    let query = &json["fri"]["queries"][0];
+    assert_eq!(keys(query), ["commit_phase_openings", "input_openings"]);
+    assert_eq!(
+        keys(&query["input_openings"][0]),
+        ["merkle_path", "opened_values"]
+    );
+    assert_eq!(
+        keys(&query["commit_phase_openings"][0]),
+        ["merkle_path", "sibling_value"]
+    );
+
+    let snapshot = proof.to_json();
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(format!(
+        "tests/fixtures/proof_json_v{PROOF_JSON_SCHEMA_VERSION}.json"
+    ));
+    let golden = fs::read_to_string(&path)
+        .unwrap_or_else(|err| panic!("failed to read fixture {}: {err}", path.display()));
+    assert_eq!(snapshot, golden);
+    let decoded = Proof::<SC>::from_json(&golden).unwrap();
+    assert_eq!(decoded.to_bytes(), proof.to_bytes());
+}
+
+#[test]
+fn test_proof_json_rejects_invalid() {
+    let json = tiny_proof().to_json_value();
+    let decode_with = |edit: &dyn Fn(&mut Value)| {
+        let mut json = json.clone();
+        edit(&mut json);
+        Proof::<SC>::from_json_value(json).err()
+    };
+
+    assert_eq!(
+        decode_with(&|json| json["schema_version"] = (PROOF_JSON_SCHEMA_VERSION + 1).into()),
+        Some(ProofJsonError::UnsupportedSchemaVersion(
+            PROOF_JSON_SCHEMA_VERSION + 1
+        ))
+    );
+    assert!(matches!(
+        decode_with(&|json| json["fri"]["extra"] = Value::Null),
+        Some(ProofJsonError::Json(_))
+    ));
+    // The BabyBear modulus is not a canonical field element, nor is a leading zero.
+    for value in ["2013265921", "01", "+1", "-1"] {
+        assert_eq!(
+            decode_with(&|json| json["airs"][0]["public_values"][0] = value.into()),
+            Some(ProofJsonError::InvalidFieldElement(value.to_string()))
+        );
+    }
+    assert_eq!(
+        decode_with(&|json| {
+            json["fri"]["final_poly"][0].as_array_mut().unwrap().pop();
+        }),
+        Some(ProofJsonError::InvalidExtensionElement(3))
+    );
+    // Digests of fewer or more words than DIGEST_WIDTH, and a word that is not canonical.
+    assert_eq!(
+        decode_with(&|json| {
+            json["commitments"]["quotient"]
+                .as_array_mut()
+                .unwrap()
+                .pop();
+        }),
+        Some(ProofJsonError::InvalidDigest(DIGEST_WIDTH - 1))
+    );
+    assert_eq!(
+        decode_with(&|json| {
+            json["fri"]["queries"][0]["input_openings"][0]["merkle_path"][0]
+                .as_array_mut()
+                .unwrap()
+                .push("0".into());
+        }),
+        Some(ProofJsonError::InvalidDigest(DIGEST_WIDTH + 1))
+    );
+    assert_eq!(
+        decode_with(&|json| json["fri"]["commit_phase_commits"][0][0] = "2013265921".into()),
+        Some(ProofJsonError::InvalidFieldElement(
+            "2013265921".to_string()
+        ))
+    );
+}
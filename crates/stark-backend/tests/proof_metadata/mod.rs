@@ -0,0 +1,109 @@
+use openvm_stark_backend::{
+    engine::StarkEngine,
+    p3_field::FieldAlgebra,
+    proof::ProofMetadata,
+    prover::types::{AirProofInput, ProofInput},
+};
+use openvm_stark_sdk::{
+    config::baby_bear_poseidon2::{default_engine, BabyBearPoseidon2Config},
+    dummy_airs::fib_lookup,
+    proof_serde::{read_proof_prefix, ProofSerde, ProofSerdeError},
+};
+use p3_baby_bear::BabyBear;
+use p3_matrix::dense::RowMajorMatrix;
+
+use crate::get_fib_number;
+
+type SC = BabyBearPoseidon2Config;
+type Val = BabyBear;
+
+const LOG_FIB_HEIGHT: usize = 5;
+const LOG_MESSAGES_HEIGHT: usize = 3;
+
+#[test]
+fn test_proof_metadata() {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    fib_lookup::add_airs(&mut keygen_builder);
+    let pk = keygen_builder.generate_pk();
+
+    let n = 1 << LOG_FIB_HEIGHT;
+    let pis = [0, 1, get_fib_number(n)].map(Val::from_canonical_u32);
+    // | count | field |, the same messages for the sender and the receiver.
+    let messages = RowMajorMatrix::new(
+        (0..1 << LOG_MESSAGES_HEIGHT)
+            .flat_map(|i| [1, 2 * i + 1])
+            .map(Val::from_canonical_u32)
+            .collect(),
+        2,
+    );
+    let input = ProofInput::new(vec![
+        (0, fib_lookup::fib_input(n)),
+        (1, AirProofInput::simple_no_pis(messages.clone())),
+        (2, AirProofInput::simple_no_pis(messages)),
+    ]);
+    let proof = engine.prove(&pk, input);
+
+    assert_eq!(
+        proof.public_values().collect::<Vec<_>>(),
+        vec![(0, &pis[..]), (1, &[][..]), (2, &[][..])]
+    );
+    assert_eq!(
+        proof.log_trace_heights(),
+        vec![
+            (0, LOG_FIB_HEIGHT),
+            (1, LOG_MESSAGES_HEIGHT),
+            (2, LOG_MESSAGES_HEIGHT)
+        ]
+    );
+    assert_eq!(
+        bincode::serialize(proof.commitments()).unwrap(),
+        bincode::serialize(&proof.commitments).unwrap()
+    );
+    assert_eq!(
+        proof.metadata(),
+        ProofMetadata {
+            air_ids: vec![0, 1, 2],
+            trace_heights: vec![n, 1 << LOG_MESSAGES_HEIGHT, 1 << LOG_MESSAGES_HEIGHT],
+            absent_air_ids: vec![],
+            num_public_values: vec![3, 0, 0],
+            num_main_commitments: 1,
+            num_after_challenge_commitments: 1,
+            num_custom_commitments: 0,
+        }
+    );
+    // The accessors do not verify the proof.
+    let mut invalid = proof.clone();
+    invalid.core.per_air[0].public_values[2] += Val::ONE;
+    assert!(engine.verify(&pk.get_vk(), &invalid).is_err());
+    assert_eq!(
+        invalid.public_values().next().unwrap().1[2],
+        pis[2] + Val::ONE
+    );
+
+    // The claims come first in the serialized proof, before the opening proof.
+    let bytes = proof.to_bytes();
+    let prefix = read_proof_prefix::<SC>(&bytes[..bytes.len() / 2]).unwrap();
+    assert_eq!(
+        prefix.air_heights,
+        vec![
+            (0, n),
+            (1, 1 << LOG_MESSAGES_HEIGHT),
+            (2, 1 << LOG_MESSAGES_HEIGHT)
+        ]
+    );
+    assert_eq!(prefix.public_values, vec![pis.to_vec(), vec![], vec![]]);
+    assert_eq!(
+        bincode::serialize(&prefix.commitments).unwrap(),
+        bincode::serialize(&proof.commitments).unwrap()
+    );
+    assert_eq!(
+        read_proof_prefix::<SC>(&bytes[..8]).err(),
+        Some(ProofSerdeError::UnexpectedEnd)
+    );
+
+    let breakdown = proof.size_breakdown();
+    assert_eq!(breakdown.total(), bincode::serialize(&proof).unwrap().len());
+    assert!(breakdown.pcs_proof > breakdown.opened_values);
+    assert_eq!(breakdown.absent_air_ids, 8);
+}
@@ -0,0 +1,83 @@
+use std::time::Duration;
+
+use openvm_stark_backend::{
+    p3_field::FieldAlgebra,
+    prover::{
+        metrics::Timing,
+        types::{AirProofInput, ProofInput},
+    },
+};
+use openvm_stark_sdk::{
+    config::baby_bear_poseidon2::default_engine, dummy_airs::fib_lookup, engine::StarkEngine,
+};
+use p3_baby_bear::BabyBear;
+use p3_matrix::dense::RowMajorMatrix;
+
+type Val = BabyBear;
+
+const LOG_HEIGHT: usize = 12;
+
+#[test]
+fn test_proof_metrics() {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    fib_lookup::add_airs(&mut keygen_builder);
+    let pk = keygen_builder.generate_pk();
+
+    // | count | field |, the same messages for the sender and the receiver.
+    let messages = RowMajorMatrix::new(
+        (0..1 << LOG_HEIGHT)
+            .flat_map(|i| [1, 3 * i + 5])
+            .map(Val::from_canonical_u32)
+            .collect(),
+        2,
+    );
+    let input = ProofInput::new(vec![
+        (0, fib_lookup::fib_input(1 << LOG_HEIGHT)),
+        (1, AirProofInput::simple_no_pis(messages.clone())),
+        (2, AirProofInput::simple_no_pis(messages)),
+    ]);
+    let expected = engine.prove(&pk, input.clone());
+    let (proof, metrics) = engine.prove_with_metrics(&pk, input);
+    assert_eq!(
+        bincode::serialize(&proof).unwrap(),
+        bincode::serialize(&expected).unwrap()
+    );
+
+    let parts = [
+        metrics.main_trace_commit,
+        metrics.after_challenge_trace,
+        metrics.quotient_evaluation,
+        metrics.quotient_commit,
+        metrics.opening,
+    ];
+    // Wall clocks are not precise enough to compare the sum of the parts with the total, so
+    // only their structure is checked: every part is measured and lies within the proof.
+    for Timing { wall, cpu } in parts.iter().chain([&metrics.total]) {
+        assert!(!wall.is_zero());
+        assert!(*wall <= metrics.total.wall, "{metrics:?}");
+        if cfg!(unix) {
+            assert!(!cpu.unwrap().is_zero());
+            assert!(cpu.unwrap() <= metrics.total.cpu.unwrap(), "{metrics:?}");
+        }
+    }
+    let sum = parts.iter().map(|timing| timing.wall).sum::<Duration>();
+    assert!(sum <= metrics.total.wall, "{metrics:?}");
+
+    let air_names = metrics
+        .quotient_evaluation_per_air
+        .iter()
+        .map(|(air_name, _)| air_name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        air_names,
+        ["FibonacciAir", "DummyInteractionAir", "DummyInteractionAir"]
+    );
+    let per_air = metrics
+        .quotient_evaluation_per_air
+        .iter()
+        .map(|(_, wall)| *wall)
+        .sum::<Duration>();
+    assert!(per_air <= metrics.quotient_evaluation.wall);
+    serde_json::to_string(&metrics).unwrap();
+}
@@ -0,0 +1,580 @@
+use std::{fs, ops::Range, path::Path, sync::Arc};
+
+use openvm_stark_backend::{
+    config::StarkGenericConfig,
+    keygen::types::{MultiStarkProvingKey, MultiStarkVerifyingKey},
+    p3_field::{FieldAlgebra, FieldExtensionAlgebra, PrimeField32, PrimeField64},
+    proof::Proof,
+    prover::types::{AirProofInput, ProofInput},
+};
+use openvm_stark_sdk::{
+    config::{
+        baby_bear_poseidon2::{
+            default_engine, BabyBearPoseidon2Config, BabyBearPoseidon2GkrConfig,
+        },
+        goldilocks_poseidon::{self, GoldilocksPoseidonConfig},
+        koala_bear_poseidon2::{self, KoalaBearPoseidon2Config},
+    },
+    dummy_airs::{
+        fib_air::{air::FibonacciAir, trace::generate_trace_rows},
+        fib_lookup,
+    },
+    engine::StarkEngine,
+    key_serde::{config_name, KeySerde},
+    proof_compression::CompressedProofSerde,
+    proof_serde::{ProofSerde, ProofSerdeError, PROOF_FORMAT_VERSION, PROOF_MAGIC},
+};
+use p3_baby_bear::BabyBear;
+use p3_goldilocks::Goldilocks;
+use p3_koala_bear::KoalaBear;
+use p3_matrix::dense::RowMajorMatrix;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::get_fib_number;
+
+type SC = BabyBearPoseidon2Config;
+type Val = BabyBear;
+type Challenge = <SC as StarkGenericConfig>::Challenge;
+
+const N: usize = 16;
+
+/// A Fibonacci AIR, and an AIR sending on bus 0 what another AIR receives.
+pub(crate) fn keygen() -> MultiStarkProvingKey<SC> {
+    let mut keygen_builder = default_engine().keygen_builder();
+    fib_lookup::add_airs(&mut keygen_builder);
+    keygen_builder.generate_pk()
+}
+
+pub(crate) fn prove(pk: &MultiStarkProvingKey<SC>) -> Proof<SC> {
+    // | count | field |
+    let interaction_trace = || {
+        AirProofInput::simple_no_pis(RowMajorMatrix::new(
+            (0..N as u32)
+                .flat_map(|i| [i % 3, i])
+                .map(Val::from_canonical_u32)
+                .collect(),
+            2,
+        ))
+    };
+    let fib_pis = [0, 1, get_fib_number(N)]
+        .map(Val::from_canonical_u32)
+        .to_vec();
+    default_engine().prove_deterministic(
+        pk,
+        ProofInput::new(vec![
+            (
+                0,
+                AirProofInput::simple(generate_trace_rows::<Val>(0, 1, N), fib_pis),
+            ),
+            (1, interaction_trace()),
+            (2, interaction_trace()),
+        ]),
+    )
+}
+
+/// A proof of random traces of height `n`, with the same messages sent and received.
+fn prove_random(pk: &MultiStarkProvingKey<SC>, rng: &mut StdRng, n: usize) -> Proof<SC> {
+    let (a, b) = (rng.gen_range(0..1 << 30), rng.gen_range(0..1 << 30));
+    let fib_trace = generate_trace_rows::<Val>(a, b, n);
+    let fib_pis = vec![
+        Val::from_canonical_u32(a),
+        Val::from_canonical_u32(b),
+        fib_trace.values[fib_trace.values.len() - 1],
+    ];
+    // | count | field |
+    let interaction_trace = RowMajorMatrix::new(
+        (0..n)
+            .flat_map(|_| [rng.gen_range(0..3), rng.gen_range(0..1 << 30)])
+            .map(Val::from_canonical_u32)
+            .collect(),
+        2,
+    );
+    default_engine().prove(
+        pk,
+        ProofInput::new(vec![
+            (0, AirProofInput::simple(fib_trace, fib_pis)),
+            (1, AirProofInput::simple_no_pis(interaction_trace.clone())),
+            (2, AirProofInput::simple_no_pis(interaction_trace)),
+        ]),
+    )
+}
+
+/// Length of the header of a serialized proof.
+fn header_len() -> usize {
+    PROOF_MAGIC.len() + 8 + config_name::<SC>().len()
+}
+
+/// The range of each section of a proof of format version 2 to 4 with a header of `header_len`
+/// bytes, without its length.
+fn section_ranges(bytes: &[u8], header_len: usize) -> Vec<Range<usize>> {
+    let mut start = header_len;
+    let mut ranges = vec![];
+    while start < bytes.len() {
+        let (mut len, mut shift) = (0, 0);
+        loop {
+            let byte = bytes[start];
+            start += 1;
+            len |= ((byte & 0x7f) as usize) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+        ranges.push(start..start + len);
+        start += len;
+    }
+    ranges
+}
+
+#[test]
+fn test_proof_serde_round_trip() {
+    let pk = keygen();
+    let proof = prove(&pk);
+    let bytes = proof.to_bytes();
+    let decoded = Proof::<SC>::from_bytes(&bytes).unwrap();
+    assert_eq!(decoded.to_bytes(), bytes);
+    default_engine()
+        .verify(&pk.get_vk(), &decoded)
+        .expect("Verification failed");
+}
+
+#[test]
+fn test_proof_serde_round_trip_random_proofs() {
+    let pk = keygen();
+    let vk = pk.get_vk();
+    let mut rng = StdRng::seed_from_u64(0);
+    for n in [4, 8, 16, 64, 256] {
+        let proof = prove_random(&pk, &mut rng, n);
+        let bytes = proof.to_bytes();
+        let decoded = Proof::<SC>::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.to_bytes(), bytes, "n = {n}");
+        assert_eq!(
+            bincode::serialize(&decoded).unwrap(),
+            bincode::serialize(&proof).unwrap(),
+            "n = {n}"
+        );
+        default_engine()
+            .verify(&vk, &decoded)
+            .expect("Verification failed");
+    }
+}
+
+#[test]
+fn test_proof_serde_smaller_than_bincode() {
+    let proof = prove(&keygen());
+    let payload_len = proof.to_bytes().len() - header_len();
+    let bincode_len = bincode::serialized_size(&proof).unwrap() as usize;
+    assert!(
+        payload_len < bincode_len,
+        "payload of {payload_len} bytes, bincode encoding of {bincode_len} bytes"
+    );
+}
+
+/// Checks that the fixture of format `version`, a proof of [prove] and its verifying key
+/// serialized by the release writing that version, decodes into a proof that verifies. Archived
+/// proofs must keep verifying after the format changes: add a decoder for the new layout instead
+/// of updating the fixtures.
+fn check_fixture_verifies(version: u32) {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let read = |name: String| {
+        fs::read(dir.join(&name))
+            .unwrap_or_else(|err| panic!("failed to read fixture {name}: {err}"))
+    };
+    let proof_bytes = read(format!("proof_v{version}.bin"));
+    assert_eq!(proof_bytes[8..12], version.to_le_bytes());
+    let proof = Proof::<SC>::from_bytes(&proof_bytes).unwrap();
+    let vk = MultiStarkVerifyingKey::<SC>::from_bytes(&read(format!("proof_v{version}_vk.bin")))
+        .unwrap();
+    default_engine()
+        .verify(&vk, &proof)
+        .expect("Verification failed");
+}
+
+#[test]
+fn test_proof_serde_v1_fixture_verifies() {
+    check_fixture_verifies(1);
+}
+
+#[test]
+fn test_proof_serde_v2_fixture_verifies() {
+    check_fixture_verifies(2);
+}
+
+#[test]
+fn test_proof_serde_v3_fixture_verifies() {
+    check_fixture_verifies(3);
+}
+
+#[test]
+fn test_proof_serde_v4_fixture_verifies() {
+    assert_eq!(PROOF_FORMAT_VERSION, 4);
+    check_fixture_verifies(4);
+}
+
+#[test]
+fn test_proof_serde_rejected() {
+    let bytes = prove(&keygen()).to_bytes();
+    let decode = |bytes: &[u8]| Proof::<SC>::from_bytes(bytes).err();
+
+    for version in [0, PROOF_FORMAT_VERSION + 1, u32::MAX] {
+        let mut corrupted = bytes.clone();
+        corrupted[8..12].copy_from_slice(&version.to_le_bytes());
+        let err = decode(&corrupted).unwrap();
+        assert_eq!(
+            err,
+            ProofSerdeError::UnsupportedVersion {
+                version,
+                supported: vec![1, 2, 3, 4],
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "unsupported proof format version {version}, supported versions are [1, 2, 3, 4]"
+            )
+        );
+    }
+    let mut wrong_magic = bytes.clone();
+    wrong_magic[..8].copy_from_slice(b"NOPROOF!");
+    assert_eq!(decode(&wrong_magic), Some(ProofSerdeError::WrongMagic));
+    assert_eq!(
+        Proof::<BabyBearPoseidon2GkrConfig>::from_bytes(&bytes).err(),
+        Some(ProofSerdeError::ConfigMismatch {
+            expected: config_name::<BabyBearPoseidon2GkrConfig>().to_string(),
+            found: config_name::<SC>().to_string(),
+        })
+    );
+    assert_eq!(
+        decode(&bytes[..bytes.len() - 1]),
+        Some(ProofSerdeError::UnexpectedEnd)
+    );
+    assert_eq!(decode(&bytes[..10]), Some(ProofSerdeError::UnexpectedEnd));
+
+    let sections = section_ranges(&bytes, header_len());
+    assert_eq!(sections.len(), 8);
+    assert_eq!(sections[7].end, bytes.len());
+    // A byte after the ids and heights of the AIRs, within the length of their section.
+    let airs = sections[1].clone();
+    assert!(airs.len() < 0x7f);
+    let mut padded = bytes[..airs.end].to_vec();
+    padded[airs.start - 1] += 1;
+    padded.push(0);
+    padded.extend_from_slice(&bytes[airs.end..]);
+    assert_eq!(
+        decode(&padded),
+        Some(ProofSerdeError::SectionTrailingBytes {
+            section: "AIRs",
+            len: 1,
+        })
+    );
+    // The first public value of the Fibonacci AIR, above the order of the field.
+    let public_values = sections[2].clone();
+    assert_eq!(bytes[public_values.start], 3);
+    let mut non_canonical = bytes.clone();
+    non_canonical[public_values.start + 1..public_values.start + 5].fill(0xff);
+    assert_eq!(
+        decode(&non_canonical),
+        Some(ProofSerdeError::NonCanonicalFieldElement {
+            section: "public values",
+            index: 1,
+        })
+    );
+    // A varint of more than 64 bits.
+    let mut overflow = bytes[..header_len()].to_vec();
+    overflow.extend_from_slice(&[0xff; 10]);
+    overflow.push(0x01);
+    assert_eq!(decode(&overflow), Some(ProofSerdeError::VarintOverflow));
+    // The length of the commitments section padded with a zero byte, which would decode to the
+    // same length.
+    let len = bytes[header_len()];
+    assert!(len < 0x80);
+    let mut padded = bytes[..header_len()].to_vec();
+    padded.extend_from_slice(&[len | 0x80, 0]);
+    padded.extend_from_slice(&bytes[header_len() + 1..]);
+    assert_eq!(decode(&padded), Some(ProofSerdeError::NonCanonicalVarint));
+    // The number of AIRs, padded within its section.
+    let mut padded = bytes[..airs.start].to_vec();
+    padded[airs.start - 1] += 1;
+    padded.extend_from_slice(&[bytes[airs.start] | 0x80, 0]);
+    padded.extend_from_slice(&bytes[airs.start + 1..]);
+    assert_eq!(decode(&padded), Some(ProofSerdeError::NonCanonicalVarint));
+
+    // Corrupted bytes are rejected or decode into another proof, without panicking.
+    let mut rng = StdRng::seed_from_u64(0);
+    for _ in 0..1000 {
+        let mut corrupted = bytes.clone();
+        let i = rng.gen_range(header_len()..bytes.len());
+        corrupted[i] ^= 1 << rng.gen_range(0..8);
+        let _ = decode(&corrupted);
+    }
+}
+
+/// The proof with the bytes at `offset` of its section `section` replaced by `value`, decoded.
+fn decode_replaced<C: StarkGenericConfig>(
+    bytes: &[u8],
+    sections: &[Range<usize>],
+    section: usize,
+    offset: usize,
+    value: &[u8],
+) -> Option<ProofSerdeError>
+where
+    Proof<C>: ProofSerde,
+{
+    let mut replaced = bytes.to_vec();
+    let start = sections[section].start + offset;
+    replaced[start..start + value.len()].copy_from_slice(value);
+    Proof::<C>::from_bytes(&replaced).err()
+}
+
+/// Offset of the first byte of `value + order` differing from `value`, in little-endian, where
+/// `value + order` is the non-canonical encoding of `value`.
+fn first_non_canonical_byte(value: u32, order: u32) -> usize {
+    let (canonical, non_canonical) = (value.to_le_bytes(), (value + order).to_le_bytes());
+    (0..4).find(|&i| canonical[i] != non_canonical[i]).unwrap()
+}
+
+#[test]
+fn test_proof_serde_rejects_non_canonical_field_elements() {
+    let proof = prove(&keygen());
+    let bytes = proof.to_bytes();
+    let sections = section_ranges(&bytes, header_len());
+    let decode = |section, offset, value: &[u8]| {
+        decode_replaced::<SC>(&bytes, &sections, section, offset, value)
+    };
+    let non_canonical =
+        |section, index| Some(ProofSerdeError::NonCanonicalFieldElement { section, index });
+    let order = Val::ORDER_U64 as u32;
+
+    // The first public value of the Fibonacci AIR, 0, after the number of public values.
+    assert_eq!(bytes[sections[2].start], 3);
+    for value in [order, order + 1, u32::MAX] {
+        assert_eq!(
+            decode(2, 1, &value.to_le_bytes()),
+            non_canonical("public values", 1)
+        );
+    }
+    assert_eq!(decode(2, 1, &(order - 1).to_le_bytes()), None);
+
+    // The second coefficient of the first opened value of the main trace of the Fibonacci AIR,
+    // after the number of preprocessed traces, of main trace commitments, of AIRs and of values.
+    let values = &proof.core.opened_values;
+    assert!(values.preprocessed.is_empty());
+    assert_eq!((values.main.len(), values.main[0].len()), (1, 3));
+    assert_eq!(values.main[0][0].local.len(), 2);
+    let coeff =
+        <Challenge as FieldExtensionAlgebra<Val>>::as_base_slice(&values.main[0][0].local[0])[1]
+            .as_canonical_u32();
+    for value in [order, coeff + order, u32::MAX] {
+        assert_eq!(
+            decode(4, 8, &value.to_le_bytes()),
+            non_canonical("opened values", 8)
+        );
+    }
+
+    // The first word of the main trace commitment, after the number of main trace commitments,
+    // in its bincode encoding, where `value + order` would decode to `value`.
+    let word = u32::from_le_bytes(bytes[sections[0].start + 8..][..4].try_into().unwrap());
+    assert_eq!(
+        decode(0, 8, &(word + order).to_le_bytes()),
+        non_canonical("commitments", 8 + first_non_canonical_byte(word, order))
+    );
+    // The proof of work witness of FRI, at the end of the opening proof.
+    let opening_len = sections[7].len();
+    let witness = u32::from_le_bytes(bytes[bytes.len() - 4..].try_into().unwrap());
+    assert_eq!(
+        decode(7, opening_len - 4, &(witness + order).to_le_bytes()),
+        non_canonical("opening proof", opening_len - 4)
+    );
+    // The byte marking the presence of the partial proof of the challenge phase.
+    assert_eq!(bytes[sections[5].start], 1);
+    assert_eq!(
+        decode(5, 0, &[2]),
+        Some(ProofSerdeError::Payload(
+            "invalid option flag 2".to_string()
+        ))
+    );
+}
+
+#[test]
+fn test_proof_serde_rejects_non_canonical_goldilocks_elements() {
+    type GoldilocksSC = GoldilocksPoseidonConfig;
+    let engine = goldilocks_poseidon::default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(Arc::new(FibonacciAir));
+    let pk = keygen_builder.generate_pk();
+    let pis = [0, 1, get_fib_number(N)]
+        .map(Goldilocks::from_canonical_u32)
+        .to_vec();
+    let proof = engine.prove(
+        &pk,
+        ProofInput::new(vec![(
+            0,
+            AirProofInput::simple(generate_trace_rows::<Goldilocks>(0, 1, N), pis),
+        )]),
+    );
+    let bytes = proof.to_bytes();
+    let header_len = PROOF_MAGIC.len() + 8 + config_name::<GoldilocksSC>().len();
+    let sections = section_ranges(&bytes, header_len);
+
+    // The first public value of the Fibonacci AIR, 0, after the number of public values.
+    for value in [Goldilocks::ORDER_U64, u64::MAX] {
+        assert_eq!(
+            decode_replaced::<GoldilocksSC>(&bytes, &sections, 2, 1, &value.to_le_bytes()),
+            Some(ProofSerdeError::NonCanonicalFieldElement {
+                section: "public values",
+                index: 1,
+            })
+        );
+    }
+    let value = (Goldilocks::ORDER_U64 - 1).to_le_bytes();
+    assert_eq!(
+        decode_replaced::<GoldilocksSC>(&bytes, &sections, 2, 1, &value),
+        None
+    );
+}
+
+#[test]
+fn test_proof_serde_rejects_non_canonical_koala_bear_elements() {
+    type KoalaBearSC = KoalaBearPoseidon2Config;
+    let engine = koala_bear_poseidon2::default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(Arc::new(FibonacciAir));
+    let pk = keygen_builder.generate_pk();
+    let pis = [0, 1, get_fib_number(N)]
+        .map(KoalaBear::from_canonical_u32)
+        .to_vec();
+    let proof = engine.prove(
+        &pk,
+        ProofInput::new(vec![(
+            0,
+            AirProofInput::simple(generate_trace_rows::<KoalaBear>(0, 1, N), pis),
+        )]),
+    );
+    let bytes = proof.to_bytes();
+    let header_len = PROOF_MAGIC.len() + 8 + config_name::<KoalaBearSC>().len();
+    let sections = section_ranges(&bytes, header_len);
+    let decode = |section, offset, value: &[u8]| {
+        decode_replaced::<KoalaBearSC>(&bytes, &sections, section, offset, value)
+    };
+    let non_canonical =
+        |section, index| Some(ProofSerdeError::NonCanonicalFieldElement { section, index });
+    let order = KoalaBear::ORDER_U64 as u32;
+
+    // The first public value of the Fibonacci AIR, 0, after the number of public values.
+    for value in [order, order + 1, u32::MAX] {
+        assert_eq!(
+            decode(2, 1, &value.to_le_bytes()),
+            non_canonical("public values", 1)
+        );
+    }
+    assert_eq!(decode(2, 1, &(order - 1).to_le_bytes()), None);
+
+    // The first word of the main trace commitment, after the number of main trace commitments,
+    // and the proof of work witness of FRI, at the end of the opening proof.
+    let word = u32::from_le_bytes(bytes[sections[0].start + 8..][..4].try_into().unwrap());
+    assert_eq!(
+        decode(0, 8, &(word + order).to_le_bytes()),
+        non_canonical("commitments", 8 + first_non_canonical_byte(word, order))
+    );
+    let opening_len = sections[7].len();
+    let witness = u32::from_le_bytes(bytes[bytes.len() - 4..].try_into().unwrap());
+    assert_eq!(
+        decode(7, opening_len - 4, &(witness + order).to_le_bytes()),
+        non_canonical("opening proof", opening_len - 4)
+    );
+}
+
+#[test]
+fn test_proof_serde_rejects_trailing_bytes() {
+    let pk = keygen();
+    let bytes = prove(&pk).to_bytes();
+    let mut rng = StdRng::seed_from_u64(0);
+    for len in [1, 4, 31, 1000] {
+        let mut padded = bytes.clone();
+        padded.extend((0..len).map(|_| rng.gen::<u8>()));
+        assert_eq!(
+            Proof::<SC>::from_bytes(&padded).err(),
+            Some(ProofSerdeError::TrailingBytes(len))
+        );
+        // The lenient decoder ignores the trailing bytes.
+        let proof = Proof::<SC>::from_bytes_lenient(&padded).unwrap();
+        assert_eq!(proof.to_bytes(), bytes);
+        default_engine()
+            .verify(&pk.get_vk(), &proof)
+            .expect("Verification failed");
+    }
+}
+
+#[test]
+fn test_compressed_proof_round_trip() {
+    let pk = keygen();
+    let vk = pk.get_vk();
+    let proof = prove(&pk);
+    let mut tampered = proof.clone();
+    tampered.core.opened_values.main[0][0].local[0] += Challenge::ONE;
+    for proof in [proof, tampered] {
+        let decoded = Proof::<SC>::from_compressed_bytes(&proof.to_compressed_bytes()).unwrap();
+        assert_eq!(decoded.to_bytes(), proof.to_bytes());
+        assert_eq!(
+            default_engine().verify(&vk, &decoded),
+            default_engine().verify(&vk, &proof)
+        );
+    }
+}
+
+#[test]
+fn test_compressed_proof_smaller() {
+    let proof = prove(&keygen());
+    let len = proof.to_bytes().len();
+    let compressed_len = proof.to_compressed_bytes().len();
+    assert!(compressed_len < len);
+}
+
+#[test]
+fn test_compressed_proof_corrupted_rejected() {
+    let pk = keygen();
+    let vk = pk.get_vk();
+    let compressed = prove(&pk).to_compressed_bytes();
+    let decode = |bytes: &[u8]| Proof::<SC>::from_compressed_bytes(bytes);
+
+    let mut wrong_magic = compressed.clone();
+    wrong_magic[..8].copy_from_slice(b"NOPROOF!");
+    assert_eq!(
+        decode(&wrong_magic).err(),
+        Some(ProofSerdeError::WrongMagic)
+    );
+    let len = compressed.len();
+    assert_eq!(
+        decode(&compressed[..len - 1]).err(),
+        Some(ProofSerdeError::UnexpectedEnd)
+    );
+    let mut padded = compressed.clone();
+    padded.push(0);
+    assert_eq!(
+        decode(&padded).err(),
+        Some(ProofSerdeError::TrailingBytes(1))
+    );
+    // The last byte is the index of the last node of the last path, in the smallest tree of the
+    // commit phase.
+    assert!(compressed[len - 2] < 0x80);
+    let mut invalid_node = compressed.clone();
+    invalid_node[len - 1] = 0x7f;
+    assert_eq!(
+        decode(&invalid_node).err(),
+        Some(ProofSerdeError::InvalidMerkleNode(0x7f))
+    );
+
+    // A corrupted byte is rejected, or decodes into a proof that does not verify.
+    let mut rng = StdRng::seed_from_u64(0);
+    for _ in 0..100 {
+        let mut corrupted = compressed.clone();
+        let i = rng.gen_range(0..len);
+        corrupted[i] ^= 1 << rng.gen_range(0..8);
+        if let Ok(proof) = decode(&corrupted) {
+            assert!(
+                default_engine().verify(&vk, &proof).is_err(),
+                "corrupted byte {i}"
+            );
+        }
+    }
+}
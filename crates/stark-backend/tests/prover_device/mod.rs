@@ -0,0 +1,164 @@
+use std::sync::{Arc, Mutex};
+
+use openvm_stark_backend::{
+    config::{Com, PcsProof, RapPhaseSeqPartialProof, StarkGenericConfig, Val},
+    interaction::public_lookup::PublicLookupTable,
+    keygen::types::MultiStarkProvingKey,
+    proof::OpeningProof,
+    prover::{
+        cpu::{CpuBackend, CpuDevice, PcsData},
+        hal::{OpeningProver, ProverDevice, QuotientCommitter, RapPartialProver, TraceCommitter},
+        types::{
+            DeviceStarkProvingKey, PairView, ProofInput, ProverDataAfterRapPhases,
+            SingleCommitPreimage,
+        },
+        ProvingError, ProvingOptions,
+    },
+};
+use openvm_stark_sdk::{
+    config::baby_bear_poseidon2::{default_engine, BabyBearPoseidon2Config},
+    dummy_airs::fib_lookup,
+    engine::StarkEngine,
+};
+use p3_matrix::dense::RowMajorMatrix;
+
+type SC = BabyBearPoseidon2Config;
+
+const N: usize = 16;
+
+/// A device that records the calls of the prover and delegates them to a [CpuDevice].
+struct RecordingDevice<'a, SC> {
+    inner: CpuDevice<'a, SC>,
+    calls: &'a Mutex<Vec<&'static str>>,
+}
+
+impl<SC> RecordingDevice<'_, SC> {
+    fn record(&self, call: &'static str) {
+        self.calls.lock().unwrap().push(call);
+    }
+}
+
+impl<SC: StarkGenericConfig> ProverDevice<CpuBackend<SC>> for RecordingDevice<'_, SC> {}
+
+impl<SC: StarkGenericConfig> TraceCommitter<CpuBackend<SC>> for RecordingDevice<'_, SC> {
+    fn commit(&self, traces: &[Arc<RowMajorMatrix<Val<SC>>>]) -> (Com<SC>, PcsData<SC>) {
+        self.record("commit");
+        self.inner.commit(traces)
+    }
+}
+
+impl<SC: StarkGenericConfig> RapPartialProver<CpuBackend<SC>> for RecordingDevice<'_, SC> {
+    fn partially_prove<'a>(
+        &self,
+        challenger: &mut SC::Challenger,
+        pk_views: &[DeviceStarkProvingKey<'a, CpuBackend<SC>>],
+        public_lookup_tables: &[PublicLookupTable<Val<SC>>],
+        trace_views: Vec<PairView<&'a Arc<RowMajorMatrix<Val<SC>>>, Val<SC>>>,
+    ) -> (
+        Option<RapPhaseSeqPartialProof<SC>>,
+        ProverDataAfterRapPhases<CpuBackend<SC>>,
+    ) {
+        self.record("partially_prove");
+        self.inner
+            .partially_prove(challenger, pk_views, public_lookup_tables, trace_views)
+    }
+}
+
+impl<SC: StarkGenericConfig> QuotientCommitter<CpuBackend<SC>> for RecordingDevice<'_, SC> {
+    fn eval_and_commit_quotient(
+        &self,
+        challenger: &mut SC::Challenger,
+        pk_views: &[DeviceStarkProvingKey<CpuBackend<SC>>],
+        public_values: &[Vec<Val<SC>>],
+        cached_views_per_air: &[Vec<SingleCommitPreimage<(), &PcsData<SC>>>],
+        common_main_views_per_air: &[Option<SingleCommitPreimage<(), &PcsData<SC>>>],
+        custom_views_per_air: &[Vec<SingleCommitPreimage<(), &PcsData<SC>>>],
+        prover_data_after: &ProverDataAfterRapPhases<CpuBackend<SC>>,
+        options: &ProvingOptions,
+    ) -> Result<(Com<SC>, PcsData<SC>), ProvingError> {
+        self.record("eval_and_commit_quotient");
+        self.inner.eval_and_commit_quotient(
+            challenger,
+            pk_views,
+            public_values,
+            cached_views_per_air,
+            common_main_views_per_air,
+            custom_views_per_air,
+            prover_data_after,
+            options,
+        )
+    }
+}
+
+impl<SC: StarkGenericConfig> OpeningProver<CpuBackend<SC>> for RecordingDevice<'_, SC> {
+    fn open(
+        &self,
+        challenger: &mut SC::Challenger,
+        preprocessed: Vec<(&PcsData<SC>, &[usize])>,
+        main: Vec<(&PcsData<SC>, Vec<&[usize]>)>,
+        custom: Vec<(&PcsData<SC>, Vec<&[usize]>)>,
+        after_phase: Vec<PcsData<SC>>,
+        quotient_data: PcsData<SC>,
+        quotient_degrees: &[u8],
+    ) -> OpeningProof<PcsProof<SC>, SC::Challenge> {
+        self.record("open");
+        self.inner.open(
+            challenger,
+            preprocessed,
+            main,
+            custom,
+            after_phase,
+            quotient_data,
+            quotient_degrees,
+        )
+    }
+}
+
+/// A Fibonacci AIR, and an AIR sending on bus 0 what another AIR receives, with AIR ids 0, 1
+/// and 2.
+fn keygen() -> MultiStarkProvingKey<SC> {
+    let mut keygen_builder = default_engine().keygen_builder();
+    fib_lookup::add_airs(&mut keygen_builder);
+    keygen_builder.generate_pk()
+}
+
+fn proof_input() -> ProofInput<SC> {
+    fib_lookup::proof_input(N)
+}
+
+#[test]
+fn test_prover_device() {
+    let engine = default_engine();
+    let pk = keygen();
+    let calls = Mutex::new(vec![]);
+    let device = RecordingDevice {
+        inner: CpuDevice::new(engine.config()),
+        calls: &calls,
+    };
+    let proof = engine
+        .prove_with_device(
+            CpuBackend::default(),
+            device,
+            &pk,
+            proof_input(),
+            ProvingOptions::default(),
+        )
+        .unwrap();
+    assert_eq!(
+        calls.into_inner().unwrap(),
+        [
+            "commit",
+            "partially_prove",
+            "eval_and_commit_quotient",
+            "open"
+        ]
+    );
+    engine
+        .verify(&pk.get_vk(), &proof)
+        .expect("Verification failed");
+    // The device only changes where the proof is computed.
+    assert_eq!(
+        bincode::serialize(&proof).unwrap(),
+        bincode::serialize(&engine.prove(&pk, proof_input())).unwrap()
+    );
+}
@@ -0,0 +1,143 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+
+use openvm_stark_backend::{
+    keygen::types::MultiStarkProvingKey,
+    p3_field::FieldAlgebra,
+    prover::{
+        types::{AirProofInput, ProofInput},
+        ProvingError, ProvingOptions, ProvingPhase,
+    },
+};
+use openvm_stark_sdk::{
+    config::baby_bear_poseidon2::{default_engine, BabyBearPoseidon2Config},
+    dummy_airs::fib_air::{self, air::FibonacciAir},
+    engine::StarkEngine,
+};
+use p3_baby_bear::BabyBear;
+
+use crate::{
+    fib_selector_air::{self, air::FibonacciSelectorAir},
+    get_conditional_fib_number, get_fib_number,
+};
+
+type SC = BabyBearPoseidon2Config;
+type Val = BabyBear;
+
+const N: usize = 16;
+
+fn selectors() -> Vec<bool> {
+    (0..N).map(|i| i % 2 == 0).collect()
+}
+
+fn keygen() -> MultiStarkProvingKey<SC> {
+    let mut keygen_builder = default_engine().keygen_builder();
+    keygen_builder.add_air(Arc::new(FibonacciAir));
+    keygen_builder.add_air(Arc::new(FibonacciSelectorAir::new(selectors(), false)));
+    keygen_builder.generate_pk()
+}
+
+fn proof_input() -> ProofInput<SC> {
+    let sels = selectors();
+    ProofInput::new(vec![
+        (
+            0,
+            AirProofInput::simple(
+                fib_air::trace::generate_trace_rows::<Val>(0, 1, N),
+                [0, 1, get_fib_number(N)]
+                    .map(Val::from_canonical_u32)
+                    .to_vec(),
+            ),
+        ),
+        (
+            1,
+            AirProofInput::simple(
+                fib_selector_air::trace::generate_trace_rows::<Val>(0, 1, &sels),
+                [0, 1, get_conditional_fib_number(&sels)]
+                    .map(Val::from_canonical_u32)
+                    .to_vec(),
+            ),
+        ),
+    ])
+}
+
+/// The progress reported for a proof of the two AIRs.
+const PROGRESS: [(ProvingPhase, f32); 6] = [
+    (ProvingPhase::MainTraceCommit, 0.0),
+    (ProvingPhase::AfterChallengeTrace, 0.0),
+    (ProvingPhase::Quotient, 0.0),
+    (ProvingPhase::Quotient, 0.5),
+    (ProvingPhase::Quotient, 1.0),
+    (ProvingPhase::Opening, 0.0),
+];
+
+/// Options recording the reported progress, and setting `cancel` once `cancel_at` progress
+/// reports were made, if any.
+fn recording_options(
+    cancel_at: Option<usize>,
+) -> (ProvingOptions, Arc<Mutex<Vec<(ProvingPhase, f32)>>>) {
+    let progress = Arc::new(Mutex::new(vec![]));
+    let cancel = Arc::new(AtomicBool::new(false));
+    let options = ProvingOptions {
+        progress: Some(Box::new({
+            let progress = progress.clone();
+            let cancel = cancel.clone();
+            move |phase, fraction| {
+                let mut progress = progress.lock().unwrap();
+                progress.push((phase, fraction));
+                if Some(progress.len()) == cancel_at {
+                    cancel.store(true, Ordering::Relaxed);
+                }
+            }
+        })),
+        cancel: Some(cancel),
+        ..Default::default()
+    };
+    (options, progress)
+}
+
+#[test]
+fn test_proving_progress() {
+    let engine = default_engine();
+    let pk = keygen();
+    let (options, progress) = recording_options(None);
+    let proof = engine
+        .prove_with_options(&pk, proof_input(), options)
+        .unwrap();
+    assert_eq!(*progress.lock().unwrap(), PROGRESS);
+    engine
+        .verify(&pk.get_vk(), &proof)
+        .expect("Verification failed");
+    // Same proof as without options.
+    assert_eq!(
+        bincode::serialize(&proof).unwrap(),
+        bincode::serialize(&engine.prove(&pk, proof_input())).unwrap()
+    );
+}
+
+#[test]
+fn test_proving_cancellation() {
+    let engine = default_engine();
+    let pk = keygen();
+    // The prover stops at the first progress report after the flag is set.
+    for cancel_at in 1..=PROGRESS.len() {
+        let (options, progress) = recording_options(Some(cancel_at));
+        assert_eq!(
+            engine.prove_with_options(&pk, proof_input(), options).err(),
+            Some(ProvingError::Cancelled)
+        );
+        assert_eq!(*progress.lock().unwrap(), PROGRESS[..cancel_at]);
+    }
+
+    // Cancelled before the proof starts.
+    let options = ProvingOptions {
+        cancel: Some(Arc::new(AtomicBool::new(true))),
+        ..Default::default()
+    };
+    assert_eq!(
+        engine.prove_with_options(&pk, proof_input(), options).err(),
+        Some(ProvingError::Cancelled)
+    );
+}
@@ -0,0 +1,80 @@
+use std::sync::Arc;
+
+use openvm_stark_backend::{
+    p3_field::FieldAlgebra,
+    prover::types::{AirProofInput, ProofInput},
+    verifier::VerificationError,
+};
+use openvm_stark_sdk::{
+    config::baby_bear_poseidon2::default_engine,
+    dummy_airs::{
+        fib_air::{self, air::FibonacciAir},
+        interaction::dummy_interaction_air::DummyInteractionAir,
+    },
+    engine::StarkEngine,
+};
+use p3_baby_bear::BabyBear;
+
+use crate::{
+    fib_selector_air::{self, air::FibonacciSelectorAir},
+    get_conditional_fib_number, get_fib_number,
+};
+
+type Val = BabyBear;
+
+const N: usize = 16;
+
+#[test]
+fn test_pruned_vk() {
+    let engine = default_engine();
+    let sels = (0..N).map(|i| i % 3 == 0).collect::<Vec<_>>();
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(Arc::new(FibonacciAir));
+    keygen_builder.add_air(Arc::new(DummyInteractionAir::new(1, true, 0)));
+    keygen_builder.add_air(Arc::new(FibonacciSelectorAir::new(sels.clone(), false)));
+    let pk = keygen_builder.generate_pk();
+    let vk = pk.get_vk();
+
+    let fib_pis = [0, 1, get_fib_number(N)].map(Val::from_canonical_u32);
+    let sel_pis = [0, 1, get_conditional_fib_number(&sels)].map(Val::from_canonical_u32);
+    let proof = engine.prove(
+        &pk,
+        ProofInput::new(vec![
+            (
+                0,
+                AirProofInput::simple(
+                    fib_air::trace::generate_trace_rows::<Val>(0, 1, N),
+                    fib_pis.to_vec(),
+                ),
+            ),
+            (
+                2,
+                AirProofInput::simple(
+                    fib_selector_air::trace::generate_trace_rows::<Val>(0, 1, &sels),
+                    sel_pis.to_vec(),
+                ),
+            ),
+        ]),
+    );
+    engine.verify(&vk, &proof).expect("Verification failed");
+
+    let pvk = vk.prune(&[0, 2]);
+    assert_eq!(pvk.vk.per_air.len(), 2);
+    assert!(pvk.vk.per_air[1].preprocessed_data.is_some());
+    engine
+        .verify_pruned(&pvk, &proof)
+        .expect("Verification failed");
+
+    assert_eq!(
+        engine.verify_pruned(&vk.prune(&[0, 1]), &proof),
+        Err(VerificationError::AirSubsetMismatch {
+            expected: vec![0, 1],
+            found: vec![0, 2],
+        })
+    );
+    // A proof of a strict subset of the retained AIRs is rejected too.
+    assert!(matches!(
+        engine.verify_pruned(&vk.prune(&[0, 1, 2]), &proof),
+        Err(VerificationError::AirSubsetMismatch { .. })
+    ));
+}
@@ -0,0 +1,153 @@
+use std::sync::Arc;
+
+use openvm_stark_backend::{
+    config::Val,
+    interaction::{fri_log_up::FriLogUpError, public_lookup::PublicLookupTable},
+    keygen::{
+        types::{MultiStarkProvingKey, VkFeature, VkFeatureSet},
+        KeygenError,
+    },
+    p3_field::FieldAlgebra,
+    proof::Proof,
+    prover::types::{AirProofInput, ProofInput},
+    verifier::VerificationError,
+    AirRef,
+};
+use openvm_stark_sdk::{
+    config::baby_bear_poseidon2::{default_engine, BabyBearPoseidon2Config},
+    dummy_airs::interaction::dummy_interaction_air::DummyInteractionAir,
+    engine::StarkEngine,
+};
+use p3_matrix::dense::RowMajorMatrix;
+
+use crate::utils::to_field_vec;
+
+type SC = BabyBearPoseidon2Config;
+
+const BUS: usize = 0;
+const TABLE_SIZE: u32 = 16;
+
+/// Keygen of a sender of 1-field messages on `BUS`, which has a public table of the values below
+/// `TABLE_SIZE`.
+fn keygen() -> MultiStarkProvingKey<SC> {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(Arc::new(DummyInteractionAir::new(1, true, BUS)));
+    keygen_builder.add_public_lookup_table(
+        BUS,
+        (0..TABLE_SIZE)
+            .map(|value| vec![Val::<SC>::from_canonical_u32(value)])
+            .collect(),
+    );
+    keygen_builder.generate_pk()
+}
+
+/// Proves the sender with the `(count, value)` rows of `trace`.
+fn prove(pk: &MultiStarkProvingKey<SC>, trace: Vec<u32>) -> Proof<SC> {
+    let trace = RowMajorMatrix::new(to_field_vec(trace), 2);
+    default_engine().prove(
+        pk,
+        ProofInput::new(vec![(0, AirProofInput::simple_no_pis(trace))]),
+    )
+}
+
+#[test]
+fn test_public_lookup_table() {
+    let pk = keygen();
+    let vk = pk.get_vk();
+    assert_eq!(
+        vk.features,
+        VkFeatureSet::from_iter([VkFeature::PublicLookupTables])
+    );
+
+    // The value 100 is not in the table, but is sent with count 0.
+    let proof = prove(&pk, vec![1, 3, 2, 15, 0, 100, 4, 3]);
+    assert_eq!(proof.core.per_air.len(), 1);
+    default_engine()
+        .verify(&vk, &proof)
+        .expect("Verification failed");
+
+    let air: AirRef<SC> = Arc::new(DummyInteractionAir::new(1, true, BUS));
+    let trace = RowMajorMatrix::new(to_field_vec(vec![1, 3, 2, 15, 0, 100, 4, 3]), 2);
+    default_engine().debug(
+        &[air],
+        &pk.per_air,
+        &pk.bus_registry,
+        &pk.public_lookup_tables,
+        &[AirProofInput::simple_no_pis(trace)],
+    );
+}
+
+#[test]
+fn test_public_lookup_table_wrong_multiplicities() {
+    let pk = keygen();
+    let mut proof = prove(&pk, vec![1, 3, 2, 15, 0, 100, 4, 3]);
+    let multiplicities = &mut proof
+        .rap_phase_seq_proof
+        .as_mut()
+        .unwrap()
+        .public_lookup_multiplicities[0];
+    assert_eq!(multiplicities[3], Val::<SC>::from_canonical_u32(5));
+    multiplicities[3] -= Val::<SC>::ONE;
+    multiplicities[4] += Val::<SC>::ONE;
+    assert!(matches!(
+        default_engine().verify(&pk.get_vk(), &proof),
+        Err(VerificationError::LogUpSumMismatch { error, report })
+            if error == FriLogUpError::NonZeroCumulativeSum.to_string()
+                && report.unbalanced_buses.is_empty()
+    ));
+
+    let mut proof = prove(&pk, vec![1, 3, 2, 15, 0, 100, 4, 3]);
+    proof
+        .rap_phase_seq_proof
+        .as_mut()
+        .unwrap()
+        .public_lookup_multiplicities
+        .clear();
+    assert_eq!(
+        default_engine().verify(&pk.get_vk(), &proof),
+        Err(VerificationError::ChallengePhaseError(
+            FriLogUpError::InvalidPublicLookupMultiplicities.to_string()
+        ))
+    );
+}
+
+#[test]
+#[should_panic(expected = "is not in the public lookup table")]
+fn test_public_lookup_table_missing_row() {
+    let pk = keygen();
+    prove(&pk, vec![1, 3, 1, TABLE_SIZE]);
+}
+
+#[test]
+fn test_public_lookup_table_well_formed() {
+    let table = |rows: Vec<Vec<u32>>| PublicLookupTable {
+        bus_index: BUS,
+        rows: rows.into_iter().map(to_field_vec::<Val<SC>>).collect(),
+    };
+    assert_eq!(table(vec![vec![1, 2], vec![3, 4]]).arity(), 2);
+    assert!(table(vec![vec![1, 2], vec![3, 4]]).is_well_formed());
+    // An empty table has no arity, which is reported as 0 instead of panicking.
+    assert_eq!(table(vec![]).arity(), 0);
+    assert!(!table(vec![]).is_well_formed());
+    assert!(!table(vec![vec![1, 2], vec![3]]).is_well_formed());
+}
+
+#[test]
+fn test_public_lookup_table_arity_mismatch() {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(Arc::new(DummyInteractionAir::new(2, true, BUS)));
+    keygen_builder.add_public_lookup_table(BUS, vec![vec![Val::<SC>::ZERO]]);
+    assert_eq!(
+        keygen_builder.try_generate_pk().err(),
+        Some(KeygenError::BusArityMismatch {
+            air_id: 0,
+            air_name: "DummyInteractionAir".to_string(),
+            bus_index: BUS,
+            bus_name: "bus_0".to_string(),
+            expected_arity: 1,
+            arity: 2,
+        })
+    );
+}
@@ -0,0 +1,128 @@
+use std::{cell::Cell, sync::Arc};
+
+use openvm_stark_backend::{
+    engine::StarkEngine,
+    keygen::types::MultiStarkProvingKey,
+    p3_field::FieldAlgebra,
+    proof::Proof,
+    prover::types::{AirProofInput, ProofInput},
+    verifier::{PublicValuesView, VerificationError},
+};
+use openvm_stark_sdk::{
+    config::baby_bear_poseidon2::{
+        default_engine, BabyBearPoseidon2Config, BabyBearPoseidon2Engine,
+    },
+    dummy_airs::fib_air::{air::FibonacciAir, trace::generate_trace_rows},
+};
+use p3_baby_bear::BabyBear;
+
+use crate::get_fib_number;
+
+type SC = BabyBearPoseidon2Config;
+
+const N: usize = 16;
+
+/// Two Fibonacci AIRs, the first one named `fib_unused`, and a proof of the second one only,
+/// with `n` rows.
+fn prove(engine: &BabyBearPoseidon2Engine, n: usize) -> (MultiStarkProvingKey<SC>, Proof<SC>) {
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_named_air("fib_unused", Arc::new(FibonacciAir));
+    keygen_builder.add_air(Arc::new(FibonacciAir));
+    let pk = keygen_builder.generate_pk();
+    let trace = generate_trace_rows::<BabyBear>(0, 1, n);
+    let pis = [0, 1, get_fib_number(n)]
+        .map(BabyBear::from_canonical_u32)
+        .to_vec();
+    let proof = engine.prove(
+        &pk,
+        ProofInput::new(vec![(1, AirProofInput::simple(trace, pis))]),
+    );
+    (pk, proof)
+}
+
+/// The application check: the proof is of the `N`-th Fibonacci number.
+fn check_fib_number(view: &PublicValuesView<BabyBear>) -> Result<(), String> {
+    let pis = view.require(1)?;
+    let expected = BabyBear::from_canonical_u32(get_fib_number(N));
+    if pis[2] != expected {
+        return Err(format!(
+            "expected Fibonacci number {expected}, found {}",
+            pis[2]
+        ));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_public_values_view() {
+    let engine = default_engine();
+    let (pk, proof) = prove(&engine, N);
+    let vk = pk.get_vk();
+    let view = proof.public_values_view(&vk);
+    let pis = [0, 1, get_fib_number(N)].map(BabyBear::from_canonical_u32);
+    assert_eq!(view.get(1), Some(&pis[..]));
+    assert_eq!(view.get(0), None);
+    assert_eq!(view.get(2), None);
+    assert_eq!(view.get_by_name("FibonacciAir"), Some(&pis[..]));
+    assert_eq!(view.get_by_name("fib_unused"), None);
+    assert_eq!(
+        view.require(0),
+        Err("AIR fib_unused (air_id = 0) is not in the proof".to_string())
+    );
+    assert_eq!(
+        view.iter().collect::<Vec<_>>(),
+        vec![(1, "FibonacciAir", &pis[..])]
+    );
+}
+
+#[test]
+fn test_public_values_check() {
+    let engine = default_engine();
+    let (pk, proof) = prove(&engine, N);
+    engine
+        .verify_with_public_values_check(&pk.get_vk(), &proof, check_fib_number)
+        .expect("Verification failed");
+
+    // A valid proof of another Fibonacci number.
+    let (pk, proof) = prove(&engine, N / 2);
+    let vk = pk.get_vk();
+    engine.verify(&vk, &proof).expect("Verification failed");
+    assert_eq!(
+        engine.verify_with_public_values_check(&vk, &proof, check_fib_number),
+        Err(VerificationError::PublicValuesRejected(format!(
+            "expected Fibonacci number {}, found {}",
+            get_fib_number(N),
+            get_fib_number(N / 2)
+        )))
+    );
+    assert_eq!(
+        engine
+            .verify_with_public_values_check(&vk, &proof, check_fib_number)
+            .unwrap_err()
+            .to_string(),
+        format!(
+            "public values rejected: expected Fibonacci number {}, found {}",
+            get_fib_number(N),
+            get_fib_number(N / 2)
+        )
+    );
+}
+
+#[test]
+fn test_public_values_check_not_run_on_invalid_proof() {
+    let engine = default_engine();
+    let (pk, mut proof) = prove(&engine, N);
+    let vk = pk.get_vk();
+    proof.core.per_air[0].public_values[2] += BabyBear::ONE;
+    let called = Cell::new(false);
+    let result = engine.verify_with_public_values_check(&vk, &proof, |_| {
+        called.set(true);
+        Ok(())
+    });
+    assert!(!called.get());
+    assert_eq!(result, Err(engine.verify(&vk, &proof).unwrap_err()));
+    assert!(!matches!(
+        result,
+        Err(VerificationError::PublicValuesRejected(_))
+    ));
+}
@@ -0,0 +1,139 @@
+//! AIR with columns
+//! | x | y |
+//!
+//! Constrains y == x^3, so its quotient degree is 2.
+
+use std::sync::Arc;
+
+use openvm_stark_backend::{
+    keygen::{types::MultiStarkProvingKey, KeygenError, MultiStarkKeygenBuilder},
+    p3_field::FieldAlgebra,
+    prover::types::{AirProofInput, ProofInput},
+    rap::{BaseAirWithPublicValues, PartitionedBaseAir},
+};
+use openvm_stark_sdk::{
+    config::{
+        baby_bear_poseidon2::{BabyBearPoseidon2Config, BabyBearPoseidon2Engine},
+        FriParameters,
+    },
+    engine::{StarkEngine, StarkFriEngine},
+};
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_baby_bear::BabyBear;
+use p3_matrix::{dense::RowMajorMatrix, Matrix};
+
+type SC = BabyBearPoseidon2Config;
+type Val = BabyBear;
+
+/// With a blowup of 4, the maximum supported quotient degree is 4.
+const LOG_BLOWUP: usize = 2;
+
+struct CubicAir;
+
+impl<F> BaseAirWithPublicValues<F> for CubicAir {}
+impl<F> PartitionedBaseAir<F> for CubicAir {}
+impl<F> BaseAir<F> for CubicAir {
+    fn width(&self) -> usize {
+        2
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for CubicAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        let x: AB::Expr = local[0].into();
+        builder.assert_eq(x.clone() * x.clone() * x, local[1]);
+    }
+}
+
+fn engine() -> BabyBearPoseidon2Engine {
+    BabyBearPoseidon2Engine::new(FriParameters::standard_with_100_bits_conjectured_security(
+        LOG_BLOWUP,
+    ))
+}
+
+fn keygen(quotient_degree: Option<usize>) -> Result<MultiStarkProvingKey<SC>, KeygenError> {
+    let engine = engine();
+    let mut keygen_builder = engine.keygen_builder();
+    let air_id = keygen_builder.add_air(Arc::new(CubicAir));
+    if let Some(quotient_degree) = quotient_degree {
+        keygen_builder.set_quotient_degree_override(air_id, quotient_degree);
+    }
+    keygen_builder.try_generate_pk()
+}
+
+fn proof_input() -> ProofInput<SC> {
+    let values = (0..16u32)
+        .flat_map(|x| {
+            let x = Val::from_canonical_u32(x);
+            [x, x * x * x]
+        })
+        .collect();
+    let trace = RowMajorMatrix::new(values, 2);
+    ProofInput::new(vec![(0, AirProofInput::simple_no_pis(trace))])
+}
+
+#[test]
+fn test_quotient_degree_override() {
+    let engine = engine();
+    assert_eq!(keygen(None).unwrap().per_air[0].vk.quotient_degree, 2);
+
+    let pk = keygen(Some(4)).unwrap();
+    let vk = pk.get_vk();
+    assert_eq!(vk.per_air[0].quotient_degree, 4);
+    let proof = engine.prove(&pk, proof_input());
+    assert_eq!(proof.core.opened_values.quotient[0].len(), 4);
+    engine.verify(&vk, &proof).expect("Verification failed");
+
+    // The quotient degree is part of the verifying key.
+    let natural_vk = keygen(None).unwrap().get_vk();
+    assert_ne!(natural_vk.canonical_bytes(), vk.canonical_bytes());
+    assert!(engine.verify(&natural_vk, &proof).is_err());
+}
+
+#[test]
+fn test_quotient_degree_override_invalid() {
+    assert_eq!(
+        keygen(Some(1)).err(),
+        Some(KeygenError::QuotientDegreeTooLow {
+            air_id: 0,
+            air_name: "CubicAir".to_string(),
+            degree: 1,
+            min_degree: 2,
+        })
+    );
+    assert_eq!(
+        keygen(Some(3)).err(),
+        Some(KeygenError::QuotientDegreeNotPowerOfTwo {
+            air_id: 0,
+            air_name: "CubicAir".to_string(),
+            degree: 3,
+        })
+    );
+    assert_eq!(
+        keygen(Some(8)).err(),
+        Some(KeygenError::QuotientDegreeTooHigh {
+            air_id: 0,
+            air_name: "CubicAir".to_string(),
+            degree: 8,
+            max_degree: 4,
+        })
+    );
+
+    // Without the bound of the PCS, the quotient degree cannot exceed the one the constraints
+    // require.
+    let engine = engine();
+    let mut keygen_builder = MultiStarkKeygenBuilder::new(engine.config());
+    let air_id = keygen_builder.add_air(Arc::new(CubicAir));
+    keygen_builder.set_quotient_degree_override(air_id, 4);
+    assert_eq!(
+        keygen_builder.try_generate_pk().err(),
+        Some(KeygenError::QuotientDegreeTooHigh {
+            air_id: 0,
+            air_name: "CubicAir".to_string(),
+            degree: 4,
+            max_degree: 2,
+        })
+    );
+}
@@ -0,0 +1,149 @@
+use std::sync::Arc;
+
+use openvm_stark_backend::{
+    engine::StarkEngine,
+    interaction::{bus::BusRegistry, InteractionBuilder},
+    p3_field::Field,
+    prover::types::{AirProofInput, ProofInput},
+    rap::{BaseAirWithPublicValues, PartitionedBaseAir},
+    AirRef,
+};
+use openvm_stark_sdk::{
+    config::baby_bear_poseidon2::{default_engine, BabyBearPoseidon2Config},
+    debug::debug_interactions,
+    dummy_airs::interaction::dummy_interaction_air::DummyInteractionAir,
+};
+use p3_air::{Air, BaseAir, PairBuilder};
+use p3_baby_bear::BabyBear;
+use p3_matrix::{dense::RowMajorMatrix, Matrix};
+
+use crate::utils::to_field_vec;
+
+type SC = BabyBearPoseidon2Config;
+
+const BUS: usize = 0;
+const NUM_BITS: usize = 8;
+
+/// Table of the `NUM_BITS`-bit values, which receives each value of its preprocessed column with
+/// the multiplicity of its only main column. It has no other constraint.
+struct RangeCheckTableAir;
+
+impl<F: Field> BaseAir<F> for RangeCheckTableAir {
+    fn width(&self) -> usize {
+        1
+    }
+
+    fn preprocessed_trace(&self) -> Option<RowMajorMatrix<F>> {
+        Some(RowMajorMatrix::new_col(
+            (0..1 << NUM_BITS).map(F::from_canonical_usize).collect(),
+        ))
+    }
+}
+impl<F: Field> BaseAirWithPublicValues<F> for RangeCheckTableAir {}
+impl<F: Field> PartitionedBaseAir<F> for RangeCheckTableAir {}
+
+impl<AB: InteractionBuilder + PairBuilder> Air<AB> for RangeCheckTableAir {
+    fn eval(&self, builder: &mut AB) {
+        let preprocessed = builder.preprocessed();
+        let main = builder.main();
+        let (value, multiplicity) = (preprocessed.row_slice(0)[0], main.row_slice(0)[0]);
+        builder.push_receive(BUS, [value], multiplicity);
+    }
+}
+
+/// AIR with one main column and neither constraints nor interactions.
+struct UnconstrainedAir;
+
+impl<F: Field> BaseAir<F> for UnconstrainedAir {
+    fn width(&self) -> usize {
+        1
+    }
+}
+impl<F: Field> BaseAirWithPublicValues<F> for UnconstrainedAir {}
+impl<F: Field> PartitionedBaseAir<F> for UnconstrainedAir {}
+impl<AB: InteractionBuilder> Air<AB> for UnconstrainedAir {
+    fn eval(&self, _builder: &mut AB) {}
+}
+
+/// The trace of a sender of the `(count, value)` range checks of `sends`, and the trace of the
+/// table with the matching multiplicities. Out-of-range values are not counted by the table.
+fn traces(sends: &[(u32, u32)]) -> (RowMajorMatrix<BabyBear>, RowMajorMatrix<BabyBear>) {
+    let mut multiplicities = vec![0; 1 << NUM_BITS];
+    for &(count, value) in sends {
+        if let Some(multiplicity) = multiplicities.get_mut(value as usize) {
+            *multiplicity += count;
+        }
+    }
+    let sender_trace = RowMajorMatrix::new(
+        to_field_vec(
+            sends
+                .iter()
+                .flat_map(|&(count, value)| [count, value])
+                .collect(),
+        ),
+        2,
+    );
+    (
+        sender_trace,
+        RowMajorMatrix::new_col(to_field_vec(multiplicities)),
+    )
+}
+
+#[test]
+fn test_range_check_table() {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    let table_id = keygen_builder.add_air(Arc::new(RangeCheckTableAir));
+    let sender_id = keygen_builder.add_air(Arc::new(DummyInteractionAir::new(1, true, BUS)));
+    let unconstrained_id = keygen_builder.add_air(Arc::new(UnconstrainedAir));
+    let pk = keygen_builder.generate_pk();
+
+    let (sender_trace, table_trace) = traces(&[(1, 3), (2, 200), (1, 255), (1, 3)]);
+    let proof = engine.prove(
+        &pk,
+        ProofInput::new(vec![
+            (table_id, AirProofInput::simple_no_pis(table_trace)),
+            (sender_id, AirProofInput::simple_no_pis(sender_trace)),
+            (
+                unconstrained_id,
+                AirProofInput::simple_no_pis(RowMajorMatrix::new_col(to_field_vec(vec![1, 2]))),
+            ),
+        ]),
+    );
+    let vk = pk.get_vk();
+    engine.verify(&vk, &proof).expect("Verification failed");
+
+    // The only constraints of the table are the log-up constraints.
+    let table_vk = &vk.per_air[table_id];
+    assert!(table_vk.has_interaction());
+    assert_ne!(
+        table_vk.symbolic_constraints.constraints.num_constraints(),
+        0
+    );
+    let unconstrained_vk = &vk.per_air[unconstrained_id];
+    assert_eq!(
+        unconstrained_vk
+            .symbolic_constraints
+            .constraints
+            .num_constraints(),
+        0
+    );
+    assert_eq!(unconstrained_vk.quotient_degree, 1);
+}
+
+#[test]
+fn test_range_check_table_debug_interactions() {
+    let airs: Vec<AirRef<SC>> = vec![
+        Arc::new(RangeCheckTableAir),
+        Arc::new(DummyInteractionAir::new(1, true, BUS)),
+    ];
+    let (sender_trace, table_trace) = traces(&[(1, 0), (3, 17)]);
+    debug_interactions(&airs, &BusRegistry::default(), &[table_trace, sender_trace])
+        .expect("range checks should balance");
+
+    // 256 is out of range.
+    let (sender_trace, table_trace) = traces(&[(1, 0), (1, 256)]);
+    assert!(
+        debug_interactions(&airs, &BusRegistry::default(), &[table_trace, sender_trace]).is_err()
+    );
+}
@@ -0,0 +1,342 @@
+use std::sync::Arc;
+
+use openvm_stark_backend::{
+    config::{Com, Domain, PcsProverData, StarkGenericConfig},
+    interaction::{InteractionBuilder, InteractionType},
+    keygen::types::{MultiStarkProvingKey, StarkVerifyingKey},
+    p3_commit::{Pcs, PolynomialSpace},
+    p3_field::{FieldAlgebra, FieldExtensionAlgebra},
+    prover::view::{BitReversed, RapViewBuilder, RapViewError, RapViewErrorKind, RapViewMatrix},
+    rap::{BaseAirWithPublicValues, PartitionedBaseAir},
+};
+use openvm_stark_sdk::{
+    config::{
+        baby_bear_poseidon2::{BabyBearPoseidon2Config, BabyBearPoseidon2Engine},
+        FriParameters,
+    },
+    engine::{StarkEngine, StarkFriEngine},
+};
+use p3_air::{Air, AirBuilderWithPublicValues, BaseAir, PairBuilder};
+use p3_baby_bear::BabyBear;
+use p3_matrix::{dense::RowMajorMatrix, Matrix};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+type SC = BabyBearPoseidon2Config;
+type F = BabyBear;
+type EF = <SC as StarkGenericConfig>::Challenge;
+type View<'a> = <<SC as StarkGenericConfig>::Pcs as Pcs<
+    EF,
+    <SC as StarkGenericConfig>::Challenger,
+>>::EvaluationsOnDomain<'a>;
+type Builder<'a> = RapViewBuilder<'a, View<'a>, F, EF, Com<SC>>;
+
+const LOG_HEIGHT: u8 = 3;
+const HEIGHT: usize = 1 << LOG_HEIGHT;
+
+/// Sends the main column `v` on bus 0 with the preprocessed column as multiplicity, and
+/// constrains the first `v` to the public value.
+struct SendAir;
+
+impl BaseAir<F> for SendAir {
+    fn width(&self) -> usize {
+        1
+    }
+
+    fn preprocessed_trace(&self) -> Option<RowMajorMatrix<F>> {
+        Some(RowMajorMatrix::new_col(vec![F::ONE; HEIGHT]))
+    }
+}
+impl BaseAirWithPublicValues<F> for SendAir {
+    fn num_public_values(&self) -> usize {
+        1
+    }
+}
+impl PartitionedBaseAir<F> for SendAir {}
+
+impl<AB> Air<AB> for SendAir
+where
+    AB: InteractionBuilder<F = F> + PairBuilder + AirBuilderWithPublicValues,
+{
+    fn eval(&self, builder: &mut AB) {
+        let count = builder.preprocessed().row_slice(0)[0];
+        let v = builder.main().row_slice(0)[0];
+        let start = builder.public_values()[0];
+        builder.when_first_row().assert_eq(v, start);
+        builder.push_interaction(0, [v], count, InteractionType::Send);
+    }
+}
+
+/// A key of [SendAir], and the PCS data of its traces: the main trace is also committed with
+/// the wrong width and the wrong height.
+struct Fixture {
+    engine: BabyBearPoseidon2Engine,
+    pk: MultiStarkProvingKey<SC>,
+    preprocessed: PcsProverData<SC>,
+    main: PcsProverData<SC>,
+    wide_main: PcsProverData<SC>,
+    tall_main: PcsProverData<SC>,
+    after_challenge: PcsProverData<SC>,
+    rng: StdRng,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        let engine = BabyBearPoseidon2Engine::new(
+            FriParameters::standard_with_100_bits_conjectured_security(2),
+        );
+        let mut keygen_builder = engine.keygen_builder();
+        keygen_builder.add_air(Arc::new(SendAir));
+        let pk = keygen_builder.generate_pk();
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let ext_degree = <EF as FieldExtensionAlgebra<F>>::D;
+        let after_challenge_width = pk.per_air[0].vk.params.width.after_challenge[0];
+        let mut commit = |height: usize, width: usize| {
+            let trace =
+                RowMajorMatrix::new((0..height * width).map(|_| rng.gen()).collect(), width);
+            let pcs = engine.config().pcs();
+            pcs.commit(vec![(pcs.natural_domain_for_degree(height), trace)])
+                .1
+        };
+        let preprocessed = commit(HEIGHT, 1);
+        let main = commit(HEIGHT, 1);
+        let wide_main = commit(HEIGHT, 2);
+        let tall_main = commit(2 * HEIGHT, 1);
+        let after_challenge = commit(HEIGHT, after_challenge_width * ext_degree);
+        Self {
+            engine,
+            pk,
+            preprocessed,
+            main,
+            wide_main,
+            tall_main,
+            after_challenge,
+            rng,
+        }
+    }
+
+    fn vk(&self) -> &StarkVerifyingKey<F, Com<SC>> {
+        &self.pk.per_air[0].vk
+    }
+
+    fn quotient_domain(&self, log_height: u8) -> Domain<SC> {
+        let pcs = self.engine.config().pcs();
+        let trace_domain = pcs.natural_domain_for_degree(1 << log_height);
+        trace_domain
+            .create_disjoint_domain(trace_domain.size() * self.vk().quotient_degree as usize)
+    }
+
+    fn extend<'a>(&'a self, data: &'a PcsProverData<SC>) -> BitReversed<View<'a>> {
+        BitReversed::on_quotient_domain::<SC>(
+            self.engine.config().pcs(),
+            data,
+            0,
+            self.quotient_domain(LOG_HEIGHT),
+        )
+    }
+
+    fn challenges(&self, count: usize) -> Vec<EF> {
+        let mut rng = self.rng.clone();
+        (0..count).map(|_| rng.gen()).collect()
+    }
+
+    /// A view with the after challenge trace, the challenges and the exposed values of the
+    /// challenge phase `phase`, where the AIR has phase 0 only.
+    fn phase<'a>(&'a self, builder: Builder<'a>, phase: usize) -> Builder<'a> {
+        let params = &self.vk().params;
+        builder.phase(
+            phase,
+            self.extend(&self.after_challenge),
+            self.challenges(params.num_challenges_to_sample[0]),
+            self.challenges(params.num_exposed_values_after_challenge[0]),
+        )
+    }
+
+    /// The builder of a view without its after challenge trace.
+    fn main_builder(&self) -> Builder<'_> {
+        RapViewBuilder::new(self.vk(), LOG_HEIGHT)
+            .preprocessed(self.extend(&self.preprocessed))
+            .main(self.extend(&self.main))
+            .public_values(vec![F::ONE])
+    }
+
+    /// The builder of a valid view.
+    fn builder(&self) -> Builder<'_> {
+        self.phase(self.main_builder(), 0)
+    }
+
+    fn assert_err(&self, builder: Builder<'_>, kind: RapViewErrorKind) {
+        assert_eq!(
+            builder.build().err(),
+            Some(RapViewError {
+                air_name: self.vk().air_name.clone(),
+                kind
+            })
+        );
+    }
+}
+
+fn rows(view: BitReversed<View<'_>>) -> RowMajorMatrix<F> {
+    view.into_inner().to_row_major_matrix()
+}
+
+#[test]
+fn test_build_keeps_matrices() {
+    let fixture = Fixture::new();
+    let view = fixture.builder().build().unwrap();
+    assert_eq!(
+        rows(view.pair.preprocessed.unwrap()),
+        rows(fixture.extend(&fixture.preprocessed))
+    );
+    let [main] = <[_; 1]>::try_from(view.pair.partitioned_main).ok().unwrap();
+    assert_eq!(rows(main), rows(fixture.extend(&fixture.main)));
+    let [phase] = <[_; 1]>::try_from(view.per_phase).ok().unwrap();
+    assert_eq!(
+        rows(phase.inner.unwrap()),
+        rows(fixture.extend(&fixture.after_challenge))
+    );
+    assert_eq!(view.pair.public_values, [F::ONE]);
+}
+
+#[test]
+fn test_missing_matrix() {
+    let fixture = Fixture::new();
+    let builder = RapViewBuilder::new(fixture.vk(), LOG_HEIGHT)
+        .main(fixture.extend(&fixture.main))
+        .public_values(vec![F::ONE]);
+    fixture.assert_err(
+        fixture.phase(builder, 0),
+        RapViewErrorKind::Missing(RapViewMatrix::Preprocessed),
+    );
+
+    let builder = RapViewBuilder::new(fixture.vk(), LOG_HEIGHT)
+        .preprocessed(fixture.extend(&fixture.preprocessed))
+        .public_values(vec![F::ONE]);
+    fixture.assert_err(
+        fixture.phase(builder, 0),
+        RapViewErrorKind::Missing(RapViewMatrix::Main(0)),
+    );
+
+    fixture.assert_err(
+        fixture.main_builder(),
+        RapViewErrorKind::Missing(RapViewMatrix::AfterChallenge(0)),
+    );
+}
+
+/// A phase given at the wrong index is not shifted into the phases before it.
+#[test]
+fn test_phase_index() {
+    let fixture = Fixture::new();
+    fixture.assert_err(
+        fixture.phase(fixture.main_builder(), 1),
+        RapViewErrorKind::Missing(RapViewMatrix::AfterChallenge(0)),
+    );
+}
+
+#[test]
+fn test_unexpected_matrix() {
+    let fixture = Fixture::new();
+    fixture.assert_err(
+        fixture.builder().main(fixture.extend(&fixture.main)),
+        RapViewErrorKind::Unexpected(RapViewMatrix::Main(1)),
+    );
+    fixture.assert_err(
+        fixture.phase(fixture.builder(), 1),
+        RapViewErrorKind::Unexpected(RapViewMatrix::AfterChallenge(1)),
+    );
+}
+
+#[test]
+fn test_matrix_width() {
+    let fixture = Fixture::new();
+    let builder = RapViewBuilder::new(fixture.vk(), LOG_HEIGHT)
+        .preprocessed(fixture.extend(&fixture.preprocessed))
+        .main(fixture.extend(&fixture.wide_main))
+        .public_values(vec![F::ONE]);
+    fixture.assert_err(
+        fixture.phase(builder, 0),
+        RapViewErrorKind::Width {
+            matrix: RapViewMatrix::Main(0),
+            width: 2,
+            expected: 1,
+        },
+    );
+}
+
+#[test]
+fn test_matrix_height() {
+    let fixture = Fixture::new();
+    // The main trace on the quotient domain of a trace twice as tall.
+    let tall_main = BitReversed::on_quotient_domain::<SC>(
+        fixture.engine.config().pcs(),
+        &fixture.tall_main,
+        0,
+        fixture.quotient_domain(LOG_HEIGHT + 1),
+    );
+    let builder = RapViewBuilder::new(fixture.vk(), LOG_HEIGHT)
+        .preprocessed(fixture.extend(&fixture.preprocessed))
+        .main(tall_main)
+        .public_values(vec![F::ONE]);
+    let height = HEIGHT * fixture.vk().quotient_degree as usize;
+    fixture.assert_err(
+        fixture.phase(builder, 0),
+        RapViewErrorKind::Height {
+            matrix: RapViewMatrix::Main(0),
+            height: 2 * height,
+            expected: height,
+        },
+    );
+}
+
+#[test]
+fn test_public_values_count() {
+    let fixture = Fixture::new();
+    fixture.assert_err(
+        fixture.builder().public_values(vec![]),
+        RapViewErrorKind::PublicValues {
+            count: 0,
+            expected: 1,
+        },
+    );
+}
+
+#[test]
+fn test_challenges_count() {
+    let fixture = Fixture::new();
+    let params = &fixture.vk().params;
+    let expected = params.num_challenges_to_sample[0];
+    let exposed_values = params.num_exposed_values_after_challenge[0];
+    fixture.assert_err(
+        fixture.main_builder().phase(
+            0,
+            fixture.extend(&fixture.after_challenge),
+            fixture.challenges(expected - 1),
+            fixture.challenges(exposed_values),
+        ),
+        RapViewErrorKind::Challenges {
+            phase: 0,
+            count: expected - 1,
+            expected,
+        },
+    );
+}
+
+#[test]
+fn test_exposed_values_count() {
+    let fixture = Fixture::new();
+    let params = &fixture.vk().params;
+    let expected = params.num_exposed_values_after_challenge[0];
+    fixture.assert_err(
+        fixture.main_builder().phase(
+            0,
+            fixture.extend(&fixture.after_challenge),
+            fixture.challenges(params.num_challenges_to_sample[0]),
+            fixture.challenges(expected + 1),
+        ),
+        RapViewErrorKind::ExposedValues {
+            phase: 0,
+            count: expected + 1,
+            expected,
+        },
+    );
+}
@@ -0,0 +1,152 @@
+use std::sync::Arc;
+
+use itertools::Itertools;
+use openvm_stark_backend::{
+    keygen::{types::MultiStarkVerifyingKey, MultiStarkKeygenBuilder},
+    p3_field::{Field, FieldAlgebra},
+    prover::types::{AirProofInput, ProofInput},
+    rap::{BaseAirWithPublicValues, PartitionedBaseAir},
+};
+use openvm_stark_sdk::{
+    config::baby_bear_poseidon2::{default_engine, BabyBearPoseidon2Config},
+    dummy_airs::{
+        fib_air::air::FibonacciAir, interaction::dummy_interaction_air::DummyInteractionAir,
+    },
+    engine::StarkEngine,
+};
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_baby_bear::BabyBear;
+use p3_matrix::{dense::RowMajorMatrix, Matrix};
+
+use crate::fib_selector_air::air::FibonacciSelectorAir;
+
+type SC = BabyBearPoseidon2Config;
+type Val = BabyBear;
+
+/// Constrains `y = x^2` on every row and, if `first_row_zero`, `x = 0` on the first row.
+///
+/// Main columns: `x, y`.
+struct SquareAir {
+    first_row_zero: bool,
+}
+
+impl<F: Field> BaseAir<F> for SquareAir {
+    fn width(&self) -> usize {
+        2
+    }
+}
+impl<F: Field> BaseAirWithPublicValues<F> for SquareAir {}
+impl<F: Field> PartitionedBaseAir<F> for SquareAir {}
+
+impl<AB: AirBuilder> Air<AB> for SquareAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        let (x, y) = (local[0], local[1]);
+        builder.assert_eq(y, x * x);
+        if self.first_row_zero {
+            builder.when_first_row().assert_zero(x);
+        }
+    }
+}
+
+fn keygen_builder(engine: &impl StarkEngine<SC>) -> MultiStarkKeygenBuilder<'_, SC> {
+    let mut keygen_builder = engine.keygen_builder();
+    let sels = (0..8).map(|i| i % 2 == 0).collect();
+    keygen_builder.add_air(Arc::new(FibonacciAir));
+    keygen_builder.add_air(Arc::new(SquareAir {
+        first_row_zero: false,
+    }));
+    keygen_builder.add_air(Arc::new(FibonacciSelectorAir::new(sels, false)));
+    keygen_builder.add_air(Arc::new(DummyInteractionAir::new(2, true, 0)));
+    keygen_builder.add_air(Arc::new(DummyInteractionAir::new(2, false, 0)));
+    keygen_builder
+}
+
+fn fingerprints(vk: &MultiStarkVerifyingKey<SC>) -> Vec<u64> {
+    vk.per_air.iter().map(|vk| vk.fingerprint()).collect()
+}
+
+#[test]
+fn test_replace_air() {
+    let engine = default_engine();
+    let vk = keygen_builder(&engine).generate_pk().get_vk();
+
+    let mut keygen_builder = keygen_builder(&engine);
+    keygen_builder.replace_air(
+        1,
+        Arc::new(SquareAir {
+            first_row_zero: true,
+        }),
+    );
+    assert_eq!(keygen_builder.airs()[1], (1, "SquareAir".to_string()));
+    let pk = keygen_builder.generate_pk();
+    let new_vk = pk.get_vk();
+
+    let (old, new) = (fingerprints(&vk), fingerprints(&new_vk));
+    let changed = (0..old.len()).filter(|&i| old[i] != new[i]).collect_vec();
+    assert_eq!(changed, [1]);
+    assert_eq!(
+        new_vk.per_air[1]
+            .symbolic_constraints
+            .constraints
+            .constraint_idx
+            .len(),
+        vk.per_air[1]
+            .symbolic_constraints
+            .constraints
+            .constraint_idx
+            .len()
+            + 1
+    );
+
+    let trace = RowMajorMatrix::new(
+        (0..8u32)
+            .flat_map(|x| [x, x * x])
+            .map(Val::from_canonical_u32)
+            .collect(),
+        2,
+    );
+    engine
+        .prove_then_verify(
+            &pk,
+            ProofInput::new(vec![(1, AirProofInput::simple_no_pis(trace))]),
+        )
+        .expect("Verification failed");
+}
+
+#[test]
+fn test_remove_air() {
+    let engine = default_engine();
+    let vk = keygen_builder(&engine).generate_pk().get_vk();
+
+    let mut keygen_builder = keygen_builder(&engine);
+    keygen_builder.remove_air(1);
+    // The ids of the AIRs after the removed one are compacted.
+    assert_eq!(
+        keygen_builder
+            .airs()
+            .into_iter()
+            .map(|(air_id, _)| air_id)
+            .collect_vec(),
+        [0, 1, 2, 3]
+    );
+    assert_eq!(keygen_builder.air(4).map(|air| air.name()), None);
+    let new_vk = keygen_builder.generate_pk().get_vk();
+
+    let mut expected = fingerprints(&vk);
+    expected.remove(1);
+    assert_eq!(fingerprints(&new_vk), expected);
+}
+
+#[test]
+#[should_panic(expected = "no AIR with id 5")]
+fn test_replace_air_out_of_bounds() {
+    let engine = default_engine();
+    keygen_builder(&engine).replace_air(
+        5,
+        Arc::new(SquareAir {
+            first_row_zero: true,
+        }),
+    );
+}
@@ -0,0 +1,99 @@
+use std::sync::Arc;
+
+use openvm_stark_backend::{
+    engine::StarkEngine,
+    p3_field::{Field, FieldAlgebra},
+    prover::types::{AirProofInput, ProofInput},
+    rap::{BaseAirWithPublicValues, PartitionedBaseAir},
+    utils::disable_debug_builder,
+    verifier::VerificationError,
+};
+use openvm_stark_sdk::{
+    any_rap_arc_vec,
+    config::baby_bear_poseidon2::{default_engine, BabyBearPoseidon2Engine},
+    engine::StarkFriEngine,
+    utils::create_seeded_rng,
+};
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_baby_bear::BabyBear;
+use p3_matrix::{dense::RowMajorMatrix, Matrix};
+use rand::Rng;
+
+/// Columns `[window, window_src]` with the constraint `window[i] == window_src[i + 2]`,
+/// where row indices wrap around the trace.
+struct RotationAir;
+
+impl<F: Field> BaseAir<F> for RotationAir {
+    fn width(&self) -> usize {
+        2
+    }
+}
+impl<F: Field> BaseAirWithPublicValues<F> for RotationAir {}
+impl<F: Field> PartitionedBaseAir<F> for RotationAir {
+    fn max_rotation(&self) -> usize {
+        2
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for RotationAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let (local, rotated) = (main.row_slice(0), main.row_slice(2));
+        builder.assert_eq(local[0], rotated[1]);
+    }
+}
+
+fn generate_trace(height: usize) -> RowMajorMatrix<BabyBear> {
+    let mut rng = create_seeded_rng();
+    let window_src: Vec<BabyBear> = (0..height)
+        .map(|_| BabyBear::from_canonical_u32(rng.gen_range(0..1 << 30)))
+        .collect();
+    let values = (0..height)
+        .flat_map(|i| [window_src[(i + 2) % height], window_src[i]])
+        .collect();
+    RowMajorMatrix::new(values, 2)
+}
+
+fn prove_and_verify(trace: RowMajorMatrix<BabyBear>) -> Result<(), VerificationError> {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    let air_id = keygen_builder.add_air(Arc::new(RotationAir));
+    let pk = keygen_builder.generate_pk();
+    assert_eq!(pk.per_air[air_id].vk.params.extra_rotations, vec![2]);
+
+    let proof_input = ProofInput::new(vec![(air_id, AirProofInput::simple_no_pis(trace))]);
+    engine.prove_then_verify(&pk, proof_input)
+}
+
+#[test]
+fn test_rotation_air() {
+    for log_height in [2, 3, 5] {
+        prove_and_verify(generate_trace(1 << log_height)).expect("Verification failed");
+    }
+}
+
+#[test]
+fn test_rotation_air_debug_builder() {
+    let trace = generate_trace(1 << 3);
+    BabyBearPoseidon2Engine::run_simple_test_no_pis_fast(
+        any_rap_arc_vec![RotationAir],
+        vec![trace],
+    )
+    .expect("Verification failed");
+}
+
+#[test]
+fn test_rotation_air_wrap_around_negative() {
+    let height = 1 << 3;
+    let mut trace = generate_trace(height);
+    // The second to last row refers to `window_src` of the first row.
+    trace.values[2 * (height - 2)] += BabyBear::ONE;
+    disable_debug_builder();
+    assert_eq!(
+        prove_and_verify(trace),
+        Err(VerificationError::OodEvaluationMismatch {
+            air_id: 0,
+            air_name: "RotationAir".to_string(),
+        })
+    );
+}
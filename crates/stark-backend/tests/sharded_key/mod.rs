@@ -0,0 +1,125 @@
+use std::{fs, sync::Arc};
+
+use openvm_stark_backend::{
+    keygen::types::MultiStarkProvingKey,
+    p3_field::FieldAlgebra,
+    prover::types::{AirProofInput, ProofInput},
+};
+use openvm_stark_sdk::{
+    config::baby_bear_poseidon2::{default_engine, BabyBearPoseidon2Config},
+    dummy_airs::fib_air::{self, air::FibonacciAir},
+    engine::StarkEngine,
+    sharded_key::{shard_path, ShardedKeyError, ShardedProvingKey, MANIFEST_FILE},
+};
+use p3_baby_bear::BabyBear;
+
+use crate::{
+    fib_selector_air::{self, air::FibonacciSelectorAir},
+    get_conditional_fib_number, get_fib_number,
+};
+
+type SC = BabyBearPoseidon2Config;
+type Val = BabyBear;
+
+const N: usize = 16;
+
+fn selectors(air_id: usize) -> Vec<bool> {
+    (0..N).map(|i| i % air_id == 0).collect()
+}
+
+/// Proving key of a Fibonacci AIR and of two Fibonacci AIRs with preprocessed selectors, with
+/// AIR ids 0, 1 and 2.
+fn keygen() -> MultiStarkProvingKey<SC> {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(Arc::new(FibonacciAir));
+    for air_id in [1, 2] {
+        keygen_builder.add_air(Arc::new(FibonacciSelectorAir::new(
+            selectors(air_id),
+            false,
+        )));
+    }
+    keygen_builder.generate_pk()
+}
+
+fn proof_input(air_ids: &[usize]) -> ProofInput<SC> {
+    ProofInput::new(
+        air_ids
+            .iter()
+            .map(|&air_id| {
+                let (trace, last) = if air_id == 0 {
+                    (
+                        fib_air::trace::generate_trace_rows::<Val>(0, 1, N),
+                        get_fib_number(N),
+                    )
+                } else {
+                    let sels = selectors(air_id);
+                    (
+                        fib_selector_air::trace::generate_trace_rows::<Val>(0, 1, &sels),
+                        get_conditional_fib_number(&sels),
+                    )
+                };
+                let pis = [0, 1, last].map(Val::from_canonical_u32).to_vec();
+                (air_id, AirProofInput::simple(trace, pis))
+            })
+            .collect(),
+    )
+}
+
+#[test]
+fn test_sharded_key() {
+    let engine = default_engine();
+    let pk = keygen();
+    let dir = tempfile::tempdir().unwrap();
+    ShardedProvingKey::write(&pk, dir.path()).unwrap();
+    assert!(dir.path().join(MANIFEST_FILE).exists());
+    assert!(!shard_path(dir.path(), 0).exists());
+    assert!(shard_path(dir.path(), 1).exists());
+
+    let key = ShardedProvingKey::<SC>::open(dir.path()).unwrap();
+    let vk = key.get_vk();
+    let proof = key.prove(&engine, proof_input(&[0, 1, 2])).unwrap();
+    engine.verify(&vk, &proof).expect("Verification failed");
+    engine
+        .verify(&pk.get_vk(), &proof)
+        .expect("Verification failed");
+
+    fs::remove_file(shard_path(dir.path(), 1)).unwrap();
+    let key = ShardedProvingKey::<SC>::open(dir.path()).unwrap();
+    let proof = key.prove(&engine, proof_input(&[0, 2])).unwrap();
+    engine.verify(&vk, &proof).expect("Verification failed");
+
+    match key.prove(&engine, proof_input(&[0, 1, 2])) {
+        Err(ShardedKeyError::MissingShard {
+            air_id, air_name, ..
+        }) => {
+            assert_eq!(air_id, 1);
+            assert_eq!(air_name, pk.per_air[1].air_name);
+        }
+        Err(err) => panic!("unexpected error: {err}"),
+        Ok(_) => panic!("proved without the shard of AIR 1"),
+    }
+    assert!(matches!(
+        key.load(&[0, 3]),
+        Err(ShardedKeyError::UnknownAir {
+            air_id: 3,
+            num_airs: 3
+        })
+    ));
+}
+
+#[test]
+fn test_sharded_key_rejects_other_shard() {
+    let engine = default_engine();
+    let dir = tempfile::tempdir().unwrap();
+    ShardedProvingKey::write(&keygen(), dir.path()).unwrap();
+    // The selectors of AIR 2 differ from those of AIR 1, and so does its preprocessed commitment.
+    fs::copy(shard_path(dir.path(), 2), shard_path(dir.path(), 1)).unwrap();
+
+    let key = ShardedProvingKey::<SC>::open(dir.path()).unwrap();
+    assert!(key.prove(&engine, proof_input(&[0, 2])).is_ok());
+    assert!(matches!(
+        key.prove(&engine, proof_input(&[0, 1])),
+        Err(ShardedKeyError::ShardCommitmentMismatch { air_id: 1, .. })
+    ));
+}
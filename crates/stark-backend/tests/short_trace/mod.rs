@@ -0,0 +1,145 @@
+//! Traces of height 1 and 2. On a trace of height 1, the only row is both the first and the last
+//! row, transition constraints do not apply, and the next row is the local row.
+
+use std::sync::Arc;
+
+use openvm_stark_backend::{
+    engine::StarkEngine,
+    p3_field::{Field, FieldAlgebra},
+    prover::types::{AirProofInput, ProofInput},
+    rap::{BaseAirWithPublicValues, PartitionedBaseAir},
+    utils::disable_debug_builder,
+    verifier::VerificationError,
+};
+use openvm_stark_sdk::{
+    config::baby_bear_poseidon2::default_engine,
+    dummy_airs::interaction::dummy_interaction_air::DummyInteractionAir,
+};
+use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, BaseAir};
+use p3_baby_bear::BabyBear;
+use p3_matrix::{dense::RowMajorMatrix, Matrix};
+
+type Val = BabyBear;
+
+/// Columns `[step, acc, constant]` with public values `[start, end]`: `acc` starts at `start`,
+/// adds `step` on each transition and ends at `end`, and `constant` is the same on every row,
+/// which is constrained without a transition selector, wrapping around the trace.
+struct CounterAir;
+
+impl<F: Field> BaseAir<F> for CounterAir {
+    fn width(&self) -> usize {
+        3
+    }
+}
+impl<F: Field> BaseAirWithPublicValues<F> for CounterAir {
+    fn num_public_values(&self) -> usize {
+        2
+    }
+}
+impl<F: Field> PartitionedBaseAir<F> for CounterAir {}
+
+impl<AB: AirBuilderWithPublicValues> Air<AB> for CounterAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let pis = builder.public_values();
+        let (start, end) = (pis[0], pis[1]);
+        let (local, next) = (main.row_slice(0), main.row_slice(1));
+        builder.when_first_row().assert_eq(local[1], start);
+        builder
+            .when_transition()
+            .assert_eq(local[1] + local[0], next[1]);
+        builder.when_last_row().assert_eq(local[1], end);
+        builder.assert_eq(local[2], next[2]);
+    }
+}
+
+/// The trace of [CounterAir] with `steps`, and its public values.
+fn counter_trace(start: u32, steps: &[u32]) -> (RowMajorMatrix<Val>, Vec<Val>) {
+    let mut acc = Val::from_canonical_u32(start);
+    let mut values = vec![];
+    for &step in steps {
+        values.extend([
+            Val::from_canonical_u32(step),
+            acc,
+            Val::from_canonical_u32(7),
+        ]);
+        acc += Val::from_canonical_u32(step);
+    }
+    let end = values[values.len() - 2];
+    (
+        RowMajorMatrix::new(values, 3),
+        vec![Val::from_canonical_u32(start), end],
+    )
+}
+
+/// Proves [CounterAir] with `trace`, together with an interaction between two AIRs with traces
+/// of the same height.
+fn prove_and_verify(trace: RowMajorMatrix<Val>, pis: Vec<Val>) -> Result<(), VerificationError> {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    let counter_air_id = keygen_builder.add_air(Arc::new(CounterAir));
+    let send_air_id = keygen_builder.add_air(Arc::new(DummyInteractionAir::new(1, true, 0)));
+    let receive_air_id = keygen_builder.add_air(Arc::new(DummyInteractionAir::new(1, false, 0)));
+    let pk = keygen_builder.generate_pk();
+
+    // | count | field |, the same messages for the sender and the receiver.
+    let messages = RowMajorMatrix::new(
+        (0..trace.height())
+            .flat_map(|i| [i as u32 + 1, 3 * i as u32 + 5])
+            .map(Val::from_canonical_u32)
+            .collect(),
+        2,
+    );
+    let proof_input = ProofInput::new(vec![
+        (counter_air_id, AirProofInput::simple(trace, pis)),
+        (send_air_id, AirProofInput::simple_no_pis(messages.clone())),
+        (receive_air_id, AirProofInput::simple_no_pis(messages)),
+    ]);
+    engine.prove_then_verify(&pk, proof_input)
+}
+
+#[test]
+fn test_short_traces() {
+    for steps in [&[3][..], &[3, 4], &[3, 4, 5, 6]] {
+        let (trace, pis) = counter_trace(10, steps);
+        prove_and_verify(trace, pis).expect("Verification failed");
+    }
+}
+
+#[test]
+fn test_single_row_transition_does_not_apply() {
+    // With the next row equal to the local row, the transition constraint would need a step of 0.
+    let (trace, pis) = counter_trace(10, &[1]);
+    assert_eq!(pis, [10, 10].map(Val::from_canonical_u32));
+    prove_and_verify(trace, pis).expect("Verification failed");
+}
+
+#[test]
+fn test_single_row_negative() {
+    disable_debug_builder();
+    // The single row is the last row.
+    let (trace, mut pis) = counter_trace(10, &[1]);
+    pis[1] += Val::ONE;
+    assert_eq!(
+        prove_and_verify(trace, pis),
+        Err(VerificationError::OodEvaluationMismatch {
+            air_id: 0,
+            air_name: "CounterAir".to_string(),
+        })
+    );
+}
+
+#[test]
+fn test_two_rows_negative() {
+    disable_debug_builder();
+    // The constant column wraps around from the second row to the first.
+    let (mut trace, pis) = counter_trace(10, &[3, 4]);
+    trace.values[5] += Val::ONE;
+    assert_eq!(
+        prove_and_verify(trace, pis),
+        Err(VerificationError::OodEvaluationMismatch {
+            air_id: 0,
+            air_name: "CounterAir".to_string(),
+        })
+    );
+}
@@ -0,0 +1,189 @@
+use std::sync::Arc;
+
+use openvm_stark_backend::{
+    p3_field::FieldAlgebra,
+    prover::types::{AirProofInput, ProofInput},
+};
+use openvm_stark_sdk::{
+    config::{
+        baby_bear_poseidon2::{default_engine, BabyBearPoseidon2Engine},
+        FriParameters,
+    },
+    dummy_airs::{
+        fib_air::{air::FibonacciAir, trace::generate_trace_rows},
+        interaction::dummy_interaction_air::DummyInteractionAir,
+    },
+    engine::{StarkEngine, StarkFriEngine},
+    security::{
+        security_report, total_soundness, SecurityReport, SoundnessComponent, WorkloadBounds,
+    },
+};
+use p3_baby_bear::BabyBear;
+
+use crate::get_fib_number;
+
+type Val = BabyBear;
+
+/// The standard parameters for `log_blowup = 1`, regardless of `OPENVM_FAST_TEST`.
+fn standard_fast_params() -> FriParameters {
+    FriParameters {
+        log_blowup: 1,
+        log_final_poly_len: 0,
+        num_queries: 100,
+        proof_of_work_bits: 16,
+    }
+}
+
+fn assert_bits_eq(actual: f64, expected: f64) {
+    assert!(
+        (actual - expected).abs() < 0.01,
+        "expected {expected} bits, got {actual}"
+    );
+}
+
+#[test]
+fn test_soundness_breakdown_standard_presets() {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(Arc::new(FibonacciAir));
+    let vk = keygen_builder.generate_pk().get_vk();
+    let bounds = WorkloadBounds::from_max_log_height(&vk, 20);
+    assert_eq!(bounds.max_interaction_count, 0);
+
+    // The standard presets with 100 bits of conjectured security, without OPENVM_FAST_TEST.
+    // (log_blowup, num_queries, fri_queries, fri_folding, total)
+    let expected = [
+        (1, 100, 116.0, 98.24, 98.17),
+        (2, 42, 100.0, 97.24, 97.01),
+        (3, 28, 100.0, 96.24, 96.12),
+        (4, 21, 100.0, 95.24, 95.17),
+    ];
+    for (log_blowup, num_queries, fri_queries, fri_folding, total) in expected {
+        let fri_params = FriParameters {
+            log_blowup,
+            log_final_poly_len: 0,
+            num_queries,
+            proof_of_work_bits: 16,
+        };
+        let breakdown = total_soundness(&vk, fri_params, &bounds);
+        assert_bits_eq(breakdown.fri_queries, fri_queries);
+        assert_bits_eq(breakdown.fri_folding, fri_folding);
+        assert_eq!(breakdown.logup, f64::INFINITY);
+        // 5 constraints of degree 2 over the degree 4 extension of BabyBear
+        assert_bits_eq(breakdown.constraint_folding, 121.31);
+        assert_bits_eq(breakdown.deep, 102.63);
+        assert_bits_eq(breakdown.total, total);
+    }
+}
+
+#[test]
+fn test_soundness_logup() {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(Arc::new(DummyInteractionAir::new(3, true, 0)));
+    let vk = keygen_builder.generate_pk().get_vk();
+    let soundness = |max_log_height| {
+        let bounds = WorkloadBounds::from_max_log_height(&vk, max_log_height);
+        total_soundness(&vk, standard_fast_params(), &bounds)
+    };
+    let (small, large) = (soundness(10), soundness(20));
+    // 2^10 messages of length 4
+    assert_bits_eq(small.logup, 123.63 - 10.0 - 5f64.log2());
+    assert_bits_eq(small.logup - large.logup, 10.0);
+    assert!(large.total < small.total);
+}
+
+#[test]
+fn test_security_report_union_bound_over_buses() {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(Arc::new(DummyInteractionAir::new(3, true, 0)));
+    keygen_builder.add_air(Arc::new(DummyInteractionAir::new(1, true, 1)));
+    let vk = keygen_builder.generate_pk().get_vk();
+
+    let report = security_report(&vk, &standard_fast_params());
+    // The two-adicity of BabyBear is 27.
+    assert_eq!(report.max_log_height, 26);
+    assert_bits_eq(report.breakdown.fri_queries, 116.0);
+    assert_bits_eq(report.breakdown.fri_folding, 91.87);
+    // 2^26 messages of length 4 on bus 0 and 2^26 messages of length 2 on bus 1
+    assert_bits_eq(report.breakdown.logup, 123.63 - 29.0);
+    assert_eq!(report.binding_component, SoundnessComponent::FriFolding);
+    assert_bits_eq(report.binding_bits, 91.87);
+    assert!(report.breakdown.total < report.binding_bits);
+    assert!(!report.logup_below_fri());
+    assert!(!report.to_string().contains("warning"));
+
+    let json = serde_json::to_string(&report).unwrap();
+    assert_eq!(
+        serde_json::from_str::<SecurityReport>(&json).unwrap(),
+        report
+    );
+}
+
+#[test]
+fn test_security_report_warns_when_logup_below_fri() {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(Arc::new(DummyInteractionAir::new(100, true, 0)));
+    let vk = keygen_builder.generate_pk().get_vk();
+
+    let report = security_report(&vk, &standard_fast_params());
+    // 2^26 messages of length 101
+    assert_bits_eq(report.breakdown.logup, 123.63 - 26.0 - 102f64.log2());
+    assert_eq!(report.binding_component, SoundnessComponent::Logup);
+    assert!(report.logup_below_fri());
+    assert!(report
+        .to_string()
+        .contains("warning: logup has 91.0 bits of security, below the 91.9 bits of fri"));
+}
+
+#[test]
+fn test_min_security_bits_refuses_keygen_and_prove() {
+    let mut engine = BabyBearPoseidon2Engine::new(standard_fast_params());
+    engine.min_security_bits = Some(100);
+
+    let mut keygen_builder = engine.keygen_builder();
+    let air_id = keygen_builder.add_air(Arc::new(FibonacciAir));
+    let Err(err) = engine.checked_generate_pk(
+        keygen_builder,
+        &WorkloadBounds {
+            max_log_height: 20,
+            max_interaction_count: 0,
+        },
+    ) else {
+        panic!("keygen below the minimum security was not refused");
+    };
+    assert_eq!(err.min_security_bits, 100);
+    assert!(err.breakdown.total < 100.0);
+    assert!(err
+        .to_string()
+        .contains("bits is below the minimum of 100 bits"));
+
+    // Small traces are within the minimum.
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(Arc::new(FibonacciAir));
+    let pk = engine
+        .checked_generate_pk(
+            keygen_builder,
+            &WorkloadBounds {
+                max_log_height: 4,
+                max_interaction_count: 0,
+            },
+        )
+        .unwrap();
+    let prove = |n: usize, pis: Vec<Val>| {
+        let trace = generate_trace_rows::<Val>(0, 1, n);
+        engine.checked_prove(
+            &pk,
+            ProofInput::new(vec![(air_id, AirProofInput::simple(trace, pis))]),
+        )
+    };
+    let pis = [0, 1, get_fib_number(16)]
+        .map(Val::from_canonical_u32)
+        .to_vec();
+    let proof = prove(16, pis).unwrap();
+    engine.verify(&pk.get_vk(), &proof).unwrap();
+    // Refused before proving, so the public values do not matter.
+    assert!(prove(1 << 20, vec![Val::ZERO; 3]).is_err());
+}
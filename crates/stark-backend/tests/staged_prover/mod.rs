@@ -0,0 +1,116 @@
+use std::{sync::Arc, thread};
+
+use openvm_stark_backend::{
+    p3_field::FieldAlgebra,
+    prover::{
+        staged::StagedProver,
+        types::{AirProofInput, AirProofRawInput, ProofInput},
+    },
+};
+use openvm_stark_sdk::{
+    config::baby_bear_poseidon2::{default_engine, BabyBearPoseidon2Config},
+    dummy_airs::{
+        fib_air::{self, air::FibonacciAir},
+        interaction::dummy_interaction_air::DummyInteractionAir,
+    },
+    engine::StarkEngine,
+};
+use p3_baby_bear::BabyBear;
+use p3_matrix::dense::RowMajorMatrix;
+
+use crate::get_fib_number;
+
+type SC = BabyBearPoseidon2Config;
+type Val = BabyBear;
+
+const N: usize = 16;
+
+/// Input of AIR 0, a Fibonacci AIR, and of AIRs 1 and 2, which send and receive the same
+/// messages with the fields in a cached main trace.
+fn air_proof_input(air_id: usize) -> AirProofInput<SC> {
+    if air_id == 0 {
+        return AirProofInput::simple(
+            fib_air::trace::generate_trace_rows::<Val>(0, 1, N),
+            [0, 1, get_fib_number(N)]
+                .map(Val::from_canonical_u32)
+                .to_vec(),
+        );
+    }
+    let count = (0..4).map(Val::from_canonical_u32).collect();
+    let fields = [5, 7, 9, 5].map(Val::from_canonical_u32).to_vec();
+    AirProofInput {
+        cached_mains_pdata: vec![],
+        raw: AirProofRawInput {
+            cached_mains: vec![Arc::new(RowMajorMatrix::new(fields, 1))],
+            common_main: Some(RowMajorMatrix::new(count, 1)),
+            custom_mains: vec![],
+            public_values: vec![],
+        },
+    }
+}
+
+#[test]
+fn test_staged_prover_matches_prove() {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(Arc::new(FibonacciAir));
+    keygen_builder.add_air(Arc::new(DummyInteractionAir::new(1, true, 0).partition()));
+    keygen_builder.add_air(Arc::new(DummyInteractionAir::new(1, false, 0).partition()));
+    let pk = keygen_builder.generate_pk();
+    let vk = pk.get_vk();
+
+    let expected = engine.prove(
+        &pk,
+        ProofInput::new(
+            (0..3)
+                .map(|air_id| (air_id, air_proof_input(air_id)))
+                .collect(),
+        ),
+    );
+    let staged_prover = StagedProver::new(&engine, &pk, [2, 0, 1]);
+    for _ in 0..2 {
+        let proof = thread::scope(|s| {
+            for air_id in [2, 0, 1] {
+                let staged_prover = &staged_prover;
+                s.spawn(move || staged_prover.stage_trace(air_id, air_proof_input(air_id)));
+            }
+            staged_prover.prove()
+        });
+        assert_eq!(
+            bincode::serialize(&proof).unwrap(),
+            bincode::serialize(&expected).unwrap()
+        );
+        engine.verify(&vk, &proof).expect("Verification failed");
+    }
+}
+
+#[test]
+#[should_panic(expected = "AIR FibonacciAir (air_id = 0) is already staged")]
+fn test_staged_prover_stage_twice() {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(Arc::new(FibonacciAir));
+    let pk = keygen_builder.generate_pk();
+    let staged_prover = StagedProver::new(&engine, &pk, [0]);
+    staged_prover.stage_trace(0, air_proof_input(0));
+    staged_prover.stage_trace(0, air_proof_input(0));
+}
+
+#[test]
+#[should_panic(expected = "(air_id = 1) panicked")]
+fn test_staged_prover_staging_panics() {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(Arc::new(FibonacciAir));
+    keygen_builder.add_air(Arc::new(DummyInteractionAir::new(1, true, 0).partition()));
+    let pk = keygen_builder.generate_pk();
+    let staged_prover = StagedProver::new(&engine, &pk, [0, 1]);
+    staged_prover.stage_trace(0, air_proof_input(0));
+    // A cached main trace whose height is not a power of two cannot be committed.
+    let mut input = air_proof_input(1);
+    input.raw.cached_mains = vec![Arc::new(RowMajorMatrix::new(vec![Val::ONE; 3], 1))];
+    let staging = thread::scope(|s| s.spawn(|| staged_prover.stage_trace(1, input)).join());
+    assert!(staging.is_err());
+    // Instead of waiting for AIR 1 forever.
+    staged_prover.prove();
+}
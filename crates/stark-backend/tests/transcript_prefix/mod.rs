@@ -0,0 +1,63 @@
+use std::sync::Arc;
+
+use openvm_stark_backend::{
+    p3_field::FieldAlgebra,
+    prover::types::{AirProofInput, ProofInput},
+};
+use openvm_stark_sdk::{
+    config::baby_bear_poseidon2::{default_engine, BabyBearPoseidon2Config},
+    dummy_airs::fib_air::{self, air::FibonacciAir},
+    engine::StarkEngine,
+};
+use p3_baby_bear::BabyBear;
+
+use crate::get_fib_number;
+
+type SC = BabyBearPoseidon2Config;
+type Val = BabyBear;
+
+/// Input of the Fibonacci AIR with `n` rows.
+fn proof_input(n: usize) -> ProofInput<SC> {
+    ProofInput::new(vec![(
+        0,
+        AirProofInput::simple(
+            fib_air::trace::generate_trace_rows::<Val>(0, 1, n),
+            [0, 1, get_fib_number(n)]
+                .map(Val::from_canonical_u32)
+                .to_vec(),
+        ),
+    )])
+}
+
+#[test]
+fn test_chained_proofs() {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(Arc::new(FibonacciAir));
+    let pk = keygen_builder.generate_pk();
+    let vk = pk.get_vk();
+
+    let proof_1 = engine.prove(&pk, proof_input(16));
+    engine.verify(&vk, &proof_1).expect("Verification failed");
+    // Proof 2 is bound to the main trace commitments of proof 1.
+    let prefix = proof_1.commitments.main_trace.clone();
+    let proof_2 = engine.prove_with_transcript_prefix(&pk, proof_input(8), &prefix);
+    engine
+        .verify_with_transcript_prefix(&vk, &proof_2, &prefix)
+        .expect("Verification failed");
+    assert!(engine.verify(&vk, &proof_2).is_err());
+
+    // Proof 1 replaced by another proof.
+    let other_proof_1 = engine.prove(&pk, proof_input(32));
+    let other_prefix = other_proof_1.commitments.main_trace.clone();
+    assert_ne!(prefix, other_prefix);
+    assert!(engine
+        .verify_with_transcript_prefix(&vk, &proof_2, &other_prefix)
+        .is_err());
+
+    // An empty prefix leaves the transcript unchanged.
+    assert_eq!(
+        bincode::serialize(&engine.prove_with_transcript_prefix(&pk, proof_input(8), &[])).unwrap(),
+        bincode::serialize(&engine.prove(&pk, proof_input(8))).unwrap()
+    );
+}
@@ -0,0 +1,482 @@
+use std::{
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::Arc,
+};
+
+use openvm_stark_backend::{
+    config::StarkGenericConfig,
+    engine::{StarkEngine, VerificationData},
+    interaction::fri_log_up::{FriLogUpError, FriLogUpPartialProof},
+    keygen::{types::CommitmentRound, TraceHeightTooLargeError},
+    p3_field::{FieldAlgebra, FieldExtensionAlgebra},
+    proof::Proof,
+    prover::types::{AirProofInput, ProofInput},
+    verifier::{
+        AirLogUpContribution, LogUpSumReport, MultiTraceStarkVerifier, ProofShapeError,
+        VerificationError,
+    },
+    Chip,
+};
+use openvm_stark_sdk::{
+    config::baby_bear_poseidon2::{default_engine, BabyBearPoseidon2Config},
+    dummy_airs::{
+        fib_air::{air::FibonacciAir, trace::generate_trace_rows},
+        interaction::dummy_interaction_air::{DummyInteractionChip, DummyInteractionData},
+    },
+};
+use p3_baby_bear::BabyBear;
+
+use crate::get_fib_number;
+
+type SC = BabyBearPoseidon2Config;
+type Challenge = <SC as StarkGenericConfig>::Challenge;
+
+const N: usize = 8;
+
+/// Proves a Fibonacci AIR, and a sender and a receiver of the same messages, with AIR ids 0, 1
+/// and 2.
+fn prove() -> VerificationData<SC> {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    let mut send_chip = DummyInteractionChip::new_without_partition(1, true, 0);
+    let mut recv_chip = DummyInteractionChip::new_without_partition(1, false, 0);
+    keygen_builder.add_air(Arc::new(FibonacciAir));
+    keygen_builder.add_air(send_chip.air());
+    keygen_builder.add_air(recv_chip.air());
+    let pk = keygen_builder.generate_pk();
+
+    let data = DummyInteractionData {
+        count: vec![1, 2, 4, 0],
+        fields: vec![vec![1], vec![2], vec![3], vec![0]],
+    };
+    send_chip.load_data(data.clone());
+    recv_chip.load_data(data);
+    let fib_pis = [0, 1, get_fib_number(N)]
+        .map(BabyBear::from_canonical_u32)
+        .to_vec();
+    let proof = engine.prove(
+        &pk,
+        ProofInput::new(vec![
+            (
+                0,
+                AirProofInput::simple(generate_trace_rows(0, 1, N), fib_pis),
+            ),
+            send_chip.generate_air_proof_input_with_id(1),
+            recv_chip.generate_air_proof_input_with_id(2),
+        ]),
+    );
+    let vk = pk.get_vk();
+    engine.verify(&vk, &proof).expect("Verification failed");
+    VerificationData { vk, proof }
+}
+
+fn verify(
+    data: &VerificationData<SC>,
+    proof: &Proof<SC>,
+    constraints_before_opening: bool,
+) -> Result<(), VerificationError> {
+    let engine = default_engine();
+    engine
+        .verifier()
+        .with_constraints_before_opening(constraints_before_opening)
+        .verify(&mut engine.new_challenger(), &data.vk, proof)
+}
+
+#[test]
+fn test_quotient_chunk_mismatch_names_air() {
+    let data = prove();
+    let mut proof = data.proof.clone();
+    proof.core.opened_values.quotient[1][0][0] += Challenge::ONE;
+    let err = verify(&data, &proof, true).unwrap_err();
+    assert_eq!(
+        err,
+        VerificationError::OodEvaluationMismatch {
+            air_id: 1,
+            air_name: "DummyInteractionAir".to_string(),
+        }
+    );
+    assert_eq!(
+        err.to_string(),
+        "AIR DummyInteractionAir (air_id = 1): out-of-domain evaluation mismatch"
+    );
+    // Without checking the constraints first, the opening proof is verified first.
+    assert!(matches!(
+        verify(&data, &proof, false),
+        Err(VerificationError::InvalidOpeningArgument { .. })
+    ));
+}
+
+#[test]
+fn test_invalid_opening_argument_locates_query() {
+    let data = prove();
+    let mut proof = data.proof.clone();
+    proof.pcs_proof.query_proofs[1].commit_phase_openings[0].sibling_value += Challenge::ONE;
+    let err = verify(&data, &proof, true).unwrap_err();
+    assert!(
+        matches!(
+            err,
+            VerificationError::InvalidOpeningArgument { query: Some(1), .. }
+        ),
+        "{err:?}"
+    );
+    assert!(err
+        .to_string()
+        .starts_with("invalid opening argument at query 1: "));
+
+    // Without the shape of the PCS, the verifier cannot locate the query.
+    let engine = default_engine();
+    assert!(matches!(
+        MultiTraceStarkVerifier::new(engine.config()).verify(
+            &mut engine.new_challenger(),
+            &data.vk,
+            &proof
+        ),
+        Err(VerificationError::InvalidOpeningArgument { query: None, .. })
+    ));
+}
+
+#[test]
+fn test_truncated_opened_values_name_air() {
+    let data = prove();
+    let receiver_vk = &data.vk.per_air[2];
+    let mut proof = data.proof.clone();
+    proof.core.opened_values.main.last_mut().unwrap()[2]
+        .local
+        .pop();
+    let expected = ProofShapeError::OpenedValues {
+        air_id: 2,
+        air_name: "DummyInteractionAir".to_string(),
+        round: CommitmentRound::CommonMain,
+        width: receiver_vk.params.width.common_main,
+        num_rotations: 0,
+    };
+    for constraints_before_opening in [false, true] {
+        assert_eq!(
+            verify(&data, &proof, constraints_before_opening),
+            Err(VerificationError::InvalidProofShape(expected.clone()))
+        );
+    }
+    assert_eq!(
+        VerificationError::from(expected).to_string(),
+        format!(
+            "invalid proof shape: opened values of AIR DummyInteractionAir (air_id = 2) in the \
+             common main commitment are not {} wide at the local, next and 0 rotated rows",
+            receiver_vk.params.width.common_main
+        )
+    );
+
+    let mut proof = data.proof.clone();
+    proof.core.opened_values.quotient[2].pop();
+    assert_eq!(
+        verify(&data, &proof, false),
+        Err(VerificationError::InvalidProofShape(
+            ProofShapeError::QuotientChunks {
+                air_id: 2,
+                air_name: "DummyInteractionAir".to_string(),
+                num_chunks: receiver_vk.quotient_degree as usize,
+                chunk_len: <Challenge as FieldExtensionAlgebra<BabyBear>>::D,
+            }
+        ))
+    );
+
+    let mut proof = data.proof.clone();
+    proof.core.opened_values.after_challenge[0].pop();
+    assert_eq!(
+        verify(&data, &proof, false),
+        Err(VerificationError::InvalidProofShape(
+            ProofShapeError::NumOpenedMatrices {
+                round: CommitmentRound::AfterChallenge { phase: 0 },
+                expected: 2,
+                found: 1,
+            }
+        ))
+    );
+}
+
+#[test]
+fn test_challenge_phase_error_is_rendered() {
+    let data = prove();
+    let mut proof = data.proof.clone();
+    proof.core.per_air[2].exposed_values_after_challenge[0][0] += Challenge::ONE;
+    let err = verify(&data, &proof, true).unwrap_err();
+    let sums =
+        [1, 2].map(|air_idx| proof.core.per_air[air_idx].exposed_values_after_challenge[0][0]);
+    let contribution =
+        |air_id: usize, air_name: &str, exposed_values: Vec<String>| AirLogUpContribution {
+            air_id,
+            air_name: air_name.to_string(),
+            exposed_values,
+        };
+    assert_eq!(
+        err,
+        VerificationError::LogUpSumMismatch {
+            error: FriLogUpError::NonZeroCumulativeSum.to_string(),
+            report: LogUpSumReport {
+                per_air: vec![
+                    contribution(0, "FibonacciAir", vec![]),
+                    contribution(1, "DummyInteractionAir", vec![sums[0].to_string()]),
+                    contribution(2, "DummyInteractionAir", vec![sums[1].to_string()]),
+                ],
+                // Both sides of the bus are in the proof.
+                unbalanced_buses: vec![],
+            },
+        }
+    );
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "challenge phase error: non-zero cumulative sum\n  \
+             Exposed values per AIR:\n    \
+             AIR FibonacciAir (air_id = 0): []\n    \
+             AIR DummyInteractionAir (air_id = 1): [{}]\n    \
+             AIR DummyInteractionAir (air_id = 2): [{}]",
+            sums[0], sums[1]
+        )
+    );
+}
+
+#[test]
+fn test_malformed_proofs_are_rejected_without_panic() {
+    let data = prove();
+    let mutations: [(&str, fn(&mut Proof<SC>)); 23] = [
+        ("no common main commitment", |proof| {
+            proof.commitments.main_trace.pop();
+        }),
+        ("extra main commitment", |proof| {
+            let commit = proof.commitments.main_trace[0];
+            proof.commitments.main_trace.push(commit);
+        }),
+        ("extra after challenge commitment", |proof| {
+            let commit = proof.commitments.after_challenge[0];
+            proof.commitments.after_challenge.push(commit);
+        }),
+        ("extra opened main round", |proof| {
+            let values = proof.core.opened_values.main[0].clone();
+            proof.core.opened_values.main.push(values);
+        }),
+        ("missing common main matrix", |proof| {
+            proof.core.opened_values.main[0].pop();
+        }),
+        ("extra common main matrix", |proof| {
+            let values = proof.core.opened_values.main[0][0].clone();
+            proof.core.opened_values.main[0].push(values);
+        }),
+        ("extra value in the next row", |proof| {
+            proof.core.opened_values.main[0][0]
+                .next
+                .push(Challenge::ZERO);
+        }),
+        ("extra rotated row", |proof| {
+            proof.core.opened_values.main[0][1].rotated.push(vec![]);
+        }),
+        ("truncated after challenge row", |proof| {
+            proof.core.opened_values.after_challenge[0][0].local.pop();
+        }),
+        ("extra value in an after challenge row", |proof| {
+            proof.core.opened_values.after_challenge[0][1]
+                .local
+                .push(Challenge::ZERO);
+        }),
+        ("missing quotient", |proof| {
+            proof.core.opened_values.quotient.pop();
+        }),
+        ("extra value in a quotient chunk", |proof| {
+            proof.core.opened_values.quotient[0][0].push(Challenge::ZERO);
+        }),
+        ("extra quotient chunk", |proof| {
+            let chunk = proof.core.opened_values.quotient[1][0].clone();
+            proof.core.opened_values.quotient[1].push(chunk);
+        }),
+        ("missing public value", |proof| {
+            proof.core.per_air[0].public_values.pop();
+        }),
+        ("extra public value", |proof| {
+            proof.core.per_air[0].public_values.push(BabyBear::ZERO);
+        }),
+        ("extra challenge phase", |proof| {
+            proof.core.per_air[1]
+                .exposed_values_after_challenge
+                .push(vec![]);
+        }),
+        ("exposed value without interactions", |proof| {
+            proof.core.per_air[0]
+                .exposed_values_after_challenge
+                .push(vec![Challenge::ONE]);
+        }),
+        ("extra exposed value", |proof| {
+            proof.core.per_air[1].exposed_values_after_challenge[0].push(Challenge::ZERO);
+        }),
+        ("missing partial proof of the challenge phase", |proof| {
+            proof.rap_phase_seq_proof = None;
+        }),
+        ("zero trace height", |proof| {
+            proof.core.per_air[0].degree = 0
+        }),
+        ("trace height not a power of two", |proof| {
+            proof.core.per_air[0].degree = 3;
+        }),
+        ("missing FRI query proof", |proof| {
+            proof.pcs_proof.query_proofs.pop();
+        }),
+    ];
+    let pcs_shape = default_engine().pcs_shape();
+    for (name, mutate) in mutations {
+        let mut proof = data.proof.clone();
+        mutate(&mut proof);
+        assert!(
+            matches!(
+                proof.validate_shape(&data.vk, pcs_shape.as_ref()),
+                Err(VerificationError::InvalidProofShape(_))
+            ),
+            "{name}"
+        );
+        for constraints_before_opening in [false, true] {
+            let result = catch_unwind(AssertUnwindSafe(|| {
+                verify(&data, &proof, constraints_before_opening)
+            }))
+            .unwrap_or_else(|_| panic!("verifying a proof with {name} panicked"));
+            assert!(
+                matches!(result, Err(VerificationError::InvalidProofShape(_))),
+                "{name}: {result:?}"
+            );
+        }
+    }
+
+    // The verifier rejects a trace above the maximum height of the key, which defaults to the
+    // two-adicity, before the shape of the proof.
+    let mut proof = data.proof.clone();
+    proof.core.per_air[0].degree = 1 << 40;
+    assert!(matches!(
+        proof.validate_shape(&data.vk, pcs_shape.as_ref()),
+        Err(VerificationError::InvalidProofShape(_))
+    ));
+    for constraints_before_opening in [false, true] {
+        assert_eq!(
+            verify(&data, &proof, constraints_before_opening),
+            Err(VerificationError::TraceHeightTooLarge(
+                TraceHeightTooLargeError {
+                    air_id: 0,
+                    air_name: "FibonacciAir".to_string(),
+                    height: 1 << 40,
+                    max_log_height: 27,
+                }
+            ))
+        );
+    }
+
+    // The committed domain of a trace at the two-adicity is above it with the blowup.
+    let mut proof = data.proof.clone();
+    proof.core.per_air[0].degree = 1 << 27;
+    assert_eq!(
+        proof.validate_shape(&data.vk, pcs_shape.as_ref()),
+        Err(VerificationError::InvalidProofShape(
+            ProofShapeError::TraceHeightAboveTwoAdicity {
+                air_id: 0,
+                air_name: "FibonacciAir".to_string(),
+                height: 1 << 27,
+                log_blowup: 1,
+                two_adicity: 27,
+            }
+        ))
+    );
+
+    let mut proof = data.proof.clone();
+    proof.pcs_proof.query_proofs.pop();
+    let num_queries = data.proof.pcs_proof.query_proofs.len();
+    assert_eq!(
+        proof.validate_shape(&data.vk, pcs_shape.as_ref()),
+        Err(VerificationError::InvalidProofShape(
+            ProofShapeError::NumEntries {
+                field: "pcs_proof.query_proofs",
+                expected: num_queries,
+                found: num_queries - 1,
+            }
+        ))
+    );
+}
+
+#[test]
+fn test_surplus_partial_proof_is_rejected() {
+    let engine = default_engine();
+    let data = prove();
+    // A proof of the Fibonacci AIR alone, which has no interactions, with the key of [prove].
+    let fib_pis = [0, 1, get_fib_number(N)]
+        .map(BabyBear::from_canonical_u32)
+        .to_vec();
+    let proof_input = ProofInput::new(vec![(
+        0,
+        AirProofInput::simple(generate_trace_rows(0, 1, N), fib_pis),
+    )]);
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(Arc::new(FibonacciAir));
+    keygen_builder.add_air(DummyInteractionChip::<SC>::new_without_partition(1, true, 0).air());
+    keygen_builder.add_air(DummyInteractionChip::<SC>::new_without_partition(1, false, 0).air());
+    let mut proof = engine.prove(&keygen_builder.generate_pk(), proof_input);
+    assert!(proof.rap_phase_seq_proof.is_none());
+    engine
+        .verify(&data.vk, &proof)
+        .expect("Verification failed");
+
+    proof.rap_phase_seq_proof = Some(FriLogUpPartialProof {
+        public_lookup_multiplicities: vec![vec![BabyBear::ONE]],
+    });
+    let expected = Err(VerificationError::InvalidProofShape(
+        ProofShapeError::NumEntries {
+            field: "rap_phase_seq_proof",
+            expected: 0,
+            found: 1,
+        },
+    ));
+    assert_eq!(
+        proof.validate_shape(&data.vk, default_engine().pcs_shape().as_ref()),
+        expected
+    );
+    for constraints_before_opening in [false, true] {
+        assert_eq!(verify(&data, &proof, constraints_before_opening), expected);
+    }
+}
+
+#[test]
+fn test_mutated_serialized_proofs_are_rejected_without_panic() {
+    let data = prove();
+    let bytes = bincode::serialize(&data.proof).unwrap();
+    for len in (0..bytes.len()).step_by(61) {
+        assert!(
+            bincode::deserialize::<Proof<SC>>(&bytes[..len]).is_err(),
+            "proof truncated to {len} bytes"
+        );
+    }
+    let check = |mutated: &[u8], name: String| {
+        // Decoding is up to the serde impls of the field and the PCS, only the verifier must
+        // reject what they decode without panicking.
+        let Ok(Ok(proof)) = catch_unwind(|| bincode::deserialize::<Proof<SC>>(mutated)) else {
+            return;
+        };
+        // A mutation may decode to the same proof, e.g. a trailing byte.
+        if bincode::serialize(&proof).unwrap() == bytes {
+            return;
+        }
+        for constraints_before_opening in [false, true] {
+            let result = catch_unwind(AssertUnwindSafe(|| {
+                verify(&data, &proof, constraints_before_opening)
+            }))
+            .unwrap_or_else(|_| panic!("verifying a proof with {name} panicked"));
+            assert!(result.is_err(), "a proof with {name} was accepted");
+        }
+    };
+    for index in (0..bytes.len()).step_by(37) {
+        let mut flipped = bytes.clone();
+        flipped[index] ^= 1;
+        check(&flipped, format!("byte {index} flipped"));
+        let mut extended = bytes.clone();
+        extended.insert(index, 0);
+        check(&extended, format!("a byte inserted at {index}"));
+    }
+    let mut extended = bytes.clone();
+    extended.push(0);
+    check(&extended, "a trailing byte".to_string());
+
+    let proof: Proof<SC> = bincode::deserialize(&bytes).unwrap();
+    let pcs_shape = default_engine().pcs_shape();
+    proof.validate_shape(&data.vk, pcs_shape.as_ref()).unwrap();
+}
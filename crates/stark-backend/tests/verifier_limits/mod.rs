@@ -0,0 +1,154 @@
+use openvm_stark_backend::{
+    engine::{StarkEngine, VerificationData},
+    p3_field::TwoAdicField,
+    proof::Proof,
+    verifier::{VerificationError, VerifierLimitError, VerifierLimits, DEFAULT_MAX_PROOF_BYTES},
+};
+use openvm_stark_sdk::{
+    config::baby_bear_poseidon2::{default_engine, BabyBearPoseidon2Config},
+    dummy_airs::fib_lookup,
+};
+use p3_baby_bear::BabyBear;
+
+type SC = BabyBearPoseidon2Config;
+type Val = BabyBear;
+
+const N: usize = 16;
+
+/// Proves a Fibonacci AIR, and a sender and a receiver of the same messages.
+fn prove() -> VerificationData<SC> {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    fib_lookup::add_airs(&mut keygen_builder);
+    let pk = keygen_builder.generate_pk();
+    let proof = engine.prove(&pk, fib_lookup::proof_input(N));
+    VerificationData {
+        vk: pk.get_vk(),
+        proof,
+    }
+}
+
+fn verify(
+    data: &VerificationData<SC>,
+    proof: &Proof<SC>,
+    limits: VerifierLimits,
+) -> Result<(), VerificationError> {
+    let engine = default_engine();
+    engine
+        .verifier()
+        .with_limits(limits)
+        .verify(&mut engine.new_challenger(), &data.vk, proof)
+}
+
+#[test]
+fn test_verifier_limits_from_vk() {
+    let data = prove();
+    let limits = VerifierLimits::from_vk(&data.vk);
+    assert_eq!(limits.max_proof_bytes, DEFAULT_MAX_PROOF_BYTES);
+    assert_eq!(limits.max_airs, 3);
+    // No AIR bounds its trace height, so each is bounded by the two-adicity of the field.
+    assert_eq!(limits.max_total_log_height, 3 * Val::TWO_ADICITY);
+    // The proof is for all the AIRs of the key, so it opens as many values as the key allows.
+    assert_eq!(
+        data.proof.core.opened_values.num_values(),
+        limits.max_opened_values
+    );
+    verify(&data, &data.proof, limits).expect("Verification failed");
+}
+
+#[test]
+fn test_verifier_limits_exceeded() {
+    let data = prove();
+    let proof = &data.proof;
+    // The tightest limits the proof is within.
+    let tight = VerifierLimits {
+        max_proof_bytes: bincode::serialize(proof).unwrap().len(),
+        max_total_log_height: proof.log_trace_heights().iter().map(|&(_, log)| log).sum(),
+        max_airs: 3,
+        max_opened_values: proof.core.opened_values.num_values(),
+    };
+    verify(&data, proof, tight).expect("Verification failed");
+
+    let limit_error = |limits: VerifierLimits, proof: &Proof<SC>| match verify(&data, proof, limits)
+    {
+        Err(VerificationError::LimitExceeded(err)) => err,
+        res => panic!("expected a limit error, got {res:?}"),
+    };
+    assert_eq!(
+        limit_error(
+            VerifierLimits {
+                max_proof_bytes: tight.max_proof_bytes - 1,
+                ..tight
+            },
+            proof
+        ),
+        VerifierLimitError::ProofBytes {
+            max: tight.max_proof_bytes - 1,
+            found: tight.max_proof_bytes,
+        }
+    );
+    assert_eq!(
+        limit_error(
+            VerifierLimits {
+                max_airs: 2,
+                ..tight
+            },
+            proof
+        ),
+        VerifierLimitError::Airs { max: 2, found: 3 }
+    );
+    assert_eq!(
+        limit_error(
+            VerifierLimits {
+                max_opened_values: tight.max_opened_values - 1,
+                ..tight
+            },
+            proof
+        ),
+        VerifierLimitError::OpenedValues {
+            max: tight.max_opened_values - 1,
+            found: tight.max_opened_values,
+        }
+    );
+
+    // A proof claiming the largest trace heights is rejected before its heights are checked
+    // against the key.
+    let mut tall = proof.clone();
+    for air_proof in &mut tall.core.per_air {
+        air_proof.degree = 1 << 31;
+    }
+    let err = limit_error(VerifierLimits::from_vk(&data.vk), &tall);
+    assert_eq!(
+        err,
+        VerifierLimitError::TotalLogHeight {
+            max: 3 * Val::TWO_ADICITY,
+            found: 3 * 31,
+        }
+    );
+    assert_eq!(
+        VerificationError::from(err).to_string(),
+        format!(
+            "verifier limit exceeded: proof has a total log trace height of 93, above the limit \
+             of {}",
+            3 * Val::TWO_ADICITY
+        )
+    );
+}
+
+#[test]
+fn test_engine_verifier_key_limits() {
+    let data = prove();
+    let engine = default_engine();
+    engine
+        .verify(&data.vk, &data.proof)
+        .expect("Verification failed");
+
+    // The limits implied by the key are checked after the trace heights and the shape of the
+    // proof, so the engine reports the more specific error.
+    let mut tall = data.proof.clone();
+    tall.core.per_air[0].degree = 1 << 31;
+    assert!(matches!(
+        engine.verify(&data.vk, &tall),
+        Err(VerificationError::TraceHeightTooLarge(err)) if err.air_id == 0
+    ));
+}
@@ -0,0 +1,87 @@
+use std::sync::Arc;
+
+use openvm_stark_backend::{
+    keygen::types::MultiStarkVerifyingKey,
+    p3_field::{Field, FieldAlgebra},
+    rap::{BaseAirWithPublicValues, PartitionedBaseAir},
+};
+use openvm_stark_sdk::{
+    config::{
+        baby_bear_poseidon2::{BabyBearPoseidon2Config, BabyBearPoseidon2Engine},
+        FriParameters,
+    },
+    dummy_airs::{
+        fib_air::air::FibonacciAir, interaction::dummy_interaction_air::DummyInteractionAir,
+    },
+    engine::{StarkEngine, StarkFriEngine},
+};
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_matrix::Matrix;
+
+type SC = BabyBearPoseidon2Config;
+
+/// Constrains `y = x^2 + offset` on every row.
+///
+/// Main columns: `x, y`.
+pub struct SquareAir {
+    pub offset: u32,
+}
+
+impl<F: Field> BaseAir<F> for SquareAir {
+    fn width(&self) -> usize {
+        2
+    }
+}
+impl<F: Field> BaseAirWithPublicValues<F> for SquareAir {}
+impl<F: Field> PartitionedBaseAir<F> for SquareAir {}
+
+impl<AB: AirBuilder> Air<AB> for SquareAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        let (x, y) = (local[0], local[1]);
+        builder.assert_eq(y, x * x + AB::Expr::from_canonical_u32(self.offset));
+    }
+}
+
+fn keygen(engine: &BabyBearPoseidon2Engine, offset: u32) -> MultiStarkVerifyingKey<SC> {
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(Arc::new(FibonacciAir));
+    keygen_builder.add_air(Arc::new(SquareAir { offset }));
+    keygen_builder.add_air(Arc::new(DummyInteractionAir::new(2, true, 0)));
+    keygen_builder.add_air(Arc::new(DummyInteractionAir::new(2, false, 0)));
+    keygen_builder.generate_pk().get_vk()
+}
+
+#[test]
+fn test_vk_digest() {
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+    let vk = keygen(&engine, 1);
+    let digest = engine.vk_digest(&vk);
+    // Two independent keygens of the same AIRs.
+    assert_eq!(engine.vk_digest(&keygen(&engine, 1)), digest);
+    assert_eq!(keygen(&engine, 1).canonical_bytes(), vk.canonical_bytes());
+
+    // A different constraint.
+    assert_ne!(engine.vk_digest(&keygen(&engine, 2)), digest);
+
+    // Different FRI parameters, with the same key.
+    let other_engine = BabyBearPoseidon2Engine::new(FriParameters {
+        num_queries: engine.fri_params().num_queries + 1,
+        ..engine.fri_params()
+    });
+    assert_eq!(
+        keygen(&other_engine, 1).canonical_bytes(),
+        vk.canonical_bytes()
+    );
+    assert_ne!(other_engine.vk_digest(&vk), digest);
+}
+
+#[test]
+fn test_vk_digest_ignores_bus_names() {
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+    let vk = keygen(&engine, 1);
+    let mut named_vk = vk.clone();
+    named_vk.bus_registry.register("pairs", 2);
+    assert_eq!(engine.vk_digest(&named_vk), engine.vk_digest(&vk));
+}
@@ -0,0 +1,89 @@
+use std::sync::Arc;
+
+use openvm_stark_backend::{
+    keygen::{
+        types::{MatrixOrdering, MultiStarkVerifyingKey, VkFeature, VkFeatureSet},
+        UnsupportedVkFeaturesError,
+    },
+    p3_field::FieldAlgebra,
+    proof::Proof,
+    prover::types::{AirProofInput, ProofInput},
+    verifier::VerificationError,
+};
+use openvm_stark_sdk::{
+    config::baby_bear_poseidon2::{default_engine, BabyBearPoseidon2Config},
+    dummy_airs::fib_air::{air::FibonacciAir, trace::generate_trace_rows},
+    engine::StarkEngine,
+};
+use p3_baby_bear::BabyBear;
+
+use crate::get_fib_number;
+
+type SC = BabyBearPoseidon2Config;
+type Val = BabyBear;
+
+fn prove_fib(matrix_ordering: MatrixOrdering) -> (MultiStarkVerifyingKey<SC>, Proof<SC>) {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.set_matrix_ordering(matrix_ordering);
+    let air_id = keygen_builder.add_air(Arc::new(FibonacciAir));
+    let pk = keygen_builder.generate_pk();
+
+    let n = 8;
+    let pis = [0, 1, get_fib_number(n)]
+        .map(Val::from_canonical_u32)
+        .to_vec();
+    let trace = generate_trace_rows::<Val>(0, 1, n);
+    let proof = engine.prove(
+        &pk,
+        ProofInput::new(vec![(air_id, AirProofInput::simple(trace, pis))]),
+    );
+    (pk.get_vk(), proof)
+}
+
+#[test]
+fn test_default_vk_has_no_features() {
+    let (vk, proof) = prove_fib(MatrixOrdering::Natural);
+    assert!(vk.features.is_empty());
+    default_engine()
+        .verify(&vk, &proof)
+        .expect("Verification failed");
+}
+
+#[test]
+fn test_vk_features_populated_by_keygen() {
+    let (vk, proof) = prove_fib(MatrixOrdering::HeightDescending);
+    assert_eq!(
+        vk.features,
+        VkFeatureSet::from_iter([VkFeature::MatrixOrdering])
+    );
+    default_engine()
+        .verify(&vk, &proof)
+        .expect("Verification failed");
+
+    // A verifier without support for the matrix ordering rejects the key.
+    let mut supported = VkFeatureSet::supported();
+    supported.remove(VkFeature::MatrixOrdering);
+    let err = vk.features.check_supported(supported).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "unsupported vk features: [matrix_ordering]"
+    );
+}
+
+#[test]
+fn test_unknown_vk_feature_rejected() {
+    let (mut vk, proof) = prove_fib(MatrixOrdering::Natural);
+    // A feature added in a later version, which this verifier does not know.
+    vk.features = VkFeatureSet::from_bits(1 << 40);
+    let vk: MultiStarkVerifyingKey<SC> =
+        serde_json::from_slice(&serde_json::to_vec(&vk).unwrap()).unwrap();
+    assert_eq!(
+        default_engine().verify(&vk, &proof),
+        Err(VerificationError::UnsupportedVkFeatures(
+            UnsupportedVkFeaturesError {
+                features: vec!["unknown(40)".to_string()],
+            }
+        ))
+    );
+}
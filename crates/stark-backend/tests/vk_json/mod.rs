@@ -0,0 +1,266 @@
+use std::sync::Arc;
+
+use openvm_stark_backend::{
+    air_builders::symbolic::{SymbolicConstraintsDag, SymbolicExpressionDag},
+    interaction::{
+        bus::BusRegistry, fri_log_up::FriLogUpParams, public_lookup::PublicLookupTable,
+        Interaction, InteractionType, RapPhaseSeqKind,
+    },
+    keygen::{
+        json::VK_JSON_SCHEMA_VERSION,
+        types::{
+            AllowedValues, CommitmentRound, MatrixOrdering, MultiStarkVerifyingKey,
+            StarkVerifyingKey, StarkVerifyingParams, TraceWidth, VerifierSinglePreprocessedData,
+            VkFeature,
+        },
+        VkJsonError,
+    },
+    p3_field::FieldAlgebra,
+    prover::types::{AirProofInput, ProofInput},
+};
+use openvm_stark_sdk::{
+    config::baby_bear_poseidon2::{default_engine, BabyBearPoseidon2Config},
+    dummy_airs::{
+        fib_air::{air::FibonacciAir, trace::generate_trace_rows},
+        interaction::dummy_interaction_air::DummyInteractionAir,
+    },
+    engine::{StarkEngine, StarkFriEngine},
+};
+use p3_baby_bear::BabyBear;
+
+use crate::{fib_selector_air::air::FibonacciSelectorAir, get_fib_number};
+
+type SC = BabyBearPoseidon2Config;
+type Val = BabyBear;
+
+const PCS_PARAMS: [(&str, usize); 2] = [("log_blowup", 1), ("num_queries", 100)];
+
+/// A key using most of the schema, built by hand so that the golden file does not depend on
+/// keygen. It has no commitments, whose encoding depends on the hash of the config.
+fn golden_vk() -> MultiStarkVerifyingKey<SC> {
+    let constraints = SymbolicExpressionDag::from_bytes(include_bytes!(
+        "../fixtures/symbolic_expression_dag_v1.bin"
+    ))
+    .unwrap();
+    let mut bus_registry = BusRegistry::default();
+    bus_registry.register("pairs", 2);
+    let air_vk = StarkVerifyingKey {
+        preprocessed_data: None,
+        params: StarkVerifyingParams {
+            width: TraceWidth {
+                preprocessed: None,
+                cached_mains: vec![4],
+                common_main: 301,
+                after_challenge: vec![6],
+                custom: vec![(0, 3)],
+            },
+            num_public_values: 1,
+            num_exposed_values_after_challenge: vec![1],
+            num_challenges_to_sample: vec![2],
+            extra_rotations: vec![2],
+            configuration_public_values: vec![(0, AllowedValues::Range { start: 0, end: 7 })],
+            max_log_height: Some(20),
+            may_be_absent: false,
+        },
+        symbolic_constraints: SymbolicConstraintsDag {
+            constraints,
+            interactions: vec![Interaction {
+                fields: vec![1, 2],
+                count: 6,
+                bus_index: 0,
+                interaction_type: InteractionType::Send,
+            }],
+        },
+        quotient_degree: 2,
+        rap_phase_seq_kind: RapPhaseSeqKind::FriLogUp,
+        log_up_params: FriLogUpParams {
+            batch_size: None,
+            num_repetitions: 1,
+            commit_inverses: false,
+        },
+        periodic_columns: vec![vec![Val::ONE, Val::ZERO]],
+        preprocessed_per_height: None,
+        air_name: "golden".to_string(),
+    };
+    MultiStarkVerifyingKey {
+        per_air: vec![air_vk],
+        commitment_rounds: vec![
+            CommitmentRound::Preprocessed,
+            CommitmentRound::CachedMain,
+            CommitmentRound::CommonMain,
+            CommitmentRound::Custom {
+                index: 0,
+                name: "memory".to_string(),
+            },
+            CommitmentRound::AfterChallenge { phase: 0 },
+            CommitmentRound::Quotient,
+        ],
+        matrix_ordering: MatrixOrdering::HeightDescending,
+        features: [
+            VkFeature::CustomRounds,
+            VkFeature::MatrixOrdering,
+            VkFeature::ExtraRotations,
+            VkFeature::ConfigurationPublicValues,
+            VkFeature::PeriodicColumns,
+            VkFeature::PublicLookupTables,
+            VkFeature::MaxTraceHeight,
+        ]
+        .into_iter()
+        .collect(),
+        bus_registry,
+        public_lookup_tables: vec![PublicLookupTable {
+            bus_index: 1,
+            rows: vec![
+                vec![Val::from_canonical_u32(5)],
+                vec![Val::from_canonical_u32(6)],
+            ],
+        }],
+    }
+}
+
+fn decode(json: &str) -> Result<MultiStarkVerifyingKey<SC>, VkJsonError> {
+    MultiStarkVerifyingKey::<SC>::from_json(json).map(|(vk, _)| vk)
+}
+
+#[test]
+fn test_vk_json_golden() {
+    // Changing the schema breaks external verifiers. Bump `VK_JSON_SCHEMA_VERSION` and add a
+    // new golden file instead of updating this one.
+    let golden = include_str!("../fixtures/vk_json_v3.json");
+    let vk = golden_vk();
+    assert_eq!(vk.to_json(&PCS_PARAMS), golden);
+
+    let (decoded, pcs_params) = MultiStarkVerifyingKey::<SC>::from_json(golden).unwrap();
+    assert_eq!(
+        pcs_params,
+        PCS_PARAMS.map(|(name, value)| (name.to_string(), value))
+    );
+    assert_eq!(decoded.canonical_bytes(), vk.canonical_bytes());
+    assert_eq!(decoded.air_name(0), "golden");
+    assert_eq!(decoded.bus_registry, vk.bus_registry);
+
+    // Version 1 has no AIRs that may be absent, versions 1 and 2 have no unregistered bus
+    // indices, and both are still decoded.
+    let v1 = decode(include_str!("../fixtures/vk_json_v1.json")).unwrap();
+    assert_eq!(v1.canonical_bytes(), vk.canonical_bytes());
+    let v2 = decode(include_str!("../fixtures/vk_json_v2.json")).unwrap();
+    assert_eq!(v2.canonical_bytes(), vk.canonical_bytes());
+    assert_eq!(v2.bus_registry, vk.bus_registry);
+}
+
+#[test]
+fn test_vk_json_commitment_words() {
+    // The words of the digest in order, each in little-endian.
+    const COMMIT: &str = "0x0102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f20";
+    let words = [
+        0x04030201, 0x08070605, 0x0c0b0a09, 0x100f0e0d, 0x14131211, 0x18171615, 0x1c1b1a19,
+        0x201f1e1d,
+    ]
+    .map(Val::from_canonical_u32);
+    let mut vk = golden_vk();
+    vk.per_air[0].params.width.preprocessed = Some(1);
+    vk.per_air[0].preprocessed_data = Some(VerifierSinglePreprocessedData {
+        commit: words.into(),
+    });
+    let json = vk.to_json(&PCS_PARAMS);
+    assert!(json.contains(&format!("\"preprocessed_commit\": \"{COMMIT}\"")));
+
+    let decoded = decode(&json).unwrap();
+    let commit = decoded.per_air[0]
+        .preprocessed_data
+        .as_ref()
+        .unwrap()
+        .commit;
+    assert_eq!(<[Val; 8]>::from(commit), words);
+
+    // The BabyBear modulus, 0x78000001, is not a canonical word.
+    let non_canonical = COMMIT.replacen("01020304", "01000078", 1);
+    assert_eq!(
+        decode(&json.replacen(COMMIT, &non_canonical, 1)).err(),
+        Some(VkJsonError::InvalidCommitment(non_canonical))
+    );
+    let truncated = &COMMIT[..COMMIT.len() - 2];
+    assert_eq!(
+        decode(&json.replacen(COMMIT, truncated, 1)).err(),
+        Some(VkJsonError::InvalidCommitment(truncated.to_string()))
+    );
+}
+
+#[test]
+fn test_vk_json_round_trip() {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    let sels = (0..8).map(|i| i % 2 == 0).collect();
+    keygen_builder.add_air(Arc::new(FibonacciAir));
+    keygen_builder.add_air(Arc::new(FibonacciSelectorAir::new(sels, true)));
+    keygen_builder.add_air(Arc::new(DummyInteractionAir::new(1, true, 0)));
+    keygen_builder.add_air(Arc::new(DummyInteractionAir::new(3, false, 1).partition()));
+    let pk = keygen_builder.generate_pk();
+    let vk = pk.get_vk();
+
+    let json = engine.vk_json(&vk);
+    let decoded = decode(&json).unwrap();
+    assert_eq!(engine.vk_json(&decoded), json);
+    // The digest also binds the preprocessed commitments.
+    assert_eq!(engine.vk_digest(&decoded), engine.vk_digest(&vk));
+
+    let n = 16;
+    let pis = [0, 1, get_fib_number(n)]
+        .map(Val::from_canonical_u32)
+        .to_vec();
+    let trace = generate_trace_rows::<Val>(0, 1, n);
+    let proof = engine.prove(
+        &pk,
+        ProofInput::new(vec![(0, AirProofInput::simple(trace, pis))]),
+    );
+    engine
+        .verify(&decoded, &proof)
+        .expect("Verification failed");
+}
+
+#[test]
+fn test_vk_json_rejected() {
+    let golden = include_str!("../fixtures/vk_json_v3.json");
+    let next_version = golden.replacen(
+        &format!("\"schema_version\": {VK_JSON_SCHEMA_VERSION}"),
+        &format!("\"schema_version\": {}", VK_JSON_SCHEMA_VERSION + 1),
+        1,
+    );
+    assert_eq!(
+        decode(&next_version).err(),
+        Some(VkJsonError::UnsupportedSchemaVersion(
+            VK_JSON_SCHEMA_VERSION + 1
+        ))
+    );
+    // The BabyBear modulus is not a canonical field element.
+    let non_canonical = golden.replacen("\"2013265920\"", "\"2013265921\"", 1);
+    assert_eq!(
+        decode(&non_canonical).err(),
+        Some(VkJsonError::InvalidFieldElement("2013265921".to_string()))
+    );
+    // The negation refers to itself.
+    let self_loop = golden.replacen("\"arg\": 4", "\"arg\": 5", 1);
+    assert_eq!(
+        decode(&self_loop).err(),
+        Some(VkJsonError::InvalidNodeIndex {
+            air_id: 0,
+            air_name: "golden".to_string(),
+            index: 5
+        })
+    );
+    let unknown_feature = golden.replacen("\"custom_rounds\"", "\"teleportation\"", 1);
+    assert_eq!(
+        decode(&unknown_feature).err(),
+        Some(VkJsonError::UnknownFeature("teleportation".to_string()))
+    );
+    assert!(matches!(
+        decode(&golden.replacen("\"quotient_degree\"", "\"quotient_chunks\"", 1)),
+        Err(VkJsonError::Json(_))
+    ));
+    // The first row of the public lookup table has no fields, unlike the second.
+    let ragged_table = golden.replacen("[\n          \"5\"\n        ]", "[]", 1);
+    assert_eq!(
+        decode(&ragged_table).err(),
+        Some(VkJsonError::InvalidPublicLookupTable(1))
+    );
+}
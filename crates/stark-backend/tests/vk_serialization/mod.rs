@@ -0,0 +1,94 @@
+use std::sync::Arc;
+
+use itertools::izip;
+use openvm_stark_backend::{
+    keygen::types::MultiStarkVerifyingKey,
+    p3_field::FieldAlgebra,
+    prover::types::{AirProofInput, ProofInput},
+};
+use openvm_stark_sdk::{
+    config::baby_bear_poseidon2::{default_engine, BabyBearPoseidon2Config},
+    dummy_airs::{
+        fib_air::{air::FibonacciAir, trace::generate_trace_rows},
+        interaction::dummy_interaction_air::DummyInteractionAir,
+    },
+    engine::StarkEngine,
+};
+use p3_baby_bear::BabyBear;
+
+use crate::{fib_selector_air::air::FibonacciSelectorAir, get_fib_number};
+
+type SC = BabyBearPoseidon2Config;
+type Val = BabyBear;
+
+/// Verifying key of a system with a plain AIR, an AIR with interactions and selectors, and
+/// interaction-only AIRs on both sides of a bus.
+fn multi_air_vk() -> MultiStarkVerifyingKey<SC> {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    let sels = (0..8).map(|i| i % 2 == 0).collect();
+    keygen_builder.add_air(Arc::new(FibonacciAir));
+    keygen_builder.add_air(Arc::new(FibonacciSelectorAir::new(sels, true)));
+    keygen_builder.add_air(Arc::new(DummyInteractionAir::new(1, true, 0)));
+    keygen_builder.add_air(Arc::new(DummyInteractionAir::new(3, false, 1)));
+    keygen_builder.generate_pk().get_vk()
+}
+
+#[test]
+fn test_vk_serde_round_trip() {
+    let vk = multi_air_vk();
+    let bytes = serde_json::to_vec(&vk).unwrap();
+    let new_vk: MultiStarkVerifyingKey<SC> = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(new_vk.per_air.len(), vk.per_air.len());
+    for (vk, new_vk) in izip!(&vk.per_air, &new_vk.per_air) {
+        assert_eq!(
+            vk.symbolic_constraints.constraints,
+            new_vk.symbolic_constraints.constraints
+        );
+        assert_eq!(
+            vk.symbolic_constraints.interactions,
+            new_vk.symbolic_constraints.interactions
+        );
+    }
+}
+
+#[test]
+fn test_vk_serde_round_trip_verifies() {
+    let engine = default_engine();
+    let mut keygen_builder = engine.keygen_builder();
+    let air_id = keygen_builder.add_air(Arc::new(FibonacciAir));
+    let pk = keygen_builder.generate_pk();
+    let vk = pk.get_vk();
+
+    let n = 16;
+    let pis = [0, 1, get_fib_number(n)]
+        .map(Val::from_canonical_u32)
+        .to_vec();
+    let trace = generate_trace_rows::<Val>(0, 1, n);
+    let proof = engine.prove(
+        &pk,
+        ProofInput::new(vec![(air_id, AirProofInput::simple(trace, pis))]),
+    );
+
+    let bytes = serde_json::to_vec(&vk).unwrap();
+    let vk: MultiStarkVerifyingKey<SC> = serde_json::from_slice(&bytes).unwrap();
+    engine.verify(&vk, &proof).expect("Verification failed");
+}
+
+#[test]
+fn test_vk_compact_dag_size() {
+    let vk = multi_air_vk();
+    let compact = serde_json::to_value(&vk).unwrap();
+    // The same key with the constraint DAGs in their derived serde encoding.
+    let mut derived = compact.clone();
+    for (i, air_vk) in vk.per_air.iter().enumerate() {
+        derived["per_air"][i]["symbolic_constraints"] =
+            serde_json::to_value(&air_vk.symbolic_constraints).unwrap();
+    }
+    let compact_len = serde_json::to_vec(&compact).unwrap().len();
+    let derived_len = serde_json::to_vec(&derived).unwrap().len();
+    assert!(
+        compact_len < derived_len,
+        "compact vk is {compact_len} bytes, derived vk is {derived_len} bytes"
+    );
+}
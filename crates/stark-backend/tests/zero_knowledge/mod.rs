@@ -0,0 +1,279 @@
+use std::sync::Arc;
+
+use openvm_stark_backend::{
+    engine::StarkEngine,
+    keygen::{
+        types::{MultiStarkProvingKey, VkFeature},
+        CachedMainError, KeygenError, MultiStarkKeygenBuilder, ProofInputError,
+    },
+    p3_field::FieldAlgebra,
+    prover::{
+        types::{AirProofInput, ProofInput},
+        ProvingError, ProvingOptions,
+    },
+    verifier::{ProofShapeError, VerificationError},
+    Chip,
+};
+use openvm_stark_sdk::{
+    config::{
+        baby_bear_poseidon2::{
+            config_from_perm_gkr, default_perm, BabyBearPoseidon2Engine, BabyBearPoseidon2ZkConfig,
+            BabyBearPoseidon2ZkEngine,
+        },
+        FriParameters,
+    },
+    dummy_airs::{
+        fib_air::{air::FibonacciAir, trace::generate_trace_rows},
+        interaction::dummy_interaction_air::{DummyInteractionChip, DummyInteractionData},
+    },
+    engine::StarkFriEngine,
+};
+use p3_baby_bear::BabyBear;
+
+use crate::{
+    fib_selector_air::{
+        air::FibonacciSelectorAir, trace::generate_trace_rows as generate_selector_trace_rows,
+    },
+    get_conditional_fib_number, get_fib_number,
+};
+
+type SC = BabyBearPoseidon2ZkConfig;
+
+/// The traces must be high enough for their random values to hide the openings of the 42 FRI
+/// queries.
+const N: usize = 128;
+
+/// The committed traces are twice as high in zero-knowledge mode, so the log-up constraints need
+/// a blowup of 4.
+fn fri_params() -> FriParameters {
+    FriParameters::standard_with_100_bits_conjectured_security(2)
+}
+
+fn engine() -> BabyBearPoseidon2ZkEngine {
+    BabyBearPoseidon2ZkEngine::new(fri_params())
+}
+
+fn interaction_data() -> DummyInteractionData {
+    DummyInteractionData {
+        count: (0..N as u32).map(|i| i % 3).collect(),
+        fields: (0..N as u32).map(|i| vec![i]).collect(),
+    }
+}
+
+/// A Fibonacci AIR with AIR id 0, and a sender and a receiver of the same messages with AIR ids
+/// 1 and 2.
+fn keygen(engine: &BabyBearPoseidon2ZkEngine, zero_knowledge: bool) -> MultiStarkProvingKey<SC> {
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.set_zero_knowledge(zero_knowledge);
+    keygen_builder.add_air(Arc::new(FibonacciAir));
+    keygen_builder.add_air(DummyInteractionChip::<SC>::new_without_partition(1, true, 0).air());
+    keygen_builder.add_air(DummyInteractionChip::<SC>::new_without_partition(1, false, 0).air());
+    keygen_builder.generate_pk()
+}
+
+fn proof_input() -> ProofInput<SC> {
+    let fib_trace = generate_trace_rows::<BabyBear>(0, 1, N);
+    let fib_pis = [0, 1, get_fib_number(N)]
+        .map(BabyBear::from_canonical_u32)
+        .to_vec();
+    let mut send_chip = DummyInteractionChip::new_without_partition(1, true, 0);
+    let mut recv_chip = DummyInteractionChip::new_without_partition(1, false, 0);
+    send_chip.load_data(interaction_data());
+    recv_chip.load_data(interaction_data());
+    ProofInput::new(vec![
+        (0, AirProofInput::simple(fib_trace, fib_pis)),
+        send_chip.generate_air_proof_input_with_id(1),
+        recv_chip.generate_air_proof_input_with_id(2),
+    ])
+}
+
+#[test]
+fn test_zero_knowledge_proof_verifies() {
+    let engine = engine();
+    let pk = keygen(&engine, true);
+    let vk = pk.get_vk();
+    assert!(vk.features.contains(VkFeature::ZeroKnowledge));
+    // The Fibonacci AIR has constraint degree 2, so its quotient degree is 4 instead of 1.
+    assert_eq!(vk.per_air[0].quotient_degree, 4);
+    assert_eq!(keygen(&engine, false).per_air[0].vk.quotient_degree, 1);
+
+    let proof = engine.prove(&pk, proof_input());
+    engine.verify(&vk, &proof).expect("Verification failed");
+    engine
+        .verifier()
+        .with_constraints_before_opening(true)
+        .verify(&mut engine.new_challenger(), &vk, &proof)
+        .expect("Verification failed");
+    // The opening of the random mask of each quotient follows its chunks.
+    for (air_vk, quotient) in vk.per_air.iter().zip(&proof.core.opened_values.quotient) {
+        assert_eq!(quotient.len(), air_vk.quotient_degree as usize + 1);
+    }
+    // The trace heights are those of the traces, not of the committed matrices.
+    assert_eq!(proof.core.per_air[0].degree, N);
+
+    // The proof does not have the shape of a proof of the key without zero knowledge.
+    let plain_vk = keygen(&engine, false).get_vk();
+    assert_eq!(
+        engine
+            .verifier()
+            .with_constraints_before_opening(true)
+            .verify(&mut engine.new_challenger(), &plain_vk, &proof),
+        Err(VerificationError::InvalidProofShape(
+            ProofShapeError::QuotientChunks {
+                air_id: 0,
+                air_name: "FibonacciAir".to_string(),
+                num_chunks: 1,
+                chunk_len: 4,
+            }
+        ))
+    );
+}
+
+#[test]
+fn test_zero_knowledge_salts_merkle_leaves() {
+    let engine = engine();
+    let pk = keygen(&engine, true);
+    let vk = pk.get_vk();
+    let mut proof = engine.prove(&pk, proof_input());
+    for query in &proof.pcs_proof.query_proofs {
+        for opening in &query.input_proof {
+            let (salts, _) = &opening.opening_proof;
+            assert_eq!(salts.len(), opening.opened_values.len());
+            assert!(salts.iter().all(|salt| salt.len() == 4));
+        }
+    }
+
+    // The salts are committed along with the rows they salt.
+    let (salts, _) = &mut proof.pcs_proof.query_proofs[0].input_proof[0].opening_proof;
+    salts[0][0] += BabyBear::ONE;
+    assert!(engine.verify(&vk, &proof).is_err());
+}
+
+#[test]
+fn test_zero_knowledge_rejects_low_traces() {
+    let engine = engine();
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.set_zero_knowledge(true);
+    let air_id = keygen_builder.add_air(Arc::new(FibonacciAir));
+    let pk = keygen_builder.generate_pk();
+
+    let proof_input = |n| {
+        let trace = generate_trace_rows::<BabyBear>(0, 1, n);
+        let pis = [0, 1, get_fib_number(n)]
+            .map(BabyBear::from_canonical_u32)
+            .to_vec();
+        ProofInput::new(vec![(air_id, AirProofInput::simple(trace, pis))])
+    };
+    // The 42 queries and the 2 out-of-domain rows need 44 random values, and the 5 matrices of
+    // the quotient of degree 4, each opened at 43 points, need 54 random values per row.
+    let err = engine
+        .prove_with_options(&pk, proof_input(32), ProvingOptions::default())
+        .unwrap_err();
+    assert_eq!(
+        err,
+        ProvingError::InvalidInput(ProofInputError::ZeroKnowledgeHeight {
+            air_id,
+            air_name: "FibonacciAir".to_string(),
+            height: 32,
+            min_height: 54,
+        })
+    );
+    let proof = engine.prove(&pk, proof_input(64));
+    engine
+        .verify(&pk.get_vk(), &proof)
+        .expect("Verification failed");
+}
+
+#[test]
+fn test_zero_knowledge_unsupported_by_engine() {
+    // The config of the engine does not salt its Merkle trees.
+    let engine = BabyBearPoseidon2Engine::new(fri_params());
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.set_zero_knowledge(true);
+    let air_id = keygen_builder.add_air(Arc::new(FibonacciAir));
+    let pk = keygen_builder.generate_pk();
+
+    let trace = generate_trace_rows::<BabyBear>(0, 1, N);
+    let pis = [0, 1, get_fib_number(N)]
+        .map(BabyBear::from_canonical_u32)
+        .to_vec();
+    let proof_input = ProofInput::new(vec![(air_id, AirProofInput::simple(trace, pis))]);
+    assert_eq!(
+        engine
+            .prove_with_options(&pk, proof_input, ProvingOptions::default())
+            .unwrap_err(),
+        ProvingError::ZeroKnowledgeUnsupported
+    );
+}
+
+#[test]
+fn test_zero_knowledge_with_preprocessed_trace() {
+    let engine = engine();
+    let sels = (0..N).map(|i| i % 2 == 0).collect::<Vec<_>>();
+    let air = FibonacciSelectorAir::new(sels, false);
+    let trace = generate_selector_trace_rows::<BabyBear>(0, 1, air.sels());
+    let pis = [0, 1, get_conditional_fib_number(air.sels())]
+        .map(BabyBear::from_canonical_u32)
+        .to_vec();
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.set_zero_knowledge(true);
+    let air_id = keygen_builder.add_air(Arc::new(air));
+    let pk = keygen_builder.generate_pk();
+
+    let proof_input = ProofInput::new(vec![(air_id, AirProofInput::simple(trace, pis))]);
+    let proof = engine.prove(&pk, proof_input);
+    engine
+        .verify(&pk.get_vk(), &proof)
+        .expect("Verification failed");
+}
+
+#[test]
+fn test_zero_knowledge_rejects_pre_committed_cached_mains() {
+    let engine = engine();
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.set_zero_knowledge(true);
+    let mut send_chip = DummyInteractionChip::new_with_partition(engine.config(), 1, true, 0);
+    let mut recv_chip = DummyInteractionChip::new_without_partition(1, false, 0);
+    let send_air_id = keygen_builder.add_air(send_chip.air());
+    let recv_air_id = keygen_builder.add_air(recv_chip.air());
+    let pk = keygen_builder.generate_pk();
+
+    send_chip.load_data(interaction_data());
+    recv_chip.load_data(interaction_data());
+    let proof_input = ProofInput::new(vec![
+        send_chip.generate_air_proof_input_with_id(send_air_id),
+        recv_chip.generate_air_proof_input_with_id(recv_air_id),
+    ]);
+    let err = engine
+        .prove_with_options(&pk, proof_input.clone(), ProvingOptions::default())
+        .unwrap_err();
+    assert_eq!(
+        err,
+        ProvingError::InvalidInput(ProofInputError::CachedMain(
+            CachedMainError::ZeroKnowledge {
+                air_id: send_air_id,
+                air_name: pk.per_air[send_air_id].air_name.clone(),
+            }
+        ))
+    );
+
+    // The prover commits and randomizes the cached main traces that are not pre-committed.
+    let mut proof_input = proof_input;
+    proof_input.per_air[0].1.cached_mains_pdata.clear();
+    let proof = engine.prove(&pk, proof_input);
+    engine
+        .verify(&pk.get_vk(), &proof)
+        .expect("Verification failed");
+}
+
+#[test]
+fn test_zero_knowledge_unsupported_by_gkr_log_up() {
+    let config = config_from_perm_gkr(&default_perm(), FriParameters::standard_fast());
+    let mut keygen_builder = MultiStarkKeygenBuilder::new(&config);
+    keygen_builder.set_zero_knowledge(true);
+    keygen_builder.add_air(Arc::new(FibonacciAir));
+    assert!(matches!(
+        keygen_builder.try_generate_pk(),
+        Err(KeygenError::ZeroKnowledgeUnsupported { .. })
+    ));
+}
@@ -0,0 +1,55 @@
+//! Compare the DFT backends of the PCS on the low-degree extension of small and large traces, and
+//! report the smallest height where `Radix2DitParallel` is faster than `Radix2Dit`, to compare
+//! with `AUTO_PARALLEL_MIN_LOG_HEIGHT`.
+//!
+//! Run with `cargo run --release --example compare_dft_backends`.
+
+use std::time::{Duration, Instant};
+
+use openvm_stark_backend::{
+    p3_field::Field,
+    p3_matrix::{dense::RowMajorMatrix, Matrix},
+};
+use openvm_stark_sdk::{
+    config::dft::{DftBackend, RuntimeDft, AUTO_PARALLEL_MIN_LOG_HEIGHT},
+    utils::create_seeded_rng,
+};
+use p3_baby_bear::BabyBear;
+use p3_dft::TwoAdicSubgroupDft;
+
+const LOG_HEIGHTS: [usize; 10] = [8, 9, 10, 11, 12, 13, 14, 16, 18, 22];
+const WIDTH: usize = 8;
+const LOG_BLOWUP: usize = 1;
+const NUM_REPETITIONS: usize = 5;
+
+fn main() {
+    let mut rng = create_seeded_rng();
+    let mut parallel_min_log_height = None;
+    for log_height in LOG_HEIGHTS {
+        let trace = RowMajorMatrix::<BabyBear>::rand(&mut rng, 1 << log_height, WIDTH);
+        let time = |backend| {
+            let dft = RuntimeDft::new(backend);
+            // The first LDE caches the twiddles.
+            dft.coset_lde_batch(trace.clone(), LOG_BLOWUP, BabyBear::GENERATOR);
+            let start = Instant::now();
+            for _ in 0..NUM_REPETITIONS {
+                let lde = dft.coset_lde_batch(trace.clone(), LOG_BLOWUP, BabyBear::GENERATOR);
+                assert_eq!(lde.height(), 1 << (log_height + LOG_BLOWUP));
+            }
+            let elapsed: Duration = start.elapsed() / NUM_REPETITIONS as u32;
+            println!("2^{log_height} rows, {backend:?}: {elapsed:?} per LDE");
+            elapsed
+        };
+        let serial = time(DftBackend::Radix2Dit);
+        let parallel = time(DftBackend::Radix2DitParallel);
+        time(DftBackend::Radix2Bowers);
+        time(DftBackend::Auto);
+        if parallel < serial && parallel_min_log_height.is_none() {
+            parallel_min_log_height = Some(log_height);
+        }
+    }
+    println!(
+        "Radix2DitParallel is faster from 2^{parallel_min_log_height:?} rows, \
+         AUTO_PARALLEL_MIN_LOG_HEIGHT is {AUTO_PARALLEL_MIN_LOG_HEIGHT}"
+    );
+}
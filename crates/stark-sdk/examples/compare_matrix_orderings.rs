@@ -0,0 +1,102 @@
+//! Compare the commitment time of the main traces and the size of the proof of the
+//! [MatrixOrdering]s, on the AIRs of a VM-like circuit: pairs of AIRs sending and receiving the
+//! same messages, of many widths and heights.
+//!
+//! Run with `cargo run --release --example compare_matrix_orderings`.
+
+use std::{sync::Arc, time::Duration};
+
+use itertools::Itertools;
+use openvm_stark_backend::{
+    keygen::types::{MatrixOrdering, MultiStarkProvingKey},
+    p3_field::FieldAlgebra,
+    p3_matrix::dense::RowMajorMatrix,
+    prover::types::{AirProofInput, ProofInput},
+};
+use openvm_stark_sdk::{
+    config::baby_bear_poseidon2::{default_engine, BabyBearPoseidon2Config},
+    dummy_airs::interaction::dummy_interaction_air::DummyInteractionAir,
+    engine::StarkEngine,
+    proof_serde::ProofSerde,
+};
+use p3_baby_bear::BabyBear;
+
+type SC = BabyBearPoseidon2Config;
+type Val = BabyBear;
+
+/// The `(field width, log height)` of each pair of AIRs, in the order they are added to the
+/// key, which is not sorted by height, as with the chips of a VM.
+const CHIPS: [(usize, usize); 8] = [
+    (2, 10),
+    (8, 16),
+    (1, 12),
+    (4, 18),
+    (16, 8),
+    (3, 14),
+    (6, 16),
+    (1, 18),
+];
+const NUM_REPETITIONS: usize = 5;
+
+fn keygen(matrix_ordering: MatrixOrdering) -> MultiStarkProvingKey<SC> {
+    let mut keygen_builder = default_engine().keygen_builder();
+    keygen_builder.set_matrix_ordering(matrix_ordering);
+    for (bus_index, &(field_width, _)) in CHIPS.iter().enumerate() {
+        for is_send in [true, false] {
+            keygen_builder.add_air(Arc::new(DummyInteractionAir::new(
+                field_width,
+                is_send,
+                bus_index,
+            )));
+        }
+    }
+    keygen_builder.generate_pk()
+}
+
+/// The same `| count | fields |` trace for the sender and the receiver of each pair.
+fn proof_input() -> ProofInput<SC> {
+    let per_air = CHIPS
+        .iter()
+        .flat_map(|&(field_width, log_height)| {
+            let width = field_width + 1;
+            let messages = RowMajorMatrix::new(
+                (0..(1 << log_height) * width)
+                    .map(|i| Val::from_canonical_usize(i % 101))
+                    .collect(),
+                width,
+            );
+            [messages.clone(), messages]
+        })
+        .map(AirProofInput::simple_no_pis)
+        .enumerate()
+        .collect();
+    ProofInput::new(per_air)
+}
+
+fn main() {
+    let engine = default_engine();
+    for matrix_ordering in [MatrixOrdering::Natural, MatrixOrdering::HeightDescending] {
+        let pk = keygen(matrix_ordering);
+        let vk = pk.get_vk();
+        let mut commit_times = vec![];
+        let mut proof = None;
+        for _ in 0..NUM_REPETITIONS {
+            let (repetition, metrics) = engine.prove_with_metrics(&pk, proof_input());
+            commit_times.push(metrics.main_trace_commit.wall);
+            proof = Some(repetition);
+        }
+        let proof = proof.unwrap();
+        engine.verify(&vk, &proof).expect("Verification failed");
+        let commit_time: Duration = commit_times
+            .into_iter()
+            .sorted()
+            .nth(NUM_REPETITIONS / 2)
+            .unwrap();
+        println!(
+            "{matrix_ordering:?}: main trace commitment {commit_time:?} (median), proof of {} \
+             bytes, {} bytes with bincode",
+            proof.to_bytes().len(),
+            bincode::serialized_size(&proof).unwrap()
+        );
+    }
+}
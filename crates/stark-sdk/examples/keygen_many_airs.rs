@@ -0,0 +1,76 @@
+//! Compare serial and parallel keygen of many AIRs with large preprocessed tables.
+
+use std::{sync::Arc, time::Instant};
+
+use openvm_stark_backend::{
+    p3_air::{Air, AirBuilder, BaseAir, PairBuilder},
+    p3_field::{Field, FieldAlgebra},
+    p3_matrix::{dense::RowMajorMatrix, Matrix},
+    rap::{BaseAirWithPublicValues, PartitionedBaseAir},
+};
+use openvm_stark_sdk::{
+    config::{baby_bear_poseidon2::BabyBearPoseidon2Engine, setup_tracing, FriParameters},
+    engine::StarkFriEngine,
+    openvm_stark_backend::engine::StarkEngine,
+};
+
+const NUM_AIRS: usize = 40;
+const LOG_TABLE_HEIGHT: usize = 16;
+const TABLE_WIDTH: usize = 4;
+const LOG_BLOWUP: usize = 2;
+
+/// AIR with a preprocessed table of `TABLE_WIDTH` columns derived from `seed`, constraining its
+/// main trace to be the square of the table entry by entry.
+struct TableAir {
+    seed: usize,
+}
+
+impl<F: Field> BaseAir<F> for TableAir {
+    fn width(&self) -> usize {
+        TABLE_WIDTH
+    }
+
+    fn preprocessed_trace(&self) -> Option<RowMajorMatrix<F>> {
+        let values = (0..TABLE_WIDTH << LOG_TABLE_HEIGHT)
+            .map(|i| F::from_canonical_usize(i * (2 * self.seed + 1) % (1 << 30)))
+            .collect();
+        Some(RowMajorMatrix::new(values, TABLE_WIDTH))
+    }
+}
+impl<F: Field> BaseAirWithPublicValues<F> for TableAir {}
+impl<F: Field> PartitionedBaseAir<F> for TableAir {}
+
+impl<AB: PairBuilder> Air<AB> for TableAir {
+    fn eval(&self, builder: &mut AB) {
+        let preprocessed = builder.preprocessed();
+        let main = builder.main();
+        let (table, local) = (preprocessed.row_slice(0), main.row_slice(0));
+        for (&x, &entry) in local.iter().zip(table.iter()) {
+            builder.assert_eq(x, entry * entry);
+        }
+    }
+}
+
+fn main() {
+    setup_tracing();
+    let engine = BabyBearPoseidon2Engine::new(
+        FriParameters::standard_with_100_bits_conjectured_security(LOG_BLOWUP),
+    );
+    let mut vk_bytes = vec![];
+    for parallel in [false, true] {
+        let mut keygen_builder = engine.keygen_builder();
+        keygen_builder.set_parallel(parallel);
+        for seed in 0..NUM_AIRS {
+            keygen_builder.add_air(Arc::new(TableAir { seed }));
+        }
+        let start = Instant::now();
+        let pk = keygen_builder.generate_pk();
+        println!(
+            "{} keygen of {NUM_AIRS} AIRs: {:?}",
+            if parallel { "parallel" } else { "serial" },
+            start.elapsed()
+        );
+        vk_bytes.push(bincode::serialize(&pk.get_vk()).unwrap());
+    }
+    assert_eq!(vk_bytes[0], vk_bytes[1]);
+}
@@ -50,6 +50,7 @@ fn main() {
     let mut keygen_builder = engine.keygen_builder();
     let air_id = keygen_builder.add_air(Arc::new(air));
     let pk = keygen_builder.generate_pk();
+    println!("{}", pk.summary());
 
     let inputs = (0..NUM_PERMUTATIONS).map(|_| rng.gen()).collect::<Vec<_>>();
     let trace = metrics_span("generate_trace", || {
@@ -0,0 +1,70 @@
+//! Compare proving many small proofs one at a time and with a batch prover.
+
+use std::{sync::Arc, time::Instant};
+
+use openvm_stark_backend::{
+    p3_field::FieldAlgebra,
+    prover::{
+        batch::BatchProver,
+        types::{AirProofInput, ProofInput},
+    },
+};
+use openvm_stark_sdk::{
+    config::{
+        baby_bear_poseidon2::{BabyBearPoseidon2Config, BabyBearPoseidon2Engine},
+        setup_tracing, FriParameters,
+    },
+    dummy_airs::fib_air::{air::FibonacciAir, trace::generate_trace_rows},
+    engine::StarkFriEngine,
+    openvm_stark_backend::engine::StarkEngine,
+};
+use p3_baby_bear::BabyBear;
+
+const NUM_PROOFS: usize = 50;
+const LOG_HEIGHT: usize = 12;
+const MAX_IN_FLIGHT: usize = 4;
+
+fn proof_input(i: usize) -> ProofInput<BabyBearPoseidon2Config> {
+    let trace = generate_trace_rows::<BabyBear>(i as u32, 1, 1 << LOG_HEIGHT);
+    let last = trace.values[trace.values.len() - 1];
+    let pis = vec![BabyBear::from_canonical_usize(i), BabyBear::ONE, last];
+    ProofInput::new(vec![(0, AirProofInput::simple(trace, pis))])
+}
+
+fn main() {
+    setup_tracing();
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(Arc::new(FibonacciAir));
+    let pk = keygen_builder.generate_pk();
+
+    let inputs = (0..NUM_PROOFS).map(proof_input).collect::<Vec<_>>();
+    let start = Instant::now();
+    let proofs = inputs
+        .clone()
+        .into_iter()
+        .map(|input| engine.prove(&pk, input))
+        .collect::<Vec<_>>();
+    let elapsed = start.elapsed();
+    println!(
+        "one at a time: {NUM_PROOFS} proofs in {elapsed:?}, {:.1} proofs/s",
+        NUM_PROOFS as f64 / elapsed.as_secs_f64()
+    );
+
+    let mut batch_prover = BatchProver::new(&engine, &pk, 0);
+    batch_prover.set_max_in_flight(MAX_IN_FLIGHT);
+    let start = Instant::now();
+    let batch_proofs = batch_prover.prove_concurrently(inputs);
+    let elapsed = start.elapsed();
+    println!(
+        "batch of {MAX_IN_FLIGHT} in flight: {NUM_PROOFS} proofs in {elapsed:?}, {:.1} proofs/s",
+        NUM_PROOFS as f64 / elapsed.as_secs_f64()
+    );
+
+    for (proof, batch_proof) in proofs.iter().zip(&batch_proofs) {
+        assert_eq!(
+            bincode::serialize(proof).unwrap(),
+            bincode::serialize(batch_proof).unwrap()
+        );
+    }
+}
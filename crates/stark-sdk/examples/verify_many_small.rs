@@ -0,0 +1,64 @@
+//! Compare verifying many small proofs one at a time and as a batch.
+
+use std::{sync::Arc, time::Instant};
+
+use openvm_stark_backend::{
+    p3_field::FieldAlgebra,
+    prover::{
+        batch::BatchProver,
+        types::{AirProofInput, ProofInput},
+    },
+};
+use openvm_stark_sdk::{
+    config::{
+        baby_bear_poseidon2::{BabyBearPoseidon2Config, BabyBearPoseidon2Engine},
+        setup_tracing, FriParameters,
+    },
+    dummy_airs::fib_air::{air::FibonacciAir, trace::generate_trace_rows},
+    engine::StarkFriEngine,
+    openvm_stark_backend::engine::StarkEngine,
+};
+use p3_baby_bear::BabyBear;
+
+const NUM_PROOFS: usize = 200;
+const LOG_HEIGHT: usize = 10;
+
+fn proof_input(i: usize) -> ProofInput<BabyBearPoseidon2Config> {
+    let trace = generate_trace_rows::<BabyBear>(i as u32, 1, 1 << LOG_HEIGHT);
+    let last = trace.values[trace.values.len() - 1];
+    let pis = vec![BabyBear::from_canonical_usize(i), BabyBear::ONE, last];
+    ProofInput::new(vec![(0, AirProofInput::simple(trace, pis))])
+}
+
+fn main() {
+    setup_tracing();
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(Arc::new(FibonacciAir));
+    let pk = keygen_builder.generate_pk();
+    let vk = pk.get_vk();
+    let proofs = BatchProver::new(&engine, &pk, 0)
+        .prove_concurrently((0..NUM_PROOFS).map(proof_input).collect());
+
+    let start = Instant::now();
+    for proof in &proofs {
+        engine.verify(&vk, proof).expect("Verification failed");
+    }
+    let serial = start.elapsed();
+    println!(
+        "one at a time: {NUM_PROOFS} proofs in {serial:?}, {:.1} proofs/s",
+        NUM_PROOFS as f64 / serial.as_secs_f64()
+    );
+
+    let start = Instant::now();
+    let results = engine.verify_batch(&vk, &proofs);
+    let batch = start.elapsed();
+    println!(
+        "batch: {NUM_PROOFS} proofs in {batch:?}, {:.1} proofs/s, {:.1}x speedup",
+        NUM_PROOFS as f64 / batch.as_secs_f64(),
+        serial.as_secs_f64() / batch.as_secs_f64()
+    );
+    for result in results {
+        result.expect("Verification failed");
+    }
+}
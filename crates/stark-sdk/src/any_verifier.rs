@@ -0,0 +1,242 @@
+//! Verification of serialized proofs of any registered STARK config, for verifiers receiving
+//! proofs generated by engines with different configs.
+//!
+//! A serialized proof records the name of its config, see [proof_serde](crate::proof_serde), and
+//! so does a serialized verifying key, see [key_serde](crate::key_serde). [AnyVerifier::verify]
+//! reads the config of the proof and verifies it with the engine registered for that config.
+//! A config is registered by implementing [VerifierConfig].
+//!
+//! Proofs are decoded strictly, see [ProofSerde::from_bytes], unless the verifier is built
+//! [with_lenient_decoding](AnyVerifier::with_lenient_decoding).
+
+use std::{collections::HashMap, error::Error, fmt};
+
+use openvm_stark_backend::{
+    config::{Com, PcsProof, RapPhaseSeqPartialProof, StarkGenericConfig, Val},
+    keygen::types::MultiStarkVerifyingKey,
+    p3_field::PrimeField64,
+    proof::Proof,
+    verifier::{VerificationError, DEFAULT_MAX_PROOF_BYTES},
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    config::{
+        baby_bear_keccak::{BabyBearKeccakConfig, BabyBearKeccakEngine},
+        baby_bear_poseidon2::{BabyBearPoseidon2Config, BabyBearPoseidon2Engine},
+        baby_bear_poseidon2_root::{BabyBearPoseidon2RootConfig, BabyBearPoseidon2RootEngine},
+        FriParameters,
+    },
+    engine::{StarkEngine, StarkFriEngine},
+    key_serde::{config_name, ConfigId, KeySerde, KeySerdeError},
+    proof_serde::{
+        read_config_name, CanonicalCommitment, CanonicalProofPart, ProofSerde, ProofSerdeError,
+    },
+};
+
+/// A STARK config whose proofs an [AnyVerifier] can verify, with the engine verifying them.
+pub trait VerifierConfig: StarkGenericConfig + ConfigId + 'static
+where
+    Val<Self>: PrimeField64,
+    Com<Self>: Serialize + DeserializeOwned + CanonicalCommitment,
+    PcsProof<Self>: CanonicalProofPart<Val<Self>>,
+    RapPhaseSeqPartialProof<Self>: CanonicalProofPart<Val<Self>>,
+{
+    type Engine: StarkFriEngine<Self> + Send + Sync + 'static;
+}
+
+impl VerifierConfig for BabyBearPoseidon2Config {
+    type Engine = BabyBearPoseidon2Engine;
+}
+
+impl VerifierConfig for BabyBearKeccakConfig {
+    type Engine = BabyBearKeccakEngine;
+}
+
+impl VerifierConfig for BabyBearPoseidon2RootConfig {
+    type Engine = BabyBearPoseidon2RootEngine;
+}
+
+/// An error verifying a serialized proof with an [AnyVerifier].
+#[derive(Debug, PartialEq, Eq)]
+pub enum AnyVerifierError {
+    /// The serialized proof failed to decode, see [ProofSerde::from_bytes].
+    Proof(ProofSerdeError),
+    /// The serialized verifying key failed to decode, e.g. because it is for another config than
+    /// the proof, see [KeySerde::from_bytes].
+    Key(KeySerdeError),
+    /// No engine is registered for the config of the proof.
+    UnknownConfig(String),
+    /// The proof does not verify against the verifying key.
+    Verification(VerificationError),
+}
+
+impl fmt::Display for AnyVerifierError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Proof(err) => write!(f, "{err}"),
+            Self::Key(err) => write!(f, "{err}"),
+            Self::UnknownConfig(config) => {
+                write!(f, "no verifier is registered for config {config}")
+            }
+            Self::Verification(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl Error for AnyVerifierError {}
+
+impl From<ProofSerdeError> for AnyVerifierError {
+    fn from(err: ProofSerdeError) -> Self {
+        Self::Proof(err)
+    }
+}
+
+impl From<KeySerdeError> for AnyVerifierError {
+    fn from(err: KeySerdeError) -> Self {
+        Self::Key(err)
+    }
+}
+
+impl From<VerificationError> for AnyVerifierError {
+    fn from(err: VerificationError) -> Self {
+        Self::Verification(err)
+    }
+}
+
+/// Verifies serialized proofs with the engine of their config.
+#[derive(Default)]
+pub struct AnyVerifier {
+    verifiers: HashMap<&'static str, Box<dyn DynVerifier>>,
+    lenient_decoding: bool,
+}
+
+impl AnyVerifier {
+    /// A verifier without any registered config.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A verifier for the configs supported by the SDK, with the engine of each config using the
+    /// FRI parameters `fri_params` returns for its [ConfigId], e.g. a larger blowup for the root
+    /// config.
+    pub fn with_default_configs(fri_params: impl Fn(&'static str) -> FriParameters) -> Self {
+        let mut verifier = Self::new();
+        verifier.register_with_fri_params::<BabyBearPoseidon2Config>(fri_params(
+            BabyBearPoseidon2Config::CONFIG_ID,
+        ));
+        verifier.register_with_fri_params::<BabyBearKeccakConfig>(fri_params(
+            BabyBearKeccakConfig::CONFIG_ID,
+        ));
+        verifier.register_with_fri_params::<BabyBearPoseidon2RootConfig>(fri_params(
+            BabyBearPoseidon2RootConfig::CONFIG_ID,
+        ));
+        verifier
+    }
+
+    /// Decodes proofs with [ProofSerde::from_bytes_lenient] if `lenient_decoding`, which ignores
+    /// the bytes after the encoding of a proof, e.g. for proofs padded by their transport. By
+    /// default, these bytes are rejected with [ProofSerdeError::TrailingBytes].
+    pub fn with_lenient_decoding(mut self, lenient_decoding: bool) -> Self {
+        self.lenient_decoding = lenient_decoding;
+        self
+    }
+
+    /// Verifies the proofs of `SC` with a new engine using `fri_params`, see
+    /// [register](Self::register).
+    pub fn register_with_fri_params<SC>(&mut self, fri_params: FriParameters)
+    where
+        SC: VerifierConfig,
+        Val<SC>: PrimeField64,
+        Com<SC>: Serialize + DeserializeOwned + CanonicalCommitment,
+        PcsProof<SC>: CanonicalProofPart<Val<SC>>,
+        RapPhaseSeqPartialProof<SC>: CanonicalProofPart<Val<SC>>,
+    {
+        self.register::<SC>(SC::Engine::new(fri_params));
+    }
+
+    /// Verifies the proofs of `SC` with `engine`, replacing the engine registered before for
+    /// `SC`, if any.
+    pub fn register<SC>(&mut self, engine: SC::Engine)
+    where
+        SC: VerifierConfig,
+        Val<SC>: PrimeField64,
+        Com<SC>: Serialize + DeserializeOwned + CanonicalCommitment,
+        PcsProof<SC>: CanonicalProofPart<Val<SC>>,
+        RapPhaseSeqPartialProof<SC>: CanonicalProofPart<Val<SC>>,
+    {
+        self.verifiers.insert(
+            config_name::<SC>(),
+            Box::new(ConfigVerifier::<SC> { engine }),
+        );
+    }
+
+    /// Names of the registered configs, see [config_name].
+    pub fn configs(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.verifiers.keys().copied()
+    }
+
+    /// Verifies the proof serialized in `proof_bytes` by [ProofSerde::to_bytes] against the
+    /// verifying key serialized in `vk_bytes` by [KeySerde::to_bytes], with the engine registered
+    /// for the config of the proof. The key must be for the same config.
+    pub fn verify(&self, vk_bytes: &[u8], proof_bytes: &[u8]) -> Result<(), AnyVerifierError> {
+        let config = read_config_name(proof_bytes)?;
+        let verifier = self
+            .verifiers
+            .get(config.as_str())
+            .ok_or(AnyVerifierError::UnknownConfig(config))?;
+        verifier.verify(vk_bytes, proof_bytes, self.lenient_decoding)
+    }
+}
+
+/// Verifier of the proofs of one config, with the config erased.
+trait DynVerifier: Send + Sync {
+    fn verify(
+        &self,
+        vk_bytes: &[u8],
+        proof_bytes: &[u8],
+        lenient_decoding: bool,
+    ) -> Result<(), AnyVerifierError>;
+}
+
+struct ConfigVerifier<SC>
+where
+    SC: VerifierConfig,
+    Val<SC>: PrimeField64,
+    Com<SC>: Serialize + DeserializeOwned + CanonicalCommitment,
+    PcsProof<SC>: CanonicalProofPart<Val<SC>>,
+    RapPhaseSeqPartialProof<SC>: CanonicalProofPart<Val<SC>>,
+{
+    engine: SC::Engine,
+}
+
+impl<SC> DynVerifier for ConfigVerifier<SC>
+where
+    SC: VerifierConfig,
+    Val<SC>: PrimeField64,
+    Com<SC>: Serialize + DeserializeOwned + CanonicalCommitment,
+    PcsProof<SC>: CanonicalProofPart<Val<SC>>,
+    RapPhaseSeqPartialProof<SC>: CanonicalProofPart<Val<SC>>,
+{
+    fn verify(
+        &self,
+        vk_bytes: &[u8],
+        proof_bytes: &[u8],
+        lenient_decoding: bool,
+    ) -> Result<(), AnyVerifierError> {
+        let vk = MultiStarkVerifyingKey::<SC>::from_bytes(vk_bytes)?;
+        let proof = if lenient_decoding {
+            if proof_bytes.len() > DEFAULT_MAX_PROOF_BYTES {
+                return Err(ProofSerdeError::TooLarge {
+                    max: DEFAULT_MAX_PROOF_BYTES,
+                    found: proof_bytes.len(),
+                }
+                .into());
+            }
+            Proof::<SC>::from_bytes_lenient(proof_bytes)?
+        } else {
+            Proof::<SC>::from_bytes_with_limit(proof_bytes, DEFAULT_MAX_PROOF_BYTES)?
+        };
+        Ok(self.engine.verify(&vk, &proof)?)
+    }
+}
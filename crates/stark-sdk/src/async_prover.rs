@@ -0,0 +1,136 @@
+//! Proving from async code, without blocking the executor.
+//!
+//! A proof runs as one task on the blocking thread pool of the tokio runtime. The state of the
+//! prover borrows the key and the traces from one phase to the next, so the phases are not split
+//! into separate tasks. Instead, the async side is notified at the start of each [ProvingPhase],
+//! so that it can report progress or give up on a phase with a timeout, e.g. with
+//! `tokio::time::timeout(duration, task.next_phase())`, and then [cancel](ProvingTask::cancel)
+//! the proof. The proofs are the same as with [StarkEngine::prove].
+
+use std::{
+    panic,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use openvm_stark_backend::{
+    config::{StarkGenericConfig, Val},
+    engine::StarkEngine,
+    keygen::types::MultiStarkProvingKey,
+    p3_field::PrimeField64,
+    proof::Proof,
+    prover::{types::ProofInput, ProvingError, ProvingOptions, ProvingPhase},
+};
+use tokio::{sync::mpsc, task::JoinHandle};
+
+/// A proof running on the blocking thread pool, see [spawn_prove].
+///
+/// Dropping the task cancels the proof, which stops at the start of its next phase, or after
+/// the quotient polynomial of the current AIR, see [ProvingOptions::cancel].
+pub struct ProvingTask<SC: StarkGenericConfig> {
+    phases: mpsc::UnboundedReceiver<ProvingPhase>,
+    handle: JoinHandle<Result<Proof<SC>, ProvingError>>,
+    cancel: CancelOnDrop,
+}
+
+/// Sets the cancellation flag of a proof when dropped.
+struct CancelOnDrop(Arc<AtomicBool>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+impl<SC: StarkGenericConfig> ProvingTask<SC> {
+    /// Waits for the start of the next phase of the proof. Returns `None` once the proof is
+    /// done.
+    pub async fn next_phase(&mut self) -> Option<ProvingPhase> {
+        self.phases.recv().await
+    }
+
+    /// Cancels the proof, which then stops as if the task was dropped, and [proof](Self::proof)
+    /// returns [ProvingError::Cancelled] unless the proof was already done.
+    pub fn cancel(&self) {
+        self.cancel.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Waits for the proof. Dropping the returned future cancels the proof.
+    ///
+    /// # Panics
+    /// With the panic of the prover, if it panicked.
+    pub async fn proof(self) -> Result<Proof<SC>, ProvingError> {
+        // Setting the flag once the proof is done has no effect.
+        let Self {
+            handle,
+            cancel: _cancel,
+            ..
+        } = self;
+        match handle.await {
+            Ok(result) => result,
+            Err(err) if err.is_panic() => panic::resume_unwind(err.into_panic()),
+            Err(err) => panic!("proving task failed: {err}"),
+        }
+    }
+}
+
+/// Starts proving `proof_input` with `pk` on the blocking thread pool of the current tokio
+/// runtime.
+///
+/// # Panics
+/// If called outside of a tokio runtime.
+pub fn spawn_prove<SC, E>(
+    engine: Arc<E>,
+    pk: Arc<MultiStarkProvingKey<SC>>,
+    proof_input: ProofInput<SC>,
+) -> ProvingTask<SC>
+where
+    Val<SC>: PrimeField64,
+    SC: StarkGenericConfig + 'static,
+    E: StarkEngine<SC> + Send + Sync + 'static,
+    MultiStarkProvingKey<SC>: Send + Sync,
+    ProofInput<SC>: Send,
+    Proof<SC>: Send,
+{
+    let (sender, phases) = mpsc::unbounded_channel();
+    let cancel = Arc::new(AtomicBool::new(false));
+    let options = ProvingOptions {
+        // The receiver may have been dropped, in which case nobody is waiting for the phases.
+        progress: Some(Box::new(move |phase, fraction| {
+            if fraction == 0.0 {
+                let _ = sender.send(phase);
+            }
+        })),
+        cancel: Some(cancel.clone()),
+        ..Default::default()
+    };
+    let handle =
+        tokio::task::spawn_blocking(move || engine.prove_with_options(&pk, proof_input, options));
+    ProvingTask {
+        phases,
+        handle,
+        cancel: CancelOnDrop(cancel),
+    }
+}
+
+/// Proves `proof_input` with `pk` on the blocking thread pool of the current tokio runtime, see
+/// [spawn_prove]. Dropping the returned future cancels the proof.
+///
+/// Returns [ProvingError::InvalidInput] if `proof_input` does not fit `pk`.
+pub async fn prove_async<SC, E>(
+    engine: Arc<E>,
+    pk: Arc<MultiStarkProvingKey<SC>>,
+    proof_input: ProofInput<SC>,
+) -> Result<Proof<SC>, ProvingError>
+where
+    Val<SC>: PrimeField64,
+    SC: StarkGenericConfig + 'static,
+    E: StarkEngine<SC> + Send + Sync + 'static,
+    MultiStarkProvingKey<SC>: Send + Sync,
+    ProofInput<SC>: Send,
+    Proof<SC>: Send,
+{
+    spawn_prove(engine, pk, proof_input).proof().await
+}
@@ -8,7 +8,7 @@ use super::{
 };
 use crate::{
     assert_sc_compatible_with_serde,
-    config::baby_bear_bytehash::BabyBearByteHashEngineWithDefaultHash,
+    config::baby_bear_bytehash::BabyBearByteHashEngineWithDefaultHash, key_serde::ConfigId,
 };
 
 pub type BabyBearBlake3Config = BabyBearByteHashConfig<Blake3>;
@@ -16,6 +16,10 @@ pub type BabyBearBlake3Engine = BabyBearByteHashEngine<Blake3>;
 
 assert_sc_compatible_with_serde!(BabyBearBlake3Config);
 
+impl ConfigId for BabyBearBlake3Config {
+    const CONFIG_ID: &'static str = "baby_bear_blake3";
+}
+
 /// `pcs_log_degree` is the upper bound on the log_2(PCS polynomial degree).
 pub fn default_engine() -> BabyBearBlake3Engine {
     baby_bear_bytehash::default_engine(Blake3)
@@ -1,17 +1,17 @@
 use openvm_stark_backend::{
     config::StarkConfig,
-    interaction::fri_log_up::FriLogUpPhase,
+    interaction::fri_log_up::{FriLogUpParams, FriLogUpPhase},
     p3_challenger::{HashChallenger, SerializingChallenger32},
     p3_commit::ExtensionMmcs,
     p3_field::extension::BinomialExtensionField,
+    verifier::PcsShape,
 };
 use p3_baby_bear::BabyBear;
-use p3_dft::Radix2DitParallel;
 use p3_fri::{FriConfig, TwoAdicFriPcs};
 use p3_merkle_tree::MerkleTreeMmcs;
 use p3_symmetric::{CompressionFunctionFromHasher, CryptographicHasher, SerializingHasher32};
 
-use super::FriParameters;
+use super::{dft::RuntimeDft, FriParameters};
 use crate::engine::{StarkEngine, StarkFriEngine};
 
 type Val = BabyBear;
@@ -24,7 +24,7 @@ type Compress<H> = CompressionFunctionFromHasher<H, 2, 32>;
 
 type ValMmcs<H> = MerkleTreeMmcs<Val, u8, FieldHash<H>, Compress<H>, 32>;
 type ChallengeMmcs<H> = ExtensionMmcs<Val, Challenge, ValMmcs<H>>;
-type Dft = Radix2DitParallel<Val>;
+type Dft = RuntimeDft<Val>;
 type Challenger<H> = SerializingChallenger32<Val, HashChallenger<u8, H, 32>>;
 
 type Pcs<H> = TwoAdicFriPcs<Val, Dft, ValMmcs<H>, ChallengeMmcs<H>>;
@@ -41,6 +41,8 @@ where
     pub config: BabyBearByteHashConfig<H>,
     pub byte_hash: H,
     pub max_constraint_degree: usize,
+    /// See [StarkFriEngine::min_security_bits].
+    pub min_security_bits: Option<usize>,
 }
 
 impl<H> StarkEngine<BabyBearByteHashConfig<H>> for BabyBearByteHashEngine<H>
@@ -55,6 +57,17 @@ where
         Some(self.max_constraint_degree)
     }
 
+    fn pcs_shape(&self) -> Option<PcsShape<BabyBearByteHashConfig<H>>> {
+        Some(self.fri_params.pcs_shape(
+            |proof| proof.query_proofs.len(),
+            |proof, num_queries| {
+                let mut proof = proof.clone();
+                proof.query_proofs.truncate(num_queries);
+                proof
+            },
+        ))
+    }
+
     fn new_challenger(&self) -> Challenger<H> {
         Challenger::from_hasher(vec![], self.byte_hash.clone())
     }
@@ -82,6 +95,7 @@ where
         byte_hash,
         fri_params,
         max_constraint_degree: fri_params.max_constraint_degree(),
+        min_security_bits: None,
     }
 }
 
@@ -105,7 +119,7 @@ where
         mmcs: challenge_mmcs,
     };
     let pcs = Pcs::new(dft, val_mmcs, fri_config);
-    let rap_phase = FriLogUpPhase::new();
+    let rap_phase = FriLogUpPhase::new(FriLogUpParams::default());
     BabyBearByteHashConfig::new(pcs, rap_phase)
 }
 
@@ -127,4 +141,7 @@ where
     fn fri_params(&self) -> FriParameters {
         self.fri_params
     }
+    fn min_security_bits(&self) -> Option<usize> {
+        self.min_security_bits
+    }
 }
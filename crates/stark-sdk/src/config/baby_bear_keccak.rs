@@ -8,7 +8,7 @@ use super::{
 };
 use crate::{
     assert_sc_compatible_with_serde,
-    config::baby_bear_bytehash::BabyBearByteHashEngineWithDefaultHash,
+    config::baby_bear_bytehash::BabyBearByteHashEngineWithDefaultHash, key_serde::ConfigId,
 };
 
 pub type BabyBearKeccakConfig = BabyBearByteHashConfig<Keccak256Hash>;
@@ -16,6 +16,10 @@ pub type BabyBearKeccakEngine = BabyBearByteHashEngine<Keccak256Hash>;
 
 assert_sc_compatible_with_serde!(BabyBearKeccakConfig);
 
+impl ConfigId for BabyBearKeccakConfig {
+    const CONFIG_ID: &'static str = "baby_bear_keccak";
+}
+
 /// `pcs_log_degree` is the upper bound on the log_2(PCS polynomial degree).
 pub fn default_engine() -> BabyBearKeccakEngine {
     baby_bear_bytehash::default_engine(Keccak256Hash)
@@ -1,14 +1,19 @@
 use std::any::type_name;
 
 use openvm_stark_backend::{
-    config::StarkConfig,
-    interaction::fri_log_up::FriLogUpPhase,
+    config::{StarkConfig, StarkGenericConfig},
+    interaction::{
+        fri_log_up::{FriLogUpParams, FriLogUpPhase},
+        gkr_log_up::GkrLogUpPhase,
+    },
     p3_challenger::DuplexChallenger,
     p3_commit::ExtensionMmcs,
     p3_field::{extension::BinomialExtensionField, Field, FieldAlgebra},
+    proof::Proof,
+    prover::cpu::ZeroKnowledge,
+    verifier::PcsShape,
 };
 use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
-use p3_dft::Radix2DitParallel;
 use p3_fri::{FriConfig, TwoAdicFriPcs};
 use p3_merkle_tree::MerkleTreeMmcs;
 use p3_poseidon2::ExternalLayerConstants;
@@ -20,18 +25,26 @@ use zkhash::{
 };
 
 use super::{
+    dft::{DftBackend, RuntimeDft},
     instrument::{HashStatistics, InstrumentCounter, Instrumented, StarkHashStatistics},
+    salted_mmcs::{SaltedMmcs, ThreadRandomness},
     FriParameters,
 };
 use crate::{
     assert_sc_compatible_with_serde,
+    cost_estimate::SpongeConfig,
     engine::{StarkEngine, StarkEngineWithHashInstrumentation, StarkFriEngine},
+    key_serde::ConfigId,
+    size_report::{ProofSizeReport, SizeReport},
 };
 
 const RATE: usize = 8;
 // permutation width
 const WIDTH: usize = 16; // rate + capacity
-const DIGEST_WIDTH: usize = 8;
+pub(crate) const DIGEST_WIDTH: usize = 8;
+/// Number of random field elements salting each leaf of the Merkle trees of zero-knowledge
+/// proofs, i.e. 124 bits.
+const SALT_LEN: usize = 4;
 
 type Val = BabyBear;
 type PackedVal = <Val as Field>::Packing;
@@ -46,15 +59,51 @@ type ValMmcs<P> =
     MerkleTreeMmcs<PackedVal, <Val as Field>::Packing, Hash<P>, Compress<P>, DIGEST_WIDTH>;
 type ChallengeMmcs<P> = ExtensionMmcs<Val, Challenge, ValMmcs<P>>;
 pub type Challenger<P> = DuplexChallenger<Val, P, WIDTH, RATE>;
-type Dft = Radix2DitParallel<Val>;
+type Dft = RuntimeDft<Val>;
 type Pcs<P> = TwoAdicFriPcs<Val, Dft, ValMmcs<P>, ChallengeMmcs<P>>;
 type RapPhase<P> = FriLogUpPhase<Val, Challenge, Challenger<P>>;
+type GkrRapPhase<P> = GkrLogUpPhase<Val, Challenge, Challenger<P>>;
+type ZkValMmcs<P> = SaltedMmcs<Val, ValMmcs<P>>;
+type ZkChallengeMmcs<P> = ExtensionMmcs<Val, Challenge, ZkValMmcs<P>>;
+type ZkPcs<P> = TwoAdicFriPcs<Val, Dft, ZkValMmcs<P>, ZkChallengeMmcs<P>>;
 
 pub type BabyBearPermutationConfig<P> = StarkConfig<Pcs<P>, RapPhase<P>, Challenge, Challenger<P>>;
 pub type BabyBearPoseidon2Config = BabyBearPermutationConfig<Perm>;
 pub type BabyBearPoseidon2Engine = BabyBearPermutationEngine<Perm>;
+/// Configuration proving interactions with the GKR log-up phase instead of committed log-up
+/// columns.
+pub type BabyBearPermutationGkrConfig<P> =
+    StarkConfig<Pcs<P>, GkrRapPhase<P>, Challenge, Challenger<P>>;
+pub type BabyBearPoseidon2GkrConfig = BabyBearPermutationGkrConfig<Perm>;
+/// Configuration of zero-knowledge proofs, whose Merkle trees have salted leaves, see
+/// [BabyBearPoseidon2ZkEngine].
+pub type BabyBearPermutationZkConfig<P> =
+    StarkConfig<ZkPcs<P>, RapPhase<P>, Challenge, Challenger<P>>;
+pub type BabyBearPoseidon2ZkConfig = BabyBearPermutationZkConfig<Perm>;
 
 assert_sc_compatible_with_serde!(BabyBearPoseidon2Config);
+assert_sc_compatible_with_serde!(BabyBearPoseidon2GkrConfig);
+assert_sc_compatible_with_serde!(BabyBearPoseidon2ZkConfig);
+
+impl ConfigId for BabyBearPoseidon2Config {
+    const CONFIG_ID: &'static str = "baby_bear_poseidon2";
+}
+
+impl<P> SpongeConfig for BabyBearPermutationConfig<P>
+where
+    BabyBearPermutationConfig<P>: StarkGenericConfig,
+{
+    const RATE: usize = RATE;
+    const DIGEST_WIDTH: usize = DIGEST_WIDTH;
+}
+
+impl ConfigId for BabyBearPoseidon2GkrConfig {
+    const CONFIG_ID: &'static str = "baby_bear_poseidon2_gkr";
+}
+
+impl ConfigId for BabyBearPoseidon2ZkConfig {
+    const CONFIG_ID: &'static str = "baby_bear_poseidon2_zk";
+}
 
 pub struct BabyBearPermutationEngine<P>
 where
@@ -66,6 +115,8 @@ where
     pub config: BabyBearPermutationConfig<P>,
     pub perm: P,
     pub max_constraint_degree: usize,
+    /// See [StarkFriEngine::min_security_bits].
+    pub min_security_bits: Option<usize>,
 }
 
 impl<P> StarkEngine<BabyBearPermutationConfig<P>> for BabyBearPermutationEngine<P>
@@ -82,6 +133,17 @@ where
         Some(self.max_constraint_degree)
     }
 
+    fn pcs_shape(&self) -> Option<PcsShape<BabyBearPermutationConfig<P>>> {
+        Some(self.fri_params.pcs_shape(
+            |proof| proof.query_proofs.len(),
+            |proof, num_queries| {
+                let mut proof = proof.clone();
+                proof.query_proofs.truncate(num_queries);
+                proof
+            },
+        ))
+    }
+
     fn new_challenger(&self) -> Challenger<P> {
         Challenger::new(self.perm.clone())
     }
@@ -118,6 +180,67 @@ where
     }
 }
 
+/// Engine proving for zero-knowledge keys, see
+/// [set_zero_knowledge](openvm_stark_backend::keygen::MultiStarkKeygenBuilder::set_zero_knowledge).
+/// The random values of the traces, the quotients and the salts of the Merkle trees are drawn
+/// from [ThreadRandomness].
+pub struct BabyBearPoseidon2ZkEngine {
+    pub fri_params: FriParameters,
+    pub config: BabyBearPoseidon2ZkConfig,
+    pub perm: Perm,
+    pub max_constraint_degree: usize,
+}
+
+impl StarkEngine<BabyBearPoseidon2ZkConfig> for BabyBearPoseidon2ZkEngine {
+    fn config(&self) -> &BabyBearPoseidon2ZkConfig {
+        &self.config
+    }
+
+    fn max_constraint_degree(&self) -> Option<usize> {
+        Some(self.max_constraint_degree)
+    }
+
+    fn zero_knowledge(&self) -> Option<ZeroKnowledge<'_>> {
+        Some(ZeroKnowledge {
+            randomness: &ThreadRandomness,
+            num_queries: self.fri_params.num_queries,
+        })
+    }
+
+    fn pcs_shape(&self) -> Option<PcsShape<BabyBearPoseidon2ZkConfig>> {
+        Some(self.fri_params.pcs_shape(
+            |proof| proof.query_proofs.len(),
+            |proof, num_queries| {
+                let mut proof = proof.clone();
+                proof.query_proofs.truncate(num_queries);
+                proof
+            },
+        ))
+    }
+
+    fn new_challenger(&self) -> Challenger<Perm> {
+        Challenger::new(self.perm.clone())
+    }
+}
+
+impl StarkFriEngine<BabyBearPoseidon2ZkConfig> for BabyBearPoseidon2ZkEngine {
+    fn new(fri_params: FriParameters) -> Self {
+        let perm = default_perm();
+        let pcs = zk_pcs_from_perm(&perm, fri_params);
+        let config =
+            BabyBearPermutationZkConfig::new(pcs, FriLogUpPhase::new(FriLogUpParams::default()));
+        BabyBearPoseidon2ZkEngine {
+            config,
+            perm,
+            fri_params,
+            max_constraint_degree: fri_params.max_constraint_degree(),
+        }
+    }
+    fn fri_params(&self) -> FriParameters {
+        self.fri_params
+    }
+}
+
 /// `pcs_log_degree` is the upper bound on the log_2(PCS polynomial degree).
 pub fn default_engine() -> BabyBearPoseidon2Engine {
     default_engine_impl(FriParameters::standard_fast())
@@ -141,16 +264,73 @@ where
         + CryptographicPermutation<[PackedVal; WIDTH]>
         + Clone,
 {
-    let config = config_from_perm(&perm, fri_params);
+    engine_from_perm_with_dft(perm, fri_params, DftBackend::default())
+}
+
+/// Same as [engine_from_perm], but the PCS computes its DFTs with `dft_backend`. The proofs do
+/// not depend on `dft_backend`.
+pub fn engine_from_perm_with_dft<P>(
+    perm: P,
+    fri_params: FriParameters,
+    dft_backend: DftBackend,
+) -> BabyBearPermutationEngine<P>
+where
+    P: CryptographicPermutation<[Val; WIDTH]>
+        + CryptographicPermutation<[PackedVal; WIDTH]>
+        + Clone,
+{
+    let pcs = pcs_from_perm(&perm, fri_params, dft_backend);
+    let config = BabyBearPermutationConfig::new(pcs, FriLogUpPhase::new(FriLogUpParams::default()));
     BabyBearPermutationEngine {
         config,
         perm,
         fri_params,
         max_constraint_degree: fri_params.max_constraint_degree(),
+        min_security_bits: None,
     }
 }
 
 pub fn config_from_perm<P>(perm: &P, fri_params: FriParameters) -> BabyBearPermutationConfig<P>
+where
+    P: CryptographicPermutation<[Val; WIDTH]>
+        + CryptographicPermutation<[PackedVal; WIDTH]>
+        + Clone,
+{
+    config_from_perm_with_log_up_params(perm, fri_params, FriLogUpParams::default())
+}
+
+/// Same as [config_from_perm], but with the given parameters for the FRI log-up phase.
+pub fn config_from_perm_with_log_up_params<P>(
+    perm: &P,
+    fri_params: FriParameters,
+    log_up_params: FriLogUpParams,
+) -> BabyBearPermutationConfig<P>
+where
+    P: CryptographicPermutation<[Val; WIDTH]>
+        + CryptographicPermutation<[PackedVal; WIDTH]>
+        + Clone,
+{
+    let pcs = pcs_from_perm(perm, fri_params, DftBackend::default());
+    let rap_phase = FriLogUpPhase::new(log_up_params);
+    BabyBearPermutationConfig::new(pcs, rap_phase)
+}
+
+/// Same as [config_from_perm], but interactions are proven with the GKR log-up phase.
+pub fn config_from_perm_gkr<P>(
+    perm: &P,
+    fri_params: FriParameters,
+) -> BabyBearPermutationGkrConfig<P>
+where
+    P: CryptographicPermutation<[Val; WIDTH]>
+        + CryptographicPermutation<[PackedVal; WIDTH]>
+        + Clone,
+{
+    let pcs = pcs_from_perm(perm, fri_params, DftBackend::default());
+    let rap_phase = GkrLogUpPhase::new();
+    BabyBearPermutationGkrConfig::new(pcs, rap_phase)
+}
+
+fn pcs_from_perm<P>(perm: &P, fri_params: FriParameters, dft_backend: DftBackend) -> Pcs<P>
 where
     P: CryptographicPermutation<[Val; WIDTH]>
         + CryptographicPermutation<[PackedVal; WIDTH]>
@@ -160,7 +340,7 @@ where
     let compress = Compress::new(perm.clone());
     let val_mmcs = ValMmcs::new(hash, compress);
     let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
-    let dft = Dft::default();
+    let dft = Dft::new(dft_backend);
     let fri_config = FriConfig {
         log_blowup: fri_params.log_blowup,
         log_final_poly_len: fri_params.log_final_poly_len,
@@ -168,9 +348,29 @@ where
         proof_of_work_bits: fri_params.proof_of_work_bits,
         mmcs: challenge_mmcs,
     };
-    let pcs = Pcs::new(dft, val_mmcs, fri_config);
-    let rap_phase = FriLogUpPhase::new();
-    BabyBearPermutationConfig::new(pcs, rap_phase)
+    Pcs::new(dft, val_mmcs, fri_config)
+}
+
+/// Same as [pcs_from_perm], but the leaves of the Merkle trees are salted, see [SaltedMmcs].
+fn zk_pcs_from_perm<P>(perm: &P, fri_params: FriParameters) -> ZkPcs<P>
+where
+    P: CryptographicPermutation<[Val; WIDTH]>
+        + CryptographicPermutation<[PackedVal; WIDTH]>
+        + Clone,
+{
+    let hash = Hash::new(perm.clone());
+    let compress = Compress::new(perm.clone());
+    let val_mmcs = ZkValMmcs::new(ValMmcs::new(hash, compress), SALT_LEN);
+    let challenge_mmcs = ZkChallengeMmcs::new(val_mmcs.clone());
+    let dft = Dft::new(DftBackend::default());
+    let fri_config = FriConfig {
+        log_blowup: fri_params.log_blowup,
+        log_final_poly_len: fri_params.log_final_poly_len,
+        num_queries: fri_params.num_queries,
+        proof_of_work_bits: fri_params.proof_of_work_bits,
+        mmcs: challenge_mmcs,
+    };
+    ZkPcs::new(dft, val_mmcs, fri_config)
 }
 
 /// Uses HorizenLabs Poseidon2 round constants, but plonky3 Mat4 and also
@@ -271,4 +471,10 @@ impl StarkFriEngine<BabyBearPoseidon2Config> for BabyBearPoseidon2Engine {
     fn fri_params(&self) -> FriParameters {
         self.fri_params
     }
+    fn min_security_bits(&self) -> Option<usize> {
+        self.min_security_bits
+    }
+    fn proof_size_report(&self, proof: &Proof<BabyBearPoseidon2Config>) -> Option<ProofSizeReport> {
+        Some(proof.size_report())
+    }
 }
@@ -1,18 +1,23 @@
-use std::any::type_name;
+use std::{any::type_name, error::Error, fmt};
 
 use ff::PrimeField;
 use openvm_stark_backend::{
-    config::StarkConfig, interaction::fri_log_up::FriLogUpPhase,
-    p3_challenger::MultiField32Challenger, p3_commit::ExtensionMmcs,
+    config::StarkConfig,
+    interaction::fri_log_up::{FriLogUpParams, FriLogUpPhase},
+    p3_challenger::MultiField32Challenger,
+    p3_commit::ExtensionMmcs,
     p3_field::extension::BinomialExtensionField,
+    proof::Proof,
+    verifier::PcsShape,
 };
 use p3_baby_bear::BabyBear;
 use p3_bn254_fr::{Bn254Fr, FFBn254Fr, Poseidon2Bn254};
-use p3_dft::Radix2DitParallel;
 use p3_fri::{FriConfig, TwoAdicFriPcs};
 use p3_merkle_tree::MerkleTreeMmcs;
 use p3_poseidon2::ExternalLayerConstants;
-use p3_symmetric::{CryptographicPermutation, MultiField32PaddingFreeSponge, TruncatedPermutation};
+use p3_symmetric::{
+    CryptographicPermutation, Hash as Digest, MultiField32PaddingFreeSponge, TruncatedPermutation,
+};
 use zkhash::{
     ark_ff::{BigInteger, PrimeField as _},
     fields::bn256::FpBN256 as ark_FpBN256,
@@ -20,12 +25,15 @@ use zkhash::{
 };
 
 use super::{
+    dft::RuntimeDft,
     instrument::{HashStatistics, InstrumentCounter, Instrumented, StarkHashStatistics},
     FriParameters,
 };
 use crate::{
     assert_sc_compatible_with_serde,
     engine::{StarkEngine, StarkEngineWithHashInstrumentation, StarkFriEngine},
+    key_serde::ConfigId,
+    size_report::{ProofSizeReport, SizeReport},
 };
 
 const WIDTH: usize = 3;
@@ -41,7 +49,7 @@ type Hash<P> = MultiField32PaddingFreeSponge<Val, Bn254Fr, P, WIDTH, RATE, DIGES
 type Compress<P> = TruncatedPermutation<P, 2, 1, WIDTH>;
 type ValMmcs<P> = MerkleTreeMmcs<BabyBear, Bn254Fr, Hash<P>, Compress<P>, 1>;
 type ChallengeMmcs<P> = ExtensionMmcs<Val, Challenge, ValMmcs<P>>;
-type Dft = Radix2DitParallel<Val>;
+type Dft = RuntimeDft<Val>;
 type Challenger<P> = MultiField32Challenger<Val, Bn254Fr, P, WIDTH, 2>;
 type Pcs<P> = TwoAdicFriPcs<Val, Dft, ValMmcs<P>, ChallengeMmcs<P>>;
 type RapPhase<P> = FriLogUpPhase<Val, Challenge, Challenger<P>>;
@@ -50,9 +58,15 @@ pub type BabyBearPermutationRootConfig<P> =
     StarkConfig<Pcs<P>, RapPhase<P>, Challenge, Challenger<P>>;
 pub type BabyBearPoseidon2RootConfig = BabyBearPermutationRootConfig<Perm>;
 pub type BabyBearPoseidon2RootEngine = BabyBearPermutationRootEngine<Perm>;
+/// The commitments of [BabyBearPermutationRootConfig], a single Bn254 field element.
+pub type RootCommitment = Digest<Val, Bn254Fr, DIGEST_WIDTH>;
 
 assert_sc_compatible_with_serde!(BabyBearPoseidon2RootConfig);
 
+impl ConfigId for BabyBearPoseidon2RootConfig {
+    const CONFIG_ID: &'static str = "baby_bear_poseidon2_root";
+}
+
 pub struct BabyBearPermutationRootEngine<P>
 where
     P: CryptographicPermutation<[Bn254Fr; WIDTH]> + Clone,
@@ -61,6 +75,8 @@ where
     pub config: BabyBearPermutationRootConfig<P>,
     pub perm: P,
     pub max_constraint_degree: usize,
+    /// See [StarkFriEngine::min_security_bits].
+    pub min_security_bits: Option<usize>,
 }
 
 impl<P> StarkEngine<BabyBearPermutationRootConfig<P>> for BabyBearPermutationRootEngine<P>
@@ -75,6 +91,17 @@ where
         Some(self.max_constraint_degree)
     }
 
+    fn pcs_shape(&self) -> Option<PcsShape<BabyBearPermutationRootConfig<P>>> {
+        Some(self.fri_params.pcs_shape(
+            |proof| proof.query_proofs.len(),
+            |proof, num_queries| {
+                let mut proof = proof.clone();
+                proof.query_proofs.truncate(num_queries);
+                proof
+            },
+        ))
+    }
+
     fn new_challenger(&self) -> Challenger<P> {
         Challenger::new(self.perm.clone()).unwrap()
     }
@@ -109,6 +136,62 @@ where
     }
 }
 
+/// Canonical encoding of Bn254 field elements, and of the [RootCommitment]s, as the 32 big-endian
+/// bytes of their canonical value, as in a Solidity `uint256`. The serde encoding of [Bn254Fr]
+/// is the little-endian representation of `ff` instead.
+pub trait Bn254Bytes: Sized {
+    fn to_bytes_be(&self) -> [u8; 32];
+
+    /// Decodes `bytes`, which must be below the modulus of the Bn254 scalar field.
+    fn from_bytes_be(bytes: &[u8; 32]) -> Result<Self, NonCanonicalBn254Error>;
+}
+
+/// The bytes are not the big-endian encoding of a value below the modulus of the Bn254 scalar
+/// field.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NonCanonicalBn254Error(pub [u8; 32]);
+
+impl fmt::Display for NonCanonicalBn254Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "non-canonical Bn254 field element 0x")?;
+        for byte in self.0 {
+            write!(f, "{byte:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for NonCanonicalBn254Error {}
+
+impl Bn254Bytes for Bn254Fr {
+    fn to_bytes_be(&self) -> [u8; 32] {
+        let mut bytes: [u8; 32] = self.value.to_repr().as_ref().try_into().unwrap();
+        bytes.reverse();
+        bytes
+    }
+
+    fn from_bytes_be(bytes: &[u8; 32]) -> Result<Self, NonCanonicalBn254Error> {
+        let mut repr = <FFBn254Fr as PrimeField>::Repr::default();
+        for (digit, &byte) in repr.as_mut().iter_mut().zip(bytes.iter().rev()) {
+            *digit = byte;
+        }
+        Option::from(FFBn254Fr::from_repr(repr))
+            .map(|value| Bn254Fr { value })
+            .ok_or(NonCanonicalBn254Error(*bytes))
+    }
+}
+
+impl Bn254Bytes for RootCommitment {
+    fn to_bytes_be(&self) -> [u8; 32] {
+        let [value]: [Bn254Fr; 1] = (*self).into();
+        value.to_bytes_be()
+    }
+
+    fn from_bytes_be(bytes: &[u8; 32]) -> Result<Self, NonCanonicalBn254Error> {
+        Bn254Fr::from_bytes_be(bytes).map(|value| [value].into())
+    }
+}
+
 /// `pcs_log_degree` is the upper bound on the log_2(PCS polynomial degree).
 pub fn default_engine() -> BabyBearPoseidon2RootEngine {
     default_engine_impl(FriParameters::standard_fast())
@@ -136,6 +219,7 @@ where
         perm,
         fri_params,
         max_constraint_degree: fri_params.max_constraint_degree(),
+        min_security_bits: None,
     }
 }
 
@@ -156,7 +240,7 @@ where
         mmcs: challenge_mmcs,
     };
     let pcs = Pcs::new(dft, val_mmcs, fri_config);
-    let rap_phase = FriLogUpPhase::new();
+    let rap_phase = FriLogUpPhase::new(FriLogUpParams::default());
     BabyBearPermutationRootConfig::new(pcs, rap_phase)
 }
 
@@ -252,4 +336,13 @@ impl StarkFriEngine<BabyBearPoseidon2RootConfig> for BabyBearPoseidon2RootEngine
     fn fri_params(&self) -> FriParameters {
         self.fri_params
     }
+    fn min_security_bits(&self) -> Option<usize> {
+        self.min_security_bits
+    }
+    fn proof_size_report(
+        &self,
+        proof: &Proof<BabyBearPoseidon2RootConfig>,
+    ) -> Option<ProofSizeReport> {
+        Some(proof.size_report())
+    }
 }
@@ -0,0 +1,223 @@
+//! DFT of the PCS, selected at runtime.
+
+use std::sync::{Arc, Mutex};
+
+use openvm_stark_backend::{
+    p3_field::TwoAdicField,
+    p3_matrix::{
+        bitrev::{BitReversedMatrixView, BitReversibleMatrix},
+        dense::RowMajorMatrix,
+        Matrix,
+    },
+    p3_util::{log2_strict_usize, reverse_bits_len},
+};
+use p3_dft::{Radix2Bowers, Radix2Dit, Radix2DitParallel, TwoAdicSubgroupDft};
+use serde::{Deserialize, Serialize};
+
+/// With [DftBackend::Auto], matrices with at least `2^AUTO_PARALLEL_MIN_LOG_HEIGHT` rows use
+/// [DftBackend::Radix2DitParallel], and smaller ones [DftBackend::Radix2Dit]. The height where the
+/// parallel algorithm gets faster depends on the machine and the width of the matrices, and is
+/// reported by the `compare_dft_backends` example. [DftBackend::Auto] is not the default, so the
+/// engines only use this threshold when asked to.
+pub const AUTO_PARALLEL_MIN_LOG_HEIGHT: usize = 12;
+
+/// Algorithm of the DFTs of a [RuntimeDft]. All algorithms compute the same evaluations, so the
+/// choice does not affect proofs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DftBackend {
+    /// [Radix2Dit](DftBackend::Radix2Dit) or [Radix2DitParallel](DftBackend::Radix2DitParallel)
+    /// depending on the height of the matrix, see [AUTO_PARALLEL_MIN_LOG_HEIGHT].
+    Auto,
+    /// Radix-2 decimation-in-time, without the setup of the parallel variant, which makes it
+    /// faster on small matrices.
+    Radix2Dit,
+    /// Radix-2 decimation-in-time parallelized over rows and columns, with cached twiddles. The
+    /// default, as before the DFT was selectable.
+    #[default]
+    Radix2DitParallel,
+    /// Radix-2 Bowers G and G^T networks.
+    Radix2Bowers,
+}
+
+/// DFT dispatching to the algorithm of a [DftBackend] on each call.
+#[derive(Clone, Default)]
+pub struct RuntimeDft<F: TwoAdicField> {
+    backend: DftBackend,
+    parallel: Radix2DitParallel<F>,
+    /// The [Radix2Dit] DFTs, each with the twiddles it cached. Each call takes one for itself,
+    /// since the cache of a [Radix2Dit] is not shared between threads.
+    serial: Arc<Mutex<Vec<Radix2Dit<F>>>>,
+}
+
+impl<F: TwoAdicField> RuntimeDft<F>
+where
+    Radix2DitParallel<F>: Default,
+{
+    /// DFT with the algorithms of `backend`.
+    pub fn new(backend: DftBackend) -> Self {
+        Self {
+            backend,
+            parallel: Radix2DitParallel::default(),
+            serial: Default::default(),
+        }
+    }
+}
+
+impl<F: TwoAdicField> RuntimeDft<F> {
+    pub fn backend(&self) -> DftBackend {
+        self.backend
+    }
+
+    /// The algorithm used for a matrix with `2^log_height` rows, never [DftBackend::Auto].
+    pub fn backend_for(&self, log_height: usize) -> DftBackend {
+        match self.backend {
+            DftBackend::Auto if log_height >= AUTO_PARALLEL_MIN_LOG_HEIGHT => {
+                DftBackend::Radix2DitParallel
+            }
+            DftBackend::Auto => DftBackend::Radix2Dit,
+            backend => backend,
+        }
+    }
+
+    /// Runs `dft` with a [Radix2Dit] of the pool, and returns it to the pool for the next calls to
+    /// reuse its twiddles.
+    fn with_serial<R>(&self, dft: impl FnOnce(&Radix2Dit<F>) -> R) -> R {
+        let serial = self.serial.lock().unwrap().pop().unwrap_or_default();
+        let result = dft(&serial);
+        self.serial.lock().unwrap().push(serial);
+        result
+    }
+}
+
+impl<F: TwoAdicField + Ord> TwoAdicSubgroupDft<F> for RuntimeDft<F> {
+    type Evaluations = RuntimeEvaluations<F>;
+
+    fn dft_batch(&self, mat: RowMajorMatrix<F>) -> Self::Evaluations {
+        match self.backend_for(log2_strict_usize(mat.height())) {
+            DftBackend::Radix2Dit => natural(self.with_serial(|dft| dft.dft_batch(mat))),
+            DftBackend::Radix2Bowers => natural(Radix2Bowers.dft_batch(mat)),
+            DftBackend::Auto | DftBackend::Radix2DitParallel => {
+                bit_reversed(self.parallel.dft_batch(mat))
+            }
+        }
+    }
+
+    fn coset_dft_batch(&self, mat: RowMajorMatrix<F>, shift: F) -> Self::Evaluations {
+        match self.backend_for(log2_strict_usize(mat.height())) {
+            DftBackend::Radix2Dit => {
+                natural(self.with_serial(|dft| dft.coset_dft_batch(mat, shift)))
+            }
+            DftBackend::Radix2Bowers => natural(Radix2Bowers.coset_dft_batch(mat, shift)),
+            DftBackend::Auto | DftBackend::Radix2DitParallel => {
+                bit_reversed(self.parallel.coset_dft_batch(mat, shift))
+            }
+        }
+    }
+
+    fn idft_batch(&self, mat: RowMajorMatrix<F>) -> RowMajorMatrix<F> {
+        match self.backend_for(log2_strict_usize(mat.height())) {
+            DftBackend::Radix2Dit => self.with_serial(|dft| dft.idft_batch(mat)),
+            DftBackend::Radix2Bowers => Radix2Bowers.idft_batch(mat),
+            DftBackend::Auto | DftBackend::Radix2DitParallel => self.parallel.idft_batch(mat),
+        }
+    }
+
+    fn coset_lde_batch(
+        &self,
+        mat: RowMajorMatrix<F>,
+        added_bits: usize,
+        shift: F,
+    ) -> Self::Evaluations {
+        match self.backend_for(log2_strict_usize(mat.height()) + added_bits) {
+            DftBackend::Radix2Dit => {
+                natural(self.with_serial(|dft| dft.coset_lde_batch(mat, added_bits, shift)))
+            }
+            DftBackend::Radix2Bowers => {
+                natural(Radix2Bowers.coset_lde_batch(mat, added_bits, shift))
+            }
+            DftBackend::Auto | DftBackend::Radix2DitParallel => {
+                bit_reversed(self.parallel.coset_lde_batch(mat, added_bits, shift))
+            }
+        }
+    }
+}
+
+/// Evaluations of a [RuntimeDft], with their rows stored in the order the algorithm computed
+/// them: natural order for [Radix2Dit] and [Radix2Bowers], bit-reversed order for
+/// [Radix2DitParallel]. Rows are read in natural order, and [bit_reverse_rows] only flips the
+/// order in which they are read, so the rows are only reordered when they are copied into a
+/// matrix in the other order.
+///
+/// [bit_reverse_rows]: BitReversibleMatrix::bit_reverse_rows
+pub struct RuntimeEvaluations<F> {
+    values: RowMajorMatrix<F>,
+    /// Whether row `r` is stored at index `reverse_bits_len(r, log_height)` of `values`.
+    bit_reversed: bool,
+}
+
+fn natural<F>(values: RowMajorMatrix<F>) -> RuntimeEvaluations<F> {
+    RuntimeEvaluations {
+        values,
+        bit_reversed: false,
+    }
+}
+
+/// The evaluations of [Radix2DitParallel], whose view in natural order is over a matrix in
+/// bit-reversed order.
+fn bit_reversed<F: Clone + Send + Sync>(
+    evaluations: BitReversedMatrixView<RowMajorMatrix<F>>,
+) -> RuntimeEvaluations<F> {
+    RuntimeEvaluations {
+        values: evaluations.bit_reverse_rows(),
+        bit_reversed: true,
+    }
+}
+
+impl<F: Clone + Send + Sync> Matrix<F> for RuntimeEvaluations<F> {
+    type Row<'a>
+        = <RowMajorMatrix<F> as Matrix<F>>::Row<'a>
+    where
+        Self: 'a;
+
+    fn width(&self) -> usize {
+        self.values.width()
+    }
+
+    fn height(&self) -> usize {
+        self.values.height()
+    }
+
+    fn row(&self, r: usize) -> Self::Row<'_> {
+        if self.bit_reversed {
+            let log_height = log2_strict_usize(self.height());
+            self.values.row(reverse_bits_len(r, log_height))
+        } else {
+            self.values.row(r)
+        }
+    }
+
+    fn to_row_major_matrix(self) -> RowMajorMatrix<F>
+    where
+        Self: Sized,
+        F: Clone,
+    {
+        if self.bit_reversed {
+            let width = self.width();
+            let values = (0..self.height()).flat_map(|r| self.row(r)).collect();
+            RowMajorMatrix::new(values, width)
+        } else {
+            self.values
+        }
+    }
+}
+
+impl<F: Clone + Send + Sync> BitReversibleMatrix<F> for RuntimeEvaluations<F> {
+    type BitRev = Self;
+
+    fn bit_reverse_rows(self) -> Self {
+        Self {
+            values: self.values,
+            bit_reversed: !self.bit_reversed,
+        }
+    }
+}
@@ -1,3 +1,8 @@
+use openvm_stark_backend::{
+    config::{PcsProof, StarkGenericConfig},
+    keygen::digest::PcsDigestParams,
+    verifier::PcsShape,
+};
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
@@ -27,11 +32,40 @@ impl FriParameters {
         standard_fri_params_with_100_bits_conjectured_security(log_blowup)
     }
 
+    /// The shape of the opening proofs of a FRI PCS with these parameters, whose queries are
+    /// counted by `num_query_proofs` and truncated by `truncate_query_proofs`, see
+    /// [StarkEngine::pcs_shape].
+    ///
+    /// [StarkEngine::pcs_shape]: openvm_stark_backend::engine::StarkEngine::pcs_shape
+    pub fn pcs_shape<SC: StarkGenericConfig>(
+        &self,
+        num_query_proofs: fn(&PcsProof<SC>) -> usize,
+        truncate_query_proofs: fn(&PcsProof<SC>, usize) -> PcsProof<SC>,
+    ) -> PcsShape<SC> {
+        PcsShape {
+            log_blowup: self.log_blowup,
+            num_queries: self.num_queries,
+            num_query_proofs,
+            truncate_query_proofs,
+        }
+    }
+
     pub fn max_constraint_degree(&self) -> usize {
         (1 << self.log_blowup) + 1
     }
 }
 
+impl PcsDigestParams for FriParameters {
+    fn digest_params(&self) -> Vec<usize> {
+        vec![
+            self.log_blowup,
+            self.log_final_poly_len,
+            self.num_queries,
+            self.proof_of_work_bits,
+        ]
+    }
+}
+
 /// Pre-defined FRI parameters with 100 bits of conjectured security.
 /// Security bits calculated following ethSTARK (<https://eprint.iacr.org/2021/582.pdf>) 5.10.1 eq (19)
 ///
@@ -1,13 +1,13 @@
 use std::any::type_name;
 
 use openvm_stark_backend::{
-    config::StarkConfig,
-    interaction::fri_log_up::FriLogUpPhase,
+    config::{StarkConfig, StarkGenericConfig},
+    interaction::fri_log_up::{FriLogUpParams, FriLogUpPhase},
     p3_challenger::DuplexChallenger,
     p3_commit::ExtensionMmcs,
     p3_field::{extension::BinomialExtensionField, Field},
+    verifier::PcsShape,
 };
-use p3_dft::Radix2DitParallel;
 use p3_fri::{FriConfig, TwoAdicFriPcs};
 use p3_goldilocks::{Goldilocks, MdsMatrixGoldilocks};
 use p3_merkle_tree::MerkleTreeMmcs;
@@ -16,12 +16,15 @@ use p3_symmetric::{CryptographicPermutation, PaddingFreeSponge, TruncatedPermuta
 use rand::{rngs::StdRng, SeedableRng};
 
 use super::{
+    dft::RuntimeDft,
     instrument::{HashStatistics, Instrumented, StarkHashStatistics},
     FriParameters,
 };
 use crate::{
     assert_sc_compatible_with_serde,
+    cost_estimate::SpongeConfig,
     engine::{StarkEngine, StarkEngineWithHashInstrumentation},
+    key_serde::ConfigId,
 };
 
 const RATE: usize = 4;
@@ -42,7 +45,7 @@ type ValMmcs<P> =
     MerkleTreeMmcs<PackedVal, <Val as Field>::Packing, Hash<P>, Compress<P>, DIGEST_WIDTH>;
 type ChallengeMmcs<P> = ExtensionMmcs<Val, Challenge, ValMmcs<P>>;
 pub type Challenger<P> = DuplexChallenger<Val, P, WIDTH, RATE>;
-type Dft = Radix2DitParallel<Val>;
+type Dft = RuntimeDft<Val>;
 type Pcs<P> = TwoAdicFriPcs<Val, Dft, ValMmcs<P>, ChallengeMmcs<P>>;
 type RapPhase<P> = FriLogUpPhase<Val, Challenge, Challenger<P>>;
 
@@ -53,6 +56,18 @@ pub type GoldilocksPoseidonEngine = GoldilocksPermutationEngine<Perm>;
 
 assert_sc_compatible_with_serde!(GoldilocksPoseidonConfig);
 
+impl ConfigId for GoldilocksPoseidonConfig {
+    const CONFIG_ID: &'static str = "goldilocks_poseidon";
+}
+
+impl<P> SpongeConfig for GoldilocksPermutationConfig<P>
+where
+    GoldilocksPermutationConfig<P>: StarkGenericConfig,
+{
+    const RATE: usize = RATE;
+    const DIGEST_WIDTH: usize = DIGEST_WIDTH;
+}
+
 pub struct GoldilocksPermutationEngine<P>
 where
     P: CryptographicPermutation<[Val; WIDTH]>
@@ -79,6 +94,17 @@ where
         Some(self.max_constraint_degree)
     }
 
+    fn pcs_shape(&self) -> Option<PcsShape<GoldilocksPermutationConfig<P>>> {
+        Some(self.fri_params.pcs_shape(
+            |proof| proof.query_proofs.len(),
+            |proof, num_queries| {
+                let mut proof = proof.clone();
+                proof.query_proofs.truncate(num_queries);
+                proof
+            },
+        ))
+    }
+
     fn new_challenger(&self) -> Challenger<P> {
         Challenger::new(self.perm.clone())
     }
@@ -162,7 +188,7 @@ where
         mmcs: challenge_mmcs,
     };
     let pcs = Pcs::new(dft, val_mmcs, fri_config);
-    let rap_phase = RapPhase::new();
+    let rap_phase = RapPhase::new(FriLogUpParams::default());
     GoldilocksPermutationConfig::new(pcs, rap_phase)
 }
 
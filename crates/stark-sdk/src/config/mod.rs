@@ -10,9 +10,12 @@ pub mod baby_bear_keccak;
 pub mod baby_bear_poseidon2;
 /// Stark Config for root stark, which field is BabyBear but polynomials are committed in Bn254.
 pub mod baby_bear_poseidon2_root;
+pub mod dft;
 pub mod fri_params;
 pub mod goldilocks_poseidon;
 pub mod instrument;
+pub mod koala_bear_poseidon2;
+pub mod salted_mmcs;
 
 pub use fri_params::FriParameters;
 
@@ -0,0 +1,168 @@
+//! A mixed matrix commitment scheme salting the leaves of another one, for zero-knowledge proofs.
+
+use std::{
+    iter::{self, zip},
+    marker::PhantomData,
+    slice,
+};
+
+use itertools::Itertools;
+use openvm_stark_backend::{
+    p3_commit::Mmcs,
+    p3_field::Field,
+    p3_matrix::{dense::RowMajorMatrix, Dimensions, Matrix},
+    prover::cpu::ZkRandomness,
+};
+use rand::Rng;
+
+/// Draws the random values of zero-knowledge proofs from [rand::thread_rng], a cryptographically
+/// secure generator seeded by the operating system.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ThreadRandomness;
+
+impl ZkRandomness for ThreadRandomness {
+    fn fill_u64(&self, dest: &mut [u64]) {
+        rand::thread_rng().fill(dest);
+    }
+}
+
+/// Commits matrices with `Inner` after appending `salt_len` random values to each of their rows,
+/// so that the commitment reveals nothing about the rows that are not opened. The salt of an
+/// opened row is part of its opening proof.
+#[derive(Clone)]
+pub struct SaltedMmcs<F, Inner> {
+    inner: Inner,
+    salt_len: usize,
+    _marker: PhantomData<F>,
+}
+
+impl<F, Inner> SaltedMmcs<F, Inner> {
+    /// ## Panics
+    /// If `salt_len` is zero.
+    pub fn new(inner: Inner, salt_len: usize) -> Self {
+        assert_ne!(salt_len, 0, "the salt must not be empty");
+        Self {
+            inner,
+            salt_len,
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// A matrix with its salt, committed by [SaltedMmcs].
+pub struct SaltedMatrix<F, M> {
+    pub matrix: M,
+    /// The salt of each row of `matrix`.
+    salt: RowMajorMatrix<F>,
+}
+
+impl<F: Clone + Send + Sync, M: Matrix<F>> Matrix<F> for SaltedMatrix<F, M> {
+    type Row<'a>
+        = iter::Chain<M::Row<'a>, iter::Cloned<slice::Iter<'a, F>>>
+    where
+        Self: 'a;
+
+    fn width(&self) -> usize {
+        self.matrix.width() + self.salt.width()
+    }
+
+    fn height(&self) -> usize {
+        self.matrix.height()
+    }
+
+    fn row(&self, r: usize) -> Self::Row<'_> {
+        let salt_len = self.salt.width();
+        let salt = &self.salt.values[r * salt_len..(r + 1) * salt_len];
+        self.matrix.row(r).chain(salt.iter().cloned())
+    }
+}
+
+/// An opening rejected by [SaltedMmcs].
+#[derive(Debug)]
+pub enum SaltedMmcsError<E> {
+    /// The proof does not have one salt of the salt length per opened row.
+    SaltShape,
+    Inner(E),
+}
+
+impl<F, Inner> Mmcs<F> for SaltedMmcs<F, Inner>
+where
+    F: Field,
+    Inner: Mmcs<F>,
+{
+    type ProverData<M> = Inner::ProverData<SaltedMatrix<F, M>>;
+    type Commitment = Inner::Commitment;
+    /// The salt of the opened row of each matrix, and the opening proof of the salted rows.
+    type Proof = (Vec<Vec<F>>, Inner::Proof);
+    type Error = SaltedMmcsError<Inner::Error>;
+
+    fn commit<M: Matrix<F>>(&self, inputs: Vec<M>) -> (Self::Commitment, Self::ProverData<M>) {
+        let mut rng = rand::thread_rng();
+        let salted = inputs
+            .into_iter()
+            .map(|matrix| {
+                let values = (0..matrix.height() * self.salt_len)
+                    .map(|_| F::from_wrapped_u64(rng.gen()))
+                    .collect();
+                SaltedMatrix {
+                    matrix,
+                    salt: RowMajorMatrix::new(values, self.salt_len),
+                }
+            })
+            .collect();
+        self.inner.commit(salted)
+    }
+
+    fn open_batch<M: Matrix<F>>(
+        &self,
+        index: usize,
+        prover_data: &Self::ProverData<M>,
+    ) -> (Vec<Vec<F>>, Self::Proof) {
+        let (salted_rows, proof) = self.inner.open_batch(index, prover_data);
+        let (rows, salts) = salted_rows
+            .into_iter()
+            .map(|mut row| {
+                let salt = row.split_off(row.len() - self.salt_len);
+                (row, salt)
+            })
+            .unzip();
+        (rows, (salts, proof))
+    }
+
+    fn get_matrices<'a, M: Matrix<F>>(&self, prover_data: &'a Self::ProverData<M>) -> Vec<&'a M> {
+        self.inner
+            .get_matrices(prover_data)
+            .into_iter()
+            .map(|salted| &salted.matrix)
+            .collect()
+    }
+
+    fn verify_batch(
+        &self,
+        commit: &Self::Commitment,
+        dimensions: &[Dimensions],
+        index: usize,
+        opened_values: &[Vec<F>],
+        proof: &Self::Proof,
+    ) -> Result<(), Self::Error> {
+        let (salts, proof) = proof;
+        if salts.len() != opened_values.len()
+            || salts.iter().any(|salt| salt.len() != self.salt_len)
+        {
+            return Err(SaltedMmcsError::SaltShape);
+        }
+        let dimensions = dimensions
+            .iter()
+            .map(|dims| Dimensions {
+                width: dims.width + self.salt_len,
+                height: dims.height,
+            })
+            .collect_vec();
+        let salted_rows = zip(opened_values, salts)
+            .map(|(row, salt)| row.iter().chain(salt).copied().collect())
+            .collect_vec();
+        self.inner
+            .verify_batch(commit, &dimensions, index, &salted_rows, proof)
+            .map_err(SaltedMmcsError::Inner)
+    }
+}
@@ -1,12 +1,20 @@
-use std::{marker::PhantomData, ops::Add};
+use std::{collections::BTreeMap, marker::PhantomData, mem, ops::Add};
 
 use openvm_stark_backend::{
+    air_builders::symbolic::SymbolicExpressionNode,
     config::{Com, StarkGenericConfig, Val},
-    keygen::types::StarkVerifyingKey,
-    p3_field::FieldExtensionAlgebra,
+    interaction::fri_log_up::STARK_LU_NUM_CHALLENGES,
+    keygen::types::{CommitmentRound, MultiStarkVerifyingKey, StarkVerifyingKey, VkFeature},
+    p3_field::{FieldExtensionAlgebra, PrimeField64},
+    p3_util::log2_ceil_usize,
 };
+use serde::{Deserialize, Serialize};
 
-use crate::config::FriParameters;
+use crate::{
+    config::FriParameters,
+    key_serde::{config_name, ConfigId},
+    proof_serde::{field_len, varint_len, CanonicalCommitment, PROOF_MAGIC},
+};
 
 /// Properties of a multi-trace circuit necessary to estimate verifier cost.
 #[derive(Clone, Copy, Debug)]
@@ -220,3 +228,714 @@ impl FriVerifierCostEstimate {
         )
     }
 }
+
+/// A config with the FRI log-up phase whose hasher, compression function and duplex challenger
+/// are built on one permutation of the base field, each taking one permutation per call, whose
+/// verifier cost [estimate_verifier_cost] counts.
+pub trait SpongeConfig: StarkGenericConfig {
+    /// Number of base field elements absorbed per permutation, by the hasher and the challenger.
+    const RATE: usize;
+    /// Number of base field elements of a digest.
+    const DIGEST_WIDTH: usize;
+}
+
+/// Phases of the verifier, in the order of the transcript.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VerifierPhase {
+    /// The AIR ids, the public values, the trace commitments and the trace heights.
+    Preamble,
+    /// The challenges of the log-up argument, the exposed values and the after challenge
+    /// commitment.
+    ChallengePhase,
+    /// The constraint folding challenge, the quotient commitment and the out-of-domain point.
+    Quotient,
+    /// The batching challenge of the opening proof, the FRI commit phase, the proof of work and
+    /// the query indices.
+    Opening,
+}
+
+/// Work of the challenger in a phase of the verifier.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TranscriptCost {
+    /// Number of base field elements observed.
+    pub observations: usize,
+    /// Number of base field elements sampled.
+    pub samples: usize,
+    /// Number of permutations of the duplex sponge.
+    pub permutations: usize,
+}
+
+/// Hashing to verify the opening of a commitment at one query index.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommitmentOpeningCost {
+    pub round: CommitmentRound,
+    /// Permutations to hash the opened rows into the leaves, one leaf per matrix height.
+    pub leaf_permutations: usize,
+    /// 2-to-1 compressions along the Merkle path, one per level of the tree and one to inject the
+    /// leaf of each matrix height below the tallest.
+    pub compressions: usize,
+}
+
+impl CommitmentOpeningCost {
+    /// `matrices` are the `(log_height, width)` of the matrices of the commitment, with heights
+    /// of their low degree extension and widths in base field elements.
+    fn new(round: CommitmentRound, matrices: &[(usize, usize)], rate: usize) -> Self {
+        let mut width_per_log_height = BTreeMap::<usize, usize>::new();
+        for &(log_height, width) in matrices {
+            *width_per_log_height.entry(log_height).or_default() += width;
+        }
+        let log_max_height = width_per_log_height.keys().last().copied().unwrap_or(0);
+        Self {
+            round,
+            leaf_permutations: width_per_log_height
+                .values()
+                .map(|width| width.div_ceil(rate))
+                .sum(),
+            compressions: log_max_height + width_per_log_height.len().saturating_sub(1),
+        }
+    }
+
+    pub fn permutations(&self) -> usize {
+        self.leaf_permutations + self.compressions
+    }
+}
+
+/// Challenge field multiplications to evaluate the constraints of an AIR at the out-of-domain
+/// point.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AirConstraintCost {
+    pub air_id: usize,
+    pub air_name: String,
+    /// Multiplications of the nodes of the constraint DAG.
+    pub dag_muls: usize,
+    /// Multiplications by the folding challenge, one per constraint.
+    pub folding_muls: usize,
+}
+
+/// Cost of verifying a proof, see [estimate_verifier_cost].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerifierCostReport {
+    pub num_queries: usize,
+    /// Work of the challenger in each phase, in the order of the transcript.
+    pub transcript: Vec<(VerifierPhase, TranscriptCost)>,
+    /// Hashing to verify the opening of each commitment, per query.
+    pub openings: Vec<CommitmentOpeningCost>,
+    /// Permutations to verify the openings of the FRI commit phase, per query.
+    pub fri_fold_permutations: usize,
+    /// Constraint evaluation per AIR of the proof.
+    pub constraints: Vec<AirConstraintCost>,
+}
+
+impl VerifierCostReport {
+    /// Total number of permutations of the verifier, for the challenger and the commitments.
+    pub fn num_permutations(&self) -> usize {
+        let transcript = self
+            .transcript
+            .iter()
+            .map(|(_, cost)| cost.permutations)
+            .sum::<usize>();
+        let per_query = self
+            .openings
+            .iter()
+            .map(CommitmentOpeningCost::permutations)
+            .sum::<usize>()
+            + self.fri_fold_permutations;
+        transcript + self.num_queries * per_query
+    }
+
+    /// Total number of challenge field multiplications to evaluate the constraints.
+    pub fn num_constraint_muls(&self) -> usize {
+        self.constraints
+            .iter()
+            .map(|air| air.dag_muls + air.folding_muls)
+            .sum()
+    }
+}
+
+/// Counts the work of a duplex challenger of rate `rate`, which permutes when it observes a full
+/// rate of elements, and when it samples after an observation or once its outputs are used up.
+struct DuplexChallengerCounter {
+    rate: usize,
+    num_inputs: usize,
+    num_outputs: usize,
+    cost: TranscriptCost,
+}
+
+impl DuplexChallengerCounter {
+    fn new(rate: usize) -> Self {
+        Self {
+            rate,
+            num_inputs: 0,
+            num_outputs: 0,
+            cost: TranscriptCost::default(),
+        }
+    }
+
+    fn observe(&mut self, num_elements: usize) {
+        for _ in 0..num_elements {
+            self.cost.observations += 1;
+            self.num_outputs = 0;
+            self.num_inputs += 1;
+            if self.num_inputs == self.rate {
+                self.duplex();
+            }
+        }
+    }
+
+    fn sample(&mut self, num_elements: usize) {
+        for _ in 0..num_elements {
+            self.cost.samples += 1;
+            if self.num_inputs != 0 || self.num_outputs == 0 {
+                self.duplex();
+            }
+            self.num_outputs -= 1;
+        }
+    }
+
+    fn duplex(&mut self) {
+        self.cost.permutations += 1;
+        self.num_inputs = 0;
+        self.num_outputs = self.rate;
+    }
+
+    /// Returns the work since the last call.
+    fn take(&mut self) -> TranscriptCost {
+        mem::take(&mut self.cost)
+    }
+}
+
+/// Estimates the cost of verifying a proof for `vk` with the AIRs and log2 trace heights
+/// `log_trace_heights`, see [Proof::log_trace_heights], in increasing AIR id order.
+///
+/// The hash counts are exact for the [SpongeConfig]s, for a proof without absent AIRs verified
+/// with a fresh challenger.
+///
+/// [Proof::log_trace_heights]: openvm_stark_backend::proof::Proof::log_trace_heights
+pub fn estimate_verifier_cost<SC: SpongeConfig>(
+    vk: &MultiStarkVerifyingKey<SC>,
+    fri_params: FriParameters,
+    log_trace_heights: &[(usize, usize)],
+) -> VerifierCostReport {
+    let ext_degree = <SC::Challenge as FieldExtensionAlgebra<Val<SC>>>::D;
+    let airs = log_trace_heights
+        .iter()
+        .map(|&(air_id, log_height)| (&vk.per_air[air_id], log_height))
+        .collect::<Vec<_>>();
+    let mut challenger = DuplexChallengerCounter::new(SC::RATE);
+    let mut transcript = Vec::new();
+
+    challenger.observe(1 + airs.len());
+    // No absent AIRs.
+    challenger.observe(1);
+    challenger.observe(
+        airs.iter()
+            .map(|(air_vk, _)| air_vk.params.num_public_values)
+            .sum(),
+    );
+    for round in &vk.commitment_rounds {
+        let num_commits = match round {
+            CommitmentRound::Preprocessed => airs
+                .iter()
+                .filter(|(air_vk, _)| has_preprocessed(air_vk))
+                .count(),
+            CommitmentRound::CachedMain => airs
+                .iter()
+                .map(|(air_vk, _)| air_vk.num_cached_mains())
+                .sum(),
+            CommitmentRound::CommonMain => 1,
+            CommitmentRound::Custom { index, .. } => airs
+                .iter()
+                .any(|(air_vk, _)| air_vk.custom_width(*index).is_some())
+                as usize,
+            CommitmentRound::AfterChallenge { .. } | CommitmentRound::Quotient => 0,
+        };
+        challenger.observe(num_commits * SC::DIGEST_WIDTH);
+    }
+    challenger.observe(airs.len());
+    transcript.push((VerifierPhase::Preamble, challenger.take()));
+
+    let interacting_airs = airs
+        .iter()
+        .filter(|(air_vk, _)| air_vk.has_interaction())
+        .collect::<Vec<_>>();
+    if let Some((air_vk, _)) = interacting_airs.first() {
+        for table in &vk.public_lookup_tables {
+            challenger.observe(table.rows.iter().map(Vec::len).sum::<usize>());
+        }
+        for table in &vk.public_lookup_tables {
+            challenger.observe(table.rows.len());
+        }
+        let num_repetitions = air_vk.log_up_params.num_repetitions;
+        challenger.sample(num_repetitions * STARK_LU_NUM_CHALLENGES * ext_degree);
+        challenger.observe(
+            interacting_airs
+                .iter()
+                .map(|(air_vk, _)| air_vk.params.num_exposed_values_after_challenge[0])
+                .sum::<usize>()
+                * ext_degree,
+        );
+        challenger.observe(SC::DIGEST_WIDTH);
+        transcript.push((VerifierPhase::ChallengePhase, challenger.take()));
+    }
+
+    challenger.sample(ext_degree);
+    challenger.observe(SC::DIGEST_WIDTH);
+    challenger.sample(ext_degree);
+    transcript.push((VerifierPhase::Quotient, challenger.take()));
+
+    let openings = commitment_matrices(vk, fri_params, &airs);
+
+    // FRI folds the largest matrix down to the final polynomial, with one commitment per fold.
+    // Each commitment opens two challenge field elements per query.
+    let log_max_height = log_max_lde_height(&openings);
+    let log_final_height = fri_params.log_blowup + fri_params.log_final_poly_len;
+    let fri_fold_permutations = (log_final_height..log_max_height)
+        .map(|log_folded_height| (2 * ext_degree).div_ceil(SC::RATE) + log_folded_height)
+        .sum();
+    let num_fri_rounds = log_max_height.saturating_sub(log_final_height);
+
+    challenger.sample(ext_degree);
+    for _ in 0..num_fri_rounds {
+        challenger.observe(SC::DIGEST_WIDTH);
+        challenger.sample(ext_degree);
+    }
+    challenger.observe((1 << fri_params.log_final_poly_len) * ext_degree);
+    // The proof of work witness, and the sample checking it.
+    challenger.observe(1);
+    challenger.sample(1);
+    challenger.sample(fri_params.num_queries);
+    transcript.push((VerifierPhase::Opening, challenger.take()));
+
+    let constraints = log_trace_heights
+        .iter()
+        .map(|&(air_id, _)| {
+            let air_vk = &vk.per_air[air_id];
+            let dag = &air_vk.symbolic_constraints.constraints;
+            AirConstraintCost {
+                air_id,
+                air_name: air_vk.air_name.clone(),
+                dag_muls: dag
+                    .nodes
+                    .iter()
+                    .filter(|node| matches!(node, SymbolicExpressionNode::Mul { .. }))
+                    .count(),
+                folding_muls: dag.constraint_idx.len(),
+            }
+        })
+        .collect();
+
+    VerifierCostReport {
+        num_queries: fri_params.num_queries,
+        transcript,
+        openings: openings
+            .into_iter()
+            .map(|(round, matrices)| CommitmentOpeningCost::new(round, &matrices, SC::RATE))
+            .collect(),
+        fri_fold_permutations,
+        constraints,
+    }
+}
+
+/// Bytes of the FRI proof of a serialized proof, see [ProofSizeEstimate].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FriProofSize {
+    pub num_queries: usize,
+    /// log2 of the height of the largest low degree extension, which FRI folds down to the final
+    /// polynomial.
+    pub log_max_height: usize,
+    /// The commitments of the commit phase, one per fold.
+    pub commit_phase_commitments: usize,
+    /// The opened rows and Merkle paths of the committed traces at one query index.
+    pub input_openings_per_query: usize,
+    /// The sibling values and Merkle paths of the commit phase at one query index.
+    pub commit_phase_openings_per_query: usize,
+    /// The openings of all the queries.
+    pub query_proofs: usize,
+    pub final_poly: usize,
+    pub pow_witness: usize,
+}
+
+impl FriProofSize {
+    /// The length of the encoding of the FRI proof.
+    pub fn total(&self) -> usize {
+        self.commit_phase_commitments + self.query_proofs + self.final_poly + self.pow_witness
+    }
+}
+
+/// Bytes of each part of a proof serialized by
+/// [ProofSerde::to_bytes](crate::proof_serde::ProofSerde::to_bytes), see [estimate_proof_size].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofSizeEstimate {
+    /// The header, with the name of the config.
+    pub header: usize,
+    /// The length prefixes of the sections of the proof, and of the lists of commitments of the
+    /// opened values.
+    pub framing: usize,
+    pub commitments: usize,
+    /// The ids and trace heights of the AIRs.
+    pub airs: usize,
+    pub public_values: usize,
+    pub exposed_values: usize,
+    /// The opened values of each trace commitment, in the order of the proof.
+    pub opened_values: Vec<(CommitmentRound, usize)>,
+    /// The opened values of the quotient chunks of all the AIRs.
+    pub quotient_opened_values: usize,
+    /// The partial proof of the challenge phase.
+    pub challenge_phase_proof: usize,
+    pub absent_air_ids: usize,
+    pub fri: FriProofSize,
+}
+
+impl ProofSizeEstimate {
+    /// The length of the serialized proof.
+    pub fn total(&self) -> usize {
+        self.header
+            + self.framing
+            + self.commitments
+            + self.airs
+            + self.public_values
+            + self.exposed_values
+            + self.opened_values.iter().map(|(_, len)| len).sum::<usize>()
+            + self.quotient_opened_values
+            + self.challenge_phase_proof
+            + self.absent_air_ids
+            + self.fri.total()
+    }
+}
+
+/// Estimates the length of the serialization by [ProofSerde::to_bytes] of a proof for `vk` with
+/// the AIRs and log2 trace heights `log_trace_heights`, see [Proof::log_trace_heights], in
+/// increasing AIR id order, with a PCS using `fri_params`. Only the FRI proof depends on the
+/// number of queries and the height of the largest trace.
+///
+/// The estimate is exact for the configs of the sdk with the FRI log-up phase, whose Merkle
+/// digests are encoded as their commitments, for a proof without absent AIRs.
+///
+/// [ProofSerde::to_bytes]: crate::proof_serde::ProofSerde::to_bytes
+/// [Proof::log_trace_heights]: openvm_stark_backend::proof::Proof::log_trace_heights
+pub fn estimate_proof_size<SC: StarkGenericConfig + ConfigId>(
+    vk: &MultiStarkVerifyingKey<SC>,
+    fri_params: FriParameters,
+    log_trace_heights: &[(usize, usize)],
+) -> ProofSizeEstimate
+where
+    Val<SC>: PrimeField64,
+    Com<SC>: CanonicalCommitment,
+{
+    let ext_degree = <SC::Challenge as FieldExtensionAlgebra<Val<SC>>>::D;
+    let zero_knowledge = vk.features.contains(VkFeature::ZeroKnowledge);
+    let airs = log_trace_heights
+        .iter()
+        .map(|&(air_id, log_height)| (&vk.per_air[air_id], log_height))
+        .collect::<Vec<_>>();
+    let interacting_airs = airs
+        .iter()
+        .filter(|(air_vk, _)| air_vk.has_interaction())
+        .collect::<Vec<_>>();
+
+    // The commitments section keeps the layout of the bincode encoding of the commitments, with
+    // lengths as `u64`.
+    let commit_list_len = mem::size_of::<u64>();
+    let digest_bytes = Com::<SC>::LEN;
+    // The rest of the proof has varint lengths and truncated field elements.
+    let field_bytes = field_len::<Val<SC>>();
+    let challenge_bytes = ext_degree * field_bytes;
+    let ext_fields = |len: usize| varint_len(len) + len * ext_degree * field_bytes;
+    let adjacent = |width: usize, num_rotations: usize| {
+        2 * ext_fields(width) + varint_len(num_rotations) + num_rotations * ext_fields(width)
+    };
+    let list = |entries: &[usize]| varint_len(entries.len()) + entries.iter().sum::<usize>();
+
+    let num_main_commits = airs
+        .iter()
+        .map(|(air_vk, _)| air_vk.num_cached_mains())
+        .sum::<usize>()
+        + 1;
+    let num_phases = interacting_airs
+        .iter()
+        .map(|(air_vk, _)| air_vk.params.width.after_challenge.len())
+        .max()
+        .unwrap_or(0);
+    let custom_rounds = vk
+        .commitment_rounds
+        .iter()
+        .filter_map(|round| match round {
+            CommitmentRound::Custom { index, .. } => {
+                let widths = airs
+                    .iter()
+                    .filter_map(|(air_vk, _)| {
+                        let rotations = air_vk.params.extra_rotations.len();
+                        Some(adjacent(air_vk.custom_width(*index)?, rotations))
+                    })
+                    .collect::<Vec<_>>();
+                (!widths.is_empty()).then(|| (round.clone(), list(&widths)))
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let commitments = 3 * commit_list_len
+        + (num_main_commits + num_phases + 1 + custom_rounds.len()) * digest_bytes;
+
+    let air_ids_and_heights = log_trace_heights
+        .iter()
+        .map(|&(air_id, log_height)| varint_len(air_id) + varint_len(1 << log_height))
+        .collect::<Vec<_>>();
+    let public_values = airs
+        .iter()
+        .map(|(air_vk, _)| {
+            let num_public_values = air_vk.params.num_public_values;
+            varint_len(num_public_values) + num_public_values * field_bytes
+        })
+        .sum();
+    let exposed_values = airs
+        .iter()
+        .map(|(air_vk, _)| {
+            let num_exposed_values: &[usize] = if air_vk.has_interaction() {
+                &air_vk.params.num_exposed_values_after_challenge
+            } else {
+                &[]
+            };
+            list(
+                &num_exposed_values
+                    .iter()
+                    .map(|&len| ext_fields(len))
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .sum();
+
+    let mut opened_values = Vec::new();
+    for (air_vk, _) in &airs {
+        if has_preprocessed(air_vk) {
+            let width = air_vk.params.width.preprocessed.unwrap_or(0);
+            let values = adjacent(width, air_vk.params.extra_rotations.len());
+            opened_values.push((CommitmentRound::Preprocessed, values));
+        }
+    }
+    let num_preprocessed = opened_values.len();
+    for (air_vk, _) in &airs {
+        for &width in &air_vk.params.width.cached_mains {
+            let values = list(&[adjacent(width, air_vk.params.extra_rotations.len())]);
+            opened_values.push((CommitmentRound::CachedMain, values));
+        }
+    }
+    let common_main = airs
+        .iter()
+        .filter(|(air_vk, _)| air_vk.has_common_main())
+        .map(|(air_vk, _)| {
+            adjacent(
+                air_vk.params.width.common_main,
+                air_vk.params.extra_rotations.len(),
+            )
+        })
+        .collect::<Vec<_>>();
+    opened_values.push((CommitmentRound::CommonMain, list(&common_main)));
+    for phase in 0..num_phases {
+        let values = interacting_airs
+            .iter()
+            .filter_map(|(air_vk, _)| {
+                let width = air_vk.params.width.after_challenge.get(phase)?;
+                Some(adjacent(width * ext_degree, 0))
+            })
+            .collect::<Vec<_>>();
+        opened_values.push((CommitmentRound::AfterChallenge { phase }, list(&values)));
+    }
+    opened_values.extend(custom_rounds.iter().cloned());
+    // In zero-knowledge mode, the opening of the random mask of each quotient follows its chunks.
+    let quotient_opened_values = list(
+        &airs
+            .iter()
+            .map(|(air_vk, _)| {
+                let num_chunks = air_vk.quotient_degree as usize + zero_knowledge as usize;
+                varint_len(num_chunks) + num_chunks * ext_fields(ext_degree)
+            })
+            .collect::<Vec<_>>(),
+    );
+
+    // The multiplicity of each row of each public lookup table, if an AIR has interactions.
+    let challenge_phase_proof = 1 + if interacting_airs.is_empty() {
+        0
+    } else {
+        varint_len(vk.public_lookup_tables.len())
+            + vk.public_lookup_tables
+                .iter()
+                .map(|table| varint_len(table.rows.len()) + table.rows.len() * field_bytes)
+                .sum::<usize>()
+    };
+    let absent_air_ids = varint_len(0);
+
+    // Each query opens a row of every committed matrix, with one Merkle path per commitment, and
+    // the sibling of each fold with its Merkle path in the folded commitment.
+    let matrices = commitment_matrices(vk, fri_params, &airs);
+    let log_max_height = log_max_lde_height(&matrices);
+    let log_final_height = fri_params.log_blowup + fri_params.log_final_poly_len;
+    let merkle_path = |log_height: usize| varint_len(log_height) + log_height * digest_bytes;
+    let input_openings_per_query = varint_len(matrices.len())
+        + matrices
+            .iter()
+            .map(|(_, matrices)| {
+                let log_height = matrices.iter().map(|&(log_height, _)| log_height).max();
+                varint_len(matrices.len())
+                    + matrices
+                        .iter()
+                        .map(|&(_, width)| varint_len(width) + width * field_bytes)
+                        .sum::<usize>()
+                    + merkle_path(log_height.unwrap_or(0))
+            })
+            .sum::<usize>();
+    let num_fri_rounds = log_max_height.saturating_sub(log_final_height);
+    let commit_phase_openings_per_query = varint_len(num_fri_rounds)
+        + (log_final_height..log_max_height)
+            .map(|log_folded_height| challenge_bytes + merkle_path(log_folded_height))
+            .sum::<usize>();
+    let fri = FriProofSize {
+        num_queries: fri_params.num_queries,
+        log_max_height,
+        commit_phase_commitments: varint_len(num_fri_rounds) + num_fri_rounds * digest_bytes,
+        input_openings_per_query,
+        commit_phase_openings_per_query,
+        query_proofs: varint_len(fri_params.num_queries)
+            + fri_params.num_queries * (input_openings_per_query + commit_phase_openings_per_query),
+        final_poly: varint_len(1 << fri_params.log_final_poly_len)
+            + (1 << fri_params.log_final_poly_len) * challenge_bytes,
+        pow_witness: field_bytes,
+    };
+
+    let airs_len = list(&air_ids_and_heights);
+    let opened_values_lists = [
+        num_preprocessed,
+        num_main_commits,
+        num_phases,
+        custom_rounds.len(),
+    ]
+    .into_iter()
+    .map(varint_len)
+    .sum::<usize>();
+    let opened_values_len = opened_values_lists
+        + opened_values.iter().map(|(_, len)| len).sum::<usize>()
+        + quotient_opened_values;
+    let framing = opened_values_lists
+        + [
+            commitments,
+            airs_len,
+            public_values,
+            exposed_values,
+            opened_values_len,
+            challenge_phase_proof,
+            absent_air_ids,
+            fri.total(),
+        ]
+        .into_iter()
+        .map(varint_len)
+        .sum::<usize>();
+
+    ProofSizeEstimate {
+        header: PROOF_MAGIC.len() + 8 + config_name::<SC>().len(),
+        framing,
+        commitments,
+        airs: airs_len,
+        public_values,
+        exposed_values,
+        opened_values,
+        quotient_opened_values,
+        challenge_phase_proof,
+        absent_air_ids,
+        fri,
+    }
+}
+
+/// The `(log_height, width)` of the low degree extension of the matrices of each commitment of a
+/// proof of `airs`, with their log2 trace heights, in the order of the opening proof. Widths are
+/// in base field elements.
+fn commitment_matrices<SC: StarkGenericConfig>(
+    vk: &MultiStarkVerifyingKey<SC>,
+    fri_params: FriParameters,
+    airs: &[(&StarkVerifyingKey<Val<SC>, Com<SC>>, usize)],
+) -> Vec<(CommitmentRound, Vec<(usize, usize)>)> {
+    let ext_degree = <SC::Challenge as FieldExtensionAlgebra<Val<SC>>>::D;
+    let zero_knowledge = vk.features.contains(VkFeature::ZeroKnowledge);
+    // In zero-knowledge mode, every trace is committed with twice its height, and the mask of each
+    // quotient on the whole quotient domain.
+    let log_blowup = fri_params.log_blowup;
+    let trace_lde = |log_height: usize, width: usize| {
+        (log_height + zero_knowledge as usize + log_blowup, width)
+    };
+    let mut openings = Vec::new();
+    for &(air_vk, log_height) in airs {
+        if has_preprocessed(air_vk) {
+            let width = air_vk.params.width.preprocessed.unwrap_or(0);
+            openings.push((
+                CommitmentRound::Preprocessed,
+                vec![trace_lde(log_height, width)],
+            ));
+        }
+    }
+    for &(air_vk, log_height) in airs {
+        for &width in &air_vk.params.width.cached_mains {
+            openings.push((
+                CommitmentRound::CachedMain,
+                vec![trace_lde(log_height, width)],
+            ));
+        }
+    }
+    let common_main = airs
+        .iter()
+        .filter(|(air_vk, _)| air_vk.has_common_main())
+        .map(|&(air_vk, log_height)| trace_lde(log_height, air_vk.params.width.common_main))
+        .collect();
+    openings.push((CommitmentRound::CommonMain, common_main));
+    for round in &vk.commitment_rounds {
+        if let CommitmentRound::Custom { index, .. } = round {
+            let matrices = airs
+                .iter()
+                .filter_map(|&(air_vk, log_height)| {
+                    Some(trace_lde(log_height, air_vk.custom_width(*index)?))
+                })
+                .collect::<Vec<_>>();
+            if !matrices.is_empty() {
+                openings.push((round.clone(), matrices));
+            }
+        }
+    }
+    let num_phases = airs
+        .iter()
+        .map(|(air_vk, _)| air_vk.params.width.after_challenge.len())
+        .max()
+        .unwrap_or(0);
+    for phase in 0..num_phases {
+        let matrices = airs
+            .iter()
+            .filter_map(|&(air_vk, log_height)| {
+                let width = air_vk.params.width.after_challenge.get(phase)?;
+                Some(trace_lde(log_height, width * ext_degree))
+            })
+            .collect();
+        openings.push((CommitmentRound::AfterChallenge { phase }, matrices));
+    }
+    let quotient = airs
+        .iter()
+        .flat_map(|&(air_vk, log_height)| {
+            let quotient_degree = air_vk.quotient_degree as usize;
+            let chunk = (log_height + log_blowup, ext_degree);
+            let mask = (
+                log_height + log2_ceil_usize(quotient_degree) + log_blowup,
+                ext_degree,
+            );
+            itertools::repeat_n(chunk, quotient_degree).chain(zero_knowledge.then_some(mask))
+        })
+        .collect();
+    openings.push((CommitmentRound::Quotient, quotient));
+    openings
+}
+
+fn log_max_lde_height(openings: &[(CommitmentRound, Vec<(usize, usize)>)]) -> usize {
+    openings
+        .iter()
+        .flat_map(|(_, matrices)| matrices.iter().map(|&(log_height, _)| log_height))
+        .max()
+        .unwrap_or(0)
+}
+
+fn has_preprocessed<Val, Com>(air_vk: &StarkVerifyingKey<Val, Com>) -> bool {
+    air_vk.preprocessed_data.is_some() || air_vk.preprocessed_per_height.is_some()
+}
@@ -0,0 +1,86 @@
+//! Debugging of AIRs on their traces, without keygen or proving.
+
+use itertools::{izip, Itertools};
+use openvm_stark_backend::{
+    air_builders::symbolic::get_symbolic_builder,
+    config::{StarkGenericConfig, Val},
+    interaction::{
+        bus::BusRegistry,
+        debug::{check_interaction_balance, InteractionReport},
+        fri_log_up::FriLogUpParams,
+        RapPhaseSeq,
+    },
+    keygen::types::TraceWidth,
+    p3_air::BaseAir,
+    p3_matrix::{dense::RowMajorMatrix, Matrix},
+    rap::{AnyRap, PartitionedBaseAir},
+    AirRef,
+};
+
+/// Checks that the interactions of `airs` balance on every bus, where `traces[i]` is the common
+/// main trace of `airs[i]`. The interactions are those found by keygen, and the preprocessed
+/// traces and periodic columns are taken from the AIRs.
+///
+/// The AIRs must not have cached main traces, and their interactions must not depend on public
+/// values.
+///
+/// On failure, the report gives for each unbalanced bus the net multiplicity of each AIR and the
+/// first unbalanced messages on each side, with their rows. The buses are named after
+/// `bus_registry`.
+pub fn debug_interactions<SC: StarkGenericConfig>(
+    airs: &[AirRef<SC>],
+    bus_registry: &BusRegistry,
+    traces: &[RowMajorMatrix<Val<SC>>],
+) -> Result<(), InteractionReport<Val<SC>>> {
+    assert_eq!(airs.len(), traces.len(), "one trace per AIR");
+    let preprocessed = airs
+        .iter()
+        .map(|air| air.preprocessed_trace())
+        .collect_vec();
+    let interactions = izip!(airs, &preprocessed)
+        .map(|(air, preprocessed)| {
+            assert!(
+                air.cached_main_widths().is_empty(),
+                "{}: cached main traces are not supported",
+                air.name()
+            );
+            let width = TraceWidth {
+                preprocessed: preprocessed.as_ref().map(|trace| trace.width()),
+                cached_mains: vec![],
+                common_main: air.common_main_width(),
+                after_challenge: vec![],
+                custom: vec![],
+            };
+            get_symbolic_builder(
+                air.as_ref(),
+                &width,
+                &[],
+                &[],
+                SC::RapPhaseSeq::ID,
+                0,
+                FriLogUpParams::default(),
+                false,
+            )
+            .constraints()
+            .interactions
+        })
+        .collect_vec();
+    let periodic_columns = airs.iter().map(|air| air.periodic_columns()).collect_vec();
+
+    check_interaction_balance(
+        &airs.iter().map(|air| air.name()).collect_vec(),
+        bus_registry,
+        &[],
+        &interactions,
+        &preprocessed
+            .iter()
+            .map(|trace| trace.as_ref().map(|trace| trace.as_view()))
+            .collect_vec(),
+        &traces
+            .iter()
+            .map(|trace| vec![trace.as_view()])
+            .collect_vec(),
+        &vec![vec![]; airs.len()],
+        &periodic_columns.iter().map(|c| c.as_slice()).collect_vec(),
+    )
+}
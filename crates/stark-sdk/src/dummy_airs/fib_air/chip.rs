@@ -47,6 +47,7 @@ where
             raw: AirProofRawInput {
                 cached_mains: vec![],
                 common_main: Some(generate_trace_rows::<Val<SC>>(self.a, self.b, self.n)),
+                custom_mains: vec![],
                 public_values: vec![a, b, last_val],
             },
         }
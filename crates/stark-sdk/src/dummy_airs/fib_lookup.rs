@@ -0,0 +1,75 @@
+//! A Fibonacci AIR, and an AIR sending on bus 0 the messages another AIR receives: the AIRs of a
+//! proof with public values, a challenge phase and, with [padded_messages], traces of several
+//! heights.
+
+use std::sync::Arc;
+
+use openvm_stark_backend::{
+    config::{StarkGenericConfig, Val},
+    keygen::MultiStarkKeygenBuilder,
+    p3_field::{FieldAlgebra, PrimeField32},
+    p3_matrix::dense::RowMajorMatrix,
+    prover::types::{AirProofInput, ProofInput},
+};
+
+use super::{
+    fib_air::{air::FibonacciAir, trace::generate_trace_rows},
+    interaction::dummy_interaction_air::DummyInteractionAir,
+};
+
+/// Adds the Fibonacci AIR, the sender and the receiver to `keygen_builder`, and returns their
+/// AIR ids.
+pub fn add_airs<SC: StarkGenericConfig>(
+    keygen_builder: &mut MultiStarkKeygenBuilder<'_, SC>,
+) -> [usize; 3] {
+    [
+        keygen_builder.add_air(Arc::new(FibonacciAir)),
+        keygen_builder.add_air(Arc::new(DummyInteractionAir::new(1, true, 0))),
+        keygen_builder.add_air(Arc::new(DummyInteractionAir::new(1, false, 0))),
+    ]
+}
+
+/// The `| count | field |` trace of 4 messages: 5 and 7, twice each.
+pub fn messages<F: FieldAlgebra>() -> RowMajorMatrix<F> {
+    RowMajorMatrix::new(
+        [1, 5, 2, 7, 0, 9, 1, 5].map(F::from_canonical_u32).to_vec(),
+        2,
+    )
+}
+
+/// The messages of [messages] in a trace of height 8, padded with rows of count 0.
+pub fn padded_messages<F: FieldAlgebra>() -> RowMajorMatrix<F> {
+    RowMajorMatrix::new(
+        [2, 5, 2, 7, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]
+            .map(F::from_canonical_u32)
+            .to_vec(),
+        2,
+    )
+}
+
+/// The Fibonacci trace of height `n` starting from 0 and 1, with its public values.
+pub fn fib_input<SC: StarkGenericConfig>(n: usize) -> AirProofInput<SC>
+where
+    Val<SC>: PrimeField32,
+{
+    let trace = generate_trace_rows::<Val<SC>>(0, 1, n);
+    let pis = vec![
+        Val::<SC>::ZERO,
+        Val::<SC>::ONE,
+        *trace.values.last().unwrap(),
+    ];
+    AirProofInput::simple(trace, pis)
+}
+
+/// The input of the AIRs of [add_airs], with ids 0, 1 and 2: the Fibonacci trace of height `n`,
+/// and [messages] sent and received.
+pub fn proof_input<SC: StarkGenericConfig>(n: usize) -> ProofInput<SC>
+where
+    Val<SC>: PrimeField32,
+{
+    ProofInput::new(vec![
+        (0, fib_input(n)),
+        (1, AirProofInput::simple_no_pis(messages())),
+        (2, AirProofInput::simple_no_pis(messages())),
+    ])
+}
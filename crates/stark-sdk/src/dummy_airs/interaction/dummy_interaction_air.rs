@@ -248,6 +248,7 @@ impl<SC: StarkGenericConfig> Chip<SC> for DummyInteractionChip<'_, SC> {
                 raw: AirProofRawInput {
                     cached_mains: vec![cached.trace],
                     common_main: Some(common_main),
+                    custom_mains: vec![],
                     public_values: vec![],
                 },
             }
@@ -258,6 +259,7 @@ impl<SC: StarkGenericConfig> Chip<SC> for DummyInteractionChip<'_, SC> {
                 raw: AirProofRawInput {
                     cached_mains: vec![],
                     common_main: Some(common_main),
+                    custom_mains: vec![],
                     public_values: vec![],
                 },
             }
@@ -1,3 +1,5 @@
 pub mod fib_air;
+/// A Fibonacci AIR next to a lookup, shared by the tests.
+pub mod fib_lookup;
 /// Some dummy AIRs for testing.
 pub mod interaction;
@@ -1,15 +1,33 @@
 pub use openvm_stark_backend::engine::StarkEngine;
+#[cfg(feature = "interaction-log")]
+use openvm_stark_backend::interaction::log::{
+    start_interaction_log, take_interaction_log, InteractionLog,
+};
 use openvm_stark_backend::{
-    config::{StarkGenericConfig, Val},
+    config::{Com, StarkGenericConfig, Val},
     engine::VerificationData,
+    keygen::{
+        digest::VK_DIGEST_WIDTH,
+        types::{MultiStarkProvingKey, MultiStarkVerifyingKey},
+        MultiStarkKeygenBuilder,
+    },
+    p3_field::PrimeField64,
     p3_matrix::dense::RowMajorMatrix,
-    prover::types::AirProofInput,
+    proof::Proof,
+    prover::types::{AirProofInput, ProofInput},
     verifier::VerificationError,
     AirRef,
 };
+use serde::{de::DeserializeOwned, Serialize};
 use tracing::Level;
 
-use crate::config::{instrument::StarkHashStatistics, setup_tracing_with_log_level, FriParameters};
+use crate::{
+    config::{instrument::StarkHashStatistics, setup_tracing_with_log_level, FriParameters},
+    key_serde::ConfigId,
+    security::{total_soundness, InsufficientSecurityError, SoundnessBreakdown, WorkloadBounds},
+    size_report::ProofSizeReport,
+    verifier_handle::{PinnedVkError, VerifierHandle, VkDigest},
+};
 
 pub trait StarkEngineWithHashInstrumentation<SC: StarkGenericConfig>: StarkEngine<SC> {
     fn clear_instruments(&mut self);
@@ -26,26 +44,179 @@ pub struct VerificationDataWithFriParams<SC: StarkGenericConfig> {
 pub trait StarkFriEngine<SC: StarkGenericConfig>: StarkEngine<SC> + Sized {
     fn new(fri_parameters: FriParameters) -> Self;
     fn fri_params(&self) -> FriParameters;
+    /// Minimum total bits of security, as estimated by [total_soundness], required by
+    /// [checked_generate_pk](Self::checked_generate_pk) and
+    /// [checked_prove](Self::checked_prove). `None` disables the check.
+    fn min_security_bits(&self) -> Option<usize> {
+        None
+    }
+    /// The byte accounting of the serialization of `proof`, printed by
+    /// [run_test](Self::run_test) with the `bench-metrics` feature. `None` if the config has no
+    /// [SizeReport](crate::size_report::SizeReport).
+    fn proof_size_report(&self, _proof: &Proof<SC>) -> Option<ProofSizeReport> {
+        None
+    }
+    /// Estimates the total soundness of proofs within `bounds`, and checks it against
+    /// [min_security_bits](Self::min_security_bits).
+    fn check_security(
+        &self,
+        vk: &MultiStarkVerifyingKey<SC>,
+        bounds: &WorkloadBounds,
+    ) -> Result<SoundnessBreakdown, InsufficientSecurityError>
+    where
+        Val<SC>: PrimeField64,
+    {
+        let breakdown = total_soundness(vk, self.fri_params(), bounds);
+        match self.min_security_bits() {
+            Some(min_security_bits) if breakdown.total < min_security_bits as f64 => {
+                Err(InsufficientSecurityError {
+                    min_security_bits,
+                    breakdown,
+                })
+            }
+            _ => Ok(breakdown),
+        }
+    }
+    /// Generates the proving key, refusing keys whose proofs within `bounds` do not meet
+    /// [min_security_bits](Self::min_security_bits).
+    fn checked_generate_pk(
+        &self,
+        keygen_builder: MultiStarkKeygenBuilder<SC>,
+        bounds: &WorkloadBounds,
+    ) -> Result<MultiStarkProvingKey<SC>, InsufficientSecurityError>
+    where
+        Val<SC>: PrimeField64,
+    {
+        let pk = keygen_builder.generate_pk();
+        self.check_security(&pk.get_vk(), bounds)?;
+        Ok(pk)
+    }
+    /// Same as [prove](StarkEngine::prove), but first checks that the proof meets
+    /// [min_security_bits](Self::min_security_bits) for the trace heights of `proof_input`.
+    fn checked_prove(
+        &self,
+        mpk: &MultiStarkProvingKey<SC>,
+        proof_input: ProofInput<SC>,
+    ) -> Result<Proof<SC>, InsufficientSecurityError>
+    where
+        Val<SC>: PrimeField64,
+    {
+        let max_log_height = proof_input
+            .per_air
+            .iter()
+            .map(|(_, input)| input.raw.height().next_power_of_two().ilog2() as usize)
+            .max()
+            .unwrap_or(0);
+        let max_interaction_count = proof_input
+            .per_air
+            .iter()
+            .map(|(air_id, input)| {
+                mpk.per_air[*air_id]
+                    .vk
+                    .symbolic_constraints
+                    .interactions
+                    .len()
+                    * input.raw.height()
+            })
+            .sum();
+        let bounds = WorkloadBounds {
+            max_log_height,
+            max_interaction_count,
+        };
+        self.check_security(&mpk.get_vk(), &bounds)?;
+        Ok(self.prove(mpk, proof_input))
+    }
+    /// Digest of `vk` with the FRI parameters of the engine, see
+    /// [MultiStarkVerifyingKey::digest].
+    fn vk_digest(&self, vk: &MultiStarkVerifyingKey<SC>) -> [Val<SC>; VK_DIGEST_WIDTH]
+    where
+        Val<SC>: PrimeField64,
+    {
+        vk.digest(&mut self.new_challenger(), &self.fri_params())
+    }
+    /// Verifies `proof` against the verifying key serialized in `vk_bytes`, e.g. loaded from
+    /// untrusted storage, after checking that the key has the `expected` digest with the FRI
+    /// parameters of the engine. A key with another digest is rejected before any work on the
+    /// proof. See [VerifierHandle] to verify many proofs against the same key.
+    fn verify_with_digest(
+        &self,
+        expected: &VkDigest<SC>,
+        vk_bytes: &[u8],
+        proof: &Proof<SC>,
+    ) -> Result<(), PinnedVkError<Val<SC>>>
+    where
+        SC: ConfigId,
+        Val<SC>: PrimeField64,
+        Com<SC>: Serialize + DeserializeOwned,
+    {
+        VerifierHandle::from_digest_and_bytes(self, expected, vk_bytes)?.verify(proof)?;
+        Ok(())
+    }
+    /// JSON encoding of `vk` with the FRI parameters of the engine, see
+    /// [MultiStarkVerifyingKey::to_json].
+    fn vk_json(&self, vk: &MultiStarkVerifyingKey<SC>) -> String
+    where
+        Val<SC>: PrimeField64,
+        Com<SC>: Serialize + DeserializeOwned,
+    {
+        let FriParameters {
+            log_blowup,
+            log_final_poly_len,
+            num_queries,
+            proof_of_work_bits,
+        } = self.fri_params();
+        vk.to_json(&[
+            ("log_blowup", log_blowup),
+            ("log_final_poly_len", log_final_poly_len),
+            ("num_queries", num_queries),
+            ("proof_of_work_bits", proof_of_work_bits),
+        ])
+    }
     fn run_test(
         &self,
         airs: Vec<AirRef<SC>>,
         air_proof_inputs: Vec<AirProofInput<SC>>,
     ) -> Result<VerificationDataWithFriParams<SC>, VerificationError>
     where
+        Val<SC>: PrimeField64,
         AirProofInput<SC>: Send + Sync,
     {
         setup_tracing_with_log_level(Level::WARN);
         let data = <Self as StarkEngine<_>>::run_test_impl(self, airs, air_proof_inputs)?;
+        #[cfg(feature = "bench-metrics")]
+        if let Some(report) = self.proof_size_report(&data.proof) {
+            println!("{report}");
+        }
         Ok(VerificationDataWithFriParams {
             data,
             fri_params: self.fri_params(),
         })
     }
+    /// Same as [run_test](Self::run_test), but also returns the messages of the interactions, as
+    /// logged by the debug builder. At most `max_entries_per_bus` messages are kept on each bus.
+    /// The log is empty if the debug builder is disabled.
+    #[cfg(feature = "interaction-log")]
+    fn run_test_with_interaction_log(
+        &self,
+        airs: Vec<AirRef<SC>>,
+        air_proof_inputs: Vec<AirProofInput<SC>>,
+        max_entries_per_bus: usize,
+    ) -> Result<(VerificationDataWithFriParams<SC>, InteractionLog<Val<SC>>), VerificationError>
+    where
+        Val<SC>: PrimeField64,
+        AirProofInput<SC>: Send + Sync,
+    {
+        start_interaction_log::<Val<SC>>(max_entries_per_bus);
+        let result = self.run_test(airs, air_proof_inputs);
+        let log = take_interaction_log().expect("the interaction log was started");
+        result.map(|data| (data, log))
+    }
     fn run_test_fast(
         airs: Vec<AirRef<SC>>,
         air_proof_inputs: Vec<AirProofInput<SC>>,
     ) -> Result<VerificationDataWithFriParams<SC>, VerificationError>
     where
+        Val<SC>: PrimeField64,
         AirProofInput<SC>: Send + Sync,
     {
         let engine = Self::new(FriParameters::standard_fast());
@@ -61,6 +232,7 @@ pub trait StarkFriEngine<SC: StarkGenericConfig>: StarkEngine<SC> + Sized {
         public_values: Vec<Vec<Val<SC>>>,
     ) -> Result<VerificationDataWithFriParams<SC>, VerificationError>
     where
+        Val<SC>: PrimeField64,
         AirProofInput<SC>: Send + Sync,
     {
         self.run_test(chips, AirProofInput::multiple_simple(traces, public_values))
@@ -69,14 +241,20 @@ pub trait StarkFriEngine<SC: StarkGenericConfig>: StarkEngine<SC> + Sized {
         airs: Vec<AirRef<SC>>,
         traces: Vec<RowMajorMatrix<Val<SC>>>,
         public_values: Vec<Vec<Val<SC>>>,
-    ) -> Result<VerificationDataWithFriParams<SC>, VerificationError> {
+    ) -> Result<VerificationDataWithFriParams<SC>, VerificationError>
+    where
+        Val<SC>: PrimeField64,
+    {
         let engine = Self::new(FriParameters::standard_fast());
         StarkFriEngine::<_>::run_simple_test_impl(&engine, airs, traces, public_values)
     }
     fn run_simple_test_no_pis_fast(
         airs: Vec<AirRef<SC>>,
         traces: Vec<RowMajorMatrix<Val<SC>>>,
-    ) -> Result<VerificationDataWithFriParams<SC>, VerificationError> {
+    ) -> Result<VerificationDataWithFriParams<SC>, VerificationError>
+    where
+        Val<SC>: PrimeField64,
+    {
         let pis = vec![vec![]; airs.len()];
         <Self as StarkFriEngine<SC>>::run_simple_test_fast(airs, traces, pis)
     }
@@ -0,0 +1,274 @@
+//! Versioned, self-describing serialization of proving and verifying keys.
+//!
+//! A serialized key is a header followed by the bincode encoding of the key. Header layout:
+//!
+//! - the 8 bytes [KEY_MAGIC]
+//! - the format version, as a little-endian `u32`
+//! - the [KeyKind], as one byte
+//! - the [ConfigId] of the STARK config, as a little-endian `u32` length followed by its UTF-8
+//!   bytes
+//!
+//! The format version is bumped whenever the encoding of the keys changes, so that a key
+//! serialized by an incompatible version is rejected instead of decoding into garbage.
+//!
+//! Keys serialized with plain bincode, without the header, start with the little-endian `u64`
+//! number of AIRs of the key. They are detected and rejected with
+//! [KeySerdeError::Unversioned].
+
+use std::{error::Error, fmt};
+
+use openvm_stark_backend::{
+    config::{Com, PcsProverData, StarkGenericConfig, Val},
+    keygen::types::{MultiStarkProvingKey, MultiStarkVerifyingKey},
+    p3_field::PrimeField64,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Magic bytes at the start of every serialized key.
+pub const KEY_MAGIC: [u8; 8] = *b"OVMSTARK";
+
+/// Current version of the key serialization format.
+pub const KEY_FORMAT_VERSION: u32 = 1;
+
+/// The kind of a serialized key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum KeyKind {
+    Proving = 0,
+    Verifying = 1,
+}
+
+impl KeyKind {
+    fn from_u8(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(Self::Proving),
+            1 => Some(Self::Verifying),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for KeyKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Proving => write!(f, "proving key"),
+            Self::Verifying => write!(f, "verifying key"),
+        }
+    }
+}
+
+/// The header of a serialized key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyHeader {
+    pub version: u32,
+    pub kind: KeyKind,
+    /// Name of the STARK config of the key, see [config_name].
+    pub config: String,
+}
+
+impl KeyHeader {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = KEY_MAGIC.to_vec();
+        out.extend_from_slice(&self.version.to_le_bytes());
+        out.push(self.kind as u8);
+        out.extend_from_slice(&(self.config.len() as u32).to_le_bytes());
+        out.extend_from_slice(self.config.as_bytes());
+        out
+    }
+
+    /// Reads the header at the start of `bytes`, and returns it with the rest of the bytes.
+    ///
+    /// The version is checked before the rest of the header is read, so that a later version may
+    /// change it.
+    pub fn read(bytes: &[u8]) -> Result<(Self, &[u8]), KeySerdeError> {
+        let (magic, rest) = split(bytes, KEY_MAGIC.len())?;
+        if magic != KEY_MAGIC {
+            // The magic read as a length is larger than any number of AIRs.
+            let len = u64::from_le_bytes(magic.try_into().unwrap());
+            return Err(if len <= u32::MAX as u64 {
+                KeySerdeError::Unversioned
+            } else {
+                KeySerdeError::WrongMagic
+            });
+        }
+        let (version, rest) = split(rest, 4)?;
+        let version = u32::from_le_bytes(version.try_into().unwrap());
+        if version != KEY_FORMAT_VERSION {
+            return Err(KeySerdeError::UnsupportedVersion(version));
+        }
+        let (kind, rest) = split(rest, 1)?;
+        let kind = KeyKind::from_u8(kind[0]).ok_or(KeySerdeError::UnknownKind(kind[0]))?;
+        let (config_len, rest) = split(rest, 4)?;
+        let config_len = u32::from_le_bytes(config_len.try_into().unwrap()) as usize;
+        let (config, rest) = split(rest, config_len)?;
+        let config = String::from_utf8(config.to_vec())
+            .map_err(|err| KeySerdeError::Payload(err.to_string()))?;
+        Ok((
+            Self {
+                version,
+                kind,
+                config,
+            },
+            rest,
+        ))
+    }
+}
+
+fn split(bytes: &[u8], len: usize) -> Result<(&[u8], &[u8]), KeySerdeError> {
+    if bytes.len() < len {
+        return Err(KeySerdeError::UnexpectedEnd);
+    }
+    Ok(bytes.split_at(len))
+}
+
+/// A STARK config with a stable identifier, recorded in the header of its serialized keys and
+/// proofs.
+pub trait ConfigId {
+    /// Identifies the field, hash, RAP phase and PCS of the config. Two configs must not share an
+    /// id, and the id of a config must never change: keys and proofs recording another id are
+    /// rejected.
+    const CONFIG_ID: &'static str;
+}
+
+/// Name of the STARK config `SC` recorded in the header of its keys, its [ConfigId].
+pub fn config_name<SC: ConfigId>() -> &'static str {
+    SC::CONFIG_ID
+}
+
+/// An error deserializing a key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum KeySerdeError {
+    /// The bytes do not start with [KEY_MAGIC].
+    WrongMagic,
+    /// The bytes are a key serialized without the header, by plain bincode.
+    Unversioned,
+    /// The key was serialized with another format version than [KEY_FORMAT_VERSION].
+    UnsupportedVersion(u32),
+    UnknownKind(u8),
+    WrongKind {
+        expected: KeyKind,
+        found: KeyKind,
+    },
+    /// The key was generated for another STARK config, see [config_name].
+    ConfigMismatch {
+        expected: String,
+        found: String,
+    },
+    UnexpectedEnd,
+    /// The key after the header failed to decode.
+    Payload(String),
+}
+
+impl fmt::Display for KeySerdeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WrongMagic => write!(f, "not a serialized key: wrong magic bytes"),
+            Self::Unversioned => write!(
+                f,
+                "unversioned key serialized without a header; regenerate the key"
+            ),
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported key format version {version}, expected {KEY_FORMAT_VERSION}"
+            ),
+            Self::UnknownKind(kind) => write!(f, "unknown key kind {kind}"),
+            Self::WrongKind { expected, found } => {
+                write!(f, "expected a {expected}, found a {found}")
+            }
+            Self::ConfigMismatch { expected, found } => write!(
+                f,
+                "key was generated for config {found}, expected config {expected}"
+            ),
+            Self::UnexpectedEnd => write!(f, "unexpected end of input"),
+            Self::Payload(err) => write!(f, "failed to decode key: {err}"),
+        }
+    }
+}
+
+impl Error for KeySerdeError {}
+
+/// Versioned serialization of a key, see the [module documentation](self).
+pub trait KeySerde: Sized {
+    const KIND: KeyKind;
+
+    /// Name of the STARK config of the key, see [config_name].
+    fn config_name() -> &'static str;
+
+    fn to_bytes(&self) -> Vec<u8>;
+
+    /// Decodes a key serialized by [to_bytes](Self::to_bytes), after checking that its header
+    /// has the current format version, the kind of `Self` and its config.
+    fn from_bytes(bytes: &[u8]) -> Result<Self, KeySerdeError>;
+}
+
+fn to_bytes<T: Serialize>(kind: KeyKind, config: &str, key: &T) -> Vec<u8> {
+    let mut out = KeyHeader {
+        version: KEY_FORMAT_VERSION,
+        kind,
+        config: config.to_string(),
+    }
+    .to_bytes();
+    bincode::serialize_into(&mut out, key).expect("failed to serialize key");
+    out
+}
+
+fn from_bytes<T: DeserializeOwned>(
+    kind: KeyKind,
+    config: &str,
+    bytes: &[u8],
+) -> Result<T, KeySerdeError> {
+    let (header, payload) = KeyHeader::read(bytes)?;
+    if header.kind != kind {
+        return Err(KeySerdeError::WrongKind {
+            expected: kind,
+            found: header.kind,
+        });
+    }
+    if header.config != config {
+        return Err(KeySerdeError::ConfigMismatch {
+            expected: config.to_string(),
+            found: header.config,
+        });
+    }
+    bincode::deserialize(payload).map_err(|err| KeySerdeError::Payload(err.to_string()))
+}
+
+impl<SC: StarkGenericConfig + ConfigId> KeySerde for MultiStarkProvingKey<SC>
+where
+    Val<SC>: PrimeField64,
+    PcsProverData<SC>: Serialize + DeserializeOwned,
+{
+    const KIND: KeyKind = KeyKind::Proving;
+
+    fn config_name() -> &'static str {
+        config_name::<SC>()
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        to_bytes(Self::KIND, Self::config_name(), self)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, KeySerdeError> {
+        from_bytes(Self::KIND, Self::config_name(), bytes)
+    }
+}
+
+impl<SC: StarkGenericConfig + ConfigId> KeySerde for MultiStarkVerifyingKey<SC>
+where
+    Val<SC>: PrimeField64,
+    Com<SC>: Serialize + DeserializeOwned,
+{
+    const KIND: KeyKind = KeyKind::Verifying;
+
+    fn config_name() -> &'static str {
+        config_name::<SC>()
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        to_bytes(Self::KIND, Self::config_name(), self)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, KeySerdeError> {
+        from_bytes(Self::KIND, Self::config_name(), bytes)
+    }
+}
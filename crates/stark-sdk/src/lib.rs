@@ -4,11 +4,36 @@ pub use p3_blake3;
 pub use p3_bn254_fr;
 pub use p3_goldilocks;
 pub use p3_keccak;
+pub use p3_koala_bear;
 
+/// Verification of serialized proofs of any registered STARK config
+pub mod any_verifier;
+#[cfg(feature = "async")]
+pub mod async_prover;
 pub mod bench;
 pub mod config;
 /// Verifier cost estimation
 pub mod cost_estimate;
+pub mod debug;
 pub mod dummy_airs;
 pub mod engine;
+pub mod key_serde;
+/// Self-contained proof bundles, with the digest of their verifying key
+pub mod proof_bundle;
+/// Content-addressed cache of proofs
+pub mod proof_cache;
+/// Compressed serialization of proofs, deduplicating their Merkle paths
+pub mod proof_compression;
+/// JSON encoding of proofs, for debugging
+pub mod proof_json;
+/// Versioned serialization of proofs
+pub mod proof_serde;
+/// Streaming serialization of proofs, for files and sockets
+pub mod proof_stream;
+pub mod security;
+pub mod sharded_key;
+/// Byte accounting of serialized proofs
+pub mod size_report;
 pub mod utils;
+/// Verification against a verifying key pinned by its digest
+pub mod verifier_handle;
@@ -0,0 +1,289 @@
+//! Self-contained proof bundles: a serialized proof with the digest of the verifying key it was
+//! generated against, the FRI parameters of the engine and the public values of the proof, so
+//! that a proof is handed over as one file.
+//!
+//! Layout:
+//!
+//! - the 8 bytes [PROOF_BUNDLE_MAGIC]
+//! - the bundle format version, as a little-endian `u32`
+//! - the FRI parameters: the log blowup, the log final polynomial length, the number of queries
+//!   and the proof of work bits, as varints
+//! - the digest of the verifying key with the FRI parameters, see
+//!   [vk_digest](StarkFriEngine::vk_digest), as field elements
+//! - the varint number of AIRs of the proof, followed by the varint id of each AIR and its public
+//!   values, as a varint number of values followed by the values
+//! - the varint length of the proof, followed by its serialization by [ProofSerde::to_bytes]
+//! - the BLAKE3 checksum of all the bytes before it, on [PROOF_BUNDLE_CHECKSUM_LEN] bytes
+//!
+//! Varints and field elements are encoded as in [proof_serde](crate::proof_serde), so that the
+//! header can be read without decoding the proof, e.g. to route a proof by its public values.
+//! [ProofBundle::open] checks the checksum before reading the header, and the FRI parameters and
+//! the digest before decoding the proof.
+
+use std::{error::Error, fmt};
+
+use derivative::Derivative;
+use openvm_stark_backend::{
+    config::{Com, PcsProof, RapPhaseSeqPartialProof, StarkGenericConfig, Val},
+    keygen::{digest::VK_DIGEST_WIDTH, types::MultiStarkVerifyingKey},
+    p3_field::{FieldAlgebra, PrimeField64},
+    proof::Proof,
+    verifier::DEFAULT_MAX_PROOF_BYTES,
+};
+use p3_blake3::Blake3;
+use p3_symmetric::CryptographicHasher;
+
+use crate::{
+    config::FriParameters,
+    engine::StarkFriEngine,
+    key_serde::ConfigId,
+    proof_serde::{
+        split, write_field, write_fields, write_varint, CanonicalCommitment, CanonicalProofPart,
+        ProofSerde, ProofSerdeError, Reader,
+    },
+    verifier_handle::VkDigest,
+};
+
+/// Magic bytes at the start of every proof bundle.
+pub const PROOF_BUNDLE_MAGIC: [u8; 8] = *b"OVMBUNDL";
+
+/// Version of the bundle format written by [ProofBundle::seal].
+pub const PROOF_BUNDLE_FORMAT_VERSION: u32 = 1;
+
+/// Length of the checksum at the end of a proof bundle.
+pub const PROOF_BUNDLE_CHECKSUM_LEN: usize = 32;
+
+/// An error opening a proof bundle.
+#[derive(Debug, PartialEq, Eq)]
+pub enum BundleError<F> {
+    /// The bytes do not start with [PROOF_BUNDLE_MAGIC].
+    WrongMagic,
+    /// The bundle was written with a format version this decoder does not know.
+    UnsupportedVersion(u32),
+    /// The bundle does not match its checksum, e.g. because it was corrupted.
+    ChecksumMismatch,
+    /// The header of the bundle failed to decode.
+    Header(ProofSerdeError),
+    /// The bundle was sealed with other FRI parameters than those of the verifier.
+    FriParamsMismatch {
+        expected: FriParameters,
+        found: FriParameters,
+    },
+    /// The bundle was sealed for another verifying key.
+    DigestMismatch {
+        expected: [F; VK_DIGEST_WIDTH],
+        found: [F; VK_DIGEST_WIDTH],
+    },
+    /// The proof of the bundle failed to decode, see [ProofSerde::from_bytes].
+    Proof(ProofSerdeError),
+    /// The public values of the header are not those of the proof.
+    PublicValuesMismatch,
+}
+
+impl<F: fmt::Debug> fmt::Display for BundleError<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WrongMagic => write!(f, "not a proof bundle: wrong magic bytes"),
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported proof bundle format version {version}, supported version is \
+                 {PROOF_BUNDLE_FORMAT_VERSION}"
+            ),
+            Self::ChecksumMismatch => write!(f, "proof bundle does not match its checksum"),
+            Self::Header(err) => write!(f, "failed to decode proof bundle header: {err}"),
+            Self::FriParamsMismatch { expected, found } => write!(
+                f,
+                "proof bundle was sealed with FRI parameters {found:?}, expected {expected:?}"
+            ),
+            Self::DigestMismatch { expected, found } => write!(
+                f,
+                "proof bundle is for verifying key digest {found:?}, expected {expected:?}"
+            ),
+            Self::Proof(err) => write!(f, "{err}"),
+            Self::PublicValuesMismatch => {
+                write!(
+                    f,
+                    "public values of the proof bundle header differ from the proof"
+                )
+            }
+        }
+    }
+}
+
+impl<F: fmt::Debug> Error for BundleError<F> {}
+
+/// A decoded proof bundle, see the [module documentation](self). The proof is kept serialized,
+/// and decoded by [open](Self::open).
+#[derive(Derivative)]
+#[derivative(
+    Clone(bound = ""),
+    Debug(bound = ""),
+    PartialEq(bound = ""),
+    Eq(bound = "")
+)]
+pub struct ProofBundle<SC: StarkGenericConfig> {
+    pub fri_params: FriParameters,
+    /// The digest of the verifying key with [fri_params](Self::fri_params).
+    pub vk_digest: VkDigest<SC>,
+    /// The id and public values of each AIR of the proof.
+    pub public_values: Vec<(usize, Vec<Val<SC>>)>,
+    /// The proof, serialized by [ProofSerde::to_bytes].
+    pub proof: Vec<u8>,
+}
+
+impl<SC: StarkGenericConfig + ConfigId> ProofBundle<SC>
+where
+    Val<SC>: PrimeField64,
+    Com<SC>: CanonicalCommitment,
+    PcsProof<SC>: CanonicalProofPart<Val<SC>>,
+    RapPhaseSeqPartialProof<SC>: CanonicalProofPart<Val<SC>>,
+{
+    /// Serializes `proof` into a bundle, with the digest of `vk` with `fri_params` computed by an
+    /// engine `E`.
+    pub fn seal<E: StarkFriEngine<SC>>(
+        proof: &Proof<SC>,
+        vk: &MultiStarkVerifyingKey<SC>,
+        fri_params: FriParameters,
+    ) -> Vec<u8> {
+        Self {
+            fri_params,
+            vk_digest: E::new(fri_params).vk_digest(vk),
+            public_values: proof
+                .core
+                .per_air
+                .iter()
+                .map(|air_proof| (air_proof.air_id, air_proof.public_values.clone()))
+                .collect(),
+            proof: proof.to_bytes(),
+        }
+        .to_bytes()
+    }
+
+    /// Decodes the proof of the bundle `bytes`, after checking the checksum of the bundle and
+    /// that it was sealed for `vk` with `fri_params`, the FRI parameters of the verifier, with the
+    /// digest of `vk` computed by an engine `E` with `fri_params`. The FRI parameters recorded in
+    /// the bundle are never trusted, so that a proof with fewer queries is rejected. The proof is
+    /// not verified.
+    pub fn open<E: StarkFriEngine<SC>>(
+        bytes: &[u8],
+        vk: &MultiStarkVerifyingKey<SC>,
+        fri_params: FriParameters,
+    ) -> Result<Proof<SC>, BundleError<Val<SC>>> {
+        let bundle = Self::from_bytes(bytes)?;
+        if bundle.fri_params != fri_params {
+            return Err(BundleError::FriParamsMismatch {
+                expected: fri_params,
+                found: bundle.fri_params,
+            });
+        }
+        let expected = E::new(fri_params).vk_digest(vk);
+        if bundle.vk_digest != expected {
+            return Err(BundleError::DigestMismatch {
+                expected,
+                found: bundle.vk_digest,
+            });
+        }
+        let proof = Proof::<SC>::from_bytes_with_limit(&bundle.proof, DEFAULT_MAX_PROOF_BYTES)
+            .map_err(BundleError::Proof)?;
+        if !proof
+            .core
+            .per_air
+            .iter()
+            .map(|air_proof| (air_proof.air_id, &air_proof.public_values))
+            .eq(bundle
+                .public_values
+                .iter()
+                .map(|(air_id, values)| (*air_id, values)))
+        {
+            return Err(BundleError::PublicValuesMismatch);
+        }
+        Ok(proof)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = PROOF_BUNDLE_MAGIC.to_vec();
+        out.extend_from_slice(&PROOF_BUNDLE_FORMAT_VERSION.to_le_bytes());
+        let FriParameters {
+            log_blowup,
+            log_final_poly_len,
+            num_queries,
+            proof_of_work_bits,
+        } = self.fri_params;
+        for param in [
+            log_blowup,
+            log_final_poly_len,
+            num_queries,
+            proof_of_work_bits,
+        ] {
+            write_varint(&mut out, param);
+        }
+        for &value in &self.vk_digest {
+            write_field(&mut out, value);
+        }
+        write_varint(&mut out, self.public_values.len());
+        for (air_id, values) in &self.public_values {
+            write_varint(&mut out, *air_id);
+            write_fields(&mut out, values);
+        }
+        write_varint(&mut out, self.proof.len());
+        out.extend_from_slice(&self.proof);
+        let checksum = Blake3.hash_iter_slices([out.as_slice()]);
+        out.extend_from_slice(&checksum);
+        out
+    }
+
+    /// Decodes the bundle `bytes` without decoding its proof, after checking its checksum.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, BundleError<Val<SC>>> {
+        let header = PROOF_BUNDLE_MAGIC.len() + 4;
+        let (magic, rest) = split(bytes, PROOF_BUNDLE_MAGIC.len()).map_err(BundleError::Header)?;
+        if magic != PROOF_BUNDLE_MAGIC {
+            return Err(BundleError::WrongMagic);
+        }
+        let version = split(rest, 4).map_err(BundleError::Header)?.0;
+        let version = u32::from_le_bytes(version.try_into().unwrap());
+        if version != PROOF_BUNDLE_FORMAT_VERSION {
+            return Err(BundleError::UnsupportedVersion(version));
+        }
+        let body_len = bytes
+            .len()
+            .checked_sub(PROOF_BUNDLE_CHECKSUM_LEN)
+            .filter(|&len| len >= header)
+            .ok_or(BundleError::Header(ProofSerdeError::UnexpectedEnd))?;
+        let (body, checksum) = bytes.split_at(body_len);
+        if Blake3.hash_iter_slices([body]) != checksum {
+            return Err(BundleError::ChecksumMismatch);
+        }
+
+        let mut reader = Reader::new("bundle header", &body[header..]);
+        let bundle = Self::read_header(&mut reader).map_err(BundleError::Header)?;
+        if !reader.bytes.is_empty() {
+            return Err(BundleError::Header(ProofSerdeError::TrailingBytes(
+                reader.bytes.len(),
+            )));
+        }
+        Ok(bundle)
+    }
+
+    fn read_header(reader: &mut Reader<'_>) -> Result<Self, ProofSerdeError> {
+        let fri_params = FriParameters {
+            log_blowup: reader.read_varint()?,
+            log_final_poly_len: reader.read_varint()?,
+            num_queries: reader.read_varint()?,
+            proof_of_work_bits: reader.read_varint()?,
+        };
+        let mut vk_digest = [Val::<SC>::ZERO; VK_DIGEST_WIDTH];
+        for value in &mut vk_digest {
+            *value = reader.read_field()?;
+        }
+        let public_values =
+            reader.read_list(|reader| Ok((reader.read_varint()?, reader.read_fields()?)))?;
+        let len = reader.read_varint()?;
+        let proof = reader.read_bytes(len)?.to_vec();
+        Ok(Self {
+            fri_params,
+            vk_digest,
+            public_values,
+            proof,
+        })
+    }
+}
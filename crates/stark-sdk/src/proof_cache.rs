@@ -0,0 +1,276 @@
+//! Content-addressed cache of proofs, for proving the same inputs repeatedly, e.g. when fuzzing
+//! or in CI.
+//!
+//! The key of a proof is the BLAKE3 hash of the name of the STARK config, the FRI parameters of
+//! the engine, the digest of the verifying key with these parameters, see
+//! [vk_digest](StarkFriEngine::vk_digest), and the proof input: for each AIR in increasing id
+//! order, the dimensions and the hash of each trace matrix and the public values, then the absent
+//! AIRs and the transcript prefix. A proof for another key or other FRI parameters is therefore
+//! never returned.
+//!
+//! An entry of the cache is a file named after the key in hex, holding the BLAKE3 hash of the
+//! bincode encoding of the proof followed by the encoding. An entry that does not match its hash,
+//! does not decode or whose proof does not verify is corrupt: the proof is proven again and the
+//! entry overwritten.
+
+use std::{
+    collections::HashSet,
+    fs, io,
+    marker::PhantomData,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+use openvm_stark_backend::{
+    config::{StarkGenericConfig, Val},
+    keygen::types::{MultiStarkProvingKey, MultiStarkVerifyingKey},
+    p3_field::PrimeField64,
+    p3_matrix::{dense::RowMajorMatrix, Matrix},
+    proof::Proof,
+    prover::types::ProofInput,
+};
+use p3_blake3::Blake3;
+use p3_symmetric::CryptographicHasher;
+
+use crate::{
+    config::FriParameters,
+    engine::StarkFriEngine,
+    key_serde::{config_name, ConfigId},
+};
+
+/// Length of the cache keys and of the hashes of the entries.
+pub const CACHE_HASH_LEN: usize = 32;
+
+/// How the proofs of a [CachingProver] were obtained.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Proofs returned from the cache.
+    pub hits: usize,
+    /// Proofs proven because their entry was missing or corrupt.
+    pub misses: usize,
+    /// Entries found corrupt, which are also counted in `misses`.
+    pub corrupt: usize,
+}
+
+/// Prover returning the proof of an input from a cache directory when the same input was
+/// proven before with the same key and FRI parameters, see the [module](self) documentation.
+///
+/// The cached proofs are the same as the proofs of [StarkEngine::prove](crate::engine::StarkEngine::prove).
+pub struct CachingProver<'a, SC: StarkGenericConfig, E> {
+    engine: &'a E,
+    dir: PathBuf,
+    /// Keys of the entries whose proof was verified by this prover.
+    verified: Mutex<HashSet<[u8; CACHE_HASH_LEN]>>,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+    corrupt: AtomicUsize,
+    phantom: PhantomData<SC>,
+}
+
+impl<'a, SC, E> CachingProver<'a, SC, E>
+where
+    SC: StarkGenericConfig + ConfigId,
+    E: StarkFriEngine<SC>,
+    Val<SC>: PrimeField64,
+{
+    /// Creates a prover caching its proofs in `dir`, which is created if it does not exist.
+    pub fn new(engine: &'a E, dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            engine,
+            dir,
+            verified: Mutex::new(HashSet::new()),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+            corrupt: AtomicUsize::new(0),
+            phantom: PhantomData,
+        })
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            corrupt: self.corrupt.load(Ordering::Relaxed),
+        }
+    }
+
+    /// The path of the entry of the proof of `proof_input` with `mpk`.
+    pub fn entry_path(
+        &self,
+        mpk: &MultiStarkProvingKey<SC>,
+        proof_input: &ProofInput<SC>,
+    ) -> PathBuf {
+        self.path(&self.cache_key(mpk, proof_input))
+    }
+
+    /// The key of the proof of `proof_input` with `mpk`.
+    pub fn cache_key(
+        &self,
+        mpk: &MultiStarkProvingKey<SC>,
+        proof_input: &ProofInput<SC>,
+    ) -> [u8; CACHE_HASH_LEN] {
+        let mut out = vec![];
+        write_bytes(&mut out, config_name::<SC>().as_bytes());
+        let FriParameters {
+            log_blowup,
+            log_final_poly_len,
+            num_queries,
+            proof_of_work_bits,
+        } = self.engine.fri_params();
+        for param in [
+            log_blowup,
+            log_final_poly_len,
+            num_queries,
+            proof_of_work_bits,
+        ] {
+            write_usize(&mut out, param);
+        }
+        write_field_elements(&mut out, &self.engine.vk_digest(&mpk.get_vk()));
+
+        let mut per_air = proof_input.per_air.iter().collect::<Vec<_>>();
+        per_air.sort_by_key(|(air_id, _)| *air_id);
+        write_usize(&mut out, per_air.len());
+        for (air_id, input) in per_air {
+            let raw = &input.raw;
+            write_usize(&mut out, *air_id);
+            write_usize(&mut out, raw.cached_mains.len());
+            for trace in &raw.cached_mains {
+                write_matrix(&mut out, trace);
+            }
+            out.push(raw.common_main.is_some() as u8);
+            if let Some(trace) = &raw.common_main {
+                write_matrix(&mut out, trace);
+            }
+            write_usize(&mut out, raw.custom_mains.len());
+            for trace in &raw.custom_mains {
+                write_matrix(&mut out, trace);
+            }
+            write_field_elements(&mut out, &raw.public_values);
+        }
+
+        let mut absent_air_ids = proof_input.absent_air_ids.clone();
+        absent_air_ids.sort_unstable();
+        absent_air_ids.dedup();
+        write_usize(&mut out, absent_air_ids.len());
+        for air_id in absent_air_ids {
+            write_usize(&mut out, air_id);
+        }
+        write_usize(&mut out, proof_input.transcript_prefix.len());
+        for commit in &proof_input.transcript_prefix {
+            write_bytes(&mut out, &bincode::serialize(commit).unwrap());
+        }
+        Blake3.hash_iter(out)
+    }
+
+    /// Returns the cached proof of `proof_input` with `mpk`, or proves it and caches the proof.
+    /// A cached proof is verified the first time this prover reads it.
+    ///
+    /// Failing to write an entry does not fail the proof, and is only logged.
+    ///
+    /// # Panics
+    /// Under the same conditions as [StarkEngine::prove](crate::engine::StarkEngine::prove).
+    pub fn prove(&self, mpk: &MultiStarkProvingKey<SC>, proof_input: ProofInput<SC>) -> Proof<SC> {
+        let key = self.cache_key(mpk, &proof_input);
+        let path = self.path(&key);
+        match fs::read(&path) {
+            Ok(bytes) => {
+                if let Some(proof) = self.read_entry(&key, &mpk.get_vk(), &bytes) {
+                    self.hits.fetch_add(1, Ordering::Relaxed);
+                    return proof;
+                }
+                tracing::warn!("corrupt proof cache entry {}", path.display());
+                self.corrupt.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => {
+                tracing::warn!("failed to read proof cache entry {}: {err}", path.display())
+            }
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let proof = self.engine.prove(mpk, proof_input);
+        if let Err(err) = write_entry(&path, &proof) {
+            tracing::warn!(
+                "failed to write proof cache entry {}: {err}",
+                path.display()
+            );
+        }
+        proof
+    }
+
+    /// The proof of an entry, if it matches its hash, decodes, and verifies unless it was
+    /// verified before.
+    fn read_entry(
+        &self,
+        key: &[u8; CACHE_HASH_LEN],
+        vk: &MultiStarkVerifyingKey<SC>,
+        bytes: &[u8],
+    ) -> Option<Proof<SC>> {
+        if bytes.len() < CACHE_HASH_LEN {
+            return None;
+        }
+        let (hash, encoding) = bytes.split_at(CACHE_HASH_LEN);
+        if hash != Blake3.hash_iter_slices([encoding]) {
+            return None;
+        }
+        let proof: Proof<SC> = bincode::deserialize(encoding).ok()?;
+        let mut verified = self.verified.lock().unwrap();
+        if !verified.contains(key) {
+            self.engine.verify(vk, &proof).ok()?;
+            verified.insert(*key);
+        }
+        Some(proof)
+    }
+
+    fn path(&self, key: &[u8; CACHE_HASH_LEN]) -> PathBuf {
+        let name = key
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>();
+        self.dir.join(format!("{name}.proof"))
+    }
+}
+
+/// Writes the entry of `proof` at `path`, through a temporary file so that a concurrent reader
+/// never sees a partial entry.
+fn write_entry<SC: StarkGenericConfig>(path: &Path, proof: &Proof<SC>) -> io::Result<()> {
+    let encoding = bincode::serialize(proof).map_err(io::Error::other)?;
+    let mut bytes = Blake3.hash_iter_slices([encoding.as_slice()]).to_vec();
+    bytes.extend_from_slice(&encoding);
+    let tmp_path = path.with_extension(format!("tmp{}", std::process::id()));
+    fs::write(&tmp_path, bytes)?;
+    fs::rename(&tmp_path, path)
+}
+
+fn write_usize(out: &mut Vec<u8>, value: usize) {
+    out.extend_from_slice(&(value as u64).to_le_bytes());
+}
+
+fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    write_usize(out, bytes.len());
+    out.extend_from_slice(bytes);
+}
+
+fn write_field_elements<F: PrimeField64>(out: &mut Vec<u8>, values: &[F]) {
+    write_usize(out, values.len());
+    for value in values {
+        out.extend_from_slice(&value.as_canonical_u64().to_le_bytes());
+    }
+}
+
+/// Writes the dimensions of `matrix` and the hash of its values.
+fn write_matrix<F: PrimeField64>(out: &mut Vec<u8>, matrix: &RowMajorMatrix<F>) {
+    write_usize(out, matrix.width());
+    write_usize(out, matrix.height());
+    let hash: [u8; CACHE_HASH_LEN] = Blake3.hash_iter(
+        matrix
+            .values
+            .iter()
+            .flat_map(|value| value.as_canonical_u64().to_le_bytes()),
+    );
+    out.extend_from_slice(&hash);
+}
@@ -0,0 +1,169 @@
+//! Compressed serialization of proofs, deduplicating the nodes of the Merkle paths of their FRI
+//! proof.
+//!
+//! The queries of FRI open the same Merkle trees at many indices, and the paths of indices
+//! sharing their high-order bits share their nodes near the root. A compressed proof stores each
+//! distinct node of a tree once, and each path as references to these nodes. Layout:
+//!
+//! - the 8 bytes [COMPRESSED_PROOF_MAGIC]
+//! - the format version, as a little-endian `u32`
+//! - the varint length of the proof without its Merkle paths, followed by its serialization by
+//!   [ProofSerde::to_bytes]
+//! - for each tree, first the trees of the opened commitments, then the trees of the commit
+//!   phase of FRI: the varint number of distinct nodes of its paths, followed by the nodes, then
+//!   for each query opening the tree, the varint length of its path, followed by the varint
+//!   index of each node of the path
+//!
+//! Varints are unsigned LEB128, and a node is its field elements as in [ProofSerde::to_bytes].
+//!
+//! Decoding reconstructs the full paths, so the decoded proof is the compressed proof, and
+//! verifies exactly when it does. Bytes after the paths are rejected.
+
+use std::{collections::HashMap, mem};
+
+use itertools::Itertools;
+use openvm_stark_backend::{p3_field::FieldAlgebra, proof::Proof};
+use p3_baby_bear::BabyBear;
+
+use crate::{
+    config::baby_bear_poseidon2::{BabyBearPoseidon2Config, DIGEST_WIDTH},
+    proof_serde::{split, write_field, write_varint, ProofSerde, ProofSerdeError, Reader},
+};
+
+/// Magic bytes at the start of every compressed proof.
+pub const COMPRESSED_PROOF_MAGIC: [u8; 8] = *b"OVMPRFCZ";
+
+/// Version of the compressed proof format written by
+/// [CompressedProofSerde::to_compressed_bytes].
+pub const COMPRESSED_PROOF_FORMAT_VERSION: u32 = 1;
+
+type Digest = [BabyBear; DIGEST_WIDTH];
+
+/// Compressed serialization of a proof, see the [module documentation](self).
+pub trait CompressedProofSerde: Sized {
+    fn to_compressed_bytes(&self) -> Vec<u8>;
+
+    /// Decodes a proof compressed by [to_compressed_bytes](Self::to_compressed_bytes). The bytes
+    /// must end with the proof.
+    fn from_compressed_bytes(bytes: &[u8]) -> Result<Self, ProofSerdeError>;
+}
+
+impl CompressedProofSerde for Proof<BabyBearPoseidon2Config> {
+    fn to_compressed_bytes(&self) -> Vec<u8> {
+        let mut stripped = self.clone();
+        let trees = tree_paths(&mut stripped)
+            .into_iter()
+            .map(|paths| paths.into_iter().map(mem::take).collect_vec())
+            .collect_vec();
+
+        let mut out = COMPRESSED_PROOF_MAGIC.to_vec();
+        out.extend_from_slice(&COMPRESSED_PROOF_FORMAT_VERSION.to_le_bytes());
+        let proof_bytes = stripped.to_bytes();
+        write_varint(&mut out, proof_bytes.len());
+        out.extend_from_slice(&proof_bytes);
+        for paths in trees {
+            let mut nodes = vec![];
+            let mut node_indices = HashMap::new();
+            let paths = paths
+                .iter()
+                .map(|path| {
+                    path.iter()
+                        .map(|node| {
+                            *node_indices.entry(node).or_insert_with(|| {
+                                nodes.push(node);
+                                nodes.len() - 1
+                            })
+                        })
+                        .collect_vec()
+                })
+                .collect_vec();
+            write_varint(&mut out, nodes.len());
+            for node in nodes {
+                for &value in node {
+                    write_field(&mut out, value);
+                }
+            }
+            for path in paths {
+                write_varint(&mut out, path.len());
+                for index in path {
+                    write_varint(&mut out, index);
+                }
+            }
+        }
+        out
+    }
+
+    fn from_compressed_bytes(bytes: &[u8]) -> Result<Self, ProofSerdeError> {
+        let (magic, rest) = split(bytes, COMPRESSED_PROOF_MAGIC.len())?;
+        if magic != COMPRESSED_PROOF_MAGIC {
+            return Err(ProofSerdeError::WrongMagic);
+        }
+        let (version, rest) = split(rest, 4)?;
+        let version = u32::from_le_bytes(version.try_into().unwrap());
+        if version != COMPRESSED_PROOF_FORMAT_VERSION {
+            return Err(ProofSerdeError::UnsupportedVersion {
+                version,
+                supported: vec![COMPRESSED_PROOF_FORMAT_VERSION],
+            });
+        }
+        let mut reader = Reader::new("compressed proof", rest);
+        let len = reader.read_varint()?;
+        let mut proof = Self::from_bytes(reader.read_bytes(len)?)?;
+        for paths in tree_paths(&mut proof) {
+            let nodes = reader.read_list(|reader| {
+                let mut node = [BabyBear::ZERO; DIGEST_WIDTH];
+                for value in &mut node {
+                    *value = reader.read_field()?;
+                }
+                Ok(node)
+            })?;
+            for path in paths {
+                if !path.is_empty() {
+                    return Err(ProofSerdeError::Payload(
+                        "Merkle path in the proof without its paths".to_string(),
+                    ));
+                }
+                *path = reader.read_list(|reader| {
+                    let index = reader.read_varint()?;
+                    nodes
+                        .get(index)
+                        .copied()
+                        .ok_or(ProofSerdeError::InvalidMerkleNode(index))
+                })?;
+            }
+        }
+        if !reader.bytes.is_empty() {
+            return Err(ProofSerdeError::TrailingBytes(reader.bytes.len()));
+        }
+        Ok(proof)
+    }
+}
+
+/// The Merkle paths of the FRI proof of `proof`, grouped by tree: the trees of the opened
+/// commitments, then the trees of the commit phase. The paths of a tree are in the order of the
+/// queries.
+fn tree_paths(proof: &mut Proof<BabyBearPoseidon2Config>) -> Vec<Vec<&mut Vec<Digest>>> {
+    let query_proofs = &mut proof.pcs_proof.query_proofs;
+    let num_input_trees = query_proofs
+        .iter()
+        .map(|query_proof| query_proof.input_proof.len())
+        .max()
+        .unwrap_or(0);
+    let num_fri_trees = query_proofs
+        .iter()
+        .map(|query_proof| query_proof.commit_phase_openings.len())
+        .max()
+        .unwrap_or(0);
+    let mut trees = (0..num_input_trees + num_fri_trees)
+        .map(|_| vec![])
+        .collect_vec();
+    for query_proof in query_proofs.iter_mut() {
+        for (tree, batch_opening) in query_proof.input_proof.iter_mut().enumerate() {
+            trees[tree].push(&mut batch_opening.opening_proof);
+        }
+        for (round, step) in query_proof.commit_phase_openings.iter_mut().enumerate() {
+            trees[num_input_trees + round].push(&mut step.opening_proof);
+        }
+    }
+    trees
+}
@@ -0,0 +1,531 @@
+//! JSON encoding of proofs, for debugging and for comparing proofs with other implementations.
+//!
+//! This is not a wire format, see [proof_serde](crate::proof_serde), but the encoding is
+//! lossless: [ProofJson::from_json] decodes the JSON of a proof into the same proof. The schema
+//! only changes along with [PROOF_JSON_SCHEMA_VERSION]. It follows the conventions of the JSON
+//! encoding of verifying keys, see [json](openvm_stark_backend::keygen::json):
+//!
+//! - field elements are strings with their canonical value in decimal, e.g. `"2013265920"`, and
+//!   extension field elements are arrays of their coefficients over the base field
+//! - commitments and the nodes of Merkle paths are digests, arrays of their [DIGEST_WIDTH] words,
+//!   each a field element
+//! - optional values are `null` when absent
+//!
+//! The top-level object has the fields:
+//!
+//! - `schema_version`: [PROOF_JSON_SCHEMA_VERSION]
+//! - `commitments`: `{ "main_trace", "after_challenge", "quotient", "custom" }`, where
+//!   `quotient` is a single commitment and the others are arrays of commitments
+//! - `airs`: `[{ "air_id", "height", "log_height", "public_values", "exposed_values" }]`, in the
+//!   order of the proof, where `height` is the trace height, `log_height` its log2 rounded up,
+//!   ignored when decoding, and `exposed_values` has the values exposed in each challenge phase
+//! - `absent_air_ids`
+//! - `opened_values`: `{ "preprocessed", "main", "after_challenge", "custom", "quotient" }`.
+//!   `preprocessed` has one matrix per preprocessed commitment, and `main`, `after_challenge`
+//!   and `custom` have the matrices of each commitment. A matrix is
+//!   `{ "local", "next", "rotated" }`, its row at the out-of-domain point, at the next point and
+//!   at the point of each extra rotation. `quotient` has the values of each quotient chunk of
+//!   each AIR.
+//! - `challenge_phase_proof`: `{ "public_lookup_multiplicities" }`, the multiplicity of each row
+//!   of each public lookup table, or `null`
+//! - `fri`: `{ "commit_phase_commits", "queries", "final_poly", "pow_witness" }`, where
+//!   `commit_phase_commits` has the commitment of each folding layer and `final_poly` the
+//!   coefficients of the final polynomial
+//!
+//! Each query of `fri` has the fields:
+//!
+//! - `input_openings`: `[{ "opened_values", "merkle_path" }]`, one per opened commitment, with
+//!   the opened row of each matrix of the commitment
+//! - `commit_phase_openings`: `[{ "sibling_value", "merkle_path" }]`, one per folding layer
+
+use std::{error::Error, fmt};
+
+use itertools::Itertools;
+use openvm_stark_backend::{
+    config::StarkGenericConfig,
+    interaction::fri_log_up::FriLogUpPartialProof,
+    p3_field::{FieldAlgebra, FieldExtensionAlgebra, PrimeField64},
+    p3_util::log2_ceil_usize,
+    proof::{AdjacentOpenedValues, AirProofData, Commitments, OpenedValues, Proof, ProofCore},
+};
+use p3_baby_bear::BabyBear;
+use p3_fri::{BatchOpening, CommitPhaseProofStep, FriProof, QueryProof};
+use serde::{Deserialize, Serialize};
+
+use crate::config::baby_bear_poseidon2::{BabyBearPoseidon2Config, DIGEST_WIDTH};
+
+/// Version of the schema written by [ProofJson::to_json_value].
+pub const PROOF_JSON_SCHEMA_VERSION: u64 = 1;
+
+type SC = BabyBearPoseidon2Config;
+type Val = BabyBear;
+type Challenge = <SC as StarkGenericConfig>::Challenge;
+type Digest = [Val; DIGEST_WIDTH];
+
+/// An error decoding the JSON of a proof.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ProofJsonError {
+    /// The JSON does not match the schema.
+    Json(String),
+    UnsupportedSchemaVersion(u64),
+    /// A field element is not the decimal string of a canonical value.
+    InvalidFieldElement(String),
+    /// An extension field element does not have one coefficient per degree of the extension.
+    InvalidExtensionElement(usize),
+    /// A commitment or a node of a Merkle path does not have [DIGEST_WIDTH] words.
+    InvalidDigest(usize),
+}
+
+impl fmt::Display for ProofJsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Json(err) => write!(f, "invalid proof JSON: {err}"),
+            Self::UnsupportedSchemaVersion(version) => {
+                write!(f, "unsupported proof JSON schema version {version}")
+            }
+            Self::InvalidFieldElement(value) => write!(f, "invalid field element {value:?}"),
+            Self::InvalidExtensionElement(len) => write!(
+                f,
+                "extension field element with {len} coefficients, expected {}",
+                <Challenge as FieldExtensionAlgebra<Val>>::D
+            ),
+            Self::InvalidDigest(len) => {
+                write!(f, "digest with {len} words, expected {DIGEST_WIDTH}")
+            }
+        }
+    }
+}
+
+impl Error for ProofJsonError {}
+
+impl From<serde_json::Error> for ProofJsonError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Json(err.to_string())
+    }
+}
+
+/// JSON encoding of a proof, see the [module documentation](self).
+pub trait ProofJson: Sized {
+    fn to_json_value(&self) -> serde_json::Value;
+
+    /// Decodes the JSON of a proof encoded by [to_json_value](Self::to_json_value).
+    fn from_json_value(value: serde_json::Value) -> Result<Self, ProofJsonError>;
+
+    fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self.to_json_value()).expect("failed to serialize proof")
+    }
+
+    fn from_json(json: &str) -> Result<Self, ProofJsonError> {
+        Self::from_json_value(serde_json::from_str(json)?)
+    }
+}
+
+impl ProofJson for Proof<SC> {
+    fn to_json_value(&self) -> serde_json::Value {
+        let commitments = &self.commitments;
+        let values = &self.core.opened_values;
+        let fri = &self.pcs_proof;
+        let json = ProofJsonSchema {
+            schema_version: PROOF_JSON_SCHEMA_VERSION,
+            commitments: CommitmentsJson {
+                main_trace: commitments.main_trace.iter().map(digest_to_json).collect(),
+                after_challenge: commitments
+                    .after_challenge
+                    .iter()
+                    .map(digest_to_json)
+                    .collect(),
+                quotient: digest_to_json(&commitments.quotient),
+                custom: commitments.custom.iter().map(digest_to_json).collect(),
+            },
+            airs: self
+                .core
+                .per_air
+                .iter()
+                .map(|air_proof| AirJson {
+                    air_id: air_proof.air_id,
+                    height: air_proof.degree,
+                    log_height: log2_ceil_usize(air_proof.degree),
+                    public_values: fields_to_json(&air_proof.public_values),
+                    exposed_values: air_proof
+                        .exposed_values_after_challenge
+                        .iter()
+                        .map(|values| exts_to_json(values))
+                        .collect(),
+                })
+                .collect(),
+            absent_air_ids: self.core.absent_air_ids.clone(),
+            opened_values: OpenedValuesJson {
+                preprocessed: values.preprocessed.iter().map(matrix_to_json).collect(),
+                main: matrices_to_json(&values.main),
+                after_challenge: matrices_to_json(&values.after_challenge),
+                custom: matrices_to_json(&values.custom),
+                quotient: values
+                    .quotient
+                    .iter()
+                    .map(|chunks| chunks.iter().map(|chunk| exts_to_json(chunk)).collect())
+                    .collect(),
+            },
+            challenge_phase_proof: self.rap_phase_seq_proof.as_ref().map(|proof| {
+                ChallengePhaseProofJson {
+                    public_lookup_multiplicities: proof
+                        .public_lookup_multiplicities
+                        .iter()
+                        .map(|multiplicities| fields_to_json(multiplicities))
+                        .collect(),
+                }
+            }),
+            fri: FriJson {
+                commit_phase_commits: fri
+                    .commit_phase_commits
+                    .iter()
+                    .map(digest_to_json)
+                    .collect(),
+                queries: fri
+                    .query_proofs
+                    .iter()
+                    .map(|query_proof| QueryJson {
+                        input_openings: query_proof
+                            .input_proof
+                            .iter()
+                            .map(|opening| InputOpeningJson {
+                                opened_values: opening
+                                    .opened_values
+                                    .iter()
+                                    .map(|row| fields_to_json(row))
+                                    .collect(),
+                                merkle_path: opening
+                                    .opening_proof
+                                    .iter()
+                                    .map(digest_to_json)
+                                    .collect(),
+                            })
+                            .collect(),
+                        commit_phase_openings: query_proof
+                            .commit_phase_openings
+                            .iter()
+                            .map(|step| CommitPhaseOpeningJson {
+                                sibling_value: ext_to_json(&step.sibling_value),
+                                merkle_path: step
+                                    .opening_proof
+                                    .iter()
+                                    .map(digest_to_json)
+                                    .collect(),
+                            })
+                            .collect(),
+                    })
+                    .collect(),
+                final_poly: exts_to_json(&fri.final_poly),
+                pow_witness: field_to_json(fri.pow_witness),
+            },
+        };
+        serde_json::to_value(json).expect("failed to serialize proof")
+    }
+
+    fn from_json_value(value: serde_json::Value) -> Result<Self, ProofJsonError> {
+        // The version is checked first, so that a document of another version is reported as
+        // such rather than as not matching the schema.
+        match value
+            .get("schema_version")
+            .and_then(|version| version.as_u64())
+        {
+            Some(PROOF_JSON_SCHEMA_VERSION) => {}
+            Some(version) => return Err(ProofJsonError::UnsupportedSchemaVersion(version)),
+            None => {
+                return Err(ProofJsonError::Json(
+                    "missing or invalid schema_version".to_string(),
+                ))
+            }
+        }
+        let json: ProofJsonSchema = serde_json::from_value(value)?;
+
+        let commitments = Commitments {
+            main_trace: digests_from_json(&json.commitments.main_trace)?,
+            after_challenge: digests_from_json(&json.commitments.after_challenge)?,
+            quotient: digest_from_json(&json.commitments.quotient)?,
+            custom: digests_from_json(&json.commitments.custom)?,
+        };
+        let per_air = json
+            .airs
+            .iter()
+            .map(|air| {
+                Ok(AirProofData {
+                    air_id: air.air_id,
+                    degree: air.height,
+                    exposed_values_after_challenge: air
+                        .exposed_values
+                        .iter()
+                        .map(|values| exts_from_json(values))
+                        .try_collect()?,
+                    public_values: fields_from_json(&air.public_values)?,
+                })
+            })
+            .collect::<Result<_, ProofJsonError>>()?;
+        let opened = &json.opened_values;
+        let values = OpenedValues {
+            preprocessed: opened
+                .preprocessed
+                .iter()
+                .map(matrix_from_json)
+                .try_collect()?,
+            main: matrices_from_json(&opened.main)?,
+            after_challenge: matrices_from_json(&opened.after_challenge)?,
+            quotient: opened
+                .quotient
+                .iter()
+                .map(|chunks| {
+                    chunks
+                        .iter()
+                        .map(|chunk| exts_from_json(chunk))
+                        .try_collect()
+                })
+                .try_collect()?,
+            custom: matrices_from_json(&opened.custom)?,
+        };
+        let rap_phase_seq_proof = json
+            .challenge_phase_proof
+            .as_ref()
+            .map(|proof| {
+                Ok::<_, ProofJsonError>(FriLogUpPartialProof {
+                    public_lookup_multiplicities: proof
+                        .public_lookup_multiplicities
+                        .iter()
+                        .map(|multiplicities| fields_from_json(multiplicities))
+                        .try_collect()?,
+                })
+            })
+            .transpose()?;
+        let fri = &json.fri;
+        let query_proofs = fri
+            .queries
+            .iter()
+            .map(|query| {
+                Ok(QueryProof {
+                    input_proof: query
+                        .input_openings
+                        .iter()
+                        .map(|opening| {
+                            Ok::<_, ProofJsonError>(BatchOpening {
+                                opened_values: opening
+                                    .opened_values
+                                    .iter()
+                                    .map(|row| fields_from_json(row))
+                                    .try_collect()?,
+                                opening_proof: digests_from_json::<Digest>(&opening.merkle_path)?,
+                            })
+                        })
+                        .try_collect()?,
+                    commit_phase_openings: query
+                        .commit_phase_openings
+                        .iter()
+                        .map(|step| {
+                            Ok::<_, ProofJsonError>(CommitPhaseProofStep {
+                                sibling_value: ext_from_json(&step.sibling_value)?,
+                                opening_proof: digests_from_json::<Digest>(&step.merkle_path)?,
+                            })
+                        })
+                        .try_collect()?,
+                })
+            })
+            .collect::<Result<_, ProofJsonError>>()?;
+        let fri_proof = FriProof {
+            commit_phase_commits: digests_from_json(&fri.commit_phase_commits)?,
+            query_proofs,
+            final_poly: exts_from_json(&fri.final_poly)?,
+            pow_witness: field_from_json(&fri.pow_witness)?,
+        };
+
+        Ok(Proof {
+            commitments,
+            core: ProofCore {
+                per_air,
+                opened_values: values,
+                absent_air_ids: json.absent_air_ids,
+            },
+            pcs_proof: fri_proof,
+            rap_phase_seq_proof,
+        })
+    }
+}
+
+fn field_to_json(value: Val) -> String {
+    value.as_canonical_u64().to_string()
+}
+
+fn fields_to_json(values: &[Val]) -> Vec<String> {
+    values.iter().map(|&value| field_to_json(value)).collect()
+}
+
+fn field_from_json(value: &str) -> Result<Val, ProofJsonError> {
+    // `u64::from_str` accepts a leading `+` and leading zeros, which are not canonical.
+    value
+        .parse::<u64>()
+        .ok()
+        .filter(|&v| v < Val::ORDER_U64 && v.to_string() == value)
+        .map(Val::from_canonical_u64)
+        .ok_or_else(|| ProofJsonError::InvalidFieldElement(value.to_string()))
+}
+
+fn fields_from_json(values: &[String]) -> Result<Vec<Val>, ProofJsonError> {
+    values.iter().map(|value| field_from_json(value)).collect()
+}
+
+fn ext_to_json(value: &Challenge) -> Vec<String> {
+    fields_to_json(<Challenge as FieldExtensionAlgebra<Val>>::as_base_slice(
+        value,
+    ))
+}
+
+fn exts_to_json(values: &[Challenge]) -> Vec<Vec<String>> {
+    values.iter().map(ext_to_json).collect()
+}
+
+fn ext_from_json(value: &[String]) -> Result<Challenge, ProofJsonError> {
+    if value.len() != <Challenge as FieldExtensionAlgebra<Val>>::D {
+        return Err(ProofJsonError::InvalidExtensionElement(value.len()));
+    }
+    Ok(<Challenge as FieldExtensionAlgebra<Val>>::from_base_slice(
+        &fields_from_json(value)?,
+    ))
+}
+
+fn exts_from_json(values: &[Vec<String>]) -> Result<Vec<Challenge>, ProofJsonError> {
+    values.iter().map(|value| ext_from_json(value)).collect()
+}
+
+fn digest_to_json<D: Copy + Into<Digest>>(digest: &D) -> Vec<String> {
+    fields_to_json(&(*digest).into())
+}
+
+fn digest_from_json<D: From<Digest>>(words: &[String]) -> Result<D, ProofJsonError> {
+    let words: Digest = fields_from_json(words)?
+        .try_into()
+        .map_err(|words: Vec<Val>| ProofJsonError::InvalidDigest(words.len()))?;
+    Ok(words.into())
+}
+
+fn digests_from_json<D: From<Digest>>(digests: &[Vec<String>]) -> Result<Vec<D>, ProofJsonError> {
+    digests
+        .iter()
+        .map(|words| digest_from_json(words))
+        .collect()
+}
+
+fn matrix_to_json(values: &AdjacentOpenedValues<Challenge>) -> MatrixJson {
+    MatrixJson {
+        local: exts_to_json(&values.local),
+        next: exts_to_json(&values.next),
+        rotated: values.rotated.iter().map(|row| exts_to_json(row)).collect(),
+    }
+}
+
+fn matrices_to_json(values: &[Vec<AdjacentOpenedValues<Challenge>>]) -> Vec<Vec<MatrixJson>> {
+    values
+        .iter()
+        .map(|matrices| matrices.iter().map(matrix_to_json).collect())
+        .collect()
+}
+
+fn matrix_from_json(json: &MatrixJson) -> Result<AdjacentOpenedValues<Challenge>, ProofJsonError> {
+    Ok(AdjacentOpenedValues {
+        local: exts_from_json(&json.local)?,
+        next: exts_from_json(&json.next)?,
+        rotated: json
+            .rotated
+            .iter()
+            .map(|row| exts_from_json(row))
+            .try_collect()?,
+    })
+}
+
+fn matrices_from_json(
+    json: &[Vec<MatrixJson>],
+) -> Result<Vec<Vec<AdjacentOpenedValues<Challenge>>>, ProofJsonError> {
+    json.iter()
+        .map(|matrices| matrices.iter().map(matrix_from_json).try_collect())
+        .try_collect()
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProofJsonSchema {
+    schema_version: u64,
+    commitments: CommitmentsJson,
+    airs: Vec<AirJson>,
+    absent_air_ids: Vec<usize>,
+    opened_values: OpenedValuesJson,
+    challenge_phase_proof: Option<ChallengePhaseProofJson>,
+    fri: FriJson,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CommitmentsJson {
+    main_trace: Vec<Vec<String>>,
+    after_challenge: Vec<Vec<String>>,
+    quotient: Vec<String>,
+    custom: Vec<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AirJson {
+    air_id: usize,
+    height: usize,
+    log_height: usize,
+    public_values: Vec<String>,
+    exposed_values: Vec<Vec<Vec<String>>>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct OpenedValuesJson {
+    preprocessed: Vec<MatrixJson>,
+    main: Vec<Vec<MatrixJson>>,
+    after_challenge: Vec<Vec<MatrixJson>>,
+    custom: Vec<Vec<MatrixJson>>,
+    quotient: Vec<Vec<Vec<Vec<String>>>>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MatrixJson {
+    local: Vec<Vec<String>>,
+    next: Vec<Vec<String>>,
+    rotated: Vec<Vec<Vec<String>>>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ChallengePhaseProofJson {
+    public_lookup_multiplicities: Vec<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FriJson {
+    commit_phase_commits: Vec<Vec<String>>,
+    queries: Vec<QueryJson>,
+    final_poly: Vec<Vec<String>>,
+    pow_witness: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct QueryJson {
+    input_openings: Vec<InputOpeningJson>,
+    commit_phase_openings: Vec<CommitPhaseOpeningJson>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct InputOpeningJson {
+    opened_values: Vec<Vec<String>>,
+    merkle_path: Vec<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CommitPhaseOpeningJson {
+    sibling_value: Vec<String>,
+    merkle_path: Vec<Vec<String>>,
+}
@@ -0,0 +1,1273 @@
+//! Versioned serialization of proofs.
+//!
+//! A serialized proof is a header followed by the encoding of the proof in the layout of its
+//! format version. Header layout:
+//!
+//! - the 8 bytes [PROOF_MAGIC]
+//! - the format version, as a little-endian `u32`
+//! - the [ConfigId] of the STARK config, as a little-endian `u32` length followed by its UTF-8
+//!   bytes
+//!
+//! A proof of any version in [SUPPORTED_PROOF_FORMAT_VERSIONS] is decoded into the current
+//! [Proof], so that archived proofs keep verifying after the format changes. When the encoding
+//! of [Proof] changes, [PROOF_FORMAT_VERSION] is bumped and the decoder of the previous version
+//! is kept, mapping the old layout into the current one.
+//!
+//! Layouts:
+//!
+//! - version 1: the bincode encoding of [Proof].
+//! - version 2: the sections of the proof below, each prefixed by its length in bytes as a
+//!   varint. The encoding does not depend on the in-memory layout of [Proof], so that reshaping
+//!   its structs does not change serialized proofs.
+//!   1. the commitments, in their bincode encoding
+//!   2. the varint number of AIRs, followed by the varint id and trace height of each AIR
+//!   3. the public values of each AIR, as a varint number of values followed by the values
+//!   4. the exposed values of each AIR, as a varint number of challenge phases followed by the
+//!      values of each phase
+//!   5. the opened values: the preprocessed, main, after challenge, quotient and custom values,
+//!      with each list prefixed by its varint length
+//!   6. the partial proof of the challenge phases, in its bincode encoding
+//!   7. the varint number of absent AIRs, followed by their varint ids
+//!   8. the opening proof of the PCS, e.g. the FRI proof, in its bincode encoding
+//! - version 3: the sections of version 2, except that the commitments are in their canonical
+//!   encoding, see [CanonicalCommitment]: the words of the digests of Bn254 commitments are their
+//!   32 big-endian bytes, as expected by Solidity verifiers, instead of their serde encoding. The
+//!   other commitments are encoded as in version 2. The digests within the PCS proof keep their
+//!   bincode encoding.
+//! - version 4: the sections of version 3, except that the partial proof of the challenge phases
+//!   and the opening proof are encoded field by field like the other sections, see
+//!   [CanonicalProofPart], instead of in their bincode encoding:
+//!   - the partial proof is a byte, 0 if the proof has none and 1 otherwise, followed by the
+//!     partial proof: the public lookup multiplicities of FRI log-up, or the sum-check proofs,
+//!     masks and output claims of GKR log-up.
+//!   - the FRI proof is the commitments of the commit phase, the query proofs, the final
+//!     polynomial and the proof of work witness. A query proof is the opened rows and Merkle path
+//!     of each input batch, followed by the sibling value and Merkle path of each fold. A Merkle
+//!     path is its list of digests, each in its canonical encoding, preceded by the salts of the
+//!     opened rows for the salted configs.
+//!
+//!   Varints are unsigned LEB128, in their shortest encoding. A field element is its canonical
+//!   value in little-endian bytes, truncated to the bytes of the field order, and an extension
+//!   field element is its coefficients over the base field. Lists are prefixed by their varint
+//!   length, except the lists of commitments of the commitments section, which keep the
+//!   little-endian `u64` length of version 3.
+//!
+//! Decoding is strict: bytes after the encoding of the proof are rejected, so that a proof cannot
+//! carry data the verifier ignores. [ProofSerde::from_bytes_lenient] tolerates them. Bytes after
+//! the encoding of a section are always rejected. Varints must be in their shortest encoding, and
+//! field elements in their canonical encoding, below the order of their field, including within
+//! the bincode encodings and the words of the commitments, so that distinct bytes never decode to
+//! the same proof, e.g. for systems identifying proofs by the hash of their bytes.
+//!
+//! Decoding never allocates in proportion to a length read from the input: a list reserves at
+//! most one entry per remaining byte, and serde caps the capacity it reserves for a list, so a
+//! list claiming more entries than the input holds fails at the end of the input. Untrusted input
+//! should also be bounded with [ProofSerde::from_bytes_with_limit] before it is decoded.
+//!
+//! The sections of versions 2 to 4 start with the claims of the proof, so [read_proof_prefix]
+//! reads its commitments, AIRs and public values from any prefix of a serialized proof holding
+//! them, e.g. for indexers that do not verify proofs.
+
+use std::{borrow::Cow, error::Error, fmt};
+
+use openvm_stark_backend::{
+    config::{Com, PcsProof, RapPhaseSeqPartialProof, StarkGenericConfig, Val},
+    gkr::{GkrBatchProof, GkrMask},
+    interaction::fri_log_up::FriLogUpPartialProof,
+    p3_commit::Mmcs,
+    p3_field::{ExtensionField, FieldExtensionAlgebra, PrimeField64},
+    poly::uni::UnivariatePolynomial,
+    proof::{AdjacentOpenedValues, AirProofData, Commitments, OpenedValues, Proof, ProofCore},
+    sumcheck::SumcheckProof,
+};
+use p3_baby_bear::BabyBear;
+use p3_bn254_fr::Bn254Fr;
+use p3_fri::{BatchOpening, CommitPhaseProofStep, FriProof, QueryProof};
+use p3_goldilocks::Goldilocks;
+use p3_koala_bear::KoalaBear;
+use p3_symmetric::Hash;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    config::baby_bear_poseidon2_root::{Bn254Bytes, NonCanonicalBn254Error},
+    key_serde::{config_name, ConfigId},
+};
+
+/// Magic bytes at the start of every serialized proof.
+pub const PROOF_MAGIC: [u8; 8] = *b"OVMPROOF";
+
+/// Version of the proof serialization format written by [ProofSerde::to_bytes].
+pub const PROOF_FORMAT_VERSION: u32 = 4;
+
+/// Versions of the proof serialization format that [ProofSerde::from_bytes] decodes.
+pub const SUPPORTED_PROOF_FORMAT_VERSIONS: [u32; 4] = [1, 2, 3, 4];
+
+/// An error deserializing a proof.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ProofSerdeError {
+    /// The bytes do not start with [PROOF_MAGIC].
+    WrongMagic,
+    /// The proof was serialized with a format version this decoder does not know.
+    UnsupportedVersion {
+        version: u32,
+        supported: Vec<u32>,
+    },
+    /// The proof was generated for another STARK config, see [config_name].
+    ConfigMismatch {
+        expected: String,
+        found: String,
+    },
+    UnexpectedEnd,
+    /// The proof after the header failed to decode.
+    Payload(String),
+    /// The given number of bytes follow the encoding of the proof.
+    TrailingBytes(usize),
+    /// The serialized proof is longer than the limit, see [ProofSerde::from_bytes_with_limit]. A
+    /// proof read from a stream is rejected as soon as it is known to exceed the limit, and `found`
+    /// is then a lower bound, see
+    /// [read_from_with_limit](crate::proof_stream::ProofStream::read_from_with_limit).
+    TooLarge {
+        max: usize,
+        found: usize,
+    },
+    /// A varint of the proof does not fit in a `usize`.
+    VarintOverflow,
+    /// A varint of the proof is not in its shortest encoding: its last byte is zero, after the
+    /// first byte.
+    NonCanonicalVarint,
+    /// A field element of the proof is not in its canonical encoding, e.g. is not below the order
+    /// of the field. `index` is the offset within the section of the element, or of its first
+    /// byte differing from the canonical encoding in a section in its bincode encoding. The Bn254
+    /// words of commitments have their own error,
+    /// [NonCanonicalBn254Element](Self::NonCanonicalBn254Element).
+    NonCanonicalFieldElement {
+        section: &'static str,
+        index: usize,
+    },
+    /// The given number of bytes follow the encoding of a section of the proof, within the
+    /// length of the section.
+    SectionTrailingBytes {
+        section: &'static str,
+        len: usize,
+    },
+    /// A Merkle path of a compressed proof refers to a node its tree does not have, see
+    /// [proof_compression](crate::proof_compression).
+    InvalidMerkleNode(usize),
+    /// A word of a commitment is not the big-endian encoding of a Bn254 field element.
+    NonCanonicalBn254Element(NonCanonicalBn254Error),
+    /// Reading the proof from a stream failed, see
+    /// [ProofStream::read_from](crate::proof_stream::ProofStream::read_from). Holds the rendered
+    /// I/O error.
+    Io(String),
+}
+
+impl fmt::Display for ProofSerdeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WrongMagic => write!(f, "not a serialized proof: wrong magic bytes"),
+            Self::UnsupportedVersion { version, supported } => write!(
+                f,
+                "unsupported proof format version {version}, supported versions are {supported:?}"
+            ),
+            Self::ConfigMismatch { expected, found } => write!(
+                f,
+                "proof was generated for config {found}, expected config {expected}"
+            ),
+            Self::UnexpectedEnd => write!(f, "unexpected end of input"),
+            Self::Payload(err) => write!(f, "failed to decode proof: {err}"),
+            Self::TrailingBytes(len) => write!(f, "{len} bytes after the encoding of the proof"),
+            Self::TooLarge { max, found } => write!(
+                f,
+                "serialized proof is {found} bytes, above the limit of {max}"
+            ),
+            Self::VarintOverflow => write!(f, "varint overflow"),
+            Self::NonCanonicalVarint => write!(f, "varint not in its shortest encoding"),
+            Self::NonCanonicalFieldElement { section, index } => write!(
+                f,
+                "non-canonical field element at byte {index} of the {section} section"
+            ),
+            Self::SectionTrailingBytes { section, len } => {
+                write!(f, "{len} bytes after the encoding of the {section} section")
+            }
+            Self::InvalidMerkleNode(index) => write!(f, "invalid Merkle node index {index}"),
+            Self::NonCanonicalBn254Element(err) => write!(f, "{err}"),
+            Self::Io(err) => write!(f, "failed to read proof: {err}"),
+        }
+    }
+}
+
+impl Error for ProofSerdeError {}
+
+/// A commitment with a canonical encoding in serialized proofs, independent of its serde
+/// encoding, see the [module documentation](self). The commitments of the configs of the sdk are
+/// digests whose words are [CommitmentWord]s.
+pub trait CanonicalCommitment: Sized {
+    /// Length of the encoding.
+    const LEN: usize;
+
+    fn write_canonical(&self, out: &mut Vec<u8>);
+
+    /// Reads the commitment at the start of `bytes`, and advances `bytes` past it.
+    fn read_canonical(bytes: &mut &[u8]) -> Result<Self, ProofSerdeError>;
+}
+
+/// A word of the digest of a [CanonicalCommitment]. Bn254 field elements are encoded as their
+/// 32 big-endian bytes, see [Bn254Bytes], and the other words as in bincode.
+pub trait CommitmentWord: Sized {
+    /// Length of the encoding.
+    const LEN: usize;
+
+    fn write_canonical(&self, out: &mut Vec<u8>);
+
+    /// Reads the word at the start of `bytes`, and advances `bytes` past it.
+    fn read_canonical(bytes: &mut &[u8]) -> Result<Self, ProofSerdeError>;
+}
+
+impl<F: Copy, W: CommitmentWord + Copy, const N: usize> CanonicalCommitment for Hash<F, W, N> {
+    const LEN: usize = N * W::LEN;
+
+    fn write_canonical(&self, out: &mut Vec<u8>) {
+        let words: [W; N] = (*self).into();
+        for word in words {
+            word.write_canonical(out);
+        }
+    }
+
+    fn read_canonical(bytes: &mut &[u8]) -> Result<Self, ProofSerdeError> {
+        let words = (0..N)
+            .map(|_| W::read_canonical(bytes))
+            .collect::<Result<Vec<_>, _>>()?;
+        let words: [W; N] = words.try_into().unwrap_or_else(|_| unreachable!());
+        Ok(words.into())
+    }
+}
+
+macro_rules! impl_bincode_commitment_word {
+    ($($word:ty => $len:expr),*) => {
+        $(
+            impl CommitmentWord for $word {
+                const LEN: usize = $len;
+
+                fn write_canonical(&self, out: &mut Vec<u8>) {
+                    write_bincode(out, self);
+                }
+
+                fn read_canonical(bytes: &mut &[u8]) -> Result<Self, ProofSerdeError> {
+                    // The deserializers of fields may reduce a word above the order of the
+                    // field, which the decoder of the commitments rejects by re-encoding them.
+                    bincode::deserialize_from(bytes)
+                        .map_err(|err| ProofSerdeError::Payload(err.to_string()))
+                }
+            }
+        )*
+    };
+}
+
+impl_bincode_commitment_word!(u8 => 1, u64 => 8, BabyBear => 4, Goldilocks => 8, KoalaBear => 4);
+
+impl CommitmentWord for Bn254Fr {
+    const LEN: usize = 32;
+
+    fn write_canonical(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_bytes_be());
+    }
+
+    fn read_canonical(bytes: &mut &[u8]) -> Result<Self, ProofSerdeError> {
+        let (word, rest) = split(bytes, 32)?;
+        *bytes = rest;
+        Bn254Fr::from_bytes_be(word.try_into().unwrap())
+            .map_err(ProofSerdeError::NonCanonicalBn254Element)
+    }
+}
+
+/// A part of a proof whose type depends on the config, the partial proof of the challenge phases
+/// or the opening proof of the PCS, with a canonical encoding over the base field `F` of the
+/// config, see the [module documentation](self).
+pub trait CanonicalProofPart<F>: Sized {
+    fn write_canonical(&self, out: &mut Vec<u8>);
+
+    /// Reads the part at the start of `reader`. The decoder of the proof checks that the bytes
+    /// read are the encoding of the part, so that distinct bytes never decode to the same part.
+    fn read_canonical(reader: &mut Reader<'_>) -> Result<Self, ProofSerdeError>;
+}
+
+/// A byte, 0 for `None` and 1 for `Some`, followed by the value.
+impl<F, T: CanonicalProofPart<F>> CanonicalProofPart<F> for Option<T> {
+    fn write_canonical(&self, out: &mut Vec<u8>) {
+        match self {
+            None => out.push(0),
+            Some(value) => {
+                out.push(1);
+                value.write_canonical(out);
+            }
+        }
+    }
+
+    fn read_canonical(reader: &mut Reader<'_>) -> Result<Self, ProofSerdeError> {
+        match reader.read_u8()? {
+            0 => Ok(None),
+            1 => T::read_canonical(reader).map(Some),
+            flag => Err(ProofSerdeError::Payload(format!(
+                "invalid option flag {flag}"
+            ))),
+        }
+    }
+}
+
+/// The Merkle path of an opening of a Merkle tree MMCS, as a list of digests.
+impl<F, W: CommitmentWord, const N: usize> CanonicalProofPart<F> for Vec<[W; N]> {
+    fn write_canonical(&self, out: &mut Vec<u8>) {
+        write_varint(out, self.len());
+        for digest in self {
+            for word in digest {
+                word.write_canonical(out);
+            }
+        }
+    }
+
+    fn read_canonical(reader: &mut Reader<'_>) -> Result<Self, ProofSerdeError> {
+        reader.read_list(|reader| {
+            let words = (0..N)
+                .map(|_| W::read_canonical(&mut reader.bytes))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(words.try_into().unwrap_or_else(|_| unreachable!()))
+        })
+    }
+}
+
+/// The opening proof of a [SaltedMmcs](crate::config::salted_mmcs::SaltedMmcs): the salt of each
+/// opened row, followed by the opening proof of the inner MMCS.
+impl<F: PrimeField64, P: CanonicalProofPart<F>> CanonicalProofPart<F> for (Vec<Vec<F>>, P) {
+    fn write_canonical(&self, out: &mut Vec<u8>) {
+        let (salts, proof) = self;
+        write_varint(out, salts.len());
+        for salt in salts {
+            write_fields(out, salt);
+        }
+        proof.write_canonical(out);
+    }
+
+    fn read_canonical(reader: &mut Reader<'_>) -> Result<Self, ProofSerdeError> {
+        let salts = reader.read_list(Reader::read_fields::<F>)?;
+        Ok((salts, P::read_canonical(reader)?))
+    }
+}
+
+impl<F: PrimeField64, EF, FriMmcs, InputMmcs> CanonicalProofPart<F>
+    for FriProof<EF, FriMmcs, F, Vec<BatchOpening<F, InputMmcs>>>
+where
+    EF: ExtensionField<F>,
+    FriMmcs: Mmcs<EF>,
+    FriMmcs::Commitment: CanonicalCommitment,
+    FriMmcs::Proof: CanonicalProofPart<F>,
+    InputMmcs: Mmcs<F>,
+    InputMmcs::Proof: CanonicalProofPart<F>,
+{
+    fn write_canonical(&self, out: &mut Vec<u8>) {
+        write_varint(out, self.commit_phase_commits.len());
+        for commit in &self.commit_phase_commits {
+            commit.write_canonical(out);
+        }
+        write_varint(out, self.query_proofs.len());
+        for query_proof in &self.query_proofs {
+            query_proof.write_canonical(out);
+        }
+        write_varint(out, self.final_poly.len());
+        for value in &self.final_poly {
+            write_ext::<F, EF>(out, value);
+        }
+        write_field(out, self.pow_witness);
+    }
+
+    fn read_canonical(reader: &mut Reader<'_>) -> Result<Self, ProofSerdeError> {
+        Ok(FriProof {
+            commit_phase_commits: reader
+                .read_list(|reader| FriMmcs::Commitment::read_canonical(&mut reader.bytes))?,
+            query_proofs: reader.read_list(CanonicalProofPart::<F>::read_canonical)?,
+            final_poly: reader.read_list(Reader::read_ext::<F, EF>)?,
+            pow_witness: reader.read_field()?,
+        })
+    }
+}
+
+/// The opened rows and opening proof of each input batch, followed by the sibling value and
+/// opening proof of each fold.
+impl<F: PrimeField64, EF, FriMmcs, InputMmcs> CanonicalProofPart<F>
+    for QueryProof<EF, FriMmcs, Vec<BatchOpening<F, InputMmcs>>>
+where
+    EF: ExtensionField<F>,
+    FriMmcs: Mmcs<EF>,
+    FriMmcs::Proof: CanonicalProofPart<F>,
+    InputMmcs: Mmcs<F>,
+    InputMmcs::Proof: CanonicalProofPart<F>,
+{
+    fn write_canonical(&self, out: &mut Vec<u8>) {
+        write_varint(out, self.input_proof.len());
+        for opening in &self.input_proof {
+            write_varint(out, opening.opened_values.len());
+            for row in &opening.opened_values {
+                write_fields(out, row);
+            }
+            opening.opening_proof.write_canonical(out);
+        }
+        write_varint(out, self.commit_phase_openings.len());
+        for step in &self.commit_phase_openings {
+            write_ext::<F, EF>(out, &step.sibling_value);
+            step.opening_proof.write_canonical(out);
+        }
+    }
+
+    fn read_canonical(reader: &mut Reader<'_>) -> Result<Self, ProofSerdeError> {
+        Ok(QueryProof {
+            input_proof: reader.read_list(|reader| {
+                Ok(BatchOpening {
+                    opened_values: reader.read_list(Reader::read_fields::<F>)?,
+                    opening_proof: CanonicalProofPart::<F>::read_canonical(reader)?,
+                })
+            })?,
+            commit_phase_openings: reader.read_list(|reader| {
+                Ok(CommitPhaseProofStep {
+                    sibling_value: reader.read_ext::<F, EF>()?,
+                    opening_proof: CanonicalProofPart::<F>::read_canonical(reader)?,
+                })
+            })?,
+        })
+    }
+}
+
+/// The multiplicities of each public lookup table.
+impl<F: PrimeField64> CanonicalProofPart<F> for FriLogUpPartialProof<F> {
+    fn write_canonical(&self, out: &mut Vec<u8>) {
+        write_varint(out, self.public_lookup_multiplicities.len());
+        for multiplicities in &self.public_lookup_multiplicities {
+            write_fields(out, multiplicities);
+        }
+    }
+
+    fn read_canonical(reader: &mut Reader<'_>) -> Result<Self, ProofSerdeError> {
+        Ok(FriLogUpPartialProof {
+            public_lookup_multiplicities: reader.read_list(Reader::read_fields::<F>)?,
+        })
+    }
+}
+
+/// The coefficients of the round polynomials of each sum-check proof, the columns of the mask of
+/// each layer of each instance, and the output claims of each instance.
+impl<F: PrimeField64, EF: ExtensionField<F>> CanonicalProofPart<F> for GkrBatchProof<EF> {
+    fn write_canonical(&self, out: &mut Vec<u8>) {
+        write_varint(out, self.sumcheck_proofs.len());
+        for sumcheck_proof in &self.sumcheck_proofs {
+            write_varint(out, sumcheck_proof.round_polys.len());
+            for poly in &sumcheck_proof.round_polys {
+                write_varint(out, poly.len());
+                for coeff in poly.iter() {
+                    write_ext::<F, EF>(out, coeff);
+                }
+            }
+        }
+        write_varint(out, self.layer_masks_by_instance.len());
+        for masks in &self.layer_masks_by_instance {
+            write_varint(out, masks.len());
+            for mask in masks {
+                write_varint(out, mask.columns().len());
+                for column in mask.columns() {
+                    for value in column {
+                        write_ext::<F, EF>(out, value);
+                    }
+                }
+            }
+        }
+        write_varint(out, self.output_claims_by_instance.len());
+        for claims in &self.output_claims_by_instance {
+            write_varint(out, claims.len());
+            for claim in claims {
+                write_ext::<F, EF>(out, claim);
+            }
+        }
+    }
+
+    fn read_canonical(reader: &mut Reader<'_>) -> Result<Self, ProofSerdeError> {
+        // A round polynomial with a leading zero coefficient is trimmed by `from_coeffs`, and
+        // rejected by the decoder of the proof when it re-encodes it.
+        let sumcheck_proofs = reader.read_list(|reader| {
+            Ok(SumcheckProof {
+                round_polys: reader.read_list(|reader| {
+                    let coeffs = reader.read_list(Reader::read_ext::<F, EF>)?;
+                    Ok(UnivariatePolynomial::from_coeffs(coeffs))
+                })?,
+            })
+        })?;
+        let layer_masks_by_instance = reader.read_list(|reader| {
+            reader.read_list(|reader| {
+                let columns = reader.read_list(|reader| {
+                    Ok([reader.read_ext::<F, EF>()?, reader.read_ext::<F, EF>()?])
+                })?;
+                Ok(GkrMask::new(columns))
+            })
+        })?;
+        let output_claims_by_instance =
+            reader.read_list(|reader| reader.read_list(Reader::read_ext::<F, EF>))?;
+        Ok(GkrBatchProof {
+            sumcheck_proofs,
+            layer_masks_by_instance,
+            output_claims_by_instance,
+        })
+    }
+}
+
+/// Versioned serialization of a proof, see the [module documentation](self).
+pub trait ProofSerde: Sized {
+    /// Encodes the proof with [PROOF_FORMAT_VERSION].
+    fn to_bytes(&self) -> Vec<u8>;
+
+    /// Decodes a proof serialized with any of the [SUPPORTED_PROOF_FORMAT_VERSIONS], after
+    /// checking that it is for the config of `Self`. The bytes must end with the proof.
+    fn from_bytes(bytes: &[u8]) -> Result<Self, ProofSerdeError>;
+
+    /// Same as [from_bytes](Self::from_bytes), but ignores any bytes after the proof.
+    fn from_bytes_lenient(bytes: &[u8]) -> Result<Self, ProofSerdeError>;
+
+    /// Same as [from_bytes](Self::from_bytes), but rejects `bytes` longer than `max_proof_bytes`
+    /// before decoding them, e.g. with the
+    /// [VerifierLimits](openvm_stark_backend::verifier::VerifierLimits) of the verifier.
+    fn from_bytes_with_limit(
+        bytes: &[u8],
+        max_proof_bytes: usize,
+    ) -> Result<Self, ProofSerdeError> {
+        if bytes.len() > max_proof_bytes {
+            return Err(ProofSerdeError::TooLarge {
+                max: max_proof_bytes,
+                found: bytes.len(),
+            });
+        }
+        Self::from_bytes(bytes)
+    }
+}
+
+impl<SC: StarkGenericConfig + ConfigId> ProofSerde for Proof<SC>
+where
+    Val<SC>: PrimeField64,
+    Com<SC>: CanonicalCommitment,
+{
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut out = vec![];
+        write_header::<SC>(&mut out);
+        encode_v4(self, &mut out);
+        out
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, ProofSerdeError> {
+        let (proof, num_trailing_bytes) = decode(bytes)?;
+        if num_trailing_bytes != 0 {
+            return Err(ProofSerdeError::TrailingBytes(num_trailing_bytes));
+        }
+        Ok(proof)
+    }
+
+    fn from_bytes_lenient(bytes: &[u8]) -> Result<Self, ProofSerdeError> {
+        decode(bytes).map(|(proof, _)| proof)
+    }
+}
+
+/// Decodes the proof at the start of `bytes`, and returns it with the number of bytes after it.
+fn decode<SC: StarkGenericConfig + ConfigId>(
+    bytes: &[u8],
+) -> Result<(Proof<SC>, usize), ProofSerdeError>
+where
+    Val<SC>: PrimeField64,
+    Com<SC>: CanonicalCommitment,
+{
+    match read_version(bytes)? {
+        (1, rest) => decode_v1(rest),
+        (version @ 2..=4, rest) => decode_sectioned(version, rest),
+        (version, _) => Err(unsupported_version(version)),
+    }
+}
+
+/// The name of the STARK config of a serialized proof, see [config_name], without decoding the
+/// proof.
+pub fn read_config_name(bytes: &[u8]) -> Result<String, ProofSerdeError> {
+    match read_version(bytes)? {
+        (1..=4, rest) => read_config_v1(rest).map(|(config, _)| config.into_owned()),
+        (version, _) => Err(unsupported_version(version)),
+    }
+}
+
+/// Reads the magic and the format version at the start of `bytes`, and returns the version with
+/// the rest of the bytes. The rest of the header is read by the decoder of the version, so that a
+/// later version may change it.
+pub(crate) fn read_version(bytes: &[u8]) -> Result<(u32, &[u8]), ProofSerdeError> {
+    let (magic, rest) = split(bytes, PROOF_MAGIC.len())?;
+    if magic != PROOF_MAGIC {
+        return Err(ProofSerdeError::WrongMagic);
+    }
+    let (version, rest) = split(rest, 4)?;
+    Ok((u32::from_le_bytes(version.try_into().unwrap()), rest))
+}
+
+pub(crate) fn unsupported_version(version: u32) -> ProofSerdeError {
+    ProofSerdeError::UnsupportedVersion {
+        version,
+        supported: SUPPORTED_PROOF_FORMAT_VERSIONS.to_vec(),
+    }
+}
+
+fn read_config_v1(bytes: &[u8]) -> Result<(Cow<'_, str>, &[u8]), ProofSerdeError> {
+    let (config_len, rest) = split(bytes, 4)?;
+    let config_len = u32::from_le_bytes(config_len.try_into().unwrap()) as usize;
+    let (config, rest) = split(rest, config_len)?;
+    Ok((String::from_utf8_lossy(config), rest))
+}
+
+/// Reads the config name of the header of versions 1 to 4, checks that it is the name of `SC`,
+/// and returns the bytes after the header.
+pub(crate) fn check_config<SC: ConfigId>(bytes: &[u8]) -> Result<&[u8], ProofSerdeError> {
+    let (config, payload) = read_config_v1(bytes)?;
+    if config != config_name::<SC>() {
+        return Err(ProofSerdeError::ConfigMismatch {
+            expected: config_name::<SC>().to_string(),
+            found: config.into_owned(),
+        });
+    }
+    Ok(payload)
+}
+
+fn decode_v1<SC: StarkGenericConfig + ConfigId>(
+    bytes: &[u8],
+) -> Result<(Proof<SC>, usize), ProofSerdeError> {
+    decode_v1_payload(check_config::<SC>(bytes)?)
+}
+
+/// Decodes the proof of format version 1 at the start of the bytes after its header, and returns
+/// it with the number of bytes after it.
+pub(crate) fn decode_v1_payload<SC: StarkGenericConfig>(
+    payload: &[u8],
+) -> Result<(Proof<SC>, usize), ProofSerdeError> {
+    let mut reader = Reader::new("proof", payload);
+    let proof = reader.read_bincode()?;
+    Ok((proof, reader.bytes.len()))
+}
+
+/// Writes the header of a proof of `SC` with [PROOF_FORMAT_VERSION].
+pub(crate) fn write_header<SC: ConfigId>(out: &mut Vec<u8>) {
+    let config = config_name::<SC>();
+    out.extend_from_slice(&PROOF_MAGIC);
+    out.extend_from_slice(&PROOF_FORMAT_VERSION.to_le_bytes());
+    out.extend_from_slice(&(config.len() as u32).to_le_bytes());
+    out.extend_from_slice(config.as_bytes());
+}
+
+fn encode_v4<SC: StarkGenericConfig>(proof: &Proof<SC>, out: &mut Vec<u8>)
+where
+    Val<SC>: PrimeField64,
+    Com<SC>: CanonicalCommitment,
+    PcsProof<SC>: CanonicalProofPart<Val<SC>>,
+    RapPhaseSeqPartialProof<SC>: CanonicalProofPart<Val<SC>>,
+{
+    encode_v4_before_opening_proof(proof, out);
+    write_section(out, |out| {
+        CanonicalProofPart::<Val<SC>>::write_canonical(&proof.pcs_proof, out)
+    });
+}
+
+/// Writes the sections of version 4 before the opening proof, which is the last section.
+pub(crate) fn encode_v4_before_opening_proof<SC: StarkGenericConfig>(
+    proof: &Proof<SC>,
+    out: &mut Vec<u8>,
+) where
+    Val<SC>: PrimeField64,
+    Com<SC>: CanonicalCommitment,
+    RapPhaseSeqPartialProof<SC>: CanonicalProofPart<Val<SC>>,
+{
+    write_section(out, |out| write_commitments(out, &proof.commitments));
+    write_section(out, |out| {
+        write_varint(out, proof.core.per_air.len());
+        for air_proof in &proof.core.per_air {
+            write_varint(out, air_proof.air_id);
+            write_varint(out, air_proof.degree);
+        }
+    });
+    write_section(out, |out| {
+        for air_proof in &proof.core.per_air {
+            write_fields(out, &air_proof.public_values);
+        }
+    });
+    write_section(out, |out| {
+        for air_proof in &proof.core.per_air {
+            write_varint(out, air_proof.exposed_values_after_challenge.len());
+            for values in &air_proof.exposed_values_after_challenge {
+                write_ext_fields::<SC>(out, values);
+            }
+        }
+    });
+    write_section(out, |out| {
+        let values = &proof.core.opened_values;
+        write_adjacent_list::<SC>(out, &values.preprocessed);
+        write_nested_adjacent_list::<SC>(out, &values.main);
+        write_nested_adjacent_list::<SC>(out, &values.after_challenge);
+        write_varint(out, values.quotient.len());
+        for chunks in &values.quotient {
+            write_varint(out, chunks.len());
+            for chunk in chunks {
+                write_ext_fields::<SC>(out, chunk);
+            }
+        }
+        write_nested_adjacent_list::<SC>(out, &values.custom);
+    });
+    write_section(out, |out| {
+        CanonicalProofPart::<Val<SC>>::write_canonical(&proof.rap_phase_seq_proof, out)
+    });
+    write_section(out, |out| {
+        write_varint(out, proof.core.absent_air_ids.len());
+        for &air_id in &proof.core.absent_air_ids {
+            write_varint(out, air_id);
+        }
+    });
+}
+
+/// Decodes a proof of format version 2, 3 or 4 from the bytes after its magic and version.
+fn decode_sectioned<SC: StarkGenericConfig + ConfigId>(
+    version: u32,
+    bytes: &[u8],
+) -> Result<(Proof<SC>, usize), ProofSerdeError>
+where
+    Val<SC>: PrimeField64,
+    Com<SC>: CanonicalCommitment,
+    PcsProof<SC>: CanonicalProofPart<Val<SC>>,
+    RapPhaseSeqPartialProof<SC>: CanonicalProofPart<Val<SC>>,
+{
+    let mut reader = Reader::new("proof", check_config::<SC>(bytes)?);
+    let proof = decode_sections(version, &mut reader, |_| Ok(()))?;
+    Ok((proof, reader.bytes.len()))
+}
+
+/// The claims at the start of a serialized proof, see [read_proof_prefix]. Like the accessors of
+/// [Proof], e.g. [Proof::metadata], they are **unverified** until the proof verifies.
+pub struct ProofPrefix<SC: StarkGenericConfig> {
+    pub commitments: Commitments<Com<SC>>,
+    /// The id and trace height of each AIR of the proof.
+    pub air_heights: Vec<(usize, usize)>,
+    /// The public values of each AIR of the proof.
+    pub public_values: Vec<Vec<Val<SC>>>,
+}
+
+/// Reads the commitments, AIRs and public values of a serialized proof without decoding the rest
+/// of it, so that `bytes` may be any prefix of the serialized proof holding them. A proof of
+/// format version 1 has no sections, and must be whole.
+pub fn read_proof_prefix<SC: StarkGenericConfig + ConfigId>(
+    bytes: &[u8],
+) -> Result<ProofPrefix<SC>, ProofSerdeError>
+where
+    Val<SC>: PrimeField64,
+    Com<SC>: CanonicalCommitment,
+{
+    match read_version(bytes)? {
+        (1, rest) => {
+            let (proof, _) = decode_v1::<SC>(rest)?;
+            let air_heights = proof
+                .core
+                .per_air
+                .iter()
+                .map(|air_proof| (air_proof.air_id, air_proof.degree))
+                .collect();
+            Ok(ProofPrefix {
+                commitments: proof.commitments,
+                air_heights,
+                public_values: proof
+                    .core
+                    .per_air
+                    .into_iter()
+                    .map(|air_proof| air_proof.public_values)
+                    .collect(),
+            })
+        }
+        (version @ 2..=4, rest) => {
+            let mut reader = Reader::new("proof", check_config::<SC>(rest)?);
+            decode_prefix(version, &mut reader, |_| Ok(()))
+        }
+        (version, _) => Err(unsupported_version(version)),
+    }
+}
+
+/// Decodes the sections of a proof of format version 2, 3 or 4 up to its public values, calling
+/// `check_airs` as [decode_sections] does.
+fn decode_prefix<SC, E>(
+    version: u32,
+    reader: &mut impl SectionReader,
+    check_airs: impl FnOnce(&[(usize, usize)]) -> Result<(), E>,
+) -> Result<ProofPrefix<SC>, E>
+where
+    SC: StarkGenericConfig,
+    Val<SC>: PrimeField64,
+    Com<SC>: CanonicalCommitment,
+    E: From<ProofSerdeError>,
+{
+    let commitments = reader.read_section("commitments", |reader| match version {
+        2 => reader.read_bincode(),
+        _ => reader.read_canonical(Reader::read_commitments, write_commitments),
+    })?;
+    let air_heights = reader.read_section("AIRs", |reader| {
+        reader.read_list(|reader| Ok((reader.read_varint()?, reader.read_varint()?)))
+    })?;
+    check_airs(&air_heights)?;
+    let public_values = reader.read_section("public values", |reader| {
+        air_heights
+            .iter()
+            .map(|_| reader.read_fields::<Val<SC>>())
+            .collect::<Result<Vec<_>, _>>()
+    })?;
+    Ok(ProofPrefix {
+        commitments,
+        air_heights,
+        public_values,
+    })
+}
+
+/// Decodes the sections of a proof of format version 2, 3 or 4, which only differ by the encoding
+/// of the commitments, and of the partial proof of the challenge phases and the opening proof.
+/// `check_airs` is called with the id and trace height of each AIR as soon as they are read, so
+/// that a proof can be rejected before its opened values and opening proof are read.
+pub(crate) fn decode_sections<SC, E>(
+    version: u32,
+    reader: &mut impl SectionReader,
+    check_airs: impl FnOnce(&[(usize, usize)]) -> Result<(), E>,
+) -> Result<Proof<SC>, E>
+where
+    SC: StarkGenericConfig,
+    Val<SC>: PrimeField64,
+    Com<SC>: CanonicalCommitment,
+    PcsProof<SC>: CanonicalProofPart<Val<SC>>,
+    RapPhaseSeqPartialProof<SC>: CanonicalProofPart<Val<SC>>,
+    E: From<ProofSerdeError>,
+{
+    let ProofPrefix {
+        commitments,
+        air_heights,
+        public_values,
+    } = decode_prefix(version, reader, check_airs)?;
+    let exposed_values = reader.read_section("exposed values", |reader| {
+        air_heights
+            .iter()
+            .map(|_| reader.read_list(Reader::read_ext_fields::<SC>))
+            .collect::<Result<Vec<_>, _>>()
+    })?;
+    let values = reader.read_section("opened values", |reader| {
+        Ok(OpenedValues {
+            preprocessed: reader.read_list(Reader::read_adjacent::<SC>)?,
+            main: reader.read_list(|reader| reader.read_list(Reader::read_adjacent::<SC>))?,
+            after_challenge: reader
+                .read_list(|reader| reader.read_list(Reader::read_adjacent::<SC>))?,
+            quotient: reader.read_list(|reader| reader.read_list(Reader::read_ext_fields::<SC>))?,
+            custom: reader.read_list(|reader| reader.read_list(Reader::read_adjacent::<SC>))?,
+        })
+    })?;
+    let rap_phase_seq_proof = match version {
+        2 | 3 => reader.read_bincode_section("challenge phase")?,
+        _ => reader.read_canonical_section::<Val<SC>, _>("challenge phase")?,
+    };
+    let absent_air_ids = reader.read_section("absent AIRs", |reader| {
+        reader.read_list(Reader::read_varint)
+    })?;
+    let pcs_proof = match version {
+        2 | 3 => reader.read_bincode_section("opening proof")?,
+        _ => reader.read_canonical_section::<Val<SC>, _>("opening proof")?,
+    };
+
+    let per_air = air_heights
+        .into_iter()
+        .zip(public_values)
+        .zip(exposed_values)
+        .map(
+            |(((air_id, degree), public_values), exposed_values_after_challenge)| AirProofData {
+                air_id,
+                degree,
+                exposed_values_after_challenge,
+                public_values,
+            },
+        )
+        .collect();
+    let proof = Proof {
+        commitments,
+        core: ProofCore {
+            per_air,
+            opened_values: values,
+            absent_air_ids,
+        },
+        pcs_proof,
+        rap_phase_seq_proof,
+    };
+    Ok(proof)
+}
+
+/// Writes the section written by `write` into `out`, prefixed by its length.
+fn write_section(out: &mut Vec<u8>, write: impl FnOnce(&mut Vec<u8>)) {
+    let mut section = vec![];
+    write(&mut section);
+    write_varint(out, section.len());
+    out.extend_from_slice(&section);
+}
+
+pub(crate) fn write_bincode<T: Serialize>(out: &mut Vec<u8>, value: &T) {
+    bincode::serialize_into(out, value).expect("failed to serialize proof");
+}
+
+/// Writes the commitments in the layout of their bincode encoding, with lists prefixed by their
+/// length as a little-endian `u64`, but each commitment in its canonical encoding.
+pub(crate) fn write_commitments<Com: CanonicalCommitment>(
+    out: &mut Vec<u8>,
+    commitments: &Commitments<Com>,
+) {
+    let write_list = |out: &mut Vec<u8>, commits: &[Com]| {
+        out.extend_from_slice(&(commits.len() as u64).to_le_bytes());
+        for commit in commits {
+            commit.write_canonical(out);
+        }
+    };
+    write_list(out, &commitments.main_trace);
+    write_list(out, &commitments.after_challenge);
+    commitments.quotient.write_canonical(out);
+    write_list(out, &commitments.custom);
+}
+
+/// Number of bytes of the varint encoding of `value`.
+pub(crate) fn varint_len(value: usize) -> usize {
+    (usize::BITS - value.leading_zeros()).div_ceil(7).max(1) as usize
+}
+
+pub(crate) fn write_varint(out: &mut Vec<u8>, value: usize) {
+    let mut value = value as u64;
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+pub(crate) fn write_field<F: PrimeField64>(out: &mut Vec<u8>, value: F) {
+    out.extend_from_slice(&value.as_canonical_u64().to_le_bytes()[..field_len::<F>()]);
+}
+
+pub(crate) fn write_fields<F: PrimeField64>(out: &mut Vec<u8>, values: &[F]) {
+    write_varint(out, values.len());
+    for &value in values {
+        write_field(out, value);
+    }
+}
+
+/// Writes the coefficients of `value` over `F`.
+pub(crate) fn write_ext<F: PrimeField64, EF: FieldExtensionAlgebra<F>>(
+    out: &mut Vec<u8>,
+    value: &EF,
+) {
+    for &coeff in value.as_base_slice() {
+        write_field(out, coeff);
+    }
+}
+
+pub(crate) fn write_ext_fields<SC: StarkGenericConfig>(out: &mut Vec<u8>, values: &[SC::Challenge])
+where
+    Val<SC>: PrimeField64,
+{
+    write_varint(out, values.len());
+    for value in values {
+        write_ext::<Val<SC>, _>(out, value);
+    }
+}
+
+pub(crate) fn write_adjacent_list<SC: StarkGenericConfig>(
+    out: &mut Vec<u8>,
+    values: &[AdjacentOpenedValues<SC::Challenge>],
+) where
+    Val<SC>: PrimeField64,
+{
+    write_varint(out, values.len());
+    for values in values {
+        write_adjacent::<SC>(out, values);
+    }
+}
+
+pub(crate) fn write_adjacent<SC: StarkGenericConfig>(
+    out: &mut Vec<u8>,
+    values: &AdjacentOpenedValues<SC::Challenge>,
+) where
+    Val<SC>: PrimeField64,
+{
+    write_ext_fields::<SC>(out, &values.local);
+    write_ext_fields::<SC>(out, &values.next);
+    write_varint(out, values.rotated.len());
+    for rotated in &values.rotated {
+        write_ext_fields::<SC>(out, rotated);
+    }
+}
+
+fn write_nested_adjacent_list<SC: StarkGenericConfig>(
+    out: &mut Vec<u8>,
+    values: &[Vec<AdjacentOpenedValues<SC::Challenge>>],
+) where
+    Val<SC>: PrimeField64,
+{
+    write_varint(out, values.len());
+    for values in values {
+        write_adjacent_list::<SC>(out, values);
+    }
+}
+
+/// Number of bytes of an encoded element of `F`.
+pub(crate) fn field_len<F: PrimeField64>() -> usize {
+    let bits = u64::BITS - F::ORDER_U64.leading_zeros();
+    bits.div_ceil(8) as usize
+}
+
+/// A reader of the bytes of a section of a serialized proof.
+pub struct Reader<'a> {
+    pub(crate) bytes: &'a [u8],
+    /// The section being read and its length, to locate the non-canonical encodings in it.
+    section: &'static str,
+    section_len: usize,
+}
+
+impl<'a> Reader<'a> {
+    /// A reader of the bytes of `section`.
+    pub(crate) fn new(section: &'static str, bytes: &'a [u8]) -> Self {
+        Self {
+            bytes,
+            section,
+            section_len: bytes.len(),
+        }
+    }
+
+    /// The offset of the next byte within the section.
+    fn offset(&self) -> usize {
+        self.section_len - self.bytes.len()
+    }
+
+    fn non_canonical(&self, index: usize) -> ProofSerdeError {
+        ProofSerdeError::NonCanonicalFieldElement {
+            section: self.section,
+            index,
+        }
+    }
+
+    pub(crate) fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], ProofSerdeError> {
+        let (head, tail) = split(self.bytes, len)?;
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, ProofSerdeError> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    pub(crate) fn read_varint(&mut self) -> Result<usize, ProofSerdeError> {
+        read_varint_with(|| self.read_u8())
+    }
+
+    /// Reads a list prefixed by its varint length, with `read` reading each entry.
+    pub(crate) fn read_list<T>(
+        &mut self,
+        mut read: impl FnMut(&mut Self) -> Result<T, ProofSerdeError>,
+    ) -> Result<Vec<T>, ProofSerdeError> {
+        let len = self.read_varint()?;
+        // Every entry takes at least one byte, which bounds the allocation by the input size.
+        let mut list = Vec::with_capacity(len.min(self.bytes.len()));
+        for _ in 0..len {
+            list.push(read(self)?);
+        }
+        Ok(list)
+    }
+
+    fn read_commitments<Com: CanonicalCommitment>(
+        &mut self,
+    ) -> Result<Commitments<Com>, ProofSerdeError> {
+        fn read_list<Com: CanonicalCommitment>(
+            reader: &mut Reader<'_>,
+        ) -> Result<Vec<Com>, ProofSerdeError> {
+            let len = u64::from_le_bytes(reader.read_bytes(8)?.try_into().unwrap());
+            let len = usize::try_from(len).unwrap_or(usize::MAX);
+            // Every commitment takes at least one byte, which bounds the allocation by the input
+            // size.
+            let mut commits = Vec::with_capacity(len.min(reader.bytes.len()));
+            for _ in 0..len {
+                commits.push(Com::read_canonical(&mut reader.bytes)?);
+            }
+            Ok(commits)
+        }
+        Ok(Commitments {
+            main_trace: read_list(self)?,
+            after_challenge: read_list(self)?,
+            quotient: Com::read_canonical(&mut self.bytes)?,
+            custom: read_list(self)?,
+        })
+    }
+
+    /// Reads the bincode encoding of a `T`, which must be the encoding of the value read: the
+    /// deserializers of some fields reduce a value above the order of the field instead of
+    /// rejecting it, so that distinct bytes would decode to the same proof.
+    pub(crate) fn read_bincode<T: Serialize + DeserializeOwned>(
+        &mut self,
+    ) -> Result<T, ProofSerdeError> {
+        self.read_canonical(
+            |reader| {
+                bincode::deserialize_from(&mut reader.bytes)
+                    .map_err(|err| ProofSerdeError::Payload(err.to_string()))
+            },
+            write_bincode,
+        )
+    }
+
+    /// Reads a value with `read`, and checks that `write` encodes it into the bytes read.
+    fn read_canonical<T>(
+        &mut self,
+        read: impl FnOnce(&mut Self) -> Result<T, ProofSerdeError>,
+        write: impl FnOnce(&mut Vec<u8>, &T),
+    ) -> Result<T, ProofSerdeError> {
+        let (start, bytes) = (self.offset(), self.bytes);
+        let value = read(self)?;
+        let bytes = &bytes[..self.offset() - start];
+        let mut encoding = Vec::with_capacity(bytes.len());
+        write(&mut encoding, &value);
+        if encoding != bytes {
+            let index = encoding
+                .iter()
+                .zip(bytes)
+                .position(|(a, b)| a != b)
+                .unwrap_or(encoding.len().min(bytes.len()));
+            return Err(self.non_canonical(start + index));
+        }
+        Ok(value)
+    }
+
+    pub(crate) fn read_field<F: PrimeField64>(&mut self) -> Result<F, ProofSerdeError> {
+        let index = self.offset();
+        let mut bytes = [0u8; 8];
+        bytes[..field_len::<F>()].copy_from_slice(self.read_bytes(field_len::<F>())?);
+        let value = u64::from_le_bytes(bytes);
+        if value >= F::ORDER_U64 {
+            return Err(self.non_canonical(index));
+        }
+        Ok(F::from_canonical_u64(value))
+    }
+
+    pub(crate) fn read_fields<F: PrimeField64>(&mut self) -> Result<Vec<F>, ProofSerdeError> {
+        self.read_list(Self::read_field)
+    }
+
+    fn read_ext_fields<SC: StarkGenericConfig>(
+        &mut self,
+    ) -> Result<Vec<SC::Challenge>, ProofSerdeError>
+    where
+        Val<SC>: PrimeField64,
+    {
+        self.read_list(Self::read_ext::<Val<SC>, SC::Challenge>)
+    }
+
+    /// Reads an element of `EF` written by [write_ext].
+    fn read_ext<F: PrimeField64, EF: FieldExtensionAlgebra<F>>(
+        &mut self,
+    ) -> Result<EF, ProofSerdeError> {
+        let coeffs = (0..EF::D)
+            .map(|_| self.read_field())
+            .collect::<Result<Vec<F>, _>>()?;
+        Ok(EF::from_base_slice(&coeffs))
+    }
+
+    fn read_adjacent<SC: StarkGenericConfig>(
+        &mut self,
+    ) -> Result<AdjacentOpenedValues<SC::Challenge>, ProofSerdeError>
+    where
+        Val<SC>: PrimeField64,
+    {
+        Ok(AdjacentOpenedValues {
+            local: self.read_ext_fields::<SC>()?,
+            next: self.read_ext_fields::<SC>()?,
+            rotated: self.read_list(Self::read_ext_fields::<SC>)?,
+        })
+    }
+}
+
+/// The sections of a serialized proof of version 2 to 4, each prefixed by its varint length,
+/// read from a byte slice or from a stream, see [ProofStream](crate::proof_stream::ProofStream).
+pub(crate) trait SectionReader {
+    /// Reads the next section with `read`, which must read all the bytes of the section.
+    fn read_section<T>(
+        &mut self,
+        section: &'static str,
+        read: impl FnOnce(&mut Reader<'_>) -> Result<T, ProofSerdeError>,
+    ) -> Result<T, ProofSerdeError>;
+
+    /// Reads the next section, the bincode encoding of a `T`.
+    fn read_bincode_section<T: Serialize + DeserializeOwned>(
+        &mut self,
+        section: &'static str,
+    ) -> Result<T, ProofSerdeError> {
+        self.read_section(section, |reader| reader.read_bincode())
+    }
+
+    /// Reads the next section, the canonical encoding of a `T`.
+    fn read_canonical_section<F, T: CanonicalProofPart<F>>(
+        &mut self,
+        section: &'static str,
+    ) -> Result<T, ProofSerdeError> {
+        self.read_section(section, |reader| {
+            reader.read_canonical(T::read_canonical, |out, value| value.write_canonical(out))
+        })
+    }
+}
+
+impl SectionReader for Reader<'_> {
+    fn read_section<T>(
+        &mut self,
+        section: &'static str,
+        read: impl FnOnce(&mut Reader<'_>) -> Result<T, ProofSerdeError>,
+    ) -> Result<T, ProofSerdeError> {
+        let len = self.read_varint()?;
+        read_whole_section(section, self.read_bytes(len)?, read)
+    }
+}
+
+/// Reads the section `bytes` with `read`, which must read all of them.
+pub(crate) fn read_whole_section<T>(
+    section: &'static str,
+    bytes: &[u8],
+    read: impl FnOnce(&mut Reader<'_>) -> Result<T, ProofSerdeError>,
+) -> Result<T, ProofSerdeError> {
+    let mut reader = Reader::new(section, bytes);
+    let value = read(&mut reader)?;
+    if !reader.bytes.is_empty() {
+        return Err(ProofSerdeError::SectionTrailingBytes {
+            section,
+            len: reader.bytes.len(),
+        });
+    }
+    Ok(value)
+}
+
+/// Reads a varint whose bytes are read by `read_u8`. Only the shortest encoding of a value is
+/// accepted, so that a value has a single encoding.
+pub(crate) fn read_varint_with(
+    mut read_u8: impl FnMut() -> Result<u8, ProofSerdeError>,
+) -> Result<usize, ProofSerdeError> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = read_u8()?;
+        let bits = (byte & 0x7f) as u64;
+        if (bits << shift) >> shift != bits {
+            return Err(ProofSerdeError::VarintOverflow);
+        }
+        value |= bits << shift;
+        if byte & 0x80 == 0 {
+            if byte == 0 && shift != 0 {
+                return Err(ProofSerdeError::NonCanonicalVarint);
+            }
+            return usize::try_from(value).map_err(|_| ProofSerdeError::VarintOverflow);
+        }
+    }
+    Err(ProofSerdeError::VarintOverflow)
+}
+
+pub(crate) fn split(bytes: &[u8], len: usize) -> Result<(&[u8], &[u8]), ProofSerdeError> {
+    if bytes.len() < len {
+        return Err(ProofSerdeError::UnexpectedEnd);
+    }
+    Ok(bytes.split_at(len))
+}
@@ -0,0 +1,317 @@
+//! Streaming serialization of proofs, to write a proof to a file or a socket and read it back
+//! without holding all of its serialized bytes in memory.
+//!
+//! [ProofStream::write_to] writes the bytes of [ProofSerde::to_bytes] one part at a time: the
+//! sections before the opening proof, then the opening proof, which holds most of the bytes of a
+//! proof and is encoded on its own to prefix it with its length. [ProofStream::read_from]
+//! decodes the proofs [ProofSerde::from_bytes] decodes, with the same errors, one section at a
+//! time: the bytes of a section are kept until it is decoded, to check that they are its canonical
+//! encoding. From version 2, whose sections are length-prefixed, the stream is read up to the end
+//! of the proof only, so it may carry other data after the proof, e.g. another proof.
+//!
+//! The header is read without buffering, so that a proof of an unsupported version or of another
+//! config is rejected after reading its header only. [verify_from_reader] also checks the AIRs of
+//! the proof against the verifying key before reading the rest of the proof.
+//!
+//! [ProofSerde::to_bytes]: crate::proof_serde::ProofSerde::to_bytes
+//! [ProofSerde::from_bytes]: crate::proof_serde::ProofSerde::from_bytes
+
+use std::{
+    error::Error,
+    fmt,
+    io::{self, BufWriter, Read, Write},
+    mem,
+};
+
+use itertools::Itertools;
+use openvm_stark_backend::{
+    config::{Com, PcsProof, RapPhaseSeqPartialProof, StarkGenericConfig, Val},
+    engine::StarkEngine,
+    keygen::types::MultiStarkVerifyingKey,
+    p3_field::PrimeField64,
+    proof::Proof,
+    verifier::{VerificationError, DEFAULT_MAX_PROOF_BYTES},
+};
+
+use crate::{
+    key_serde::ConfigId,
+    proof_serde::{
+        check_config, decode_sections, decode_v1_payload, encode_v4_before_opening_proof,
+        read_varint_with, read_version, read_whole_section, unsupported_version, write_header,
+        write_varint, CanonicalCommitment, CanonicalProofPart, ProofSerdeError, Reader,
+        SectionReader, PROOF_MAGIC,
+    },
+};
+
+/// Capacity of the buffer of [ProofStream::write_to], which holds a few thousand field elements.
+pub const PROOF_STREAM_BUFFER_LEN: usize = 1 << 16;
+
+/// Streaming serialization of a proof, see the [module documentation](self).
+pub trait ProofStream: Sized {
+    /// Writes the proof as [ProofSerde::to_bytes](crate::proof_serde::ProofSerde::to_bytes)
+    /// encodes it.
+    fn write_to(&self, writer: impl Write) -> io::Result<()>;
+
+    /// Reads a proof written by [write_to](Self::write_to), or serialized with any of the
+    /// [SUPPORTED_PROOF_FORMAT_VERSIONS](crate::proof_serde::SUPPORTED_PROOF_FORMAT_VERSIONS),
+    /// of at most [DEFAULT_MAX_PROOF_BYTES].
+    ///
+    /// From version 2, whose sections are length-prefixed, no byte after the proof is read: the
+    /// reader is left at the end of the proof, and a peer keeping the stream open does not block
+    /// it. A proof of version 1 has no length, and is read to the end of the stream.
+    fn read_from(reader: impl Read) -> Result<Self, ProofSerdeError> {
+        Self::read_from_with_limit(reader, DEFAULT_MAX_PROOF_BYTES)
+    }
+
+    /// Same as [read_from](Self::read_from), but rejects a proof longer than `max_proof_bytes` as
+    /// soon as the length of a section, or the bytes read, exceed it.
+    fn read_from_with_limit(
+        reader: impl Read,
+        max_proof_bytes: usize,
+    ) -> Result<Self, ProofSerdeError>;
+}
+
+impl<SC: StarkGenericConfig + ConfigId> ProofStream for Proof<SC>
+where
+    Val<SC>: PrimeField64,
+    Com<SC>: CanonicalCommitment,
+    PcsProof<SC>: CanonicalProofPart<Val<SC>>,
+    RapPhaseSeqPartialProof<SC>: CanonicalProofPart<Val<SC>>,
+{
+    fn write_to(&self, writer: impl Write) -> io::Result<()> {
+        let mut writer = BufWriter::with_capacity(PROOF_STREAM_BUFFER_LEN, writer);
+        let mut head = vec![];
+        write_header::<SC>(&mut head);
+        encode_v4_before_opening_proof(self, &mut head);
+        writer.write_all(&head)?;
+        let mut pcs_proof = vec![];
+        CanonicalProofPart::<Val<SC>>::write_canonical(&self.pcs_proof, &mut pcs_proof);
+        let mut pcs_proof_len = vec![];
+        write_varint(&mut pcs_proof_len, pcs_proof.len());
+        writer.write_all(&pcs_proof_len)?;
+        writer.write_all(&pcs_proof)?;
+        writer.flush()
+    }
+
+    fn read_from_with_limit(
+        reader: impl Read,
+        max_proof_bytes: usize,
+    ) -> Result<Self, ProofSerdeError> {
+        read_proof(reader, max_proof_bytes, |_| Ok(()))
+    }
+}
+
+/// An error verifying a proof read from a stream, see [verify_from_reader].
+#[derive(Debug, PartialEq, Eq)]
+pub enum StreamVerifyError {
+    /// The proof failed to decode, see [ProofStream::read_from].
+    Proof(ProofSerdeError),
+    /// The proof does not verify against the verifying key.
+    Verification(VerificationError),
+}
+
+impl fmt::Display for StreamVerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Proof(err) => write!(f, "{err}"),
+            Self::Verification(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl Error for StreamVerifyError {}
+
+impl From<ProofSerdeError> for StreamVerifyError {
+    fn from(err: ProofSerdeError) -> Self {
+        Self::Proof(err)
+    }
+}
+
+impl From<VerificationError> for StreamVerifyError {
+    fn from(err: VerificationError) -> Self {
+        Self::Verification(err)
+    }
+}
+
+/// Reads a proof from `reader` as [ProofStream::read_from] does, verifies it against `vk` with
+/// `engine`, and returns the verified proof.
+///
+/// The AIR ids and trace heights of the proof are checked against `vk` as soon as they are read,
+/// so that a proof for other AIRs, or with a trace above the maximum height of its AIR, is
+/// rejected before its opened values and opening proof are read. The rest of the shape of the
+/// proof is checked by [StarkEngine::verify] before any hashing.
+pub fn verify_from_reader<SC, E>(
+    engine: &E,
+    vk: &MultiStarkVerifyingKey<SC>,
+    reader: impl Read,
+) -> Result<Proof<SC>, StreamVerifyError>
+where
+    SC: StarkGenericConfig + ConfigId,
+    E: StarkEngine<SC>,
+    Val<SC>: PrimeField64,
+    Com<SC>: CanonicalCommitment,
+    PcsProof<SC>: CanonicalProofPart<Val<SC>>,
+    RapPhaseSeqPartialProof<SC>: CanonicalProofPart<Val<SC>>,
+{
+    let proof =
+        read_proof::<SC, StreamVerifyError>(reader, DEFAULT_MAX_PROOF_BYTES, |air_heights| {
+            let air_ids = air_heights.iter().map(|&(air_id, _)| air_id).collect_vec();
+            if !air_ids.iter().tuple_windows().all(|(a, b)| a < b)
+                || air_ids.iter().any(|&air_id| air_id >= vk.per_air.len())
+            {
+                return Err(VerificationError::InvalidAirIds(air_ids).into());
+            }
+            for &(air_id, height) in air_heights {
+                vk.per_air[air_id]
+                    .check_trace_height(air_id, height)
+                    .map_err(VerificationError::from)?;
+            }
+            Ok(())
+        })?;
+    engine.verify(vk, &proof)?;
+    Ok(proof)
+}
+
+/// Reads a proof of at most `max_proof_bytes` from `reader`, calling `check_airs` with the id and
+/// trace height of each AIR of the proof as soon as they are read.
+fn read_proof<SC, E>(
+    reader: impl Read,
+    max_proof_bytes: usize,
+    check_airs: impl FnOnce(&[(usize, usize)]) -> Result<(), E>,
+) -> Result<Proof<SC>, E>
+where
+    SC: StarkGenericConfig + ConfigId,
+    Val<SC>: PrimeField64,
+    Com<SC>: CanonicalCommitment,
+    PcsProof<SC>: CanonicalProofPart<Val<SC>>,
+    RapPhaseSeqPartialProof<SC>: CanonicalProofPart<Val<SC>>,
+    E: From<ProofSerdeError>,
+{
+    let mut reader = StreamReader {
+        reader,
+        section: vec![],
+        len: 0,
+        max_proof_bytes,
+    };
+    let mut header = vec![];
+    reader.read_into(PROOF_MAGIC.len() + 4, &mut header)?;
+    let version = match read_version(&header)? {
+        (version @ 1..=4, _) => version,
+        (version, _) => return Err(unsupported_version(version).into()),
+    };
+    // Versions 1 to 4 have the same header, ending with the name of the config.
+    let mut config = vec![];
+    reader.read_into(4, &mut config)?;
+    let config_len = u32::from_le_bytes(config[..].try_into().unwrap());
+    reader.read_into(config_len as usize, &mut config)?;
+    check_config::<SC>(&config)?;
+
+    let proof = match version {
+        1 => {
+            // Version 1 is a single bincode encoding, with no length, decoded once all of it is
+            // read.
+            let mut payload = vec![];
+            reader.read_to_end(&mut payload)?;
+            let (proof, num_trailing_bytes) = decode_v1_payload::<SC>(&payload)?;
+            if num_trailing_bytes != 0 {
+                return Err(ProofSerdeError::TrailingBytes(num_trailing_bytes).into());
+            }
+            let air_heights = proof
+                .core
+                .per_air
+                .iter()
+                .map(|air_proof| (air_proof.air_id, air_proof.degree))
+                .collect_vec();
+            check_airs(&air_heights)?;
+            proof
+        }
+        _ => decode_sections(version, &mut reader, check_airs)?,
+    };
+    Ok(proof)
+}
+
+/// The sections of a proof read from a stream. The stream is not buffered, so that no byte after
+/// the proof is read: the bytes of each section are read at once, and only the varints, a few per
+/// proof, are read byte by byte.
+struct StreamReader<R> {
+    reader: R,
+    /// The bytes of the last section read, reused by the next section.
+    section: Vec<u8>,
+    /// Number of bytes of the proof read so far.
+    len: usize,
+    max_proof_bytes: usize,
+}
+
+impl<R: Read> StreamReader<R> {
+    /// Counts `len` more bytes of the proof, rejecting a proof longer than `max_proof_bytes`.
+    fn consume(&mut self, len: usize) -> Result<(), ProofSerdeError> {
+        let found = self.len.saturating_add(len);
+        if found > self.max_proof_bytes {
+            return Err(ProofSerdeError::TooLarge {
+                max: self.max_proof_bytes,
+                found,
+            });
+        }
+        self.len = found;
+        Ok(())
+    }
+
+    /// Appends the next `len` bytes of the stream to `bytes`.
+    fn read_into(&mut self, len: usize, bytes: &mut Vec<u8>) -> Result<(), ProofSerdeError> {
+        self.consume(len)?;
+        let start = bytes.len();
+        // The buffer grows with the bytes read, so a length beyond the end of the stream does not
+        // allocate in proportion to it.
+        (&mut self.reader)
+            .take(len as u64)
+            .read_to_end(bytes)
+            .map_err(io_error)?;
+        if bytes.len() - start < len {
+            return Err(ProofSerdeError::UnexpectedEnd);
+        }
+        Ok(())
+    }
+
+    /// Appends the rest of the stream to `bytes`. At most one byte beyond `max_proof_bytes` is
+    /// read, so that a stream longer than a proof is not read to its end.
+    fn read_to_end(&mut self, bytes: &mut Vec<u8>) -> Result<(), ProofSerdeError> {
+        let start = bytes.len();
+        let remaining = self.max_proof_bytes - self.len;
+        (&mut self.reader)
+            .take(remaining as u64 + 1)
+            .read_to_end(bytes)
+            .map_err(io_error)?;
+        self.consume(bytes.len() - start)
+    }
+
+    fn read_varint(&mut self) -> Result<usize, ProofSerdeError> {
+        read_varint_with(|| {
+            let mut byte = Vec::with_capacity(1);
+            self.read_into(1, &mut byte)?;
+            Ok(byte[0])
+        })
+    }
+}
+
+impl<R: Read> SectionReader for StreamReader<R> {
+    fn read_section<T>(
+        &mut self,
+        section: &'static str,
+        read: impl FnOnce(&mut Reader<'_>) -> Result<T, ProofSerdeError>,
+    ) -> Result<T, ProofSerdeError> {
+        let len = self.read_varint()?;
+        let mut bytes = mem::take(&mut self.section);
+        bytes.clear();
+        self.read_into(len, &mut bytes)?;
+        let result = read_whole_section(section, &bytes, read);
+        self.section = bytes;
+        result
+    }
+}
+
+fn io_error(err: io::Error) -> ProofSerdeError {
+    match err.kind() {
+        io::ErrorKind::UnexpectedEof => ProofSerdeError::UnexpectedEnd,
+        _ => ProofSerdeError::Io(err.to_string()),
+    }
+}
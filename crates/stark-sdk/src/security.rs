@@ -0,0 +1,323 @@
+//! Estimate of the total soundness error of a configuration, combining the errors of the FRI,
+//! log-up, constraint folding and DEEP components.
+
+use std::{collections::BTreeMap, error::Error, fmt};
+
+use openvm_stark_backend::{
+    config::{StarkGenericConfig, Val},
+    keygen::types::MultiStarkVerifyingKey,
+    p3_field::{FieldExtensionAlgebra, PrimeField64, TwoAdicField},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::config::FriParameters;
+
+/// Bounds on the proofs covered by a soundness estimate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WorkloadBounds {
+    /// log_2 of the maximum height of a trace.
+    pub max_log_height: usize,
+    /// Maximum number of interaction messages in a proof, summed over the rows of all traces.
+    pub max_interaction_count: usize,
+}
+
+impl WorkloadBounds {
+    /// Bounds for proofs in which every AIR of `vk` has a trace of height at most
+    /// `2^max_log_height`.
+    pub fn from_max_log_height<SC: StarkGenericConfig>(
+        vk: &MultiStarkVerifyingKey<SC>,
+        max_log_height: usize,
+    ) -> Self {
+        let max_interaction_count = vk
+            .per_air
+            .iter()
+            .map(|vk| vk.symbolic_constraints.interactions.len() << max_log_height)
+            .sum();
+        Self {
+            max_log_height,
+            max_interaction_count,
+        }
+    }
+}
+
+/// Bits of security of each component of the protocol, and of the protocol as a whole. A
+/// component without any error, such as log-up in a system without interactions, has infinite
+/// bits.
+///
+/// Except for the FRI queries, the error of each component is a number of bad challenges over
+/// the size `|EF|` of the challenge field.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SoundnessBreakdown {
+    /// FRI query phase, conjectured: `num_queries * log_blowup + proof_of_work_bits`.
+    pub fri_queries: f64,
+    /// FRI commit phase: the batching challenge and each folding challenge are bad with
+    /// probability at most the maximum LDE height over `|EF|`.
+    pub fri_folding: f64,
+    /// Log-up: for `N` interaction messages of length at most `L`, including the bus index, the
+    /// fingerprints collide with probability at most `N * L / |EF|`, and the sum of fractions
+    /// vanishes with probability at most `N / |EF|`. With `k` repetitions of the argument, each
+    /// with independent challenges, the error is raised to the power `k`.
+    pub logup: f64,
+    /// Folding of the constraints of an AIR with powers of a challenge: at most
+    /// `num_constraints / |EF|`.
+    pub constraint_folding: f64,
+    /// DEEP quotient argument: the out-of-domain point is bad with probability at most
+    /// `max_constraint_degree * max_height / |EF|`.
+    pub deep: f64,
+    /// The protocol as a whole, from the sum of the errors of all components.
+    pub total: f64,
+}
+
+/// A component of the protocol with its own soundness error.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SoundnessComponent {
+    FriQueries,
+    FriFolding,
+    Logup,
+    ConstraintFolding,
+    Deep,
+}
+
+impl fmt::Display for SoundnessComponent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::FriQueries => "fri queries",
+            Self::FriFolding => "fri folding",
+            Self::Logup => "logup",
+            Self::ConstraintFolding => "constraint folding",
+            Self::Deep => "deep",
+        };
+        f.pad(name)
+    }
+}
+
+impl SoundnessBreakdown {
+    /// Bits of security of each component, without the total.
+    pub fn components(&self) -> [(SoundnessComponent, f64); 5] {
+        [
+            (SoundnessComponent::FriQueries, self.fri_queries),
+            (SoundnessComponent::FriFolding, self.fri_folding),
+            (SoundnessComponent::Logup, self.logup),
+            (
+                SoundnessComponent::ConstraintFolding,
+                self.constraint_folding,
+            ),
+            (SoundnessComponent::Deep, self.deep),
+        ]
+    }
+}
+
+impl fmt::Display for SoundnessBreakdown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:<20}{:>8}", "component", "bits")?;
+        for (component, bits) in self.components() {
+            writeln!(f, "{component:<20}{bits:>8.1}")?;
+        }
+        writeln!(f, "{:<20}{:>8.1}", "total", self.total)
+    }
+}
+
+/// Estimates the total soundness of proving the AIRs of `vk` with FRI parameters `fri_params`,
+/// for proofs within `bounds`.
+pub fn total_soundness<SC: StarkGenericConfig>(
+    vk: &MultiStarkVerifyingKey<SC>,
+    fri_params: FriParameters,
+    bounds: &WorkloadBounds,
+) -> SoundnessBreakdown
+where
+    Val<SC>: PrimeField64,
+{
+    let bits = |num_bad: usize| challenge_bits::<SC>(num_bad);
+
+    let fri_queries =
+        (fri_params.num_queries * fri_params.log_blowup + fri_params.proof_of_work_bits) as f64;
+    let num_fri_challenges = bounds
+        .max_log_height
+        .saturating_sub(fri_params.log_final_poly_len)
+        + 1;
+    let fri_folding = bits(num_fri_challenges << (bounds.max_log_height + fri_params.log_blowup));
+
+    let max_message_len = vk
+        .per_air
+        .iter()
+        .flat_map(|vk| &vk.symbolic_constraints.interactions)
+        .map(|interaction| interaction.fields.len() + 1)
+        .max();
+    let logup = num_log_up_repetitions(vk) as f64
+        * bits(max_message_len.map_or(0, |len| bounds.max_interaction_count * (len + 1)));
+
+    let max_num_constraints = vk
+        .per_air
+        .iter()
+        .map(|vk| vk.symbolic_constraints.constraints.num_constraints())
+        .max()
+        .unwrap_or(0);
+    let constraint_folding = bits(max_num_constraints);
+
+    // The constraint degree is at most one more than the quotient degree.
+    let max_constraint_degree = vk
+        .per_air
+        .iter()
+        .map(|vk| vk.quotient_degree as usize + 1)
+        .max()
+        .unwrap_or(0);
+    let deep = bits(max_constraint_degree << bounds.max_log_height);
+
+    let mut breakdown = SoundnessBreakdown {
+        fri_queries,
+        fri_folding,
+        logup,
+        constraint_folding,
+        deep,
+        total: 0.0,
+    };
+    breakdown.total = total_bits(&breakdown);
+    breakdown
+}
+
+/// Bits of security of an error of `num_bad / |EF|`.
+fn challenge_bits<SC: StarkGenericConfig>(num_bad: usize) -> f64
+where
+    Val<SC>: PrimeField64,
+{
+    if num_bad == 0 {
+        return f64::INFINITY;
+    }
+    let ext_degree = <SC::Challenge as FieldExtensionAlgebra<Val<SC>>>::D;
+    ext_degree as f64 * (Val::<SC>::ORDER_U64 as f64).log2() - (num_bad as f64).log2()
+}
+
+/// The number of repetitions of the log-up argument shared by all AIRs with interactions.
+fn num_log_up_repetitions<SC: StarkGenericConfig>(vk: &MultiStarkVerifyingKey<SC>) -> usize {
+    vk.per_air
+        .iter()
+        .filter(|vk| vk.has_interaction())
+        .map(|vk| vk.log_up_params.num_repetitions)
+        .min()
+        .unwrap_or(1)
+}
+
+/// Bits of security of the protocol, from the sum of the errors of all components.
+fn total_bits(breakdown: &SoundnessBreakdown) -> f64 {
+    let total_error: f64 = breakdown
+        .components()
+        .into_iter()
+        .map(|(_, bits)| (-bits).exp2())
+        .sum();
+    -total_error.log2()
+}
+
+/// Soundness of proving the AIRs of a verifying key, for traces as tall as the two-adic subgroups
+/// of the field allow. It is serializable, to be kept alongside the key for audits.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SecurityReport {
+    /// log_2 of the maximum height of a trace: the two-adicity of the field minus the log blowup.
+    pub max_log_height: usize,
+    /// Bits of security of each component. The log-up error is union-bounded over the buses.
+    pub breakdown: SoundnessBreakdown,
+    /// The component with the fewest bits of security.
+    pub binding_component: SoundnessComponent,
+    /// Bits of security of [Self::binding_component].
+    pub binding_bits: f64,
+}
+
+impl SecurityReport {
+    /// Bits of security of FRI: the minimum of its query and folding phases.
+    pub fn fri_bits(&self) -> f64 {
+        self.breakdown.fri_queries.min(self.breakdown.fri_folding)
+    }
+
+    /// Whether the volume of interactions makes log-up less secure than FRI.
+    pub fn logup_below_fri(&self) -> bool {
+        self.breakdown.logup < self.fri_bits()
+    }
+}
+
+impl fmt::Display for SecurityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "traces of height at most 2^{}", self.max_log_height)?;
+        write!(f, "{}", self.breakdown)?;
+        writeln!(
+            f,
+            "binding component: {} ({:.1} bits)",
+            self.binding_component, self.binding_bits
+        )?;
+        if self.logup_below_fri() {
+            writeln!(
+                f,
+                "warning: logup has {:.1} bits of security, below the {:.1} bits of fri",
+                self.breakdown.logup,
+                self.fri_bits()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Reports the soundness of proving the AIRs of `vk` with FRI parameters `fri_params`, for any
+/// proof the field supports.
+///
+/// The log-up error is union-bounded over the buses: a bus with `N` messages of length at most
+/// `L`, including the bus index, contributes `N * (L + 1)` bad challenges, where every AIR may
+/// send on every row of a trace of the maximum height.
+pub fn security_report<SC: StarkGenericConfig>(
+    vk: &MultiStarkVerifyingKey<SC>,
+    fri_params: &FriParameters,
+) -> SecurityReport
+where
+    Val<SC>: PrimeField64 + TwoAdicField,
+{
+    let max_log_height = Val::<SC>::TWO_ADICITY.saturating_sub(fri_params.log_blowup);
+    let bounds = WorkloadBounds::from_max_log_height(vk, max_log_height);
+    let mut breakdown = total_soundness(vk, *fri_params, &bounds);
+
+    // Number of messages and maximum message length of each bus.
+    let mut buses = BTreeMap::<usize, (usize, usize)>::new();
+    for interaction in vk
+        .per_air
+        .iter()
+        .flat_map(|vk| &vk.symbolic_constraints.interactions)
+    {
+        let (num_messages, max_len) = buses.entry(interaction.bus_index).or_default();
+        *num_messages += 1 << max_log_height;
+        *max_len = (*max_len).max(interaction.fields.len() + 1);
+    }
+    let num_bad = buses
+        .values()
+        .map(|&(num_messages, max_len)| num_messages * (max_len + 1))
+        .sum();
+    breakdown.logup = num_log_up_repetitions(vk) as f64 * challenge_bits::<SC>(num_bad);
+    breakdown.total = total_bits(&breakdown);
+
+    let (binding_component, binding_bits) = breakdown
+        .components()
+        .into_iter()
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .unwrap();
+    SecurityReport {
+        max_log_height,
+        breakdown,
+        binding_component,
+        binding_bits,
+    }
+}
+
+/// The total soundness of a configuration is below the required minimum.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InsufficientSecurityError {
+    pub min_security_bits: usize,
+    pub breakdown: SoundnessBreakdown,
+}
+
+impl fmt::Display for InsufficientSecurityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "total soundness of {:.1} bits is below the minimum of {} bits",
+            self.breakdown.total, self.min_security_bits
+        )?;
+        write!(f, "{}", self.breakdown)
+    }
+}
+
+impl Error for InsufficientSecurityError {}
@@ -0,0 +1,245 @@
+//! Proving keys split into one file per AIR, whose preprocessed data is only read when a proof
+//! uses the AIR.
+//!
+//! A sharded key is a directory with:
+//!
+//! - [MANIFEST_FILE]: the proving key without the preprocessed data, serialized with
+//!   [KeySerde]
+//! - `air_{air_id}.bin`: the bincode encoding of the preprocessed commitment and data of each
+//!   AIR with a preprocessed trace, see [shard_path]
+//!
+//! The manifest holds everything the verifying key needs, so it is the only file read by
+//! [ShardedProvingKey::open]. A shard is only used if its commitment is the preprocessed
+//! commitment of the AIR in the manifest, so that shards of another key are rejected.
+
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use openvm_stark_backend::{
+    config::{Com, PcsProverData, StarkGenericConfig, Val},
+    engine::StarkEngine,
+    keygen::types::{
+        MultiStarkProvingKey, MultiStarkVerifyingKey, ProverOnlySinglePreprocessedData,
+    },
+    p3_field::PrimeField64,
+    proof::Proof,
+    prover::types::ProofInput,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::key_serde::{KeySerde, KeySerdeError};
+
+/// Name of the file of a sharded key with everything but the preprocessed data.
+pub const MANIFEST_FILE: &str = "manifest.bin";
+
+/// Path of the shard with the preprocessed data of the AIR with id `air_id`.
+pub fn shard_path(dir: &Path, air_id: usize) -> PathBuf {
+    dir.join(format!("air_{air_id}.bin"))
+}
+
+/// An error writing or reading a sharded key.
+#[derive(Debug)]
+pub enum ShardedKeyError {
+    Io(io::Error),
+    Manifest(KeySerdeError),
+    /// The shard of an AIR needed by a proof does not exist.
+    MissingShard {
+        air_id: usize,
+        air_name: String,
+        path: PathBuf,
+    },
+    /// The shard of an AIR failed to decode.
+    InvalidShard {
+        air_id: usize,
+        err: String,
+    },
+    /// The shard of an AIR is not for the preprocessed commitment of the AIR in the manifest,
+    /// e.g. it is the shard of another key.
+    ShardCommitmentMismatch {
+        air_id: usize,
+        air_name: String,
+    },
+    /// An AIR id is not an AIR of the key.
+    UnknownAir {
+        air_id: usize,
+        num_airs: usize,
+    },
+}
+
+impl fmt::Display for ShardedKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "{err}"),
+            Self::Manifest(err) => write!(f, "invalid manifest: {err}"),
+            Self::MissingShard {
+                air_id,
+                air_name,
+                path,
+            } => write!(
+                f,
+                "missing shard {} of AIR {air_name} (air_id = {air_id})",
+                path.display()
+            ),
+            Self::InvalidShard { air_id, err } => {
+                write!(f, "invalid shard of AIR {air_id}: {err}")
+            }
+            Self::ShardCommitmentMismatch { air_id, air_name } => write!(
+                f,
+                "shard of AIR {air_name} (air_id = {air_id}) is not for the preprocessed \
+                 commitment of the key"
+            ),
+            Self::UnknownAir { air_id, num_airs } => {
+                write!(f, "AIR id {air_id}, but the key has {num_airs} AIRs")
+            }
+        }
+    }
+}
+
+impl Error for ShardedKeyError {}
+
+impl From<io::Error> for ShardedKeyError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// A proving key read from a sharded key directory. The preprocessed data of each AIR is read
+/// from its shard the first time a proof uses the AIR, and kept afterwards.
+pub struct ShardedProvingKey<SC: StarkGenericConfig> {
+    dir: PathBuf,
+    /// The proving key without the preprocessed data.
+    pk: MultiStarkProvingKey<SC>,
+    /// Preprocessed data of the AIRs read so far, by AIR id.
+    loaded: Mutex<HashMap<usize, ProverOnlySinglePreprocessedData<SC>>>,
+}
+
+impl<SC: StarkGenericConfig> ShardedProvingKey<SC>
+where
+    Val<SC>: PrimeField64,
+    Com<SC>: PartialEq,
+    PcsProverData<SC>: Serialize + DeserializeOwned,
+    MultiStarkProvingKey<SC>: KeySerde,
+{
+    /// Writes `pk` as a sharded key in `dir`, which is created if needed.
+    pub fn write(
+        pk: &MultiStarkProvingKey<SC>,
+        dir: impl AsRef<Path>,
+    ) -> Result<(), ShardedKeyError> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        let mut manifest = pk.clone();
+        for (air_id, air_pk) in manifest.per_air.iter_mut().enumerate() {
+            if let Some(data) = air_pk.preprocessed_data.take() {
+                let commit = &air_pk
+                    .vk
+                    .preprocessed_data
+                    .as_ref()
+                    .expect("preprocessed data without commitment")
+                    .commit;
+                let bytes =
+                    bincode::serialize(&(commit, &data)).expect("failed to serialize shard");
+                fs::write(shard_path(dir, air_id), bytes)?;
+            }
+        }
+        fs::write(dir.join(MANIFEST_FILE), manifest.to_bytes())?;
+        Ok(())
+    }
+
+    /// Opens the sharded key in `dir`, reading only its manifest.
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self, ShardedKeyError> {
+        let dir = dir.as_ref().to_path_buf();
+        let bytes = fs::read(dir.join(MANIFEST_FILE))?;
+        let pk = MultiStarkProvingKey::from_bytes(&bytes).map_err(ShardedKeyError::Manifest)?;
+        Ok(Self {
+            dir,
+            pk,
+            loaded: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn get_vk(&self) -> MultiStarkVerifyingKey<SC> {
+        self.pk.get_vk()
+    }
+
+    /// Returns the proving key with the preprocessed data of the AIRs `air_ids`, reading the
+    /// shards not read yet. The other AIRs have no preprocessed data, so the key must only be
+    /// used for proofs of a subset of `air_ids`.
+    pub fn load(&self, air_ids: &[usize]) -> Result<MultiStarkProvingKey<SC>, ShardedKeyError> {
+        let num_airs = self.pk.per_air.len();
+        if let Some(&air_id) = air_ids.iter().find(|&&air_id| air_id >= num_airs) {
+            return Err(ShardedKeyError::UnknownAir { air_id, num_airs });
+        }
+        let mut pk = self.pk.clone();
+        let mut loaded = self.loaded.lock().unwrap();
+        for &air_id in air_ids {
+            if pk.per_air[air_id].vk.preprocessed_data.is_none() {
+                continue;
+            }
+            if !loaded.contains_key(&air_id) {
+                let data = self.read_shard(air_id)?;
+                loaded.insert(air_id, data);
+            }
+            pk.per_air[air_id].preprocessed_data = Some(loaded[&air_id].clone());
+        }
+        Ok(pk)
+    }
+
+    /// Same as [prove](StarkEngine::prove) with the full proving key, but only reads the shards
+    /// of the AIRs of `proof_input`.
+    pub fn prove(
+        &self,
+        engine: &impl StarkEngine<SC>,
+        proof_input: ProofInput<SC>,
+    ) -> Result<Proof<SC>, ShardedKeyError> {
+        let air_ids = proof_input
+            .per_air
+            .iter()
+            .map(|(air_id, _)| *air_id)
+            .collect::<Vec<_>>();
+        let pk = self.load(&air_ids)?;
+        Ok(engine.prove(&pk, proof_input))
+    }
+
+    /// Reads the shard of the AIR with id `air_id`, which has a preprocessed commitment.
+    fn read_shard(
+        &self,
+        air_id: usize,
+    ) -> Result<ProverOnlySinglePreprocessedData<SC>, ShardedKeyError> {
+        let air_pk = &self.pk.per_air[air_id];
+        let path = shard_path(&self.dir, air_id);
+        let bytes = match fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                return Err(ShardedKeyError::MissingShard {
+                    air_id,
+                    air_name: air_pk.air_name.clone(),
+                    path,
+                });
+            }
+            Err(err) => return Err(err.into()),
+        };
+        let (commit, data): (Com<SC>, ProverOnlySinglePreprocessedData<SC>) =
+            bincode::deserialize(&bytes).map_err(|err| ShardedKeyError::InvalidShard {
+                air_id,
+                err: err.to_string(),
+            })?;
+        if air_pk
+            .vk
+            .preprocessed_data
+            .as_ref()
+            .map(|data| &data.commit)
+            != Some(&commit)
+        {
+            return Err(ShardedKeyError::ShardCommitmentMismatch {
+                air_id,
+                air_name: air_pk.air_name.clone(),
+            });
+        }
+        Ok(data)
+    }
+}
@@ -0,0 +1,336 @@
+//! Byte accounting of proofs serialized by [ProofSerde::to_bytes], to find where the bytes of a
+//! proof go.
+//!
+//! [ProofSerde::to_bytes]: crate::proof_serde::ProofSerde::to_bytes
+
+use std::{cmp::Reverse, fmt};
+
+use openvm_stark_backend::{
+    config::{Com, PcsProof, RapPhaseSeqPartialProof, StarkGenericConfig, Val},
+    p3_commit::Mmcs,
+    p3_field::{ExtensionField, PrimeField64},
+    proof::Proof,
+};
+use p3_fri::{BatchOpening, FriProof};
+
+use crate::{
+    key_serde::{config_name, ConfigId},
+    proof_serde::{
+        field_len, varint_len, write_adjacent, write_adjacent_list, write_commitments,
+        write_ext_fields, write_fields, CanonicalCommitment, CanonicalProofPart, PROOF_MAGIC,
+    },
+};
+
+/// Bytes of each section of a serialized proof, see [ProofSizeReport].
+pub trait SizeReport {
+    fn size_report(&self) -> ProofSizeReport;
+}
+
+/// The breakdown of the PCS proof of a config by [SizeReport].
+pub trait FriProofSizes {
+    fn fri_size_report(&self) -> FriProofSizeReport;
+}
+
+/// A commitment whose opened values are accounted for in [ProofSizeReport::opened_values].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OpenedValuesRound {
+    /// The preprocessed trace with this index among the AIRs of the proof with one.
+    Preprocessed(usize),
+    /// The main trace commitment with this index: the cached main traces, then the common main
+    /// traces.
+    Main(usize),
+    AfterChallenge {
+        phase: usize,
+    },
+    /// The custom round with this index among the custom rounds of the proof.
+    Custom(usize),
+}
+
+impl fmt::Display for OpenedValuesRound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Preprocessed(index) => write!(f, "preprocessed trace {index}"),
+            Self::Main(index) => write!(f, "main commitment {index}"),
+            Self::AfterChallenge { phase } => write!(f, "challenge phase {phase}"),
+            Self::Custom(index) => write!(f, "custom round {index}"),
+        }
+    }
+}
+
+/// Bytes of the FRI proof of a serialized proof.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FriProofSizeReport {
+    /// The commitments of the commit phase, one per fold.
+    pub commit_phase_commitments: usize,
+    /// The opened rows, sibling values and Merkle paths of each query.
+    pub query_proofs: Vec<usize>,
+    pub final_poly: usize,
+    pub pow_witness: usize,
+}
+
+impl FriProofSizeReport {
+    /// The length of the encoding of the list of query proofs.
+    pub fn query_proofs_len(&self) -> usize {
+        varint_len(self.query_proofs.len()) + self.query_proofs.iter().sum::<usize>()
+    }
+
+    /// The length of the encoding of the FRI proof.
+    pub fn total(&self) -> usize {
+        self.commit_phase_commitments + self.query_proofs_len() + self.final_poly + self.pow_witness
+    }
+}
+
+/// Bytes of each section of a proof serialized by
+/// [ProofSerde::to_bytes](crate::proof_serde::ProofSerde::to_bytes), see [SizeReport]. The
+/// fields are those of [ProofSizeEstimate](crate::cost_estimate::ProofSizeEstimate), measured on
+/// the proof instead of estimated from its verifying key.
+///
+/// Displays as a table of the sections, largest first.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProofSizeReport {
+    /// The header, with the name of the config.
+    pub header: usize,
+    /// The length prefixes of the sections of the proof, and of the lists of commitments of the
+    /// opened values.
+    pub framing: usize,
+    pub commitments: usize,
+    /// The ids and trace heights of the AIRs.
+    pub airs: usize,
+    pub public_values: usize,
+    pub exposed_values: usize,
+    /// The opened values of each trace commitment, in the order of the proof.
+    pub opened_values: Vec<(OpenedValuesRound, usize)>,
+    /// The opened values of the quotient chunks of all the AIRs.
+    pub quotient_opened_values: usize,
+    /// The partial proof of the challenge phase.
+    pub challenge_phase_proof: usize,
+    pub absent_air_ids: usize,
+    pub fri: FriProofSizeReport,
+}
+
+impl ProofSizeReport {
+    /// The length of the serialized proof.
+    pub fn total(&self) -> usize {
+        self.rows().iter().map(|(_, len)| len).sum()
+    }
+
+    /// The sections of the proof with their length, in the order of the proof.
+    pub fn rows(&self) -> Vec<(String, usize)> {
+        let mut rows = vec![
+            ("header".to_string(), self.header),
+            ("framing".to_string(), self.framing),
+            ("commitments".to_string(), self.commitments),
+            ("AIR ids and heights".to_string(), self.airs),
+            ("public values".to_string(), self.public_values),
+            ("exposed values".to_string(), self.exposed_values),
+        ];
+        rows.extend(
+            self.opened_values
+                .iter()
+                .map(|(round, len)| (format!("opened values: {round}"), *len)),
+        );
+        rows.extend([
+            (
+                "opened values: quotient".to_string(),
+                self.quotient_opened_values,
+            ),
+            (
+                "challenge phase proof".to_string(),
+                self.challenge_phase_proof,
+            ),
+            ("absent AIR ids".to_string(), self.absent_air_ids),
+            (
+                "FRI commit phase commitments".to_string(),
+                self.fri.commit_phase_commitments,
+            ),
+            (
+                format!("FRI query proofs ({} queries)", self.fri.query_proofs.len()),
+                self.fri.query_proofs_len(),
+            ),
+            ("FRI final polynomial".to_string(), self.fri.final_poly),
+            (
+                "FRI proof of work witness".to_string(),
+                self.fri.pow_witness,
+            ),
+        ]);
+        rows
+    }
+}
+
+impl fmt::Display for ProofSizeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut rows = self.rows();
+        rows.sort_by_key(|(_, len)| Reverse(*len));
+        let total = self.total();
+        let width = rows.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+        writeln!(f, "{:<width$} {:>10} {:>7}", "section", "bytes", "%")?;
+        for (name, len) in rows {
+            let percent = 100.0 * len as f64 / total.max(1) as f64;
+            writeln!(f, "{name:<width$} {len:>10} {percent:>6.2}%")?;
+        }
+        write!(f, "{:<width$} {total:>10} {:>6.2}%", "total", 100.0)
+    }
+}
+
+impl<SC: StarkGenericConfig + ConfigId> SizeReport for Proof<SC>
+where
+    Val<SC>: PrimeField64,
+    Com<SC>: CanonicalCommitment,
+    PcsProof<SC>: CanonicalProofPart<Val<SC>>,
+    RapPhaseSeqPartialProof<SC>: CanonicalProofPart<Val<SC>>,
+    PcsProof<SC>: FriProofSizes,
+{
+    fn size_report(&self) -> ProofSizeReport {
+        let commitments = encoded_len(|out| write_commitments(out, &self.commitments));
+        let airs = varint_len(self.core.per_air.len())
+            + self
+                .core
+                .per_air
+                .iter()
+                .map(|air_proof| varint_len(air_proof.air_id) + varint_len(air_proof.degree))
+                .sum::<usize>();
+        let public_values = self
+            .core
+            .per_air
+            .iter()
+            .map(|air_proof| encoded_len(|out| write_fields(out, &air_proof.public_values)))
+            .sum();
+        let exposed_values = self
+            .core
+            .per_air
+            .iter()
+            .map(|air_proof| {
+                let exposed_values = &air_proof.exposed_values_after_challenge;
+                varint_len(exposed_values.len())
+                    + exposed_values
+                        .iter()
+                        .map(|values| encoded_len(|out| write_ext_fields::<SC>(out, values)))
+                        .sum::<usize>()
+            })
+            .sum();
+
+        let values = &self.core.opened_values;
+        let mut opened_values = Vec::new();
+        for (index, values) in values.preprocessed.iter().enumerate() {
+            let len = encoded_len(|out| write_adjacent::<SC>(out, values));
+            opened_values.push((OpenedValuesRound::Preprocessed(index), len));
+        }
+        let rounds: [(_, fn(usize) -> OpenedValuesRound); 3] = [
+            (&values.main, OpenedValuesRound::Main),
+            (&values.after_challenge, |phase| {
+                OpenedValuesRound::AfterChallenge { phase }
+            }),
+            (&values.custom, OpenedValuesRound::Custom),
+        ];
+        for (commitments, round) in rounds {
+            for (index, values) in commitments.iter().enumerate() {
+                let len = encoded_len(|out| write_adjacent_list::<SC>(out, values));
+                opened_values.push((round(index), len));
+            }
+        }
+        let quotient_opened_values = varint_len(values.quotient.len())
+            + values
+                .quotient
+                .iter()
+                .map(|chunks| {
+                    varint_len(chunks.len())
+                        + chunks
+                            .iter()
+                            .map(|chunk| encoded_len(|out| write_ext_fields::<SC>(out, chunk)))
+                            .sum::<usize>()
+                })
+                .sum::<usize>();
+
+        let challenge_phase_proof = encoded_len(|out| {
+            CanonicalProofPart::<Val<SC>>::write_canonical(&self.rap_phase_seq_proof, out)
+        });
+        let absent_air_ids = varint_len(self.core.absent_air_ids.len())
+            + self
+                .core
+                .absent_air_ids
+                .iter()
+                .map(|&air_id| varint_len(air_id))
+                .sum::<usize>();
+        let fri = self.pcs_proof.fri_size_report();
+
+        let opened_values_lists = [
+            values.preprocessed.len(),
+            values.main.len(),
+            values.after_challenge.len(),
+            values.custom.len(),
+        ]
+        .into_iter()
+        .map(varint_len)
+        .sum::<usize>();
+        let opened_values_len = opened_values_lists
+            + opened_values.iter().map(|(_, len)| len).sum::<usize>()
+            + quotient_opened_values;
+        let framing = opened_values_lists
+            + [
+                commitments,
+                airs,
+                public_values,
+                exposed_values,
+                opened_values_len,
+                challenge_phase_proof,
+                absent_air_ids,
+                fri.total(),
+            ]
+            .into_iter()
+            .map(varint_len)
+            .sum::<usize>();
+
+        ProofSizeReport {
+            header: PROOF_MAGIC.len() + 8 + config_name::<SC>().len(),
+            framing,
+            commitments,
+            airs,
+            public_values,
+            exposed_values,
+            opened_values,
+            quotient_opened_values,
+            challenge_phase_proof,
+            absent_air_ids,
+            fri,
+        }
+    }
+}
+
+impl<F: PrimeField64, EF, FriMmcs, InputMmcs> FriProofSizes
+    for FriProof<EF, FriMmcs, F, Vec<BatchOpening<F, InputMmcs>>>
+where
+    EF: ExtensionField<F>,
+    FriMmcs: Mmcs<EF>,
+    FriMmcs::Commitment: CanonicalCommitment,
+    FriMmcs::Proof: CanonicalProofPart<F>,
+    InputMmcs: Mmcs<F>,
+    InputMmcs::Proof: CanonicalProofPart<F>,
+{
+    fn fri_size_report(&self) -> FriProofSizeReport {
+        FriProofSizeReport {
+            commit_phase_commitments: varint_len(self.commit_phase_commits.len())
+                + self
+                    .commit_phase_commits
+                    .iter()
+                    .map(|commit| encoded_len(|out| commit.write_canonical(out)))
+                    .sum::<usize>(),
+            query_proofs: self
+                .query_proofs
+                .iter()
+                .map(|query_proof| {
+                    encoded_len(|out| CanonicalProofPart::<F>::write_canonical(query_proof, out))
+                })
+                .collect(),
+            final_poly: varint_len(self.final_poly.len())
+                + self.final_poly.len() * EF::D * field_len::<F>(),
+            pow_witness: field_len::<F>(),
+        }
+    }
+}
+
+/// Number of bytes written by `write`.
+fn encoded_len(write: impl FnOnce(&mut Vec<u8>)) -> usize {
+    let mut out = vec![];
+    write(&mut out);
+    out.len()
+}
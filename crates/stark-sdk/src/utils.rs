@@ -1,13 +1,29 @@
-use std::{cmp::Reverse, iter::zip};
+use std::{cmp::Reverse, env, fs, iter::zip, path::Path};
 
 use itertools::Itertools;
 use openvm_stark_backend::{
-    config::StarkGenericConfig, p3_field::FieldAlgebra, p3_matrix::Matrix,
-    prover::types::AirProofInput, verifier::VerificationError, AirRef,
+    config::{Com, PcsProof, RapPhaseSeqPartialProof, StarkGenericConfig, Val},
+    keygen::types::MultiStarkVerifyingKey,
+    p3_field::{FieldAlgebra, PrimeField64},
+    p3_matrix::Matrix,
+    proof::Proof,
+    prover::types::AirProofInput,
+    verifier::VerificationError,
+    AirRef,
 };
 use rand::{rngs::StdRng, Rng, SeedableRng};
 
-use crate::engine::{StarkFriEngine, VerificationDataWithFriParams};
+use crate::{
+    config::FriParameters,
+    engine::{StarkFriEngine, VerificationDataWithFriParams},
+    key_serde::ConfigId,
+    proof_bundle::ProofBundle,
+    proof_serde::CanonicalCommitment,
+    CanonicalProofPart,
+};
+
+/// Set to write the golden proof bundles of [open_golden_bundle] when they do not exist yet.
+pub const WRITE_GOLDEN_BUNDLES_VAR: &str = "WRITE_GOLDEN_BUNDLES";
 
 /// `stark-backend::prover::types::ProofInput` without specifying AIR IDs.
 pub struct ProofInputForTest<SC: StarkGenericConfig> {
@@ -19,7 +35,10 @@ impl<SC: StarkGenericConfig> ProofInputForTest<SC> {
     pub fn run_test(
         self,
         engine: &impl StarkFriEngine<SC>,
-    ) -> Result<VerificationDataWithFriParams<SC>, VerificationError> {
+    ) -> Result<VerificationDataWithFriParams<SC>, VerificationError>
+    where
+        Val<SC>: PrimeField64,
+    {
         assert_eq!(self.airs.len(), self.per_air.len());
         engine.run_test(self.airs, self.per_air)
     }
@@ -79,6 +98,42 @@ pub fn to_field_vec<F: FieldAlgebra>(v: Vec<u32>) -> Vec<F> {
     v.into_iter().map(F::from_canonical_u32).collect()
 }
 
+/// Opens the golden proof bundle at `path` for `vk` with an engine `E` with `fri_params`, see
+/// [ProofBundle::open]. When the file does not exist and [WRITE_GOLDEN_BUNDLES_VAR] is set, the
+/// proof of `prove` is first sealed there with `fri_params`.
+///
+/// A bundle records the digest of its verifying key, so that a golden file gone stale after a
+/// change of the key fails with a digest mismatch rather than with a verification error.
+pub fn open_golden_bundle<SC, E>(
+    path: &Path,
+    vk: &MultiStarkVerifyingKey<SC>,
+    fri_params: FriParameters,
+    prove: impl FnOnce() -> Proof<SC>,
+) -> Proof<SC>
+where
+    SC: StarkGenericConfig + ConfigId,
+    E: StarkFriEngine<SC>,
+    Val<SC>: PrimeField64,
+    Com<SC>: CanonicalCommitment,
+    PcsProof<SC>: CanonicalProofPart<Val<SC>>,
+    RapPhaseSeqPartialProof<SC>: CanonicalProofPart<Val<SC>>,
+{
+    if env::var(WRITE_GOLDEN_BUNDLES_VAR).is_ok() && !path.exists() {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).unwrap();
+        }
+        fs::write(path, ProofBundle::seal::<E>(&prove(), vk, fri_params)).unwrap();
+    }
+    let bytes = fs::read(path).unwrap_or_else(|err| {
+        panic!(
+            "failed to read golden bundle {}: {err}; set {WRITE_GOLDEN_BUNDLES_VAR} to write it",
+            path.display()
+        )
+    });
+    ProofBundle::<SC>::open::<E>(&bytes, vk, fri_params)
+        .unwrap_or_else(|err| panic!("failed to open golden bundle {}: {err}", path.display()))
+}
+
 /// A macro to create a `Vec<Arc<dyn AnyRap<_>>>` from a list of AIRs because Rust cannot infer the
 /// type correctly when using `vec!`.
 #[macro_export]
@@ -97,5 +152,6 @@ macro_rules! assert_sc_compatible_with_serde {
         static_assertions::assert_impl_all!(openvm_stark_backend::keygen::types::MultiStarkProvingKey<$sc>: serde::Serialize, serde::de::DeserializeOwned);
         static_assertions::assert_impl_all!(openvm_stark_backend::keygen::types::MultiStarkVerifyingKey<$sc>: serde::Serialize, serde::de::DeserializeOwned);
         static_assertions::assert_impl_all!(openvm_stark_backend::proof::Proof<$sc>: serde::Serialize, serde::de::DeserializeOwned);
+        static_assertions::assert_impl_all!(openvm_stark_backend::proof::Proof<$sc>: $crate::proof_serde::ProofSerde);
     };
 }
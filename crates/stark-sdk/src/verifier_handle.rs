@@ -0,0 +1,113 @@
+//! Verifying against a verifying key pinned by its digest, for verifiers configured with the
+//! digest only that load the key itself from untrusted storage.
+
+use std::{error::Error, fmt};
+
+use openvm_stark_backend::{
+    config::{Com, StarkGenericConfig, Val},
+    keygen::{digest::VK_DIGEST_WIDTH, types::MultiStarkVerifyingKey},
+    p3_field::PrimeField64,
+    proof::Proof,
+    verifier::VerificationError,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    engine::StarkFriEngine,
+    key_serde::{ConfigId, KeySerde, KeySerdeError},
+};
+
+/// Digest of a verifying key with the FRI parameters of an engine, see
+/// [StarkFriEngine::vk_digest].
+pub type VkDigest<SC> = [Val<SC>; VK_DIGEST_WIDTH];
+
+/// An error verifying a proof against a pinned verifying key.
+#[derive(Debug, PartialEq, Eq)]
+pub enum PinnedVkError<F> {
+    /// The serialized verifying key failed to decode, see [KeySerde::from_bytes].
+    Key(KeySerdeError),
+    /// The verifying key does not have the pinned digest.
+    DigestMismatch {
+        expected: [F; VK_DIGEST_WIDTH],
+        found: [F; VK_DIGEST_WIDTH],
+    },
+    /// The proof does not verify against the verifying key.
+    Verification(VerificationError),
+}
+
+impl<F: fmt::Debug> fmt::Display for PinnedVkError<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Key(err) => write!(f, "{err}"),
+            Self::DigestMismatch { expected, found } => write!(
+                f,
+                "verifying key has digest {found:?}, expected the pinned digest {expected:?}"
+            ),
+            Self::Verification(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl<F: fmt::Debug> Error for PinnedVkError<F> {}
+
+impl<F> From<KeySerdeError> for PinnedVkError<F> {
+    fn from(err: KeySerdeError) -> Self {
+        Self::Key(err)
+    }
+}
+
+impl<F> From<VerificationError> for PinnedVkError<F> {
+    fn from(err: VerificationError) -> Self {
+        Self::Verification(err)
+    }
+}
+
+/// A verifying key whose digest was checked against a pinned digest, to verify any number of
+/// proofs with the engine it was checked with.
+pub struct VerifierHandle<'a, SC: StarkGenericConfig, E> {
+    engine: &'a E,
+    vk: MultiStarkVerifyingKey<SC>,
+    digest: VkDigest<SC>,
+}
+
+impl<'a, SC, E> VerifierHandle<'a, SC, E>
+where
+    SC: StarkGenericConfig + ConfigId,
+    E: StarkFriEngine<SC>,
+    Val<SC>: PrimeField64,
+    Com<SC>: Serialize + DeserializeOwned,
+{
+    /// Decodes the verifying key serialized in `vk_bytes` by [KeySerde::to_bytes], and checks
+    /// that its digest with the FRI parameters of `engine` is `expected`. The key is only kept if
+    /// it has the pinned digest.
+    pub fn from_digest_and_bytes(
+        engine: &'a E,
+        expected: &VkDigest<SC>,
+        vk_bytes: &[u8],
+    ) -> Result<Self, PinnedVkError<Val<SC>>> {
+        let vk = MultiStarkVerifyingKey::<SC>::from_bytes(vk_bytes)?;
+        let digest = engine.vk_digest(&vk);
+        if digest != *expected {
+            return Err(PinnedVkError::DigestMismatch {
+                expected: *expected,
+                found: digest,
+            });
+        }
+        Ok(Self { engine, vk, digest })
+    }
+
+    pub fn vk(&self) -> &MultiStarkVerifyingKey<SC> {
+        &self.vk
+    }
+
+    pub fn digest(&self) -> &VkDigest<SC> {
+        &self.digest
+    }
+
+    /// Verifies `proof` against the pinned verifying key, see [StarkEngine::verify].
+    ///
+    /// [StarkEngine::verify]: openvm_stark_backend::engine::StarkEngine::verify
+    pub fn verify(&self, proof: &Proof<SC>) -> Result<(), VerificationError> {
+        self.engine.verify(&self.vk, proof)
+    }
+}
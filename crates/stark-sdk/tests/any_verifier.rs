@@ -0,0 +1,148 @@
+use openvm_stark_backend::{p3_field::FieldAlgebra, p3_matrix::dense::RowMajorMatrix};
+use openvm_stark_sdk::{
+    any_rap_arc_vec,
+    any_verifier::{AnyVerifier, AnyVerifierError},
+    config::{
+        baby_bear_keccak::{BabyBearKeccakConfig, BabyBearKeccakEngine},
+        baby_bear_poseidon2::{self, BabyBearPoseidon2Config, BabyBearPoseidon2Engine},
+        FriParameters,
+    },
+    dummy_airs::fib_air::{air::FibonacciAir, trace::generate_trace_rows},
+    engine::StarkFriEngine,
+    key_serde::{config_name, ConfigId, KeySerde, KeySerdeError},
+    proof_serde::{ProofSerde, ProofSerdeError},
+};
+use p3_baby_bear::BabyBear;
+
+const N: usize = 16;
+
+fn fib_inputs() -> (Vec<RowMajorMatrix<BabyBear>>, Vec<Vec<BabyBear>>) {
+    let trace = generate_trace_rows::<BabyBear>(0, 1, N);
+    let last = trace.values[trace.values.len() - 1];
+    (vec![trace], vec![vec![BabyBear::ZERO, BabyBear::ONE, last]])
+}
+
+/// The serialized verifying key and proof of a Fibonacci AIR.
+fn poseidon2_bytes() -> (Vec<u8>, Vec<u8>) {
+    let (traces, pis) = fib_inputs();
+    let data =
+        BabyBearPoseidon2Engine::run_simple_test_fast(any_rap_arc_vec![FibonacciAir], traces, pis)
+            .unwrap()
+            .data;
+    (data.vk.to_bytes(), data.proof.to_bytes())
+}
+
+fn keccak_bytes(fri_params: FriParameters) -> (Vec<u8>, Vec<u8>) {
+    let (traces, pis) = fib_inputs();
+    let data = BabyBearKeccakEngine::new(fri_params)
+        .run_simple_test_impl(any_rap_arc_vec![FibonacciAir], traces, pis)
+        .unwrap()
+        .data;
+    (data.vk.to_bytes(), data.proof.to_bytes())
+}
+
+#[test]
+fn test_any_verifier_dispatches_on_config() {
+    let verifier = AnyVerifier::with_default_configs(|_| FriParameters::standard_fast());
+    let (poseidon2_vk, poseidon2_proof) = poseidon2_bytes();
+    let (keccak_vk, keccak_proof) = keccak_bytes(FriParameters::standard_fast());
+    verifier
+        .verify(&poseidon2_vk, &poseidon2_proof)
+        .expect("Verification failed");
+    verifier
+        .verify(&keccak_vk, &keccak_proof)
+        .expect("Verification failed");
+
+    // The key must be for the config of the proof.
+    assert_eq!(
+        verifier.verify(&keccak_vk, &poseidon2_proof),
+        Err(AnyVerifierError::Key(KeySerdeError::ConfigMismatch {
+            expected: config_name::<BabyBearPoseidon2Config>().to_string(),
+            found: config_name::<BabyBearKeccakConfig>().to_string(),
+        }))
+    );
+    // A verifier only verifies the proofs of its registered configs.
+    let mut poseidon2_only = AnyVerifier::new();
+    poseidon2_only.register::<BabyBearPoseidon2Config>(baby_bear_poseidon2::default_engine());
+    assert_eq!(
+        poseidon2_only.configs().collect::<Vec<_>>(),
+        [config_name::<BabyBearPoseidon2Config>()]
+    );
+    poseidon2_only
+        .verify(&poseidon2_vk, &poseidon2_proof)
+        .expect("Verification failed");
+    assert_eq!(
+        poseidon2_only.verify(&keccak_vk, &keccak_proof),
+        Err(AnyVerifierError::UnknownConfig(
+            config_name::<BabyBearKeccakConfig>().to_string()
+        ))
+    );
+}
+
+#[test]
+fn test_any_verifier_per_config_fri_params() {
+    let keccak_params = FriParameters::standard_with_100_bits_conjectured_security(2);
+    let verifier = AnyVerifier::with_default_configs(|config| {
+        if config == BabyBearKeccakConfig::CONFIG_ID {
+            keccak_params
+        } else {
+            FriParameters::standard_fast()
+        }
+    });
+    let (poseidon2_vk, poseidon2_proof) = poseidon2_bytes();
+    let (keccak_vk, keccak_proof) = keccak_bytes(keccak_params);
+    verifier
+        .verify(&poseidon2_vk, &poseidon2_proof)
+        .expect("Verification failed");
+    verifier
+        .verify(&keccak_vk, &keccak_proof)
+        .expect("Verification failed");
+}
+
+#[test]
+fn test_any_verifier_rejects_tampered_config_tag() {
+    let verifier = AnyVerifier::with_default_configs(|_| FriParameters::standard_fast());
+    let (vk, proof) = poseidon2_bytes();
+    // The config name follows the magic, the version and its length.
+    let mut tampered = proof.clone();
+    tampered[16] ^= 1;
+    let mut tampered_config = config_name::<BabyBearPoseidon2Config>().as_bytes().to_vec();
+    tampered_config[0] ^= 1;
+    assert_eq!(
+        verifier.verify(&vk, &tampered),
+        Err(AnyVerifierError::UnknownConfig(
+            String::from_utf8(tampered_config).unwrap()
+        ))
+    );
+
+    // The proof tagged with the name of another registered config is decoded for that config,
+    // which the key is not for.
+    let keccak_name = config_name::<BabyBearKeccakConfig>();
+    let poseidon2_name = config_name::<BabyBearPoseidon2Config>();
+    let mut retagged = proof[..12].to_vec();
+    retagged.extend_from_slice(&(keccak_name.len() as u32).to_le_bytes());
+    retagged.extend_from_slice(keccak_name.as_bytes());
+    retagged.extend_from_slice(&proof[16 + poseidon2_name.len()..]);
+    assert_eq!(
+        verifier.verify(&vk, &retagged),
+        Err(AnyVerifierError::Key(KeySerdeError::ConfigMismatch {
+            expected: keccak_name.to_string(),
+            found: poseidon2_name.to_string(),
+        }))
+    );
+}
+
+#[test]
+fn test_any_verifier_strict_decoding() {
+    let (vk, proof) = poseidon2_bytes();
+    let mut padded = proof.clone();
+    padded.extend_from_slice(&[0; 3]);
+    let verifier = AnyVerifier::with_default_configs(|_| FriParameters::standard_fast());
+    assert_eq!(
+        verifier.verify(&vk, &padded),
+        Err(AnyVerifierError::Proof(ProofSerdeError::TrailingBytes(3)))
+    );
+    let lenient = verifier.with_lenient_decoding(true);
+    lenient.verify(&vk, &padded).expect("Verification failed");
+    lenient.verify(&vk, &proof).expect("Verification failed");
+}
@@ -0,0 +1,100 @@
+#![cfg(feature = "async")]
+
+use std::{sync::Arc, time::Duration};
+
+use openvm_stark_backend::{
+    engine::StarkEngine,
+    p3_field::FieldAlgebra,
+    prover::{
+        types::{AirProofInput, ProofInput},
+        ProvingError, ProvingPhase,
+    },
+};
+use openvm_stark_sdk::{
+    async_prover::{prove_async, spawn_prove},
+    config::baby_bear_poseidon2::{default_engine, BabyBearPoseidon2Config},
+    dummy_airs::fib_air::{air::FibonacciAir, trace::generate_trace_rows},
+};
+use p3_baby_bear::BabyBear;
+use tokio::time::timeout;
+
+const PHASE_TIMEOUT: Duration = Duration::from_secs(60);
+
+fn fib_input(a: u32, log_height: usize) -> ProofInput<BabyBearPoseidon2Config> {
+    let trace = generate_trace_rows::<BabyBear>(a, 1, 1 << log_height);
+    let last = trace.values[trace.values.len() - 1];
+    let pis = vec![BabyBear::from_canonical_u32(a), BabyBear::ONE, last];
+    ProofInput::new(vec![(0, AirProofInput::simple(trace, pis))])
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_prove_async() {
+    let engine = Arc::new(default_engine());
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(Arc::new(FibonacciAir));
+    let pk = Arc::new(keygen_builder.generate_pk());
+    let vk = pk.get_vk();
+
+    let mut task = spawn_prove(engine.clone(), pk.clone(), fib_input(0, 5));
+    let mut phases = vec![];
+    while let Some(phase) = timeout(PHASE_TIMEOUT, task.next_phase())
+        .await
+        .expect("phase timed out")
+    {
+        phases.push(phase);
+    }
+    assert_eq!(
+        phases,
+        [
+            ProvingPhase::MainTraceCommit,
+            ProvingPhase::AfterChallengeTrace,
+            ProvingPhase::Quotient,
+            ProvingPhase::Opening,
+        ]
+    );
+    let proof = task.proof().await.unwrap();
+    engine.verify(&vk, &proof).expect("Verification failed");
+    // Same proof as the synchronous path.
+    assert_eq!(
+        bincode::serialize(&proof).unwrap(),
+        bincode::serialize(&engine.prove(&pk, fib_input(0, 5))).unwrap()
+    );
+
+    // Several proofs in flight while the executor keeps running other tasks.
+    let proofs = futures_join(
+        (1..4).map(|a| prove_async(engine.clone(), pk.clone(), fib_input(a, 4 + a as usize))),
+    )
+    .await;
+    for proof in proofs {
+        engine
+            .verify(&vk, &proof.unwrap())
+            .expect("Verification failed");
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_prove_async_cancel() {
+    let engine = Arc::new(default_engine());
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(Arc::new(FibonacciAir));
+    let pk = Arc::new(keygen_builder.generate_pk());
+
+    // The flag is checked at the start of the first phase.
+    let task = spawn_prove(engine, pk, fib_input(0, 5));
+    task.cancel();
+    assert_eq!(task.proof().await.err(), Some(ProvingError::Cancelled));
+}
+
+/// Awaits the futures concurrently, each in its own task.
+async fn futures_join<F>(futures: impl Iterator<Item = F>) -> Vec<F::Output>
+where
+    F: std::future::Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    let handles = futures.map(tokio::spawn).collect::<Vec<_>>();
+    let mut outputs = vec![];
+    for handle in handles {
+        outputs.push(handle.await.unwrap());
+    }
+    outputs
+}
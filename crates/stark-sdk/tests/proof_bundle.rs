@@ -0,0 +1,144 @@
+use std::{path::Path, sync::Arc};
+
+use openvm_stark_backend::{p3_field::FieldAlgebra, proof::Proof};
+use openvm_stark_sdk::{
+    any_rap_arc_vec,
+    config::baby_bear_poseidon2::{BabyBearPoseidon2Config, BabyBearPoseidon2Engine},
+    dummy_airs::{
+        fib_air::{air::FibonacciAir, trace::generate_trace_rows},
+        interaction::dummy_interaction_air::DummyInteractionAir,
+    },
+    engine::{StarkEngine, StarkFriEngine, VerificationDataWithFriParams},
+    proof_bundle::{BundleError, ProofBundle, PROOF_BUNDLE_CHECKSUM_LEN, PROOF_BUNDLE_MAGIC},
+    proof_serde::ProofSerde,
+    utils::open_golden_bundle,
+};
+use p3_baby_bear::BabyBear;
+
+type SC = BabyBearPoseidon2Config;
+type Engine = BabyBearPoseidon2Engine;
+
+const N: usize = 16;
+
+fn fib_proof() -> VerificationDataWithFriParams<SC> {
+    let trace = generate_trace_rows::<BabyBear>(0, 1, N);
+    let pis = vec![BabyBear::ZERO, BabyBear::ONE, *trace.values.last().unwrap()];
+    Engine::run_simple_test_fast(any_rap_arc_vec![FibonacciAir], vec![trace], vec![pis]).unwrap()
+}
+
+#[test]
+fn test_proof_bundle_round_trip() {
+    let data = fib_proof();
+    let engine = Engine::new(data.fri_params);
+    let (vk, proof) = (data.data.vk, data.data.proof);
+    let bytes = ProofBundle::seal::<Engine>(&proof, &vk, data.fri_params);
+    assert_eq!(bytes[..PROOF_BUNDLE_MAGIC.len()], PROOF_BUNDLE_MAGIC);
+
+    let bundle = ProofBundle::<SC>::from_bytes(&bytes).unwrap();
+    assert_eq!(bundle.fri_params, data.fri_params);
+    assert_eq!(bundle.vk_digest, engine.vk_digest(&vk));
+    assert_eq!(
+        bundle.public_values,
+        vec![(0, proof.core.per_air[0].public_values.clone())]
+    );
+    assert_eq!(bundle.proof, proof.to_bytes());
+    assert_eq!(bundle.to_bytes(), bytes);
+
+    let opened = ProofBundle::<SC>::open::<Engine>(&bytes, &vk, data.fri_params).unwrap();
+    assert_eq!(opened.to_bytes(), proof.to_bytes());
+    engine.verify(&vk, &opened).expect("Verification failed");
+}
+
+#[test]
+fn test_proof_bundle_wrong_vk() {
+    let data = fib_proof();
+    let engine = Engine::new(data.fri_params);
+    let (vk, proof) = (data.data.vk, data.data.proof);
+    let bytes = ProofBundle::seal::<Engine>(&proof, &vk, data.fri_params);
+
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(Arc::new(DummyInteractionAir::new(1, true, 0)));
+    let other_vk = keygen_builder.generate_pk().get_vk();
+    assert_eq!(
+        ProofBundle::<SC>::open::<Engine>(&bytes, &other_vk, data.fri_params).err(),
+        Some(BundleError::DigestMismatch {
+            expected: engine.vk_digest(&other_vk),
+            found: engine.vk_digest(&vk),
+        })
+    );
+
+    // The digest is of the key with the FRI parameters of the bundle.
+    let mut other_params = data.fri_params;
+    other_params.num_queries += 1;
+    let bytes = ProofBundle::seal::<Engine>(&proof, &vk, other_params);
+    let bundle = ProofBundle::<SC>::from_bytes(&bytes).unwrap();
+    assert_eq!(bundle.fri_params, other_params);
+    assert_ne!(bundle.vk_digest, engine.vk_digest(&vk));
+    assert_eq!(bundle.vk_digest, Engine::new(other_params).vk_digest(&vk));
+}
+
+#[test]
+fn test_proof_bundle_wrong_fri_params() {
+    let data = fib_proof();
+    let (vk, proof) = (data.data.vk, data.data.proof);
+    // A bundle sealed with fewer queries than the verifier expects, with a digest matching them.
+    let mut fewer_queries = data.fri_params;
+    fewer_queries.num_queries -= 1;
+    let bytes = ProofBundle::seal::<Engine>(&proof, &vk, fewer_queries);
+    assert_eq!(
+        ProofBundle::<SC>::open::<Engine>(&bytes, &vk, data.fri_params).err(),
+        Some(BundleError::FriParamsMismatch {
+            expected: data.fri_params,
+            found: fewer_queries,
+        })
+    );
+}
+
+#[test]
+fn test_proof_bundle_rejects_bit_flips() {
+    let data = fib_proof();
+    let (vk, proof) = (data.data.vk, data.data.proof);
+    let bytes = ProofBundle::seal::<Engine>(&proof, &vk, data.fri_params);
+    let proof_start = bytes.len() - PROOF_BUNDLE_CHECKSUM_LEN - proof.to_bytes().len();
+
+    // A flip in the header, in the header of the proof, in its opening proof or in the checksum,
+    // which would otherwise fail to decode or decode into another proof.
+    for i in [
+        PROOF_BUNDLE_MAGIC.len() + 4,
+        proof_start,
+        bytes.len() - PROOF_BUNDLE_CHECKSUM_LEN - 1,
+        bytes.len() - 1,
+    ] {
+        let mut flipped = bytes.clone();
+        flipped[i] ^= 1;
+        assert_eq!(
+            ProofBundle::<SC>::from_bytes(&flipped).err(),
+            Some(BundleError::ChecksumMismatch),
+            "i = {i}"
+        );
+        assert_eq!(
+            ProofBundle::<SC>::open::<Engine>(&flipped, &vk, data.fri_params).err(),
+            Some(BundleError::ChecksumMismatch),
+            "i = {i}"
+        );
+    }
+    let mut wrong_magic = bytes.clone();
+    wrong_magic[0] ^= 1;
+    assert_eq!(
+        ProofBundle::<SC>::from_bytes(&wrong_magic).err(),
+        Some(BundleError::WrongMagic)
+    );
+}
+
+/// The golden bundle of a Fibonacci proof keeps opening and verifying against the key of the
+/// Fibonacci AIR.
+#[test]
+fn test_proof_bundle_golden() {
+    let data = fib_proof();
+    let engine = Engine::new(data.fri_params);
+    let vk = data.data.vk;
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/fib_bundle_v1.bin");
+    let proof: Proof<SC> =
+        open_golden_bundle::<SC, Engine>(&path, &vk, data.fri_params, || data.data.proof);
+    engine.verify(&vk, &proof).expect("Verification failed");
+}
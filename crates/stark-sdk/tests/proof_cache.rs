@@ -0,0 +1,131 @@
+use std::{fs, sync::Arc};
+
+use openvm_stark_backend::{
+    engine::StarkEngine,
+    keygen::types::MultiStarkProvingKey,
+    p3_field::FieldAlgebra,
+    prover::types::{AirProofInput, ProofInput},
+};
+use openvm_stark_sdk::{
+    config::{
+        baby_bear_poseidon2::{default_engine, BabyBearPoseidon2Config, BabyBearPoseidon2Engine},
+        FriParameters,
+    },
+    dummy_airs::fib_air::{air::FibonacciAir, trace::generate_trace_rows},
+    engine::StarkFriEngine,
+    proof_cache::{CacheStats, CachingProver},
+};
+use p3_baby_bear::BabyBear;
+
+type SC = BabyBearPoseidon2Config;
+
+fn fib_input(a: u32, log_height: usize) -> ProofInput<SC> {
+    let trace = generate_trace_rows::<BabyBear>(a, 1, 1 << log_height);
+    let last = trace.values[trace.values.len() - 1];
+    let pis = vec![BabyBear::from_canonical_u32(a), BabyBear::ONE, last];
+    ProofInput::new(vec![(0, AirProofInput::simple(trace, pis))])
+}
+
+fn fib_pk(engine: &BabyBearPoseidon2Engine) -> MultiStarkProvingKey<SC> {
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(Arc::new(FibonacciAir));
+    keygen_builder.generate_pk()
+}
+
+#[test]
+fn test_second_prove_is_cache_hit() {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = default_engine();
+    let pk = fib_pk(&engine);
+    let prover = CachingProver::new(&engine, dir.path()).unwrap();
+
+    let proof = prover.prove(&pk, fib_input(0, 5));
+    let cached = prover.prove(&pk, fib_input(0, 5));
+    assert_eq!(
+        prover.stats(),
+        CacheStats {
+            hits: 1,
+            misses: 1,
+            corrupt: 0,
+        }
+    );
+    engine
+        .verify(&pk.get_vk(), &cached)
+        .expect("Verification failed");
+    assert_eq!(
+        bincode::serialize(&cached).unwrap(),
+        bincode::serialize(&proof).unwrap()
+    );
+
+    // Another prover reads the same directory.
+    let prover = CachingProver::new(&engine, dir.path()).unwrap();
+    prover.prove(&pk, fib_input(0, 5));
+    prover.prove(&pk, fib_input(1, 5));
+    assert_eq!(
+        prover.stats(),
+        CacheStats {
+            hits: 1,
+            misses: 1,
+            corrupt: 0,
+        }
+    );
+}
+
+#[test]
+fn test_corrupt_entry_is_regenerated() {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = default_engine();
+    let pk = fib_pk(&engine);
+    let prover = CachingProver::new(&engine, dir.path()).unwrap();
+    prover.prove(&pk, fib_input(0, 5));
+
+    let path = prover.entry_path(&pk, &fib_input(0, 5));
+    let mut bytes = fs::read(&path).unwrap();
+    let last = bytes.len() - 1;
+    bytes[last] ^= 1;
+    fs::write(&path, &bytes).unwrap();
+    let proof = prover.prove(&pk, fib_input(0, 5));
+    engine
+        .verify(&pk.get_vk(), &proof)
+        .expect("Verification failed");
+
+    // A truncated entry.
+    fs::write(&path, &bytes[..16]).unwrap();
+    prover.prove(&pk, fib_input(0, 5));
+
+    // The entry was overwritten with the new proof.
+    prover.prove(&pk, fib_input(0, 5));
+    assert_eq!(
+        prover.stats(),
+        CacheStats {
+            hits: 1,
+            misses: 3,
+            corrupt: 2,
+        }
+    );
+}
+
+#[test]
+fn test_key_depends_on_vk_and_fri_params() {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = default_engine();
+    let pk = fib_pk(&engine);
+    let prover = CachingProver::new(&engine, dir.path()).unwrap();
+    let key = prover.cache_key(&pk, &fib_input(0, 5));
+    assert_eq!(prover.cache_key(&pk, &fib_input(0, 5)), key);
+    assert_ne!(prover.cache_key(&pk, &fib_input(0, 6)), key);
+
+    // Another key of the same AIR, with a maximum trace height.
+    let mut keygen_builder = engine.keygen_builder();
+    let air_id = keygen_builder.add_air(Arc::new(FibonacciAir));
+    keygen_builder.set_max_log_height(air_id, 10);
+    let other_pk = keygen_builder.generate_pk();
+    assert_ne!(prover.cache_key(&other_pk, &fib_input(0, 5)), key);
+
+    let other_engine = BabyBearPoseidon2Engine::new(FriParameters {
+        num_queries: engine.fri_params().num_queries + 1,
+        ..engine.fri_params()
+    });
+    let other_prover = CachingProver::new(&other_engine, dir.path()).unwrap();
+    assert_ne!(other_prover.cache_key(&pk, &fib_input(0, 5)), key);
+}
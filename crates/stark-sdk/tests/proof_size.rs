@@ -0,0 +1,179 @@
+use std::sync::Arc;
+
+use openvm_stark_backend::{
+    keygen::types::MultiStarkProvingKey,
+    proof::Proof,
+    prover::types::{AirProofInput, ProofInput},
+};
+use openvm_stark_sdk::{
+    config::{
+        baby_bear_keccak::BabyBearKeccakEngine,
+        baby_bear_poseidon2::{BabyBearPoseidon2Config, BabyBearPoseidon2Engine},
+        FriParameters,
+    },
+    cost_estimate::estimate_proof_size,
+    dummy_airs::{fib_air::air::FibonacciAir, fib_lookup},
+    engine::{StarkEngine, StarkFriEngine},
+    key_serde::config_name,
+    proof_serde::{CanonicalProofPart, ProofSerde},
+    size_report::{OpenedValuesRound, SizeReport},
+};
+use p3_baby_bear::BabyBear;
+use serde::Serialize;
+
+type SC = BabyBearPoseidon2Config;
+type Val = BabyBear;
+
+const N: usize = 16;
+
+fn bincode_size<T: Serialize>(value: &T) -> usize {
+    bincode::serialized_size(value).unwrap() as usize
+}
+
+fn canonical_size<T: CanonicalProofPart<Val>>(value: &T) -> usize {
+    let mut out = vec![];
+    value.write_canonical(&mut out);
+    out.len()
+}
+
+/// Checks that the estimate of the size of `proof` is its size, section by section.
+fn check_estimate(pk: &MultiStarkProvingKey<SC>, fri_params: FriParameters, proof: &Proof<SC>) {
+    let estimate = estimate_proof_size(&pk.get_vk(), fri_params, &proof.log_trace_heights());
+    assert_eq!(estimate.header, 16 + config_name::<SC>().len());
+    assert_eq!(estimate.commitments, bincode_size(&proof.commitments));
+    assert_eq!(
+        estimate.challenge_phase_proof,
+        canonical_size(&proof.rap_phase_seq_proof)
+    );
+    assert_eq!(estimate.fri.total(), canonical_size(&proof.pcs_proof));
+    assert_eq!(estimate.fri.num_queries, fri_params.num_queries);
+    assert_eq!(estimate.total(), proof.to_bytes().len());
+
+    let report = proof.size_report();
+    assert_eq!(report.total(), proof.to_bytes().len());
+    assert_eq!(
+        (
+            report.header,
+            report.framing,
+            report.commitments,
+            report.airs
+        ),
+        (
+            estimate.header,
+            estimate.framing,
+            estimate.commitments,
+            estimate.airs
+        )
+    );
+    assert_eq!(
+        report
+            .opened_values
+            .iter()
+            .map(|(_, len)| len)
+            .collect::<Vec<_>>(),
+        estimate
+            .opened_values
+            .iter()
+            .map(|(_, len)| len)
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(
+        report.quotient_opened_values,
+        estimate.quotient_opened_values
+    );
+    assert_eq!(report.fri.total(), estimate.fri.total());
+    assert_eq!(report.fri.query_proofs.len(), fri_params.num_queries);
+    for &len in &report.fri.query_proofs {
+        assert_eq!(
+            len,
+            estimate.fri.input_openings_per_query + estimate.fri.commit_phase_openings_per_query
+        );
+    }
+}
+
+#[test]
+fn test_proof_size_estimate_fibonacci() {
+    let fri_params = FriParameters::standard_fast();
+    let engine = BabyBearPoseidon2Engine::new(fri_params);
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(Arc::new(FibonacciAir));
+    let pk = keygen_builder.generate_pk();
+    let proof = engine.prove(&pk, ProofInput::new(vec![(0, fib_lookup::fib_input(N))]));
+    check_estimate(&pk, fri_params, &proof);
+}
+
+#[test]
+fn test_proof_size_estimate_byte_digests() {
+    // Digests of 32 bytes rather than 8 field elements.
+    let fri_params = FriParameters::standard_fast();
+    let engine = BabyBearKeccakEngine::new(fri_params);
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(Arc::new(FibonacciAir));
+    let pk = keygen_builder.generate_pk();
+    let proof = engine.prove(&pk, ProofInput::new(vec![(0, fib_lookup::fib_input(N))]));
+    let estimate = estimate_proof_size(&pk.get_vk(), fri_params, &proof.log_trace_heights());
+    assert_eq!(estimate.commitments, bincode_size(&proof.commitments));
+    assert_eq!(estimate.total(), proof.to_bytes().len());
+}
+
+#[test]
+fn test_proof_size_estimate_interactions() {
+    // A final polynomial of several coefficients, and AIRs of different heights.
+    let fri_params = FriParameters {
+        log_blowup: 2,
+        log_final_poly_len: 2,
+        num_queries: 10,
+        proof_of_work_bits: 0,
+    };
+    let engine = BabyBearPoseidon2Engine::new(fri_params);
+    let mut keygen_builder = engine.keygen_builder();
+    fib_lookup::add_airs(&mut keygen_builder);
+    let pk = keygen_builder.generate_pk();
+    let proof = engine.prove(
+        &pk,
+        ProofInput::new(vec![
+            (0, fib_lookup::fib_input(N)),
+            (1, AirProofInput::simple_no_pis(fib_lookup::messages())),
+            (
+                2,
+                AirProofInput::simple_no_pis(fib_lookup::padded_messages()),
+            ),
+        ]),
+    );
+    engine
+        .verify(&pk.get_vk(), &proof)
+        .expect("Verification failed");
+    check_estimate(&pk, fri_params, &proof);
+}
+
+#[test]
+fn test_proof_size_report_display() {
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(Arc::new(FibonacciAir));
+    let pk = keygen_builder.generate_pk();
+    let proof = engine.prove(&pk, ProofInput::new(vec![(0, fib_lookup::fib_input(N))]));
+    let report = proof.size_report();
+    assert_eq!(report.opened_values[0].0, OpenedValuesRound::Main(0));
+
+    // The header, the sections largest first, then the total.
+    let table = report.to_string();
+    let lines = table.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), report.rows().len() + 2);
+    assert!(lines[0].starts_with("section"));
+    let lens = lines[1..lines.len() - 1]
+        .iter()
+        .map(|line| {
+            line.split_whitespace()
+                .nth_back(1)
+                .unwrap()
+                .parse()
+                .unwrap()
+        })
+        .collect::<Vec<usize>>();
+    assert!(lens.windows(2).all(|pair| pair[0] >= pair[1]));
+    assert_eq!(lens.iter().sum::<usize>(), proof.to_bytes().len());
+    assert!(lines[1].starts_with("FRI query proofs"));
+    assert!(lines[lines.len() - 1].starts_with("total"));
+    assert!(lines[lines.len() - 1].ends_with(&format!("{} 100.00%", report.total())));
+}
@@ -0,0 +1,249 @@
+use std::{
+    io::{self, Cursor, Read, Write},
+    sync::mpsc::{sync_channel, Receiver, SyncSender},
+    thread,
+};
+
+use openvm_stark_backend::{p3_field::FieldAlgebra, proof::Proof};
+use openvm_stark_sdk::{
+    any_rap_arc_vec,
+    config::baby_bear_poseidon2::{BabyBearPoseidon2Config, BabyBearPoseidon2Engine},
+    dummy_airs::fib_air::{air::FibonacciAir, trace::generate_trace_rows},
+    engine::{StarkEngine, StarkFriEngine, VerificationDataWithFriParams},
+    key_serde::config_name,
+    proof_serde::{ProofSerde, ProofSerdeError, PROOF_MAGIC, SUPPORTED_PROOF_FORMAT_VERSIONS},
+    proof_stream::{verify_from_reader, ProofStream, StreamVerifyError},
+};
+use p3_baby_bear::BabyBear;
+
+type SC = BabyBearPoseidon2Config;
+
+const N: usize = 16;
+
+fn fib_proof() -> VerificationDataWithFriParams<SC> {
+    let trace = generate_trace_rows::<BabyBear>(0, 1, N);
+    let pis = vec![BabyBear::ZERO, BabyBear::ONE, *trace.values.last().unwrap()];
+    BabyBearPoseidon2Engine::run_simple_test_fast(
+        any_rap_arc_vec![FibonacciAir],
+        vec![trace],
+        vec![pis],
+    )
+    .unwrap()
+}
+
+/// The writing end of an in-process pipe, see [pipe].
+struct PipeWriter(SyncSender<Vec<u8>>);
+
+impl Write for PipeWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0
+            .send(buf.to_vec())
+            .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The reading end of an in-process pipe, see [pipe].
+struct PipeReader {
+    receiver: Receiver<Vec<u8>>,
+    chunk: Cursor<Vec<u8>>,
+}
+
+impl Read for PipeReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let len = self.chunk.read(buf)?;
+            if len != 0 || buf.is_empty() {
+                return Ok(len);
+            }
+            match self.receiver.recv() {
+                Ok(chunk) => self.chunk = Cursor::new(chunk),
+                // The writer was dropped.
+                Err(_) => return Ok(0),
+            }
+        }
+    }
+}
+
+/// A pipe holding at most one pending write, so that the writer blocks until the reader catches
+/// up, as with an OS pipe.
+fn pipe() -> (PipeWriter, PipeReader) {
+    let (sender, receiver) = sync_channel(1);
+    let reader = PipeReader {
+        receiver,
+        chunk: Cursor::new(vec![]),
+    };
+    (PipeWriter(sender), reader)
+}
+
+/// A reader counting the bytes read from it.
+struct CountingReader<R> {
+    reader: R,
+    count: usize,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.reader.read(buf)?;
+        self.count += len;
+        Ok(len)
+    }
+}
+
+#[test]
+fn test_proof_stream_round_trip() {
+    let data = fib_proof();
+    let engine = BabyBearPoseidon2Engine::new(data.fri_params);
+    let (vk, proof) = (data.data.vk, data.data.proof);
+    let bytes = proof.to_bytes();
+
+    let mut written = vec![];
+    proof.write_to(&mut written).unwrap();
+    assert_eq!(written, bytes);
+    let decoded = Proof::<SC>::read_from(Cursor::new(&bytes)).unwrap();
+    assert_eq!(decoded.to_bytes(), bytes);
+    let verified = verify_from_reader(&engine, &vk, Cursor::new(&bytes)).unwrap();
+    assert_eq!(verified.to_bytes(), bytes);
+
+    for len in [bytes.len() - 1, bytes.len() / 2, PROOF_MAGIC.len() + 6] {
+        assert_eq!(
+            Proof::<SC>::read_from(&bytes[..len]).err(),
+            Some(ProofSerdeError::UnexpectedEnd)
+        );
+    }
+    // The reader is left at the end of the proof, even if the stream does not end there.
+    let mut reader = CountingReader {
+        reader: Cursor::new(&bytes).chain(io::repeat(0)),
+        count: 0,
+    };
+    let decoded = Proof::<SC>::read_from(&mut reader).unwrap();
+    assert_eq!(decoded.to_bytes(), bytes);
+    assert_eq!(reader.count, bytes.len());
+}
+
+#[test]
+fn test_proof_stream_limit() {
+    let proof = fib_proof().data.proof;
+    let bytes = proof.to_bytes();
+    let decoded = Proof::<SC>::read_from_with_limit(Cursor::new(&bytes), bytes.len()).unwrap();
+    assert_eq!(decoded.to_bytes(), bytes);
+    assert!(matches!(
+        Proof::<SC>::read_from_with_limit(Cursor::new(&bytes), bytes.len() - 1).err(),
+        Some(ProofSerdeError::TooLarge { max, found }) if max == bytes.len() - 1 && found > max
+    ));
+
+    // A proof of version 1 has no length, so it is read to the end of the stream, but not past
+    // the limit.
+    let config = config_name::<SC>();
+    let mut v1_bytes = PROOF_MAGIC.to_vec();
+    v1_bytes.extend_from_slice(&1u32.to_le_bytes());
+    v1_bytes.extend_from_slice(&(config.len() as u32).to_le_bytes());
+    v1_bytes.extend_from_slice(config.as_bytes());
+    v1_bytes.extend(bincode::serialize(&proof).unwrap());
+    let decoded = Proof::<SC>::read_from(Cursor::new(&v1_bytes)).unwrap();
+    assert_eq!(decoded.to_bytes(), bytes);
+    let max = v1_bytes.len() + 10;
+    let mut reader = CountingReader {
+        reader: Cursor::new(&v1_bytes).chain(io::repeat(0)),
+        count: 0,
+    };
+    assert_eq!(
+        Proof::<SC>::read_from_with_limit(&mut reader, max).err(),
+        Some(ProofSerdeError::TooLarge {
+            max,
+            found: max + 1
+        })
+    );
+    assert_eq!(reader.count, max + 1);
+}
+
+#[test]
+fn test_proof_stream_through_pipe() {
+    let data = fib_proof();
+    let engine = BabyBearPoseidon2Engine::new(data.fri_params);
+    let (vk, proof) = (data.data.vk, data.data.proof);
+    let bytes = proof.to_bytes();
+
+    // The proof is written and read concurrently.
+    let (writer, reader) = pipe();
+    let writer = thread::spawn(move || proof.write_to(writer));
+    let verified = verify_from_reader(&engine, &vk, reader).unwrap();
+    writer.join().unwrap().unwrap();
+    assert_eq!(verified.to_bytes(), bytes);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_proof_stream_through_os_pipe() {
+    use std::process::{Command, Stdio};
+
+    let data = fib_proof();
+    let engine = BabyBearPoseidon2Engine::new(data.fri_params);
+    let (vk, proof) = (data.data.vk, data.data.proof);
+    let bytes = proof.to_bytes();
+
+    // `cat` copies its stdin pipe to its stdout pipe, so the proofs are written and read
+    // concurrently through OS pipes. The writer keeps the pipe open until both proofs are read,
+    // which does not block the reader, since it reads no byte after a proof.
+    let mut cat = Command::new("cat")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = cat.stdin.take().unwrap();
+    let (done, wait_done) = sync_channel::<()>(0);
+    let writer = thread::spawn(move || {
+        proof.write_to(&mut stdin)?;
+        proof.write_to(&mut stdin)?;
+        wait_done.recv().unwrap();
+        Ok::<_, io::Error>(())
+    });
+    let mut stdout = cat.stdout.take().unwrap();
+    let verified = verify_from_reader(&engine, &vk, &mut stdout).unwrap();
+    assert_eq!(verified.to_bytes(), bytes);
+    let decoded = Proof::<SC>::read_from(&mut stdout).unwrap();
+    assert_eq!(decoded.to_bytes(), bytes);
+    done.send(()).unwrap();
+    writer.join().unwrap().unwrap();
+    assert!(cat.wait().unwrap().success());
+    let mut rest = vec![];
+    stdout.read_to_end(&mut rest).unwrap();
+    assert!(rest.is_empty());
+}
+
+#[test]
+fn test_proof_stream_rejects_wrong_version_after_header() {
+    let data = fib_proof();
+    let engine = BabyBearPoseidon2Engine::new(data.fri_params);
+    let mut bytes = data.data.proof.to_bytes();
+    let version = PROOF_MAGIC.len()..PROOF_MAGIC.len() + 4;
+    bytes[version].copy_from_slice(&99u32.to_le_bytes());
+    let expected = ProofSerdeError::UnsupportedVersion {
+        version: 99,
+        supported: SUPPORTED_PROOF_FORMAT_VERSIONS.to_vec(),
+    };
+
+    let mut reader = CountingReader {
+        reader: Cursor::new(&bytes),
+        count: 0,
+    };
+    assert_eq!(
+        Proof::<SC>::read_from(&mut reader).err(),
+        Some(expected.clone())
+    );
+    assert_eq!(reader.count, PROOF_MAGIC.len() + 4);
+
+    let mut reader = CountingReader {
+        reader: Cursor::new(&bytes),
+        count: 0,
+    };
+    assert_eq!(
+        verify_from_reader(&engine, &data.data.vk, &mut reader).err(),
+        Some(StreamVerifyError::Proof(expected))
+    );
+    assert_eq!(reader.count, PROOF_MAGIC.len() + 4);
+}
@@ -0,0 +1,119 @@
+use openvm_stark_backend::{p3_field::FieldAlgebra, proof::Proof};
+use openvm_stark_sdk::{
+    any_rap_arc_vec,
+    config::baby_bear_poseidon2_root::{
+        BabyBearPoseidon2RootConfig, BabyBearPoseidon2RootEngine, Bn254Bytes,
+        NonCanonicalBn254Error, RootCommitment,
+    },
+    dummy_airs::fib_air::{air::FibonacciAir, trace::generate_trace_rows},
+    engine::{StarkEngine, StarkFriEngine},
+    key_serde::config_name,
+    proof_serde::{ProofSerde, ProofSerdeError, PROOF_MAGIC},
+};
+use p3_baby_bear::BabyBear;
+use p3_bn254_fr::Bn254Fr;
+use zkhash::{
+    ark_ff::{BigInteger, Field, PrimeField},
+    fields::bn256::FpBN256,
+};
+
+type SC = BabyBearPoseidon2RootConfig;
+
+const N: usize = 16;
+
+/// The modulus of the Bn254 scalar field, in big-endian.
+const MODULUS: &str = "30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000001";
+
+fn from_hex(hex: &str) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).unwrap();
+    }
+    bytes
+}
+
+#[test]
+fn test_bn254_bytes_known_answers() {
+    // The values of `bytes32(uint256(x))` in Solidity, and the same elements computed by ark-ff.
+    let cases = [
+        (
+            Bn254Fr::ONE,
+            FpBN256::from(1u64),
+            "0000000000000000000000000000000000000000000000000000000000000001",
+        ),
+        (
+            Bn254Fr::from_canonical_u64(0x0102030405060708),
+            FpBN256::from(0x0102030405060708u64),
+            "0000000000000000000000000000000000000000000000000102030405060708",
+        ),
+        (
+            Bn254Fr::TWO.exp_u64(255),
+            FpBN256::from(2u64).pow([255]),
+            "1f37631a3d9cbfac8f5f7492fcfd4f45af982f6f0c8d1edd783c14d81ffffffe",
+        ),
+        (
+            Bn254Fr::NEG_ONE,
+            -FpBN256::from(1u64),
+            "30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000000",
+        ),
+    ];
+    for (value, ark_value, expected) in cases {
+        let bytes = value.to_bytes_be();
+        assert_eq!(bytes, from_hex(expected));
+        assert_eq!(bytes.to_vec(), ark_value.into_bigint().to_bytes_be());
+        assert_eq!(Bn254Fr::from_bytes_be(&bytes), Ok(value));
+
+        let commitment = RootCommitment::from([value]);
+        assert_eq!(commitment.to_bytes_be(), bytes);
+        assert_eq!(RootCommitment::from_bytes_be(&bytes), Ok(commitment));
+    }
+
+    for bytes in [from_hex(MODULUS), [0xff; 32]] {
+        assert_eq!(
+            Bn254Fr::from_bytes_be(&bytes),
+            Err(NonCanonicalBn254Error(bytes))
+        );
+        assert_eq!(
+            RootCommitment::from_bytes_be(&bytes),
+            Err(NonCanonicalBn254Error(bytes))
+        );
+    }
+}
+
+#[test]
+fn test_root_proof_commitments_canonical() {
+    let trace = generate_trace_rows::<BabyBear>(0, 1, N);
+    let pis = vec![BabyBear::ZERO, BabyBear::ONE, *trace.values.last().unwrap()];
+    let data = BabyBearPoseidon2RootEngine::run_simple_test_fast(
+        any_rap_arc_vec![FibonacciAir],
+        vec![trace],
+        vec![pis],
+    )
+    .unwrap();
+    let engine = BabyBearPoseidon2RootEngine::new(data.fri_params);
+    let (vk, proof) = (data.data.vk, data.data.proof);
+    let bytes = proof.to_bytes();
+
+    // The commitments section follows the header and its one-byte length, and starts with the
+    // number of main trace commitments and the first of them.
+    let start = PROOF_MAGIC.len() + 8 + config_name::<SC>().len() + 1;
+    assert_eq!(bytes[start..start + 8], 1u64.to_le_bytes());
+    let main_commit = start + 8..start + 40;
+    assert_eq!(
+        bytes[main_commit.clone()],
+        proof.commitments.main_trace[0].to_bytes_be()
+    );
+
+    let decoded = Proof::<SC>::from_bytes(&bytes).unwrap();
+    assert_eq!(decoded.to_bytes(), bytes);
+    engine.verify(&vk, &decoded).expect("Verification failed");
+
+    let mut corrupted = bytes.clone();
+    corrupted[main_commit].copy_from_slice(&from_hex(MODULUS));
+    assert_eq!(
+        Proof::<SC>::from_bytes(&corrupted).err(),
+        Some(ProofSerdeError::NonCanonicalBn254Element(
+            NonCanonicalBn254Error(from_hex(MODULUS))
+        ))
+    );
+}
@@ -0,0 +1,79 @@
+use openvm_stark_backend::prover::types::{AirProofInput, ProofInput};
+use openvm_stark_sdk::{
+    config::{
+        baby_bear_poseidon2::{engine_from_perm, random_instrumented_perm},
+        FriParameters,
+    },
+    cost_estimate::{estimate_verifier_cost, TranscriptCost, VerifierPhase},
+    dummy_airs::fib_lookup,
+    engine::{StarkEngine, StarkEngineWithHashInstrumentation},
+};
+const N: usize = 16;
+
+#[test]
+fn test_verifier_cost_matches_instrumented_verification() {
+    // Not `standard_fast`, which `OPENVM_FAST_TEST` changes, as the transcript depends on them.
+    let fri_params = FriParameters {
+        log_blowup: 1,
+        log_final_poly_len: 0,
+        num_queries: 100,
+        proof_of_work_bits: 16,
+    };
+    let mut engine = engine_from_perm(random_instrumented_perm(), fri_params);
+    let mut keygen_builder = engine.keygen_builder();
+    fib_lookup::add_airs(&mut keygen_builder);
+    let pk = keygen_builder.generate_pk();
+
+    // The AIRs have different heights, so that the common main and after challenge commitments
+    // have matrices of several heights.
+    let proof = engine.prove(
+        &pk,
+        ProofInput::new(vec![
+            (0, fib_lookup::fib_input(N)),
+            (1, AirProofInput::simple_no_pis(fib_lookup::messages())),
+            (
+                2,
+                AirProofInput::simple_no_pis(fib_lookup::padded_messages()),
+            ),
+        ]),
+    );
+    let vk = pk.get_vk();
+
+    // Only count the permutations of the verification.
+    engine.clear_instruments();
+    engine.verify(&vk, &proof).expect("Verification failed");
+    let stats = engine.stark_hash_statistics(());
+
+    let report = estimate_verifier_cost(&vk, fri_params, &proof.log_trace_heights());
+    assert_eq!(report.num_permutations(), stats.stats.permutations);
+    // The duplex challenger of rate 8 permutes when it observes 8 elements, and when it samples
+    // after an observation or after sampling 8 elements.
+    let cost = |observations, samples, permutations| TranscriptCost {
+        observations,
+        samples,
+        permutations,
+    };
+    assert_eq!(
+        report.transcript,
+        [
+            // The number of AIRs and their ids, the absent AIRs, 3 public values, the common main
+            // commitment and the log2 heights: 4 + 1 + 3 + 8 + 3 elements.
+            (VerifierPhase::Preamble, cost(19, 0, 2)),
+            // 2 challenges, 1 exposed value per interacting AIR and the after challenge
+            // commitment, sampling after the 3 elements left from the preamble.
+            (VerifierPhase::ChallengePhase, cost(16, 8, 3)),
+            // alpha, the quotient commitment and zeta.
+            (VerifierPhase::Quotient, cost(8, 8, 1)),
+            // alpha, the 4 FRI rounds folding the LDE of height 2^5 down to 2^1, each
+            // observing a commitment and sampling a challenge, the final polynomial, the proof
+            // of work witness and its check, and the 100 query indices, 7 of which come from
+            // the permutation of the check.
+            (
+                VerifierPhase::Opening,
+                cost(37, 4 + 16 + 1 + 100, 4 + 1 + 12)
+            ),
+        ]
+    );
+    assert_eq!(report.constraints.len(), 3);
+    assert!(report.num_constraint_muls() > 0);
+}
@@ -0,0 +1,116 @@
+use std::sync::Arc;
+
+use openvm_stark_backend::{
+    keygen::types::{MultiStarkProvingKey, MultiStarkVerifyingKey},
+    p3_air::{Air, AirBuilder, BaseAir},
+    p3_field::{Field, FieldAlgebra},
+    p3_matrix::{dense::RowMajorMatrix, Matrix},
+    prover::types::{AirProofInput, ProofInput},
+    rap::{BaseAirWithPublicValues, PartitionedBaseAir},
+};
+use openvm_stark_sdk::{
+    config::baby_bear_poseidon2::{
+        default_engine, BabyBearPoseidon2Config, BabyBearPoseidon2Engine,
+    },
+    engine::{StarkEngine, StarkFriEngine},
+    key_serde::KeySerde,
+    verifier_handle::{PinnedVkError, VerifierHandle},
+};
+use p3_baby_bear::BabyBear;
+
+type SC = BabyBearPoseidon2Config;
+
+/// Constrains its single column to be boolean, as `x^2 - x = 0`, or as `x (x - 1) = 0` when
+/// `factored`. Both constraints are the same polynomial, so a proof for one key verifies against
+/// the other, but the keys have different digests.
+struct BoolAir {
+    factored: bool,
+}
+
+impl<F: Field> BaseAir<F> for BoolAir {
+    fn width(&self) -> usize {
+        1
+    }
+}
+impl<F: Field> BaseAirWithPublicValues<F> for BoolAir {}
+impl<F: Field> PartitionedBaseAir<F> for BoolAir {}
+
+impl<AB: AirBuilder> Air<AB> for BoolAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let x: AB::Expr = main.row_slice(0)[0].into();
+        if self.factored {
+            builder.assert_zero(x.clone() * (x - AB::Expr::ONE));
+        } else {
+            builder.assert_zero(x.clone() * x.clone() - x);
+        }
+    }
+}
+
+fn keygen(engine: &BabyBearPoseidon2Engine, factored: bool) -> MultiStarkProvingKey<SC> {
+    let mut keygen_builder = engine.keygen_builder();
+    keygen_builder.add_air(Arc::new(BoolAir { factored }));
+    keygen_builder.generate_pk()
+}
+
+fn proof_input(offset: usize) -> ProofInput<SC> {
+    let values = (0..8)
+        .map(|i| BabyBear::from_bool((i + offset) % 2 == 0))
+        .collect();
+    ProofInput::new(vec![(
+        0,
+        AirProofInput::simple_no_pis(RowMajorMatrix::new(values, 1)),
+    )])
+}
+
+#[test]
+fn test_verify_with_pinned_digest() {
+    let engine = default_engine();
+    let pk = keygen(&engine, false);
+    let vk = pk.get_vk();
+    let digest = engine.vk_digest(&vk);
+    let vk_bytes = vk.to_bytes();
+    let proofs = [0, 1].map(|offset| engine.prove(&pk, proof_input(offset)));
+    engine
+        .verify_with_digest(&digest, &vk_bytes, &proofs[0])
+        .expect("Verification failed");
+
+    // The handle decodes and checks the key once.
+    let handle = VerifierHandle::from_digest_and_bytes(&engine, &digest, &vk_bytes).unwrap();
+    assert_eq!(handle.digest(), &digest);
+    for proof in &proofs {
+        handle.verify(proof).expect("Verification failed");
+    }
+}
+
+#[test]
+fn test_altered_constraint_is_rejected_by_digest() {
+    let engine = default_engine();
+    let pk = keygen(&engine, false);
+    let digest = engine.vk_digest(&pk.get_vk());
+    let proof = engine.prove(&pk, proof_input(0));
+
+    // The key with the altered constraint verifies the proof on its own.
+    let altered_vk: MultiStarkVerifyingKey<SC> = keygen(&engine, true).get_vk();
+    engine
+        .verify(&altered_vk, &proof)
+        .expect("Verification failed");
+    let altered_digest = engine.vk_digest(&altered_vk);
+    assert_ne!(altered_digest, digest);
+
+    let altered_bytes = altered_vk.to_bytes();
+    assert_eq!(
+        engine.verify_with_digest(&digest, &altered_bytes, &proof),
+        Err(PinnedVkError::DigestMismatch {
+            expected: digest,
+            found: altered_digest,
+        })
+    );
+    assert!(VerifierHandle::from_digest_and_bytes(&engine, &digest, &altered_bytes).is_err());
+
+    // Bytes that are not a key.
+    assert!(matches!(
+        engine.verify_with_digest(&digest, &altered_bytes[1..], &proof),
+        Err(PinnedVkError::Key(_))
+    ));
+}
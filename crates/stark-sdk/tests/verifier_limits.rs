@@ -0,0 +1,127 @@
+//! The allocation counts are process-wide, so the checks are in one test of their own binary to
+//! not count the allocations of other tests.
+#![cfg(not(any(feature = "mem-metrics", feature = "jemalloc", feature = "mimalloc")))]
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use openvm_stark_backend::{
+    p3_field::FieldAlgebra,
+    proof::Proof,
+    verifier::{VerificationError, VerifierLimitError, VerifierLimits},
+};
+use openvm_stark_sdk::{
+    any_rap_arc_vec,
+    config::{
+        baby_bear_poseidon2::{BabyBearPoseidon2Config, BabyBearPoseidon2Engine},
+        FriParameters,
+    },
+    dummy_airs::fib_air::{air::FibonacciAir, trace::generate_trace_rows},
+    engine::{StarkEngine, StarkFriEngine},
+    key_serde::config_name,
+    proof_serde::{ProofSerde, ProofSerdeError},
+};
+use p3_baby_bear::BabyBear;
+
+type SC = BabyBearPoseidon2Config;
+
+const N: usize = 16;
+const NUM_CLAIMED_AIRS: usize = 50;
+/// Rejecting an oversized proof must not allocate more than this, which is far below what its
+/// claims would take. serde reserves up to 1 MiB for a list of unknown length.
+const MAX_REJECTION_BYTES: usize = 1 << 24;
+
+/// The system allocator, recording the peak of the bytes allocated at once.
+struct PeakAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for PeakAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let allocated = ALLOCATED.fetch_add(layout.size(), Ordering::SeqCst) + layout.size();
+        PEAK.fetch_max(allocated, Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::SeqCst);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: PeakAllocator = PeakAllocator;
+
+/// Runs `f`, and returns its result with the peak of the bytes it allocated at once.
+fn peak_allocation<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let before = ALLOCATED.load(Ordering::SeqCst);
+    PEAK.store(before, Ordering::SeqCst);
+    let res = f();
+    (res, PEAK.load(Ordering::SeqCst) - before)
+}
+
+#[test]
+fn test_oversized_proofs_are_rejected_without_large_allocations() {
+    let trace = generate_trace_rows::<BabyBear>(0, 1, N);
+    let last = trace.values[trace.values.len() - 1];
+    let data = BabyBearPoseidon2Engine::run_simple_test_fast(
+        any_rap_arc_vec![FibonacciAir],
+        vec![trace],
+        vec![vec![BabyBear::ZERO, BabyBear::ONE, last]],
+    )
+    .unwrap()
+    .data;
+    let limits = VerifierLimits::from_vk(&data.vk);
+    let engine = BabyBearPoseidon2Engine::new(FriParameters::standard_fast());
+    let verifier = engine.verifier().with_limits(limits);
+
+    // A proof claiming many AIRs of the largest trace height.
+    let mut proof = data.proof.clone();
+    proof.core.per_air[0].degree = 1 << 31;
+    proof.core.per_air = vec![proof.core.per_air[0].clone(); NUM_CLAIMED_AIRS];
+    let mut challenger = engine.new_challenger();
+    let (res, peak) = peak_allocation(|| verifier.verify(&mut challenger, &data.vk, &proof));
+    assert_eq!(
+        res,
+        Err(VerificationError::LimitExceeded(VerifierLimitError::Airs {
+            max: 1,
+            found: NUM_CLAIMED_AIRS,
+        }))
+    );
+    assert!(peak < MAX_REJECTION_BYTES, "allocated {peak} bytes");
+
+    // The section of the commitments follows the header, and starts with the list of main trace
+    // commitments, which claims far more commitments than the input holds.
+    let bytes = data.proof.to_bytes();
+    let mut claimed = bytes.clone();
+    let section_start = 16 + config_name::<SC>().len();
+    let len_bytes = bytes[section_start..]
+        .iter()
+        .position(|byte| byte & 0x80 == 0)
+        .unwrap()
+        + 1;
+    let commitments_start = section_start + len_bytes;
+    claimed[commitments_start..commitments_start + 8].copy_from_slice(&(1u64 << 40).to_le_bytes());
+    let (res, peak) = peak_allocation(|| Proof::<SC>::from_bytes(&claimed));
+    assert!(matches!(res, Err(ProofSerdeError::Payload(_))));
+    assert!(peak < MAX_REJECTION_BYTES, "allocated {peak} bytes");
+
+    // An input above the limit is not decoded.
+    let (res, peak) =
+        peak_allocation(|| Proof::<SC>::from_bytes_with_limit(&bytes, bytes.len() - 1));
+    assert_eq!(
+        res.err(),
+        Some(ProofSerdeError::TooLarge {
+            max: bytes.len() - 1,
+            found: bytes.len(),
+        })
+    );
+    assert!(peak < MAX_REJECTION_BYTES, "allocated {peak} bytes");
+    let proof = Proof::<SC>::from_bytes_with_limit(&bytes, bytes.len()).unwrap();
+    verifier
+        .verify(&mut engine.new_challenger(), &data.vk, &proof)
+        .expect("Verification failed");
+}